@@ -1,19 +1,24 @@
 use serde_json::{json, Value};
+use std::cell::Cell;
 use std::collections::HashMap;
 use std::env;
 use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, ChildStdin, Command};
 use tokio::sync::{mpsc, oneshot, Mutex};
 use tokio::time::timeout;
 
+use crate::backend::codex_capabilities::SessionCapabilities;
 use crate::backend::events::{AppServerEvent, EventSink};
-use crate::types::WorkspaceEntry;
+use crate::types::{ExperimentalFlags, WorkspaceEntry};
+
+/// The error of a request whose session stopped reading before answering.
+pub(crate) const REQUEST_CANCELED: &str = "request canceled";
 
 fn extract_thread_id(value: &Value) -> Option<String> {
     value
@@ -23,6 +28,18 @@ fn extract_thread_id(value: &Value) -> Option<String> {
         .map(|s| s.to_string())
 }
 
+/// Calls made through `WorkspaceSession::send_request` and the time spent
+/// waiting on them, for callers that scope `SESSION_TIMING` around a request.
+#[derive(Default)]
+pub(crate) struct SessionTiming {
+    pub(crate) requests: Cell<u32>,
+    pub(crate) wait_us: Cell<u64>,
+}
+
+tokio::task_local! {
+    pub(crate) static SESSION_TIMING: SessionTiming;
+}
+
 pub(crate) struct WorkspaceSession {
     pub(crate) entry: WorkspaceEntry,
     pub(crate) child: Mutex<Child>,
@@ -31,9 +48,20 @@ pub(crate) struct WorkspaceSession {
     pub(crate) next_id: AtomicU64,
     /// Callbacks for background threads - events for these threadIds are sent through the channel
     pub(crate) background_thread_callbacks: Mutex<HashMap<String, mpsc::UnboundedSender<Value>>>,
+    /// Experimental flags from app settings when the process was spawned.
+    pub(crate) experimental_flags: ExperimentalFlags,
+    /// The codex version and the methods it doesn't serve, checked before
+    /// each request.
+    pub(crate) capabilities: std::sync::Mutex<SessionCapabilities>,
 }
 
 impl WorkspaceSession {
+    pub(crate) fn capabilities(&self) -> std::sync::MutexGuard<'_, SessionCapabilities> {
+        self.capabilities
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+    }
+
     async fn write_message(&self, value: Value) -> Result<(), String> {
         let mut stdin = self.stdin.lock().await;
         let mut line = serde_json::to_string(&value).map_err(|e| e.to_string())?;
@@ -45,12 +73,78 @@ impl WorkspaceSession {
     }
 
     pub(crate) async fn send_request(&self, method: &str, params: Value) -> Result<Value, String> {
+        // Without a `SESSION_TIMING` scope this costs one failed lookup.
+        let Ok(started_at) = SESSION_TIMING.try_with(|_| Instant::now()) else {
+            return self.request(method, params).await;
+        };
+        let result = self.request(method, params).await;
+        let waited = u64::try_from(started_at.elapsed().as_micros()).unwrap_or(u64::MAX);
+        let _ = SESSION_TIMING.try_with(|timing| {
+            timing.requests.set(timing.requests.get().saturating_add(1));
+            timing
+                .wait_us
+                .set(timing.wait_us.get().saturating_add(waited));
+        });
+        result
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let (_, rx) = self.start_request(method, params).await?;
+        self.finish_request(method, rx.await)
+    }
+
+    /// `send_request` that gives up after `limit` and returns `None`. The
+    /// request is then forgotten, so a late answer is dropped instead of
+    /// waiting in `pending` forever.
+    pub(crate) async fn send_request_within(
+        &self,
+        method: &str,
+        params: Value,
+        limit: Duration,
+    ) -> Option<Result<Value, String>> {
+        let deadline = tokio::time::Instant::now() + limit;
+        let (id, rx) =
+            match tokio::time::timeout_at(deadline, self.start_request(method, params)).await {
+                Ok(Ok(started)) => started,
+                Ok(Err(error)) => return Some(Err(error)),
+                Err(_) => return None,
+            };
+        match tokio::time::timeout_at(deadline, rx).await {
+            Ok(response) => Some(self.finish_request(method, response)),
+            Err(_) => {
+                self.pending.lock().await.remove(&id);
+                None
+            }
+        }
+    }
+
+    async fn start_request(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<(u64, oneshot::Receiver<Value>), String> {
+        self.capabilities().check(method)?;
         let id = self.next_id.fetch_add(1, Ordering::SeqCst);
         let (tx, rx) = oneshot::channel();
         self.pending.lock().await.insert(id, tx);
-        self.write_message(json!({ "id": id, "method": method, "params": params }))
-            .await?;
-        rx.await.map_err(|_| "request canceled".to_string())
+        let written = self
+            .write_message(json!({ "id": id, "method": method, "params": params }))
+            .await;
+        if let Err(error) = written {
+            self.pending.lock().await.remove(&id);
+            return Err(error);
+        }
+        Ok((id, rx))
+    }
+
+    fn finish_request(
+        &self,
+        method: &str,
+        response: Result<Value, oneshot::error::RecvError>,
+    ) -> Result<Value, String> {
+        let response = response.map_err(|_| REQUEST_CANCELED.to_string())?;
+        self.capabilities().record_response(method, &response)?;
+        Ok(response)
     }
 
     pub(crate) async fn send_notification(
@@ -72,6 +166,77 @@ impl WorkspaceSession {
     }
 }
 
+fn is_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Expands a leading `~` and `$VAR`/`${VAR}` references in a stored path or
+/// `codex_bin`, so the stored value stays portable across machines. `$$` is a
+/// literal `$`, and a `$` not followed by a name is kept as is. Unset
+/// variables are an error rather than an empty string. A value naming a path
+/// that exists, such as a folder with `$` in its name, is not a template and
+/// is returned as is.
+pub(crate) fn expand_path_vars(value: &str) -> Result<String, String> {
+    if Path::new(value).exists() {
+        return Ok(value.to_string());
+    }
+    expand_path_vars_with(value, |name| env::var(name).ok())
+}
+
+fn expand_path_vars_with(
+    value: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let var = |name: &str| {
+        lookup(name).ok_or_else(|| format!("`{value}` uses ${name}, which is not set."))
+    };
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    if rest == "~" || rest.starts_with("~/") || rest.starts_with("~\\") {
+        let home = lookup("HOME")
+            .or_else(|| lookup("USERPROFILE"))
+            .ok_or_else(|| format!("`{value}` starts with ~, but HOME is not set."))?;
+        expanded.push_str(&home);
+        rest = &rest[1..];
+    }
+    while let Some(index) = rest.find('$') {
+        expanded.push_str(&rest[..index]);
+        let after = &rest[index + 1..];
+        if let Some(after) = after.strip_prefix('$') {
+            expanded.push('$');
+            rest = after;
+        } else if let Some(braced) = after.strip_prefix('{') {
+            let end = braced
+                .find('}')
+                .ok_or_else(|| format!("`{value}` has an unclosed ${{."))?;
+            let name = &braced[..end];
+            if !is_var_name(name) {
+                return Err(format!("`{value}` has an invalid variable name `{name}`."));
+            }
+            expanded.push_str(&var(name)?);
+            rest = &braced[end + 1..];
+        } else {
+            let end = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            let name = &after[..end];
+            if is_var_name(name) {
+                expanded.push_str(&var(name)?);
+                rest = &after[end..];
+            } else {
+                expanded.push('$');
+                rest = after;
+            }
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
 pub(crate) fn build_codex_path_env(codex_bin: Option<&str>) -> Option<String> {
     let mut paths: Vec<String> = env::var("PATH")
         .unwrap_or_default()
@@ -186,6 +351,7 @@ pub(crate) async fn check_codex_installation(
 pub(crate) async fn spawn_workspace_session<E: EventSink>(
     entry: WorkspaceEntry,
     default_codex_bin: Option<String>,
+    experimental_flags: ExperimentalFlags,
     client_version: String,
     event_sink: E,
     codex_home: Option<PathBuf>,
@@ -194,8 +360,16 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
         .codex_bin
         .clone()
         .filter(|value| !value.trim().is_empty())
-        .or(default_codex_bin);
-    let _ = check_codex_installation(codex_bin.clone()).await?;
+        .or(default_codex_bin)
+        .map(|bin| expand_path_vars(&bin))
+        .transpose()?;
+    let codex_version = check_codex_installation(codex_bin.clone()).await?;
+    // The session works in the expanded path; the stored entry keeps the
+    // original.
+    let entry = WorkspaceEntry {
+        path: expand_path_vars(&entry.path)?,
+        ..entry
+    };
 
     let mut command = build_codex_command_with_bin(codex_bin);
     command.current_dir(&entry.path);
@@ -219,6 +393,8 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
         pending: Mutex::new(HashMap::new()),
         next_id: AtomicU64::new(1),
         background_thread_callbacks: Mutex::new(HashMap::new()),
+        experimental_flags,
+        capabilities: std::sync::Mutex::new(SessionCapabilities::new(codex_version)),
     });
 
     let session_clone = Arc::clone(&session);
@@ -342,7 +518,14 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
             );
         }
     };
-    init_response?;
+    let user_agent = init_response?
+        .get("result")
+        .and_then(|result| result.get("userAgent"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    if let Some(user_agent) = user_agent {
+        session.capabilities().set_user_agent(&user_agent);
+    }
     session.send_notification("initialized", None).await?;
 
     let payload = AppServerEvent {
@@ -359,8 +542,18 @@ pub(crate) async fn spawn_workspace_session<E: EventSink>(
 
 #[cfg(test)]
 mod tests {
-    use super::extract_thread_id;
+    use super::{expand_path_vars, expand_path_vars_with, extract_thread_id};
     use serde_json::json;
+    use uuid::Uuid;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOME" => Some("/home/dev".to_string()),
+            "TOOLS" => Some("/opt/tools".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
 
     #[test]
     fn extract_thread_id_reads_camel_case() {
@@ -379,4 +572,55 @@ mod tests {
         let value = json!({ "params": {} });
         assert_eq!(extract_thread_id(&value), None);
     }
+
+    #[test]
+    fn expands_tilde_and_variables() {
+        let expand = |value: &str| expand_path_vars_with(value, lookup);
+        assert_eq!(
+            expand("~/.local/bin/codex").unwrap(),
+            "/home/dev/.local/bin/codex"
+        );
+        assert_eq!(expand("~").unwrap(), "/home/dev");
+        assert_eq!(
+            expand("$HOME/.local/bin/codex").unwrap(),
+            "/home/dev/.local/bin/codex"
+        );
+        assert_eq!(expand("${TOOLS}/codex").unwrap(), "/opt/tools/codex");
+        assert_eq!(
+            expand("${TOOLS}bin/$HOME").unwrap(),
+            "/opt/toolsbin//home/dev"
+        );
+        assert_eq!(expand("/bin/codex$EMPTY").unwrap(), "/bin/codex");
+        assert_eq!(expand("/usr/bin/codex").unwrap(), "/usr/bin/codex");
+    }
+
+    #[test]
+    fn leaves_non_variable_syntax_alone() {
+        let expand = |value: &str| expand_path_vars_with(value, lookup);
+        assert_eq!(expand("/tmp/a~b/~").unwrap(), "/tmp/a~b/~");
+        assert_eq!(expand("~other/codex").unwrap(), "~other/codex");
+        assert_eq!(expand("/price$/$1/$").unwrap(), "/price$/$1/$");
+        assert_eq!(expand("/a$$HOME").unwrap(), "/a$HOME");
+    }
+
+    #[test]
+    fn existing_paths_are_not_templates() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-$HOME-$$-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("create dir");
+        let path = dir.to_string_lossy().to_string();
+        let expanded = expand_path_vars(&path);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(expanded, Ok(path));
+    }
+
+    #[test]
+    fn rejects_unset_and_malformed_variables() {
+        let expand = |value: &str| expand_path_vars_with(value, lookup);
+        assert!(expand("$MISSING/codex").unwrap_err().contains("$MISSING"));
+        assert!(expand("${MISSING}").is_err());
+        assert!(expand("${TOOLS/codex").is_err());
+        assert!(expand("${1X}/codex").is_err());
+        assert!(expand("${}").is_err());
+        assert!(expand_path_vars_with("~/codex", |_| None).is_err());
+    }
 }
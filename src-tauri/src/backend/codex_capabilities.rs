@@ -0,0 +1,182 @@
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+
+use crate::types::CodexCapabilities;
+
+/// Part of the error `WorkspaceSession::send_request` returns for a method
+/// the session's codex doesn't serve.
+pub(crate) const UNSUPPORTED_METHOD_ERROR: &str = "unsupported by this codex version";
+
+/// JSON-RPC's "method not found".
+const METHOD_NOT_FOUND: i64 = -32601;
+
+type Version = (u32, u32, u32);
+
+/// The first `x.y.z` in `text`, such as `0.46.0` in `codex-cli 0.46.0` or
+/// in the `codex_monitor/0.46.0 (...)` user agent. Pre-release and build
+/// suffixes are ignored.
+pub(crate) fn parse_version(text: &str) -> Option<Version> {
+    text.split(|c: char| c.is_whitespace() || matches!(c, '/' | '(' | ')' | ';'))
+        .find_map(|token| {
+            let token = token.strip_prefix('v').unwrap_or(token);
+            let core = token.split(['-', '+']).next()?;
+            let mut parts = core.split('.').map(|part| part.parse::<u32>().ok());
+            let version = (parts.next()??, parts.next()??, parts.next()??);
+            parts.next().is_none().then_some(version)
+        })
+}
+
+fn format_version((major, minor, patch): Version) -> String {
+    format!("{major}.{minor}.{patch}")
+}
+
+/// Whether an app-server response says it doesn't know `method`.
+fn is_unknown_method(method: &str, response: &Value) -> bool {
+    let Some(error) = response.get("error") else {
+        return false;
+    };
+    error.get("code").and_then(Value::as_i64) == Some(METHOD_NOT_FOUND)
+        || error
+            .get("message")
+            .and_then(Value::as_str)
+            .is_some_and(|message| message.contains(&format!("unknown variant `{method}`")))
+}
+
+/// The codex version of one session, and the methods it turned out not to
+/// serve. Methods are only marked unsupported once codex answers that it
+/// doesn't know them; versions are not mapped to methods.
+#[derive(Debug, Default)]
+pub(crate) struct SessionCapabilities {
+    /// As `codex --version` printed it.
+    version: Option<String>,
+    user_agent: Option<String>,
+    parsed: Option<Version>,
+    refused: BTreeSet<String>,
+}
+
+impl SessionCapabilities {
+    pub(crate) fn new(version: Option<String>) -> Self {
+        Self {
+            parsed: version.as_deref().and_then(parse_version),
+            version,
+            ..Self::default()
+        }
+    }
+
+    /// Keeps the `initialize` user agent, whose version stands in when
+    /// `codex --version` printed none.
+    pub(crate) fn set_user_agent(&mut self, user_agent: &str) {
+        self.parsed = self.parsed.or_else(|| parse_version(user_agent));
+        self.user_agent = Some(user_agent.to_string());
+    }
+
+    fn unsupported_error(&self, method: &str) -> String {
+        let have = self
+            .parsed
+            .map_or_else(|| "unknown".to_string(), format_version);
+        format!("`{method}` is {UNSUPPORTED_METHOD_ERROR} (have {have}).")
+    }
+
+    /// Fails before `method` is sent to a codex that already refused it.
+    pub(crate) fn check(&self, method: &str) -> Result<(), String> {
+        if !self.refused.contains(method) {
+            Ok(())
+        } else {
+            Err(self.unsupported_error(method))
+        }
+    }
+
+    /// Turns codex's answer to an unknown method into the same error as
+    /// `check`, and remembers the method so later calls fail before sending.
+    pub(crate) fn record_response(&mut self, method: &str, response: &Value) -> Result<(), String> {
+        if !is_unknown_method(method, response) {
+            return Ok(());
+        }
+        self.refused.insert(method.to_string());
+        Err(self.unsupported_error(method))
+    }
+
+    /// Known methods this codex doesn't serve, sorted.
+    pub(crate) fn unsupported_methods(&self) -> Vec<String> {
+        self.refused.iter().cloned().collect()
+    }
+
+    /// For `workspace_status`.
+    pub(crate) fn report(&self) -> CodexCapabilities {
+        CodexCapabilities {
+            version: self.version.clone(),
+            user_agent: self.user_agent.clone(),
+            unsupported_methods: self.unsupported_methods(),
+        }
+    }
+
+    /// For `list_workspaces`, without the user agent.
+    pub(crate) fn summary(&self) -> CodexCapabilities {
+        CodexCapabilities {
+            user_agent: None,
+            ..self.report()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_version, SessionCapabilities, UNSUPPORTED_METHOD_ERROR};
+    use serde_json::json;
+
+    #[test]
+    fn parses_versions_from_cli_and_user_agent() {
+        assert_eq!(parse_version("codex-cli 0.46.0"), Some((0, 46, 0)));
+        assert_eq!(parse_version("codex-cli v1.2.3-alpha.1"), Some((1, 2, 3)));
+        assert_eq!(
+            parse_version("codex_monitor/0.50.1 (Mac OS 14.5.0; arm64) vscode"),
+            Some((0, 50, 1))
+        );
+        assert_eq!(parse_version("codex 14.5"), None);
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn refuses_methods_codex_said_it_does_not_know() {
+        let mut old = SessionCapabilities::new(Some("codex-cli 0.41.0".to_string()));
+        assert!(old.check("review/start").is_ok());
+        assert!(old.unsupported_methods().is_empty());
+
+        let unknown = json!({ "id": 3, "error": { "code": -32601, "message": "not found" } });
+        let error = old
+            .record_response("skills/list", &unknown)
+            .expect_err("unknown method");
+        assert_eq!(
+            error,
+            "`skills/list` is unsupported by this codex version (have 0.41.0)."
+        );
+        assert!(error.contains(UNSUPPORTED_METHOD_ERROR));
+        assert_eq!(old.check("skills/list"), Err(error));
+        assert!(old
+            .record_response("thread/start", &json!({ "id": 4, "result": {} }))
+            .is_ok());
+        assert_eq!(old.unsupported_methods(), ["skills/list"]);
+
+        let mut unversioned = SessionCapabilities::new(None);
+        assert!(unversioned.check("review/start").is_ok());
+        unversioned.set_user_agent("codex_monitor/0.90.0 (Linux; x86_64)");
+        assert!(unversioned.check("collaborationMode/list").is_ok());
+        let report = unversioned.report();
+        assert_eq!(report.version, None);
+        assert!(report.user_agent.is_some());
+        assert!(report.unsupported_methods.is_empty());
+        assert!(unversioned.summary().user_agent.is_none());
+
+        let variant = json!({ "error": { "code": -32600, "message":
+            "Invalid request: unknown variant `collaborationMode/list`, expected one of ..." } });
+        let error = unversioned
+            .record_response("collaborationMode/list", &variant)
+            .expect_err("unknown variant");
+        assert!(error.ends_with("(have 0.90.0)."));
+        assert_eq!(
+            unversioned.unsupported_methods(),
+            ["collaborationMode/list"]
+        );
+    }
+}
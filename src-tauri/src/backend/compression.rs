@@ -0,0 +1,103 @@
+use std::io::Read;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{json, Value};
+
+/// The `compression` a daemon connection can negotiate at auth.
+pub(crate) const ZSTD_ENCODING: &str = "zstd";
+/// Messages shorter than this are sent as they are; compressing them costs
+/// more than it saves.
+pub(crate) const MIN_COMPRESSED_BYTES: usize = 8 * 1024;
+/// Largest message a client inflates from the daemon.
+pub(crate) const MAX_DECOMPRESSED_BYTES: usize = 256 * 1024 * 1024;
+const ZSTD_LEVEL: i32 = 3;
+
+/// Wraps a JSON line in a `{ compressed, encoding, data }` envelope when it
+/// is at least `MIN_COMPRESSED_BYTES` and the envelope comes out smaller.
+pub(crate) fn compress_message(message: String) -> String {
+    if message.len() < MIN_COMPRESSED_BYTES {
+        return message;
+    }
+    let Ok(compressed) = zstd::bulk::compress(message.as_bytes(), ZSTD_LEVEL) else {
+        return message;
+    };
+    let envelope = json!({
+        "compressed": true,
+        "encoding": ZSTD_ENCODING,
+        "data": STANDARD.encode(compressed),
+    })
+    .to_string();
+    if envelope.len() < message.len() {
+        envelope
+    } else {
+        message
+    }
+}
+
+/// Unwraps a compressed envelope into the message it carries; anything else
+/// passes through. Inflating past `max_bytes` is an error.
+pub(crate) fn decompress_message(message: Value, max_bytes: usize) -> Result<Value, String> {
+    if message.get("compressed").and_then(Value::as_bool) != Some(true) {
+        return Ok(message);
+    }
+    let encoding = message
+        .get("encoding")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if encoding != ZSTD_ENCODING {
+        return Err(format!("unsupported encoding: {encoding}"));
+    }
+    let data = message
+        .get("data")
+        .and_then(Value::as_str)
+        .ok_or("compressed message without data")?;
+    let compressed = STANDARD
+        .decode(data)
+        .map_err(|err| format!("invalid compressed data: {err}"))?;
+    let decoder = zstd::stream::read::Decoder::new(compressed.as_slice())
+        .map_err(|err| format!("invalid compressed data: {err}"))?;
+    let mut decompressed = Vec::new();
+    decoder
+        .take(max_bytes as u64 + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|err| format!("invalid compressed data: {err}"))?;
+    if decompressed.len() > max_bytes {
+        return Err(format!(
+            "decompressed message exceeds the {max_bytes}-byte limit"
+        ));
+    }
+    serde_json::from_slice(&decompressed).map_err(|err| format!("invalid JSON: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress_message, decompress_message, MIN_COMPRESSED_BYTES};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn compresses_large_messages_only() {
+        let small = json!({ "id": 1, "result": { "ok": true } }).to_string();
+        assert_eq!(compress_message(small.clone()), small);
+
+        let files: Vec<String> = (0..2000).map(|i| format!("src/module_{i}.rs")).collect();
+        let large = json!({ "id": 2, "result": { "files": files } });
+        let line = large.to_string();
+        assert!(line.len() > MIN_COMPRESSED_BYTES);
+        let compressed = compress_message(line.clone());
+        assert!(compressed.len() < line.len());
+
+        let envelope: Value = serde_json::from_str(&compressed).expect("envelope");
+        assert_eq!(envelope["encoding"], "zstd");
+        assert_eq!(decompress_message(envelope.clone(), line.len()), Ok(large));
+        assert!(decompress_message(envelope, line.len() - 1).is_err());
+    }
+
+    #[test]
+    fn passes_plain_messages_through() {
+        let message = json!({ "id": 1, "method": "ping" });
+        assert_eq!(decompress_message(message.clone(), 16), Ok(message));
+        let unknown = json!({ "compressed": true, "encoding": "gzip", "data": "" });
+        assert!(decompress_message(unknown, 16).is_err());
+    }
+}
@@ -0,0 +1,274 @@
+use std::ffi::OsStr;
+use std::ops::{Deref, DerefMut};
+use std::path::Path;
+use std::process::{ExitStatus, Output, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::process::{Child, Command};
+
+/// Waits before retrying a command that found a git lock file in place.
+const LOCK_RETRY_DELAYS: [Duration; 2] = [Duration::from_millis(100), Duration::from_millis(400)];
+
+/// Receives how long each git invocation run through `git_command` took.
+pub(crate) type GitTimer = Arc<dyn Fn(Duration) + Send + Sync>;
+
+tokio::task_local! {
+    /// Scoped around work whose git commands should be timed.
+    pub(crate) static GIT_TIMER: GitTimer;
+}
+
+/// Git failures clients can act on. Git's messages are only matched in
+/// English, which `git_command` guarantees.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GitErrorKind {
+    NotAWorkingTree,
+    BranchExists,
+    NonFastForward,
+    DirtyWorkTree,
+    AuthFailed,
+    /// Another git process holds `index.lock` or a ref lock.
+    LockHeld,
+}
+
+impl GitErrorKind {
+    /// Lock contention usually clears within moments, when the other git
+    /// process finishes.
+    pub(crate) fn is_transient(self) -> bool {
+        self == Self::LockHeld
+    }
+}
+
+const PATTERNS: [(GitErrorKind, &[&str]); 6] = [
+    (GitErrorKind::NotAWorkingTree, &["is not a working tree"]),
+    (
+        GitErrorKind::LockHeld,
+        &[
+            ".lock': file exists",
+            "another git process seems to be running",
+        ],
+    ),
+    (
+        GitErrorKind::BranchExists,
+        &["a branch named '", "branch already exists"],
+    ),
+    (
+        GitErrorKind::NonFastForward,
+        &[
+            "non-fast-forward",
+            "not possible to fast-forward",
+            "updates were rejected because",
+        ],
+    ),
+    (
+        GitErrorKind::DirtyWorkTree,
+        &[
+            "contains modified or untracked files",
+            "would be overwritten by",
+            "please commit your changes or stash them",
+            "you have unstaged changes",
+            "your index contains uncommitted changes",
+        ],
+    ),
+    (
+        GitErrorKind::AuthFailed,
+        &[
+            "authentication failed",
+            "permission denied (publickey",
+            "could not read username",
+            "could not read password",
+            "terminal prompts disabled",
+            "host key verification failed",
+        ],
+    ),
+];
+
+/// Recognizes the failures in `GitErrorKind` from git's output.
+pub(crate) fn classify_git_error(output: &str) -> Option<GitErrorKind> {
+    let output = output.to_lowercase();
+    PATTERNS
+        .iter()
+        .find(|(_, patterns)| patterns.iter().any(|pattern| output.contains(pattern)))
+        .map(|(kind, _)| *kind)
+}
+
+/// A `git` command whose messages stay in English whatever the user's
+/// locale, so `classify_git_error` can read them. Stdin is closed so git
+/// never reads from the daemon's request stream.
+pub(crate) fn git_command() -> GitCommand {
+    let mut command = Command::new("git");
+    command
+        .env("LC_ALL", "C")
+        .env("LANG", "C")
+        .stdin(Stdio::null());
+    GitCommand(command)
+}
+
+/// A `Command` running git. `output` and `status` report how long git ran
+/// to the `GIT_TIMER` in scope; spawned children, which callers stream and
+/// wait on themselves, aren't timed.
+pub(crate) struct GitCommand(Command);
+
+impl GitCommand {
+    pub(crate) fn arg(&mut self, arg: impl AsRef<OsStr>) -> &mut Self {
+        self.0.arg(arg);
+        self
+    }
+
+    pub(crate) fn args<I, S>(&mut self, args: I) -> &mut Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.0.args(args);
+        self
+    }
+
+    pub(crate) fn current_dir(&mut self, dir: impl AsRef<Path>) -> &mut Self {
+        self.0.current_dir(dir);
+        self
+    }
+
+    pub(crate) fn env(&mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> &mut Self {
+        self.0.env(key, value);
+        self
+    }
+
+    pub(crate) fn stdin(&mut self, cfg: impl Into<Stdio>) -> &mut Self {
+        self.0.stdin(cfg);
+        self
+    }
+
+    pub(crate) fn stdout(&mut self, cfg: impl Into<Stdio>) -> &mut Self {
+        self.0.stdout(cfg);
+        self
+    }
+
+    pub(crate) fn stderr(&mut self, cfg: impl Into<Stdio>) -> &mut Self {
+        self.0.stderr(cfg);
+        self
+    }
+
+    pub(crate) async fn output(&mut self) -> std::io::Result<Output> {
+        let started_at = Instant::now();
+        let output = self.0.output().await;
+        record_duration(started_at.elapsed());
+        output
+    }
+
+    pub(crate) async fn status(&mut self) -> std::io::Result<ExitStatus> {
+        let started_at = Instant::now();
+        let status = self.0.status().await;
+        record_duration(started_at.elapsed());
+        status
+    }
+
+    pub(crate) fn spawn(&mut self) -> std::io::Result<Child> {
+        self.0.spawn()
+    }
+}
+
+impl Deref for GitCommand {
+    type Target = Command;
+
+    fn deref(&self) -> &Command {
+        &self.0
+    }
+}
+
+impl DerefMut for GitCommand {
+    fn deref_mut(&mut self) -> &mut Command {
+        &mut self.0
+    }
+}
+
+fn record_duration(elapsed: Duration) {
+    // Without a `GIT_TIMER` scope this costs one failed lookup.
+    let _ = GIT_TIMER.try_with(|timer| timer(elapsed));
+}
+
+/// Runs git in `repo_path` and returns its stdout. Lock contention is
+/// retried a couple of times; other failures return git's stderr, or its
+/// stdout when stderr is empty.
+pub(crate) async fn run_git(repo_path: &Path, args: &[&str]) -> Result<Vec<u8>, String> {
+    let mut retries = LOCK_RETRY_DELAYS.iter();
+    loop {
+        let output = git_command()
+            .args(args)
+            .current_dir(repo_path)
+            .output()
+            .await
+            .map_err(|e| format!("Failed to run git: {e}"))?;
+        if output.status.success() {
+            return Ok(output.stdout);
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let detail = if stderr.trim().is_empty() {
+            stdout.trim()
+        } else {
+            stderr.trim()
+        };
+        if detail.is_empty() {
+            return Err("Git command failed.".to_string());
+        }
+        let transient = classify_git_error(detail).is_some_and(GitErrorKind::is_transient);
+        match retries.next() {
+            Some(delay) if transient => tokio::time::sleep(*delay).await,
+            _ => return Err(detail.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{classify_git_error, GitErrorKind};
+
+    #[test]
+    fn classifies_common_git_failures() {
+        let cases = [
+            (
+                "fatal: '/tmp/wt' is not a working tree",
+                GitErrorKind::NotAWorkingTree,
+            ),
+            (
+                "fatal: a branch named 'feature' already exists",
+                GitErrorKind::BranchExists,
+            ),
+            (
+                " ! [rejected]        main -> main (non-fast-forward)",
+                GitErrorKind::NonFastForward,
+            ),
+            (
+                "fatal: Not possible to fast-forward, aborting.",
+                GitErrorKind::NonFastForward,
+            ),
+            (
+                "fatal: '/tmp/wt' contains modified or untracked files, use --force to delete it",
+                GitErrorKind::DirtyWorkTree,
+            ),
+            (
+                "error: Your local changes to the following files would be overwritten by checkout:",
+                GitErrorKind::DirtyWorkTree,
+            ),
+            (
+                "fatal: Authentication failed for 'https://example.com/repo.git/'",
+                GitErrorKind::AuthFailed,
+            ),
+            (
+                "git@example.com: Permission denied (publickey).",
+                GitErrorKind::AuthFailed,
+            ),
+            (
+                "fatal: Unable to create '/repo/.git/index.lock': File exists.\n\nAnother git process seems to be running in this repository",
+                GitErrorKind::LockHeld,
+            ),
+        ];
+        for (output, kind) in cases {
+            assert_eq!(classify_git_error(output), Some(kind), "{output}");
+        }
+        assert_eq!(classify_git_error("fatal: bad revision 'nope'"), None);
+        assert!(GitErrorKind::LockHeld.is_transient());
+        assert!(!GitErrorKind::DirtyWorkTree.is_transient());
+    }
+}
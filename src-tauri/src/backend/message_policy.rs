@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+
+use serde_json::{json, Value};
+
+use crate::types::{WorkspaceEntry, WorkspaceSettings};
+
+/// Access mode applied when neither the client nor the workspace picks one.
+pub(crate) const DEFAULT_ACCESS_MODE: &str = "current";
+
+const ACCESS_MODES: [&str; 4] = [
+    "read-only",
+    "current",
+    "workspace-write-offline",
+    "full-access",
+];
+
+const APPROVAL_POLICIES: [&str; 4] = ["untrusted", "on-failure", "on-request", "never"];
+
+/// Sandbox and approval policy sent with `turn/start` for an access mode.
+pub(crate) struct MessagePolicy {
+    pub(crate) sandbox_policy: Value,
+    pub(crate) approval_policy: &'static str,
+}
+
+impl MessagePolicy {
+    pub(crate) fn to_value(&self) -> Value {
+        json!({
+            "sandboxPolicy": self.sandbox_policy,
+            "approvalPolicy": self.approval_policy,
+        })
+    }
+
+    /// Replaces the approval policy implied by the access mode, if an
+    /// override is given.
+    pub(crate) fn with_approval_policy(mut self, approval_policy: Option<&'static str>) -> Self {
+        if let Some(approval_policy) = approval_policy {
+            self.approval_policy = approval_policy;
+        }
+        self
+    }
+}
+
+/// Maps `accessMode` (`read-only`, `current`, `workspace-write-offline`,
+/// `full-access`) to the policies used by `send_user_message`. Unknown modes
+/// fall back to `current`, which lets the agent write inside the workspace only.
+pub(crate) fn build_message_policy(
+    access_mode: Option<&str>,
+    workspace_path: &str,
+    writable_roots: &[String],
+) -> MessagePolicy {
+    let (sandbox_policy, approval_policy) = build_sandbox_policy(
+        access_mode.unwrap_or(DEFAULT_ACCESS_MODE),
+        workspace_path,
+        writable_roots,
+    );
+    MessagePolicy {
+        sandbox_policy,
+        approval_policy,
+    }
+}
+
+fn non_empty(mode: Option<&str>) -> Option<&str> {
+    mode.map(str::trim).filter(|mode| !mode.is_empty())
+}
+
+fn known_access_mode(mode: Option<&str>) -> Option<&str> {
+    non_empty(mode).filter(|mode| ACCESS_MODES.contains(mode))
+}
+
+/// The access mode a message runs with: the one it sent, else the
+/// workspace's `defaultAccessMode`, else `current`. An unknown mode counts
+/// as not sent, so it can't widen what the workspace default allows.
+pub(crate) fn effective_access_mode(requested: Option<&str>, entry: &WorkspaceEntry) -> String {
+    let mode = known_access_mode(requested)
+        .or_else(|| known_access_mode(entry.settings.default_access_mode.as_deref()))
+        .unwrap_or(DEFAULT_ACCESS_MODE);
+    mode.to_string()
+}
+
+/// Rejects a `defaultAccessMode` that would silently fall back to `current`.
+pub(crate) fn validate_default_access_mode(settings: &WorkspaceSettings) -> Result<(), String> {
+    match non_empty(settings.default_access_mode.as_deref()) {
+        Some(mode) if !ACCESS_MODES.contains(&mode) => Err(format!(
+            "Unknown access mode: {mode} (expected {}).",
+            ACCESS_MODES.join(", ")
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// The workspace's `approvalPolicy` (`untrusted`, `on-failure`, `on-request`
+/// or `never`). `None` when it doesn't set a known one, in which case the
+/// access mode decides: `never` for `full-access`, `on-request` otherwise.
+pub(crate) fn workspace_approval_policy(entry: &WorkspaceEntry) -> Option<&'static str> {
+    let policy = non_empty(entry.settings.approval_policy.as_deref())?;
+    APPROVAL_POLICIES
+        .iter()
+        .copied()
+        .find(|known| *known == policy)
+}
+
+/// Rejects an `approvalPolicy` codex doesn't know.
+pub(crate) fn validate_approval_policy(settings: &WorkspaceSettings) -> Result<(), String> {
+    match non_empty(settings.approval_policy.as_deref()) {
+        Some(policy) if !APPROVAL_POLICIES.contains(&policy) => Err(format!(
+            "Unknown approval policy: {policy} (expected {}).",
+            APPROVAL_POLICIES.join(", ")
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Writable roots beyond the workspace itself. A worktree with
+/// `parentWritable` set gets its parent repo, resolved like `connect_workspace`
+/// does; everything else stays limited to its own folder.
+pub(crate) fn workspace_writable_roots(
+    entry: &WorkspaceEntry,
+    workspaces: &HashMap<String, WorkspaceEntry>,
+) -> Vec<String> {
+    if !entry.kind.is_worktree() || !entry.settings.parent_writable {
+        return Vec::new();
+    }
+    entry
+        .parent_id
+        .as_ref()
+        .and_then(|parent_id| workspaces.get(parent_id))
+        .map(|parent| vec![parent.path.clone()])
+        .unwrap_or_default()
+}
+
+/// Sandbox policy and approval policy for an access mode. `writable_roots`
+/// are extra directories the agent may write to next to the workspace; they
+/// only apply to workspace-write modes.
+pub(crate) fn build_sandbox_policy(
+    access_mode: &str,
+    workspace_path: &str,
+    writable_roots: &[String],
+) -> (Value, &'static str) {
+    match access_mode {
+        "full-access" => (json!({ "type": "dangerFullAccess" }), "never"),
+        "read-only" => (json!({ "type": "readOnly" }), "on-request"),
+        "workspace-write-offline" => (
+            workspace_write_policy(workspace_path, writable_roots, false),
+            "on-request",
+        ),
+        _ => (
+            workspace_write_policy(workspace_path, writable_roots, true),
+            "on-request",
+        ),
+    }
+}
+
+fn workspace_write_policy(
+    workspace_path: &str,
+    writable_roots: &[String],
+    network_access: bool,
+) -> Value {
+    let mut roots = vec![workspace_path.to_string()];
+    roots.extend(
+        writable_roots
+            .iter()
+            .filter(|root| root.as_str() != workspace_path)
+            .cloned(),
+    );
+    json!({
+        "type": "workspaceWrite",
+        "writableRoots": roots,
+        "networkAccess": network_access
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_message_policy, build_sandbox_policy, effective_access_mode,
+        validate_approval_policy, validate_default_access_mode, workspace_approval_policy,
+        workspace_writable_roots,
+    };
+    use crate::types::{WorkspaceEntry, WorkspaceKind, WorkspaceSettings};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn entry(id: &str, path: &str, parent_id: Option<&str>) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            path: path.to_string(),
+            codex_bin: None,
+            kind: if parent_id.is_some() {
+                WorkspaceKind::Worktree
+            } else {
+                WorkspaceKind::Main
+            },
+            parent_id: parent_id.map(str::to_string),
+            worktree: None,
+            settings: WorkspaceSettings::default(),
+            status: None,
+        }
+    }
+
+    #[test]
+    fn current_mode_limits_writes_to_the_workspace() {
+        let policy = build_message_policy(None, "/tmp/repo", &[]);
+        assert_eq!(
+            policy.sandbox_policy,
+            json!({
+                "type": "workspaceWrite",
+                "writableRoots": ["/tmp/repo"],
+                "networkAccess": true
+            })
+        );
+        assert_eq!(policy.approval_policy, "on-request");
+    }
+
+    #[test]
+    fn read_only_mode_still_asks_for_approval() {
+        let policy = build_message_policy(Some("read-only"), "/tmp/repo", &[]);
+        assert_eq!(policy.sandbox_policy, json!({ "type": "readOnly" }));
+        assert_eq!(policy.approval_policy, "on-request");
+    }
+
+    #[test]
+    fn full_access_mode_never_asks() {
+        let policy = build_message_policy(Some("full-access"), "/tmp/repo", &[]);
+        assert_eq!(policy.sandbox_policy, json!({ "type": "dangerFullAccess" }));
+        assert_eq!(policy.approval_policy, "never");
+    }
+
+    #[test]
+    fn unknown_modes_fall_back_to_current() {
+        let policy = build_message_policy(Some("bogus"), "/tmp/repo", &[]);
+        assert_eq!(policy.sandbox_policy["type"], "workspaceWrite");
+        assert_eq!(
+            policy.to_value(),
+            build_message_policy(Some("current"), "/tmp/repo", &[]).to_value()
+        );
+    }
+
+    #[test]
+    fn sandbox_policy_covers_each_mode() {
+        let (sandbox, approval) = build_sandbox_policy("read-only", "/tmp/repo", &[]);
+        assert_eq!(sandbox, json!({ "type": "readOnly" }));
+        assert_eq!(approval, "on-request");
+
+        let (sandbox, approval) = build_sandbox_policy("full-access", "/tmp/repo", &[]);
+        assert_eq!(sandbox, json!({ "type": "dangerFullAccess" }));
+        assert_eq!(approval, "never");
+
+        let (sandbox, approval) = build_sandbox_policy("current", "/tmp/repo", &[]);
+        assert_eq!(
+            sandbox,
+            json!({
+                "type": "workspaceWrite",
+                "writableRoots": ["/tmp/repo"],
+                "networkAccess": true
+            })
+        );
+        assert_eq!(approval, "on-request");
+
+        let (sandbox, approval) = build_sandbox_policy("workspace-write-offline", "/tmp/repo", &[]);
+        assert_eq!(
+            sandbox,
+            json!({
+                "type": "workspaceWrite",
+                "writableRoots": ["/tmp/repo"],
+                "networkAccess": false
+            })
+        );
+        assert_eq!(approval, "on-request");
+    }
+
+    #[test]
+    fn sandbox_policy_falls_through_to_workspace_write() {
+        for mode in ["", "Read-Only", "readonly", "full_access"] {
+            let (sandbox, approval) = build_sandbox_policy(mode, "/tmp/repo", &[]);
+            assert_eq!(sandbox["type"], "workspaceWrite", "mode {mode:?}");
+            assert_eq!(approval, "on-request", "mode {mode:?}");
+        }
+    }
+
+    #[test]
+    fn extra_writable_roots_only_widen_workspace_write() {
+        let roots = vec!["/tmp/cache".to_string(), "/tmp/repo".to_string()];
+        let (sandbox, _) = build_sandbox_policy("current", "/tmp/repo", &roots);
+        assert_eq!(sandbox["writableRoots"], json!(["/tmp/repo", "/tmp/cache"]));
+
+        let (sandbox, _) = build_sandbox_policy("workspace-write-offline", "/tmp/repo", &roots);
+        assert_eq!(sandbox["writableRoots"], json!(["/tmp/repo", "/tmp/cache"]));
+
+        let (sandbox, _) = build_sandbox_policy("read-only", "/tmp/repo", &roots);
+        assert_eq!(sandbox, json!({ "type": "readOnly" }));
+    }
+
+    #[test]
+    fn parent_repo_is_writable_only_when_opted_in() {
+        let parent = entry("parent", "/tmp/repo", None);
+        let mut worktree = entry("wt", "/tmp/worktrees/wt", Some("parent"));
+        let workspaces = HashMap::from([
+            (parent.id.clone(), parent.clone()),
+            (worktree.id.clone(), worktree.clone()),
+        ]);
+        assert!(workspace_writable_roots(&worktree, &workspaces).is_empty());
+
+        worktree.settings.parent_writable = true;
+        let roots = workspace_writable_roots(&worktree, &workspaces);
+        assert_eq!(roots, vec!["/tmp/repo".to_string()]);
+        let policy = build_message_policy(None, &worktree.path, &roots);
+        assert_eq!(
+            policy.sandbox_policy["writableRoots"],
+            json!(["/tmp/worktrees/wt", "/tmp/repo"])
+        );
+
+        let mut main = parent.clone();
+        main.settings.parent_writable = true;
+        assert!(workspace_writable_roots(&main, &workspaces).is_empty());
+    }
+
+    #[test]
+    fn workspace_default_applies_when_no_mode_is_sent() {
+        let mut workspace = entry("ws", "/tmp/repo", None);
+        assert_eq!(effective_access_mode(None, &workspace), "current");
+
+        workspace.settings.default_access_mode = Some("read-only".to_string());
+        assert_eq!(effective_access_mode(None, &workspace), "read-only");
+        assert_eq!(effective_access_mode(Some(" "), &workspace), "read-only");
+        assert_eq!(
+            effective_access_mode(Some("full-access"), &workspace),
+            "full-access"
+        );
+        assert_eq!(
+            effective_access_mode(Some("bogus"), &workspace),
+            "read-only"
+        );
+        assert!(validate_default_access_mode(&workspace.settings).is_ok());
+
+        workspace.settings.default_access_mode = Some("readonly".to_string());
+        assert!(validate_default_access_mode(&workspace.settings).is_err());
+        assert_eq!(effective_access_mode(Some("bogus"), &workspace), "current");
+    }
+
+    #[test]
+    fn workspace_approval_policy_overrides_the_access_mode() {
+        let mut workspace = entry("ws", "/tmp/repo", None);
+        assert_eq!(workspace_approval_policy(&workspace), None);
+        let policy = build_message_policy(Some("full-access"), "/tmp/repo", &[])
+            .with_approval_policy(workspace_approval_policy(&workspace));
+        assert_eq!(policy.approval_policy, "never");
+
+        workspace.settings.approval_policy = Some("untrusted".to_string());
+        let policy = build_message_policy(Some("full-access"), "/tmp/repo", &[])
+            .with_approval_policy(workspace_approval_policy(&workspace));
+        assert_eq!(policy.approval_policy, "untrusted");
+        assert_eq!(policy.sandbox_policy, json!({ "type": "dangerFullAccess" }));
+        assert!(validate_approval_policy(&workspace.settings).is_ok());
+
+        workspace.settings.approval_policy = Some("on_failure".to_string());
+        assert_eq!(workspace_approval_policy(&workspace), None);
+        assert!(validate_approval_policy(&workspace.settings).is_err());
+    }
+}
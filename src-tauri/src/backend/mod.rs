@@ -1,2 +1,7 @@
 pub(crate) mod app_server;
+pub(crate) mod codex_capabilities;
+pub(crate) mod compression;
 pub(crate) mod events;
+pub(crate) mod git_command;
+pub(crate) mod message_policy;
+pub(crate) mod workspace_settings;
@@ -0,0 +1,362 @@
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::backend::message_policy::{validate_approval_policy, validate_default_access_mode};
+use crate::types::WorkspaceSettings;
+
+/// Highest `sortOrder` accepted; anything above is a client bug, such as a
+/// negative index that wrapped around.
+const MAX_SORT_ORDER: u32 = 1_000_000;
+const MAX_CONCURRENT_TURNS: u32 = 64;
+/// One week.
+const MAX_IDLE_DISCONNECT_MINUTES: u32 = 7 * 24 * 60;
+/// Enough for emoji sequences and icon names such as `git-branch`.
+const MAX_ICON_CHARS: usize = 32;
+/// Below this, the inline end of a captured output would be most of it.
+const MIN_ARTIFACT_THRESHOLD_BYTES: u64 = 4 * 1024;
+
+/// One rejected field of a workspace settings update.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub(crate) struct InvalidSetting {
+    pub(crate) field: String,
+    pub(crate) reason: String,
+}
+
+/// Every field a settings update got wrong, so a client can fix them at once.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct InvalidSettings {
+    pub(crate) fields: Vec<InvalidSetting>,
+}
+
+impl InvalidSettings {
+    fn single(field: &str, reason: impl Into<String>) -> Self {
+        Self {
+            fields: vec![InvalidSetting {
+                field: field.to_string(),
+                reason: reason.into(),
+            }],
+        }
+    }
+
+    pub(crate) fn message(&self) -> String {
+        let fields: Vec<String> = self
+            .fields
+            .iter()
+            .map(|invalid| format!("{}: {}", invalid.field, invalid.reason))
+            .collect();
+        format!("Invalid workspace settings: {}.", fields.join("; "))
+    }
+}
+
+impl From<InvalidSettings> for String {
+    fn from(error: InvalidSettings) -> Self {
+        error.message()
+    }
+}
+
+/// `#rgb`, `#rrggbb` or `#rrggbbaa`.
+fn is_hex_color(value: &str) -> bool {
+    value.strip_prefix('#').is_some_and(|digits| {
+        matches!(digits.len(), 3 | 6 | 8) && digits.chars().all(|c| c.is_ascii_hexdigit())
+    })
+}
+
+fn is_icon(value: &str) -> bool {
+    let chars = value.chars().count();
+    chars > 0
+        && chars <= MAX_ICON_CHARS
+        && !value.chars().any(|c| c.is_whitespace() || c.is_control())
+}
+
+fn invalid(fields: &mut Vec<InvalidSetting>, field: &str, reason: impl Into<String>) {
+    fields.push(InvalidSetting {
+        field: field.to_string(),
+        reason: reason.into(),
+    });
+}
+
+/// Reads a full settings object as sent by a client. Each field is checked on
+/// its own so every wrongly typed one is reported, and unknown keys are
+/// rejected unless `lenient` is set, in which case they are dropped.
+pub(crate) fn parse_workspace_settings(
+    value: &Value,
+    lenient: bool,
+) -> Result<WorkspaceSettings, InvalidSettings> {
+    let Value::Object(map) = value else {
+        return Err(InvalidSettings::single("settings", "must be a JSON object"));
+    };
+    let known = match serde_json::to_value(WorkspaceSettings::default()) {
+        Ok(Value::Object(known)) => known,
+        _ => return Err(InvalidSettings::single("settings", "could not be checked")),
+    };
+    let mut fields = Vec::new();
+    let mut accepted = Map::new();
+    for (key, value) in map {
+        if !known.contains_key(key) {
+            if !lenient {
+                invalid(&mut fields, key, "unknown setting");
+            }
+            continue;
+        }
+        let single = Value::Object(Map::from_iter([(key.clone(), value.clone())]));
+        match serde_json::from_value::<WorkspaceSettings>(single) {
+            Ok(_) => {
+                accepted.insert(key.clone(), value.clone());
+            }
+            Err(err) => invalid(&mut fields, key, err.to_string()),
+        }
+    }
+    if !fields.is_empty() {
+        return Err(InvalidSettings { fields });
+    }
+    serde_json::from_value(Value::Object(accepted))
+        .map_err(|err| InvalidSettings::single("settings", err.to_string()))
+}
+
+/// Range and consistency checks shared by every settings update. `gitRoot`
+/// must name an existing folder, resolved against `workspace_path` when
+/// relative, as git commands resolve it.
+pub(crate) fn validate_workspace_settings(
+    settings: &WorkspaceSettings,
+    workspace_path: &str,
+) -> Result<(), InvalidSettings> {
+    let mut fields = Vec::new();
+    if settings
+        .sort_order
+        .is_some_and(|order| order > MAX_SORT_ORDER)
+    {
+        invalid(
+            &mut fields,
+            "sortOrder",
+            format!("must be at most {MAX_SORT_ORDER}"),
+        );
+    }
+    if settings
+        .max_concurrent_turns
+        .is_some_and(|turns| turns == 0 || turns > MAX_CONCURRENT_TURNS)
+    {
+        invalid(
+            &mut fields,
+            "maxConcurrentTurns",
+            format!("must be between 1 and {MAX_CONCURRENT_TURNS}, or null for no limit"),
+        );
+    }
+    if let Err(reason) = validate_default_access_mode(settings) {
+        invalid(
+            &mut fields,
+            "defaultAccessMode",
+            reason.trim_end_matches('.'),
+        );
+    }
+    if let Err(reason) = validate_approval_policy(settings) {
+        invalid(&mut fields, "approvalPolicy", reason.trim_end_matches('.'));
+    }
+    if settings
+        .idle_disconnect_minutes
+        .is_some_and(|minutes| minutes > MAX_IDLE_DISCONNECT_MINUTES)
+    {
+        invalid(
+            &mut fields,
+            "idleDisconnectMinutes",
+            format!("must be at most {MAX_IDLE_DISCONNECT_MINUTES}, or 0 to stay connected"),
+        );
+    }
+    if settings
+        .color
+        .as_deref()
+        .is_some_and(|color| !is_hex_color(color))
+    {
+        invalid(
+            &mut fields,
+            "color",
+            "must be a hex color such as #3b82f6, or null",
+        );
+    }
+    if settings.icon.as_deref().is_some_and(|icon| !is_icon(icon)) {
+        invalid(
+            &mut fields,
+            "icon",
+            format!("must be at most {MAX_ICON_CHARS} characters without spaces, or null"),
+        );
+    }
+    if settings
+        .artifact_threshold_bytes
+        .is_some_and(|bytes| bytes < MIN_ARTIFACT_THRESHOLD_BYTES)
+    {
+        invalid(
+            &mut fields,
+            "artifactThresholdBytes",
+            format!("must be at least {MIN_ARTIFACT_THRESHOLD_BYTES}, or null for 64 KiB"),
+        );
+    }
+    let git_root = settings
+        .git_root
+        .as_deref()
+        .map(str::trim)
+        .filter(|root| !root.is_empty());
+    if let Some(root) = git_root {
+        if !Path::new(workspace_path).join(root).is_dir() {
+            invalid(&mut fields, "gitRoot", format!("folder not found: {root}"));
+        }
+    }
+    if fields.is_empty() {
+        Ok(())
+    } else {
+        Err(InvalidSettings { fields })
+    }
+}
+
+/// `validate_workspace_settings` for an update from `previous`: only fields
+/// the update changes are checked, so a value stored before a check existed
+/// (or a `gitRoot` folder that has since gone) doesn't block other edits.
+pub(crate) fn validate_settings_change(
+    previous: &WorkspaceSettings,
+    settings: &WorkspaceSettings,
+    workspace_path: &str,
+) -> Result<(), InvalidSettings> {
+    let Err(InvalidSettings { fields }) = validate_workspace_settings(settings, workspace_path)
+    else {
+        return Ok(());
+    };
+    let (Ok(Value::Object(previous)), Ok(Value::Object(settings))) = (
+        serde_json::to_value(previous),
+        serde_json::to_value(settings),
+    ) else {
+        return Err(InvalidSettings { fields });
+    };
+    let fields: Vec<InvalidSetting> = fields
+        .into_iter()
+        .filter(|invalid| previous.get(&invalid.field) != settings.get(&invalid.field))
+        .collect();
+    if fields.is_empty() {
+        Ok(())
+    } else {
+        Err(InvalidSettings { fields })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_workspace_settings, validate_settings_change, validate_workspace_settings};
+    use crate::types::WorkspaceSettings;
+    use serde_json::json;
+
+    fn invalid_fields(error: super::InvalidSettings) -> Vec<String> {
+        error
+            .fields
+            .into_iter()
+            .map(|invalid| invalid.field)
+            .collect()
+    }
+
+    #[test]
+    fn reports_every_mistyped_and_unknown_field() {
+        let settings = json!({
+            "sortOrder": -1,
+            "sidebarCollapsed": "yes",
+            "groupId": "group-1",
+            "sort_order": 2,
+        });
+        let error = parse_workspace_settings(&settings, false).expect_err("invalid");
+        let mut fields = invalid_fields(error.clone());
+        fields.sort();
+        assert_eq!(fields, ["sidebarCollapsed", "sortOrder", "sort_order"]);
+        assert!(error.message().starts_with("Invalid workspace settings: "));
+
+        let lenient =
+            parse_workspace_settings(&json!({ "groupId": "group-1", "sort_order": 2 }), true)
+                .expect("lenient");
+        assert_eq!(lenient.group_id.as_deref(), Some("group-1"));
+        assert!(lenient.sort_order.is_none());
+        assert!(parse_workspace_settings(&json!(null), true).is_err());
+    }
+
+    #[test]
+    fn checks_ranges_modes_and_git_root() {
+        let workspace = std::env::temp_dir();
+        let workspace = workspace.to_string_lossy();
+        assert!(validate_workspace_settings(&WorkspaceSettings::default(), &workspace).is_ok());
+
+        let settings = WorkspaceSettings {
+            sort_order: Some(u32::MAX),
+            max_concurrent_turns: Some(0),
+            default_access_mode: Some("root".to_string()),
+            approval_policy: Some("sometimes".to_string()),
+            idle_disconnect_minutes: Some(100_000),
+            color: Some("blue".to_string()),
+            icon: Some("two words".to_string()),
+            artifact_threshold_bytes: Some(100),
+            git_root: Some("missing-child-folder".to_string()),
+            ..WorkspaceSettings::default()
+        };
+        let error = validate_workspace_settings(&settings, &workspace).expect_err("invalid");
+        assert_eq!(
+            invalid_fields(error),
+            [
+                "sortOrder",
+                "maxConcurrentTurns",
+                "defaultAccessMode",
+                "approvalPolicy",
+                "idleDisconnectMinutes",
+                "color",
+                "icon",
+                "artifactThresholdBytes",
+                "gitRoot",
+            ]
+        );
+
+        let settings = WorkspaceSettings {
+            sort_order: Some(3),
+            max_concurrent_turns: Some(4),
+            default_access_mode: Some("read-only".to_string()),
+            approval_policy: Some("on-failure".to_string()),
+            idle_disconnect_minutes: Some(0),
+            color: Some("#3B82F6".to_string()),
+            icon: Some("🚀".to_string()),
+            artifact_threshold_bytes: Some(1024 * 1024),
+            git_root: Some(workspace.to_string()),
+            ..WorkspaceSettings::default()
+        };
+        assert!(validate_workspace_settings(&settings, &workspace).is_ok());
+        for color in ["#abc", "#aabbccdd"] {
+            let settings = WorkspaceSettings {
+                color: Some(color.to_string()),
+                icon: Some("git-branch".to_string()),
+                ..WorkspaceSettings::default()
+            };
+            assert!(validate_workspace_settings(&settings, &workspace).is_ok());
+        }
+        for color in ["", "#abcd", "#gggggg", "3b82f6"] {
+            let settings = WorkspaceSettings {
+                color: Some(color.to_string()),
+                ..WorkspaceSettings::default()
+            };
+            assert!(validate_workspace_settings(&settings, &workspace).is_err());
+        }
+    }
+    #[test]
+    fn checks_only_the_fields_an_update_changes() {
+        let workspace = std::env::temp_dir();
+        let workspace = workspace.to_string_lossy();
+        let stored = WorkspaceSettings {
+            color: Some("blue".to_string()),
+            git_root: Some("removed-child-folder".to_string()),
+            ..WorkspaceSettings::default()
+        };
+        let renamed = WorkspaceSettings {
+            icon: Some("git-branch".to_string()),
+            ..stored.clone()
+        };
+        assert!(validate_settings_change(&stored, &renamed, &workspace).is_ok());
+
+        let recolored = WorkspaceSettings {
+            color: Some("red".to_string()),
+            sort_order: Some(u32::MAX),
+            ..stored.clone()
+        };
+        let error = validate_settings_change(&stored, &recolored, &workspace).expect_err("invalid");
+        assert_eq!(invalid_fields(error), ["sortOrder", "color"]);
+    }
+}
@@ -1,47 +1,357 @@
+#[path = "codex_monitor_daemon/access_log.rs"]
+mod access_log;
+#[path = "codex_monitor_daemon/account_limits.rs"]
+mod account_limits;
+#[path = "codex_monitor_daemon/activity.rs"]
+mod activity;
+#[path = "codex_monitor_daemon/artifacts.rs"]
+mod artifacts;
+#[path = "codex_monitor_daemon/authorization.rs"]
+mod authorization;
 #[allow(dead_code)]
 #[path = "../backend/mod.rs"]
 mod backend;
+#[path = "codex_monitor_daemon/blame.rs"]
+mod blame;
+#[path = "codex_monitor_daemon/bulk_settings.rs"]
+mod bulk_settings;
+#[path = "codex_monitor_daemon/cherry_pick.rs"]
+mod cherry_pick;
+#[path = "codex_monitor_daemon/clone.rs"]
+mod clone;
+#[path = "codex_monitor_daemon/codex_bin_reload.rs"]
+mod codex_bin_reload;
 #[path = "../codex_home.rs"]
 mod codex_home;
 #[path = "../codex_config.rs"]
 mod codex_config;
+#[path = "codex_monitor_daemon/collaboration_modes.rs"]
+mod collaboration_modes;
+#[path = "codex_monitor_daemon/conflicts.rs"]
+mod conflicts;
+#[path = "codex_monitor_daemon/data_usage.rs"]
+mod data_usage;
+#[path = "codex_monitor_daemon/discard.rs"]
+mod discard;
+#[path = "codex_monitor_daemon/discover.rs"]
+mod discover;
+#[path = "codex_monitor_daemon/event_lag.rs"]
+mod event_lag;
+#[path = "codex_monitor_daemon/fetch.rs"]
+mod fetch;
+#[path = "codex_monitor_daemon/file_index.rs"]
+mod file_index;
+#[path = "codex_monitor_daemon/framing.rs"]
+mod framing;
+#[path = "../git_identity.rs"]
+mod git_identity;
+#[path = "../git_state.rs"]
+mod git_state;
+#[path = "codex_monitor_daemon/health.rs"]
+mod health;
+#[path = "codex_monitor_daemon/http.rs"]
+mod http;
+#[path = "codex_monitor_daemon/idle.rs"]
+mod idle;
+#[path = "codex_monitor_daemon/images.rs"]
+mod images;
+#[path = "codex_monitor_daemon/metrics.rs"]
+mod metrics;
+#[path = "codex_monitor_daemon/mirror.rs"]
+mod mirror;
+#[path = "codex_monitor_daemon/models.rs"]
+mod models;
+#[path = "codex_monitor_daemon/operations.rs"]
+mod operations;
+#[path = "codex_monitor_daemon/params.rs"]
+mod params;
+#[path = "codex_monitor_daemon/paused.rs"]
+mod paused;
+#[path = "../preflight.rs"]
+mod preflight;
+#[path = "codex_monitor_daemon/preflight_summary.rs"]
+mod preflight_summary;
+#[path = "codex_monitor_daemon/process.rs"]
+mod process;
+#[path = "codex_monitor_daemon/profiles.rs"]
+mod profiles;
+#[path = "codex_monitor_daemon/pull_request.rs"]
+mod pull_request;
+#[path = "codex_monitor_daemon/rate_limit.rs"]
+mod rate_limit;
+#[path = "codex_monitor_daemon/read_only.rs"]
+mod read_only;
+#[path = "codex_monitor_daemon/remote_info.rs"]
+mod remote_info;
+#[path = "codex_monitor_daemon/rpc_error.rs"]
+mod rpc_error;
 #[path = "../rules.rs"]
 mod rules;
+#[path = "codex_monitor_daemon/session_info.rs"]
+mod session_info;
+#[path = "codex_monitor_daemon/show_commit.rs"]
+mod show_commit;
+#[path = "codex_monitor_daemon/snapshot.rs"]
+mod snapshot;
+#[path = "codex_monitor_daemon/spawn_queue.rs"]
+mod spawn_queue;
+#[path = "codex_monitor_daemon/squash.rs"]
+mod squash;
 #[path = "../storage.rs"]
 mod storage;
+#[path = "../submodules.rs"]
+mod submodules;
+#[path = "codex_monitor_daemon/tags.rs"]
+mod tags;
+#[path = "codex_monitor_daemon/thread_import.rs"]
+mod thread_import;
+#[path = "codex_monitor_daemon/thread_list.rs"]
+mod thread_list;
+#[path = "codex_monitor_daemon/thread_reads.rs"]
+mod thread_reads;
+#[path = "codex_monitor_daemon/thread_seed.rs"]
+mod thread_seed;
+#[path = "codex_monitor_daemon/thread_subscriptions.rs"]
+mod thread_subscriptions;
+#[path = "codex_monitor_daemon/thread_titles.rs"]
+mod thread_titles;
+#[path = "codex_monitor_daemon/trash.rs"]
+mod trash;
+#[path = "codex_monitor_daemon/turn_inputs.rs"]
+mod turn_inputs;
+#[path = "codex_monitor_daemon/turns.rs"]
+mod turns;
 #[allow(dead_code)]
 #[path = "../types.rs"]
 mod types;
+#[path = "../utils.rs"]
+mod utils;
+#[path = "codex_monitor_daemon/webhook.rs"]
+mod webhook;
+#[path = "codex_monitor_daemon/workspace_errors.rs"]
+mod workspace_errors;
+#[path = "codex_monitor_daemon/workspace_sort.rs"]
+mod workspace_sort;
+#[path = "codex_monitor_daemon/worktree_checkout.rs"]
+mod worktree_checkout;
+#[path = "codex_monitor_daemon/worktree_gc.rs"]
+mod worktree_gc;
+#[path = "../worktree_move.rs"]
+mod worktree_move;
+#[path = "../worktree_paths.rs"]
+mod worktree_paths;
 
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Map, Value};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::env;
 use std::fs::File;
-use std::io::Read;
-use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::future::Future;
+use std::io::{Read, SeekFrom};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 
 use ignore::WalkBuilder;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
 use tokio::net::{TcpListener, TcpStream};
-use tokio::process::Command;
-use tokio::sync::{broadcast, mpsc, Mutex};
+use tokio::sync::{broadcast, Mutex, OnceCell, Semaphore};
 use uuid::Uuid;
 
-use backend::app_server::{spawn_workspace_session, WorkspaceSession};
+use access_log::{AccessLog, AccessStatus, DEFAULT_ACCESS_LOG_MAX_BYTES};
+use account_limits::{
+    rate_limits_of, RateLimitUpdate, RateLimitWatch, DEFAULT_RATE_LIMIT_WARN_PERCENT,
+    MIN_RATE_LIMIT_POLL,
+};
+use activity::{ActivityItem, ActivityLog, DEFAULT_ACTIVITY_LIMIT};
+use artifacts::{Artifacts, ARTIFACT_PRUNE_INTERVAL, DEFAULT_ARTIFACT_RETENTION_DAYS};
+use authorization::{request_workspaces, AuthorizationPolicy};
+use backend::app_server::{
+    expand_path_vars, spawn_workspace_session, SessionTiming, WorkspaceSession, SESSION_TIMING,
+};
+use backend::compression::{decompress_message, ZSTD_ENCODING};
 use backend::events::{AppServerEvent, EventSink, TerminalOutput};
+use backend::git_command::{
+    classify_git_error, git_command, run_git, GitErrorKind, GitTimer, GIT_TIMER,
+};
+use backend::message_policy::{
+    build_message_policy, effective_access_mode, workspace_approval_policy,
+    workspace_writable_roots,
+};
+use backend::workspace_settings::{
+    parse_workspace_settings, validate_settings_change, InvalidSettings,
+};
+use blame::{git_blame, BlameResponse};
+use bulk_settings::{
+    apply_bulk_patch, parse_bulk_target, target_ids, BulkSettingsResult, BulkTarget,
+};
+use cherry_pick::{
+    ensure_cherry_pick_in_progress, resolve_commit, resolve_worktree_commits, run_cherry_pick,
+    CherryPickError, CherryPickResult,
+};
+use clone::{
+    clone_destination, repo_name_from_url, run_git_clone, CloneProgress, CloneRequest,
+    DEFAULT_PROJECTS_DIR,
+};
+use codex_bin_reload::{CodexBinReload, RestartFailure};
+use collaboration_modes::resolve_collaboration_mode;
+use conflicts::{
+    abort_operation, conflict_status, continue_operation, list_conflicts, mark_resolved,
+    resolve_conflict, ConflictFile, ConflictResolution, ConflictStatus,
+};
+use data_usage::{
+    collect_data_usage, parse_cleanup_categories, CleanupCategory, CleanupSummary, DataDirReport,
+    DiskUsageReport,
+};
+use discard::{discard_changes, DiscardResult};
+use discover::{
+    discover_workspaces, DiscoveryResult, DEFAULT_DISCOVERY_DEPTH, MAX_DISCOVERY_DEPTH,
+};
+use event_lag::{EventLagTracker, DEFAULT_EVENT_BUFFER};
+use fetch::{
+    now_ms, run_fetch, FetchTracker, RepoLocks, FETCH_TICK, MAX_CONCURRENT_FETCHES,
+    MIN_FETCH_INTERVAL_SECS,
+};
+use file_index::{FileDelta, FileIndexes};
+use framing::{
+    build_parse_error_response, cap_response_size, discard_line, outbound_queue, read_line_limited,
+    write_outbound, CappedResponse, FramingCounters, FramingLimits, OutboundSender, ReadLine,
+    AUTH_TIMEOUT, DEFAULT_MAX_OUTBOUND_QUEUE_BYTES, DEFAULT_MAX_REQUEST_BYTES,
+    DEFAULT_MAX_RESPONSE_BYTES, LINE_READ_TIMEOUT,
+};
+use git_identity::{read_git_identity, write_git_identity, GitIdentity, GitIdentityScope};
+use git_state::workspace_git_path;
+use health::{ping_session, ping_timeout, slow_after, SessionHealth, MAX_CONCURRENT_PINGS};
+use http::{
+    byte_range, content_type, empty_response, error_response, etag_matches, file_etag, file_head,
+    json_response, metrics_response, parse_request_head, read_request_head, route,
+    rpc_error_status, sse_event, sse_head, ByteRange, HttpRequest, HttpRoute, HEAD_TIMEOUT,
+    SSE_KEEP_ALIVE,
+};
+use idle::{idle_limit, IdleTracker, IDLE_CHECK_INTERVAL};
+use images::{validate_images, with_rejected_images, DEFAULT_MAX_IMAGE_BYTES};
+use metrics::Metrics;
+use mirror::{Mirror, MirrorConfig, READ_ONLY_MESSAGE};
+use models::{model_details, ModelCatalog, ModelCheckError, ModelDetails};
+use operations::{OperationProgress, OperationStatus, Operations};
+use params::normalize_params;
+use paused::PausedWorkspaces;
+use preflight::{run_checks, PreflightReport};
+use preflight_summary::{
+    parse_workspace_scope, PreflightCache, WorkspaceScope, PREFLIGHT_INTERVAL,
+};
+use process::PidFile;
+use profiles::{ProfileStore, WorkspaceProfile};
+use pull_request::{
+    create_pull_request, pull_request_status, push_with_upstream, CreatedPullRequest,
+    PullRequestStatus,
+};
+use rate_limit::{
+    ConnectionRateLimiter, RateLimitConfig, DEFAULT_EXPENSIVE_REQUESTS_PER_SEC,
+    DEFAULT_REQUESTS_PER_SEC,
+};
+use read_only::{is_read_request, READ_ONLY_ERROR};
+use remote_info::{
+    default_branch, fill_compare_template, preferred_remote, read_remote_info, BranchCompareUrl,
+    RemoteInfo,
+};
+use rpc_error::RpcError;
+use session_info::{instant_ms, resident_memory_bytes, SessionInfo};
+use show_commit::{show_commit, CommitDetails};
+use spawn_queue::{SpawnQueue, DEFAULT_MAX_CONCURRENT_SPAWNS};
+use squash::{squash_worktree, SquashResult};
 use storage::{read_settings, read_workspaces, write_settings, write_workspaces};
+use submodules::{submodule_paths, SUBMODULE_TERMINAL_ID};
+use tags::{create_tag, list_tags, TagInfo};
+use thread_import::{find_importable, list_position, ImportedThreads, MAX_LISTED_PAGES};
+use thread_list::{
+    clamp_page_size, first_thread_in, normalize_thread_list, ThreadListCache, ThreadListError,
+    ThreadPage, MAX_THREAD_PAGE_SIZE,
+};
+use thread_reads::ThreadReads;
+use thread_seed::{item_text, parse_seed_source, started_thread_id, SeedSource};
+use thread_subscriptions::ThreadSubscriptions;
+use thread_titles::{generate_title, ThreadTitles, TitleSource, AUTO_TITLE_INTERVAL};
+use trash::{
+    blocking, create_item, expires_at, finish_item, item_dir, list_tombstones, read_tombstone,
+    remove_item, restore_worktree, trash_root, trash_worktree, write_tombstone, RestoreSummary,
+    Tombstone, TrashItem, TrashedWorkspace, DEFAULT_TRASH_RETENTION_DAYS, TRASH_PURGE_INTERVAL,
+};
+use turn_inputs::{started_turn_id, TurnInput, TurnInputPage, TurnInputs};
+use turns::{
+    turn_cap, wait_turn_timeout, Slot, TurnOutcome, TurnTracker, DEFAULT_MAX_TURNS_PER_SESSION,
+};
 use types::{
-    AppSettings, WorkspaceEntry, WorkspaceInfo, WorkspaceKind, WorkspaceSettings, WorktreeInfo,
+    AppSettings, GitOperation, WorkspaceEntry, WorkspaceEntryStatus, WorkspaceFetchStatus,
+    WorkspaceInfo, WorkspaceKind, WorkspaceSettings, WorktreeInfo,
+};
+use utils::{is_path_within, normalize_git_path};
+use webhook::{
+    default_webhook_events, parse_webhook_events, parse_webhook_hosts, parse_webhook_url,
+    WebhookConfig, WebhookUpdate, Webhooks, DEFAULT_WEBHOOK_EVENTS,
+};
+use workspace_errors::{ErrorSource, NewError, WorkspaceError, WorkspaceErrors};
+use workspace_sort::{sort_workspaces_by, WorkspaceSort};
+use worktree_checkout::{
+    add_checkout, git_list_worktrees, prune_worktrees, remove_checkout, workspace_entry_status,
+    GitWorktreeRecord,
+};
+use worktree_gc::{WorktreeGcFailure, WorktreeGcReport};
+use worktree_move::{move_destination, relocate_worktree};
+use worktree_paths::{
+    create_dir_all_tracked, first_free_name, validate_worktree_path, worktree_dir_name,
+    worktree_path_for_branch, worktree_path_for_rename,
 };
 
 const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:4732";
+/// Sessions restarted at once by `reload_codex_bin`.
+const MAX_CONCURRENT_RESTARTS: usize = 4;
+/// `confirm` value `purge_data` requires.
+const PURGE_CONFIRMATION: &str = "purge-all-data";
+/// State files in the data dir besides `workspaces.json` and `settings.json`.
+const PURGED_STATE_FILES: [&str; 8] = [
+    "imported_threads.json",
+    "paused_workspaces.json",
+    "thread_reads.json",
+    "thread_titles.json",
+    "turn_inputs.json",
+    "webhooks.json",
+    "workspace_errors.json",
+    "workspace_profiles.json",
+];
 
 #[derive(Clone)]
 struct DaemonEventSink {
-    tx: broadcast::Sender<DaemonEvent>,
+    tx: broadcast::Sender<SequencedEvent>,
+    /// Sequence number of the last event sent; notifications carry it as `seq`.
+    seq: Arc<AtomicU64>,
+    thread_reads: Arc<ThreadReads>,
+    thread_titles: Arc<ThreadTitles>,
+    turns: Arc<TurnTracker>,
+    activity: Arc<ActivityLog>,
+    idle: Arc<IdleTracker>,
+    artifacts: Arc<Artifacts>,
+}
+
+impl DaemonEventSink {
+    fn send(&self, event: DaemonEvent) {
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed) + 1;
+        let _ = self.tx.send(SequencedEvent { seq, event });
+    }
+
+    fn latest_seq(&self) -> u64 {
+        self.seq.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Clone)]
+struct SequencedEvent {
+    seq: u64,
+    event: DaemonEvent,
 }
 
 #[derive(Clone)]
@@ -49,24 +359,239 @@ enum DaemonEvent {
     AppServer(AppServerEvent),
     #[allow(dead_code)]
     TerminalOutput(TerminalOutput),
+    WorktreeGc(WorktreeGcReport),
+    RestartRequired(RestartRequiredNotice),
+    AutoDisconnected(AutoDisconnectNotice),
+    CloneProgress(CloneProgress),
+    ThreadRead(ThreadReadNotice),
+    ThreadTitle(ThreadTitleNotice),
+    QueuedTurn(QueuedTurnNotice),
+    RateLimitUpdate(RateLimitUpdate),
+    SessionSpawnQueued(SessionSpawnNotice),
+    SessionSpawnStarted(SessionSpawnNotice),
+    SessionSpawnFinished(SessionSpawnNotice),
+    WorkspacePaused(WorkspacePausedNotice),
+    WorkspaceError(WorkspaceErrorNotice),
+    WorkspacesChanged(WorkspacesChangedNotice),
+    Operation(OperationEvent),
+    /// Notification from the primary daemon, passed on by a mirror.
+    Upstream(UpstreamEvent),
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct RestartRequiredNotice {
+    workspace_ids: Vec<String>,
+    reason: &'static str,
+}
+
+/// A session stopped after `idle_minutes` without activity.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct AutoDisconnectNotice {
+    workspace_id: String,
+    idle_minutes: u64,
+}
+
+/// What `purge_data` removed; paths are absolute.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PurgeSummary {
+    sessions_stopped: usize,
+    workspaces_removed: usize,
+    worktrees_removed: Vec<String>,
+    files_removed: Vec<String>,
+    /// Folders and files that could not be deleted, with the error.
+    failures: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ThreadReadNotice {
+    workspace_id: String,
+    /// `None` when the whole workspace was marked read.
+    thread_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct ThreadTitleNotice {
+    workspace_id: String,
+    thread_id: String,
+    /// `None` once the title was cleared.
+    title: Option<String>,
+    source: TitleSource,
+}
+
+/// Outcome of a `send_user_message` that waited for a free turn slot.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct QueuedTurnNotice {
+    workspace_id: String,
+    thread_id: String,
+    /// `None` once the turn started.
+    error: Option<String>,
+}
+
+/// A session spawn moving through the spawn queue.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct SessionSpawnNotice {
+    workspace_id: String,
+    /// Place in line, on `session-spawn-queued` only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    position: Option<usize>,
+    /// Why no session was started, on `session-spawn-finished` only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl SessionSpawnNotice {
+    fn new(workspace_id: &str) -> Self {
+        Self {
+            workspace_id: workspace_id.to_string(),
+            position: None,
+            error: None,
+        }
+    }
+}
+
+/// A workspace paused or resumed with `pause_workspace`/`resume_workspace`.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct WorkspacePausedNotice {
+    workspace_id: String,
+    paused: bool,
+    /// Threads whose running turn was interrupted by the pause.
+    interrupted_threads: Vec<String>,
+}
+
+/// An error recorded in a workspace's journal, or `error: None` once
+/// `clear_workspace_errors` dropped some.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct WorkspaceErrorNotice {
+    workspace_id: String,
+    error: Option<WorkspaceError>,
+    error_count: usize,
+}
+
+/// Workspaces added, removed or changed (settings, name, path or whether
+/// connected) by one mutation.
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+struct WorkspacesChangedNotice {
+    added: Vec<String>,
+    removed: Vec<String>,
+    updated: Vec<String>,
+    /// Every workspace id, in `list_workspaces` order after the change.
+    order: Vec<String>,
+}
+
+impl WorkspacesChangedNotice {
+    fn added(id: &str) -> Self {
+        Self {
+            added: vec![id.to_string()],
+            ..Self::default()
+        }
+    }
+
+    fn removed(ids: Vec<String>) -> Self {
+        Self {
+            removed: ids,
+            ..Self::default()
+        }
+    }
+
+    fn updated(id: &str) -> Self {
+        Self::updated_many(vec![id.to_string()])
+    }
+
+    fn updated_many(ids: Vec<String>) -> Self {
+        Self {
+            updated: ids,
+            ..Self::default()
+        }
+    }
+}
+
+/// `operation-progress`, `operation-completed` or `operation-failed`.
+#[derive(Debug, Clone)]
+struct OperationEvent {
+    method: &'static str,
+    status: OperationStatus,
+}
+
+#[derive(Debug, Clone)]
+struct UpstreamEvent {
+    method: String,
+    params: Value,
 }
 
 impl EventSink for DaemonEventSink {
-    fn emit_app_server_event(&self, event: AppServerEvent) {
-        let _ = self.tx.send(DaemonEvent::AppServer(event));
+    fn emit_app_server_event(&self, mut event: AppServerEvent) {
+        if let Err(err) = self
+            .artifacts
+            .capture(&event.workspace_id, &mut event.message)
+        {
+            eprintln!(
+                "codex-monitor-daemon: artifact capture failed in workspace {}: {err}",
+                event.workspace_id
+            );
+        }
+        self.thread_reads
+            .record_event(&event.workspace_id, &event.message);
+        self.thread_titles
+            .record_event(&event.workspace_id, &event.message);
+        self.turns.record_event(&event.workspace_id, &event.message);
+        self.activity
+            .record_event(&event.workspace_id, &event.message);
+        self.idle.record_event(&event.workspace_id, &event.message);
+        self.send(DaemonEvent::AppServer(event));
     }
 
     fn emit_terminal_output(&self, event: TerminalOutput) {
-        let _ = self.tx.send(DaemonEvent::TerminalOutput(event));
+        self.send(DaemonEvent::TerminalOutput(event));
     }
 }
 
 struct DaemonConfig {
     listen: SocketAddr,
+    /// Address of the read-only HTTP endpoint, if any.
+    listen_http: Option<SocketAddr>,
+    /// Serve `/metrics` on the HTTP endpoint without the token.
+    metrics_no_auth: bool,
+    /// Serve one connection over stdin/stdout instead of listening on `listen`.
+    stdio: bool,
     token: Option<String>,
     data_dir: PathBuf,
+    rate_limits: RateLimitConfig,
+    pid_file: Option<PathBuf>,
+    daemonize: bool,
+    access_log: Option<PathBuf>,
+    access_log_max_bytes: u64,
+    auth_policy: Option<PathBuf>,
+    event_buffer: usize,
+    framing: FramingLimits,
+    validate_models: bool,
+    max_turns_per_session: Option<u32>,
+    max_concurrent_spawns: usize,
+    max_image_bytes: u64,
+    /// Set with `--poll-rate-limits`; at least `MIN_RATE_LIMIT_POLL`.
+    rate_limit_poll: Option<Duration>,
+    rate_limit_warn_percent: f64,
+    allow_purge: bool,
+    read_only: bool,
+    mirror: Option<MirrorConfig>,
+    webhook: Option<WebhookConfig>,
+    /// Hosts `set_webhook` endpoints may post to.
+    webhook_hosts: BTreeSet<String>,
 }
 
+/// A session spawn shared by every connect that asked for the workspace
+/// while it ran.
+type SharedSpawn = Arc<OnceCell<Result<Arc<WorkspaceSession>, String>>>;
+
 struct DaemonState {
     data_dir: PathBuf,
     workspaces: Mutex<HashMap<String, WorkspaceEntry>>,
@@ -75,6 +600,89 @@ struct DaemonState {
     settings_path: PathBuf,
     app_settings: Mutex<AppSettings>,
     event_sink: DaemonEventSink,
+    rate_limits: RateLimitConfig,
+    started_at: Instant,
+    active_connections: AtomicUsize,
+    rate_limited_requests: AtomicU64,
+    metrics: Arc<Metrics>,
+    access_log: Option<AccessLog>,
+    authorization: AuthorizationPolicy,
+    event_buffer: usize,
+    event_lag: EventLagTracker,
+    framing: FramingLimits,
+    framing_counters: FramingCounters,
+    /// Destinations of `clone_workspace` calls that are still running.
+    clones_in_progress: Mutex<HashSet<PathBuf>>,
+    repo_locks: RepoLocks,
+    fetch_tracker: FetchTracker,
+    file_indexes: FileIndexes,
+    thread_list_cache: ThreadListCache,
+    thread_reads: Arc<ThreadReads>,
+    thread_titles: Arc<ThreadTitles>,
+    turns: Arc<TurnTracker>,
+    activity: Arc<ActivityLog>,
+    idle: Arc<IdleTracker>,
+    artifacts: Arc<Artifacts>,
+    models: ModelCatalog,
+    profiles: ProfileStore,
+    turn_inputs: TurnInputs,
+    imported_threads: ImportedThreads,
+    paused: PausedWorkspaces,
+    workspace_errors: WorkspaceErrors,
+    /// Summary of the last environment check, for `daemon_status`.
+    preflight: PreflightCache,
+    /// Held while items are added to, restored from or purged from the trash.
+    trash_lock: Mutex<()>,
+    /// Reject models and efforts missing from the cached `model/list`.
+    validate_models: bool,
+    /// Running turns allowed per app-server session, whatever the workspace
+    /// settings say; `None` means no limit.
+    max_turns_per_session: Option<u32>,
+    /// Every session spawn waits here for one of `--max-concurrent-spawns`
+    /// slots.
+    spawn_queue: SpawnQueue,
+    /// Spawns under way by workspace; a second connect of the workspace
+    /// waits for the same session instead of starting another process.
+    spawns_in_flight: Mutex<HashMap<String, SharedSpawn>>,
+    /// Largest local file `send_user_message` accepts as an image.
+    max_image_bytes: u64,
+    rate_limit_watch: RateLimitWatch,
+    /// How often connected sessions' account rate limits are polled; off
+    /// without `--poll-rate-limits`.
+    rate_limit_poll: Option<Duration>,
+    /// `usedPercent` from which a `rate-limit-update` is `nearLimit`.
+    rate_limit_warn_percent: f64,
+    /// `purge_data` is refused unless the daemon runs with `--allow-purge`.
+    allow_purge: bool,
+    /// Set with `--read-only`: requests that would change anything are
+    /// refused.
+    read_only: bool,
+    /// Long RPCs started with `async: true`.
+    operations: Arc<Operations>,
+    /// Set with `--mirror`: only reads are served, from the primary's state.
+    mirror: Option<Arc<Mirror>>,
+    webhooks: Arc<Webhooks>,
+}
+
+/// Per-connection facts available to authorization and auditing.
+#[derive(Clone)]
+struct ConnectionContext {
+    peer: SocketAddr,
+    /// Which token the connection authenticated with; `None` before auth.
+    token_label: Option<&'static str>,
+    /// Narrows the events forwarded to the connection.
+    thread_subscriptions: Arc<ThreadSubscriptions>,
+}
+
+#[derive(Clone)]
+struct UserMessage {
+    thread_id: String,
+    text: String,
+    model: Option<String>,
+    effort: Option<String>,
+    access_mode: Option<String>,
+    images: Option<Vec<String>>,
+    collaboration_mode: Option<Value>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -83,12 +691,48 @@ struct WorkspaceFileResponse {
     truncated: bool,
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WorkspaceFileEntry {
+    path: String,
+    /// True when the file was reached through a symlink (the file itself or a
+    /// linked parent directory); only detected when links are followed.
+    via_symlink: bool,
+    /// Resolved location: relative to the workspace root when it lives inside
+    /// it, absolute otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    link_target: Option<String>,
+    #[serde(default)]
+    link_target_in_root: bool,
+}
+
 impl DaemonState {
-    fn load(config: &DaemonConfig, event_sink: DaemonEventSink) -> Self {
+    fn load(
+        config: &DaemonConfig,
+        event_sink: DaemonEventSink,
+        access_log: Option<AccessLog>,
+        authorization: AuthorizationPolicy,
+    ) -> Self {
         let storage_path = config.data_dir.join("workspaces.json");
         let settings_path = config.data_dir.join("settings.json");
         let workspaces = read_workspaces(&storage_path).unwrap_or_default();
         let app_settings = read_settings(&settings_path).unwrap_or_default();
+        let thread_reads = Arc::clone(&event_sink.thread_reads);
+        let thread_titles = Arc::clone(&event_sink.thread_titles);
+        let turns = Arc::clone(&event_sink.turns);
+        let activity = Arc::clone(&event_sink.activity);
+        let idle = Arc::clone(&event_sink.idle);
+        let artifacts = Arc::clone(&event_sink.artifacts);
+        artifacts.configure(workspaces.values());
+        let operations = {
+            let event_sink = event_sink.clone();
+            Arc::new(Operations::new(move |method, status| {
+                event_sink.send(DaemonEvent::Operation(OperationEvent {
+                    method,
+                    status: status.clone(),
+                }));
+            }))
+        };
         Self {
             data_dir: config.data_dir.clone(),
             workspaces: Mutex::new(workspaces),
@@ -97,6 +741,179 @@ impl DaemonState {
             settings_path,
             app_settings: Mutex::new(app_settings),
             event_sink,
+            rate_limits: config.rate_limits,
+            started_at: Instant::now(),
+            active_connections: AtomicUsize::new(0),
+            rate_limited_requests: AtomicU64::new(0),
+            metrics: Arc::new(Metrics::default()),
+            access_log,
+            authorization,
+            event_buffer: config.event_buffer,
+            event_lag: EventLagTracker::default(),
+            framing: config.framing,
+            framing_counters: FramingCounters::default(),
+            clones_in_progress: Mutex::new(HashSet::new()),
+            repo_locks: RepoLocks::default(),
+            fetch_tracker: FetchTracker::default(),
+            file_indexes: FileIndexes::default(),
+            thread_list_cache: ThreadListCache::default(),
+            thread_reads,
+            thread_titles,
+            turns,
+            activity,
+            idle,
+            artifacts,
+            models: ModelCatalog::default(),
+            profiles: ProfileStore::new(config.data_dir.join("workspace_profiles.json")),
+            turn_inputs: TurnInputs::load(config.data_dir.join("turn_inputs.json")),
+            imported_threads: ImportedThreads::load(config.data_dir.join("imported_threads.json")),
+            paused: PausedWorkspaces::load(config.data_dir.join("paused_workspaces.json")),
+            workspace_errors: WorkspaceErrors::load(config.data_dir.join("workspace_errors.json")),
+            preflight: PreflightCache::default(),
+            trash_lock: Mutex::new(()),
+            validate_models: config.validate_models,
+            max_turns_per_session: config.max_turns_per_session,
+            spawn_queue: SpawnQueue::new(config.max_concurrent_spawns),
+            spawns_in_flight: Mutex::new(HashMap::new()),
+            max_image_bytes: config.max_image_bytes,
+            rate_limit_watch: RateLimitWatch::default(),
+            rate_limit_poll: config.rate_limit_poll,
+            rate_limit_warn_percent: config.rate_limit_warn_percent,
+            allow_purge: config.allow_purge,
+            read_only: config.read_only,
+            operations,
+            mirror: config
+                .mirror
+                .clone()
+                .map(|config| Arc::new(Mirror::new(config))),
+            webhooks: Arc::new(Webhooks::load(
+                config.data_dir.join("webhooks.json"),
+                config.webhook.clone(),
+                config.webhook_hosts.clone(),
+            )),
+        }
+    }
+
+    fn record_access(
+        &self,
+        context: &ConnectionContext,
+        method: &str,
+        status: AccessStatus,
+        started_at: Instant,
+    ) {
+        // HTTP paths carry workspace ids; their methods are recorded instead.
+        if !method.starts_with("http:") {
+            self.metrics
+                .record_rpc(method, status == AccessStatus::Ok, started_at.elapsed());
+        }
+        if let Some(access_log) = &self.access_log {
+            access_log.record(
+                context.peer,
+                context.token_label,
+                method,
+                status,
+                started_at.elapsed(),
+            );
+        }
+    }
+
+    /// Records git command durations inside a `GIT_TIMER` scope.
+    fn git_timer(&self) -> GitTimer {
+        let metrics = Arc::clone(&self.metrics);
+        Arc::new(move |duration| metrics.record_git(duration))
+    }
+
+    async fn render_metrics(&self) -> String {
+        let sessions = self.sessions.lock().await.len();
+        self.metrics
+            .render(self.active_connections.load(Ordering::Relaxed), sessions)
+    }
+
+    /// `{ ok: true }` with the caller's `nonce` echoed back, the server
+    /// clock and cheap load figures, for latency and skew checks.
+    async fn ping(&self, params: &Value) -> Value {
+        let mut pong = json!({
+            "ok": true,
+            "serverTime": now_ms(),
+            "connections": self.active_connections.load(Ordering::Relaxed),
+            "sessions": self.sessions.lock().await.len(),
+        });
+        if let Some(nonce) = params.get("nonce") {
+            pong["nonce"] = nonce.clone();
+        }
+        pong
+    }
+
+    async fn daemon_status(&self) -> Value {
+        let mut status = json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "uptimeSecs": self.started_at.elapsed().as_secs(),
+            "readOnly": self.read_only,
+            "connections": self.active_connections.load(Ordering::Relaxed),
+            "sessions": self.sessions.lock().await.len(),
+            "rateLimits": self.rate_limits,
+            "rateLimitedRequests": self.rate_limited_requests.load(Ordering::Relaxed),
+            "events": {
+                "buffer": self.event_buffer,
+                "latestSeq": self.event_sink.latest_seq(),
+                "lagged": self.event_lag.snapshot(),
+            },
+            "protocol": {
+                "limits": self.framing,
+                "violations": self.framing_counters.snapshot(),
+            },
+        });
+        if let Some(mirror) = &self.mirror {
+            status["mirror"] = json!(mirror.status());
+        }
+        status["preflight"] = json!(self.preflight.summary());
+        status
+    }
+
+    /// Checks what the daemon needs from its machine, then the folders of
+    /// the workspaces in `scope`. A `--read-only` daemon leaves out the data
+    /// dir check, which writes a scratch file. The summary is kept for
+    /// `daemon_status`.
+    async fn preflight_check(
+        &self,
+        scope: Option<WorkspaceScope>,
+    ) -> Result<PreflightReport, String> {
+        let entries: Vec<WorkspaceEntry> = {
+            let workspaces = self.workspaces.lock().await;
+            match &scope {
+                None => Vec::new(),
+                Some(WorkspaceScope::All) => {
+                    let mut entries: Vec<_> = workspaces.values().cloned().collect();
+                    entries.sort_by(|a, b| a.id.cmp(&b.id));
+                    entries
+                }
+                Some(WorkspaceScope::Ids(ids)) => ids
+                    .iter()
+                    .map(|id| {
+                        workspaces
+                            .get(id)
+                            .cloned()
+                            .ok_or_else(|| format!("workspace not found: {id}"))
+                    })
+                    .collect::<Result<_, _>>()?,
+            }
+        };
+        let codex_bin = self.app_settings.lock().await.codex_bin.clone();
+        let data_dir = (!self.read_only).then_some(self.data_dir.as_path());
+        let checks = run_checks(codex_bin, data_dir, &entries).await;
+        let report = PreflightReport::new(checks, now_ms());
+        self.preflight.store(&report);
+        Ok(report)
+    }
+
+    async fn kill_all_sessions(&self) {
+        let sessions: Vec<Arc<WorkspaceSession>> = {
+            let mut sessions = self.sessions.lock().await;
+            sessions.drain().map(|(_, session)| session).collect()
+        };
+        for session in sessions {
+            let mut child = session.child.lock().await;
+            let _ = child.kill().await;
         }
     }
 
@@ -105,36 +922,442 @@ impl DaemonState {
             let mut sessions = self.sessions.lock().await;
             sessions.remove(workspace_id)
         };
+        self.turns.forget(workspace_id);
+        self.idle.forget(workspace_id);
 
         let Some(session) = session else {
             return;
         };
 
-        let mut child = session.child.lock().await;
-        let _ = child.kill().await;
+        {
+            let mut child = session.child.lock().await;
+            let _ = child.kill().await;
+        }
+        self.workspaces_changed(WorkspacesChangedNotice::updated(workspace_id))
+            .await;
+    }
+
+    async fn insert_session(&self, workspace_id: String, session: Arc<WorkspaceSession>) {
+        let change = WorkspacesChangedNotice::updated(&workspace_id);
+        self.idle.connected(&workspace_id);
+        self.sessions.lock().await.insert(workspace_id, session);
+        self.workspaces_changed(change).await;
+    }
+
+    /// Saves the workspace list and tells clients what `change` did to it.
+    async fn save_workspaces(
+        &self,
+        list: &[WorkspaceEntry],
+        change: WorkspacesChangedNotice,
+    ) -> Result<(), String> {
+        write_workspaces(&self.storage_path, list)?;
+        self.artifacts.configure(list);
+        self.workspaces_changed(change).await;
+        Ok(())
+    }
+
+    /// Sends `workspaces-changed`. Every change to the workspace list or to
+    /// which sessions run ends up here, through `save_workspaces`,
+    /// `insert_session` or `kill_session`.
+    async fn workspaces_changed(&self, mut change: WorkspacesChangedNotice) {
+        change.order = self
+            .list_workspaces()
+            .await
+            .into_iter()
+            .map(|info| info.id)
+            .collect();
+        self.event_sink.send(DaemonEvent::WorkspacesChanged(change));
     }
 
     async fn list_workspaces(&self) -> Vec<WorkspaceInfo> {
+        let current_flags = self.app_settings.lock().await.experimental_flags();
         let workspaces = self.workspaces.lock().await;
         let sessions = self.sessions.lock().await;
         let mut result = Vec::new();
         for entry in workspaces.values() {
-            result.push(WorkspaceInfo {
-                id: entry.id.clone(),
-                name: entry.name.clone(),
-                path: entry.path.clone(),
-                connected: sessions.contains_key(&entry.id),
-                codex_bin: entry.codex_bin.clone(),
-                kind: entry.kind.clone(),
-                parent_id: entry.parent_id.clone(),
-                worktree: entry.worktree.clone(),
-                settings: entry.settings.clone(),
-            });
+            let session = sessions.get(&entry.id);
+            let mut info = WorkspaceInfo::from_entry(entry.clone(), session.is_some());
+            info.flags_stale =
+                session.is_some_and(|session| session.experimental_flags != current_flags);
+            info.inherit_color(entry.parent_id.as_deref().and_then(|id| workspaces.get(id)));
+            info.fetch = self.fetch_tracker.status(repo_id(entry));
+            info.active_turns = Some(self.turns.active_count(&entry.id));
+            info.queued_turns = Some(self.turns.queued_count(&entry.id));
+            info.paused = self.paused.is_paused(&entry.id);
+            info.error_count = Some(self.workspace_errors.count(&entry.id) as u32);
+            info.capabilities = session.map(|session| session.capabilities().summary());
+            result.push(info);
         }
         sort_workspaces(&mut result);
         result
     }
 
+    async fn list_workspaces_by(&self, sort: WorkspaceSort) -> Vec<WorkspaceInfo> {
+        let mut workspaces = self.list_workspaces().await;
+        sort_workspaces_by(&mut workspaces, sort, |id| {
+            self.idle.activity(id).last_active
+        });
+        workspaces
+    }
+
+    async fn workspace_status(&self, id: &str) -> Result<WorkspaceInfo, String> {
+        let current_flags = self.app_settings.lock().await.experimental_flags();
+        let (entry, parent) = {
+            let workspaces = self.workspaces.lock().await;
+            let entry = workspaces.get(id).cloned().ok_or("workspace not found")?;
+            let parent = entry
+                .parent_id
+                .as_deref()
+                .and_then(|parent_id| workspaces.get(parent_id))
+                .cloned();
+            (entry, parent)
+        };
+        let session = self.sessions.lock().await.get(id).cloned();
+        let fetch = self.fetch_tracker.status(repo_id(&entry));
+        let mut info = WorkspaceInfo::from_entry(entry, session.is_some());
+        info.inherit_color(parent.as_ref());
+        info.flags_stale = session
+            .as_ref()
+            .is_some_and(|session| session.experimental_flags != current_flags);
+        info.fetch = fetch;
+        info.active_turns = Some(self.turns.active_count(id));
+        info.queued_turns = Some(self.turns.queued_count(id));
+        info.paused = self.paused.is_paused(id);
+        info.error_count = Some(self.workspace_errors.count(id) as u32);
+        info.capabilities = session.map(|session| session.capabilities().report());
+        Ok(info)
+    }
+
+    /// Refuses new turns until `resume_workspace`. With `interrupt`, turns
+    /// already running in the workspace are interrupted as well.
+    async fn pause_workspace(
+        &self,
+        id: String,
+        interrupt: bool,
+    ) -> Result<WorkspacePausedNotice, String> {
+        if !self.workspaces.lock().await.contains_key(&id) {
+            return Err("workspace not found".to_string());
+        }
+        let changed = self.paused.set(&id, true)?;
+        let mut interrupted_threads = Vec::new();
+        let session = self.sessions.lock().await.get(&id).cloned();
+        if let (true, Some(session)) = (interrupt, session) {
+            for (thread_id, turn_id) in self.turns.running_turns(&id) {
+                let params = json!({ "threadId": thread_id, "turnId": turn_id });
+                let error = match session.send_request("turn/interrupt", params).await {
+                    Ok(response) => match response.get("error") {
+                        None => {
+                            interrupted_threads.push(thread_id);
+                            continue;
+                        }
+                        Some(error) => error.to_string(),
+                    },
+                    Err(error) => error,
+                };
+                let error = NewError {
+                    thread_id: Some(&thread_id),
+                    turn_id: Some(&turn_id),
+                    ..NewError::new(
+                        ErrorSource::Interrupt,
+                        format!("failed to interrupt thread {thread_id}: {error}"),
+                    )
+                };
+                self.record_workspace_error(&id, error).await;
+            }
+        }
+        let notice = WorkspacePausedNotice {
+            workspace_id: id,
+            paused: true,
+            interrupted_threads,
+        };
+        if changed || !notice.interrupted_threads.is_empty() {
+            self.workspace_paused(notice.clone()).await;
+        }
+        Ok(notice)
+    }
+
+    async fn resume_workspace(&self, id: String) -> Result<WorkspacePausedNotice, String> {
+        if !self.workspaces.lock().await.contains_key(&id) {
+            return Err("workspace not found".to_string());
+        }
+        let changed = self.paused.set(&id, false)?;
+        let notice = WorkspacePausedNotice {
+            workspace_id: id,
+            paused: false,
+            interrupted_threads: Vec::new(),
+        };
+        if changed {
+            self.workspace_paused(notice.clone()).await;
+        }
+        Ok(notice)
+    }
+
+    async fn workspace_paused(&self, notice: WorkspacePausedNotice) {
+        let change = WorkspacesChangedNotice::updated(&notice.workspace_id);
+        self.event_sink.send(DaemonEvent::WorkspacePaused(notice));
+        self.workspaces_changed(change).await;
+    }
+
+    /// Refuses to start turns in a workspace paused with `pause_workspace`.
+    fn ensure_not_paused(&self, workspace_id: &str) -> Result<(), RpcError> {
+        if !self.paused.is_paused(workspace_id) {
+            return Ok(());
+        }
+        Err(RpcError {
+            message: "workspace paused".to_string(),
+            code: Some("workspace_paused"),
+            data: Some(json!({ "workspaceId": workspace_id })),
+        })
+    }
+
+    /// Keeps an error of background work in the workspace's journal, where
+    /// `workspace_errors` finds it, and broadcasts it. Errors of workspaces
+    /// removed meanwhile are dropped, so they don't outlive the workspace.
+    async fn record_workspace_error(&self, workspace_id: &str, error: NewError<'_>) {
+        let workspaces = self.workspaces.lock().await;
+        if !workspaces.contains_key(workspace_id) {
+            return;
+        }
+        let (error, error_count) = self.workspace_errors.record(workspace_id, error, now_ms());
+        drop(workspaces);
+        let notice = WorkspaceErrorNotice {
+            workspace_id: workspace_id.to_string(),
+            error: Some(error),
+            error_count,
+        };
+        self.event_sink.send(DaemonEvent::WorkspaceError(notice));
+    }
+
+    async fn workspace_errors(
+        &self,
+        workspace_id: &str,
+        limit: Option<u32>,
+    ) -> Result<Value, String> {
+        if !self.workspaces.lock().await.contains_key(workspace_id) {
+            return Err("workspace not found".to_string());
+        }
+        let limit = limit.unwrap_or(50).clamp(1, 100) as usize;
+        Ok(json!({
+            "errors": self.workspace_errors.list(workspace_id, limit),
+            "errorCount": self.workspace_errors.count(workspace_id),
+        }))
+    }
+
+    /// Drops the workspace's errors, or those up to the `id` a client has
+    /// seen, and tells clients the new count.
+    async fn clear_workspace_errors(
+        &self,
+        workspace_id: &str,
+        up_to: Option<u64>,
+    ) -> Result<Value, String> {
+        if !self.workspaces.lock().await.contains_key(workspace_id) {
+            return Err("workspace not found".to_string());
+        }
+        let cleared = self.workspace_errors.clear(workspace_id, up_to)?;
+        let error_count = self.workspace_errors.count(workspace_id);
+        if cleared > 0 {
+            let notice = WorkspaceErrorNotice {
+                workspace_id: workspace_id.to_string(),
+                error: None,
+                error_count,
+            };
+            self.event_sink.send(DaemonEvent::WorkspaceError(notice));
+        }
+        Ok(json!({ "cleared": cleared, "errorCount": error_count }))
+    }
+
+    /// Connected workspaces whose session predates the current experimental flags.
+    async fn stale_flag_sessions(&self) -> Vec<String> {
+        let current_flags = self.app_settings.lock().await.experimental_flags();
+        let sessions = self.sessions.lock().await;
+        let mut ids: Vec<String> = sessions
+            .iter()
+            .filter(|(_, session)| session.experimental_flags != current_flags)
+            .map(|(id, _)| id.clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    async fn restart_workspace(
+        &self,
+        id: String,
+        client_version: String,
+    ) -> Result<WorkspaceInfo, String> {
+        if !self.workspaces.lock().await.contains_key(&id) {
+            return Err("workspace not found".to_string());
+        }
+        self.kill_session(&id).await;
+        self.connect_workspace(id.clone(), client_version).await?;
+        self.workspace_status(&id).await
+    }
+
+    /// Factory reset: stops every session, removes every worktree the daemon
+    /// created and deletes its state files, so the daemon carries on as if
+    /// started with an empty data dir. Repos cloned into the projects root
+    /// are left alone.
+    async fn purge_data(&self) -> PurgeSummary {
+        let _reconnects = self.idle.lock_reconnects().await;
+        let mut summary = PurgeSummary {
+            sessions_stopped: self.sessions.lock().await.len(),
+            ..PurgeSummary::default()
+        };
+        self.kill_all_sessions().await;
+
+        let entries: Vec<WorkspaceEntry> = {
+            let mut workspaces = self.workspaces.lock().await;
+            workspaces.drain().map(|(_, entry)| entry).collect()
+        };
+        let repo_paths: HashMap<&str, &str> = entries
+            .iter()
+            .map(|entry| (entry.id.as_str(), entry.path.as_str()))
+            .collect();
+        let mut parents = HashSet::new();
+        for entry in entries.iter().filter(|entry| entry.kind.is_worktree()) {
+            let path = PathBuf::from(&entry.path);
+            if !path.exists() {
+                continue;
+            }
+            let parent = entry
+                .parent_id
+                .as_deref()
+                .and_then(|id| repo_paths.get(id).copied());
+            if let Some(parent) = parent {
+                let args = ["worktree", "remove", "--force", entry.path.as_str()];
+                let _ = run_git_command(&PathBuf::from(parent), &args).await;
+                parents.insert(parent);
+            }
+            if path.exists() {
+                if let Err(err) = std::fs::remove_dir_all(&path) {
+                    summary.failures.push(format!("{}: {err}", entry.path));
+                    continue;
+                }
+            }
+            summary.worktrees_removed.push(entry.path.clone());
+        }
+        for parent in parents {
+            let _ = run_git_command(
+                &PathBuf::from(parent),
+                &["worktree", "prune", "--expire", "now"],
+            )
+            .await;
+        }
+        let roots = [
+            self.data_dir.join("worktrees"),
+            self.data_dir.join("artifacts"),
+            trash_root(&self.data_dir),
+        ];
+        let removal_failures = blocking(move || {
+            roots
+                .iter()
+                .filter(|root| root.exists())
+                .filter_map(|root| {
+                    std::fs::remove_dir_all(root)
+                        .err()
+                        .map(|err| format!("{}: {err}", root.display()))
+                })
+                .collect::<Vec<_>>()
+        })
+        .await;
+        match removal_failures {
+            Ok(failures) => summary.failures.extend(failures),
+            Err(err) => summary.failures.push(err),
+        }
+
+        for entry in &entries {
+            self.turns.forget(&entry.id);
+            self.fetch_tracker.forget(&entry.id);
+            self.file_indexes.forget(&entry.id);
+            self.thread_list_cache.forget(&entry.id);
+            self.thread_reads.forget(&entry.id);
+            self.thread_titles.forget(&entry.id);
+            self.turn_inputs.forget(&entry.id);
+            self.imported_threads.forget(&entry.id);
+            self.paused.forget(&entry.id);
+            self.idle.forget(&entry.id);
+            self.workspace_errors.forget(&entry.id);
+            self.artifacts.forget(&entry.id);
+            self.rate_limit_watch.forget(&entry.id);
+            self.activity.forget(&entry.id);
+            self.models.forget(&entry.id);
+        }
+        summary.workspaces_removed = entries.len();
+        *self.app_settings.lock().await = AppSettings::default();
+        self.webhooks.clear();
+        let removed = entries.iter().map(|entry| entry.id.clone()).collect();
+        self.workspaces_changed(WorkspacesChangedNotice::removed(removed))
+            .await;
+
+        let files = [self.storage_path.clone(), self.settings_path.clone()]
+            .into_iter()
+            .chain(
+                PURGED_STATE_FILES
+                    .iter()
+                    .map(|name| self.data_dir.join(name)),
+            );
+        for file in files.filter(|file| file.exists()) {
+            match std::fs::remove_file(&file) {
+                Ok(()) => summary.files_removed.push(file.display().to_string()),
+                Err(err) => summary.failures.push(format!("{}: {err}", file.display())),
+            }
+        }
+        summary
+    }
+
+    /// Restarts the sessions that run the default codex binary so a new
+    /// `codexBin` from app settings takes effect; workspaces with their own
+    /// `codex_bin` keep running. Workspaces `authorize` rejects are reported
+    /// as failed.
+    async fn reload_codex_bin(
+        self: &Arc<Self>,
+        client_version: String,
+        authorize: impl Fn(&str) -> Result<(), String>,
+    ) -> CodexBinReload {
+        let mut connected: Vec<String> = self.sessions.lock().await.keys().cloned().collect();
+        connected.sort();
+        let mut report = CodexBinReload::default();
+        let mut targets = Vec::new();
+        {
+            let workspaces = self.workspaces.lock().await;
+            for id in connected {
+                let overridden = workspaces
+                    .get(&id)
+                    .and_then(|entry| entry.codex_bin.as_deref())
+                    .is_some_and(|bin| !bin.trim().is_empty());
+                if overridden {
+                    report.skipped.push(id);
+                } else if let Err(error) = authorize(&id) {
+                    report.failed.push(RestartFailure {
+                        workspace_id: id,
+                        error,
+                    });
+                } else {
+                    targets.push(id);
+                }
+            }
+        }
+
+        let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_RESTARTS));
+        let mut tasks = Vec::new();
+        for id in targets {
+            let state = Arc::clone(self);
+            let permits = Arc::clone(&permits);
+            let client_version = client_version.clone();
+            let task = tokio::spawn({
+                let id = id.clone();
+                async move {
+                    let _permit = permits.acquire().await;
+                    state.restart_workspace(id, client_version).await
+                }
+            });
+            tasks.push((id, task));
+        }
+        for (id, task) in tasks {
+            report.record(id, task.await);
+        }
+        report
+    }
+
     async fn is_workspace_path_dir(&self, path: String) -> bool {
         PathBuf::from(&path).is_dir()
     }
@@ -148,6 +1371,7 @@ impl DaemonState {
         if !PathBuf::from(&path).is_dir() {
             return Err("Workspace path must be a folder.".to_string());
         }
+        git_state::ensure_not_bare_repo(Path::new(&path))?;
 
         let name = PathBuf::from(&path)
             .file_name()
@@ -164,50 +1388,127 @@ impl DaemonState {
             parent_id: None,
             worktree: None,
             settings: WorkspaceSettings::default(),
-        };
-
-        let default_bin = {
-            let settings = self.app_settings.lock().await;
-            settings.codex_bin.clone()
+            status: None,
         };
 
         let codex_home = codex_home::resolve_workspace_codex_home(&entry, None);
-        let session = spawn_workspace_session(
-            entry.clone(),
-            default_bin,
-            client_version,
-            self.event_sink.clone(),
-            codex_home,
-        )
-        .await?;
+        let session = self
+            .spawn_session(entry.clone(), client_version, codex_home)
+            .await?;
 
         let list = {
             let mut workspaces = self.workspaces.lock().await;
             workspaces.insert(entry.id.clone(), entry.clone());
             workspaces.values().cloned().collect::<Vec<_>>()
         };
-        write_workspaces(&self.storage_path, &list)?;
-
+        self.idle.connected(&entry.id);
         self.sessions.lock().await.insert(entry.id.clone(), session);
+        self.save_workspaces(&list, WorkspacesChangedNotice::added(&entry.id))
+            .await?;
 
-        Ok(WorkspaceInfo {
-            id: entry.id,
-            name: entry.name,
-            path: entry.path,
-            connected: true,
-            codex_bin: entry.codex_bin,
-            kind: entry.kind,
-            parent_id: entry.parent_id,
-            worktree: entry.worktree,
-            settings: entry.settings,
-        })
+        Ok(WorkspaceInfo::from_entry(entry, true))
     }
 
-    async fn add_worktree(
+    async fn projects_root(&self) -> PathBuf {
+        let settings = self.app_settings.lock().await;
+        settings
+            .projects_root
+            .as_deref()
+            .map(str::trim)
+            .filter(|root| !root.is_empty())
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.data_dir.join(DEFAULT_PROJECTS_DIR))
+    }
+
+    async fn clone_workspace(
+        &self,
+        url: String,
+        destination: Option<String>,
+        branch: Option<String>,
+        depth: Option<u32>,
+        client_version: String,
+        progress: &OperationProgress,
+    ) -> Result<WorkspaceInfo, String> {
+        let url = url.trim().to_string();
+        if url.is_empty() {
+            return Err("Repository URL is required.".to_string());
+        }
+        let projects_root = self.projects_root().await;
+        let destination = match destination
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+        {
+            Some(value) => clone_destination(&projects_root, value)?,
+            None => {
+                let name = repo_name_from_url(&url)
+                    .ok_or("Could not derive a folder name from the URL; pass a destination.")?;
+                clone_destination(&projects_root, &name)?
+            }
+        };
+        if destination.is_file()
+            || (destination.is_dir()
+                && std::fs::read_dir(&destination)
+                    .map(|mut entries| entries.next().is_some())
+                    .unwrap_or(true))
+        {
+            return Err(format!(
+                "Destination already exists and is not empty: {}",
+                destination.display()
+            ));
+        }
+
+        if !self
+            .clones_in_progress
+            .lock()
+            .await
+            .insert(destination.clone())
+        {
+            return Err(format!(
+                "A clone into {} is already running.",
+                destination.display()
+            ));
+        }
+        let request = CloneRequest {
+            url,
+            destination: destination.clone(),
+            branch: branch
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty()),
+            depth: depth.filter(|value| *value > 0),
+        };
+        let event_sink = self.event_sink.clone();
+        let result = run_git_clone(
+            &request,
+            |clone_progress| {
+                progress.report(
+                    &clone_progress.phase,
+                    clone_progress.percent,
+                    Some(&clone_progress.line),
+                );
+                event_sink.send(DaemonEvent::CloneProgress(clone_progress));
+            },
+            progress.cancel_signal(),
+        )
+        .await;
+        self.clones_in_progress.lock().await.remove(&destination);
+        result?;
+
+        self.add_workspace(
+            destination.to_string_lossy().to_string(),
+            None,
+            client_version,
+        )
+        .await
+    }
+
+    async fn add_worktree(
         &self,
         parent_id: String,
         branch: String,
+        init_submodules: Option<bool>,
         client_version: String,
+        progress: &OperationProgress,
     ) -> Result<WorkspaceInfo, String> {
         let branch = branch.trim().to_string();
         if branch.trim().is_empty() {
@@ -225,39 +1526,43 @@ impl DaemonState {
         if parent_entry.kind.is_worktree() {
             return Err("Cannot create a worktree from another worktree.".to_string());
         }
+        git_state::ensure_git_repo(&workspace_git_path(&parent_entry))?;
+        let repo_lock = self.repo_locks.lock_for(&parent_entry.id);
+        let _repo_guard = repo_lock.lock().await;
 
         let worktree_root = self.data_dir.join("worktrees").join(&parent_entry.id);
-        std::fs::create_dir_all(&worktree_root)
-            .map_err(|e| format!("Failed to create worktree directory: {e}"))?;
-
-        let safe_name = sanitize_worktree_name(&branch);
-        let worktree_path = unique_worktree_path(&worktree_root, &safe_name)?;
+        let naming = self.app_settings.lock().await.worktree_naming;
+        let worktree_path = worktree_path_for_branch(&worktree_root, &branch, naming)?;
         let worktree_path_string = worktree_path.to_string_lossy().to_string();
+        let created_dirs = create_dir_all_tracked(&worktree_root)?;
 
         let repo_path = PathBuf::from(&parent_entry.path);
-        let branch_exists = git_branch_exists(&repo_path, &branch).await?;
-        if branch_exists {
-            run_git_command(
-                &repo_path,
-                &["worktree", "add", &worktree_path_string, &branch],
-            )
-            .await?;
-        } else if let Some(remote_ref) = git_find_remote_tracking_branch(&repo_path, &branch).await? {
-            run_git_command(
-                &repo_path,
-                &["worktree", "add", "-b", &branch, &worktree_path_string, &remote_ref],
-            )
-            .await?;
-        } else {
-            run_git_command(
-                &repo_path,
-                &["worktree", "add", "-b", &branch, &worktree_path_string],
-            )
-            .await?;
+        progress.report("Creating worktree", None, None);
+        if let Err(error) = add_checkout(&repo_path, &worktree_path, &branch).await {
+            let _ = std::fs::remove_dir(&worktree_path);
+            created_dirs.remove_empty();
+            return Err(error);
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let should_init = init_submodules.unwrap_or(parent_entry.settings.init_submodules);
+        if should_init && !submodule_paths(&worktree_path).is_empty() {
+            progress.report("Initializing submodules", None, None);
+            // A failed init still leaves a usable worktree; the error is shown
+            // in the streamed output instead of failing the whole call.
+            if let Err(error) =
+                submodules::init_submodules(&worktree_path, &id, &self.event_sink).await
+            {
+                self.event_sink.emit_terminal_output(TerminalOutput {
+                    workspace_id: id.clone(),
+                    terminal_id: SUBMODULE_TERMINAL_ID.to_string(),
+                    data: format!("\r\nSubmodule init failed: {error}\r\n"),
+                });
+            }
         }
 
         let entry = WorkspaceEntry {
-            id: Uuid::new_v4().to_string(),
+            id,
             name: branch.to_string(),
             path: worktree_path_string,
             codex_bin: parent_entry.codex_bin.clone(),
@@ -265,48 +1570,48 @@ impl DaemonState {
             parent_id: Some(parent_entry.id.clone()),
             worktree: Some(WorktreeInfo {
                 branch: branch.to_string(),
+                dir_name: worktree_dir_name(&worktree_path),
             }),
             settings: WorkspaceSettings::default(),
+            status: None,
         };
 
-        let default_bin = {
-            let settings = self.app_settings.lock().await;
-            settings.codex_bin.clone()
-        };
-
+        progress.report("Starting Codex", None, None);
         let codex_home = codex_home::resolve_workspace_codex_home(&entry, Some(&parent_entry.path));
-        let session = spawn_workspace_session(
-            entry.clone(),
-            default_bin,
-            client_version,
-            self.event_sink.clone(),
-            codex_home,
-        )
-        .await?;
+        let session = self
+            .spawn_session(entry.clone(), client_version, codex_home)
+            .await?;
 
         let list = {
             let mut workspaces = self.workspaces.lock().await;
             workspaces.insert(entry.id.clone(), entry.clone());
             workspaces.values().cloned().collect::<Vec<_>>()
         };
-        write_workspaces(&self.storage_path, &list)?;
-
+        self.idle.connected(&entry.id);
         self.sessions.lock().await.insert(entry.id.clone(), session);
+        self.save_workspaces(&list, WorkspacesChangedNotice::added(&entry.id))
+            .await?;
 
-        Ok(WorkspaceInfo {
-            id: entry.id,
-            name: entry.name,
-            path: entry.path,
-            connected: true,
-            codex_bin: entry.codex_bin,
-            kind: entry.kind,
-            parent_id: entry.parent_id,
-            worktree: entry.worktree,
-            settings: entry.settings,
-        })
+        Ok(WorkspaceInfo::from_entry(entry, true))
     }
 
-    async fn remove_workspace(&self, id: String) -> Result<(), String> {
+    /// Whether a removal goes to the trash: `softDelete` when the request
+    /// gave it, else the `softDelete` setting.
+    async fn soft_delete(&self, requested: Option<bool>) -> bool {
+        match requested {
+            Some(soft_delete) => soft_delete,
+            None => self.app_settings.lock().await.soft_delete,
+        }
+    }
+
+    /// Removes a main workspace and its worktrees. With `soft_delete` the
+    /// worktrees are moved into one trash item instead of being deleted, and
+    /// its id is returned.
+    async fn remove_workspace(
+        &self,
+        id: String,
+        soft_delete: Option<bool>,
+    ) -> Result<Option<String>, String> {
         let (entry, child_worktrees) = {
             let workspaces = self.workspaces.lock().await;
             let entry = workspaces.get(&id).cloned().ok_or("workspace not found")?;
@@ -324,56 +1629,106 @@ impl DaemonState {
         let repo_path = PathBuf::from(&entry.path);
         let mut removed_child_ids = Vec::new();
         let mut failures = Vec::new();
+        let trash_guard = if self.soft_delete(soft_delete).await {
+            Some(self.trash_lock.lock().await)
+        } else {
+            None
+        };
+        let trash_dir = trash_root(&self.data_dir);
+        let mut tombstone = if trash_guard.is_some() {
+            let deleted_at = now_ms();
+            let (item_id, item) = {
+                let (trash_dir, id) = (trash_dir.clone(), id.clone());
+                blocking(move || create_item(&trash_dir, deleted_at, &id)).await??
+            };
+            Some((
+                item,
+                Tombstone {
+                    id: item_id,
+                    deleted_at,
+                    workspaces: Vec::new(),
+                },
+            ))
+        } else {
+            None
+        };
 
         for child in &child_worktrees {
             let child_path = PathBuf::from(&child.path);
-            if child_path.exists() {
-                if let Err(err) = run_git_command(
-                    &repo_path,
-                    &["worktree", "remove", "--force", &child.path],
-                )
-                .await
-                {
-                    if is_missing_worktree_error(&err) {
-                        if let Err(fs_err) = std::fs::remove_dir_all(&child_path) {
-                            failures.push((
-                                child.id.clone(),
-                                format!("Failed to remove worktree folder: {fs_err}"),
-                            ));
+            if let Some((item, tombstone)) = tombstone.as_mut() {
+                if child_path.exists() {
+                    self.kill_session(&child.id).await;
+                    let (item, trashed_child) = (item.clone(), child.clone());
+                    match blocking(move || trash_worktree(&item, &trashed_child)).await? {
+                        Ok(trashed) => tombstone.workspaces.push(trashed),
+                        Err(err) => {
+                            failures.push((child.id.clone(), err));
                             continue;
                         }
-                    } else {
-                        failures.push((child.id.clone(), err));
-                        continue;
                     }
                 }
+            } else if let Err(err) = remove_checkout(&repo_path, &child_path).await {
+                failures.push((child.id.clone(), err));
+                continue;
             }
 
             self.kill_session(&child.id).await;
             removed_child_ids.push(child.id.clone());
         }
 
-        let _ = run_git_command(&repo_path, &["worktree", "prune", "--expire", "now"]).await;
+        prune_worktrees(&repo_path).await;
 
         let mut ids_to_remove = removed_child_ids;
         if failures.is_empty() {
             self.kill_session(&id).await;
+            self.fetch_tracker.forget(&id);
+            self.file_indexes.forget(&id);
+            self.thread_list_cache.forget(&id);
+            self.thread_reads.forget(&id);
+            self.thread_titles.forget(&id);
+            self.turn_inputs.forget(&id);
+            self.imported_threads.forget(&id);
+            self.paused.forget(&id);
+            self.workspace_errors.forget(&id);
+            self.artifacts.forget(&id);
+            self.rate_limit_watch.forget(&id);
+            self.activity.forget(&id);
+            self.models.forget(&id);
             ids_to_remove.push(id.clone());
+            if let Some((_, tombstone)) = tombstone.as_mut() {
+                tombstone.workspaces.push(TrashedWorkspace {
+                    original_path: entry.path.clone(),
+                    entry,
+                    branch: None,
+                    folder: None,
+                    git_dir: None,
+                    git_dir_folder: None,
+                });
+            }
         }
+        let trash_id = match tombstone {
+            Some((_, tombstone)) => {
+                let trash_id = (!tombstone.workspaces.is_empty()).then(|| tombstone.id.clone());
+                blocking(move || finish_item(&trash_dir, &tombstone)).await??;
+                trash_id
+            }
+            None => None,
+        };
 
         if !ids_to_remove.is_empty() {
             let list = {
                 let mut workspaces = self.workspaces.lock().await;
-                for workspace_id in ids_to_remove {
-                    workspaces.remove(&workspace_id);
+                for workspace_id in &ids_to_remove {
+                    workspaces.remove(workspace_id);
                 }
                 workspaces.values().cloned().collect::<Vec<_>>()
             };
-            write_workspaces(&self.storage_path, &list)?;
+            self.save_workspaces(&list, WorkspacesChangedNotice::removed(ids_to_remove))
+                .await?;
         }
 
         if failures.is_empty() {
-            return Ok(());
+            return Ok(trash_id);
         }
 
         let mut message =
@@ -384,7 +1739,14 @@ impl DaemonState {
         Err(message)
     }
 
-    async fn remove_worktree(&self, id: String) -> Result<(), String> {
+    /// Removes a worktree, or with `soft_delete` moves it into a new trash
+    /// item whose id is returned.
+    async fn remove_worktree(
+        &self,
+        id: String,
+        force: bool,
+        soft_delete: Option<bool>,
+    ) -> Result<Option<String>, String> {
         let (entry, parent) = {
             let workspaces = self.workspaces.lock().await;
             let entry = workspaces.get(&id).cloned().ok_or("workspace not found")?;
@@ -399,27 +1761,43 @@ impl DaemonState {
             (entry, parent)
         };
 
+        let repo_lock = self.repo_locks.lock_for(&parent.id);
+        let _repo_guard = repo_lock.lock().await;
         let parent_path = PathBuf::from(&parent.path);
         let entry_path = PathBuf::from(&entry.path);
-        if entry_path.exists() {
-            if let Err(err) = run_git_command(
-                &parent_path,
-                &["worktree", "remove", "--force", &entry.path],
-            )
-            .await
-            {
-                if is_missing_worktree_error(&err) {
-                    if entry_path.exists() {
-                        std::fs::remove_dir_all(&entry_path).map_err(|fs_err| {
-                            format!("Failed to remove worktree folder: {fs_err}")
-                        })?;
+        if !force {
+            git_state::ensure_no_git_operation(&entry_path, "remove the worktree")?;
+        }
+        let mut trash_id = None;
+        if entry_path.exists() && self.soft_delete(soft_delete).await {
+            let _trash_guard = self.trash_lock.lock().await;
+            self.kill_session(&entry.id).await;
+            let trash_dir = trash_root(&self.data_dir);
+            let deleted_at = now_ms();
+            let trashed_entry = entry.clone();
+            let item_id = blocking(move || {
+                let (item_id, item) = create_item(&trash_dir, deleted_at, &trashed_entry.id)?;
+                let mut tombstone = Tombstone {
+                    id: item_id,
+                    deleted_at,
+                    workspaces: Vec::new(),
+                };
+                match trash_worktree(&item, &trashed_entry) {
+                    Ok(trashed) => tombstone.workspaces.push(trashed),
+                    Err(err) => {
+                        let _ = finish_item(&trash_dir, &tombstone);
+                        return Err(err);
                     }
-                } else {
-                    return Err(err);
                 }
-            }
+                finish_item(&trash_dir, &tombstone)?;
+                Ok(tombstone.id)
+            })
+            .await??;
+            trash_id = Some(item_id);
+        } else {
+            remove_checkout(&parent_path, &entry_path).await?;
         }
-        let _ = run_git_command(&parent_path, &["worktree", "prune", "--expire", "now"]).await;
+        prune_worktrees(&parent_path).await;
 
         self.kill_session(&entry.id).await;
 
@@ -428,9 +1806,211 @@ impl DaemonState {
             workspaces.remove(&entry.id);
             workspaces.values().cloned().collect::<Vec<_>>()
         };
-        write_workspaces(&self.storage_path, &list)?;
+        self.save_workspaces(&list, WorkspacesChangedNotice::removed(vec![entry.id]))
+            .await?;
 
-        Ok(())
+        Ok(trash_id)
+    }
+
+    async fn trash_retention_days(&self) -> u32 {
+        self.app_settings
+            .lock()
+            .await
+            .trash_retention_days
+            .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS)
+    }
+
+    async fn list_trash(&self) -> Vec<TrashItem> {
+        let retention_days = self.trash_retention_days().await;
+        let trash_dir = trash_root(&self.data_dir);
+        blocking(move || list_tombstones(&trash_dir))
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tombstone| TrashItem {
+                expires_at: expires_at(&tombstone, retention_days),
+                tombstone,
+            })
+            .collect()
+    }
+
+    /// The workspaces trash item `id` holds, or every item's without `id`,
+    /// for policy checks.
+    async fn trashed_workspace_ids(&self, id: Option<String>) -> Vec<String> {
+        let trash_dir = trash_root(&self.data_dir);
+        let tombstones = blocking(move || match id {
+            Some(id) => read_tombstone(&trash_dir, &id).into_iter().collect(),
+            None => list_tombstones(&trash_dir),
+        })
+        .await
+        .unwrap_or_default();
+        tombstones
+            .into_iter()
+            .flat_map(|tombstone| tombstone.workspaces)
+            .map(|trashed| trashed.entry.id)
+            .collect()
+    }
+
+    /// Puts back what one removal trashed, main workspaces before their
+    /// worktrees. Workspaces that can't be restored stay in the item.
+    async fn restore_from_trash(&self, id: &str) -> Result<RestoreSummary, String> {
+        let _trash_guard = self.trash_lock.lock().await;
+        let trash_dir = trash_root(&self.data_dir);
+        let mut tombstone = {
+            let (trash_dir, id) = (trash_dir.clone(), id.to_string());
+            blocking(move || read_tombstone(&trash_dir, &id)).await??
+        };
+        let item = item_dir(&trash_dir, id)?;
+        let mut summary = RestoreSummary::default();
+        let mut workspaces = std::mem::take(&mut tombstone.workspaces);
+        workspaces.sort_by_key(|trashed| trashed.entry.kind.is_worktree());
+        for trashed in workspaces {
+            match self.restore_trashed(&item, &trashed).await {
+                Ok(warnings) => {
+                    summary.restored.push(trashed.entry.id.clone());
+                    summary.warnings.extend(warnings);
+                }
+                Err(err) => {
+                    summary.failed.insert(trashed.entry.id.clone(), err);
+                    tombstone.workspaces.push(trashed);
+                }
+            }
+        }
+
+        let id = id.to_string();
+        blocking(move || {
+            if tombstone.workspaces.is_empty() {
+                remove_item(&trash_dir, &id)
+            } else {
+                write_tombstone(&trash_dir, &tombstone)
+            }
+        })
+        .await??;
+        if !summary.restored.is_empty() {
+            let list = {
+                let workspaces = self.workspaces.lock().await;
+                workspaces.values().cloned().collect::<Vec<_>>()
+            };
+            let change = WorkspacesChangedNotice {
+                added: summary.restored.clone(),
+                ..WorkspacesChangedNotice::default()
+            };
+            self.save_workspaces(&list, change).await?;
+        }
+        Ok(summary)
+    }
+
+    /// Moves one trashed workspace back and re-adds its entry. A worktree's
+    /// parent must be a workspace again, and `git worktree repair` relinks
+    /// the two.
+    async fn restore_trashed(
+        &self,
+        item: &Path,
+        trashed: &TrashedWorkspace,
+    ) -> Result<Vec<String>, String> {
+        let entry = &trashed.entry;
+        let parent_path = {
+            let workspaces = self.workspaces.lock().await;
+            if workspaces.contains_key(&entry.id) {
+                return Err("workspace already exists".to_string());
+            }
+            if workspaces
+                .values()
+                .any(|workspace| workspace.path == entry.path)
+            {
+                return Err(format!("{} is already a workspace", entry.path));
+            }
+            match entry.parent_id.as_deref() {
+                Some(parent_id) if entry.kind.is_worktree() => Some(
+                    workspaces
+                        .get(parent_id)
+                        .map(|parent| PathBuf::from(&parent.path))
+                        .ok_or("worktree parent not found")?,
+                ),
+                _ => None,
+            }
+        };
+        if trashed.folder.is_none() && !Path::new(&entry.path).is_dir() {
+            return Err(format!("folder not found: {}", entry.path));
+        }
+
+        let (item, restored) = (item.to_path_buf(), trashed.clone());
+        let mut warnings: Vec<String> = blocking(move || restore_worktree(&item, &restored))
+            .await??
+            .into_iter()
+            .collect();
+        if let Some(parent_path) = parent_path {
+            if let Err(err) =
+                run_git_command(&parent_path, &["worktree", "repair", &entry.path]).await
+            {
+                warnings.push(format!("{}: git worktree repair failed: {err}", entry.id));
+            }
+        }
+        self.workspaces
+            .lock()
+            .await
+            .insert(entry.id.clone(), entry.clone());
+        Ok(warnings)
+    }
+
+    /// Deletes one trash item, or all of them without `id`.
+    async fn empty_trash(&self, id: Option<String>) -> Result<Vec<String>, String> {
+        let _trash_guard = self.trash_lock.lock().await;
+        let trash_dir = trash_root(&self.data_dir);
+        blocking(move || {
+            if let Some(id) = id {
+                remove_item(&trash_dir, &id)?;
+                return Ok(vec![id]);
+            }
+            let removed = list_tombstones(&trash_dir)
+                .into_iter()
+                .map(|tombstone| tombstone.id)
+                .collect();
+            if trash_dir.exists() {
+                std::fs::remove_dir_all(&trash_dir)
+                    .map_err(|err| format!("Failed to empty the trash: {err}"))?;
+            }
+            Ok(removed)
+        })
+        .await?
+    }
+
+    /// Deletes trash items older than the retention.
+    async fn purge_expired_trash(&self) -> Vec<String> {
+        let retention_days = self.trash_retention_days().await;
+        let _trash_guard = self.trash_lock.lock().await;
+        let trash_dir = trash_root(&self.data_dir);
+        let now = now_ms();
+        blocking(move || {
+            let mut purged = Vec::new();
+            for tombstone in list_tombstones(&trash_dir) {
+                let expired = expires_at(&tombstone, retention_days).is_some_and(|at| at <= now);
+                if expired && remove_item(&trash_dir, &tombstone.id).is_ok() {
+                    purged.push(tombstone.id);
+                }
+            }
+            purged
+        })
+        .await
+        .unwrap_or_default()
+    }
+
+    /// Deletes saved command outputs older than `artifactRetentionDays`.
+    async fn prune_artifacts(&self) -> usize {
+        let retention_days = self
+            .app_settings
+            .lock()
+            .await
+            .artifact_retention_days
+            .unwrap_or(DEFAULT_ARTIFACT_RETENTION_DAYS);
+        if retention_days == 0 {
+            return 0;
+        }
+        let retention = Duration::from_secs(u64::from(retention_days) * 24 * 60 * 60);
+        match SystemTime::now().checked_sub(retention) {
+            Some(cutoff) => self.artifacts.prune(cutoff),
+            None => 0,
+        }
     }
 
     async fn rename_worktree(
@@ -485,10 +2065,10 @@ impl DaemonState {
         std::fs::create_dir_all(&worktree_root)
             .map_err(|e| format!("Failed to create worktree directory: {e}"))?;
 
-        let safe_name = sanitize_worktree_name(&final_branch);
+        let naming = self.app_settings.lock().await.worktree_naming;
         let current_path = PathBuf::from(&entry.path);
         let next_path =
-            unique_worktree_path_for_rename(&worktree_root, &safe_name, &current_path)?;
+            worktree_path_for_rename(&worktree_root, &final_branch, naming, &current_path)?;
         let next_path_string = next_path.to_string_lossy().to_string();
         if next_path_string != entry.path {
             if let Err(error) = run_git_command(
@@ -517,10 +2097,12 @@ impl DaemonState {
             match entry.worktree.as_mut() {
                 Some(worktree) => {
                     worktree.branch = final_branch.clone();
+                    worktree.dir_name = worktree_dir_name(&next_path);
                 }
                 None => {
                     entry.worktree = Some(WorktreeInfo {
                         branch: final_branch.clone(),
+                        dir_name: worktree_dir_name(&next_path),
                     });
                 }
             }
@@ -528,53 +2110,141 @@ impl DaemonState {
             let list: Vec<_> = workspaces.values().cloned().collect();
             (snapshot, list)
         };
-        write_workspaces(&self.storage_path, &list)?;
+        self.save_workspaces(&list, WorkspacesChangedNotice::updated(&id))
+            .await?;
 
         let was_connected = self.sessions.lock().await.contains_key(&entry_snapshot.id);
         if was_connected {
             self.kill_session(&entry_snapshot.id).await;
-            let default_bin = {
-                let settings = self.app_settings.lock().await;
-                settings.codex_bin.clone()
-            };
             let codex_home =
                 codex_home::resolve_workspace_codex_home(&entry_snapshot, Some(&parent.path));
-            match spawn_workspace_session(
-                entry_snapshot.clone(),
-                default_bin,
-                client_version,
-                self.event_sink.clone(),
-                codex_home,
-            )
+            match self
+                .spawn_session(entry_snapshot.clone(), client_version, codex_home)
+                .await
+            {
+                Ok(session) => {
+                    self.insert_session(entry_snapshot.id.clone(), session)
+                        .await;
+                }
+                Err(error) => {
+                    let error = NewError::new(
+                        ErrorSource::Respawn,
+                        format!("respawn failed after rename_worktree: {error}"),
+                    );
+                    self.record_workspace_error(&entry_snapshot.id, error).await;
+                }
+            }
+        }
+
+        let connected = self.sessions.lock().await.contains_key(&entry_snapshot.id);
+        Ok(WorkspaceInfo::from_entry(entry_snapshot, connected))
+    }
+
+    async fn move_worktree(
+        &self,
+        id: String,
+        base_dir: String,
+        client_version: String,
+    ) -> Result<WorkspaceInfo, String> {
+        let (entry, parent) = {
+            let workspaces = self.workspaces.lock().await;
+            let entry = workspaces.get(&id).cloned().ok_or("workspace not found")?;
+            if !entry.kind.is_worktree() {
+                return Err("Not a worktree workspace.".to_string());
+            }
+            let parent_id = entry.parent_id.clone().ok_or("worktree parent not found")?;
+            let parent = workspaces
+                .get(&parent_id)
+                .cloned()
+                .ok_or("worktree parent not found")?;
+            (entry, parent)
+        };
+
+        let current_path = PathBuf::from(&entry.path);
+        if !current_path.is_dir() {
+            return Err(format!("folder not found: {}", entry.path));
+        }
+        let next_path = move_destination(&base_dir, &current_path)?;
+        validate_worktree_path(&next_path)?;
+        let next_path_string = next_path.to_string_lossy().to_string();
+
+        let repo_lock = self.repo_locks.lock_for(&parent.id);
+        let _repo_guard = repo_lock.lock().await;
+        let parent_root = PathBuf::from(&parent.path);
+
+        let created_dirs =
+            create_dir_all_tracked(next_path.parent().ok_or("invalid destination")?)?;
+        if let Err(error) = relocate_worktree(&parent_root, &current_path, &next_path).await {
+            created_dirs.remove_empty();
+            return Err(error);
+        }
+
+        let updated = {
+            let mut workspaces = self.workspaces.lock().await;
+            match workspaces.get_mut(&id) {
+                Some(entry) => {
+                    entry.path = next_path_string.clone();
+                    if let Some(worktree) = entry.worktree.as_mut() {
+                        worktree.dir_name = worktree_dir_name(&next_path);
+                    }
+                    let snapshot = entry.clone();
+                    let list: Vec<_> = workspaces.values().cloned().collect();
+                    Some((snapshot, list))
+                }
+                None => None,
+            }
+        };
+        let Some((entry_snapshot, list)) = updated else {
+            if relocate_worktree(&parent_root, &next_path, &current_path)
+                .await
+                .is_ok()
+            {
+                created_dirs.remove_empty();
+            }
+            return Err("workspace not found".to_string());
+        };
+        if let Err(error) = self
+            .save_workspaces(&list, WorkspacesChangedNotice::updated(&id))
             .await
+        {
+            if let Some(stored) = self.workspaces.lock().await.get_mut(&id) {
+                stored.path = entry.path.clone();
+                stored.worktree = entry.worktree.clone();
+            }
+            if relocate_worktree(&parent_root, &next_path, &current_path)
+                .await
+                .is_ok()
+            {
+                created_dirs.remove_empty();
+            }
+            return Err(error);
+        }
+
+        let was_connected = self.sessions.lock().await.contains_key(&entry_snapshot.id);
+        if was_connected {
+            self.kill_session(&entry_snapshot.id).await;
+            let codex_home =
+                codex_home::resolve_workspace_codex_home(&entry_snapshot, Some(&parent.path));
+            match self
+                .spawn_session(entry_snapshot.clone(), client_version, codex_home)
+                .await
             {
                 Ok(session) => {
-                    self.sessions
-                        .lock()
-                        .await
-                        .insert(entry_snapshot.id.clone(), session);
+                    self.insert_session(entry_snapshot.id.clone(), session)
+                        .await;
                 }
                 Err(error) => {
-                    eprintln!(
-                        "rename_worktree: respawn failed for {} after rename: {error}",
-                        entry_snapshot.id
+                    let error = NewError::new(
+                        ErrorSource::Respawn,
+                        format!("respawn failed after move_worktree: {error}"),
                     );
+                    self.record_workspace_error(&entry_snapshot.id, error).await;
                 }
             }
         }
 
         let connected = self.sessions.lock().await.contains_key(&entry_snapshot.id);
-        Ok(WorkspaceInfo {
-            id: entry_snapshot.id,
-            name: entry_snapshot.name,
-            path: entry_snapshot.path,
-            connected,
-            codex_bin: entry_snapshot.codex_bin,
-            kind: entry_snapshot.kind,
-            parent_id: entry_snapshot.parent_id,
-            worktree: entry_snapshot.worktree,
-            settings: entry_snapshot.settings,
-        })
+        Ok(WorkspaceInfo::from_entry(entry_snapshot, connected))
     }
 
     async fn rename_worktree_upstream(
@@ -664,87 +2334,198 @@ impl DaemonState {
         &self,
         id: String,
         settings: WorkspaceSettings,
-    ) -> Result<WorkspaceInfo, String> {
-        let (entry_snapshot, list) = {
+    ) -> Result<WorkspaceInfo, RpcError> {
+        let (entry_snapshot, parent, list) = {
             let mut workspaces = self.workspaces.lock().await;
             let entry_snapshot = match workspaces.get_mut(&id) {
                 Some(entry) => {
-                    entry.settings = settings.clone();
+                    validate_settings_change(&entry.settings, &settings, &entry.path)?;
+                    entry.settings = settings;
                     entry.clone()
                 }
-                None => return Err("workspace not found".to_string()),
+                None => return Err("workspace not found".into()),
             };
+            let parent = entry_snapshot
+                .parent_id
+                .as_deref()
+                .and_then(|parent_id| workspaces.get(parent_id))
+                .cloned();
             let list: Vec<_> = workspaces.values().cloned().collect();
-            (entry_snapshot, list)
+            (entry_snapshot, parent, list)
         };
-        write_workspaces(&self.storage_path, &list)?;
+        self.save_workspaces(&list, WorkspacesChangedNotice::updated(&id))
+            .await?;
+        if entry_snapshot.settings.disable_turn_inputs {
+            self.turn_inputs.forget(&id);
+        }
 
         let connected = self.sessions.lock().await.contains_key(&id);
-        Ok(WorkspaceInfo {
-            id: entry_snapshot.id,
-            name: entry_snapshot.name,
-            path: entry_snapshot.path,
-            connected,
-            codex_bin: entry_snapshot.codex_bin,
-            kind: entry_snapshot.kind,
-            parent_id: entry_snapshot.parent_id,
-            worktree: entry_snapshot.worktree,
-            settings: entry_snapshot.settings,
-        })
+        let mut info = WorkspaceInfo::from_entry(entry_snapshot, connected);
+        info.inherit_color(parent.as_ref());
+        Ok(info)
     }
 
-    async fn update_workspace_codex_bin(
+    async fn patch_workspace_settings(
         &self,
         id: String,
-        codex_bin: Option<String>,
-    ) -> Result<WorkspaceInfo, String> {
-        let (entry_snapshot, list) = {
+        patch: Value,
+    ) -> Result<WorkspaceInfo, RpcError> {
+        let (entry_snapshot, parent, list) = {
             let mut workspaces = self.workspaces.lock().await;
             let entry_snapshot = match workspaces.get_mut(&id) {
                 Some(entry) => {
-                    entry.codex_bin = codex_bin.clone();
+                    let settings = entry.settings.with_patch(&patch)?;
+                    validate_settings_change(&entry.settings, &settings, &entry.path)?;
+                    entry.settings = settings;
                     entry.clone()
                 }
-                None => return Err("workspace not found".to_string()),
+                None => return Err("workspace not found".into()),
             };
+            let parent = entry_snapshot
+                .parent_id
+                .as_deref()
+                .and_then(|parent_id| workspaces.get(parent_id))
+                .cloned();
             let list: Vec<_> = workspaces.values().cloned().collect();
-            (entry_snapshot, list)
+            (entry_snapshot, parent, list)
         };
-        write_workspaces(&self.storage_path, &list)?;
+        self.save_workspaces(&list, WorkspacesChangedNotice::updated(&id))
+            .await?;
+        if entry_snapshot.settings.disable_turn_inputs {
+            self.turn_inputs.forget(&id);
+        }
 
         let connected = self.sessions.lock().await.contains_key(&id);
-        Ok(WorkspaceInfo {
-            id: entry_snapshot.id,
-            name: entry_snapshot.name,
-            path: entry_snapshot.path,
-            connected,
-            codex_bin: entry_snapshot.codex_bin,
-            kind: entry_snapshot.kind,
-            parent_id: entry_snapshot.parent_id,
-            worktree: entry_snapshot.worktree,
-            settings: entry_snapshot.settings,
-        })
+        let mut info = WorkspaceInfo::from_entry(entry_snapshot, connected);
+        info.inherit_color(parent.as_ref());
+        Ok(info)
     }
 
-    async fn connect_workspace(&self, id: String, client_version: String) -> Result<(), String> {
-        {
-            let sessions = self.sessions.lock().await;
-            if sessions.contains_key(&id) {
-                return Ok(());
+    /// Applies one settings patch to several workspaces, each checked like
+    /// `patch_workspace_settings`. Workspaces that fail keep their settings;
+    /// the others are saved at once with a single `workspaces-changed`.
+    async fn update_workspaces_settings_bulk(
+        &self,
+        target: BulkTarget,
+        patch: Value,
+        authorize: impl Fn(&str) -> Result<(), String>,
+    ) -> Result<Vec<BulkSettingsResult>, RpcError> {
+        // Unknown keys and wrongly typed values fail the whole call.
+        WorkspaceSettings::default().with_patch(&patch)?;
+        let groups = self.app_settings.lock().await.workspace_groups.clone();
+        let (results, list) = {
+            let mut workspaces = self.workspaces.lock().await;
+            let ids = target_ids(&target, &workspaces, &groups)?;
+            let results = apply_bulk_patch(ids, &mut workspaces, &patch, authorize);
+            let list: Vec<_> = workspaces.values().cloned().collect();
+            (results, list)
+        };
+        let changed: Vec<String> = results
+            .iter()
+            .filter(|result| !result.changed.is_empty())
+            .map(|result| result.id.clone())
+            .collect();
+        if changed.is_empty() {
+            return Ok(results);
+        }
+        let notice = WorkspacesChangedNotice::updated_many(changed.clone());
+        self.save_workspaces(&list, notice).await?;
+        for entry in list.iter().filter(|entry| changed.contains(&entry.id)) {
+            if entry.settings.disable_turn_inputs {
+                self.turn_inputs.forget(&entry.id);
             }
         }
+        Ok(results)
+    }
 
-        let entry = {
+    async fn save_workspace_profile(
+        &self,
+        workspace_id: &str,
+        name: &str,
+    ) -> Result<WorkspaceProfile, String> {
+        let profile = {
             let workspaces = self.workspaces.lock().await;
-            workspaces
-                .get(&id)
-                .cloned()
-                .ok_or("workspace not found")?
+            let entry = workspaces.get(workspace_id).ok_or("workspace not found")?;
+            WorkspaceProfile::capture(name, entry)?
         };
+        self.profiles.save(profile.clone())?;
+        Ok(profile)
+    }
 
-        let default_bin = {
-            let settings = self.app_settings.lock().await;
-            settings.codex_bin.clone()
+    /// Applies a saved profile through the regular settings and codex_bin
+    /// updates; a running session has to restart for a new codex_bin.
+    async fn apply_workspace_profile(
+        &self,
+        workspace_id: String,
+        name: &str,
+    ) -> Result<WorkspaceInfo, String> {
+        let profile = self.profiles.get(name)?;
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(&workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        let settings = profile.settings_for(&entry.settings);
+        let info = self
+            .update_workspace_settings(workspace_id.clone(), settings)
+            .await
+            .map_err(|err| err.message)?;
+        if profile.codex_bin == entry.codex_bin {
+            return Ok(info);
+        }
+        let info = self
+            .update_workspace_codex_bin(workspace_id.clone(), profile.codex_bin)
+            .await?;
+        if info.connected {
+            let notice = RestartRequiredNotice {
+                workspace_ids: vec![workspace_id],
+                reason: "codex-bin",
+            };
+            self.event_sink.send(DaemonEvent::RestartRequired(notice));
+        }
+        Ok(info)
+    }
+
+    async fn update_workspace_codex_bin(
+        &self,
+        id: String,
+        codex_bin: Option<String>,
+    ) -> Result<WorkspaceInfo, String> {
+        let (entry_snapshot, list) = {
+            let mut workspaces = self.workspaces.lock().await;
+            let entry_snapshot = match workspaces.get_mut(&id) {
+                Some(entry) => {
+                    entry.codex_bin = codex_bin.clone();
+                    entry.clone()
+                }
+                None => return Err("workspace not found".to_string()),
+            };
+            let list: Vec<_> = workspaces.values().cloned().collect();
+            (entry_snapshot, list)
+        };
+        self.save_workspaces(&list, WorkspacesChangedNotice::updated(&id))
+            .await?;
+
+        let connected = self.sessions.lock().await.contains_key(&id);
+        Ok(WorkspaceInfo::from_entry(entry_snapshot, connected))
+    }
+
+    async fn connect_workspace(&self, id: String, client_version: String) -> Result<(), String> {
+        {
+            let sessions = self.sessions.lock().await;
+            if sessions.contains_key(&id) {
+                return Ok(());
+            }
+        }
+
+        let entry = {
+            let workspaces = self.workspaces.lock().await;
+            workspaces
+                .get(&id)
+                .cloned()
+                .ok_or("workspace not found")?
         };
 
         let parent_path = if entry.kind.is_worktree() {
@@ -758,17 +2539,140 @@ impl DaemonState {
             None
         };
         let codex_home = codex_home::resolve_workspace_codex_home(&entry, parent_path.as_deref());
-        let session = spawn_workspace_session(
+        let session = self
+            .spawn_session(entry, client_version, codex_home)
+            .await?;
+
+        self.insert_session(id, session).await;
+        Ok(())
+    }
+
+    /// Starts `connect_workspace` in the background for every disconnected
+    /// workspace without a spawn already waiting that `authorize` accepts,
+    /// in `list_workspaces` order, and returns their ids. Progress comes as
+    /// `session-spawn-*` events.
+    async fn connect_all_workspaces(
+        self: &Arc<Self>,
+        client_version: String,
+        authorize: impl Fn(&str) -> Result<(), String>,
+    ) -> Vec<String> {
+        let waiting = self.spawn_queue.waiting_ids();
+        let ids: Vec<String> = self
+            .list_workspaces()
+            .await
+            .into_iter()
+            .filter(|workspace| !workspace.connected && !waiting.contains(&workspace.id))
+            .filter(|workspace| authorize(&workspace.id).is_ok())
+            .map(|workspace| workspace.id)
+            .collect();
+        for id in &ids {
+            let state = Arc::clone(self);
+            let id = id.clone();
+            let client_version = client_version.clone();
+            tokio::spawn(async move {
+                // Failures are also reported by `session-spawn-finished`.
+                if let Err(error) = state.connect_workspace(id.clone(), client_version).await {
+                    let error = NewError::new(
+                        ErrorSource::Respawn,
+                        format!("connect_all_workspaces failed to start the session: {error}"),
+                    );
+                    state.record_workspace_error(&id, error).await;
+                }
+            });
+        }
+        ids
+    }
+
+    /// Spawns a codex session once the spawn queue has a free slot. Every
+    /// spawn goes through here so connecting many workspaces can't start
+    /// every process at once. Concurrent spawns of one workspace share the
+    /// first one's result.
+    async fn spawn_session(
+        &self,
+        entry: WorkspaceEntry,
+        client_version: String,
+        codex_home: Option<PathBuf>,
+    ) -> Result<Arc<WorkspaceSession>, String> {
+        let workspace_id = entry.id.clone();
+        let spawn = Arc::clone(
+            self.spawns_in_flight
+                .lock()
+                .await
+                .entry(workspace_id.clone())
+                .or_default(),
+        );
+        let result = spawn
+            .get_or_init(|| self.spawn_queued_session(entry, client_version, codex_home))
+            .await
+            .clone();
+        let mut in_flight = self.spawns_in_flight.lock().await;
+        if in_flight
+            .get(&workspace_id)
+            .is_some_and(|current| Arc::ptr_eq(current, &spawn))
+        {
+            in_flight.remove(&workspace_id);
+        }
+        result
+    }
+
+    async fn spawn_queued_session(
+        &self,
+        entry: WorkspaceEntry,
+        client_version: String,
+        codex_home: Option<PathBuf>,
+    ) -> Result<Arc<WorkspaceSession>, String> {
+        let workspace_id = entry.id.clone();
+        let ticket = self.spawn_queue.enqueue(&workspace_id);
+        let queued = SessionSpawnNotice {
+            position: Some(ticket.position),
+            ..SessionSpawnNotice::new(&workspace_id)
+        };
+        self.event_sink
+            .send(DaemonEvent::SessionSpawnQueued(queued));
+        let Some(_permit) = ticket.wait().await else {
+            let error = "session spawn cancelled".to_string();
+            let finished = SessionSpawnNotice {
+                error: Some(error.clone()),
+                ..SessionSpawnNotice::new(&workspace_id)
+            };
+            self.event_sink
+                .send(DaemonEvent::SessionSpawnFinished(finished));
+            return Err(error);
+        };
+        // Another spawn may have connected the workspace while this one
+        // waited.
+        let existing = self.sessions.lock().await.get(&workspace_id).cloned();
+        if let Some(session) = existing {
+            self.event_sink
+                .send(DaemonEvent::SessionSpawnFinished(SessionSpawnNotice::new(
+                    &workspace_id,
+                )));
+            return Ok(session);
+        }
+        let started = SessionSpawnNotice::new(&workspace_id);
+        self.event_sink
+            .send(DaemonEvent::SessionSpawnStarted(started));
+
+        let (default_bin, experimental_flags) = {
+            let settings = self.app_settings.lock().await;
+            (settings.codex_bin.clone(), settings.experimental_flags())
+        };
+        let result = spawn_workspace_session(
             entry,
             default_bin,
+            experimental_flags,
             client_version,
             self.event_sink.clone(),
             codex_home,
         )
-        .await?;
-
-        self.sessions.lock().await.insert(id, session);
-        Ok(())
+        .await;
+        let finished = SessionSpawnNotice {
+            error: result.as_ref().err().cloned(),
+            ..SessionSpawnNotice::new(&workspace_id)
+        };
+        self.event_sink
+            .send(DaemonEvent::SessionSpawnFinished(finished));
+        result
     }
 
     async fn update_app_settings(&self, settings: AppSettings) -> Result<AppSettings, String> {
@@ -779,12 +2683,616 @@ impl DaemonState {
         let _ = codex_config::write_steer_enabled(settings.experimental_steer_enabled);
         let _ = codex_config::write_unified_exec_enabled(settings.experimental_unified_exec_enabled);
         write_settings(&self.settings_path, &settings)?;
-        let mut current = self.app_settings.lock().await;
-        *current = settings.clone();
+        {
+            let mut current = self.app_settings.lock().await;
+            *current = settings.clone();
+        }
+        let workspace_ids = self.stale_flag_sessions().await;
+        if !workspace_ids.is_empty() {
+            self.event_sink
+                .send(DaemonEvent::RestartRequired(RestartRequiredNotice {
+                    workspace_ids,
+                    reason: "experimental-flags",
+                }));
+        }
         Ok(settings)
     }
 
+    async fn get_git_identity(&self, workspace_id: &str) -> Result<GitIdentity, String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        git_state::ensure_git_repo(&workspace_git_path(&entry))?;
+        read_git_identity(Path::new(&entry.path)).await
+    }
+
+    async fn set_git_identity(
+        &self,
+        workspace_id: &str,
+        name: Option<String>,
+        email: Option<String>,
+        scope: Option<String>,
+    ) -> Result<GitIdentity, String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        git_state::ensure_git_repo(&workspace_git_path(&entry))?;
+        let scope = GitIdentityScope::parse(scope.as_deref(), entry.kind.is_worktree())?;
+        write_git_identity(
+            Path::new(&entry.path),
+            scope,
+            name.as_deref(),
+            email.as_deref(),
+        )
+        .await
+    }
+
+    async fn list_conflicts(&self, workspace_id: &str) -> Result<Vec<ConflictFile>, String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        git_state::ensure_git_repo(&workspace_git_path(&entry))?;
+        list_conflicts(&PathBuf::from(&entry.path)).await
+    }
+
+    async fn mark_resolved(&self, workspace_id: &str, path: &str) -> Result<(), String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        mark_resolved(&PathBuf::from(&entry.path), path).await
+    }
+
+    async fn conflict_status(&self, workspace_id: &str) -> Result<ConflictStatus, String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        git_state::ensure_git_repo(&workspace_git_path(&entry))?;
+        conflict_status(&PathBuf::from(&entry.path)).await
+    }
+
+    async fn resolve_conflict(
+        &self,
+        workspace_id: &str,
+        path: &str,
+        resolution: ConflictResolution,
+    ) -> Result<(), String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        resolve_conflict(&PathBuf::from(&entry.path), path, resolution).await
+    }
+
+    async fn abort_operation(&self, workspace_id: &str) -> Result<GitOperation, String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        abort_operation(&PathBuf::from(&entry.path)).await
+    }
+
+    async fn continue_operation(&self, workspace_id: &str) -> Result<Option<GitOperation>, String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        continue_operation(&PathBuf::from(&entry.path)).await
+    }
+
+    async fn git_blame(
+        &self,
+        workspace_id: &str,
+        path: &str,
+        start_line: Option<u32>,
+        end_line: Option<u32>,
+    ) -> Result<BlameResponse, String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        git_state::ensure_git_repo(&workspace_git_path(&entry))?;
+        git_blame(&PathBuf::from(&entry.path), path, start_line, end_line).await
+    }
+
+    /// Cherry-picks commits from a worktree branch into its parent repo. A
+    /// conflict is reported as an error carrying the parent's conflict status.
+    async fn cherry_pick_commits(
+        &self,
+        workspace_id: &str,
+        commits: Vec<String>,
+        no_commit: bool,
+    ) -> Result<CherryPickResult, RpcError> {
+        let (entry, parent) = {
+            let workspaces = self.workspaces.lock().await;
+            let entry = workspaces
+                .get(workspace_id)
+                .cloned()
+                .ok_or("workspace not found")?;
+            if !entry.kind.is_worktree() {
+                return Err("cherry_pick_commits requires a worktree workspace.".into());
+            }
+            let parent = entry
+                .parent_id
+                .as_ref()
+                .and_then(|parent_id| workspaces.get(parent_id))
+                .cloned()
+                .ok_or("worktree parent not found")?;
+            (entry, parent)
+        };
+        let commits = resolve_worktree_commits(&PathBuf::from(&entry.path), &commits).await?;
+        let repo_lock = self.repo_locks.lock_for(&parent.id);
+        let _repo_guard = repo_lock.lock().await;
+        let parent_path = PathBuf::from(&parent.path);
+        match run_cherry_pick(&parent_path, &commits, no_commit).await {
+            Ok(result) => Ok(result),
+            Err(error) => Err(cherry_pick_rpc_error(&parent_path, error).await),
+        }
+    }
+
+    /// Cherry-picks one commit onto the workspace's own checkout. Conflicts are
+    /// reported like `cherry_pick_commits`.
+    async fn cherry_pick(
+        &self,
+        workspace_id: &str,
+        hash: &str,
+        no_commit: bool,
+    ) -> Result<CherryPickResult, RpcError> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        let path = PathBuf::from(&entry.path);
+        git_state::ensure_git_repo(&workspace_git_path(&entry))?;
+        let commit = resolve_commit(&path, hash).await?;
+        let repo_lock = self.repo_locks.lock_for(repo_id(&entry));
+        let _repo_guard = repo_lock.lock().await;
+        match run_cherry_pick(&path, &[commit], no_commit).await {
+            Ok(result) => Ok(result),
+            Err(error) => Err(cherry_pick_rpc_error(&path, error).await),
+        }
+    }
+
+    async fn cherry_pick_continue(
+        &self,
+        workspace_id: &str,
+    ) -> Result<Option<GitOperation>, String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        let path = PathBuf::from(&entry.path);
+        ensure_cherry_pick_in_progress(&path)?;
+        continue_operation(&path).await
+    }
+
+    async fn cherry_pick_abort(&self, workspace_id: &str) -> Result<(), String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        let path = PathBuf::from(&entry.path);
+        ensure_cherry_pick_in_progress(&path)?;
+        abort_operation(&path).await.map(|_| ())
+    }
+
+    async fn show_commit(&self, workspace_id: &str, hash: &str) -> Result<CommitDetails, String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        git_state::ensure_git_repo(&workspace_git_path(&entry))?;
+        show_commit(&PathBuf::from(&entry.path), hash).await
+    }
+
+    /// Squashes a worktree's commits since it forked from the parent's `HEAD`.
+    async fn squash_worktree(
+        &self,
+        workspace_id: &str,
+        message: Option<String>,
+        force: bool,
+        progress: &OperationProgress,
+    ) -> Result<SquashResult, String> {
+        let (entry, parent) = {
+            let workspaces = self.workspaces.lock().await;
+            let entry = workspaces
+                .get(workspace_id)
+                .cloned()
+                .ok_or("workspace not found")?;
+            if !entry.kind.is_worktree() {
+                return Err("squash_worktree requires a worktree workspace.".to_string());
+            }
+            let parent = entry
+                .parent_id
+                .as_ref()
+                .and_then(|parent_id| workspaces.get(parent_id))
+                .cloned()
+                .ok_or("worktree parent not found")?;
+            (entry, parent)
+        };
+        let branch = entry
+            .worktree
+            .as_ref()
+            .map(|worktree| worktree.branch.clone())
+            .unwrap_or_else(|| entry.name.clone());
+        let repo_lock = self.repo_locks.lock_for(&parent.id);
+        let _repo_guard = repo_lock.lock().await;
+        progress.report("Squashing", None, None);
+        let parent_head =
+            run_git_command(&PathBuf::from(&parent.path), &["rev-parse", "HEAD"]).await?;
+        squash_worktree(
+            &PathBuf::from(&entry.path),
+            &branch,
+            &parent_head,
+            message,
+            force,
+        )
+        .await
+    }
+
+    /// A worktree, its parent and its branch name.
+    async fn worktree_with_parent(
+        &self,
+        workspace_id: &str,
+        method: &str,
+    ) -> Result<(WorkspaceEntry, WorkspaceEntry, String), String> {
+        let workspaces = self.workspaces.lock().await;
+        let entry = workspaces
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        if !entry.kind.is_worktree() {
+            return Err(format!("{method} requires a worktree workspace."));
+        }
+        let parent = entry
+            .parent_id
+            .as_ref()
+            .and_then(|parent_id| workspaces.get(parent_id))
+            .cloned()
+            .ok_or("worktree parent not found")?;
+        let branch = entry
+            .worktree
+            .as_ref()
+            .map(|worktree| worktree.branch.clone())
+            .unwrap_or_else(|| entry.name.clone());
+        Ok((entry, parent, branch))
+    }
+
+    /// Pushes the worktree branch with upstream tracking, then opens a PR
+    /// for it with `gh` from the parent repo.
+    async fn create_pull_request(
+        &self,
+        workspace_id: &str,
+        title: String,
+        body: Option<String>,
+        base: Option<String>,
+        draft: bool,
+    ) -> Result<CreatedPullRequest, String> {
+        if title.trim().is_empty() {
+            return Err("title is required".to_string());
+        }
+        let (entry, parent, branch) = self
+            .worktree_with_parent(workspace_id, "create_pull_request")
+            .await?;
+        let parent_path = PathBuf::from(&parent.path);
+        let remote = preferred_remote(&parent_path)
+            .await
+            .ok_or("The parent repository has no remote to push to.")?;
+        push_with_upstream(Path::new(&entry.path), &remote, &branch).await?;
+        create_pull_request(
+            &parent_path,
+            &branch,
+            base.as_deref()
+                .map(str::trim)
+                .filter(|base| !base.is_empty()),
+            title.trim(),
+            body.as_deref().unwrap_or(""),
+            draft,
+        )
+        .await
+    }
+
+    async fn pull_request_status(
+        &self,
+        workspace_id: &str,
+    ) -> Result<Option<PullRequestStatus>, String> {
+        let (_, parent, branch) = self
+            .worktree_with_parent(workspace_id, "pull_request_status")
+            .await?;
+        pull_request_status(Path::new(&parent.path), &branch).await
+    }
+
+    async fn list_tags(
+        &self,
+        workspace_id: &str,
+        pattern: Option<String>,
+    ) -> Result<Vec<TagInfo>, String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        git_state::ensure_git_repo(&workspace_git_path(&entry))?;
+        list_tags(&PathBuf::from(&entry.path), pattern.as_deref()).await
+    }
+
+    async fn create_tag(
+        &self,
+        workspace_id: &str,
+        name: &str,
+        message: Option<String>,
+        target: Option<String>,
+    ) -> Result<TagInfo, String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        git_state::ensure_git_repo(&workspace_git_path(&entry))?;
+        create_tag(
+            &PathBuf::from(&entry.path),
+            name,
+            message.as_deref(),
+            target.as_deref(),
+        )
+        .await
+    }
+
+    /// Fetches the repo behind `workspace_id` now (the parent repo for
+    /// worktrees), waiting for other operations on it to finish first.
+    async fn fetch_workspace(&self, workspace_id: &str) -> Result<WorkspaceFetchStatus, String> {
+        let repo = {
+            let workspaces = self.workspaces.lock().await;
+            let entry = workspaces.get(workspace_id).ok_or("workspace not found")?;
+            workspaces
+                .get(repo_id(entry))
+                .cloned()
+                .ok_or("worktree parent not found")?
+        };
+        git_state::ensure_git_repo(&workspace_git_path(&repo))?;
+        let repo_lock = self.repo_locks.lock_for(&repo.id);
+        let _repo_guard = repo_lock.lock().await;
+        let result = run_fetch(Path::new(&repo.path)).await;
+        let status = self.fetch_tracker.record(&repo.id, now_ms(), &result);
+        result.map(|()| status)
+    }
+
+    /// One pass of the background fetch loop: fetches every main repo that is
+    /// due, a few at a time. Repos busy with another operation are skipped
+    /// until the next pass.
+    async fn background_fetch_round(self: &Arc<Self>) {
+        let Some(interval_secs) = self
+            .app_settings
+            .lock()
+            .await
+            .background_fetch_interval_secs
+        else {
+            return;
+        };
+        let interval_secs = interval_secs.max(MIN_FETCH_INTERVAL_SECS);
+        let now = now_ms();
+        let due: Vec<WorkspaceEntry> = self
+            .workspaces
+            .lock()
+            .await
+            .values()
+            .filter(|entry| !entry.kind.is_worktree())
+            .filter(|entry| self.fetch_tracker.is_due(&entry.id, now, interval_secs))
+            .cloned()
+            .collect();
+        let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_FETCHES));
+        let mut tasks = Vec::new();
+        for repo in due {
+            if !git_state::is_git_repo(&workspace_git_path(&repo)) {
+                continue;
+            }
+            let state = Arc::clone(self);
+            let permits = Arc::clone(&permits);
+            tasks.push(tokio::spawn(async move {
+                let Ok(_permit) = permits.acquire().await else {
+                    return;
+                };
+                let repo_lock = state.repo_locks.lock_for(&repo.id);
+                let Ok(_repo_guard) = repo_lock.try_lock() else {
+                    return;
+                };
+                let result = run_fetch(Path::new(&repo.path)).await;
+                if let Err(error) = &result {
+                    let error = NewError::new(
+                        ErrorSource::Fetch,
+                        format!("background fetch failed for {}: {error}", repo.path),
+                    );
+                    state.record_workspace_error(&repo.id, error).await;
+                }
+                state.fetch_tracker.record(&repo.id, now_ms(), &result);
+            }));
+        }
+        for task in tasks {
+            let _ = task.await;
+        }
+    }
+
+    async fn discard_changes(
+        &self,
+        workspace_id: &str,
+        paths: Vec<String>,
+        include_untracked: bool,
+        dry_run: bool,
+        confirm: bool,
+    ) -> Result<DiscardResult, String> {
+        if !dry_run && !confirm {
+            return Err(
+                "discard_changes deletes work; pass confirm: true, or dryRun: true to preview."
+                    .to_string(),
+            );
+        }
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        let path = PathBuf::from(&entry.path);
+        git_state::ensure_git_repo(&workspace_git_path(&entry))?;
+        let repo_lock = self.repo_locks.lock_for(repo_id(&entry));
+        let _repo_guard = repo_lock.lock().await;
+        discard_changes(&path, &paths, include_untracked, dry_run).await
+    }
+
+    /// Lists repos under `roots` (default: the `discoveryRoots` setting, else
+    /// the projects root) that could be added as workspaces. Nothing is added.
+    async fn discover_workspaces(
+        &self,
+        roots: Option<Vec<String>>,
+        max_depth: Option<u32>,
+    ) -> Result<DiscoveryResult, String> {
+        let roots = match roots {
+            Some(roots) => roots,
+            None => self.app_settings.lock().await.discovery_roots.clone(),
+        };
+        let mut root_paths = Vec::new();
+        for root in roots.iter().map(|root| root.trim()) {
+            if root.is_empty() {
+                continue;
+            }
+            let path = PathBuf::from(root);
+            if !path.is_absolute() {
+                return Err(format!("Discovery root must be an absolute path: {root}"));
+            }
+            root_paths.push(path);
+        }
+        if root_paths.is_empty() {
+            root_paths.push(self.projects_root().await);
+        }
+        let registered: HashSet<PathBuf> = self
+            .workspaces
+            .lock()
+            .await
+            .values()
+            .map(|entry| discover::canonical(Path::new(&entry.path)))
+            .collect();
+        let max_depth = max_depth
+            .unwrap_or(DEFAULT_DISCOVERY_DEPTH)
+            .clamp(1, MAX_DISCOVERY_DEPTH);
+        Ok(discover_workspaces(&root_paths, max_depth, &registered))
+    }
+
+    async fn workspace_remote_info(&self, workspace_id: &str) -> Result<RemoteInfo, String> {
+        let entry = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        Ok(read_remote_info(&workspace_git_path(&entry)).await)
+    }
+
+    /// Compare view of a worktree's branch against its parent's default branch.
+    async fn branch_compare_url(&self, workspace_id: &str) -> Result<BranchCompareUrl, String> {
+        let (entry, parent) = {
+            let workspaces = self.workspaces.lock().await;
+            let entry = workspaces
+                .get(workspace_id)
+                .cloned()
+                .ok_or("workspace not found")?;
+            if !entry.kind.is_worktree() {
+                return Err("branch_compare_url requires a worktree workspace.".to_string());
+            }
+            let parent = entry
+                .parent_id
+                .as_ref()
+                .and_then(|parent_id| workspaces.get(parent_id))
+                .cloned()
+                .ok_or("worktree parent not found")?;
+            (entry, parent)
+        };
+        let head = entry
+            .worktree
+            .as_ref()
+            .map(|worktree| worktree.branch.clone())
+            .unwrap_or_else(|| entry.name.clone());
+        let parent_path = workspace_git_path(&parent);
+        let info = read_remote_info(&parent_path).await;
+        let base = default_branch(&parent_path, info.remote_name.as_deref()).await;
+        let url = match (&info.templates.compare, &base) {
+            (Some(template), Some(base)) => Some(fill_compare_template(template, base, &head)),
+            _ => None,
+        };
+        Ok(BranchCompareUrl { url, base, head })
+    }
+
+    /// The workspace's session, counted as activity. A session stopped for
+    /// idleness is reconnected first while `autoReconnect` is on.
     async fn get_session(&self, workspace_id: &str) -> Result<Arc<WorkspaceSession>, String> {
+        if let Some(session) = self.sessions.lock().await.get(workspace_id).cloned() {
+            self.idle.touch(workspace_id);
+            return Ok(session);
+        }
+        let auto_reconnect = self.app_settings.lock().await.auto_reconnect;
+        if !auto_reconnect || !self.idle.was_reaped(workspace_id) {
+            return Err("workspace not connected".to_string());
+        }
+        let _guard = self.idle.lock_reconnects().await;
+        // A request that waited here may find the session back, or the
+        // workspace purged.
+        if let Some(session) = self.sessions.lock().await.get(workspace_id).cloned() {
+            return Ok(session);
+        }
+        if !self.idle.was_reaped(workspace_id) {
+            return Err("workspace not connected".to_string());
+        }
+        let client_version = format!("daemon-{}", env!("CARGO_PKG_VERSION"));
+        self.connect_workspace(workspace_id.to_string(), client_version)
+            .await?;
         let sessions = self.sessions.lock().await;
         sessions
             .get(workspace_id)
@@ -792,7 +3300,167 @@ impl DaemonState {
             .ok_or("workspace not connected".to_string())
     }
 
-    async fn list_workspace_files(&self, workspace_id: String) -> Result<Vec<String>, String> {
+    /// Stops sessions past their `idleDisconnectMinutes` that have no running
+    /// or queued turn and no server request waiting for an answer.
+    async fn disconnect_idle_sessions(&self) {
+        let app_limit = self.app_settings.lock().await.idle_disconnect_minutes;
+        let connected: Vec<String> = self.sessions.lock().await.keys().cloned().collect();
+        let candidates: Vec<(String, Duration)> = {
+            let workspaces = self.workspaces.lock().await;
+            connected
+                .into_iter()
+                .filter_map(|id| {
+                    let workspace_limit = workspaces.get(&id)?.settings.idle_disconnect_minutes;
+                    let limit = idle_limit(app_limit, workspace_limit)?;
+                    Some((id, limit))
+                })
+                .collect()
+        };
+        for (workspace_id, limit) in candidates {
+            if self.turns.active_count(&workspace_id) > 0
+                || self.turns.queued_count(&workspace_id) > 0
+                || !self.idle.is_idle(&workspace_id, limit)
+            {
+                continue;
+            }
+            self.kill_session(&workspace_id).await;
+            self.idle.reaped(&workspace_id);
+            let notice = AutoDisconnectNotice {
+                workspace_id,
+                idle_minutes: limit.as_secs() / 60,
+            };
+            self.event_sink.send(DaemonEvent::AutoDisconnected(notice));
+        }
+    }
+
+    /// Running sessions by workspace name, for operators; `list_workspaces`
+    /// covers every registered workspace instead.
+    async fn list_sessions(&self) -> Vec<SessionInfo> {
+        let sessions: Vec<(String, Arc<WorkspaceSession>)> = self
+            .sessions
+            .lock()
+            .await
+            .iter()
+            .map(|(id, session)| (id.clone(), Arc::clone(session)))
+            .collect();
+        let mut infos = Vec::with_capacity(sessions.len());
+        for (workspace_id, session) in sessions {
+            let pid = session.child.lock().await.id();
+            let pending_requests = session.pending.lock().await.len();
+            let activity = self.idle.activity(&workspace_id);
+            infos.push(SessionInfo {
+                name: session.entry.name.clone(),
+                pid,
+                started_at: activity.connected_at.map(instant_ms),
+                uptime_secs: activity.connected_at.map(|at| at.elapsed().as_secs()),
+                last_activity_at: activity.last_active.map(instant_ms),
+                active_turns: self.turns.active_count(&workspace_id),
+                queued_turns: self.turns.queued_count(&workspace_id),
+                pending_requests,
+                open_server_requests: activity.open_requests,
+                memory_bytes: pid.and_then(resident_memory_bytes),
+                workspace_id,
+            });
+        }
+        infos.sort_by(|a, b| {
+            a.name
+                .cmp(&b.name)
+                .then(a.workspace_id.cmp(&b.workspace_id))
+        });
+        infos
+    }
+
+    /// Probes a connected session without counting as activity, so a
+    /// monitor polling it doesn't keep idle sessions alive.
+    async fn ping_session(
+        &self,
+        workspace_id: &str,
+        timeout_ms: Option<u64>,
+        slow_ms: Option<u64>,
+    ) -> Result<SessionHealth, String> {
+        let session = self
+            .sessions
+            .lock()
+            .await
+            .get(workspace_id)
+            .cloned()
+            .ok_or("workspace not connected")?;
+        Ok(ping_session(&session, ping_timeout(timeout_ms), slow_after(slow_ms)).await)
+    }
+
+    /// Pings every connected session, `MAX_CONCURRENT_PINGS` at a time. With
+    /// `reap_dead`, sessions that didn't answer and that `authorize` accepts
+    /// are stopped and, like idle ones, reconnected by the next request that
+    /// needs them.
+    async fn health_check_all(
+        self: &Arc<Self>,
+        timeout_ms: Option<u64>,
+        slow_ms: Option<u64>,
+        reap_dead: bool,
+        authorize: impl Fn(&str) -> Result<(), String>,
+    ) -> Value {
+        let sessions: Vec<(String, Arc<WorkspaceSession>)> = self
+            .sessions
+            .lock()
+            .await
+            .iter()
+            .map(|(id, session)| (id.clone(), Arc::clone(session)))
+            .collect();
+        let limit = ping_timeout(timeout_ms);
+        let slow_limit = slow_after(slow_ms);
+        let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_PINGS));
+        let mut tasks = Vec::new();
+        for (id, session) in sessions {
+            let permits = Arc::clone(&permits);
+            tasks.push(tokio::spawn(async move {
+                let _permit = permits.acquire().await;
+                (id, ping_session(&session, limit, slow_limit).await)
+            }));
+        }
+        let mut results = BTreeMap::new();
+        for task in tasks {
+            if let Ok((id, health)) = task.await {
+                results.insert(id, health);
+            }
+        }
+
+        let dead: Vec<String> = results
+            .iter()
+            .filter(|(_, health)| health.dead)
+            .map(|(id, _)| id.clone())
+            .collect();
+        let slow: Vec<&String> = results
+            .iter()
+            .filter(|(_, health)| health.slow)
+            .map(|(id, _)| id)
+            .collect();
+        let mut reaped = Vec::new();
+        if reap_dead {
+            for id in dead.iter().filter(|id| authorize(id).is_ok()) {
+                self.kill_session(id).await;
+                self.idle.reaped(id);
+                reaped.push(id.clone());
+            }
+        }
+        json!({ "sessions": results, "slow": slow, "dead": dead, "reaped": reaped })
+    }
+
+    async fn list_workspace_files(
+        &self,
+        workspace_id: String,
+        follow_links: bool,
+    ) -> Result<Vec<String>, String> {
+        let entries = self
+            .list_workspace_files_detailed(workspace_id, follow_links)
+            .await?;
+        Ok(entries.into_iter().map(|entry| entry.path).collect())
+    }
+
+    async fn list_workspace_files_detailed(
+        &self,
+        workspace_id: String,
+        follow_links: bool,
+    ) -> Result<Vec<WorkspaceFileEntry>, String> {
         let entry = {
             let workspaces = self.workspaces.lock().await;
             workspaces
@@ -802,7 +3470,30 @@ impl DaemonState {
         };
 
         let root = PathBuf::from(entry.path);
-        Ok(list_workspace_files_inner(&root, 20000))
+        Ok(list_workspace_files_inner(
+            &root,
+            MAX_WORKSPACE_FILES,
+            follow_links,
+        ))
+    }
+
+    /// Reports what changed in the workspace since `since_token` (a full
+    /// listing when the token is unknown or too old).
+    async fn list_workspace_files_delta(
+        &self,
+        workspace_id: String,
+        since_token: Option<String>,
+    ) -> Result<FileDelta, String> {
+        let root = {
+            let workspaces = self.workspaces.lock().await;
+            workspaces
+                .get(&workspace_id)
+                .map(|entry| PathBuf::from(&entry.path))
+                .ok_or("workspace not found")?
+        };
+        Ok(self
+            .file_indexes
+            .delta(&workspace_id, &root, since_token.as_deref()))
     }
 
     async fn read_workspace_file(
@@ -818,39 +3509,347 @@ impl DaemonState {
                 .ok_or("workspace not found")?
         };
 
-        let root = PathBuf::from(entry.path);
-        read_workspace_file_inner(&root, &path)
+        let root = PathBuf::from(entry.path);
+        read_workspace_file_inner(&root, &path)
+    }
+
+    async fn list_turn_artifacts(
+        &self,
+        workspace_id: &str,
+        turn_id: Option<&str>,
+    ) -> Result<Value, String> {
+        if !self.workspaces.lock().await.contains_key(workspace_id) {
+            return Err("workspace not found".to_string());
+        }
+        let artifacts = self.artifacts.list(workspace_id, turn_id);
+        Ok(json!({ "artifacts": artifacts }))
+    }
+
+    /// Reads a saved command output with the caps of `read_workspace_file`,
+    /// rooted at the workspace's artifacts dir.
+    async fn read_turn_artifact(
+        &self,
+        workspace_id: &str,
+        path: &str,
+    ) -> Result<WorkspaceFileResponse, String> {
+        if !self.workspaces.lock().await.contains_key(workspace_id) {
+            return Err("workspace not found".to_string());
+        }
+        read_workspace_file_inner(&self.artifacts.workspace_dir(workspace_id), path)
     }
 
     async fn start_thread(&self, workspace_id: String) -> Result<Value, String> {
+        let approval_policy = self
+            .workspaces
+            .lock()
+            .await
+            .get(&workspace_id)
+            .and_then(workspace_approval_policy);
         let session = self.get_session(&workspace_id).await?;
         let params = json!({
             "cwd": session.entry.path,
-            "approvalPolicy": "on-request"
+            "approvalPolicy": approval_policy.unwrap_or("on-request")
         });
         session.send_request("thread/start", params).await
     }
 
     async fn resume_thread(&self, workspace_id: String, thread_id: String) -> Result<Value, String> {
         let session = self.get_session(&workspace_id).await?;
-        let params = json!({
+        let mut params = json!({
             "threadId": thread_id
         });
+        // Imported threads aren't listed by the app-server, so point it at
+        // their rollout.
+        if let Some(imported) = self.imported_threads.find(&workspace_id, &thread_id) {
+            params["path"] = json!(imported.path);
+        }
         session.send_request("thread/resume", params).await
     }
 
+    /// Looks for threads in the workspace's CODEX_HOME rollouts, e.g. from
+    /// using the codex CLI directly, that `thread/list` doesn't return. Unless
+    /// `dry_run`, they are added to `list_threads`.
+    async fn import_threads(&self, workspace_id: String, dry_run: bool) -> Result<Value, String> {
+        let (entry, parent_path) = {
+            let workspaces = self.workspaces.lock().await;
+            let entry = workspaces
+                .get(&workspace_id)
+                .ok_or("workspace not found")?
+                .clone();
+            let parent_path = entry
+                .parent_id
+                .as_ref()
+                .and_then(|parent_id| workspaces.get(parent_id))
+                .map(|parent| parent.path.clone());
+            (entry, parent_path)
+        };
+        let codex_home = codex_home::resolve_workspace_codex_home(&entry, parent_path.as_deref())
+            .ok_or("Unable to resolve CODEX_HOME".to_string())?;
+        let workspace_path = PathBuf::from(expand_path_vars(&entry.path)?);
+
+        let session = self.get_session(&workspace_id).await?;
+        let mut known = self.imported_threads.ids(&workspace_id);
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_LISTED_PAGES {
+            let params = json!({ "cursor": cursor, "limit": MAX_THREAD_PAGE_SIZE });
+            let response = session.send_request("thread/list", params).await?;
+            let page = match normalize_thread_list(&response, cursor.is_some()) {
+                Ok(page) => page,
+                Err(ThreadListError::InvalidCursor(message) | ThreadListError::Failed(message)) => {
+                    return Err(message)
+                }
+            };
+            known.extend(
+                page.threads
+                    .iter()
+                    .filter_map(|thread| thread.get("id").and_then(Value::as_str))
+                    .map(str::to_string),
+            );
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        let (scanned, threads) = tokio::task::spawn_blocking(move || {
+            find_importable(&codex_home, &workspace_path, &known)
+        })
+        .await
+        .map_err(|err| err.to_string())?;
+        if !dry_run {
+            self.imported_threads.add(&workspace_id, &threads)?;
+        }
+        Ok(json!({
+            "dryRun": dry_run,
+            "scannedRollouts": scanned,
+            "threads": threads.iter().map(|thread| thread.to_thread()).collect::<Vec<_>>(),
+        }))
+    }
+
+    /// Resumes the workspace's most recent thread, or starts one when it has
+    /// none. Threads of other folders sharing the CODEX_HOME are paged past.
+    /// The response gets `resumed` to tell the two apart.
+    async fn resume_last_thread(&self, workspace_id: String) -> Result<Value, RpcError> {
+        let workspace_path = self
+            .workspaces
+            .lock()
+            .await
+            .get(&workspace_id)
+            .map(|entry| PathBuf::from(&entry.path))
+            .ok_or("workspace not found")?;
+        let mut newest = None;
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_LISTED_PAGES {
+            let page = self
+                .list_threads(workspace_id.clone(), cursor, Some(MAX_THREAD_PAGE_SIZE))
+                .await?;
+            newest = first_thread_in(&page.threads, &workspace_path);
+            cursor = page.next_cursor;
+            if newest.is_some() || cursor.is_none() {
+                break;
+            }
+        }
+        let (mut response, resumed) = match newest {
+            Some(thread_id) => (self.resume_thread(workspace_id, thread_id).await?, true),
+            None => (self.start_thread(workspace_id).await?, false),
+        };
+        if let Some(object) = response.as_object_mut() {
+            object.insert("resumed".to_string(), Value::Bool(resumed));
+        }
+        Ok(response)
+    }
+
+    /// One page of threads as `{ threads, nextCursor, fromCache }`. While the
+    /// workspace is disconnected the last listed threads are served instead.
     async fn list_threads(
         &self,
         workspace_id: String,
         cursor: Option<String>,
         limit: Option<u32>,
+    ) -> Result<ThreadPage, RpcError> {
+        let session = match self.get_session(&workspace_id).await {
+            Ok(session) => session,
+            Err(error) => {
+                let mut page = self
+                    .thread_list_cache
+                    .page(&workspace_id, cursor.is_some())
+                    .ok_or(error)?;
+                self.thread_reads.annotate(&workspace_id, &mut page.threads);
+                self.thread_titles
+                    .annotate(&workspace_id, &mut page.threads);
+                return Ok(page);
+            }
+        };
+        let position = list_position(cursor.as_deref()).map_err(|message| RpcError {
+            message: format!("{message}. Restart pagination without a cursor."),
+            code: Some("invalid_cursor"),
+            data: None,
+        })?;
+        let limit = clamp_page_size(limit);
+        let listed = if position.listed_done {
+            Ok(ThreadPage {
+                threads: Vec::new(),
+                next_cursor: None,
+                from_cache: false,
+            })
+        } else {
+            let params = json!({
+                "cursor": position.listed,
+                "limit": limit
+            });
+            let response = session.send_request("thread/list", params).await?;
+            normalize_thread_list(&response, position.listed.is_some())
+        };
+        match listed {
+            Ok(mut page) => {
+                self.imported_threads
+                    .merge(&workspace_id, &mut page, &position, limit as usize);
+                self.thread_list_cache
+                    .record(&workspace_id, cursor.is_none(), &page.threads);
+                self.thread_reads.annotate(&workspace_id, &mut page.threads);
+                self.thread_titles
+                    .annotate(&workspace_id, &mut page.threads);
+                Ok(page)
+            }
+            Err(ThreadListError::InvalidCursor(message)) => Err(RpcError {
+                message: format!("{message}. Restart pagination without a cursor."),
+                code: Some("invalid_cursor"),
+                data: None,
+            }),
+            Err(ThreadListError::Failed(message)) => Err(message.into()),
+        }
+    }
+
+    /// Latest turns and file changes in the workspace, newest first.
+    async fn workspace_activity(
+        &self,
+        workspace_id: &str,
+        limit: Option<u32>,
+        since_ms: Option<u64>,
+    ) -> Result<Vec<ActivityItem>, String> {
+        if !self.workspaces.lock().await.contains_key(workspace_id) {
+            return Err("workspace not found".to_string());
+        }
+        let limit = limit.map_or(DEFAULT_ACTIVITY_LIMIT, |limit| limit.max(1) as usize);
+        Ok(self.activity.recent(workspace_id, limit, since_ms))
+    }
+
+    /// Records how far `thread_id` (or, with `all`, every thread in the
+    /// workspace) has been read and tells the other clients.
+    async fn mark_thread_read(
+        &self,
+        workspace_id: String,
+        thread_id: Option<String>,
+        all: bool,
+        item_id: Option<String>,
+        timestamp: Option<u64>,
     ) -> Result<Value, String> {
-        let session = self.get_session(&workspace_id).await?;
-        let params = json!({
-            "cursor": cursor,
-            "limit": limit
-        });
-        session.send_request("thread/list", params).await
+        if !self.workspaces.lock().await.contains_key(&workspace_id) {
+            return Err("workspace not found".to_string());
+        }
+        let thread_id = if all { None } else { thread_id };
+        if !all && thread_id.is_none() {
+            return Err("threadId is required unless all is true.".to_string());
+        }
+        self.thread_reads.mark_read(
+            &workspace_id,
+            thread_id.as_deref(),
+            item_id.as_deref(),
+            timestamp,
+        )?;
+        let notice = ThreadReadNotice {
+            workspace_id,
+            thread_id,
+        };
+        self.event_sink.send(DaemonEvent::ThreadRead(notice));
+        Ok(json!({ "ok": true }))
+    }
+
+    /// Stores (or with an empty `title` clears) the thread's title and tells
+    /// every client.
+    async fn set_thread_title(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+        title: Option<String>,
+    ) -> Result<Value, String> {
+        if !self.workspaces.lock().await.contains_key(&workspace_id) {
+            return Err("workspace not found".to_string());
+        }
+        let stored = self.thread_titles.set(
+            &workspace_id,
+            &thread_id,
+            title.as_deref(),
+            TitleSource::Manual,
+        )?;
+        let notice = ThreadTitleNotice {
+            workspace_id,
+            thread_id,
+            title: stored.as_ref().map(|stored| stored.title.clone()),
+            source: TitleSource::Manual,
+        };
+        self.event_sink.send(DaemonEvent::ThreadTitle(notice));
+        Ok(json!({ "title": stored }))
+    }
+
+    /// Titles threads queued by the event sink, one at a time and at most one
+    /// per `AUTO_TITLE_INTERVAL`, while `autoThreadTitles` is on. Threads of
+    /// disconnected workspaces are skipped rather than spawning codex, and
+    /// those of paused workspaces because titling runs a turn.
+    async fn auto_title_threads(self: &Arc<Self>) {
+        loop {
+            let request = self.thread_titles.next_request().await;
+            if !self.app_settings.lock().await.auto_thread_titles
+                || self.paused.is_paused(&request.workspace_id)
+            {
+                continue;
+            }
+            let session = self
+                .sessions
+                .lock()
+                .await
+                .get(&request.workspace_id)
+                .cloned();
+            let Some(session) = session else {
+                continue;
+            };
+            let stored = match generate_title(&session, &request.first_message).await {
+                Ok(title) => self.thread_titles.set(
+                    &request.workspace_id,
+                    &request.thread_id,
+                    Some(&title),
+                    TitleSource::Auto,
+                ),
+                Err(error) => Err(error),
+            };
+            match stored {
+                Ok(Some(stored)) => {
+                    let notice = ThreadTitleNotice {
+                        workspace_id: request.workspace_id,
+                        thread_id: request.thread_id,
+                        title: Some(stored.title),
+                        source: TitleSource::Auto,
+                    };
+                    self.event_sink.send(DaemonEvent::ThreadTitle(notice));
+                }
+                Ok(None) => {}
+                Err(error) => {
+                    let error = NewError {
+                        thread_id: Some(&request.thread_id),
+                        ..NewError::new(
+                            ErrorSource::Scheduler,
+                            format!(
+                                "auto-title failed for thread {}: {error}",
+                                request.thread_id
+                            ),
+                        )
+                    };
+                    self.record_workspace_error(&request.workspace_id, error)
+                        .await;
+                }
+            }
+            tokio::time::sleep(AUTO_TITLE_INTERVAL).await;
+        }
     }
 
     async fn archive_thread(&self, workspace_id: String, thread_id: String) -> Result<Value, String> {
@@ -859,45 +3858,295 @@ impl DaemonState {
         session.send_request("thread/archive", params).await
     }
 
+    /// Starts a turn within the workspace's `maxConcurrentTurns` and the
+    /// daemon's per-session limit. Over the cap the message is rejected, or
+    /// with `queue` sent as soon as one of the running turns completes.
+    /// Paused workspaces refuse the message, queued or not. Invalid images
+    /// are dropped and listed in `rejectedImages`, or with `strict` fail the
+    /// whole call.
     async fn send_user_message(
-        &self,
+        self: &Arc<Self>,
         workspace_id: String,
-        thread_id: String,
-        text: String,
-        model: Option<String>,
-        effort: Option<String>,
-        access_mode: Option<String>,
-        images: Option<Vec<String>>,
-        collaboration_mode: Option<Value>,
+        mut message: UserMessage,
+        queue: bool,
+        strict: bool,
+    ) -> Result<Value, RpcError> {
+        let (workspace_cap, workspace_path) = {
+            let workspaces = self.workspaces.lock().await;
+            let entry = workspaces.get(&workspace_id).ok_or("workspace not found")?;
+            (
+                entry.settings.max_concurrent_turns,
+                PathBuf::from(&entry.path),
+            )
+        };
+        self.ensure_not_paused(&workspace_id)?;
+        let rejected = match &message.images {
+            Some(images) => {
+                let (accepted, rejected) =
+                    validate_images(images, &workspace_path, self.max_image_bytes);
+                message.images = Some(accepted);
+                rejected
+            }
+            None => Vec::new(),
+        };
+        if strict && !rejected.is_empty() {
+            return Err(RpcError {
+                message: format!("invalid images: {} rejected.", rejected.len()),
+                code: Some("invalid_images"),
+                data: Some(json!({ "rejectedImages": rejected })),
+            });
+        }
+        let cap = turn_cap(workspace_cap, self.max_turns_per_session);
+        if self.validate_models {
+            self.check_model(&workspace_id, &message)?;
+        }
+        if let Some(Value::String(name)) = &message.collaboration_mode {
+            let mode = self.collaboration_mode(&workspace_id, name).await?;
+            message.collaboration_mode = Some(mode);
+        }
+        match self.turns.try_start(&workspace_id, &message.thread_id, cap) {
+            Slot::Busy if queue => {
+                let position = self.turns.enqueue(&workspace_id);
+                let state = Arc::clone(self);
+                tokio::spawn(async move {
+                    let slot = state
+                        .turns
+                        .start_when_free(&workspace_id, &message.thread_id, cap)
+                        .await;
+                    let result = if state.paused.is_paused(&workspace_id) {
+                        if slot == Slot::Claimed {
+                            state.turns.finish(&workspace_id, &message.thread_id);
+                        }
+                        Err("workspace paused".to_string())
+                    } else {
+                        state.run_turn(&workspace_id, &message, slot).await
+                    };
+                    let notice = QueuedTurnNotice {
+                        workspace_id,
+                        thread_id: message.thread_id,
+                        error: result.err(),
+                    };
+                    state.event_sink.send(DaemonEvent::QueuedTurn(notice));
+                });
+                Ok(with_rejected_images(
+                    json!({ "queued": true, "position": position }),
+                    &rejected,
+                ))
+            }
+            Slot::Busy => {
+                let active = self.turns.active_count(&workspace_id);
+                if let Some(limit) = self.max_turns_per_session.filter(|limit| active >= *limit) {
+                    return Err(RpcError {
+                        message: format!(
+                            "too many turns: {active} of {limit} running in this session."
+                        ),
+                        code: Some("too_many_turns"),
+                        data: Some(json!({ "activeTurns": active, "maxTurnsPerSession": limit })),
+                    });
+                }
+                let limit = cap.unwrap_or_default();
+                Err(RpcError {
+                    message: format!("workspace busy: {active} of {limit} turns running."),
+                    code: Some("workspace_busy"),
+                    data: Some(json!({ "activeTurns": active, "maxConcurrentTurns": cap })),
+                })
+            }
+            slot => {
+                let response = self.run_turn(&workspace_id, &message, slot).await?;
+                Ok(with_rejected_images(response, &rejected))
+            }
+        }
+    }
+
+    /// Starts a thread and sends it a copy of `source` as its first message,
+    /// unlike a fork, which copies the whole history on the app-server.
+    /// `message` carries the turn options; its thread id and text are filled
+    /// in here.
+    async fn seed_thread(
+        self: &Arc<Self>,
+        workspace_id: String,
+        source: SeedSource,
+        mut message: UserMessage,
+        queue: bool,
+    ) -> Result<Value, RpcError> {
+        message.text = match &source {
+            SeedSource::Text { text, .. } => text.clone(),
+            SeedSource::Item { thread_id, item_id } => {
+                let resumed = self
+                    .resume_thread(workspace_id.clone(), thread_id.clone())
+                    .await?;
+                if let Some(error) = resumed.get("error") {
+                    return Err(format!("thread/resume failed: {error}").into());
+                }
+                item_text(&resumed, item_id)?
+            }
+        };
+        let started = self.start_thread(workspace_id.clone()).await?;
+        if let Some(error) = started.get("error") {
+            return Err(format!("thread/start failed: {error}").into());
+        }
+        message.thread_id = started_thread_id(&started)
+            .ok_or("thread/start returned no thread id")?
+            .to_string();
+        let thread_id = message.thread_id.clone();
+        let turn = self
+            .send_user_message(workspace_id, message, queue, false)
+            .await?;
+        Ok(json!({
+            "threadId": thread_id,
+            "turnId": started_turn_id(&turn),
+            "sourceThreadId": source.thread_id(),
+            "turn": turn,
+        }))
+    }
+
+    fn check_model(&self, workspace_id: &str, message: &UserMessage) -> Result<(), RpcError> {
+        let error = match self.models.check(
+            workspace_id,
+            message.model.as_deref(),
+            message.effort.as_deref(),
+        ) {
+            Ok(()) => return Ok(()),
+            Err(error) => error,
+        };
+        let (code, data) = match &error {
+            ModelCheckError::UnknownModel { model, known } => (
+                "unknown_model",
+                json!({ "model": model, "available": known }),
+            ),
+            ModelCheckError::UnsupportedEffort {
+                model,
+                effort,
+                supported,
+            } => (
+                "unsupported_effort",
+                json!({ "model": model, "effort": effort, "supported": supported }),
+            ),
+        };
+        Err(RpcError {
+            message: error.message(),
+            code: Some(code),
+            data: Some(data),
+        })
+    }
+
+    /// Sends `turn/start`, giving a newly claimed slot back if it fails.
+    async fn run_turn(
+        &self,
+        workspace_id: &str,
+        message: &UserMessage,
+        slot: Slot,
     ) -> Result<Value, String> {
-        let session = self.get_session(&workspace_id).await?;
-        let access_mode = access_mode.unwrap_or_else(|| "current".to_string());
-        let sandbox_policy = match access_mode.as_str() {
-            "full-access" => json!({
-                "type": "dangerFullAccess"
-            }),
-            "read-only" => json!({
-                "type": "readOnly"
-            }),
-            _ => json!({
-                "type": "workspaceWrite",
-                "writableRoots": [session.entry.path],
-                "networkAccess": true
-            }),
+        let result = self.start_turn(workspace_id, message).await;
+        let failed = match &result {
+            Ok(response) => response.get("error").is_some(),
+            Err(_) => true,
+        };
+        if failed && slot == Slot::Claimed {
+            self.turns.finish(workspace_id, &message.thread_id);
+        }
+        if let (false, Ok(response)) = (failed, &result) {
+            if let Some(turn_id) = started_turn_id(response) {
+                self.turns
+                    .record_turn_id(workspace_id, &message.thread_id, turn_id);
+            }
+            self.record_turn_input(workspace_id, message, response)
+                .await;
+        }
+        result
+    }
+
+    /// Keeps what a client sent to start a turn for `turn_inputs`, unless the
+    /// workspace has `disableTurnInputs` on.
+    async fn record_turn_input(&self, workspace_id: &str, message: &UserMessage, response: &Value) {
+        let Some(turn_id) = started_turn_id(response) else {
+            return;
+        };
+        let disabled = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .is_none_or(|entry| entry.settings.disable_turn_inputs);
+        if disabled {
+            return;
+        }
+        let input = TurnInput {
+            turn_id: turn_id.to_string(),
+            at: now_ms(),
+            text: message.text.clone(),
+            images: message.images.clone().unwrap_or_default(),
+            model: message.model.clone(),
+            effort: message.effort.clone(),
+            access_mode: response
+                .get("accessMode")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .or_else(|| message.access_mode.clone()),
+            collaboration_mode: message.collaboration_mode.clone(),
         };
+        if let Err(error) = self
+            .turn_inputs
+            .record(workspace_id, &message.thread_id, input)
+        {
+            let error = NewError {
+                thread_id: Some(&message.thread_id),
+                turn_id: Some(turn_id),
+                ..NewError::new(
+                    ErrorSource::Storage,
+                    format!("failed to save input of turn {turn_id}: {error}"),
+                )
+            };
+            self.record_workspace_error(workspace_id, error).await;
+        }
+    }
 
-        let approval_policy = if access_mode == "full-access" {
-            "never"
-        } else {
-            "on-request"
+    async fn turn_inputs(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        cursor: Option<&str>,
+        limit: Option<u32>,
+    ) -> Result<TurnInputPage, String> {
+        let disabled = self
+            .workspaces
+            .lock()
+            .await
+            .get(workspace_id)
+            .ok_or("workspace not found")?
+            .settings
+            .disable_turn_inputs;
+        if disabled {
+            return Ok(TurnInputPage {
+                inputs: Vec::new(),
+                next_cursor: None,
+                disabled: true,
+            });
+        }
+        self.turn_inputs
+            .page(workspace_id, thread_id, cursor, limit)
+    }
+
+    async fn start_turn(&self, workspace_id: &str, message: &UserMessage) -> Result<Value, String> {
+        let (access_mode, writable_roots, approval_policy) = {
+            let workspaces = self.workspaces.lock().await;
+            let entry = workspaces.get(workspace_id).ok_or("workspace not found")?;
+            (
+                effective_access_mode(message.access_mode.as_deref(), entry),
+                workspace_writable_roots(entry, &workspaces),
+                workspace_approval_policy(entry),
+            )
         };
+        let session = self.get_session(workspace_id).await?;
+        let policy = build_message_policy(Some(&access_mode), &session.entry.path, &writable_roots)
+            .with_approval_policy(approval_policy);
 
-        let trimmed_text = text.trim();
+        let trimmed_text = message.text.trim();
         let mut input: Vec<Value> = Vec::new();
         if !trimmed_text.is_empty() {
             input.push(json!({ "type": "text", "text": trimmed_text }));
         }
-        if let Some(paths) = images {
+        if let Some(paths) = &message.images {
             for path in paths {
                 let trimmed = path.trim();
                 if trimmed.is_empty() {
@@ -918,16 +4167,37 @@ impl DaemonState {
         }
 
         let params = json!({
-            "threadId": thread_id,
+            "threadId": message.thread_id,
             "input": input,
             "cwd": session.entry.path,
-            "approvalPolicy": approval_policy,
-            "sandboxPolicy": sandbox_policy,
-            "model": model,
-            "effort": effort,
-            "collaborationMode": collaboration_mode,
+            "approvalPolicy": policy.approval_policy,
+            "sandboxPolicy": policy.sandbox_policy,
+            "model": message.model,
+            "effort": message.effort,
+            "collaborationMode": message.collaboration_mode,
         });
-        session.send_request("turn/start", params).await
+        let mut response = session.send_request("turn/start", params).await?;
+        if let Some(object) = response.as_object_mut() {
+            object.insert("accessMode".to_string(), Value::String(access_mode));
+            object.insert("approvalPolicy".to_string(), json!(policy.approval_policy));
+        }
+        Ok(response)
+    }
+
+    async fn preview_message_policy(
+        &self,
+        workspace_id: String,
+        access_mode: Option<String>,
+    ) -> Result<Value, String> {
+        let workspaces = self.workspaces.lock().await;
+        let entry = workspaces.get(&workspace_id).ok_or("workspace not found")?;
+        let writable_roots = workspace_writable_roots(entry, &workspaces);
+        let access_mode = effective_access_mode(access_mode.as_deref(), entry);
+        let mut policy = build_message_policy(Some(&access_mode), &entry.path, &writable_roots)
+            .with_approval_policy(workspace_approval_policy(entry))
+            .to_value();
+        policy["accessMode"] = Value::String(access_mode);
+        Ok(policy)
     }
 
     async fn turn_interrupt(
@@ -944,13 +4214,35 @@ impl DaemonState {
         session.send_request("turn/interrupt", params).await
     }
 
+    /// Resolves once the turn ends, with how it ended. Fails with
+    /// `wait_timeout` if it still runs after the timeout.
+    async fn wait_turn(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        turn_id: &str,
+        timeout_ms: Option<u64>,
+    ) -> Result<TurnOutcome, RpcError> {
+        let limit = wait_turn_timeout(timeout_ms);
+        let timeout_ms = u64::try_from(limit.as_millis()).unwrap_or(u64::MAX);
+        self.turns
+            .wait_for_end(workspace_id, thread_id, turn_id, limit)
+            .await?
+            .ok_or_else(|| RpcError {
+                message: format!("turn {turn_id} still running after {timeout_ms} ms."),
+                code: Some("wait_timeout"),
+                data: Some(json!({ "timeoutMs": timeout_ms })),
+            })
+    }
+
     async fn start_review(
         &self,
         workspace_id: String,
         thread_id: String,
         target: Value,
         delivery: Option<String>,
-    ) -> Result<Value, String> {
+    ) -> Result<Value, RpcError> {
+        self.ensure_not_paused(&workspace_id)?;
         let session = self.get_session(&workspace_id).await?;
         let mut params = Map::new();
         params.insert("threadId".to_string(), json!(thread_id));
@@ -958,14 +4250,22 @@ impl DaemonState {
         if let Some(delivery) = delivery {
             params.insert("delivery".to_string(), json!(delivery));
         }
-        session
+        Ok(session
             .send_request("review/start", Value::Object(params))
-            .await
+            .await?)
     }
 
     async fn model_list(&self, workspace_id: String) -> Result<Value, String> {
         let session = self.get_session(&workspace_id).await?;
-        session.send_request("model/list", json!({})).await
+        let response = session.send_request("model/list", json!({})).await?;
+        self.models.record(&workspace_id, &response);
+        Ok(response)
+    }
+
+    /// `model_list` mapped to a stable shape with capability flags.
+    async fn models_detailed(&self, workspace_id: String) -> Result<Vec<ModelDetails>, String> {
+        let response = self.model_list(workspace_id).await?;
+        model_details(&response)
     }
 
     async fn collaboration_mode_list(&self, workspace_id: String) -> Result<Value, String> {
@@ -975,6 +4275,25 @@ impl DaemonState {
             .await
     }
 
+    /// Expands a collaboration mode name into its `collaborationMode/list`
+    /// entry.
+    async fn collaboration_mode(&self, workspace_id: &str, name: &str) -> Result<Value, RpcError> {
+        let list = self
+            .collaboration_mode_list(workspace_id.to_string())
+            .await?;
+        if let Some(error) = list.get("error") {
+            return Err(format!("collaborationMode/list failed: {error}").into());
+        }
+        resolve_collaboration_mode(&list, name).map_err(|available| RpcError {
+            message: format!(
+                "unknown collaboration mode \"{name}\"; available: {}",
+                available.join(", ")
+            ),
+            code: Some("unknown_collaboration_mode"),
+            data: Some(json!({ "mode": name, "available": available })),
+        })
+    }
+
     async fn account_rate_limits(&self, workspace_id: String) -> Result<Value, String> {
         let session = self.get_session(&workspace_id).await?;
         session
@@ -982,6 +4301,45 @@ impl DaemonState {
             .await
     }
 
+    /// Reads the account rate limits of every connected session and sends
+    /// `rate-limit-update` for those that changed since the last poll. Polls
+    /// go straight to the session so they don't count as activity.
+    async fn poll_rate_limits(&self) {
+        let sessions: Vec<(String, Arc<WorkspaceSession>)> = self
+            .sessions
+            .lock()
+            .await
+            .iter()
+            .map(|(id, session)| (id.clone(), Arc::clone(session)))
+            .collect();
+        for (workspace_id, session) in sessions {
+            let response = match session
+                .send_request("account/rateLimits/read", Value::Null)
+                .await
+            {
+                Ok(response) => response,
+                Err(error) => {
+                    let error = NewError::new(
+                        ErrorSource::Scheduler,
+                        format!("rate limit poll failed: {error}"),
+                    );
+                    self.record_workspace_error(&workspace_id, error).await;
+                    continue;
+                }
+            };
+            let Some(rate_limits) = rate_limits_of(&response) else {
+                continue;
+            };
+            if let Some(update) = self.rate_limit_watch.record(
+                &workspace_id,
+                rate_limits,
+                self.rate_limit_warn_percent,
+            ) {
+                self.event_sink.send(DaemonEvent::RateLimitUpdate(update));
+            }
+        }
+    }
+
     async fn skills_list(&self, workspace_id: String) -> Result<Value, String> {
         let session = self.get_session(&workspace_id).await?;
         let params = json!({
@@ -997,6 +4355,7 @@ impl DaemonState {
         result: Value,
     ) -> Result<Value, String> {
         let session = self.get_session(&workspace_id).await?;
+        self.idle.record_response(&workspace_id, &request_id);
         session.send_response(request_id, result).await?;
         Ok(json!({ "ok": true }))
     }
@@ -1015,34 +4374,192 @@ impl DaemonState {
             return Err("empty command".to_string());
         }
 
-        let (entry, parent_path) = {
+        let (entry, parent_path) = {
+            let workspaces = self.workspaces.lock().await;
+            let entry = workspaces
+                .get(&workspace_id)
+                .ok_or("workspace not found")?
+                .clone();
+            let parent_path = entry
+                .parent_id
+                .as_ref()
+                .and_then(|parent_id| workspaces.get(parent_id))
+                .map(|parent| parent.path.clone());
+            (entry, parent_path)
+        };
+
+        let codex_home = codex_home::resolve_workspace_codex_home(&entry, parent_path.as_deref())
+            .ok_or("Unable to resolve CODEX_HOME".to_string())?;
+        let rules_path = rules::default_rules_path(&codex_home);
+        rules::append_prefix_rule(&rules_path, &command)?;
+
+        Ok(json!({
+            "ok": true,
+            "rulesPath": rules_path,
+        }))
+    }
+
+    /// Compares stored entries with the filesystem and `git worktree list` of
+    /// each main workspace, recording the outcome on every entry. Nothing is
+    /// repaired here.
+    async fn verify_workspaces(&self) -> Vec<WorkspaceInfo> {
+        let entries = {
+            let workspaces = self.workspaces.lock().await;
+            workspaces.values().cloned().collect::<Vec<_>>()
+        };
+
+        let mut git_worktrees: HashMap<String, Vec<GitWorktreeRecord>> = HashMap::new();
+        for entry in entries.iter().filter(|entry| !entry.kind.is_worktree()) {
+            let repo_path = PathBuf::from(&entry.path);
+            if !repo_path.is_dir() {
+                continue;
+            }
+            if let Ok(records) = git_list_worktrees(&repo_path).await {
+                git_worktrees.insert(entry.id.clone(), records);
+            }
+        }
+
+        let statuses: HashMap<String, WorkspaceEntryStatus> = entries
+            .iter()
+            .map(|entry| {
+                let records = entry
+                    .parent_id
+                    .as_ref()
+                    .and_then(|parent_id| git_worktrees.get(parent_id));
+                (entry.id.clone(), workspace_entry_status(entry, records))
+            })
+            .collect();
+
+        {
+            let mut workspaces = self.workspaces.lock().await;
+            for (id, status) in statuses {
+                if let Some(entry) = workspaces.get_mut(&id) {
+                    entry.status = Some(status);
+                }
+            }
+        }
+        self.list_workspaces().await
+    }
+
+    async fn gc_worktrees(
+        &self,
+        apply: bool,
+        authorize: impl Fn(&str) -> Result<(), String>,
+    ) -> WorktreeGcReport {
+        let entries = {
+            let workspaces = self.workspaces.lock().await;
+            workspaces.values().cloned().collect::<Vec<_>>()
+        };
+        let worktrees_root = self.data_dir.join("worktrees");
+        worktree_gc::collect_worktree_gc(&worktrees_root, &entries, apply, authorize).await
+    }
+
+    async fn report_orphaned_worktrees(&self) {
+        let report = self.gc_worktrees(false, |_| Ok(())).await;
+        if report.orphans.is_empty() {
+            return;
+        }
+        eprintln!(
+            "codex-monitor-daemon: {} orphaned worktree folder(s), {} bytes reclaimable (run gc_worktrees with apply: true)",
+            report.orphans.len(),
+            report.reclaimable_bytes
+        );
+        self.event_sink.send(DaemonEvent::WorktreeGc(report));
+    }
+
+    async fn size_data_dir(&self, progress: &OperationProgress) -> DataDirReport {
+        let entries = {
             let workspaces = self.workspaces.lock().await;
-            let entry = workspaces
-                .get(&workspace_id)
-                .ok_or("workspace not found")?
-                .clone();
-            let parent_path = entry
-                .parent_id
-                .as_ref()
-                .and_then(|parent_id| workspaces.get(parent_id))
-                .map(|parent| parent.path.clone());
-            (entry, parent_path)
+            workspaces.values().cloned().collect::<Vec<_>>()
         };
+        let access_log = self
+            .access_log
+            .as_ref()
+            .map(|log| log.paths().to_vec())
+            .unwrap_or_default();
+        collect_data_usage(&self.data_dir, &entries, &access_log, progress).await
+    }
 
-        let codex_home = codex_home::resolve_workspace_codex_home(&entry, parent_path.as_deref())
-            .ok_or("Unable to resolve CODEX_HOME".to_string())?;
-        let rules_path = rules::default_rules_path(&codex_home);
-        rules::append_prefix_rule(&rules_path, &command)?;
+    /// Sizes the data dir, then runs the requested cleanup. Orphans are only
+    /// removed under the same checks as `gc_worktrees`, parent folders only
+    /// when empty.
+    async fn data_dir_report(
+        &self,
+        cleanup: BTreeSet<CleanupCategory>,
+        progress: &OperationProgress,
+        authorize: impl Fn(&str) -> Result<(), String>,
+    ) -> DataDirReport {
+        let mut report = self.size_data_dir(progress).await;
+        if cleanup.is_empty() {
+            return report;
+        }
+        let mut summary = CleanupSummary::default();
+        for category in cleanup {
+            match category {
+                CleanupCategory::OrphanedWorktrees => {
+                    progress.report("removing orphaned worktrees", None, None);
+                    let gc = self.gc_worktrees(true, &authorize).await;
+                    summary.reclaimed_bytes += gc
+                        .orphans
+                        .iter()
+                        .filter(|orphan| gc.removed.contains(&orphan.path))
+                        .map(|orphan| orphan.size_bytes)
+                        .sum::<u64>();
+                    summary.removed.extend(gc.removed);
+                    summary.failures.extend(gc.failures);
+                }
+                CleanupCategory::EmptyWorktreeDirs => {
+                    progress.report("removing empty worktree folders", None, None);
+                    for parent in &report.parents {
+                        if std::fs::remove_dir(&parent.path).is_ok() {
+                            summary.removed.push(parent.path.clone());
+                        }
+                    }
+                }
+                CleanupCategory::Trash => {
+                    progress.report("emptying trash", None, None);
+                    let trash_dir = trash_root(&self.data_dir);
+                    let trash_bytes = report
+                        .categories
+                        .iter()
+                        .find(|category| category.category == "trash")
+                        .map_or(0, |category| category.usage.size_bytes);
+                    match self.empty_trash(None).await {
+                        Ok(removed) => {
+                            summary.reclaimed_bytes += trash_bytes;
+                            summary.removed.extend(
+                                removed
+                                    .iter()
+                                    .map(|id| trash_dir.join(id).display().to_string()),
+                            );
+                        }
+                        Err(error) => summary.failures.push(WorktreeGcFailure {
+                            path: trash_dir.display().to_string(),
+                            error,
+                        }),
+                    }
+                }
+            }
+        }
+        report.cleanup = Some(summary);
+        report
+    }
 
-        Ok(json!({
-            "ok": true,
-            "rulesPath": rules_path,
-        }))
+    /// Sizes the state files, worktrees and other folders of the data dir
+    /// without cleaning anything up.
+    async fn disk_usage(&self) -> Result<DiskUsageReport, String> {
+        let report = self.size_data_dir(&OperationProgress::detached()).await;
+        tokio::task::spawn_blocking(move || DiskUsageReport::from(report))
+            .await
+            .map_err(|err| err.to_string())
     }
 }
 
 fn sort_workspaces(workspaces: &mut [WorkspaceInfo]) {
     workspaces.sort_by(|a, b| {
+        if a.settings.pinned != b.settings.pinned {
+            return b.settings.pinned.cmp(&a.settings.pinned);
+        }
         let a_order = a.settings.sort_order.unwrap_or(u32::MAX);
         let b_order = b.settings.sort_order.unwrap_or(u32::MAX);
         if a_order != b_order {
@@ -1059,16 +4576,32 @@ fn should_skip_dir(name: &str) -> bool {
     )
 }
 
-fn normalize_git_path(path: &str) -> String {
-    path.replace('\\', "/")
+/// Most files a workspace listing returns.
+const MAX_WORKSPACE_FILES: usize = 20000;
+
+/// The walk behind workspace listings: hidden files are included and ignore
+/// files apply even outside a git repo.
+fn workspace_walker(root: &Path, follow_links: bool) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .hidden(false)
+        .follow_links(follow_links)
+        .require_git(false);
+    builder
 }
 
-fn list_workspace_files_inner(root: &PathBuf, max_files: usize) -> Vec<String> {
+fn list_workspace_files_inner(
+    root: &PathBuf,
+    max_files: usize,
+    follow_links: bool,
+) -> Vec<WorkspaceFileEntry> {
+    let canonical_root = if follow_links {
+        root.canonicalize().ok()
+    } else {
+        None
+    };
     let mut results = Vec::new();
-    let walker = WalkBuilder::new(root)
-        .hidden(false)
-        .follow_links(false)
-        .require_git(false)
+    let walker = workspace_walker(root, follow_links)
         .filter_entry(|entry| {
             if entry.depth() == 0 {
                 return true;
@@ -1092,7 +4625,15 @@ fn list_workspace_files_inner(root: &PathBuf, max_files: usize) -> Vec<String> {
         if let Ok(rel_path) = entry.path().strip_prefix(root) {
             let normalized = normalize_git_path(&rel_path.to_string_lossy());
             if !normalized.is_empty() {
-                results.push(normalized);
+                let link_target = canonical_root.as_ref().and_then(|canonical_root| {
+                    resolve_link_target(entry.path(), canonical_root, rel_path)
+                });
+                results.push(WorkspaceFileEntry {
+                    path: normalized,
+                    via_symlink: link_target.is_some(),
+                    link_target_in_root: link_target.as_ref().is_some_and(|(_, in_root)| *in_root),
+                    link_target: link_target.map(|(target, _)| target),
+                });
             }
         }
         if results.len() >= max_files {
@@ -1100,16 +4641,35 @@ fn list_workspace_files_inner(root: &PathBuf, max_files: usize) -> Vec<String> {
         }
     }
 
-    results.sort();
+    results.sort_by(|a, b| a.path.cmp(&b.path));
     results
 }
 
+/// Returns the resolved target of a path reached through a symlink, or `None`
+/// when the canonical location matches the listed location.
+fn resolve_link_target(
+    path: &Path,
+    canonical_root: &Path,
+    rel_path: &Path,
+) -> Option<(String, bool)> {
+    let canonical = path.canonicalize().ok()?;
+    if canonical == canonical_root.join(rel_path) {
+        return None;
+    }
+    match canonical.strip_prefix(canonical_root) {
+        Ok(inside) => Some((normalize_git_path(&inside.to_string_lossy()), true)),
+        Err(_) => Some((canonical.to_string_lossy().to_string(), false)),
+    }
+}
+
 const MAX_WORKSPACE_FILE_BYTES: u64 = 400_000;
 
-fn read_workspace_file_inner(
-    root: &PathBuf,
+/// Resolves a path inside the workspace to a regular file; links leading
+/// out of the workspace are refused.
+fn resolve_workspace_file(
+    root: &Path,
     relative_path: &str,
-) -> Result<WorkspaceFileResponse, String> {
+) -> Result<(PathBuf, std::fs::Metadata), String> {
     let canonical_root = root
         .canonicalize()
         .map_err(|err| format!("Failed to resolve workspace root: {err}"))?;
@@ -1117,7 +4677,7 @@ fn read_workspace_file_inner(
     let canonical_path = candidate
         .canonicalize()
         .map_err(|err| format!("Failed to open file: {err}"))?;
-    if !canonical_path.starts_with(&canonical_root) {
+    if !is_path_within(&canonical_root, &canonical_path) {
         return Err("Invalid file path".to_string());
     }
     let metadata = std::fs::metadata(&canonical_path)
@@ -1125,7 +4685,14 @@ fn read_workspace_file_inner(
     if !metadata.is_file() {
         return Err("Path is not a file".to_string());
     }
+    Ok((canonical_path, metadata))
+}
 
+fn read_workspace_file_inner(
+    root: &PathBuf,
+    relative_path: &str,
+) -> Result<WorkspaceFileResponse, String> {
+    let (canonical_path, _) = resolve_workspace_file(root, relative_path)?;
     let mut file =
         File::open(&canonical_path).map_err(|err| format!("Failed to open file: {err}"))?;
     let mut buffer = Vec::new();
@@ -1143,39 +4710,60 @@ fn read_workspace_file_inner(
     Ok(WorkspaceFileResponse { content, truncated })
 }
 
-async fn run_git_command(repo_path: &PathBuf, args: &[&str]) -> Result<String, String> {
-    let output = Command::new("git")
-        .args(args)
-        .current_dir(repo_path)
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run git: {e}"))?;
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+/// Maps a cherry-pick failure to an RPC error; conflicts carry the
+/// `cherry_pick_conflict` code and the checkout's conflict status.
+async fn cherry_pick_rpc_error(repo_path: &PathBuf, error: CherryPickError) -> RpcError {
+    let (commit, landed, message) = match error {
+        CherryPickError::Failed(message) => return message.into(),
+        CherryPickError::Conflict {
+            commit,
+            landed,
+            message,
+        } => (commit, landed, message),
+    };
+    let conflicts = match conflict_status(repo_path).await {
+        Ok(conflicts) => conflicts,
+        Err(error) => return error.into(),
+    };
+    RpcError {
+        message: format!("Cherry-pick of {commit} stopped on conflicts: {message}"),
+        code: Some("cherry_pick_conflict"),
+        data: Some(json!({
+            "commit": commit,
+            "landed": landed,
+            "conflicts": conflicts,
+        })),
+    }
+}
+
+/// Id of the main workspace whose repo `entry` lives in.
+fn repo_id(entry: &WorkspaceEntry) -> &str {
+    if entry.kind.is_worktree() {
+        entry.parent_id.as_deref().unwrap_or(&entry.id)
     } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let detail = if stderr.trim().is_empty() {
-            stdout.trim()
-        } else {
-            stderr.trim()
-        };
-        if detail.is_empty() {
-            Err("Git command failed.".to_string())
-        } else {
-            Err(detail.to_string())
-        }
+        &entry.id
     }
 }
 
+async fn run_git_command(repo_path: &PathBuf, args: &[&str]) -> Result<String, String> {
+    let stdout = run_git(repo_path, args).await?;
+    Ok(String::from_utf8_lossy(&stdout).trim().to_string())
+}
+
 fn is_missing_worktree_error(error: &str) -> bool {
-    error.contains("is not a working tree")
+    classify_git_error(error) == Some(GitErrorKind::NotAWorkingTree)
+}
+
+fn comparable_path(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
 }
 
 async fn git_branch_exists(repo_path: &PathBuf, branch: &str) -> Result<bool, String> {
-    let status = Command::new("git")
+    let status = git_command()
         .args(["show-ref", "--verify", &format!("refs/heads/{branch}")])
         .current_dir(repo_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
         .status()
         .await
         .map_err(|e| format!("Failed to run git: {e}"))?;
@@ -1183,9 +4771,11 @@ async fn git_branch_exists(repo_path: &PathBuf, branch: &str) -> Result<bool, St
 }
 
 async fn git_remote_exists(repo_path: &PathBuf, remote: &str) -> Result<bool, String> {
-    let status = Command::new("git")
+    let status = git_command()
         .args(["remote", "get-url", remote])
         .current_dir(repo_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
         .status()
         .await
         .map_err(|e| format!("Failed to run git: {e}"))?;
@@ -1197,7 +4787,7 @@ async fn git_remote_branch_exists_live(
     remote: &str,
     branch: &str,
 ) -> Result<bool, String> {
-    let output = Command::new("git")
+    let output = git_command()
         .args([
             "ls-remote",
             "--heads",
@@ -1227,13 +4817,15 @@ async fn git_remote_branch_exists_live(
 }
 
 async fn git_remote_branch_exists(repo_path: &PathBuf, remote: &str, branch: &str) -> Result<bool, String> {
-    let status = Command::new("git")
+    let status = git_command()
         .args([
             "show-ref",
             "--verify",
             &format!("refs/remotes/{remote}/{branch}"),
         ])
         .current_dir(repo_path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
         .status()
         .await
         .map_err(|e| format!("Failed to run git: {e}"))?;
@@ -1245,30 +4837,45 @@ async fn unique_branch_name(
     desired: &str,
     remote: Option<&str>,
 ) -> Result<(String, bool), String> {
-    let mut candidate = desired.to_string();
     if desired.is_empty() {
-        return Ok((candidate, false));
-    }
-    if !git_branch_exists(repo_path, &candidate).await?
-        && match remote {
-            Some(remote) => !git_remote_branch_exists_live(repo_path, remote, &candidate).await?,
-            None => true,
-        }
-    {
-        return Ok((candidate, false));
+        return Ok((desired.to_string(), false));
     }
-    for index in 2..1000 {
-        candidate = format!("{desired}-{index}");
-        let local_exists = git_branch_exists(repo_path, &candidate).await?;
-        let remote_exists = match remote {
-            Some(remote) => git_remote_branch_exists_live(repo_path, remote, &candidate).await?,
-            None => false,
-        };
-        if !local_exists && !remote_exists {
-            return Ok((candidate, true));
-        }
+    let mut taken = git_list_branches(repo_path).await?;
+    if let Some(remote) = remote {
+        taken.extend(git_list_remote_heads(repo_path, remote).await?);
     }
-    Err("Unable to find an available branch name.".to_string())
+    let candidate = first_free_name(desired, |name| taken.contains(name));
+    let renamed = candidate != desired;
+    Ok((candidate, renamed))
+}
+
+/// Local branch names, listed once so picking a free name needs no git call
+/// per candidate.
+async fn git_list_branches(repo_path: &PathBuf) -> Result<HashSet<String>, String> {
+    let output = run_git_command(
+        repo_path,
+        &["for-each-ref", "--format=%(refname)", "refs/heads"],
+    )
+    .await?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("refs/heads/"))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Branch names on `remote` as `git ls-remote --heads` reports them.
+async fn git_list_remote_heads(
+    repo_path: &PathBuf,
+    remote: &str,
+) -> Result<HashSet<String>, String> {
+    let output = run_git_command(repo_path, &["ls-remote", "--heads", remote]).await?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(_, reference)| reference.trim().strip_prefix("refs/heads/"))
+        .map(str::to_string)
+        .collect())
 }
 
 async fn git_list_remotes(repo_path: &PathBuf) -> Result<Vec<String>, String> {
@@ -1320,85 +4927,76 @@ async fn git_find_remote_tracking_branch(repo_path: &PathBuf, branch: &str) -> R
     Ok(None)
 }
 
-fn sanitize_worktree_name(branch: &str) -> String {
-    let mut result = String::new();
-    for ch in branch.chars() {
-        if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.') {
-            result.push(ch);
-        } else {
-            result.push('-');
-        }
-    }
-    let trimmed = result.trim_matches('-').to_string();
-    if trimmed.is_empty() {
-        "worktree".to_string()
-    } else {
-        trimmed
+const DATA_DIR_NAME: &str = "codex-monitor-daemon";
+
+fn default_data_dir() -> PathBuf {
+    let platform_dir = platform_data_dir();
+    let legacy_dir = legacy_data_dir();
+    // Earlier builds used the XDG layout everywhere; keep using it when it
+    // already holds data and nothing exists at the platform location yet.
+    if platform_dir != legacy_dir
+        && !platform_dir.exists()
+        && legacy_dir.join("workspaces.json").exists()
+    {
+        return legacy_dir;
     }
+    platform_dir
 }
 
-fn unique_worktree_path(base_dir: &PathBuf, name: &str) -> Result<PathBuf, String> {
-    let candidate = base_dir.join(name);
-    if !candidate.exists() {
-        return Ok(candidate);
-    }
+#[cfg(target_os = "macos")]
+fn platform_data_dir() -> PathBuf {
+    home_dir()
+        .join("Library")
+        .join("Application Support")
+        .join(DATA_DIR_NAME)
+}
 
-    for index in 2..1000 {
-        let next = base_dir.join(format!("{name}-{index}"));
-        if !next.exists() {
-            return Ok(next);
+#[cfg(target_os = "windows")]
+fn platform_data_dir() -> PathBuf {
+    if let Ok(app_data) = env::var("APPDATA") {
+        let trimmed = app_data.trim();
+        if !trimmed.is_empty() {
+            return PathBuf::from(trimmed).join(DATA_DIR_NAME);
         }
     }
-
-    Err(format!(
-        "Failed to find an available worktree path under {}.",
-        base_dir.display()
-    ))
+    home_dir()
+        .join("AppData")
+        .join("Roaming")
+        .join(DATA_DIR_NAME)
 }
 
-fn unique_worktree_path_for_rename(
-    base_dir: &PathBuf,
-    name: &str,
-    current_path: &PathBuf,
-) -> Result<PathBuf, String> {
-    let candidate = base_dir.join(name);
-    if candidate == *current_path {
-        return Ok(candidate);
-    }
-    if !candidate.exists() {
-        return Ok(candidate);
-    }
-    for index in 2..1000 {
-        let next = base_dir.join(format!("{name}-{index}"));
-        if next == *current_path || !next.exists() {
-            return Ok(next);
-        }
-    }
-    Err(format!(
-        "Failed to find an available worktree path under {}.",
-        base_dir.display()
-    ))
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn platform_data_dir() -> PathBuf {
+    legacy_data_dir()
 }
 
-fn default_data_dir() -> PathBuf {
+fn legacy_data_dir() -> PathBuf {
     if let Ok(xdg) = env::var("XDG_DATA_HOME") {
         let trimmed = xdg.trim();
         if !trimmed.is_empty() {
-            return PathBuf::from(trimmed).join("codex-monitor-daemon");
+            return PathBuf::from(trimmed).join(DATA_DIR_NAME);
+        }
+    }
+    home_dir().join(".local").join("share").join(DATA_DIR_NAME)
+}
+
+fn home_dir() -> PathBuf {
+    for key in ["HOME", "USERPROFILE"] {
+        if let Ok(value) = env::var(key) {
+            if !value.trim().is_empty() {
+                return PathBuf::from(value);
+            }
         }
     }
-    let home = env::var("HOME").unwrap_or_else(|_| ".".to_string());
-    PathBuf::from(home)
-        .join(".local")
-        .join("share")
-        .join("codex-monitor-daemon")
+    PathBuf::from(".")
 }
 
 fn usage() -> String {
+    let webhook_events = DEFAULT_WEBHOOK_EVENTS.join(",");
     format!(
         "\
-USAGE:\n  codex-monitor-daemon [--listen <addr>] [--data-dir <path>] [--token <token> | --insecure-no-auth]\n\n\
-OPTIONS:\n  --listen <addr>        Bind address (default: {DEFAULT_LISTEN_ADDR})\n  --data-dir <path>      Data dir holding workspaces.json/settings.json\n  --token <token>        Shared token required by clients\n  --insecure-no-auth      Disable auth (dev only)\n  -h, --help             Show this help\n"
+USAGE:\n  codex-monitor-daemon [--listen <addr> | --stdio] [--listen-http <addr> [--metrics-no-auth]] [--data-dir <path>] [--token <token> | --insecure-no-auth]\n                       [--rate-limit <req/s>] [--rate-limit-expensive <req/s>]\n                       [--pid-file <path>] [--daemonize | --foreground]\n                       [--access-log <path>] [--access-log-max-bytes <bytes>]\n                       [--auth-policy <path>] [--event-buffer <events>]\n                       [--max-request-bytes <bytes>] [--max-response-bytes <bytes>]\n                       [--max-outbound-queue-bytes <bytes>] [--validate-models]\n                       [--max-turns-per-session <turns>] [--max-concurrent-spawns <n>]\n                       [--max-image-bytes <bytes>]\n                       [--poll-rate-limits <secs> [--rate-limit-warn-percent <percent>]]\n                       [--allow-purge] [--read-only]\n                       [--mirror <addr> [--mirror-token <token>]]\n                       [--webhook-url <url> [--webhook-events <methods>] [--webhook-secret <secret>]]\n                       [--webhook-hosts <hosts>]\n\n\
+OPTIONS:\n  --listen <addr>        Bind address (default: {DEFAULT_LISTEN_ADDR})\n  --stdio                Serve one client on stdin/stdout instead; no token needed unless set\n  --listen-http <addr>   Also serve read-only HTTP routes, a /events SSE stream and /metrics here\n  --metrics-no-auth      Serve /metrics without the token\n  --data-dir <path>      Data dir holding workspaces.json/settings.json (default: platform data dir)\n  --token <token>        Shared token required by clients\n  --insecure-no-auth      Disable auth (dev only)\n  --rate-limit <req/s>   Per-connection request cap, 0 disables limiting (default: {DEFAULT_REQUESTS_PER_SEC})\n  --rate-limit-expensive <req/s>\n                         Per-connection cap for file walks and git network calls (default: {DEFAULT_EXPENSIVE_REQUESTS_PER_SEC})\n  --pid-file <path>      Write the PID here; refuses to start if it names a running daemon\n  --daemonize            Fork into the background, stdio goes to /dev/null (Unix only)\n  --foreground           Stay attached to the terminal (default)\n  --access-log <path>    Append one JSON line per RPC (no params) for auditing\n  --access-log-max-bytes <bytes>\n                         Rotate the access log to <path>.1 past this size (default: {DEFAULT_ACCESS_LOG_MAX_BYTES})\n  --auth-policy <path>   JSON file with allow/deny rules per method, workspace, token and peer\n  --event-buffer <events>\n                         Events buffered for slow clients before they are dropped (default: {DEFAULT_EVENT_BUFFER})\n  --max-request-bytes <bytes>\n                         Longest accepted request line; longer ones get a request_too_large error (default: {DEFAULT_MAX_REQUEST_BYTES})\n  --max-response-bytes <bytes>\n                         Largest response; big arrays are truncated to fit (default: {DEFAULT_MAX_RESPONSE_BYTES})\n  --max-outbound-queue-bytes <bytes>\n                         Unwritten output per connection before it is closed (default: {DEFAULT_MAX_OUTBOUND_QUEUE_BYTES})\n  --validate-models      Reject send_user_message models/efforts missing from the cached model_list\n  --max-turns-per-session <turns>\n                         Running turns per codex session, 0 disables the limit (default: {DEFAULT_MAX_TURNS_PER_SESSION})\n  --max-concurrent-spawns <n>\n                         Codex processes started at once; more connects wait in line (default: {DEFAULT_MAX_CONCURRENT_SPAWNS})\n  --max-image-bytes <bytes>\n                         Largest local image send_user_message accepts (default: {DEFAULT_MAX_IMAGE_BYTES})\n  --poll-rate-limits <secs>\n                         Poll connected sessions' account rate limits and send rate-limit-update events (minimum 60)\n  --rate-limit-warn-percent <percent>\n                         Usage from which those events are nearLimit (default: {DEFAULT_RATE_LIMIT_WARN_PERCENT})\n  --allow-purge          Enable the purge_data factory-reset RPC\n  --read-only            Refuse every request that would change workspaces, repos or daemon state\n  --mirror <addr>        Read-only mirror of the daemon at <addr>; never spawns codex\n  --mirror-token <token> Token for the primary (or set CODEX_MONITOR_MIRROR_TOKEN)\n  --webhook-url <url>    POST selected events to <url> as JSON, retrying failures\n  --webhook-events <methods>\n                         Comma-separated event methods to post (default: {webhook_events})\n  --webhook-secret <secret>\n                         Sign posts with an HMAC-SHA256 of the timestamp and body (or set CODEX_MONITOR_WEBHOOK_SECRET)\n  --webhook-hosts <hosts>\n                         Comma-separated hosts set_webhook endpoints may post to (default: none)\n  -h, --help             Show this help\n"
     )
 }
 
@@ -1406,12 +5004,43 @@ fn parse_args() -> Result<DaemonConfig, String> {
     let mut listen = DEFAULT_LISTEN_ADDR
         .parse::<SocketAddr>()
         .map_err(|err| err.to_string())?;
+    let mut listen_http: Option<SocketAddr> = None;
+    let mut metrics_no_auth = false;
+    let mut stdio = false;
     let mut token = env::var("CODEX_MONITOR_DAEMON_TOKEN")
         .ok()
         .map(|value| value.trim().to_string())
         .filter(|value| !value.is_empty());
     let mut insecure_no_auth = false;
     let mut data_dir: Option<PathBuf> = None;
+    let mut rate_limits = RateLimitConfig::default();
+    let mut pid_file: Option<PathBuf> = None;
+    let mut daemonize = false;
+    let mut access_log: Option<PathBuf> = None;
+    let mut access_log_max_bytes = DEFAULT_ACCESS_LOG_MAX_BYTES;
+    let mut auth_policy: Option<PathBuf> = None;
+    let mut event_buffer = DEFAULT_EVENT_BUFFER;
+    let mut framing = FramingLimits::default();
+    let mut validate_models = false;
+    let mut max_turns_per_session = Some(DEFAULT_MAX_TURNS_PER_SESSION);
+    let mut max_concurrent_spawns = DEFAULT_MAX_CONCURRENT_SPAWNS;
+    let mut max_image_bytes = DEFAULT_MAX_IMAGE_BYTES;
+    let mut rate_limit_poll = None;
+    let mut rate_limit_warn_percent = DEFAULT_RATE_LIMIT_WARN_PERCENT;
+    let mut allow_purge = false;
+    let mut read_only = false;
+    let mut mirror: Option<String> = None;
+    let mut mirror_token = env::var("CODEX_MONITOR_MIRROR_TOKEN")
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    let mut webhook_url: Option<String> = None;
+    let mut webhook_secret = env::var("CODEX_MONITOR_WEBHOOK_SECRET")
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty());
+    let mut webhook_events: Option<BTreeSet<String>> = None;
+    let mut webhook_hosts = BTreeSet::new();
 
     let mut args = env::args().skip(1);
     while let Some(arg) = args.next() {
@@ -1424,6 +5053,16 @@ fn parse_args() -> Result<DaemonConfig, String> {
                 let value = args.next().ok_or("--listen requires a value")?;
                 listen = value.parse::<SocketAddr>().map_err(|err| err.to_string())?;
             }
+            "--listen-http" => {
+                let value = args.next().ok_or("--listen-http requires a value")?;
+                listen_http = Some(value.parse::<SocketAddr>().map_err(|err| err.to_string())?);
+            }
+            "--metrics-no-auth" => {
+                metrics_no_auth = true;
+            }
+            "--stdio" => {
+                stdio = true;
+            }
             "--token" => {
                 let value = args.next().ok_or("--token requires a value")?;
                 let trimmed = value.trim();
@@ -1444,11 +5083,214 @@ fn parse_args() -> Result<DaemonConfig, String> {
                 insecure_no_auth = true;
                 token = None;
             }
+            "--rate-limit" => {
+                let value = args.next().ok_or("--rate-limit requires a value")?;
+                let rate = parse_rate_arg("--rate-limit", &value)?;
+                rate_limits.enabled = rate > 0.0;
+                if rate > 0.0 {
+                    rate_limits.requests_per_sec = rate;
+                }
+            }
+            "--rate-limit-expensive" => {
+                let value = args
+                    .next()
+                    .ok_or("--rate-limit-expensive requires a value")?;
+                let rate = parse_rate_arg("--rate-limit-expensive", &value)?;
+                if rate <= 0.0 {
+                    return Err("--rate-limit-expensive must be greater than 0".to_string());
+                }
+                rate_limits.expensive_requests_per_sec = rate;
+            }
+            "--pid-file" => {
+                let value = args.next().ok_or("--pid-file requires a value")?;
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    return Err("--pid-file requires a non-empty value".to_string());
+                }
+                pid_file = Some(PathBuf::from(trimmed));
+            }
+            "--daemonize" => {
+                if cfg!(not(unix)) {
+                    return Err("--daemonize is only supported on Unix".to_string());
+                }
+                daemonize = true;
+            }
+            "--foreground" => {
+                daemonize = false;
+            }
+            "--access-log" => {
+                let value = args.next().ok_or("--access-log requires a value")?;
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    return Err("--access-log requires a non-empty value".to_string());
+                }
+                access_log = Some(PathBuf::from(trimmed));
+            }
+            "--auth-policy" => {
+                let value = args.next().ok_or("--auth-policy requires a value")?;
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    return Err("--auth-policy requires a non-empty value".to_string());
+                }
+                auth_policy = Some(PathBuf::from(trimmed));
+            }
+            "--access-log-max-bytes" => {
+                let value = args
+                    .next()
+                    .ok_or("--access-log-max-bytes requires a value")?;
+                access_log_max_bytes = value
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|bytes| *bytes > 0)
+                    .ok_or("--access-log-max-bytes requires a positive number")?;
+            }
+            "--event-buffer" => {
+                let value = args.next().ok_or("--event-buffer requires a value")?;
+                event_buffer = value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|events| *events > 0)
+                    .ok_or("--event-buffer requires a positive number")?;
+            }
+            "--max-request-bytes" => {
+                let value = args.next().ok_or("--max-request-bytes requires a value")?;
+                framing.max_request_bytes = value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|bytes| *bytes > 0)
+                    .ok_or("--max-request-bytes requires a positive number")?;
+            }
+            "--max-response-bytes" => {
+                let value = args.next().ok_or("--max-response-bytes requires a value")?;
+                framing.max_response_bytes = value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|bytes| *bytes > 0)
+                    .ok_or("--max-response-bytes requires a positive number")?;
+            }
+            "--max-outbound-queue-bytes" => {
+                let value = args
+                    .next()
+                    .ok_or("--max-outbound-queue-bytes requires a value")?;
+                framing.max_outbound_queue_bytes = value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|bytes| *bytes > 0)
+                    .ok_or("--max-outbound-queue-bytes requires a positive number")?;
+            }
+            "--validate-models" => {
+                validate_models = true;
+            }
+            "--max-turns-per-session" => {
+                let value = args
+                    .next()
+                    .ok_or("--max-turns-per-session requires a value")?;
+                let turns = value
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|_| "--max-turns-per-session requires a number")?;
+                max_turns_per_session = (turns > 0).then_some(turns);
+            }
+            "--max-concurrent-spawns" => {
+                let value = args
+                    .next()
+                    .ok_or("--max-concurrent-spawns requires a value")?;
+                max_concurrent_spawns = value
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|spawns| *spawns > 0)
+                    .ok_or("--max-concurrent-spawns requires a positive number")?;
+            }
+            "--max-image-bytes" => {
+                let value = args.next().ok_or("--max-image-bytes requires a value")?;
+                max_image_bytes = value
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|bytes| *bytes > 0)
+                    .ok_or("--max-image-bytes requires a positive number")?;
+            }
+            "--poll-rate-limits" => {
+                let value = args.next().ok_or("--poll-rate-limits requires a value")?;
+                let secs = value
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|secs| *secs > 0)
+                    .ok_or("--poll-rate-limits requires a positive number of seconds")?;
+                rate_limit_poll = Some(Duration::from_secs(secs).max(MIN_RATE_LIMIT_POLL));
+            }
+            "--rate-limit-warn-percent" => {
+                let value = args
+                    .next()
+                    .ok_or("--rate-limit-warn-percent requires a value")?;
+                rate_limit_warn_percent = value
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|percent| *percent > 0.0 && *percent <= 100.0)
+                    .ok_or("--rate-limit-warn-percent requires a number from 0 to 100")?;
+            }
+            "--allow-purge" => {
+                allow_purge = true;
+            }
+            "--read-only" => {
+                read_only = true;
+            }
+            "--mirror" => {
+                let value = args.next().ok_or("--mirror requires a value")?;
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    return Err("--mirror requires a non-empty value".to_string());
+                }
+                mirror = Some(trimmed.to_string());
+            }
+            "--mirror-token" => {
+                let value = args.next().ok_or("--mirror-token requires a value")?;
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    return Err("--mirror-token requires a non-empty value".to_string());
+                }
+                mirror_token = Some(trimmed.to_string());
+            }
+            "--webhook-url" => {
+                let value = args.next().ok_or("--webhook-url requires a value")?;
+                webhook_url = Some(parse_webhook_url(&value)?);
+            }
+            "--webhook-events" => {
+                let value = args.next().ok_or("--webhook-events requires a value")?;
+                webhook_events = Some(parse_webhook_events(&value)?);
+            }
+            "--webhook-secret" => {
+                let value = args.next().ok_or("--webhook-secret requires a value")?;
+                let trimmed = value.trim();
+                if trimmed.is_empty() {
+                    return Err("--webhook-secret requires a non-empty value".to_string());
+                }
+                webhook_secret = Some(trimmed.to_string());
+            }
+            "--webhook-hosts" => {
+                let value = args.next().ok_or("--webhook-hosts requires a value")?;
+                webhook_hosts = parse_webhook_hosts(&value)?;
+            }
             _ => return Err(format!("Unknown argument: {arg}")),
         }
     }
 
-    if token.is_none() && !insecure_no_auth {
+    if stdio && daemonize {
+        return Err("--stdio can't be combined with --daemonize".to_string());
+    }
+    if listen_http.is_some() && token.is_none() && !insecure_no_auth {
+        return Err("--listen-http requires --token (or --insecure-no-auth).".to_string());
+    }
+    // Only the process that spawned the daemon can reach stdin.
+    if token.is_none() && !insecure_no_auth && !stdio {
         return Err(
             "Missing --token (or set CODEX_MONITOR_DAEMON_TOKEN). Use --insecure-no-auth for local dev only."
                 .to_string(),
@@ -1457,11 +5299,112 @@ fn parse_args() -> Result<DaemonConfig, String> {
 
     Ok(DaemonConfig {
         listen,
+        listen_http,
+        metrics_no_auth,
+        stdio,
         token,
         data_dir: data_dir.unwrap_or_else(default_data_dir),
+        rate_limits,
+        pid_file,
+        daemonize,
+        access_log,
+        access_log_max_bytes,
+        auth_policy,
+        event_buffer,
+        framing,
+        validate_models,
+        max_turns_per_session,
+        max_concurrent_spawns,
+        max_image_bytes,
+        rate_limit_poll,
+        rate_limit_warn_percent,
+        allow_purge,
+        read_only,
+        mirror: mirror.map(|upstream| MirrorConfig {
+            upstream,
+            token: mirror_token,
+        }),
+        webhook: webhook_url.map(|url| WebhookConfig {
+            url,
+            events: webhook_events.unwrap_or_else(default_webhook_events),
+            secret: webhook_secret,
+        }),
+        webhook_hosts,
     })
 }
 
+fn absolute_path(path: PathBuf) -> PathBuf {
+    if path.is_absolute() {
+        return path;
+    }
+    env::current_dir()
+        .map(|cwd| cwd.join(&path))
+        .unwrap_or(path)
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+fn parse_rate_arg(flag: &str, value: &str) -> Result<f64, String> {
+    let rate = value
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("{flag} requires a number"))?;
+    if !rate.is_finite() || rate < 0.0 {
+        return Err(format!("{flag} must be a non-negative number"));
+    }
+    Ok(rate)
+}
+
+impl From<InvalidSettings> for RpcError {
+    fn from(error: InvalidSettings) -> Self {
+        Self {
+            message: error.message(),
+            code: Some("invalid_settings"),
+            data: Some(json!({ "errors": error.fields })),
+        }
+    }
+}
+
+fn build_rpc_error_response(id: Option<u64>, error: &RpcError) -> Option<String> {
+    if error.code.is_none() && error.data.is_none() {
+        return build_error_response(id, &error.message);
+    }
+    let id = id?;
+    Some(
+        serde_json::to_string(&json!({
+            "id": id,
+            "error": {
+                "code": error.code,
+                "message": error.message,
+                "data": error.data,
+            }
+        }))
+        .unwrap_or_else(|_| {
+            "{\"id\":0,\"error\":{\"message\":\"serialization failed\"}}".to_string()
+        }),
+    )
+}
+
 fn build_error_response(id: Option<u64>, message: &str) -> Option<String> {
     let id = id?;
     Some(
@@ -1473,6 +5416,24 @@ fn build_error_response(id: Option<u64>, message: &str) -> Option<String> {
     )
 }
 
+fn build_rate_limited_response(id: Option<u64>, retry_after_ms: u64) -> Option<String> {
+    let id = id?;
+    let message = format!("rate limited, retry after {retry_after_ms}ms");
+    Some(
+        serde_json::to_string(&json!({
+            "id": id,
+            "error": {
+                "code": "rate_limited",
+                "message": message,
+                "retryAfterMs": retry_after_ms,
+            }
+        }))
+        .unwrap_or_else(|_| {
+            "{\"id\":0,\"error\":{\"message\":\"serialization failed\"}}".to_string()
+        }),
+    )
+}
+
 fn build_result_response(id: Option<u64>, result: Value) -> Option<String> {
     let id = id?;
     Some(serde_json::to_string(&json!({ "id": id, "result": result })).unwrap_or_else(|_| {
@@ -1480,18 +5441,156 @@ fn build_result_response(id: Option<u64>, result: Value) -> Option<String> {
     }))
 }
 
-fn build_event_notification(event: DaemonEvent) -> Option<String> {
-    let payload = match event {
+fn attach_response_meta(response: String, meta: Value) -> String {
+    let Ok(mut envelope) = serde_json::from_str::<Value>(&response) else {
+        return response;
+    };
+    envelope["_meta"] = meta;
+    envelope.to_string()
+}
+
+/// Adds the `envelope` a connection asked for at auth: when the response was
+/// built (ms since the epoch), the method it answers and how long it took.
+fn attach_response_envelope(response: String, method: &str, duration: Duration) -> String {
+    let Ok(mut envelope) = serde_json::from_str::<Value>(&response) else {
+        return response;
+    };
+    envelope["envelope"] = json!({
+        "ts": now_ms(),
+        "method": method,
+        "durationMs": u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
+    });
+    envelope.to_string()
+}
+
+/// Lists the deprecated parameter names a request used under `warnings`.
+fn attach_response_warnings(response: String, warnings: &[String]) -> String {
+    let Ok(mut envelope) = serde_json::from_str::<Value>(&response) else {
+        return response;
+    };
+    envelope["warnings"] = json!(warnings);
+    envelope.to_string()
+}
+
+/// Sent with `id: null` because an oversized request is never parsed.
+fn build_request_too_large_response(length: usize, max_bytes: usize) -> String {
+    json!({
+        "id": null,
+        "error": {
+            "code": "request_too_large",
+            "message": format!("request of at least {length} bytes exceeds the {max_bytes}-byte limit"),
+        }
+    })
+    .to_string()
+}
+
+/// Applies the options a client sent with `auth` and returns its result.
+/// `compression: "zstd"` makes large messages to the client compressed and
+/// is confirmed in the result; other encodings are left out of it, so the
+/// client knows to send plain messages.
+fn apply_auth_options(params: &Value, out_tx: &OutboundSender) -> Value {
+    let mut result = json!({ "ok": true });
+    if parse_optional_string(params, "compression").as_deref() == Some(ZSTD_ENCODING) {
+        out_tx.enable_compression();
+        result["compression"] = json!(ZSTD_ENCODING);
+    }
+    result
+}
+
+/// Caps the size of a result response with `cap_response_size` and counts
+/// what it did.
+fn cap_response(state: &DaemonState, response: String, max_bytes: usize) -> String {
+    match cap_response_size(response, max_bytes) {
+        CappedResponse::Unchanged(response) => response,
+        CappedResponse::Truncated(response) => {
+            state.framing_counters.record_truncated_response();
+            response
+        }
+        CappedResponse::TooLarge(response) => {
+            state.framing_counters.record_oversized_response();
+            response
+        }
+    }
+}
+
+fn event_notification(event: SequencedEvent) -> Value {
+    let SequencedEvent { seq, event } = event;
+    let mut payload = match event {
         DaemonEvent::AppServer(payload) => json!({
             "method": "app-server-event",
             "params": payload,
         }),
-        DaemonEvent::TerminalOutput(payload) => json!({
-            "method": "terminal-output",
+        DaemonEvent::TerminalOutput(payload) => json!({
+            "method": "terminal-output",
+            "params": payload,
+        }),
+        DaemonEvent::WorktreeGc(payload) => json!({
+            "method": "worktree-gc",
+            "params": payload,
+        }),
+        DaemonEvent::RestartRequired(payload) => json!({
+            "method": "workspace-restart-required",
+            "params": payload,
+        }),
+        DaemonEvent::AutoDisconnected(payload) => json!({
+            "method": "workspace-auto-disconnected",
+            "params": payload,
+        }),
+        DaemonEvent::CloneProgress(payload) => json!({
+            "method": "clone-progress",
+            "params": payload,
+        }),
+        DaemonEvent::ThreadRead(payload) => json!({
+            "method": "thread-read",
+            "params": payload,
+        }),
+        DaemonEvent::ThreadTitle(payload) => json!({
+            "method": "thread-title-updated",
+            "params": payload,
+        }),
+        DaemonEvent::QueuedTurn(payload) => json!({
+            "method": "queued-turn",
+            "params": payload,
+        }),
+        DaemonEvent::RateLimitUpdate(payload) => json!({
+            "method": "rate-limit-update",
+            "params": payload,
+        }),
+        DaemonEvent::SessionSpawnQueued(payload) => json!({
+            "method": "session-spawn-queued",
+            "params": payload,
+        }),
+        DaemonEvent::SessionSpawnStarted(payload) => json!({
+            "method": "session-spawn-started",
             "params": payload,
         }),
+        DaemonEvent::SessionSpawnFinished(payload) => json!({
+            "method": "session-spawn-finished",
+            "params": payload,
+        }),
+        DaemonEvent::WorkspacePaused(payload) => json!({
+            "method": "workspace-paused",
+            "params": payload,
+        }),
+        DaemonEvent::WorkspaceError(payload) => json!({
+            "method": "workspace-error",
+            "params": payload,
+        }),
+        DaemonEvent::WorkspacesChanged(payload) => json!({
+            "method": "workspaces-changed",
+            "params": payload,
+        }),
+        DaemonEvent::Operation(payload) => json!({
+            "method": payload.method,
+            "params": payload.status,
+        }),
+        DaemonEvent::Upstream(payload) => json!({
+            "method": payload.method,
+            "params": payload.params,
+        }),
     };
-    serde_json::to_string(&payload).ok()
+    payload["seq"] = json!(seq);
+    payload
 }
 
 fn parse_auth_token(params: &Value) -> Option<String> {
@@ -1526,6 +5625,13 @@ fn parse_optional_string(value: &Value, key: &str) -> Option<String> {
     }
 }
 
+fn parse_optional_u64(value: &Value, key: &str) -> Option<u64> {
+    match value {
+        Value::Object(map) => map.get(key).and_then(|value| value.as_u64()),
+        _ => None,
+    }
+}
+
 fn parse_optional_u32(value: &Value, key: &str) -> Option<u32> {
     match value {
         Value::Object(map) => map.get(key).and_then(|value| value.as_u64()).and_then(|v| {
@@ -1539,6 +5645,13 @@ fn parse_optional_u32(value: &Value, key: &str) -> Option<u32> {
     }
 }
 
+fn parse_optional_bool(value: &Value, key: &str) -> Option<bool> {
+    match value {
+        Value::Object(map) => map.get(key).and_then(|value| value.as_bool()),
+        _ => None,
+    }
+}
+
 fn parse_optional_string_array(value: &Value, key: &str) -> Option<Vec<String>> {
     match value {
         Value::Object(map) => map.get(key).and_then(|value| value.as_array()).map(|items| {
@@ -1562,16 +5675,160 @@ fn parse_optional_value(value: &Value, key: &str) -> Option<Value> {
     }
 }
 
-async fn handle_rpc_request(
-    state: &DaemonState,
+/// Runs a long RPC inline, or with `async: true` in the background, returning
+/// `{ operationId }` right away and reporting through operation events.
+async fn run_operation<F, Fut>(
+    state: &Arc<DaemonState>,
+    params: &Value,
+    method: &'static str,
+    cancellable: bool,
+    run: F,
+) -> Result<Value, String>
+where
+    F: FnOnce(Arc<DaemonState>, OperationProgress) -> Fut,
+    Fut: Future<Output = Result<Value, String>> + Send + 'static,
+{
+    if !parse_optional_bool(params, "async").unwrap_or(false) {
+        return run(Arc::clone(state), OperationProgress::detached()).await;
+    }
+    let (operation_id, progress) = state.operations.start(method, cancellable);
+    let operations = Arc::clone(&state.operations);
+    let task = run(Arc::clone(state), progress);
+    let git_timer = state.git_timer();
+    let id = operation_id.clone();
+    tokio::spawn(async move {
+        let result = GIT_TIMER.scope(git_timer, task).await;
+        operations.finish(&id, result);
+    });
+    Ok(json!({ "operationId": operation_id }))
+}
+
+/// Runs `handle_rpc_request` for a request sent with `meta: true` and returns
+/// its `_meta`: when it was received, dispatched and completed (ms since the
+/// epoch), how long the handler ran and how long its workspace session calls
+/// waited on `send_request` (µs).
+async fn handle_rpc_request_timed(
+    state: &Arc<DaemonState>,
+    context: &ConnectionContext,
     method: &str,
     params: Value,
     client_version: String,
+    received: Instant,
+) -> (Result<Value, RpcError>, Value) {
+    let dispatched_at = now_ms();
+    let queued_ms = u64::try_from(received.elapsed().as_millis()).unwrap_or(u64::MAX);
+    let dispatched = Instant::now();
+    let (result, session_requests, session_wait_us) = SESSION_TIMING
+        .scope(SessionTiming::default(), async {
+            let result = handle_rpc_request(state, context, method, params, client_version).await;
+            let (requests, wait_us) =
+                SESSION_TIMING.with(|timing| (timing.requests.get(), timing.wait_us.get()));
+            (result, requests, wait_us)
+        })
+        .await;
+    let handler_us = u64::try_from(dispatched.elapsed().as_micros()).unwrap_or(u64::MAX);
+    let meta = json!({
+        "receivedAt": dispatched_at.saturating_sub(queued_ms),
+        "dispatchedAt": dispatched_at,
+        "completedAt": now_ms(),
+        "handlerUs": handler_us,
+        "sessionRequests": session_requests,
+        "sessionWaitUs": session_wait_us,
+    });
+    (result, meta)
+}
+
+/// Subscriptions only filter what the connection receives, so mirrors
+/// accept them too.
+fn update_thread_subscription(
+    context: &ConnectionContext,
+    method: &str,
+    params: &Value,
 ) -> Result<Value, String> {
-    match method {
-        "ping" => Ok(json!({ "ok": true })),
+    let workspace_id = parse_string(params, "workspaceId")?;
+    let thread_id = parse_string(params, "threadId")?;
+    let subscriptions = &context.thread_subscriptions;
+    let count = if method == "subscribe_thread" {
+        subscriptions.subscribe(workspace_id, thread_id)
+    } else {
+        subscriptions.unsubscribe(workspace_id, thread_id)?
+    };
+    Ok(json!({ "count": count }))
+}
+
+/// Dispatches a request with the git commands it runs timed for `/metrics`.
+async fn handle_rpc_request(
+    state: &Arc<DaemonState>,
+    context: &ConnectionContext,
+    method: &str,
+    params: Value,
+    client_version: String,
+) -> Result<Value, RpcError> {
+    let dispatch = dispatch_rpc_request(state, context, method, params, client_version);
+    GIT_TIMER.scope(state.git_timer(), dispatch).await
+}
+
+async fn dispatch_rpc_request(
+    state: &Arc<DaemonState>,
+    context: &ConnectionContext,
+    method: &str,
+    params: Value,
+    client_version: String,
+) -> Result<Value, RpcError> {
+    let mut workspaces = request_workspaces(method, &params);
+    if workspaces.all {
+        workspaces
+            .ids
+            .extend(state.workspaces.lock().await.keys().cloned());
+    }
+    if matches!(method, "restore_from_trash" | "empty_trash") {
+        let item_id = parse_optional_string(&params, "id");
+        workspaces
+            .ids
+            .extend(state.trashed_workspace_ids(item_id).await);
+    }
+    state
+        .authorization
+        .authorize_request(method, &workspaces, context)?;
+    // Requests that name no workspace but act on many check each one.
+    let authorize_workspace = |id: &str| state.authorization.authorize(method, Some(id), context);
+    if state.read_only && !is_read_request(method, &params) {
+        return Err(RpcError {
+            message: format!("{READ_ONLY_ERROR}: the daemon runs with --read-only"),
+            code: Some("read_only"),
+            data: Some(json!({ "method": method })),
+        });
+    }
+
+    if matches!(method, "subscribe_thread" | "unsubscribe_thread") {
+        return update_thread_subscription(context, method, &params).map_err(RpcError::from);
+    }
+
+    if let Some(mirror) = &state.mirror {
+        let result = match method {
+            "ping" => Ok(state.ping(&params).await),
+            "daemon_status" => Ok(state.daemon_status().await),
+            _ if Mirror::serves(method) => mirror.read(method, &params),
+            _ => {
+                return Err(RpcError {
+                    message: READ_ONLY_MESSAGE.to_string(),
+                    code: Some("mirror_read_only"),
+                    data: Some(json!({ "method": method })),
+                })
+            }
+        };
+        return result.map_err(RpcError::from);
+    }
+
+    let result = match method {
+        "ping" => Ok(state.ping(&params).await),
+        "daemon_status" => Ok(state.daemon_status().await),
         "list_workspaces" => {
-            let workspaces = state.list_workspaces().await;
+            let sort = parse_optional_string(&params, "sortBy")
+                .map(|sort| WorkspaceSort::parse(&sort))
+                .transpose()?
+                .unwrap_or_default();
+            let workspaces = state.list_workspaces_by(sort).await;
             serde_json::to_value(workspaces).map_err(|err| err.to_string())
         }
         "is_workspace_path_dir" => {
@@ -1581,32 +5838,343 @@ async fn handle_rpc_request(
         }
         "add_workspace" => {
             let path = parse_string(&params, "path")?;
-            let codex_bin = parse_optional_string(&params, "codex_bin");
+            let codex_bin = parse_optional_string(&params, "codexBin");
             let workspace = state.add_workspace(path, codex_bin, client_version).await?;
             serde_json::to_value(workspace).map_err(|err| err.to_string())
         }
+        "discover_workspaces" => {
+            let roots = parse_optional_string_array(&params, "roots");
+            let max_depth = parse_optional_u32(&params, "maxDepth");
+            let result = state.discover_workspaces(roots, max_depth).await?;
+            serde_json::to_value(result).map_err(|err| err.to_string())
+        }
+        "clone_workspace" => {
+            let url = parse_string(&params, "url")?;
+            let destination = parse_optional_string(&params, "destination");
+            let branch = parse_optional_string(&params, "branch");
+            let depth = parse_optional_u32(&params, "depth");
+            let run = |state: Arc<DaemonState>, progress: OperationProgress| async move {
+                let workspace = state
+                    .clone_workspace(url, destination, branch, depth, client_version, &progress)
+                    .await?;
+                serde_json::to_value(workspace).map_err(|err| err.to_string())
+            };
+            run_operation(state, &params, "clone_workspace", true, run).await
+        }
         "add_worktree" => {
             let parent_id = parse_string(&params, "parentId")?;
             let branch = parse_string(&params, "branch")?;
-            let workspace = state
-                .add_worktree(parent_id, branch, client_version)
-                .await?;
-            serde_json::to_value(workspace).map_err(|err| err.to_string())
+            let init_submodules = parse_optional_bool(&params, "initSubmodules");
+            let run = |state: Arc<DaemonState>, progress: OperationProgress| async move {
+                let workspace = state
+                    .add_worktree(
+                        parent_id,
+                        branch,
+                        init_submodules,
+                        client_version,
+                        &progress,
+                    )
+                    .await?;
+                serde_json::to_value(workspace).map_err(|err| err.to_string())
+            };
+            run_operation(state, &params, "add_worktree", false, run).await
+        }
+        "get_operation" => {
+            let operation_id = parse_string(&params, "operationId")?;
+            let status = state
+                .operations
+                .get(&operation_id)
+                .ok_or("operation not found")?;
+            serde_json::to_value(status).map_err(|err| err.to_string())
+        }
+        "cancel_operation" => {
+            let operation_id = parse_string(&params, "operationId")?;
+            state.operations.cancel(&operation_id)?;
+            Ok(json!({ "ok": true }))
         }
         "connect_workspace" => {
             let id = parse_string(&params, "id")?;
             state.connect_workspace(id, client_version).await?;
             Ok(json!({ "ok": true }))
         }
-        "remove_workspace" => {
+        "connect_all_workspaces" => {
+            let queued = state
+                .connect_all_workspaces(client_version, authorize_workspace)
+                .await;
+            Ok(json!({ "queued": queued }))
+        }
+        "cancel_session_spawn" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let cancelled = state.spawn_queue.cancel(&workspace_id);
+            Ok(json!({ "cancelled": cancelled }))
+        }
+        "workspace_status" => {
+            let id = parse_string(&params, "id")?;
+            let workspace = state.workspace_status(&id).await?;
+            serde_json::to_value(workspace).map_err(|err| err.to_string())
+        }
+        "ping_session" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let timeout_ms = parse_optional_u64(&params, "timeoutMs");
+            let slow_ms = parse_optional_u64(&params, "slowMs");
+            let health = state
+                .ping_session(&workspace_id, timeout_ms, slow_ms)
+                .await?;
+            serde_json::to_value(health).map_err(|err| err.to_string())
+        }
+        "list_sessions" => {
+            let sessions = state.list_sessions().await;
+            serde_json::to_value(sessions).map_err(|err| err.to_string())
+        }
+        "health_check_all" => {
+            let timeout_ms = parse_optional_u64(&params, "timeoutMs");
+            let slow_ms = parse_optional_u64(&params, "slowMs");
+            let reap_dead = parse_optional_bool(&params, "reapDead").unwrap_or(false);
+            Ok(state
+                .health_check_all(timeout_ms, slow_ms, reap_dead, authorize_workspace)
+                .await)
+        }
+        "restart_workspace" => {
+            let id = parse_string(&params, "id")?;
+            let workspace = state.restart_workspace(id, client_version).await?;
+            serde_json::to_value(workspace).map_err(|err| err.to_string())
+        }
+        "pause_workspace" => {
             let id = parse_string(&params, "id")?;
-            state.remove_workspace(id).await?;
+            let interrupt = parse_optional_bool(&params, "interruptTurns").unwrap_or(false);
+            let notice = state.pause_workspace(id, interrupt).await?;
+            serde_json::to_value(notice).map_err(|err| err.to_string())
+        }
+        "resume_workspace" => {
+            let id = parse_string(&params, "id")?;
+            let notice = state.resume_workspace(id).await?;
+            serde_json::to_value(notice).map_err(|err| err.to_string())
+        }
+        "workspace_errors" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let limit = parse_optional_u32(&params, "limit");
+            state.workspace_errors(&workspace_id, limit).await
+        }
+        "preflight_check" => {
+            let scope = parse_workspace_scope(&params)?;
+            let report = state.preflight_check(scope).await?;
+            serde_json::to_value(report).map_err(|err| err.to_string())
+        }
+        "clear_workspace_errors" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let up_to = parse_optional_u64(&params, "upTo");
+            state.clear_workspace_errors(&workspace_id, up_to).await
+        }
+        "purge_data" => {
+            let confirm = parse_optional_string(&params, "confirm");
+            if !state.allow_purge {
+                Err("purge_data is disabled; start the daemon with --allow-purge.".to_string())
+            } else if confirm.as_deref() != Some(PURGE_CONFIRMATION) {
+                Err(format!(
+                    "Pass confirm: \"{PURGE_CONFIRMATION}\" to purge all daemon data."
+                ))
+            } else {
+                let summary = state.purge_data().await;
+                serde_json::to_value(summary).map_err(|err| err.to_string())
+            }
+        }
+        "reload_codex_bin" => {
+            let report = state
+                .reload_codex_bin(client_version, authorize_workspace)
+                .await;
+            serde_json::to_value(report).map_err(|err| err.to_string())
+        }
+        "workspace_remote_info" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let info = state.workspace_remote_info(&workspace_id).await?;
+            serde_json::to_value(info).map_err(|err| err.to_string())
+        }
+        "branch_compare_url" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let compare = state.branch_compare_url(&workspace_id).await?;
+            serde_json::to_value(compare).map_err(|err| err.to_string())
+        }
+        "get_git_identity" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let identity = state.get_git_identity(&workspace_id).await?;
+            serde_json::to_value(identity).map_err(|err| err.to_string())
+        }
+        "set_git_identity" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let name = parse_optional_string(&params, "name");
+            let email = parse_optional_string(&params, "email");
+            let scope = parse_optional_string(&params, "scope");
+            let identity = state
+                .set_git_identity(&workspace_id, name, email, scope)
+                .await?;
+            serde_json::to_value(identity).map_err(|err| err.to_string())
+        }
+        "list_conflicts" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let files = state.list_conflicts(&workspace_id).await?;
+            Ok(json!({ "files": files }))
+        }
+        "mark_resolved" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let path = parse_string(&params, "path")?;
+            state.mark_resolved(&workspace_id, &path).await?;
+            Ok(json!({ "ok": true }))
+        }
+        "conflict_status" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let status = state.conflict_status(&workspace_id).await?;
+            serde_json::to_value(status).map_err(|err| err.to_string())
+        }
+        "resolve_conflict" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let path = parse_string(&params, "path")?;
+            let resolution = parse_string(&params, "resolution")?;
+            let content = parse_optional_string(&params, "content");
+            let resolution = ConflictResolution::parse(&resolution, content)?;
+            state
+                .resolve_conflict(&workspace_id, &path, resolution)
+                .await?;
+            Ok(json!({ "ok": true }))
+        }
+        "abort_operation" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let aborted = state.abort_operation(&workspace_id).await?;
+            Ok(json!({ "aborted": aborted }))
+        }
+        "continue_operation" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let operation = state.continue_operation(&workspace_id).await?;
+            Ok(json!({ "operation": operation }))
+        }
+        "git_blame" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let path = parse_string(&params, "path")?;
+            let start_line = parse_optional_u32(&params, "startLine");
+            let end_line = parse_optional_u32(&params, "endLine");
+            let blame = state
+                .git_blame(&workspace_id, &path, start_line, end_line)
+                .await?;
+            serde_json::to_value(blame).map_err(|err| err.to_string())
+        }
+        "cherry_pick_commits" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let commits = parse_string_array(&params, "commits")?;
+            let no_commit = parse_optional_bool(&params, "noCommit").unwrap_or(false);
+            let result = state
+                .cherry_pick_commits(&workspace_id, commits, no_commit)
+                .await?;
+            serde_json::to_value(result).map_err(|err| err.to_string())
+        }
+        "cherry_pick" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let hash = parse_string(&params, "hash")?;
+            let no_commit = parse_optional_bool(&params, "noCommit").unwrap_or(false);
+            let result = state.cherry_pick(&workspace_id, &hash, no_commit).await?;
+            serde_json::to_value(result).map_err(|err| err.to_string())
+        }
+        "cherry_pick_continue" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let operation = state.cherry_pick_continue(&workspace_id).await?;
+            Ok(json!({ "operation": operation }))
+        }
+        "cherry_pick_abort" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            state.cherry_pick_abort(&workspace_id).await?;
             Ok(json!({ "ok": true }))
         }
+        "show_commit" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let hash = parse_string(&params, "hash")?;
+            let commit = state.show_commit(&workspace_id, &hash).await?;
+            serde_json::to_value(commit).map_err(|err| err.to_string())
+        }
+        "squash_worktree" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let message = parse_optional_string(&params, "message");
+            let force = parse_optional_bool(&params, "force").unwrap_or(false);
+            let run = |state: Arc<DaemonState>, progress: OperationProgress| async move {
+                let result = state
+                    .squash_worktree(&workspace_id, message, force, &progress)
+                    .await?;
+                serde_json::to_value(result).map_err(|err| err.to_string())
+            };
+            run_operation(state, &params, "squash_worktree", false, run).await
+        }
+        "create_pull_request" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let title = parse_string(&params, "title")?;
+            let body = parse_optional_string(&params, "body");
+            let base = parse_optional_string(&params, "base");
+            let draft = parse_optional_bool(&params, "draft").unwrap_or(false);
+            let created = state
+                .create_pull_request(&workspace_id, title, body, base, draft)
+                .await?;
+            serde_json::to_value(created).map_err(|err| err.to_string())
+        }
+        "pull_request_status" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let status = state.pull_request_status(&workspace_id).await?;
+            Ok(json!({ "pullRequest": status }))
+        }
+        "list_tags" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let pattern = parse_optional_string(&params, "pattern");
+            let tags = state.list_tags(&workspace_id, pattern).await?;
+            Ok(json!({ "tags": tags }))
+        }
+        "create_tag" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let name = parse_string(&params, "name")?;
+            let message = parse_optional_string(&params, "message");
+            let target = parse_optional_string(&params, "target");
+            let tag = state
+                .create_tag(&workspace_id, &name, message, target)
+                .await?;
+            serde_json::to_value(tag).map_err(|err| err.to_string())
+        }
+        "discard_changes" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let paths = parse_optional_string_array(&params, "paths").unwrap_or_default();
+            let include_untracked =
+                parse_optional_bool(&params, "includeUntracked").unwrap_or(false);
+            let dry_run = parse_optional_bool(&params, "dryRun").unwrap_or(false);
+            let confirm = parse_optional_bool(&params, "confirm").unwrap_or(false);
+            let result = state
+                .discard_changes(&workspace_id, paths, include_untracked, dry_run, confirm)
+                .await?;
+            serde_json::to_value(result).map_err(|err| err.to_string())
+        }
+        "fetch_workspace" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let status = state.fetch_workspace(&workspace_id).await?;
+            serde_json::to_value(status).map_err(|err| err.to_string())
+        }
+        "remove_workspace" => {
+            let id = parse_string(&params, "id")?;
+            let soft_delete = parse_optional_bool(&params, "softDelete");
+            let trash_id = state.remove_workspace(id, soft_delete).await?;
+            Ok(json!({ "ok": true, "trashId": trash_id }))
+        }
         "remove_worktree" => {
             let id = parse_string(&params, "id")?;
-            state.remove_worktree(id).await?;
-            Ok(json!({ "ok": true }))
+            let force = parse_optional_bool(&params, "force").unwrap_or(false);
+            let soft_delete = parse_optional_bool(&params, "softDelete");
+            let trash_id = state.remove_worktree(id, force, soft_delete).await?;
+            Ok(json!({ "ok": true, "trashId": trash_id }))
+        }
+        "list_trash" => {
+            let items = state.list_trash().await;
+            Ok(json!({ "items": items }))
+        }
+        "restore_from_trash" => {
+            let id = parse_string(&params, "id")?;
+            let summary = state.restore_from_trash(&id).await?;
+            serde_json::to_value(summary).map_err(|err| err.to_string())
+        }
+        "empty_trash" => {
+            let id = parse_optional_string(&params, "id");
+            let removed = state.empty_trash(id).await?;
+            Ok(json!({ "removed": removed }))
         }
         "rename_worktree" => {
             let id = parse_string(&params, "id")?;
@@ -1614,6 +6182,12 @@ async fn handle_rpc_request(
             let workspace = state.rename_worktree(id, branch, client_version).await?;
             serde_json::to_value(workspace).map_err(|err| err.to_string())
         }
+        "move_worktree" => {
+            let id = parse_string(&params, "workspaceId")?;
+            let base_dir = parse_string(&params, "baseDir")?;
+            let workspace = state.move_worktree(id, base_dir, client_version).await?;
+            serde_json::to_value(workspace).map_err(|err| err.to_string())
+        }
         "rename_worktree_upstream" => {
             let id = parse_string(&params, "id")?;
             let old_branch = parse_string(&params, "oldBranch")?;
@@ -1625,32 +6199,97 @@ async fn handle_rpc_request(
         }
         "update_workspace_settings" => {
             let id = parse_string(&params, "id")?;
+            let lenient = parse_optional_bool(&params, "lenient").unwrap_or(false);
             let settings_value = match params {
                 Value::Object(map) => map.get("settings").cloned().unwrap_or(Value::Null),
                 _ => Value::Null,
             };
-            let settings: WorkspaceSettings =
-                serde_json::from_value(settings_value).map_err(|err| err.to_string())?;
+            let settings = parse_workspace_settings(&settings_value, lenient)?;
             let workspace = state.update_workspace_settings(id, settings).await?;
             serde_json::to_value(workspace).map_err(|err| err.to_string())
         }
+        "update_workspaces_settings_bulk" => {
+            let target = parse_bulk_target(&params)?;
+            let patch = params.get("patch").cloned().unwrap_or(Value::Null);
+            let results = state
+                .update_workspaces_settings_bulk(target, patch, authorize_workspace)
+                .await?;
+            Ok(json!({ "results": results }))
+        }
+        "patch_workspace_settings" => {
+            let id = parse_string(&params, "id")?;
+            let patch = match params {
+                Value::Object(map) => map.get("patch").cloned().unwrap_or(Value::Null),
+                _ => Value::Null,
+            };
+            let workspace = state.patch_workspace_settings(id, patch).await?;
+            serde_json::to_value(workspace).map_err(|err| err.to_string())
+        }
         "update_workspace_codex_bin" => {
             let id = parse_string(&params, "id")?;
-            let codex_bin = parse_optional_string(&params, "codex_bin");
+            let codex_bin = parse_optional_string(&params, "codexBin");
             let workspace = state.update_workspace_codex_bin(id, codex_bin).await?;
             serde_json::to_value(workspace).map_err(|err| err.to_string())
         }
+        "save_workspace_profile" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let name = parse_string(&params, "name")?;
+            let profile = state.save_workspace_profile(&workspace_id, &name).await?;
+            serde_json::to_value(profile).map_err(|err| err.to_string())
+        }
+        "list_workspace_profiles" => {
+            let profiles = state.profiles.list()?;
+            serde_json::to_value(profiles).map_err(|err| err.to_string())
+        }
+        "apply_workspace_profile" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let name = parse_string(&params, "name")?;
+            let workspace = state.apply_workspace_profile(workspace_id, &name).await?;
+            serde_json::to_value(workspace).map_err(|err| err.to_string())
+        }
         "list_workspace_files" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
-            let files = state.list_workspace_files(workspace_id).await?;
+            let follow_links = parse_optional_bool(&params, "followLinks").unwrap_or(false);
+            let files = state
+                .list_workspace_files(workspace_id, follow_links)
+                .await?;
+            serde_json::to_value(files).map_err(|err| err.to_string())
+        }
+        "list_workspace_files_detailed" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let follow_links = parse_optional_bool(&params, "followLinks").unwrap_or(false);
+            let files = state
+                .list_workspace_files_detailed(workspace_id, follow_links)
+                .await?;
             serde_json::to_value(files).map_err(|err| err.to_string())
         }
+        "list_workspace_files_delta" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let since_token = parse_optional_string(&params, "sinceToken");
+            let delta = state
+                .list_workspace_files_delta(workspace_id, since_token)
+                .await?;
+            serde_json::to_value(delta).map_err(|err| err.to_string())
+        }
         "read_workspace_file" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let path = parse_string(&params, "path")?;
             let response = state.read_workspace_file(workspace_id, path).await?;
             serde_json::to_value(response).map_err(|err| err.to_string())
         }
+        "list_turn_artifacts" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let turn_id = parse_optional_string(&params, "turnId");
+            state
+                .list_turn_artifacts(&workspace_id, turn_id.as_deref())
+                .await
+        }
+        "read_turn_artifact" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let path = parse_string(&params, "path")?;
+            let response = state.read_turn_artifact(&workspace_id, &path).await?;
+            serde_json::to_value(response).map_err(|err| err.to_string())
+        }
         "get_app_settings" => {
             let mut settings = state.app_settings.lock().await.clone();
             if let Ok(Some(collab_enabled)) = codex_config::read_collab_enabled() {
@@ -1696,11 +6335,56 @@ async fn handle_rpc_request(
             let thread_id = parse_string(&params, "threadId")?;
             state.resume_thread(workspace_id, thread_id).await
         }
+        "import_threads" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let dry_run = parse_optional_bool(&params, "dryRun").unwrap_or(false);
+            state.import_threads(workspace_id, dry_run).await
+        }
+        "resume_last_thread" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            Ok(state.resume_last_thread(workspace_id).await?)
+        }
         "list_threads" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let cursor = parse_optional_string(&params, "cursor");
             let limit = parse_optional_u32(&params, "limit");
-            state.list_threads(workspace_id, cursor, limit).await
+            let page = state.list_threads(workspace_id, cursor, limit).await?;
+            serde_json::to_value(page).map_err(|err| err.to_string())
+        }
+        "workspace_activity" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let limit = parse_optional_u32(&params, "limit");
+            let since = parse_optional_u64(&params, "since");
+            let items = state
+                .workspace_activity(&workspace_id, limit, since)
+                .await?;
+            Ok(json!({ "items": items }))
+        }
+        "mark_thread_read" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_optional_string(&params, "threadId");
+            let all = parse_optional_bool(&params, "all").unwrap_or(false);
+            let item_id = parse_optional_string(&params, "itemId");
+            let timestamp = parse_optional_u64(&params, "timestamp");
+            state
+                .mark_thread_read(workspace_id, thread_id, all, item_id, timestamp)
+                .await
+        }
+        "set_thread_title" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let title = parse_optional_string(&params, "title");
+            state.set_thread_title(workspace_id, thread_id, title).await
+        }
+        "turn_inputs" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let cursor = parse_optional_string(&params, "cursor");
+            let limit = parse_optional_u32(&params, "limit");
+            let page = state
+                .turn_inputs(&workspace_id, &thread_id, cursor.as_deref(), limit)
+                .await?;
+            serde_json::to_value(page).map_err(|err| err.to_string())
         }
         "archive_thread" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
@@ -1709,24 +6393,43 @@ async fn handle_rpc_request(
         }
         "send_user_message" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
-            let thread_id = parse_string(&params, "threadId")?;
-            let text = parse_string(&params, "text")?;
-            let model = parse_optional_string(&params, "model");
-            let effort = parse_optional_string(&params, "effort");
+            let message = UserMessage {
+                thread_id: parse_string(&params, "threadId")?,
+                text: parse_string(&params, "text")?,
+                model: parse_optional_string(&params, "model"),
+                effort: parse_optional_string(&params, "effort"),
+                access_mode: parse_optional_string(&params, "accessMode"),
+                images: parse_optional_string_array(&params, "images"),
+                collaboration_mode: parse_optional_value(&params, "collaborationMode"),
+            };
+            let queue = parse_optional_bool(&params, "queue").unwrap_or(false);
+            let strict = parse_optional_bool(&params, "strict").unwrap_or(false);
+            Ok(state
+                .send_user_message(workspace_id, message, queue, strict)
+                .await?)
+        }
+        "seed_thread" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let source = parse_seed_source(&params)?;
+            let message = UserMessage {
+                thread_id: String::new(),
+                text: String::new(),
+                model: parse_optional_string(&params, "model"),
+                effort: parse_optional_string(&params, "effort"),
+                access_mode: parse_optional_string(&params, "accessMode"),
+                images: None,
+                collaboration_mode: parse_optional_value(&params, "collaborationMode"),
+            };
+            let queue = parse_optional_bool(&params, "queue").unwrap_or(false);
+            Ok(state
+                .seed_thread(workspace_id, source, message, queue)
+                .await?)
+        }
+        "preview_message_policy" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
             let access_mode = parse_optional_string(&params, "accessMode");
-            let images = parse_optional_string_array(&params, "images");
-            let collaboration_mode = parse_optional_value(&params, "collaborationMode");
             state
-                .send_user_message(
-                    workspace_id,
-                    thread_id,
-                    text,
-                    model,
-                    effort,
-                    access_mode,
-                    images,
-                    collaboration_mode,
-                )
+                .preview_message_policy(workspace_id, access_mode)
                 .await
         }
         "turn_interrupt" => {
@@ -1735,6 +6438,16 @@ async fn handle_rpc_request(
             let turn_id = parse_string(&params, "turnId")?;
             state.turn_interrupt(workspace_id, thread_id, turn_id).await
         }
+        "wait_turn" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let thread_id = parse_string(&params, "threadId")?;
+            let turn_id = parse_string(&params, "turnId")?;
+            let timeout_ms = parse_optional_u64(&params, "timeoutMs");
+            let outcome = state
+                .wait_turn(&workspace_id, &thread_id, &turn_id, timeout_ms)
+                .await?;
+            serde_json::to_value(outcome).map_err(|err| err.to_string())
+        }
         "start_review" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             let thread_id = parse_string(&params, "threadId")?;
@@ -1744,12 +6457,19 @@ async fn handle_rpc_request(
                 .cloned()
                 .ok_or("missing `target`")?;
             let delivery = parse_optional_string(&params, "delivery");
-            state.start_review(workspace_id, thread_id, target, delivery).await
+            Ok(state
+                .start_review(workspace_id, thread_id, target, delivery)
+                .await?)
         }
         "model_list" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             state.model_list(workspace_id).await
         }
+        "models_detailed" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let models = state.models_detailed(workspace_id).await?;
+            serde_json::to_value(models).map_err(|err| err.to_string())
+        }
         "collaboration_mode_list" => {
             let workspace_id = parse_string(&params, "workspaceId")?;
             state.collaboration_mode_list(workspace_id).await
@@ -1775,67 +6495,575 @@ async fn handle_rpc_request(
                 .respond_to_server_request(workspace_id, request_id, result)
                 .await
         }
-        "remember_approval_rule" => {
-            let workspace_id = parse_string(&params, "workspaceId")?;
-            let command = parse_string_array(&params, "command")?;
-            state.remember_approval_rule(workspace_id, command).await
+        "remember_approval_rule" => {
+            let workspace_id = parse_string(&params, "workspaceId")?;
+            let command = parse_string_array(&params, "command")?;
+            state.remember_approval_rule(workspace_id, command).await
+        }
+        "verify_workspaces" => {
+            let workspaces = state.verify_workspaces().await;
+            serde_json::to_value(workspaces).map_err(|err| err.to_string())
+        }
+        "gc_worktrees" => {
+            let apply = parse_optional_bool(&params, "apply").unwrap_or(false);
+            let report = state.gc_worktrees(apply, authorize_workspace).await;
+            serde_json::to_value(report).map_err(|err| err.to_string())
+        }
+        "data_dir_report" => {
+            let cleanup = parse_optional_string_array(&params, "cleanup").unwrap_or_default();
+            let cleanup = parse_cleanup_categories(&cleanup)?;
+            let context = context.clone();
+            let run = |state: Arc<DaemonState>, progress: OperationProgress| async move {
+                let authorize = |id: &str| {
+                    state
+                        .authorization
+                        .authorize("data_dir_report", Some(id), &context)
+                };
+                let report = state.data_dir_report(cleanup, &progress, authorize).await;
+                serde_json::to_value(report).map_err(|err| err.to_string())
+            };
+            run_operation(state, &params, "data_dir_report", false, run).await
+        }
+        "disk_usage" => {
+            let report = state.disk_usage().await?;
+            serde_json::to_value(report).map_err(|err| err.to_string())
+        }
+        "list_webhooks" => Ok(json!(state.webhooks.endpoints())),
+        "set_webhook" => {
+            let update = WebhookUpdate {
+                id: parse_optional_string(&params, "id"),
+                url: parse_optional_string(&params, "url"),
+                secret: parse_optional_string(&params, "secret"),
+                events: parse_optional_string_array(&params, "events"),
+                workspace_ids: parse_optional_string_array(&params, "workspaceIds"),
+            };
+            let endpoint = state.webhooks.set_endpoint(update)?;
+            serde_json::to_value(endpoint).map_err(|err| err.to_string())
+        }
+        "remove_webhook" => {
+            let id = parse_string(&params, "id")?;
+            let removed = state.webhooks.remove_endpoint(&id)?;
+            Ok(json!({ "removed": removed }))
+        }
+        "list_webhook_deliveries" => {
+            let endpoint_id = parse_optional_string(&params, "endpointId");
+            let limit = parse_optional_u32(&params, "limit")
+                .unwrap_or(50)
+                .clamp(1, 200);
+            let deliveries = state
+                .webhooks
+                .deliveries(endpoint_id.as_deref(), limit as usize);
+            Ok(json!({ "deliveries": deliveries }))
+        }
+        "test_webhook" => {
+            let id = parse_string(&params, "id")?;
+            let delivery = state.webhooks.test(&id).await?;
+            serde_json::to_value(delivery).map_err(|err| err.to_string())
         }
         _ => Err(format!("unknown method: {method}")),
-    }
+    };
+    result.map_err(|message| RpcError::classified(method, message))
 }
 
+/// Sends the connection every event its thread subscriptions and the
+/// policy let it see.
 async fn forward_events(
-    mut rx: broadcast::Receiver<DaemonEvent>,
-    out_tx_events: mpsc::UnboundedSender<String>,
+    mut rx: broadcast::Receiver<SequencedEvent>,
+    out_tx_events: OutboundSender,
+    state: Arc<DaemonState>,
+    context: ConnectionContext,
 ) {
     loop {
         let event = match rx.recv().await {
             Ok(event) => event,
-            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Lagged(dropped)) => {
+                // Tell the client once per connection; the counters keep growing.
+                if state.event_lag.record(context.peer, dropped) {
+                    let notification = json!({
+                        "method": "events-resync-recommended",
+                        "params": {
+                            "latestSeq": state.event_sink.latest_seq(),
+                            "droppedEvents": dropped,
+                        },
+                    });
+                    if !out_tx_events.send(notification.to_string()) {
+                        break;
+                    }
+                }
+                continue;
+            }
             Err(broadcast::error::RecvError::Closed) => break,
         };
 
-        let Some(payload) = build_event_notification(event) else {
+        let notification = event_notification(event);
+        if !context.thread_subscriptions.allows(&notification)
+            || !state.authorization.allows_event(&notification, &context)
+        {
+            continue;
+        }
+        let Ok(payload) = serde_json::to_string(&notification) else {
             continue;
         };
 
-        if out_tx_events.send(payload).is_err() {
+        if !out_tx_events.send(payload) {
             break;
         }
     }
 }
 
-async fn handle_client(
+/// Offers every event to the webhooks, which post the selected ones.
+async fn forward_webhook_events(
+    mut rx: broadcast::Receiver<SequencedEvent>,
+    webhooks: Arc<Webhooks>,
+) {
+    loop {
+        let event = match rx.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(dropped)) => {
+                eprintln!("codex-monitor-daemon: webhook missed {dropped} events");
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        webhooks.offer(&event_notification(event));
+    }
+}
+
+async fn serve_http(
+    listener: TcpListener,
+    config: Arc<DaemonConfig>,
+    state: Arc<DaemonState>,
+    events: broadcast::Sender<SequencedEvent>,
+) {
+    loop {
+        let Ok((socket, peer)) = listener.accept().await else {
+            continue;
+        };
+        let config = Arc::clone(&config);
+        let state = Arc::clone(&state);
+        let events = events.clone();
+        tokio::spawn(async move {
+            handle_http_client(socket, peer, config, state, events).await;
+        });
+    }
+}
+
+/// Serves one request of the read-only HTTP endpoint: `/events` streams
+/// notifications as server-sent events, `/workspace/<id>/file/<path>` a
+/// workspace file, and the other routes answer once with the JSON result of
+/// the method behind them.
+async fn handle_http_client(
     socket: TcpStream,
+    peer: SocketAddr,
     config: Arc<DaemonConfig>,
     state: Arc<DaemonState>,
-    events: broadcast::Sender<DaemonEvent>,
+    events: broadcast::Sender<SequencedEvent>,
 ) {
+    let started_at = Instant::now();
     let (reader, mut writer) = socket.into_split();
-    let mut lines = BufReader::new(reader).lines();
+    let mut reader = BufReader::new(reader);
+    let head = tokio::time::timeout(HEAD_TIMEOUT, read_request_head(&mut reader))
+        .await
+        .ok()
+        .flatten();
+    let Some(request) = head.and_then(|head| parse_request_head(&head).ok()) else {
+        let _ = writer
+            .write_all(error_response(400, "bad request").as_bytes())
+            .await;
+        return;
+    };
+    let mut context = ConnectionContext {
+        peer,
+        token_label: None,
+        thread_subscriptions: Arc::default(),
+    };
+    let access_method = format!("http:{}", request.path);
+    let route = route(&request);
+    let public = config.metrics_no_auth && route == Some(HttpRoute::Metrics);
+    if let Some(expected) = config.token.as_ref().filter(|_| !public) {
+        if request.token.as_deref() != Some(expected.as_str()) {
+            state.record_access(
+                &context,
+                &access_method,
+                AccessStatus::Unauthorized,
+                started_at,
+            );
+            let _ = writer
+                .write_all(error_response(401, "unauthorized").as_bytes())
+                .await;
+            return;
+        }
+        context.token_label = Some(SHARED_TOKEN_LABEL);
+    } else if config.token.is_none() {
+        context.token_label = Some(NO_AUTH_TOKEN_LABEL);
+    }
+    let policy_method = match route {
+        Some(HttpRoute::Metrics) => Some("metrics"),
+        Some(HttpRoute::Events) => Some("events"),
+        _ => None,
+    };
+    if let Some(method) = policy_method {
+        if let Err(error) = state.authorization.authorize(method, None, &context) {
+            state.record_access(&context, &access_method, AccessStatus::Error, started_at);
+            let _ = writer
+                .write_all(error_response(403, &error).as_bytes())
+                .await;
+            return;
+        }
+    }
 
-    let (out_tx, mut out_rx) = mpsc::unbounded_channel::<String>();
-    let write_task = tokio::spawn(async move {
-        while let Some(message) = out_rx.recv().await {
-            if writer.write_all(message.as_bytes()).await.is_err() {
-                break;
-            }
-            if writer.write_all(b"\n").await.is_err() {
-                break;
+    let response = match route {
+        _ if request.method != "GET" => error_response(405, "only GET is supported"),
+        None => error_response(404, "not found"),
+        Some(HttpRoute::Metrics) => {
+            state.record_access(&context, &access_method, AccessStatus::Ok, started_at);
+            metrics_response(&state.render_metrics().await)
+        }
+        Some(HttpRoute::Events) => {
+            state.record_access(&context, &access_method, AccessStatus::Ok, started_at);
+            stream_http_events(writer, events.subscribe(), &state, &context).await;
+            return;
+        }
+        Some(HttpRoute::File { workspace_id, path }) => {
+            let served = serve_workspace_file(
+                &mut writer,
+                &state,
+                &context,
+                &request,
+                &workspace_id,
+                &path,
+            )
+            .await;
+            let status = if served {
+                AccessStatus::Ok
+            } else {
+                AccessStatus::Error
+            };
+            state.record_access(&context, &access_method, status, started_at);
+            let _ = writer.shutdown().await;
+            return;
+        }
+        Some(HttpRoute::Rpc(method, params)) => {
+            let client_version = format!("daemon-{}", env!("CARGO_PKG_VERSION"));
+            let result = handle_rpc_request(&state, &context, method, params, client_version).await;
+            let status = if result.is_ok() {
+                AccessStatus::Ok
+            } else {
+                AccessStatus::Error
+            };
+            state.record_access(&context, method, status, started_at);
+            match result {
+                Ok(result) => json_response(200, &result),
+                Err(error) => json_response(
+                    rpc_error_status(&error.message),
+                    &json!({
+                        "error": {
+                            "code": error.code,
+                            "message": error.message,
+                            "data": error.data,
+                        }
+                    }),
+                ),
             }
         }
-    });
+    };
+    let _ = writer.write_all(response.as_bytes()).await;
+    let _ = writer.shutdown().await;
+}
+
+/// Writes every event the policy lets the client see as it is broadcast,
+/// until the client goes away, with a comment line whenever the stream has
+/// been quiet for `SSE_KEEP_ALIVE`.
+async fn stream_http_events(
+    mut writer: OwnedWriteHalf,
+    mut rx: broadcast::Receiver<SequencedEvent>,
+    state: &DaemonState,
+    context: &ConnectionContext,
+) {
+    if writer.write_all(sse_head().as_bytes()).await.is_err() {
+        return;
+    }
+    let mut keep_alive = tokio::time::interval(SSE_KEEP_ALIVE);
+    keep_alive.tick().await;
+    loop {
+        let chunk = tokio::select! {
+            received = rx.recv() => match received {
+                Ok(event) => {
+                    let seq = event.seq;
+                    let notification = event_notification(event);
+                    if !state.authorization.allows_event(&notification, context) {
+                        continue;
+                    }
+                    let Ok(notification) = serde_json::to_string(&notification) else {
+                        continue;
+                    };
+                    sse_event(Some(seq), &notification)
+                }
+                Err(broadcast::error::RecvError::Lagged(dropped)) => {
+                    let notification = json!({
+                        "method": "events-resync-recommended",
+                        "params": {
+                            "latestSeq": state.event_sink.latest_seq(),
+                            "droppedEvents": dropped,
+                        },
+                    });
+                    sse_event(None, &notification.to_string())
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            _ = keep_alive.tick() => ": keep-alive\n\n".to_string(),
+        };
+        if writer.write_all(chunk.as_bytes()).await.is_err() {
+            break;
+        }
+        keep_alive.reset();
+    }
+}
+
+/// Streams a workspace file under the policy rules of `read_workspace_file`
+/// and the same path checks. Directories and paths outside the workspace
+/// are 404s. Returns whether the file, or a 304, was sent.
+async fn serve_workspace_file(
+    writer: &mut OwnedWriteHalf,
+    state: &DaemonState,
+    context: &ConnectionContext,
+    request: &HttpRequest,
+    workspace_id: &str,
+    path: &str,
+) -> bool {
+    let policy = &state.authorization;
+    if let Err(error) = policy.authorize("read_workspace_file", Some(workspace_id), context) {
+        let _ = writer
+            .write_all(error_response(403, &error).as_bytes())
+            .await;
+        return false;
+    }
+    let root = state
+        .workspaces
+        .lock()
+        .await
+        .get(workspace_id)
+        .map(|entry| PathBuf::from(&entry.path));
+    let resolved = root
+        .ok_or_else(|| "workspace not found".to_string())
+        .and_then(|root| resolve_workspace_file(&root, path));
+    let Ok((file_path, metadata)) = resolved else {
+        let _ = writer
+            .write_all(error_response(404, "not found").as_bytes())
+            .await;
+        return false;
+    };
+    let total = metadata.len();
+    let etag = file_etag(&metadata);
+    let header = |name: &str| request.headers.get(name).map(String::as_str);
+    if etag_matches(header("if-none-match"), &etag) {
+        let _ = writer
+            .write_all(empty_response(304, &etag, total).as_bytes())
+            .await;
+        return true;
+    }
+    let (status, start, length, range) = match byte_range(header("range"), total) {
+        ByteRange::Full => (200, 0, total, None),
+        ByteRange::Partial(start, end) => (206, start, end - start + 1, Some((start, end, total))),
+        ByteRange::Unsatisfiable => {
+            let _ = writer
+                .write_all(empty_response(416, &etag, total).as_bytes())
+                .await;
+            return false;
+        }
+    };
+    let mut file = match tokio::fs::File::open(&file_path).await {
+        Ok(file) => file,
+        Err(err) => {
+            let _ = writer
+                .write_all(error_response(404, &err.to_string()).as_bytes())
+                .await;
+            return false;
+        }
+    };
+    if start > 0 && file.seek(SeekFrom::Start(start)).await.is_err() {
+        let _ = writer
+            .write_all(error_response(500, "failed to read file").as_bytes())
+            .await;
+        return false;
+    }
+    let head = file_head(status, content_type(path), &etag, length, range);
+    if writer.write_all(head.as_bytes()).await.is_err() {
+        return false;
+    }
+    tokio::io::copy(&mut file.take(length), writer)
+        .await
+        .is_ok()
+}
+
+/// Token label recorded in the access log; there is a single shared token for now.
+const SHARED_TOKEN_LABEL: &str = "shared";
+const NO_AUTH_TOKEN_LABEL: &str = "no-auth";
+/// Peer recorded for the `--stdio` connection, which has no address.
+const STDIO_PEER: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+/// How long a closed connection's queued output may take to be written.
+const OUTBOUND_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An authenticated request read off a connection.
+struct ConnectionRequest {
+    id: Option<u64>,
+    method: String,
+    params: Value,
+    /// From `normalize_params`.
+    warnings: Vec<String>,
+    /// Negotiated with `envelope: true` on `auth`.
+    envelope: bool,
+    started_at: Instant,
+}
+
+/// Dispatches `request` and queues its response.
+async fn respond_to_request(
+    state: Arc<DaemonState>,
+    context: ConnectionContext,
+    out_tx: OutboundSender,
+    request: ConnectionRequest,
+) {
+    let ConnectionRequest {
+        id,
+        method,
+        params,
+        warnings,
+        envelope,
+        started_at,
+    } = request;
+    let client_version = format!("daemon-{}", env!("CARGO_PKG_VERSION"));
+    let wants_meta = params.get("meta").and_then(Value::as_bool) == Some(true);
+    let (result, meta) = if wants_meta {
+        let (result, meta) = handle_rpc_request_timed(
+            &state,
+            &context,
+            &method,
+            params,
+            client_version,
+            started_at,
+        )
+        .await;
+        (result, Some(meta))
+    } else {
+        let result = handle_rpc_request(&state, &context, &method, params, client_version).await;
+        (result, None)
+    };
+    let status = if result.is_ok() {
+        AccessStatus::Ok
+    } else {
+        AccessStatus::Error
+    };
+    let capped = result.is_ok();
+    let response = match result {
+        Ok(result) => build_result_response(id, result),
+        Err(error) => build_rpc_error_response(id, &error),
+    };
+    let response = match meta {
+        Some(meta) => response.map(|response| attach_response_meta(response, meta)),
+        None => response,
+    };
+    let response = if envelope {
+        let duration = started_at.elapsed();
+        response.map(|response| attach_response_envelope(response, &method, duration))
+    } else {
+        response
+    };
+    let response = if warnings.is_empty() {
+        response
+    } else {
+        response.map(|response| attach_response_warnings(response, &warnings))
+    };
+    // Capped last, so `_meta`, `envelope` and `warnings` count against the limit.
+    let response = if capped {
+        let max_response_bytes = state.framing.max_response_bytes;
+        response.map(|response| cap_response(&state, response, max_response_bytes))
+    } else {
+        response
+    };
+    if let Some(response) = response {
+        let _ = out_tx.send(response);
+    }
+    state.record_access(&context, &method, status, started_at);
+}
+
+async fn handle_client(
+    socket: TcpStream,
+    peer: SocketAddr,
+    config: Arc<DaemonConfig>,
+    state: Arc<DaemonState>,
+    events: broadcast::Sender<SequencedEvent>,
+) {
+    let (reader, writer) = socket.into_split();
+    serve_connection(reader, writer, peer, config, state, events).await;
+}
 
+/// Runs the RPC protocol over one connection, a TCP client or `--stdio`.
+async fn serve_connection<R, W>(
+    reader: R,
+    writer: W,
+    peer: SocketAddr,
+    config: Arc<DaemonConfig>,
+    state: Arc<DaemonState>,
+    events: broadcast::Sender<SequencedEvent>,
+) where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let mut reader = BufReader::new(reader);
+    let limits = state.framing;
+
+    let (out_tx, out_rx) = outbound_queue(limits.max_outbound_queue_bytes);
+    let write_task = tokio::spawn(write_outbound(writer, out_rx));
+
+    state.active_connections.fetch_add(1, Ordering::Relaxed);
+    let mut rate_limiter = ConnectionRateLimiter::new(state.rate_limits);
     let mut authenticated = config.token.is_none();
+    // Negotiated with `envelope: true` on `auth`.
+    let mut envelope = false;
+    let mut context = ConnectionContext {
+        peer,
+        token_label: authenticated.then_some(NO_AUTH_TOKEN_LABEL),
+        thread_subscriptions: Arc::default(),
+    };
     let mut events_task: Option<tokio::task::JoinHandle<()>> = None;
+    let mut waits = tokio::task::JoinSet::new();
 
     if authenticated {
         let rx = events.subscribe();
         let out_tx_events = out_tx.clone();
-        events_task = Some(tokio::spawn(forward_events(rx, out_tx_events)));
+        events_task = Some(tokio::spawn(forward_events(
+            rx,
+            out_tx_events,
+            Arc::clone(&state),
+            context.clone(),
+        )));
     }
 
-    while let Ok(Some(line)) = lines.next_line().await {
+    while !out_tx.is_overflowed() {
+        let read = read_line_limited(&mut reader, limits.max_request_bytes, LINE_READ_TIMEOUT);
+        let read = if authenticated {
+            read.await
+        } else {
+            match tokio::time::timeout(AUTH_TIMEOUT, read).await {
+                Ok(read) => read,
+                Err(_) => break,
+            }
+        };
+        let line = match read {
+            Ok(ReadLine::Line(line)) => line,
+            Ok(ReadLine::TooLong(length)) => {
+                state.framing_counters.record_oversized_request();
+                let _ = out_tx.send(build_request_too_large_response(
+                    length,
+                    limits.max_request_bytes,
+                ));
+                match discard_line(&mut reader, LINE_READ_TIMEOUT).await {
+                    Ok(true) => continue,
+                    Ok(false) | Err(_) => break,
+                }
+            }
+            Ok(ReadLine::Eof) | Err(_) => break,
+        };
         let line = line.trim();
         if line.is_empty() {
             continue;
@@ -1843,7 +7071,17 @@ async fn handle_client(
 
         let message: Value = match serde_json::from_str(line) {
             Ok(value) => value,
-            Err(_) => continue,
+            Err(err) => {
+                let _ = out_tx.send(build_parse_error_response(&format!("invalid JSON: {err}")));
+                continue;
+            }
+        };
+        let message = match decompress_message(message, limits.max_request_bytes) {
+            Ok(message) => message,
+            Err(err) => {
+                let _ = out_tx.send(build_parse_error_response(&err));
+                continue;
+            }
         };
 
         let id = message.get("id").and_then(|value| value.as_u64());
@@ -1852,13 +7090,41 @@ async fn handle_client(
             .and_then(|value| value.as_str())
             .unwrap_or("")
             .to_string();
-        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let mut params = message.get("params").cloned().unwrap_or(Value::Null);
+        let warnings = normalize_params(&method, &mut params);
+        let started_at = Instant::now();
+
+        // Checked before auth so token guessing is throttled as well.
+        if let Err(retry_after) = rate_limiter.check(&method) {
+            state.rate_limited_requests.fetch_add(1, Ordering::Relaxed);
+            let retry_after_ms = u64::try_from(retry_after.as_millis())
+                .unwrap_or(u64::MAX)
+                .max(1);
+            if let Some(response) = build_rate_limited_response(id, retry_after_ms) {
+                let _ = out_tx.send(response);
+            }
+            state.record_access(&context, &method, AccessStatus::RateLimited, started_at);
+            continue;
+        }
+
+        // Without a token there is nothing to check, but `auth` still
+        // negotiates connection options.
+        if method == "auth" && config.token.is_none() {
+            envelope = parse_optional_bool(&params, "envelope").unwrap_or(false);
+            let result = apply_auth_options(&params, &out_tx);
+            if let Some(response) = build_result_response(id, result) {
+                let _ = out_tx.send(response);
+            }
+            state.record_access(&context, &method, AccessStatus::Ok, started_at);
+            continue;
+        }
 
         if !authenticated {
             if method != "auth" {
                 if let Some(response) = build_error_response(id, "unauthorized") {
                     let _ = out_tx.send(response);
                 }
+                state.record_access(&context, &method, AccessStatus::Unauthorized, started_at);
                 continue;
             }
 
@@ -1868,47 +7134,264 @@ async fn handle_client(
                 if let Some(response) = build_error_response(id, "invalid token") {
                     let _ = out_tx.send(response);
                 }
+                state.record_access(&context, &method, AccessStatus::Unauthorized, started_at);
                 continue;
             }
 
             authenticated = true;
-            if let Some(response) = build_result_response(id, json!({ "ok": true })) {
+            envelope = parse_optional_bool(&params, "envelope").unwrap_or(false);
+            context.token_label = Some(SHARED_TOKEN_LABEL);
+            let result = apply_auth_options(&params, &out_tx);
+            if let Some(response) = build_result_response(id, result) {
                 let _ = out_tx.send(response);
             }
+            state.record_access(&context, &method, AccessStatus::Ok, started_at);
 
             let rx = events.subscribe();
             let out_tx_events = out_tx.clone();
-            events_task = Some(tokio::spawn(forward_events(rx, out_tx_events)));
+            events_task = Some(tokio::spawn(forward_events(
+                rx,
+                out_tx_events,
+                Arc::clone(&state),
+                context.clone(),
+            )));
 
             continue;
         }
 
-        let client_version = format!("daemon-{}", env!("CARGO_PKG_VERSION"));
-        let result = handle_rpc_request(&state, &method, params, client_version).await;
-        let response = match result {
-            Ok(result) => build_result_response(id, result),
-            Err(message) => build_error_response(id, &message),
+        let request = ConnectionRequest {
+            id,
+            method,
+            params,
+            warnings,
+            envelope,
+            started_at,
         };
-        if let Some(response) = response {
-            let _ = out_tx.send(response);
+        // Waits can take up to an hour; requests behind them keep flowing.
+        if request.method == "wait_turn" {
+            while waits.try_join_next().is_some() {}
+            waits.spawn(respond_to_request(
+                Arc::clone(&state),
+                context.clone(),
+                out_tx.clone(),
+                request,
+            ));
+        } else {
+            respond_to_request(Arc::clone(&state), context.clone(), out_tx.clone(), request).await;
         }
     }
 
+    waits.abort_all();
+    if out_tx.is_overflowed() {
+        state.framing_counters.record_outbound_overflow();
+    }
     drop(out_tx);
     if let Some(task) = events_task {
         task.abort();
     }
-    write_task.abort();
+    // Responses to the last requests are still queued when the client stops
+    // sending, so let them go out before closing.
+    let mut write_task = write_task;
+    if tokio::time::timeout(OUTBOUND_FLUSH_TIMEOUT, &mut write_task)
+        .await
+        .is_err()
+    {
+        write_task.abort();
+    }
+    state.event_lag.forget(peer);
+    state.active_connections.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// Workspace checks, background fetches, idle disconnects, thread auto-titles,
+/// trash and artifact pruning, rate limit polls, environment preflight checks
+/// and the startup worktree GC report; a mirror runs none of them.
+async fn spawn_background_tasks(state: &Arc<DaemonState>) {
+    {
+        let state = Arc::clone(state);
+        tokio::spawn(async move {
+            let workspaces = state.verify_workspaces().await;
+            for workspace in workspaces {
+                match workspace.status {
+                    Some(WorkspaceEntryStatus::Ok) | None => {}
+                    Some(status) => {
+                        let error = NewError::new(
+                            ErrorSource::Check,
+                            format!("workspace {} is inconsistent: {status:?}", workspace.path),
+                        );
+                        state.record_workspace_error(&workspace.id, error).await;
+                    }
+                }
+            }
+        });
+    }
+
+    {
+        let state = Arc::clone(state);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(FETCH_TICK);
+            loop {
+                ticker.tick().await;
+                state.background_fetch_round().await;
+            }
+        });
+    }
+
+    {
+        let state = Arc::clone(state);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(IDLE_CHECK_INTERVAL);
+            loop {
+                ticker.tick().await;
+                state.disconnect_idle_sessions().await;
+            }
+        });
+    }
+
+    {
+        let state = Arc::clone(state);
+        tokio::spawn(async move {
+            state.auto_title_threads().await;
+        });
+    }
+
+    {
+        let state = Arc::clone(state);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(TRASH_PURGE_INTERVAL);
+            loop {
+                ticker.tick().await;
+                for id in state.purge_expired_trash().await {
+                    eprintln!("codex-monitor-daemon: purged trash item {id}");
+                }
+            }
+        });
+    }
+
+    if let Some(interval) = state.rate_limit_poll {
+        let state = Arc::clone(state);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                state.poll_rate_limits().await;
+            }
+        });
+    }
+
+    {
+        let state = Arc::clone(state);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(ARTIFACT_PRUNE_INTERVAL);
+            loop {
+                ticker.tick().await;
+                let pruned = state.prune_artifacts().await;
+                if pruned > 0 {
+                    eprintln!("codex-monitor-daemon: pruned {pruned} command output artifacts");
+                }
+            }
+        });
+    }
+
+    {
+        let state = Arc::clone(state);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(PREFLIGHT_INTERVAL);
+            loop {
+                ticker.tick().await;
+                if let Err(err) = state.preflight_check(None).await {
+                    eprintln!("codex-monitor-daemon: preflight check failed: {err}");
+                }
+            }
+        });
+    }
+
+    let gc_on_startup = state.app_settings.lock().await.worktree_gc_on_startup;
+    if gc_on_startup {
+        let state = Arc::clone(state);
+        tokio::spawn(async move {
+            state.report_orphaned_worktrees().await;
+        });
+    }
 }
 
 fn main() {
-    let config = match parse_args() {
+    let mut config = match parse_args() {
         Ok(config) => config,
         Err(err) => {
             eprintln!("{err}\n\n{}", usage());
             std::process::exit(2);
         }
     };
+    if config.daemonize {
+        // The daemonized process runs from `/`.
+        config.data_dir = absolute_path(config.data_dir);
+        config.pid_file = config.pid_file.map(absolute_path);
+        config.access_log = config.access_log.map(absolute_path);
+    }
+
+    let authorization = match config.auth_policy.as_deref() {
+        Some(path) => match AuthorizationPolicy::load(path) {
+            Ok(policy) => policy,
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        },
+        None => AuthorizationPolicy::default(),
+    };
+
+    let access_log = match config
+        .access_log
+        .as_deref()
+        .map(|path| AccessLog::open(path, config.access_log_max_bytes))
+        .transpose()
+    {
+        Ok(access_log) => access_log,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+
+    let pid_file = match config.pid_file.as_deref().map(PidFile::acquire).transpose() {
+        Ok(pid_file) => pid_file,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+
+    // `exit` skips destructors, so the PID file is released by hand.
+    let exit_with = |pid_file: Option<PidFile>, message: String| -> ! {
+        drop(pid_file);
+        eprintln!("{message}");
+        std::process::exit(1);
+    };
+    let bind = |addr: SocketAddr| {
+        std::net::TcpListener::bind(addr)
+            .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+            .map_err(|err| format!("failed to bind {addr}: {err}"))
+    };
+
+    // Bind before detaching so address conflicts are still reported on the terminal.
+    let std_listener = match (!config.stdio).then(|| bind(config.listen)).transpose() {
+        Ok(listener) => listener,
+        Err(err) => exit_with(pid_file, err),
+    };
+    let std_http_listener = match config.listen_http.map(bind).transpose() {
+        Ok(listener) => listener,
+        Err(err) => exit_with(pid_file, err),
+    };
+
+    #[cfg(unix)]
+    if config.daemonize {
+        if let Err(err) = process::daemonize() {
+            exit_with(pid_file, err);
+        }
+        if let Some(Err(err)) = pid_file.as_ref().map(PidFile::refresh) {
+            exit_with(pid_file, err);
+        }
+    }
 
     let runtime = tokio::runtime::Builder::new_current_thread()
         .enable_all()
@@ -1916,38 +7399,115 @@ fn main() {
         .expect("failed to build tokio runtime");
 
     runtime.block_on(async move {
-        let (events_tx, _events_rx) = broadcast::channel::<DaemonEvent>(2048);
+        let (events_tx, _events_rx) = broadcast::channel::<SequencedEvent>(config.event_buffer);
         let event_sink = DaemonEventSink {
             tx: events_tx.clone(),
+            seq: Arc::new(AtomicU64::new(0)),
+            thread_reads: Arc::new(ThreadReads::load(config.data_dir.join("thread_reads.json"))),
+            thread_titles: Arc::new(ThreadTitles::load(
+                config.data_dir.join("thread_titles.json"),
+            )),
+            turns: Arc::new(TurnTracker::default()),
+            activity: Arc::new(ActivityLog::default()),
+            idle: Arc::new(IdleTracker::default()),
+            artifacts: Arc::new(Artifacts::new(config.data_dir.join("artifacts"))),
         };
-        let state = Arc::new(DaemonState::load(&config, event_sink));
+        let state = Arc::new(DaemonState::load(
+            &config,
+            event_sink,
+            access_log,
+            authorization,
+        ));
         let config = Arc::new(config);
 
-        let listener = TcpListener::bind(config.listen)
-            .await
-            .unwrap_or_else(|err| panic!("failed to bind {}: {err}", config.listen));
-        eprintln!(
-            "codex-monitor-daemon listening on {} (data dir: {})",
-            config.listen,
-            state
-                .storage_path
-                .parent()
-                .unwrap_or(&state.storage_path)
-                .display()
-        );
+        tokio::spawn(forward_webhook_events(
+            events_tx.subscribe(),
+            Arc::clone(&state.webhooks),
+        ));
+
+        if let Some(mirror) = state.mirror.clone() {
+            let event_sink = state.event_sink.clone();
+            tokio::spawn(async move {
+                mirror
+                    .run(|method, params| {
+                        event_sink.send(DaemonEvent::Upstream(UpstreamEvent {
+                            method: method.to_string(),
+                            params,
+                        }));
+                    })
+                    .await;
+            });
+        } else {
+            spawn_background_tasks(&state).await;
+        }
+
+        let data_dir = state
+            .storage_path
+            .parent()
+            .unwrap_or(&state.storage_path)
+            .display();
+        let listener = std_listener.map(|std_listener| {
+            TcpListener::from_std(std_listener)
+                .unwrap_or_else(|err| panic!("failed to bind {}: {err}", config.listen))
+        });
+        match &listener {
+            Some(_) => eprintln!(
+                "codex-monitor-daemon listening on {} (data dir: {data_dir})",
+                config.listen
+            ),
+            None => eprintln!("codex-monitor-daemon serving stdio (data dir: {data_dir})"),
+        }
+
+        if let Some(http_listener) = std_http_listener {
+            let http_listener = TcpListener::from_std(http_listener)
+                .unwrap_or_else(|err| panic!("failed to bind HTTP listener: {err}"));
+            tokio::spawn(serve_http(
+                http_listener,
+                Arc::clone(&config),
+                Arc::clone(&state),
+                events_tx.clone(),
+            ));
+        }
 
+        let shutdown = shutdown_signal();
+        tokio::pin!(shutdown);
+        let Some(listener) = listener else {
+            // The process ends with its only connection, once stdin closes.
+            let connection = serve_connection(
+                tokio::io::stdin(),
+                tokio::io::stdout(),
+                STDIO_PEER,
+                Arc::clone(&config),
+                Arc::clone(&state),
+                events_tx.clone(),
+            );
+            tokio::select! {
+                _ = connection => {}
+                _ = &mut shutdown => {}
+            }
+            eprintln!("codex-monitor-daemon shutting down");
+            state.kill_all_sessions().await;
+            return;
+        };
         loop {
-            match listener.accept().await {
-                Ok((socket, _addr)) => {
-                    let config = Arc::clone(&config);
-                    let state = Arc::clone(&state);
-                    let events = events_tx.clone();
-                    tokio::spawn(async move {
-                        handle_client(socket, config, state, events).await;
-                    });
-                }
-                Err(_) => continue,
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((socket, peer)) => {
+                        let config = Arc::clone(&config);
+                        let state = Arc::clone(&state);
+                        let events = events_tx.clone();
+                        tokio::spawn(async move {
+                            handle_client(socket, peer, config, state, events).await;
+                        });
+                    }
+                    Err(_) => continue,
+                },
+                _ = &mut shutdown => break,
             }
         }
+
+        eprintln!("codex-monitor-daemon shutting down");
+        state.kill_all_sessions().await;
     });
+    drop(pid_file);
 }
@@ -0,0 +1,174 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+
+pub(crate) const DEFAULT_ACCESS_LOG_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AccessStatus {
+    Ok,
+    Error,
+    Unauthorized,
+    RateLimited,
+}
+
+/// One line of the access log. Request params are never recorded.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AccessLogEntry<'a> {
+    timestamp: String,
+    peer: String,
+    token: Option<&'a str>,
+    method: &'a str,
+    status: AccessStatus,
+    duration_ms: u64,
+}
+
+/// Append-only JSON lines file. Once it grows past `max_bytes` it is moved to
+/// `<path>.1` (replacing the previous rotation) and a fresh file is started.
+pub(crate) struct AccessLog {
+    path: PathBuf,
+    max_bytes: u64,
+    file: Mutex<Option<File>>,
+}
+
+impl AccessLog {
+    pub(crate) fn open(path: &Path, max_bytes: u64) -> Result<Self, String> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|err| format!("Failed to create access log dir: {err}"))?;
+            }
+        }
+        let file = open_append(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_bytes,
+            file: Mutex::new(Some(file)),
+        })
+    }
+
+    /// The log file and its previous rotation.
+    pub(crate) fn paths(&self) -> [PathBuf; 2] {
+        [self.path.clone(), rotated_path(&self.path)]
+    }
+
+    pub(crate) fn record(
+        &self,
+        peer: SocketAddr,
+        token: Option<&str>,
+        method: &str,
+        status: AccessStatus,
+        duration: Duration,
+    ) {
+        let entry = AccessLogEntry {
+            timestamp: chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+            peer: peer.to_string(),
+            token,
+            method,
+            status,
+            duration_ms: u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
+        };
+        let Ok(mut line) = serde_json::to_string(&entry) else {
+            return;
+        };
+        line.push('\n');
+        if let Err(err) = self.append(line.as_bytes()) {
+            eprintln!("codex-monitor-daemon: access log write failed: {err}");
+        }
+    }
+
+    fn append(&self, bytes: &[u8]) -> Result<(), String> {
+        let mut guard = self
+            .file
+            .lock()
+            .map_err(|_| "access log lock poisoned".to_string())?;
+        let needs_rotation = match guard.as_ref() {
+            Some(file) => file
+                .metadata()
+                .map(|metadata| metadata.len() + bytes.len() as u64 > self.max_bytes)
+                .unwrap_or(false),
+            None => true,
+        };
+        if needs_rotation {
+            *guard = None;
+            if self.path.exists() {
+                let _ = std::fs::rename(&self.path, rotated_path(&self.path));
+            }
+            *guard = Some(open_append(&self.path)?);
+        }
+        let file = guard.as_mut().ok_or("access log unavailable")?;
+        file.write_all(bytes).map_err(|err| err.to_string())
+    }
+}
+
+fn open_append(path: &Path) -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|err| format!("Failed to open access log {}: {err}", path.display()))
+}
+
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".1");
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rotated_path, AccessLog, AccessStatus};
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    #[test]
+    fn records_json_lines_without_params() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-test-{}", Uuid::new_v4()));
+        let path = dir.join("access.log");
+        let log = AccessLog::open(&path, 1024 * 1024).expect("open access log");
+        log.record(
+            "127.0.0.1:5000".parse().unwrap(),
+            Some("default"),
+            "list_workspaces",
+            AccessStatus::Ok,
+            Duration::from_millis(12),
+        );
+
+        let contents = std::fs::read_to_string(&path).expect("read access log");
+        let entry: serde_json::Value =
+            serde_json::from_str(contents.lines().next().expect("one line")).expect("json line");
+        assert_eq!(entry["peer"], "127.0.0.1:5000");
+        assert_eq!(entry["token"], "default");
+        assert_eq!(entry["method"], "list_workspaces");
+        assert_eq!(entry["status"], "ok");
+        assert_eq!(entry["durationMs"], 12);
+        assert!(entry.get("params").is_none());
+    }
+
+    #[test]
+    fn rotates_when_size_cap_is_exceeded() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-test-{}", Uuid::new_v4()));
+        let path = dir.join("access.log");
+        let log = AccessLog::open(&path, 200).expect("open access log");
+        for _ in 0..5 {
+            log.record(
+                "127.0.0.1:5000".parse().unwrap(),
+                None,
+                "ping",
+                AccessStatus::Unauthorized,
+                Duration::ZERO,
+            );
+        }
+
+        assert!(rotated_path(&path).exists());
+        let size = std::fs::metadata(&path).expect("access log metadata").len();
+        assert!(size <= 200);
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Shortest `--poll-rate-limits` interval; shorter ones are raised to it so
+/// the account API isn't hammered.
+pub(crate) const MIN_RATE_LIMIT_POLL: Duration = Duration::from_secs(60);
+/// `usedPercent` from which an update counts as near the limit unless
+/// `--rate-limit-warn-percent` says otherwise.
+pub(crate) const DEFAULT_RATE_LIMIT_WARN_PERCENT: f64 = 90.0;
+
+/// A changed `account/rateLimits/read` result, sent as `rate-limit-update`.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RateLimitUpdate {
+    pub(crate) workspace_id: String,
+    /// The app-server's `rateLimits`, as read.
+    pub(crate) rate_limits: Value,
+    /// Highest `usedPercent` across the windows, if any reported one.
+    pub(crate) max_used_percent: Option<f64>,
+    pub(crate) near_limit: bool,
+}
+
+/// The `rateLimits` of a raw `account/rateLimits/read` response; `None` for
+/// error responses.
+pub(crate) fn rate_limits_of(response: &Value) -> Option<&Value> {
+    if response.get("error").is_some() {
+        return None;
+    }
+    let result = response.get("result").unwrap_or(response);
+    Some(result.get("rateLimits").unwrap_or(result))
+}
+
+fn max_used_percent(rate_limits: &Value) -> Option<f64> {
+    ["primary", "secondary"]
+        .iter()
+        .filter_map(|window| rate_limits.get(window))
+        .filter_map(|window| {
+            ["usedPercent", "used_percent"]
+                .iter()
+                .find_map(|key| window.get(key).and_then(Value::as_f64))
+        })
+        .reduce(f64::max)
+}
+
+/// The last rate limits each session reported to the poller.
+#[derive(Default)]
+pub(crate) struct RateLimitWatch {
+    last: Mutex<HashMap<String, Value>>,
+}
+
+impl RateLimitWatch {
+    /// Remembers a poll result; returns the update to send when it differs
+    /// from the previous one for the workspace.
+    pub(crate) fn record(
+        &self,
+        workspace_id: &str,
+        rate_limits: &Value,
+        warn_percent: f64,
+    ) -> Option<RateLimitUpdate> {
+        let mut last = self.last.lock().unwrap_or_else(|err| err.into_inner());
+        if last.get(workspace_id) == Some(rate_limits) {
+            return None;
+        }
+        last.insert(workspace_id.to_string(), rate_limits.clone());
+        let max_used_percent = max_used_percent(rate_limits);
+        Some(RateLimitUpdate {
+            workspace_id: workspace_id.to_string(),
+            rate_limits: rate_limits.clone(),
+            max_used_percent,
+            near_limit: max_used_percent.is_some_and(|used| used >= warn_percent),
+        })
+    }
+
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        let mut last = self.last.lock().unwrap_or_else(|err| err.into_inner());
+        last.remove(workspace_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rate_limits_of, RateLimitWatch};
+    use serde_json::json;
+
+    #[test]
+    fn reports_changed_limits_and_flags_near_limit() {
+        let watch = RateLimitWatch::default();
+        let response = json!({ "result": { "rateLimits": {
+            "primary": { "usedPercent": 42.0, "windowDurationMins": 300 },
+            "secondary": { "usedPercent": 91.5, "windowDurationMins": 10080 }
+        } } });
+        let limits = rate_limits_of(&response).expect("limits");
+
+        let update = watch.record("ws", limits, 90.0).expect("first poll");
+        assert_eq!(update.max_used_percent, Some(91.5));
+        assert!(update.near_limit);
+        assert!(watch.record("ws", limits, 90.0).is_none());
+
+        let lower = json!({ "primary": { "used_percent": 10 } });
+        let update = watch.record("ws", &lower, 90.0).expect("changed");
+        assert_eq!(update.max_used_percent, Some(10.0));
+        assert!(!update.near_limit);
+
+        watch.forget("ws");
+        assert!(watch.record("ws", &lower, 90.0).is_some());
+        assert!(rate_limits_of(&json!({ "error": { "message": "boom" } })).is_none());
+    }
+}
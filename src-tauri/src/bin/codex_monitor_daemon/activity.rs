@@ -0,0 +1,214 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::fetch::now_ms;
+
+/// Items kept per workspace; the oldest are dropped first.
+const MAX_ACTIVITY_ITEMS: usize = 500;
+pub(crate) const DEFAULT_ACTIVITY_LIMIT: usize = 50;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ActivityKind {
+    TurnStarted,
+    TurnCompleted,
+    FileChange,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FileChange {
+    pub(crate) path: String,
+    /// `add`, `delete` or `update`, when the app-server reported it.
+    pub(crate) kind: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ActivityItem {
+    pub(crate) kind: ActivityKind,
+    /// When the daemon saw the event, in ms since the epoch.
+    pub(crate) at: u64,
+    pub(crate) thread_id: Option<String>,
+    pub(crate) turn_id: Option<String>,
+    /// Turn status (`completed`, `interrupted`, `failed`) or item status.
+    pub(crate) status: Option<String>,
+    pub(crate) error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) changes: Vec<FileChange>,
+}
+
+/// Recent turns and file changes per workspace, newest last, from the
+/// app-server events passing through the daemon. Only covers this run.
+#[derive(Default)]
+pub(crate) struct ActivityLog {
+    items: Mutex<HashMap<String, VecDeque<ActivityItem>>>,
+}
+
+fn string_field(value: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .find_map(|key| value.get(key).and_then(Value::as_str))
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn file_changes(item: &Value) -> Vec<FileChange> {
+    let Some(changes) = item.get("changes").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    changes
+        .iter()
+        .filter_map(|change| {
+            let path = string_field(change, &["path"])?;
+            let kind = match change.get("kind") {
+                Some(Value::String(kind)) => Some(kind.to_lowercase()),
+                Some(kind) => string_field(kind, &["type"]).map(|kind| kind.to_lowercase()),
+                None => None,
+            };
+            Some(FileChange { path, kind })
+        })
+        .collect()
+}
+
+fn parse_item(message: &Value) -> Option<ActivityItem> {
+    let method = message.get("method").and_then(Value::as_str)?;
+    let params = message.get("params")?;
+    let turn = params.get("turn");
+    let thread_id = string_field(params, &["threadId", "thread_id"])
+        .or_else(|| turn.and_then(|turn| string_field(turn, &["threadId", "thread_id"])));
+    let mut item = ActivityItem {
+        kind: ActivityKind::TurnStarted,
+        at: now_ms(),
+        thread_id,
+        turn_id: string_field(params, &["turnId", "turn_id"])
+            .or_else(|| turn.and_then(|turn| string_field(turn, &["id"]))),
+        status: turn.and_then(|turn| string_field(turn, &["status"])),
+        error: None,
+        changes: Vec::new(),
+    };
+    match method {
+        "turn/started" => {}
+        "turn/completed" => {
+            item.kind = ActivityKind::TurnCompleted;
+            item.error = turn.and_then(|turn| turn.get("error")).and_then(|error| {
+                string_field(error, &["message"]).or_else(|| error.as_str().map(str::to_string))
+            });
+        }
+        "item/completed" => {
+            let change = params.get("item")?;
+            if change.get("type").and_then(Value::as_str) != Some("fileChange") {
+                return None;
+            }
+            item.kind = ActivityKind::FileChange;
+            item.status = string_field(change, &["status"]);
+            item.changes = file_changes(change);
+        }
+        _ => return None,
+    }
+    Some(item)
+}
+
+impl ActivityLog {
+    pub(crate) fn record_event(&self, workspace_id: &str, message: &Value) {
+        let Some(item) = parse_item(message) else {
+            return;
+        };
+        let mut items = self.items.lock().unwrap_or_else(|err| err.into_inner());
+        let log = items.entry(workspace_id.to_string()).or_default();
+        if log.len() >= MAX_ACTIVITY_ITEMS {
+            log.pop_front();
+        }
+        log.push_back(item);
+    }
+
+    /// Up to `limit` items, newest first, optionally only those after
+    /// `since_ms`.
+    pub(crate) fn recent(
+        &self,
+        workspace_id: &str,
+        limit: usize,
+        since_ms: Option<u64>,
+    ) -> Vec<ActivityItem> {
+        let items = self.items.lock().unwrap_or_else(|err| err.into_inner());
+        let Some(log) = items.get(workspace_id) else {
+            return Vec::new();
+        };
+        log.iter()
+            .rev()
+            .filter(|item| since_ms.is_none_or(|since| item.at > since))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        let mut items = self.items.lock().unwrap_or_else(|err| err.into_inner());
+        items.remove(workspace_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ActivityKind, ActivityLog, FileChange};
+    use serde_json::json;
+
+    #[test]
+    fn records_turns_and_file_changes_newest_first() {
+        let log = ActivityLog::default();
+        log.record_event(
+            "ws",
+            &json!({ "method": "turn/started", "params": { "threadId": "t1", "turn": { "id": "u1" } } }),
+        );
+        log.record_event(
+            "ws",
+            &json!({ "method": "item/completed", "params": {
+                "threadId": "t1",
+                "turnId": "u1",
+                "item": { "type": "fileChange", "status": "completed", "changes": [
+                    { "path": "src/a.rs", "kind": { "type": "update" } },
+                    { "path": "b.txt", "kind": "add" }
+                ] }
+            } }),
+        );
+        log.record_event(
+            "ws",
+            &json!({ "method": "item/completed", "params": { "threadId": "t1", "item": { "type": "agentMessage" } } }),
+        );
+        log.record_event(
+            "ws",
+            &json!({ "method": "turn/completed", "params": { "threadId": "t1", "turn": {
+                "id": "u1", "status": "failed", "error": { "message": "boom" }
+            } } }),
+        );
+
+        let items = log.recent("ws", 10, None);
+        let kinds: Vec<ActivityKind> = items.iter().map(|item| item.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ActivityKind::TurnCompleted,
+                ActivityKind::FileChange,
+                ActivityKind::TurnStarted
+            ]
+        );
+        assert_eq!(items[0].error.as_deref(), Some("boom"));
+        assert_eq!(items[0].status.as_deref(), Some("failed"));
+        assert_eq!(items[1].turn_id.as_deref(), Some("u1"));
+        assert_eq!(
+            items[1].changes[1],
+            FileChange {
+                path: "b.txt".to_string(),
+                kind: Some("add".to_string()),
+            }
+        );
+        assert_eq!(log.recent("ws", 1, None).len(), 1);
+        let newest = items[0].at;
+        assert!(log.recent("ws", 10, Some(newest)).is_empty());
+
+        log.forget("ws");
+        assert!(log.recent("ws", 10, None).is_empty());
+    }
+}
@@ -0,0 +1,444 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::types::WorkspaceEntry;
+
+/// Output size from which a command's output is saved as an artifact when
+/// the workspace sets no `artifactThresholdBytes`.
+pub(crate) const DEFAULT_ARTIFACT_THRESHOLD_BYTES: u64 = 64 * 1024;
+/// End of a captured output kept inline in the forwarded event.
+const INLINE_TAIL_BYTES: usize = 4 * 1024;
+/// How often artifacts older than the retention are deleted.
+pub(crate) const ARTIFACT_PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Retention when the app settings set no `artifactRetentionDays`.
+pub(crate) const DEFAULT_ARTIFACT_RETENTION_DAYS: u32 = 7;
+
+/// One saved command output.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TurnArtifact {
+    /// `<turn-id>/<item-id>.txt`, relative to the workspace's artifacts dir;
+    /// what `read_turn_artifact` takes.
+    pub(crate) path: String,
+    pub(crate) turn_id: String,
+    pub(crate) item_id: String,
+    pub(crate) bytes: u64,
+    /// Milliseconds since the epoch the output was saved.
+    pub(crate) created_at: u64,
+}
+
+/// Full outputs of long commands, in `<data-dir>/artifacts/<workspace-id>/`,
+/// for workspaces with `captureArtifacts` on.
+pub(crate) struct Artifacts {
+    root: PathBuf,
+    /// Threshold of each workspace that captures artifacts.
+    thresholds: Mutex<HashMap<String, u64>>,
+}
+
+/// An id as a single path component that maps back to it, so distinct ids
+/// never share a file: ASCII letters, digits and `-` are kept, and every
+/// other byte becomes `_` and two hex digits. The empty id is `_`.
+fn path_component(id: &str) -> String {
+    if id.is_empty() {
+        return "_".to_string();
+    }
+    let mut component = String::with_capacity(id.len());
+    for byte in id.bytes() {
+        if byte.is_ascii_alphanumeric() || byte == b'-' {
+            component.push(char::from(byte));
+        } else {
+            component.push_str(&format!("_{byte:02X}"));
+        }
+    }
+    component
+}
+
+/// The id `path_component` turned into `component`.
+fn id_from_component(component: &str) -> Option<String> {
+    if component == "_" {
+        return Some(String::new());
+    }
+    let mut bytes = Vec::with_capacity(component.len());
+    let mut rest = component.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'_' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// `text` from the first char boundary at most `max` bytes before its end.
+fn tail(text: &str, max: usize) -> &str {
+    let mut start = text.len().saturating_sub(max);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+fn sub_dirs(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+impl Artifacts {
+    pub(crate) fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            thresholds: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub(crate) fn workspace_dir(&self, workspace_id: &str) -> PathBuf {
+        self.root.join(path_component(workspace_id))
+    }
+
+    /// Follows the workspaces' `captureArtifacts` and `artifactThresholdBytes`
+    /// settings; called whenever the workspace list is loaded or saved.
+    pub(crate) fn configure<'a>(&self, entries: impl IntoIterator<Item = &'a WorkspaceEntry>) {
+        let thresholds = entries
+            .into_iter()
+            .filter(|entry| entry.settings.capture_artifacts)
+            .map(|entry| {
+                let threshold = entry
+                    .settings
+                    .artifact_threshold_bytes
+                    .unwrap_or(DEFAULT_ARTIFACT_THRESHOLD_BYTES);
+                (entry.id.clone(), threshold)
+            })
+            .collect();
+        *self
+            .thresholds
+            .lock()
+            .unwrap_or_else(|err| err.into_inner()) = thresholds;
+    }
+
+    /// For an `item/completed` of a command whose output is over the
+    /// workspace's threshold: saves the full output and leaves only its end
+    /// in `message`, after a truncation notice, with the artifact as
+    /// `item.artifact`. Other messages are left alone. Inside a tokio
+    /// runtime the file is written on a blocking thread, and failures are
+    /// logged there.
+    pub(crate) fn capture(
+        &self,
+        workspace_id: &str,
+        message: &mut Value,
+    ) -> Result<Option<TurnArtifact>, String> {
+        if message.get("method").and_then(Value::as_str) != Some("item/completed") {
+            return Ok(None);
+        }
+        let Some(threshold) = self
+            .thresholds
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .get(workspace_id)
+            .copied()
+        else {
+            return Ok(None);
+        };
+        let Some(params) = message.get_mut("params") else {
+            return Ok(None);
+        };
+        let Some(turn_id) = params.get("turnId").and_then(Value::as_str) else {
+            return Ok(None);
+        };
+        let turn_id = turn_id.to_string();
+        let Some(item) = params.get_mut("item") else {
+            return Ok(None);
+        };
+        if item.get("type").and_then(Value::as_str) != Some("commandExecution") {
+            return Ok(None);
+        }
+        let Some(item_id) = item.get("id").and_then(Value::as_str).map(str::to_string) else {
+            return Ok(None);
+        };
+        let Some(output) = item.get("aggregatedOutput").and_then(Value::as_str) else {
+            return Ok(None);
+        };
+        if (output.len() as u64) <= threshold {
+            return Ok(None);
+        }
+
+        let turn_dir = self
+            .workspace_dir(workspace_id)
+            .join(path_component(&turn_id));
+        let file_name = format!("{}.txt", path_component(&item_id));
+        let write = {
+            let file = turn_dir.join(&file_name);
+            let output = output.to_string();
+            move || {
+                std::fs::create_dir_all(&turn_dir)
+                    .and_then(|()| std::fs::write(&file, output))
+                    .map_err(|err| format!("Failed to save command output: {err}"))
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                let workspace_id = workspace_id.to_string();
+                runtime.spawn_blocking(move || {
+                    if let Err(err) = write() {
+                        eprintln!(
+                            "codex-monitor-daemon: artifact capture failed in workspace {workspace_id}: {err}"
+                        );
+                    }
+                });
+            }
+            Err(_) => write()?,
+        }
+        let artifact = TurnArtifact {
+            path: format!("{}/{file_name}", path_component(&turn_id)),
+            turn_id,
+            item_id,
+            bytes: output.len() as u64,
+            created_at: millis(SystemTime::now()),
+        };
+        let inline = format!(
+            "[Output truncated: {} bytes saved as artifact {}; showing the last {} bytes.]\n{}",
+            artifact.bytes,
+            artifact.path,
+            INLINE_TAIL_BYTES,
+            tail(output, INLINE_TAIL_BYTES)
+        );
+        item["aggregatedOutput"] = Value::String(inline);
+        item["artifact"] = json!(artifact);
+        Ok(Some(artifact))
+    }
+
+    /// The workspace's artifacts, newest first, optionally of one turn.
+    pub(crate) fn list(&self, workspace_id: &str, turn_id: Option<&str>) -> Vec<TurnArtifact> {
+        let workspace_dir = self.workspace_dir(workspace_id);
+        let turn_dirs = match turn_id {
+            Some(turn_id) => vec![workspace_dir.join(path_component(turn_id))],
+            None => sub_dirs(&workspace_dir),
+        };
+        let mut artifacts = Vec::new();
+        for turn_dir in turn_dirs {
+            let Some(turn) = turn_dir.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let Some(turn_id) = id_from_component(turn) else {
+                continue;
+            };
+            let Ok(entries) = std::fs::read_dir(&turn_dir) else {
+                continue;
+            };
+            for entry in entries.filter_map(Result::ok) {
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                let Some(item_id) = file_name.strip_suffix(".txt").and_then(id_from_component)
+                else {
+                    continue;
+                };
+                let Ok(metadata) = entry.metadata() else {
+                    continue;
+                };
+                artifacts.push(TurnArtifact {
+                    path: format!("{turn}/{file_name}"),
+                    turn_id: turn_id.clone(),
+                    item_id,
+                    bytes: metadata.len(),
+                    created_at: metadata.modified().map(millis).unwrap_or(0),
+                });
+            }
+        }
+        artifacts.sort_by(|a, b| {
+            b.created_at
+                .cmp(&a.created_at)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        artifacts
+    }
+
+    /// Deletes artifacts saved before `cutoff`, and the folders left empty;
+    /// returns how many were deleted.
+    pub(crate) fn prune(&self, cutoff: SystemTime) -> usize {
+        let mut pruned = 0;
+        for workspace_dir in sub_dirs(&self.root) {
+            for turn_dir in sub_dirs(&workspace_dir) {
+                let Ok(entries) = std::fs::read_dir(&turn_dir) else {
+                    continue;
+                };
+                for entry in entries.filter_map(Result::ok) {
+                    let expired = entry
+                        .metadata()
+                        .and_then(|metadata| metadata.modified())
+                        .is_ok_and(|modified| modified < cutoff);
+                    if expired && std::fs::remove_file(entry.path()).is_ok() {
+                        pruned += 1;
+                    }
+                }
+                let _ = std::fs::remove_dir(&turn_dir);
+            }
+            let _ = std::fs::remove_dir(&workspace_dir);
+        }
+        pruned
+    }
+
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        self.thresholds
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(workspace_id);
+        let _ = std::fs::remove_dir_all(self.workspace_dir(workspace_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{id_from_component, path_component, tail, Artifacts, INLINE_TAIL_BYTES};
+    use crate::types::{WorkspaceEntry, WorkspaceKind, WorkspaceSettings};
+    use serde_json::{json, Value};
+    use std::time::{Duration, SystemTime};
+    use uuid::Uuid;
+
+    fn entry(id: &str, threshold: Option<u64>) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            path: "/tmp".to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings {
+                capture_artifacts: true,
+                artifact_threshold_bytes: threshold,
+                ..WorkspaceSettings::default()
+            },
+            status: None,
+        }
+    }
+
+    fn completed(item_type: &str, output: &str) -> Value {
+        json!({ "method": "item/completed", "params": {
+            "threadId": "thread-1",
+            "turnId": "turn-1",
+            "item": { "type": item_type, "id": "call/1", "aggregatedOutput": output },
+        } })
+    }
+
+    #[test]
+    fn captures_long_outputs_of_opted_in_workspaces() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-artifacts-{}", Uuid::new_v4()));
+        let artifacts = Artifacts::new(root.clone());
+        artifacts.configure(&[entry("ws", Some(10))]);
+
+        let mut short = completed("commandExecution", "ok");
+        assert_eq!(artifacts.capture("ws", &mut short), Ok(None));
+        let mut other = completed("commandExecution", &"x".repeat(20));
+        assert_eq!(artifacts.capture("other", &mut other), Ok(None));
+        let mut agent = completed("agentMessage", &"x".repeat(20));
+        assert_eq!(artifacts.capture("ws", &mut agent), Ok(None));
+
+        let output = format!("{}é{}", "a".repeat(INLINE_TAIL_BYTES), "b".repeat(100));
+        let mut long = completed("commandExecution", &output);
+        let artifact = artifacts
+            .capture("ws", &mut long)
+            .expect("capture")
+            .expect("artifact");
+        assert_eq!(artifact.path, "turn-1/call_2F1.txt");
+        assert_eq!(artifact.item_id, "call/1");
+        assert_eq!(
+            std::fs::read_to_string(artifacts.workspace_dir("ws").join(&artifact.path))
+                .expect("read artifact"),
+            output
+        );
+        let item = &long["params"]["item"];
+        let inline = item["aggregatedOutput"].as_str().expect("inline output");
+        assert!(inline.starts_with("[Output truncated: "));
+        assert!(inline.ends_with(&"b".repeat(100)));
+        assert!(inline.len() < output.len());
+        assert_eq!(item["artifact"]["path"], "turn-1/call_2F1.txt");
+
+        // An id that used to map to the same file name gets its own.
+        let mut similar = completed("commandExecution", &"c".repeat(20));
+        similar["params"]["item"]["id"] = json!("call_1");
+        let similar = artifacts
+            .capture("ws", &mut similar)
+            .expect("capture")
+            .expect("artifact");
+        assert_eq!(similar.path, "turn-1/call_5F1.txt");
+
+        let mut listed = artifacts.list("ws", None);
+        listed.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].item_id, "call/1");
+        assert_eq!(listed[0].turn_id, "turn-1");
+        assert_eq!(listed[0].bytes, output.len() as u64);
+        assert_eq!(listed[1].item_id, "call_1");
+        assert_eq!(artifacts.list("ws", Some("turn-1")).len(), 2);
+        assert!(artifacts.list("ws", Some("turn-2")).is_empty());
+
+        assert_eq!(
+            artifacts.prune(SystemTime::now() - Duration::from_secs(60)),
+            0
+        );
+        assert_eq!(
+            artifacts.prune(SystemTime::now() + Duration::from_secs(60)),
+            2
+        );
+        assert!(!artifacts.workspace_dir("ws").exists());
+
+        artifacts.configure(&[]);
+        let mut long = completed("commandExecution", &output);
+        assert_eq!(artifacts.capture("ws", &mut long), Ok(None));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn keeps_ids_and_tails_safe() {
+        assert_eq!(path_component("../etc"), "_2E_2E_2Fetc");
+        assert_eq!(path_component(""), "_");
+        assert_eq!(path_component("turn-1_a"), "turn-1_5Fa");
+        for id in ["", "call/1", "call_1", "é ü", "turn-1"] {
+            assert_eq!(id_from_component(&path_component(id)).as_deref(), Some(id));
+        }
+        assert_eq!(id_from_component("bad_2"), None);
+        assert_eq!(tail("héllo", 4), "llo");
+        assert_eq!(tail("hi", 10), "hi");
+    }
+    #[tokio::test]
+    async fn saves_outputs_off_the_event_thread() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-artifacts-{}", Uuid::new_v4()));
+        let artifacts = Artifacts::new(root.clone());
+        artifacts.configure(&[entry("ws", Some(10))]);
+        let output = "x".repeat(100);
+        let mut long = completed("commandExecution", &output);
+        let artifact = artifacts
+            .capture("ws", &mut long)
+            .expect("capture")
+            .expect("artifact");
+        let file = artifacts.workspace_dir("ws").join(&artifact.path);
+        for _ in 0..100 {
+            if std::fs::read_to_string(&file).is_ok_and(|saved| saved == output) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(std::fs::read_to_string(&file).expect("saved"), output);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,404 @@
+use std::net::IpAddr;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::thread_subscriptions::event_workspace_ids;
+use super::ConnectionContext;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum PolicyEffect {
+    Allow,
+    Deny,
+}
+
+/// One policy rule. Every listed condition must match; an omitted condition
+/// matches anything. `"*"` in `methods` matches every method.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PolicyRule {
+    pub(crate) effect: PolicyEffect,
+    #[serde(default)]
+    pub(crate) methods: Vec<String>,
+    #[serde(default)]
+    pub(crate) workspaces: Option<Vec<String>>,
+    #[serde(default)]
+    pub(crate) tokens: Option<Vec<String>>,
+    #[serde(default)]
+    pub(crate) peers: Option<Vec<IpAddr>>,
+}
+
+/// Method-level authorization consulted before every RPC dispatch. Rules are
+/// evaluated in order and the first match decides; with no match (or no
+/// policy file) the request is allowed.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub(crate) struct AuthorizationPolicy {
+    #[serde(default)]
+    rules: Vec<PolicyRule>,
+}
+
+/// Keys naming one workspace a request acts on.
+const WORKSPACE_ID_KEYS: [&str; 3] = ["workspaceId", "id", "parentId"];
+
+/// Methods whose `id` is a trash item or webhook endpoint rather than a
+/// workspace. The daemon looks up a trash item's workspaces itself.
+const NON_WORKSPACE_ID_METHODS: [&str; 5] = [
+    "restore_from_trash",
+    "empty_trash",
+    "set_webhook",
+    "remove_webhook",
+    "test_webhook",
+];
+
+/// The workspaces a request names, for policy checks.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct RequestWorkspaces {
+    pub(crate) ids: Vec<String>,
+    /// `workspaceIds: "all"`; the caller adds every workspace id.
+    pub(crate) all: bool,
+    /// A workspace key held something other than an id.
+    pub(crate) unresolved: bool,
+}
+
+impl RequestWorkspaces {
+    fn add(&mut self, value: &Value) {
+        match value.as_str() {
+            Some(id) if !self.ids.iter().any(|known| known == id) => self.ids.push(id.to_string()),
+            Some(_) => {}
+            None => self.unresolved = true,
+        }
+    }
+}
+
+/// Reads the single-id keys and every entry of `workspaceIds`.
+pub(crate) fn request_workspaces(method: &str, params: &Value) -> RequestWorkspaces {
+    let mut workspaces = RequestWorkspaces::default();
+    for key in WORKSPACE_ID_KEYS {
+        if key == "id" && NON_WORKSPACE_ID_METHODS.contains(&method) {
+            continue;
+        }
+        match params.get(key) {
+            None | Some(Value::Null) => {}
+            Some(value) => workspaces.add(value),
+        }
+    }
+    match params.get("workspaceIds") {
+        None | Some(Value::Null) => {}
+        Some(Value::String(all)) if all == "all" => workspaces.all = true,
+        Some(Value::Array(ids)) => ids.iter().for_each(|id| workspaces.add(id)),
+        Some(_) => workspaces.unresolved = true,
+    }
+    workspaces
+}
+
+/// What a rule's `workspaces` condition is matched against.
+#[derive(Debug, Clone, Copy)]
+enum WorkspaceTarget<'a> {
+    None,
+    Id(&'a str),
+    /// Named but not readable as an id: deny rules match it, allow rules
+    /// don't.
+    Unresolved,
+}
+
+impl AuthorizationPolicy {
+    /// Checks every workspace the request names; one denied workspace
+    /// denies the request.
+    pub(crate) fn authorize_request(
+        &self,
+        method: &str,
+        workspaces: &RequestWorkspaces,
+        context: &ConnectionContext,
+    ) -> Result<(), String> {
+        if workspaces.unresolved {
+            self.check(method, WorkspaceTarget::Unresolved, context)?;
+        }
+        if workspaces.ids.is_empty() {
+            return self.check(method, WorkspaceTarget::None, context);
+        }
+        for id in &workspaces.ids {
+            self.check(method, WorkspaceTarget::Id(id), context)?;
+        }
+        Ok(())
+    }
+
+    /// Whether an event notification may be forwarded to the connection:
+    /// checked as the method `events` against the workspaces it is about.
+    pub(crate) fn allows_event(&self, notification: &Value, context: &ConnectionContext) -> bool {
+        let workspaces = RequestWorkspaces {
+            ids: event_workspace_ids(notification)
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            ..RequestWorkspaces::default()
+        };
+        self.authorize_request("events", &workspaces, context)
+            .is_ok()
+    }
+
+    pub(crate) fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read policy file {}: {err}", path.display()))?;
+        serde_json::from_str(&data)
+            .map_err(|err| format!("Invalid policy file {}: {err}", path.display()))
+    }
+
+    pub(crate) fn authorize(
+        &self,
+        method: &str,
+        workspace_id: Option<&str>,
+        context: &ConnectionContext,
+    ) -> Result<(), String> {
+        let target = match workspace_id {
+            Some(id) => WorkspaceTarget::Id(id),
+            None => WorkspaceTarget::None,
+        };
+        self.check(method, target, context)
+    }
+
+    fn check(
+        &self,
+        method: &str,
+        target: WorkspaceTarget,
+        context: &ConnectionContext,
+    ) -> Result<(), String> {
+        let effect = self
+            .rules
+            .iter()
+            .find(|rule| rule.matches(method, target, context))
+            .map(|rule| rule.effect)
+            .unwrap_or(PolicyEffect::Allow);
+        match (effect, target) {
+            (PolicyEffect::Allow, _) => Ok(()),
+            (PolicyEffect::Deny, WorkspaceTarget::Id(workspace_id)) => Err(format!(
+                "forbidden: `{method}` is denied by policy for workspace {workspace_id}"
+            )),
+            (PolicyEffect::Deny, WorkspaceTarget::Unresolved) => Err(format!(
+                "forbidden: `{method}` is denied by policy for unrecognized workspace ids"
+            )),
+            (PolicyEffect::Deny, WorkspaceTarget::None) => {
+                Err(format!("forbidden: `{method}` is denied by policy"))
+            }
+        }
+    }
+}
+
+impl PolicyRule {
+    fn matches(&self, method: &str, target: WorkspaceTarget, context: &ConnectionContext) -> bool {
+        let method_matches = self
+            .methods
+            .iter()
+            .any(|candidate| candidate == "*" || candidate == method);
+        let workspace_matches = match (&self.workspaces, target) {
+            (None, _) => true,
+            (Some(workspaces), WorkspaceTarget::Id(id)) => {
+                workspaces.iter().any(|candidate| candidate == id)
+            }
+            (Some(_), WorkspaceTarget::None) => false,
+            (Some(_), WorkspaceTarget::Unresolved) => self.effect == PolicyEffect::Deny,
+        };
+        let token_matches = match &self.tokens {
+            None => true,
+            Some(tokens) => context
+                .token_label
+                .is_some_and(|label| tokens.iter().any(|candidate| candidate == label)),
+        };
+        let peer_matches = match &self.peers {
+            None => true,
+            Some(peers) => peers.contains(&context.peer.ip()),
+        };
+        method_matches && workspace_matches && token_matches && peer_matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{request_workspaces, AuthorizationPolicy, RequestWorkspaces};
+    use crate::ConnectionContext;
+    use serde_json::json;
+
+    fn context(peer: &str) -> ConnectionContext {
+        ConnectionContext {
+            peer: peer.parse().unwrap(),
+            token_label: Some("shared"),
+            thread_subscriptions: Default::default(),
+        }
+    }
+
+    fn policy(value: serde_json::Value) -> AuthorizationPolicy {
+        serde_json::from_value(value).expect("valid policy")
+    }
+
+    #[test]
+    fn empty_policy_allows_everything() {
+        let policy = AuthorizationPolicy::default();
+        assert!(policy
+            .authorize("send_user_message", Some("ws-1"), &context("127.0.0.1:1"))
+            .is_ok());
+    }
+
+    #[test]
+    fn denies_method_on_listed_workspace_only() {
+        let policy = policy(json!({
+            "rules": [
+                { "effect": "deny", "methods": ["send_user_message"], "workspaces": ["secret"] }
+            ]
+        }));
+        let ctx = context("127.0.0.1:1");
+        assert!(policy
+            .authorize("send_user_message", Some("secret"), &ctx)
+            .is_err());
+        assert!(policy
+            .authorize("send_user_message", Some("other"), &ctx)
+            .is_ok());
+        assert!(policy
+            .authorize("list_threads", Some("secret"), &ctx)
+            .is_ok());
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let policy = policy(json!({
+            "rules": [
+                { "effect": "allow", "methods": ["*"], "peers": ["127.0.0.1"] },
+                { "effect": "deny", "methods": ["*"] }
+            ]
+        }));
+        assert!(policy
+            .authorize("ping", None, &context("127.0.0.1:1"))
+            .is_ok());
+        assert!(policy
+            .authorize("ping", None, &context("10.0.0.5:1"))
+            .is_err());
+    }
+
+    #[test]
+    fn token_conditions_require_a_matching_label() {
+        let policy = policy(json!({
+            "rules": [
+                { "effect": "deny", "methods": ["remove_workspace"], "tokens": ["shared"] }
+            ]
+        }));
+        let mut ctx = context("127.0.0.1:1");
+        assert!(policy
+            .authorize("remove_workspace", Some("a"), &ctx)
+            .is_err());
+        ctx.token_label = Some("no-auth");
+        assert!(policy
+            .authorize("remove_workspace", Some("a"), &ctx)
+            .is_ok());
+    }
+
+    #[test]
+    fn reads_every_workspace_a_request_names() {
+        let workspaces = request_workspaces(
+            "workspace_status",
+            &json!({
+                "workspaceId": "a",
+                "parentId": "b",
+                "workspaceIds": ["c", "a"],
+            }),
+        );
+        assert_eq!(workspaces.ids, ["a", "b", "c"]);
+        assert!(!workspaces.all && !workspaces.unresolved);
+        assert!(request_workspaces("workspace_status", &json!({ "workspaceIds": "all" })).all);
+        assert!(request_workspaces("workspace_status", &json!({ "workspaceIds": [1] })).unresolved);
+        assert!(request_workspaces("workspace_status", &json!({ "workspaceId": 7 })).unresolved);
+        assert_eq!(
+            request_workspaces("workspace_status", &json!({ "threadId": "t" })),
+            RequestWorkspaces::default()
+        );
+    }
+
+    #[test]
+    fn trash_and_webhook_ids_are_not_workspaces() {
+        for method in ["restore_from_trash", "empty_trash", "remove_webhook"] {
+            assert_eq!(
+                request_workspaces(method, &json!({ "id": "1700000000-ws" })),
+                RequestWorkspaces::default()
+            );
+        }
+        let webhook = request_workspaces(
+            "set_webhook",
+            &json!({ "id": "hook", "workspaceIds": ["a"] }),
+        );
+        assert_eq!(webhook.ids, ["a"]);
+    }
+
+    #[test]
+    fn denies_requests_naming_any_denied_workspace() {
+        let policy = policy(json!({
+            "rules": [
+                { "effect": "deny", "methods": ["*"], "workspaces": ["secret"] }
+            ]
+        }));
+        let ctx = context("127.0.0.1:1");
+        let both = request_workspaces(
+            "workspace_status",
+            &json!({ "workspaceIds": ["open", "secret"] }),
+        );
+        assert!(policy
+            .authorize_request("preflight_check", &both, &ctx)
+            .is_err());
+        let open = request_workspaces("workspace_status", &json!({ "workspaceIds": ["open"] }));
+        assert!(policy
+            .authorize_request("preflight_check", &open, &ctx)
+            .is_ok());
+        let unresolved =
+            request_workspaces("workspace_status", &json!({ "workspaceId": ["secret"] }));
+        assert!(policy
+            .authorize_request("workspace_status", &unresolved, &ctx)
+            .is_err());
+        let none = RequestWorkspaces::default();
+        assert!(policy
+            .authorize_request("list_workspaces", &none, &ctx)
+            .is_ok());
+    }
+
+    #[test]
+    fn allow_rules_do_not_match_unrecognized_workspaces() {
+        let policy = policy(json!({
+            "rules": [
+                { "effect": "allow", "methods": ["*"], "workspaces": ["open"] },
+                { "effect": "deny", "methods": ["*"] }
+            ]
+        }));
+        let ctx = context("127.0.0.1:1");
+        let unresolved = request_workspaces(
+            "workspace_status",
+            &json!({ "workspaceId": { "id": "open" } }),
+        );
+        assert!(policy
+            .authorize_request("workspace_status", &unresolved, &ctx)
+            .is_err());
+        let open = request_workspaces("workspace_status", &json!({ "workspaceId": "open" }));
+        assert!(policy
+            .authorize_request("workspace_status", &open, &ctx)
+            .is_ok());
+    }
+
+    #[test]
+    fn events_of_denied_workspaces_are_withheld() {
+        let policy = policy(json!({
+            "rules": [
+                { "effect": "deny", "methods": ["events"], "workspaces": ["secret"] }
+            ]
+        }));
+        let ctx = context("127.0.0.1:1");
+        let event = |workspace_id: &str| {
+            json!({
+                "method": "app-server-event",
+                "params": {
+                    "workspace_id": workspace_id,
+                    "message": { "method": "item/started", "params": { "threadId": "t" } },
+                },
+            })
+        };
+        assert!(!policy.allows_event(&event("secret"), &ctx));
+        assert!(policy.allows_event(&event("open"), &ctx));
+        let daemon_wide = json!({ "method": "workspaces-changed", "params": {} });
+        assert!(policy.allows_event(&daemon_wide, &ctx));
+    }
+}
@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Serialize;
+
+use super::run_git_command;
+use super::utils::is_path_within;
+
+/// Most lines a single `git_blame` call returns.
+pub(crate) const MAX_BLAME_LINES: usize = 5000;
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BlameLine {
+    pub(crate) line: usize,
+    pub(crate) commit: String,
+    pub(crate) author: String,
+    /// Author time, seconds since the epoch.
+    pub(crate) timestamp: i64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BlameResponse {
+    pub(crate) lines: Vec<BlameLine>,
+    /// More lines exist past the last one returned.
+    pub(crate) truncated: bool,
+}
+
+/// Parses `git blame --porcelain`; commit headers appear only the first time
+/// a commit is seen, so they are remembered per hash.
+pub(crate) fn parse_blame_porcelain(output: &str) -> Vec<BlameLine> {
+    let mut authors: HashMap<String, (String, i64)> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut current: Option<(String, usize)> = None;
+    for row in output.lines() {
+        if row.starts_with('\t') {
+            if let Some((commit, line)) = current.take() {
+                let (author, timestamp) = authors.get(&commit).cloned().unwrap_or_default();
+                lines.push(BlameLine {
+                    line,
+                    commit,
+                    author,
+                    timestamp,
+                });
+            }
+            continue;
+        }
+        let Some((commit, _)) = current.as_ref() else {
+            let mut parts = row.split(' ');
+            let commit = parts.next().unwrap_or_default();
+            let final_line = parts.nth(1).and_then(|value| value.parse::<usize>().ok());
+            if let (true, Some(final_line)) = (commit.len() >= 40, final_line) {
+                current = Some((commit.to_string(), final_line));
+            }
+            continue;
+        };
+        let entry = authors.entry(commit.clone()).or_default();
+        if let Some(author) = row.strip_prefix("author ") {
+            entry.0 = author.to_string();
+        } else if let Some(time) = row.strip_prefix("author-time ") {
+            entry.1 = time.trim().parse().unwrap_or_default();
+        }
+    }
+    lines
+}
+
+/// Clamps the requested range to the file and to `MAX_BLAME_LINES`. Returns
+/// `(start, end, truncated)`.
+pub(crate) fn blame_range(
+    line_count: usize,
+    start: Option<u32>,
+    end: Option<u32>,
+) -> Result<(usize, usize, bool), String> {
+    let start = start.map(|value| value as usize).unwrap_or(1).max(1);
+    if start > line_count {
+        return Err(format!("file has only {line_count} lines"));
+    }
+    let end = end
+        .map(|value| value as usize)
+        .unwrap_or(line_count)
+        .min(line_count);
+    if end < start {
+        return Err("endLine must not be before startLine".to_string());
+    }
+    let capped_end = end.min(start + MAX_BLAME_LINES - 1);
+    Ok((start, capped_end, capped_end < end))
+}
+
+pub(crate) async fn git_blame(
+    root: &Path,
+    relative_path: &str,
+    start: Option<u32>,
+    end: Option<u32>,
+) -> Result<BlameResponse, String> {
+    let canonical_root = root
+        .canonicalize()
+        .map_err(|err| format!("Failed to resolve workspace root: {err}"))?;
+    let canonical_path = canonical_root
+        .join(relative_path)
+        .canonicalize()
+        .map_err(|err| format!("Failed to open file: {err}"))?;
+    if !is_path_within(&canonical_root, &canonical_path) || !canonical_path.is_file() {
+        return Err("Invalid file path".to_string());
+    }
+    let content =
+        std::fs::read(&canonical_path).map_err(|err| format!("Failed to read file: {err}"))?;
+    let line_count = content.split(|byte| *byte == b'\n').count()
+        - usize::from(content.ends_with(b"\n") || content.is_empty());
+    if line_count == 0 {
+        return Ok(BlameResponse {
+            lines: Vec::new(),
+            truncated: false,
+        });
+    }
+    let (start, end, truncated) = blame_range(line_count, start, end)?;
+    let range = format!("{start},{end}");
+    let path = canonical_path.to_string_lossy().to_string();
+    let output = run_git_command(
+        &canonical_root,
+        &["blame", "--porcelain", "-L", &range, "--", &path],
+    )
+    .await?;
+    Ok(BlameResponse {
+        lines: parse_blame_porcelain(&output),
+        truncated,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{blame_range, parse_blame_porcelain, BlameLine, MAX_BLAME_LINES};
+
+    const FIRST: &str = "1111111111111111111111111111111111111111";
+    const SECOND: &str = "2222222222222222222222222222222222222222";
+
+    #[test]
+    fn parses_porcelain_with_repeated_commits() {
+        let output = format!(
+            "{FIRST} 1 1 2\nauthor Ada\nauthor-mail <ada@example.com>\nauthor-time 1700000000\n\
+summary init\nfilename a.rs\n\tfn main() {{\n\
+{FIRST} 2 2\n\t}}\n\
+{SECOND} 3 3 1\nauthor Grace\nauthor-time 1700000100\nfilename a.rs\n\t// note\n"
+        );
+        assert_eq!(
+            parse_blame_porcelain(&output),
+            vec![
+                BlameLine {
+                    line: 1,
+                    commit: FIRST.to_string(),
+                    author: "Ada".to_string(),
+                    timestamp: 1_700_000_000,
+                },
+                BlameLine {
+                    line: 2,
+                    commit: FIRST.to_string(),
+                    author: "Ada".to_string(),
+                    timestamp: 1_700_000_000,
+                },
+                BlameLine {
+                    line: 3,
+                    commit: SECOND.to_string(),
+                    author: "Grace".to_string(),
+                    timestamp: 1_700_000_100,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn clamps_ranges() {
+        assert_eq!(blame_range(10, None, None), Ok((1, 10, false)));
+        assert_eq!(blame_range(10, Some(3), Some(50)), Ok((3, 10, false)));
+        assert!(blame_range(10, Some(11), None).is_err());
+        assert!(blame_range(10, Some(5), Some(4)).is_err());
+        assert_eq!(
+            blame_range(MAX_BLAME_LINES + 10, None, None),
+            Ok((1, MAX_BLAME_LINES, true))
+        );
+    }
+}
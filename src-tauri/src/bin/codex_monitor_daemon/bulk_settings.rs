@@ -0,0 +1,279 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::backend::workspace_settings::{validate_settings_change, InvalidSetting};
+use crate::types::{WorkspaceEntry, WorkspaceGroup, WorkspaceSettings};
+
+/// The workspaces `update_workspaces_settings_bulk` applies to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BulkTarget {
+    All,
+    Ids(Vec<String>),
+    /// A workspace group, by id or name.
+    Group(String),
+}
+
+/// Reads `ids` (a list of workspace ids, or `"all"`) or `group`; exactly one
+/// must be given.
+pub(crate) fn parse_bulk_target(params: &Value) -> Result<BulkTarget, String> {
+    let ids = params.get("ids").filter(|ids| !ids.is_null());
+    let group = params.get("group").filter(|group| !group.is_null());
+    match (ids, group) {
+        (Some(Value::String(all)), None) if all == "all" => Ok(BulkTarget::All),
+        (Some(Value::Array(ids)), None) => ids
+            .iter()
+            .map(|id| {
+                id.as_str()
+                    .map(str::to_string)
+                    .ok_or_else(|| "`ids` must be a list of strings or \"all\"".to_string())
+            })
+            .collect::<Result<_, _>>()
+            .map(BulkTarget::Ids),
+        (Some(_), None) => Err("`ids` must be a list of strings or \"all\"".to_string()),
+        (None, Some(Value::String(group))) if !group.trim().is_empty() => {
+            Ok(BulkTarget::Group(group.trim().to_string()))
+        }
+        (None, Some(_)) => Err("`group` must be a group id or name".to_string()),
+        (Some(_), Some(_)) => Err("pass either `ids` or `group`, not both".to_string()),
+        (None, None) => Err("missing `ids` or `group`".to_string()),
+    }
+}
+
+/// The workspace ids `target` names: every workspace or a group's, sorted,
+/// or the given ids in order without repeats.
+pub(crate) fn target_ids(
+    target: &BulkTarget,
+    workspaces: &HashMap<String, WorkspaceEntry>,
+    groups: &[WorkspaceGroup],
+) -> Result<Vec<String>, String> {
+    let mut ids: Vec<String> = match target {
+        BulkTarget::Ids(ids) => {
+            let mut unique = Vec::new();
+            for id in ids {
+                if !unique.contains(id) {
+                    unique.push(id.clone());
+                }
+            }
+            return Ok(unique);
+        }
+        BulkTarget::All => workspaces.keys().cloned().collect(),
+        BulkTarget::Group(group) => {
+            let group = groups
+                .iter()
+                .find(|known| known.id == *group)
+                .or_else(|| groups.iter().find(|known| known.name == *group))
+                .ok_or_else(|| format!("workspace group not found: {group}"))?;
+            workspaces
+                .values()
+                .filter(|entry| entry.settings.group_id.as_deref() == Some(group.id.as_str()))
+                .map(|entry| entry.id.clone())
+                .collect()
+        }
+    };
+    ids.sort();
+    Ok(ids)
+}
+
+/// Settings fields whose values differ, by their JSON names, sorted.
+pub(crate) fn changed_fields(before: &WorkspaceSettings, after: &WorkspaceSettings) -> Vec<String> {
+    let (Ok(Value::Object(before)), Ok(Value::Object(after))) =
+        (serde_json::to_value(before), serde_json::to_value(after))
+    else {
+        return Vec::new();
+    };
+    after
+        .iter()
+        .filter(|(key, value)| before.get(*key) != Some(*value))
+        .map(|(key, _)| key.clone())
+        .collect()
+}
+
+/// What happened to one workspace of a bulk update.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct BulkSettingsResult {
+    pub(crate) id: String,
+    pub(crate) ok: bool,
+    /// Fields the patch actually changed; empty when it failed.
+    pub(crate) changed: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) error: Option<String>,
+    /// The rejected fields when the patched settings failed validation.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) errors: Vec<InvalidSetting>,
+}
+
+impl BulkSettingsResult {
+    pub(crate) fn failed(id: &str, error: String, errors: Vec<InvalidSetting>) -> Self {
+        Self {
+            id: id.to_string(),
+            ok: false,
+            changed: Vec::new(),
+            error: Some(error),
+            errors,
+        }
+    }
+}
+
+/// Applies `patch` to one workspace with the checks `patch_workspace_settings`
+/// runs. A workspace that fails keeps its settings.
+pub(crate) fn apply_settings_patch(
+    entry: &mut WorkspaceEntry,
+    patch: &Value,
+) -> BulkSettingsResult {
+    let settings = match entry.settings.with_patch(patch) {
+        Ok(settings) => settings,
+        Err(error) => return BulkSettingsResult::failed(&entry.id, error, Vec::new()),
+    };
+    if let Err(invalid) = validate_settings_change(&entry.settings, &settings, &entry.path) {
+        return BulkSettingsResult::failed(&entry.id, invalid.message(), invalid.fields);
+    }
+    let changed = changed_fields(&entry.settings, &settings);
+    entry.settings = settings;
+    BulkSettingsResult {
+        id: entry.id.clone(),
+        ok: true,
+        changed,
+        error: None,
+        errors: Vec::new(),
+    }
+}
+
+/// Applies `patch` to each of `ids`. Workspaces that are missing or that
+/// `authorize` refuses fail without being touched.
+pub(crate) fn apply_bulk_patch(
+    ids: Vec<String>,
+    workspaces: &mut HashMap<String, WorkspaceEntry>,
+    patch: &Value,
+    authorize: impl Fn(&str) -> Result<(), String>,
+) -> Vec<BulkSettingsResult> {
+    ids.into_iter()
+        .map(|id| {
+            if let Err(error) = authorize(&id) {
+                return BulkSettingsResult::failed(&id, error, Vec::new());
+            }
+            match workspaces.get_mut(&id) {
+                Some(entry) => apply_settings_patch(entry, patch),
+                None => {
+                    BulkSettingsResult::failed(&id, "workspace not found".to_string(), Vec::new())
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        apply_bulk_patch, apply_settings_patch, parse_bulk_target, target_ids, BulkTarget,
+    };
+    use crate::types::{WorkspaceEntry, WorkspaceGroup, WorkspaceKind, WorkspaceSettings};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn entry(id: &str, group_id: Option<&str>) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            path: std::env::temp_dir().to_string_lossy().into_owned(),
+            codex_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings {
+                group_id: group_id.map(str::to_string),
+                idle_disconnect_minutes: Some(30),
+                ..WorkspaceSettings::default()
+            },
+            status: None,
+        }
+    }
+
+    #[test]
+    fn resolves_targets() {
+        assert_eq!(
+            parse_bulk_target(&json!({ "ids": "all" })),
+            Ok(BulkTarget::All)
+        );
+        assert_eq!(
+            parse_bulk_target(&json!({ "group": " Work " })),
+            Ok(BulkTarget::Group("Work".to_string()))
+        );
+        assert!(parse_bulk_target(&json!({ "ids": "some" })).is_err());
+        assert!(parse_bulk_target(&json!({ "ids": [1] })).is_err());
+        assert!(parse_bulk_target(&json!({ "ids": [], "group": "Work" })).is_err());
+        assert!(parse_bulk_target(&json!({})).is_err());
+
+        let workspaces = HashMap::from([
+            ("b".to_string(), entry("b", Some("g1"))),
+            ("a".to_string(), entry("a", Some("g1"))),
+            ("c".to_string(), entry("c", None)),
+        ]);
+        let groups = vec![WorkspaceGroup {
+            id: "g1".to_string(),
+            name: "Work".to_string(),
+            sort_order: None,
+            copies_folder: None,
+        }];
+        let ids = |target| target_ids(&target, &workspaces, &groups);
+        assert_eq!(ids(BulkTarget::All).unwrap(), ["a", "b", "c"]);
+        assert_eq!(
+            ids(BulkTarget::Group("Work".to_string())).unwrap(),
+            ["a", "b"]
+        );
+        assert_eq!(
+            ids(BulkTarget::Group("g1".to_string())).unwrap(),
+            ["a", "b"]
+        );
+        assert!(ids(BulkTarget::Group("Home".to_string())).is_err());
+        let listed = BulkTarget::Ids(vec!["c".to_string(), "x".to_string(), "c".to_string()]);
+        assert_eq!(ids(listed).unwrap(), ["c", "x"]);
+    }
+
+    #[test]
+    fn applies_patches_and_reports_changed_fields() {
+        let mut workspace = entry("a", None);
+        let result = apply_settings_patch(
+            &mut workspace,
+            &json!({ "idleDisconnectMinutes": null, "pinned": true, "sortOrder": null }),
+        );
+        assert!(result.ok);
+        assert_eq!(result.changed, ["idleDisconnectMinutes", "pinned"]);
+        assert_eq!(workspace.settings.idle_disconnect_minutes, None);
+
+        let again = apply_settings_patch(&mut workspace, &json!({ "pinned": true }));
+        assert!(again.ok);
+        assert!(again.changed.is_empty());
+
+        let invalid = apply_settings_patch(&mut workspace, &json!({ "color": "blue" }));
+        assert!(!invalid.ok);
+        assert_eq!(invalid.errors[0].field, "color");
+        assert!(workspace.settings.color.is_none());
+    }
+
+    #[test]
+    fn skips_workspaces_the_policy_denies() {
+        let mut workspaces = HashMap::from([
+            ("a".to_string(), entry("a", None)),
+            ("secret".to_string(), entry("secret", None)),
+        ]);
+        let ids = vec!["a".to_string(), "secret".to_string(), "gone".to_string()];
+        let results =
+            apply_bulk_patch(
+                ids,
+                &mut workspaces,
+                &json!({ "pinned": true }),
+                |id| match id {
+                    "secret" => Err("forbidden".to_string()),
+                    _ => Ok(()),
+                },
+            );
+        assert!(results[0].ok);
+        assert_eq!(results[1].error.as_deref(), Some("forbidden"));
+        assert_eq!(results[2].error.as_deref(), Some("workspace not found"));
+        assert!(workspaces["a"].settings.pinned);
+        assert!(!workspaces["secret"].settings.pinned);
+    }
+}
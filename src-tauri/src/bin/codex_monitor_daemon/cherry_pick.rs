@@ -0,0 +1,179 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::conflicts::conflicted_paths;
+use super::git_state::{detect_git_operation, ensure_no_git_operation};
+use super::run_git_command;
+use super::types::GitOperation;
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LandedCommit {
+    /// The picked commit.
+    pub(crate) source: String,
+    /// Its copy on the target branch; `None` with `noCommit`.
+    pub(crate) commit: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CherryPickResult {
+    pub(crate) landed: Vec<LandedCommit>,
+    pub(crate) no_commit: bool,
+}
+
+#[derive(Debug)]
+pub(crate) enum CherryPickError {
+    Failed(String),
+    /// Stopped on conflicts; the cherry-pick is left in progress for
+    /// `conflict_status`/`continue_operation`/`abort_operation`.
+    Conflict {
+        commit: String,
+        landed: Vec<LandedCommit>,
+        message: String,
+    },
+}
+
+impl From<String> for CherryPickError {
+    fn from(message: String) -> Self {
+        Self::Failed(message)
+    }
+}
+
+fn is_commit_ish(value: &str) -> bool {
+    (4..=64).contains(&value.len()) && value.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+/// Resolves `hash` to the full hash of an existing commit.
+pub(crate) async fn resolve_commit(repo_path: &PathBuf, hash: &str) -> Result<String, String> {
+    let hash = hash.trim();
+    if !is_commit_ish(hash) {
+        return Err(format!("invalid commit hash: {hash}"));
+    }
+    let spec = format!("{hash}^{{commit}}");
+    run_git_command(repo_path, &["rev-parse", "--verify", "--quiet", &spec])
+        .await
+        .map_err(|_| format!("unknown commit: {hash}"))
+}
+
+/// Resolves `commits` to full hashes, requiring each to be reachable from the
+/// worktree's `HEAD`.
+pub(crate) async fn resolve_worktree_commits(
+    worktree_path: &PathBuf,
+    commits: &[String],
+) -> Result<Vec<String>, String> {
+    if commits.is_empty() {
+        return Err("commits must not be empty".to_string());
+    }
+    let mut resolved = Vec::with_capacity(commits.len());
+    for commit in commits {
+        let full = resolve_commit(worktree_path, commit).await?;
+        if run_git_command(
+            worktree_path,
+            &["merge-base", "--is-ancestor", &full, "HEAD"],
+        )
+        .await
+        .is_err()
+        {
+            return Err(format!("{} is not on the worktree branch", commit.trim()));
+        }
+        resolved.push(full);
+    }
+    Ok(resolved)
+}
+
+/// Pairs `sources` with the commits that now sit on top of `original_head`.
+async fn landed_commits(
+    repo_path: &PathBuf,
+    original_head: &str,
+    sources: &[String],
+    no_commit: bool,
+) -> Vec<LandedCommit> {
+    let created: Vec<String> = if no_commit {
+        Vec::new()
+    } else {
+        let range = format!("{original_head}..HEAD");
+        run_git_command(repo_path, &["rev-list", "--reverse", &range])
+            .await
+            .map(|output| output.lines().map(str::to_string).collect())
+            .unwrap_or_default()
+    };
+    sources
+        .iter()
+        .enumerate()
+        .map(|(index, source)| LandedCommit {
+            source: source.clone(),
+            commit: created.get(index).cloned(),
+        })
+        .collect()
+}
+
+/// Cherry-picks `commits` (full hashes, oldest first) onto the checkout at
+/// `repo_path` as one sequence, so `git cherry-pick --continue` picks up the
+/// rest.
+pub(crate) async fn run_cherry_pick(
+    repo_path: &PathBuf,
+    commits: &[String],
+    no_commit: bool,
+) -> Result<CherryPickResult, CherryPickError> {
+    ensure_no_git_operation(repo_path, "cherry-pick")?;
+    let original_head = run_git_command(repo_path, &["rev-parse", "HEAD"]).await?;
+
+    let mut args = vec!["cherry-pick"];
+    if no_commit {
+        args.push("--no-commit");
+    }
+    args.extend(commits.iter().map(String::as_str));
+    let Err(message) = run_git_command(repo_path, &args).await else {
+        return Ok(CherryPickResult {
+            landed: landed_commits(repo_path, &original_head, commits, no_commit).await,
+            no_commit,
+        });
+    };
+
+    if conflicted_paths(repo_path).await?.is_empty() {
+        // Nothing to resolve (for example an empty pick): undo the attempt.
+        let _ = run_git_command(repo_path, &["cherry-pick", "--abort"]).await;
+        return Err(CherryPickError::Failed(message));
+    }
+    let stopped_at = run_git_command(repo_path, &["rev-parse", "--verify", "CHERRY_PICK_HEAD"])
+        .await
+        .unwrap_or_default();
+    let done = commits
+        .iter()
+        .position(|commit| *commit == stopped_at)
+        .unwrap_or(0);
+    Err(CherryPickError::Conflict {
+        commit: stopped_at,
+        landed: landed_commits(repo_path, &original_head, &commits[..done], no_commit).await,
+        message,
+    })
+}
+
+/// Errors unless a cherry-pick is what's in progress in `repo_path`.
+pub(crate) fn ensure_cherry_pick_in_progress(repo_path: &Path) -> Result<(), String> {
+    match detect_git_operation(repo_path) {
+        Some(GitOperation::CherryPick) => Ok(()),
+        Some(other) => Err(format!(
+            "A {} is in progress, not a cherry-pick.",
+            other.label()
+        )),
+        None => Err("No cherry-pick in progress.".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_commit_ish;
+
+    #[test]
+    fn accepts_only_hex_hashes() {
+        assert!(is_commit_ish("abc1234"));
+        assert!(is_commit_ish("0123456789abcdef0123456789abcdef01234567"));
+        assert!(!is_commit_ish("abc"));
+        assert!(!is_commit_ish("--abort"));
+        assert!(!is_commit_ish("HEAD~1"));
+        assert!(!is_commit_ish("main"));
+    }
+}
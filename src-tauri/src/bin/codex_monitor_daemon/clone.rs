@@ -0,0 +1,312 @@
+use std::path::{Component, Path, PathBuf};
+use std::process::Stdio;
+
+use serde::Serialize;
+use tokio::io::AsyncReadExt;
+
+use super::backend::git_command::git_command;
+use super::operations::CancelSignal;
+use super::utils::is_path_within;
+
+/// Directory under the data dir used when `projectsRoot` is not set.
+pub(crate) const DEFAULT_PROJECTS_DIR: &str = "projects";
+
+#[derive(Debug, Clone)]
+pub(crate) struct CloneRequest {
+    pub(crate) url: String,
+    pub(crate) destination: PathBuf,
+    pub(crate) branch: Option<String>,
+    pub(crate) depth: Option<u32>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CloneProgress {
+    pub(crate) destination: String,
+    /// Git's phase label, e.g. `Receiving objects`.
+    pub(crate) phase: String,
+    pub(crate) percent: Option<u32>,
+    /// The progress line as git printed it, for the operation's `line`.
+    #[serde(skip)]
+    pub(crate) line: String,
+}
+
+/// Folder name git itself would pick for `url`.
+pub(crate) fn repo_name_from_url(url: &str) -> Option<String> {
+    let trimmed = url.trim().trim_end_matches('/');
+    let trimmed = trimmed.strip_suffix(".git").unwrap_or(trimmed);
+    let name = trimmed.rsplit(['/', ':', '\\']).next()?.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        return None;
+    }
+    Some(name.to_string())
+}
+
+/// `projects_root/name`, where `name` must be a single folder name so the
+/// clone stays inside the projects root.
+pub(crate) fn clone_destination(projects_root: &Path, name: &str) -> Result<PathBuf, String> {
+    let mut components = Path::new(name).components();
+    let is_folder_name = matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    );
+    let destination = projects_root.join(name);
+    if !is_folder_name || !is_path_within(projects_root, &destination) {
+        return Err(format!(
+            "Destination must be a folder name inside the projects root: {name}"
+        ));
+    }
+    Ok(destination)
+}
+
+/// Parses one `--progress` line (`Receiving objects:  45% (450/1000), ...`).
+pub(crate) fn parse_progress_line(line: &str) -> Option<(String, Option<u32>)> {
+    let line = line.trim();
+    let line = line.strip_prefix("remote:").unwrap_or(line).trim();
+    let (phase, rest) = line.split_once(':')?;
+    let phase = phase.trim();
+    // Phases are capitalized (`Resolving deltas`); `fatal:`/`error:` lines are not.
+    if !phase.starts_with(|ch: char| ch.is_ascii_uppercase())
+        || !phase
+            .chars()
+            .all(|ch| ch.is_ascii_alphabetic() || ch == ' ')
+    {
+        return None;
+    }
+    let percent = rest
+        .trim()
+        .split_once('%')
+        .and_then(|(value, _)| value.trim().parse::<u32>().ok())
+        .filter(|value| *value <= 100);
+    Some((phase.to_string(), percent))
+}
+
+pub(crate) fn is_auth_failure(stderr: &str) -> bool {
+    let stderr = stderr.to_ascii_lowercase();
+    [
+        "authentication failed",
+        "could not read username",
+        "could not read password",
+        "terminal prompts disabled",
+        "permission denied (publickey",
+        "host key verification failed",
+        "repository not found",
+    ]
+    .iter()
+    .any(|needle| stderr.contains(needle))
+}
+
+/// Runs `git clone` without any interactive prompt, reporting progress lines
+/// through `on_progress`. On failure or `cancel` the destination is left as
+/// it was found.
+pub(crate) async fn run_git_clone(
+    request: &CloneRequest,
+    mut on_progress: impl FnMut(CloneProgress),
+    mut cancel: CancelSignal,
+) -> Result<(), String> {
+    let existed = request.destination.exists();
+    if let Some(parent) = request.destination.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|err| format!("Failed to create {}: {err}", parent.display()))?;
+    }
+
+    let mut command = git_command();
+    command
+        .arg("clone")
+        .arg("--progress")
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped());
+    if std::env::var_os("GIT_SSH_COMMAND").is_none() {
+        command.env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes");
+    }
+    if let Some(branch) = &request.branch {
+        command.arg("--branch").arg(branch);
+    }
+    if let Some(depth) = request.depth {
+        command.arg("--depth").arg(depth.to_string());
+    }
+    command
+        .arg("--")
+        .arg(&request.url)
+        .arg(&request.destination);
+
+    let mut child = command
+        .spawn()
+        .map_err(|err| format!("Failed to run git: {err}"))?;
+    let destination = request.destination.to_string_lossy().to_string();
+    let mut stderr_log = String::new();
+    if let Some(mut stderr) = child.stderr.take() {
+        let mut buffer = [0u8; 4096];
+        let mut pending = String::new();
+        let mut last: Option<(String, Option<u32>)> = None;
+        loop {
+            let read = tokio::select! {
+                read = stderr.read(&mut buffer) => read,
+                _ = cancel.cancelled() => {
+                    let _ = child.kill().await;
+                    cleanup_failed_clone(&request.destination, existed);
+                    return Err("Clone cancelled.".to_string());
+                }
+            };
+            let read = match read {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read,
+            };
+            pending.push_str(&String::from_utf8_lossy(&buffer[..read]));
+            // Progress updates end with `\r`, everything else with `\n`.
+            while let Some(index) = pending.find(['\r', '\n']) {
+                let line: String = pending.drain(..=index).collect();
+                let line = line.trim_end_matches(['\r', '\n']);
+                match parse_progress_line(line) {
+                    Some(progress) if last.as_ref() != Some(&progress) => {
+                        on_progress(CloneProgress {
+                            destination: destination.clone(),
+                            phase: progress.0.clone(),
+                            percent: progress.1,
+                            line: line.trim().to_string(),
+                        });
+                        last = Some(progress);
+                    }
+                    Some(_) => {}
+                    None if !line.trim().is_empty() => {
+                        stderr_log.push_str(line.trim());
+                        stderr_log.push('\n');
+                    }
+                    None => {}
+                }
+            }
+        }
+        stderr_log.push_str(pending.trim());
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|err| format!("Failed to run git: {err}"))?;
+    if status.success() {
+        return Ok(());
+    }
+
+    cleanup_failed_clone(&request.destination, existed);
+    let detail = stderr_log.trim();
+    if is_auth_failure(detail) {
+        return Err(format!(
+            "Authentication failed for {}. Configure credentials for this remote and try again.",
+            request.url
+        ));
+    }
+    if detail.is_empty() {
+        Err("git clone failed.".to_string())
+    } else {
+        Err(detail.to_string())
+    }
+}
+
+/// Removes whatever a failed clone left behind; a pre-existing (empty)
+/// destination folder is kept.
+fn cleanup_failed_clone(destination: &Path, existed: bool) {
+    if !existed {
+        let _ = std::fs::remove_dir_all(destination);
+        return;
+    }
+    if let Ok(entries) = std::fs::read_dir(destination) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                let _ = std::fs::remove_dir_all(&path);
+            } else {
+                let _ = std::fs::remove_file(&path);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        cleanup_failed_clone, clone_destination, is_auth_failure, parse_progress_line,
+        repo_name_from_url,
+    };
+    use uuid::Uuid;
+
+    #[test]
+    fn repo_name_matches_git_defaults() {
+        assert_eq!(
+            repo_name_from_url("https://github.com/xlab/CodexMonitor.git").as_deref(),
+            Some("CodexMonitor")
+        );
+        assert_eq!(
+            repo_name_from_url("git@github.com:xlab/codex.git/").as_deref(),
+            Some("codex")
+        );
+        assert_eq!(repo_name_from_url("git@host:repo").as_deref(), Some("repo"));
+        assert_eq!(
+            repo_name_from_url("/srv/git/project").as_deref(),
+            Some("project")
+        );
+        assert_eq!(repo_name_from_url(""), None);
+    }
+
+    #[test]
+    fn clone_destinations_are_folder_names_under_the_root() {
+        let root = std::env::temp_dir().join("codex-monitor-projects");
+        assert_eq!(clone_destination(&root, "codex"), Ok(root.join("codex")));
+        for name in ["../escape", "a/b", "/tmp/elsewhere", "..", "."] {
+            assert!(clone_destination(&root, name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn parses_progress_lines() {
+        assert_eq!(
+            parse_progress_line("Receiving objects:  45% (450/1000), 1.20 MiB | 2.00 MiB/s"),
+            Some(("Receiving objects".to_string(), Some(45)))
+        );
+        assert_eq!(
+            parse_progress_line("remote: Counting objects: 100% (12/12), done."),
+            Some(("Counting objects".to_string(), Some(100)))
+        );
+        assert_eq!(
+            parse_progress_line("remote: Enumerating objects: 12, done."),
+            Some(("Enumerating objects".to_string(), None))
+        );
+        assert_eq!(parse_progress_line("Cloning into 'repo'..."), None);
+        assert_eq!(
+            parse_progress_line("fatal: repository 'https://x/y' not found"),
+            None
+        );
+    }
+
+    #[test]
+    fn detects_auth_failures() {
+        assert!(is_auth_failure(
+            "fatal: could not read Username for 'https://github.com': terminal prompts disabled"
+        ));
+        assert!(is_auth_failure(
+            "git@github.com: Permission denied (publickey)."
+        ));
+        assert!(!is_auth_failure(
+            "fatal: destination path 'x' already exists"
+        ));
+    }
+
+    #[test]
+    fn cleanup_keeps_existing_destination_folder() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-clone-{}", Uuid::new_v4()));
+        let existing = root.join("existing");
+        std::fs::create_dir_all(existing.join(".git")).expect("create dir");
+        std::fs::write(existing.join("README"), "x").expect("write file");
+        cleanup_failed_clone(&existing, true);
+        assert!(existing.is_dir());
+        assert_eq!(std::fs::read_dir(&existing).expect("read dir").count(), 0);
+
+        let created = root.join("created");
+        std::fs::create_dir_all(created.join(".git")).expect("create dir");
+        cleanup_failed_clone(&created, false);
+        assert!(!created.exists());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,68 @@
+use serde::Serialize;
+use tokio::task::JoinError;
+
+/// Outcome of `reload_codex_bin`, workspace ids sorted.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CodexBinReload {
+    pub(crate) restarted: Vec<String>,
+    /// Connected workspaces with their own `codex_bin`, left running.
+    pub(crate) skipped: Vec<String>,
+    pub(crate) failed: Vec<RestartFailure>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RestartFailure {
+    pub(crate) workspace_id: String,
+    pub(crate) error: String,
+}
+
+impl CodexBinReload {
+    /// Adds the outcome of one restart task. A task that panicked or was
+    /// cancelled counts as a failed restart of its workspace.
+    pub(crate) fn record<T>(
+        &mut self,
+        workspace_id: String,
+        outcome: Result<Result<T, String>, JoinError>,
+    ) {
+        let error = match outcome {
+            Ok(Ok(_)) => {
+                self.restarted.push(workspace_id);
+                return;
+            }
+            Ok(Err(error)) => error,
+            Err(error) => format!("restart task failed: {error}"),
+        };
+        self.failed.push(RestartFailure {
+            workspace_id,
+            error,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CodexBinReload, RestartFailure};
+
+    #[tokio::test]
+    async fn reports_every_restart_task() {
+        let mut report = CodexBinReload::default();
+        report.record("a".to_string(), Ok(Ok(())));
+        report.record::<()>("b".to_string(), Ok(Err("spawn failed".to_string())));
+        let panicked = tokio::spawn(async { panic!("boom") }).await;
+        report.record::<()>("c".to_string(), panicked.map(Ok));
+
+        assert_eq!(report.restarted, ["a"]);
+        assert_eq!(report.failed.len(), 2);
+        assert_eq!(
+            report.failed[0],
+            RestartFailure {
+                workspace_id: "b".to_string(),
+                error: "spawn failed".to_string(),
+            }
+        );
+        assert_eq!(report.failed[1].workspace_id, "c");
+        assert!(report.failed[1].error.starts_with("restart task failed"));
+    }
+}
@@ -0,0 +1,71 @@
+use serde_json::Value;
+
+/// The entries of a `collaborationMode/list` response, with or without the
+/// `result` wrapper.
+fn mode_entries(list: &Value) -> &[Value] {
+    list.get("result")
+        .unwrap_or(list)
+        .get("data")
+        .and_then(Value::as_array)
+        .map_or(&[], Vec::as_slice)
+}
+
+fn mode_name(entry: &Value) -> Option<&str> {
+    ["mode", "name"]
+        .iter()
+        .find_map(|key| entry.get(key).and_then(Value::as_str))
+        .filter(|name| !name.is_empty())
+}
+
+/// The `collaborationMode/list` entry named `name`, ignoring case, as the
+/// full object `turn/start` expects. Fails with the known names.
+pub(crate) fn resolve_collaboration_mode(list: &Value, name: &str) -> Result<Value, Vec<String>> {
+    let entries = mode_entries(list);
+    let wanted = name.trim();
+    entries
+        .iter()
+        .find(|entry| mode_name(entry).is_some_and(|mode| mode.eq_ignore_ascii_case(wanted)))
+        .cloned()
+        .ok_or_else(|| {
+            entries
+                .iter()
+                .filter_map(mode_name)
+                .map(str::to_string)
+                .collect()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve_collaboration_mode;
+    use serde_json::json;
+
+    #[test]
+    fn expands_mode_names_into_list_entries() {
+        let plan = json!({ "mode": "plan", "model": "gpt-5", "reasoningEffort": "high" });
+        let list = json!({
+            "id": 7,
+            "result": { "data": [plan, { "mode": "pairProgramming", "model": "gpt-5" }] },
+        });
+        assert_eq!(
+            resolve_collaboration_mode(&list, " Plan "),
+            Ok(plan.clone())
+        );
+        assert_eq!(
+            resolve_collaboration_mode(&list, "pairprogramming").map(|mode| mode["mode"].clone()),
+            Ok(json!("pairProgramming"))
+        );
+        assert_eq!(
+            resolve_collaboration_mode(&list, "plna"),
+            Err(vec!["plan".to_string(), "pairProgramming".to_string()])
+        );
+        assert_eq!(
+            resolve_collaboration_mode(&json!({ "data": [plan] }), "plan").map(|_| ()),
+            Ok(())
+        );
+        assert_eq!(
+            resolve_collaboration_mode(&json!({ "error": {} }), "plan"),
+            Err(Vec::new())
+        );
+    }
+}
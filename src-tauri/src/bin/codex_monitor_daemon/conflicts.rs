@@ -0,0 +1,453 @@
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+
+use super::backend::git_command::git_command;
+use super::git_state::detect_git_operation;
+use super::types::GitOperation;
+use super::utils::is_path_within;
+use super::{read_workspace_file_inner, run_git_command};
+
+/// Per-stage cap for `conflict_status` blob contents.
+pub(crate) const MAX_CONFLICT_BLOB_BYTES: usize = 200_000;
+
+/// One `<<<<<<< ... >>>>>>>` block of a conflicted file.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ConflictSection {
+    /// 1-based line of the `<<<<<<<` marker.
+    pub(crate) start_line: usize,
+    /// 1-based line of the `>>>>>>>` marker.
+    pub(crate) end_line: usize,
+    pub(crate) ours: String,
+    pub(crate) theirs: String,
+    /// Present with `merge.conflictStyle=diff3`/`zdiff3`.
+    pub(crate) base: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ConflictFile {
+    pub(crate) path: String,
+    pub(crate) sections: Vec<ConflictSection>,
+    /// The file was too large to read whole; `sections` may be incomplete.
+    pub(crate) truncated: bool,
+    /// Set when the content could not be read (deleted on one side, binary).
+    pub(crate) error: Option<String>,
+}
+
+/// One index stage of a conflicted path.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ConflictBlob {
+    /// `None` for binary content.
+    pub(crate) content: Option<String>,
+    pub(crate) truncated: bool,
+    pub(crate) binary: bool,
+}
+
+/// Three-way view of a conflicted path; a side is `None` when the path does
+/// not exist there (added on one side, deleted on the other).
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ConflictEntry {
+    pub(crate) path: String,
+    pub(crate) base: Option<ConflictBlob>,
+    pub(crate) ours: Option<ConflictBlob>,
+    pub(crate) theirs: Option<ConflictBlob>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ConflictStatus {
+    /// `None` when nothing is in progress; `files` is then empty.
+    pub(crate) operation: Option<GitOperation>,
+    pub(crate) files: Vec<ConflictEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ConflictResolution {
+    Ours,
+    Theirs,
+    Custom(String),
+}
+
+impl ConflictResolution {
+    pub(crate) fn parse(value: &str, content: Option<String>) -> Result<Self, String> {
+        match (value.trim(), content) {
+            ("ours", _) => Ok(Self::Ours),
+            ("theirs", _) => Ok(Self::Theirs),
+            ("custom", Some(content)) => Ok(Self::Custom(content)),
+            ("custom", None) => Err("content is required for a custom resolution".to_string()),
+            (other, _) => Err(format!(
+                "invalid resolution \"{other}\": expected \"ours\", \"theirs\" or \"custom\""
+            )),
+        }
+    }
+}
+
+enum Side {
+    Ours,
+    Base,
+    Theirs,
+}
+
+/// Parses conflict markers in `content`. Unterminated blocks are dropped.
+pub(crate) fn parse_conflict_sections(content: &str) -> Vec<ConflictSection> {
+    let mut sections = Vec::new();
+    let mut current: Option<(usize, Side, String, Option<String>, String)> = None;
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let line_number = index + 1;
+        let marker = line.trim_end_matches(['\r', '\n']);
+        if marker.starts_with("<<<<<<<") {
+            current = Some((line_number, Side::Ours, String::new(), None, String::new()));
+            continue;
+        }
+        let Some((start_line, side, ours, base, theirs)) = current.as_mut() else {
+            continue;
+        };
+        if marker.starts_with("|||||||") && matches!(side, Side::Ours) {
+            *side = Side::Base;
+            *base = Some(String::new());
+        } else if marker == "=======" && !matches!(side, Side::Theirs) {
+            *side = Side::Theirs;
+        } else if marker.starts_with(">>>>>>>") && matches!(side, Side::Theirs) {
+            sections.push(ConflictSection {
+                start_line: *start_line,
+                end_line: line_number,
+                ours: std::mem::take(ours),
+                theirs: std::mem::take(theirs),
+                base: base.take(),
+            });
+            current = None;
+        } else {
+            match side {
+                Side::Ours => ours.push_str(line),
+                Side::Base => base.get_or_insert_with(String::new).push_str(line),
+                Side::Theirs => theirs.push_str(line),
+            }
+        }
+    }
+    sections
+}
+
+/// Accepts only plain relative paths, made of one or more normal
+/// components, so a path cannot leave the workspace even when the file no
+/// longer exists (and so cannot be canonicalized).
+pub(crate) fn validate_relative_path(path: &str) -> Result<(), String> {
+    let trimmed = path.trim();
+    if trimmed.is_empty() {
+        return Err("path is required".to_string());
+    }
+    let mut components = Path::new(trimmed).components().peekable();
+    let valid = components.peek().is_some()
+        && components.all(|component| matches!(component, Component::Normal(_)));
+    if valid {
+        Ok(())
+    } else {
+        Err("Invalid file path".to_string())
+    }
+}
+
+/// Paths git reports as unmerged.
+pub(crate) async fn conflicted_paths(repo_path: &PathBuf) -> Result<Vec<String>, String> {
+    let output = run_git_command(repo_path, &["diff", "--name-only", "--diff-filter=U"]).await?;
+    Ok(output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+pub(crate) async fn list_conflicts(repo_path: &PathBuf) -> Result<Vec<ConflictFile>, String> {
+    let paths = conflicted_paths(repo_path).await?;
+    Ok(paths
+        .into_iter()
+        .map(|path| match read_workspace_file_inner(repo_path, &path) {
+            Ok(file) => ConflictFile {
+                sections: parse_conflict_sections(&file.content),
+                truncated: file.truncated,
+                error: None,
+                path,
+            },
+            Err(error) => ConflictFile {
+                path,
+                sections: Vec::new(),
+                truncated: false,
+                error: Some(error),
+            },
+        })
+        .collect())
+}
+
+/// Stages `path` (or its deletion) to mark the conflict resolved.
+pub(crate) async fn mark_resolved(repo_path: &PathBuf, path: &str) -> Result<(), String> {
+    validate_relative_path(path)?;
+    run_git_command(repo_path, &["add", "-A", "--", path.trim()]).await?;
+    Ok(())
+}
+
+fn blob_from_bytes(mut bytes: Vec<u8>, limit: usize) -> ConflictBlob {
+    if bytes.contains(&0) {
+        return ConflictBlob {
+            content: None,
+            truncated: false,
+            binary: true,
+        };
+    }
+    let truncated = bytes.len() > limit;
+    if truncated {
+        bytes.truncate(limit);
+    }
+    // A cut may split a multi-byte character; only the tail is lossy then.
+    let content = match String::from_utf8(bytes) {
+        Ok(content) => content,
+        Err(err) if truncated => String::from_utf8_lossy(err.as_bytes()).to_string(),
+        Err(_) => {
+            return ConflictBlob {
+                content: None,
+                truncated: false,
+                binary: true,
+            }
+        }
+    };
+    ConflictBlob {
+        content: Some(content),
+        truncated,
+        binary: false,
+    }
+}
+
+/// Contents of index stage `stage` (1 base, 2 ours, 3 theirs) for `path`.
+async fn read_stage(repo_path: &PathBuf, stage: u8, path: &str) -> Option<ConflictBlob> {
+    let output = git_command()
+        .args(["cat-file", "blob", &format!(":{stage}:{path}")])
+        .current_dir(repo_path)
+        .output()
+        .await
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(blob_from_bytes(output.stdout, MAX_CONFLICT_BLOB_BYTES))
+}
+
+pub(crate) async fn conflict_status(repo_path: &PathBuf) -> Result<ConflictStatus, String> {
+    let Some(operation) = detect_git_operation(repo_path) else {
+        return Ok(ConflictStatus {
+            operation: None,
+            files: Vec::new(),
+        });
+    };
+    let mut files = Vec::new();
+    for path in conflicted_paths(repo_path).await? {
+        if validate_relative_path(&path).is_err() {
+            continue;
+        }
+        files.push(ConflictEntry {
+            base: read_stage(repo_path, 1, &path).await,
+            ours: read_stage(repo_path, 2, &path).await,
+            theirs: read_stage(repo_path, 3, &path).await,
+            path,
+        });
+    }
+    Ok(ConflictStatus {
+        operation: Some(operation),
+        files,
+    })
+}
+
+/// Writes `content` to `path` inside `repo_path`, refusing symlinks and
+/// parent directories that resolve outside the workspace.
+fn write_resolved_file(repo_path: &Path, path: &str, content: &str) -> Result<(), String> {
+    let canonical_root = repo_path
+        .canonicalize()
+        .map_err(|err| format!("Failed to resolve workspace root: {err}"))?;
+    let target = canonical_root.join(path.trim());
+    let parent = target.parent().ok_or("Invalid file path")?;
+    let canonical_parent = parent
+        .canonicalize()
+        .map_err(|err| format!("Failed to resolve file directory: {err}"))?;
+    if !is_path_within(&canonical_root, &canonical_parent) {
+        return Err("Invalid file path".to_string());
+    }
+    let is_symlink = target
+        .symlink_metadata()
+        .is_ok_and(|metadata| metadata.file_type().is_symlink());
+    if is_symlink {
+        return Err(format!("{} is a symlink", path.trim()));
+    }
+    std::fs::write(&target, content).map_err(|err| format!("Failed to write file: {err}"))
+}
+
+/// Resolves one conflicted path and stages the result. A side that deleted
+/// the path resolves to deleting it.
+pub(crate) async fn resolve_conflict(
+    repo_path: &PathBuf,
+    path: &str,
+    resolution: ConflictResolution,
+) -> Result<(), String> {
+    validate_relative_path(path)?;
+    let path = path.trim();
+    if !conflicted_paths(repo_path).await?.iter().any(|p| p == path) {
+        return Err(format!("{path} is not conflicted"));
+    }
+    let (flag, stage) = match &resolution {
+        ConflictResolution::Ours => ("--ours", 2),
+        ConflictResolution::Theirs => ("--theirs", 3),
+        ConflictResolution::Custom(content) => {
+            write_resolved_file(repo_path, path, content)?;
+            run_git_command(repo_path, &["add", "--", path]).await?;
+            return Ok(());
+        }
+    };
+    if read_stage(repo_path, stage, path).await.is_some() {
+        run_git_command(repo_path, &["checkout", flag, "--", path]).await?;
+        run_git_command(repo_path, &["add", "--", path]).await?;
+    } else {
+        run_git_command(repo_path, &["rm", "-f", "--", path]).await?;
+    }
+    Ok(())
+}
+
+fn no_operation_error() -> String {
+    "No merge, rebase, cherry-pick or revert in progress.".to_string()
+}
+
+pub(crate) async fn abort_operation(repo_path: &PathBuf) -> Result<GitOperation, String> {
+    let operation = detect_git_operation(repo_path).ok_or_else(no_operation_error)?;
+    let args: &[&str] = match operation {
+        GitOperation::Merge => &["merge", "--abort"],
+        GitOperation::Rebase => &["rebase", "--abort"],
+        GitOperation::CherryPick => &["cherry-pick", "--abort"],
+        GitOperation::Revert => &["revert", "--abort"],
+        GitOperation::Bisect => &["bisect", "reset"],
+    };
+    run_git_command(repo_path, args).await?;
+    Ok(operation)
+}
+
+/// Continues the operation with git's default messages (no editor). Returns
+/// the operation that is still in progress afterwards, if any (a rebase can
+/// stop again on the next commit).
+pub(crate) async fn continue_operation(
+    repo_path: &PathBuf,
+) -> Result<Option<GitOperation>, String> {
+    let operation = detect_git_operation(repo_path).ok_or_else(no_operation_error)?;
+    let command = match operation {
+        GitOperation::Merge => "merge",
+        GitOperation::Rebase => "rebase",
+        GitOperation::CherryPick => "cherry-pick",
+        GitOperation::Revert => "revert",
+        GitOperation::Bisect => return Err("A bisect cannot be continued.".to_string()),
+    };
+    if !conflicted_paths(repo_path).await?.is_empty() {
+        return Err("Resolve all conflicts before continuing.".to_string());
+    }
+    run_git_command(
+        repo_path,
+        &["-c", "core.editor=true", command, "--continue"],
+    )
+    .await?;
+    Ok(detect_git_operation(repo_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        blob_from_bytes, parse_conflict_sections, validate_relative_path, write_resolved_file,
+        ConflictResolution,
+    };
+    use uuid::Uuid;
+
+    #[test]
+    fn parses_merge_and_diff3_sections() {
+        let content = "a\n<<<<<<< HEAD\nours 1\nours 2\n=======\ntheirs\n>>>>>>> feature\nb\n\
+<<<<<<< HEAD\nx\n||||||| base\nw\n=======\ny\n>>>>>>> feature\n";
+        let sections = parse_conflict_sections(content);
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].start_line, 2);
+        assert_eq!(sections[0].end_line, 7);
+        assert_eq!(sections[0].ours, "ours 1\nours 2\n");
+        assert_eq!(sections[0].theirs, "theirs\n");
+        assert_eq!(sections[0].base, None);
+        assert_eq!(sections[1].ours, "x\n");
+        assert_eq!(sections[1].base.as_deref(), Some("w\n"));
+        assert_eq!(sections[1].theirs, "y\n");
+    }
+
+    #[test]
+    fn ignores_unterminated_sections() {
+        assert!(parse_conflict_sections("<<<<<<< HEAD\nours\n=======\n").is_empty());
+        assert!(parse_conflict_sections("plain\n=======\n").is_empty());
+    }
+
+    #[test]
+    fn rejects_paths_outside_the_workspace() {
+        assert!(validate_relative_path("src/main.rs").is_ok());
+        assert!(validate_relative_path("./notes.md").is_err());
+        assert!(validate_relative_path(".").is_err());
+        assert!(validate_relative_path("../outside").is_err());
+        assert!(validate_relative_path("src/../../outside").is_err());
+        assert!(validate_relative_path("/etc/passwd").is_err());
+        assert!(validate_relative_path(" ").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_to_write_through_symlinks() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-conflicts-{}", Uuid::new_v4()));
+        let repo = root.join("repo");
+        std::fs::create_dir_all(&repo).expect("create repo");
+        std::fs::write(root.join("outside.txt"), "keep").expect("outside");
+        std::os::unix::fs::symlink(root.join("outside.txt"), repo.join("escape.txt"))
+            .expect("outside link");
+        std::fs::write(repo.join("notes.txt"), "a").expect("notes");
+        std::os::unix::fs::symlink(repo.join("notes.txt"), repo.join("inside.txt"))
+            .expect("inside link");
+
+        let escape = write_resolved_file(&repo, "escape.txt", "changed");
+        let inside = write_resolved_file(&repo, "inside.txt", "changed");
+        let plain = write_resolved_file(&repo, "notes.txt", "resolved");
+        let outside = std::fs::read_to_string(root.join("outside.txt")).expect("read outside");
+        let notes = std::fs::read_to_string(repo.join("notes.txt")).expect("read notes");
+        std::fs::remove_dir_all(&root).expect("cleanup");
+
+        assert_eq!(escape, Err("escape.txt is a symlink".to_string()));
+        assert!(inside.is_err());
+        assert_eq!(plain, Ok(()));
+        assert_eq!(outside, "keep");
+        assert_eq!(notes, "resolved");
+    }
+
+    #[test]
+    fn caps_and_classifies_blobs() {
+        let blob = blob_from_bytes(b"hello world".to_vec(), 5);
+        assert_eq!(blob.content.as_deref(), Some("hello"));
+        assert!(blob.truncated);
+        assert!(!blob.binary);
+
+        let blob = blob_from_bytes(vec![0x89, b'P', b'N', b'G', 0, 1], 100);
+        assert!(blob.binary);
+        assert_eq!(blob.content, None);
+
+        // Cutting inside "é" keeps the valid prefix.
+        let blob = blob_from_bytes("aé".as_bytes().to_vec(), 2);
+        assert!(blob.content.unwrap().starts_with('a'));
+    }
+
+    #[test]
+    fn parses_resolutions() {
+        assert_eq!(
+            ConflictResolution::parse("ours", None),
+            Ok(ConflictResolution::Ours)
+        );
+        assert_eq!(
+            ConflictResolution::parse("custom", Some("x".to_string())),
+            Ok(ConflictResolution::Custom("x".to_string()))
+        );
+        assert!(ConflictResolution::parse("custom", None).is_err());
+        assert!(ConflictResolution::parse("mine", None).is_err());
+    }
+}
@@ -0,0 +1,443 @@
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use serde::Serialize;
+
+use crate::types::WorkspaceEntry;
+
+use super::comparable_path;
+use super::operations::OperationProgress;
+use super::worktree_gc::{list_child_dirs, WorktreeGcFailure};
+
+/// Categories `data_dir_report` can clean up, in the order they run: orphans
+/// go first so the parent folders they leave empty are removed too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum CleanupCategory {
+    /// Worktree folders no stored entry owns, removed the way `gc_worktrees`
+    /// does with `apply: true`.
+    OrphanedWorktrees,
+    /// Empty `<data-dir>/worktrees/<parent-id>` folders.
+    EmptyWorktreeDirs,
+    Trash,
+}
+
+pub(crate) fn parse_cleanup_categories(
+    values: &[String],
+) -> Result<BTreeSet<CleanupCategory>, String> {
+    values
+        .iter()
+        .map(|value| match value.as_str() {
+            "orphanedWorktrees" => Ok(CleanupCategory::OrphanedWorktrees),
+            "emptyWorktreeDirs" => Ok(CleanupCategory::EmptyWorktreeDirs),
+            "trash" => Ok(CleanupCategory::Trash),
+            other => Err(format!("unknown cleanup category: {other}")),
+        })
+        .collect()
+}
+
+/// Size, file count and latest modification (ms since the epoch) of a
+/// folder or file, counted without following symlinks.
+#[derive(Debug, Serialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DiskUsage {
+    pub(crate) size_bytes: u64,
+    pub(crate) file_count: u64,
+    pub(crate) modified_at: Option<u64>,
+}
+
+impl DiskUsage {
+    fn add(&mut self, other: DiskUsage) {
+        self.size_bytes += other.size_bytes;
+        self.file_count += other.file_count;
+        self.modified_at = self.modified_at.max(other.modified_at);
+    }
+}
+
+fn modified_ms(metadata: &std::fs::Metadata) -> Option<u64> {
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    u64::try_from(modified.as_millis()).ok()
+}
+
+pub(crate) fn disk_usage(path: &Path) -> DiskUsage {
+    let mut usage = DiskUsage::default();
+    let Ok(metadata) = path.symlink_metadata() else {
+        return usage;
+    };
+    usage.modified_at = modified_ms(&metadata);
+    if !metadata.is_dir() {
+        usage.size_bytes = metadata.len();
+        usage.file_count = 1;
+        return usage;
+    }
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.path().symlink_metadata() else {
+                continue;
+            };
+            usage.modified_at = usage.modified_at.max(modified_ms(&metadata));
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                usage.size_bytes += metadata.len();
+                usage.file_count += 1;
+            }
+        }
+    }
+    usage
+}
+
+async fn disk_usage_blocking(path: PathBuf) -> DiskUsage {
+    tokio::task::spawn_blocking(move || disk_usage(&path))
+        .await
+        .unwrap_or_default()
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorktreeUsage {
+    pub(crate) path: String,
+    /// The stored worktree entry that owns the folder, if any.
+    pub(crate) workspace_id: Option<String>,
+    #[serde(flatten)]
+    pub(crate) usage: DiskUsage,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ParentUsage {
+    pub(crate) parent_id: String,
+    /// `None` once the parent workspace has been removed.
+    pub(crate) name: Option<String>,
+    pub(crate) registered: bool,
+    pub(crate) path: String,
+    /// Totals over its worktrees.
+    #[serde(flatten)]
+    pub(crate) usage: DiskUsage,
+    pub(crate) worktrees: Vec<WorktreeUsage>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CategoryUsage {
+    /// The folder name under the data dir (`trash`, `projects`, ...),
+    /// `state` for the files directly in it, or `accessLog`.
+    pub(crate) category: String,
+    pub(crate) paths: Vec<String>,
+    #[serde(flatten)]
+    pub(crate) usage: DiskUsage,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CleanupSummary {
+    pub(crate) removed: Vec<String>,
+    pub(crate) reclaimed_bytes: u64,
+    pub(crate) failures: Vec<WorktreeGcFailure>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DataDirReport {
+    pub(crate) data_dir: String,
+    pub(crate) parents: Vec<ParentUsage>,
+    pub(crate) categories: Vec<CategoryUsage>,
+    pub(crate) total_bytes: u64,
+    /// What the requested cleanup removed; sizes above are from before it.
+    pub(crate) cleanup: Option<CleanupSummary>,
+}
+
+/// Sizes everything under `data_dir`: each `worktrees/<parent-id>` folder
+/// with its worktrees, every other top-level folder as its own category and
+/// the top-level files as `state`. `access_log` files count as `accessLog`
+/// wherever they live.
+pub(crate) async fn collect_data_usage(
+    data_dir: &Path,
+    workspaces: &[WorkspaceEntry],
+    access_log: &[PathBuf],
+    progress: &OperationProgress,
+) -> DataDirReport {
+    let worktrees_root = data_dir.join("worktrees");
+    let owners: HashMap<PathBuf, &str> = workspaces
+        .iter()
+        .filter(|entry| entry.kind.is_worktree())
+        .map(|entry| (comparable_path(Path::new(&entry.path)), entry.id.as_str()))
+        .collect();
+    let parents: HashMap<&str, &WorkspaceEntry> = workspaces
+        .iter()
+        .filter(|entry| !entry.kind.is_worktree())
+        .map(|entry| (entry.id.as_str(), entry))
+        .collect();
+
+    let parent_dirs: Vec<(PathBuf, Vec<PathBuf>)> = list_child_dirs(&worktrees_root)
+        .into_iter()
+        .map(|dir| {
+            let children = list_child_dirs(&dir);
+            (dir, children)
+        })
+        .collect();
+    let total_dirs = parent_dirs
+        .iter()
+        .map(|(_, children)| children.len())
+        .sum::<usize>()
+        .max(1);
+    let mut sized_dirs = 0;
+
+    let mut report = DataDirReport {
+        data_dir: data_dir.to_string_lossy().to_string(),
+        parents: Vec::new(),
+        categories: Vec::new(),
+        total_bytes: 0,
+        cleanup: None,
+    };
+    for (parent_dir, children) in parent_dirs {
+        let Some(parent_id) = parent_dir.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let parent = parents.get(parent_id).copied();
+        let mut worktrees = Vec::new();
+        for child in children {
+            let path = child.to_string_lossy().to_string();
+            let percent = u32::try_from(sized_dirs * 100 / total_dirs).unwrap_or(100);
+            progress.report("sizing", Some(percent), Some(&path));
+            worktrees.push(WorktreeUsage {
+                workspace_id: owners
+                    .get(&comparable_path(&child))
+                    .map(|id| id.to_string()),
+                usage: disk_usage_blocking(child).await,
+                path,
+            });
+            sized_dirs += 1;
+        }
+        let mut usage = DiskUsage {
+            modified_at: parent_dir
+                .symlink_metadata()
+                .ok()
+                .and_then(|metadata| modified_ms(&metadata)),
+            ..DiskUsage::default()
+        };
+        for worktree in &worktrees {
+            usage.add(worktree.usage);
+        }
+        report.parents.push(ParentUsage {
+            parent_id: parent_id.to_string(),
+            name: parent.map(|parent| parent.name.clone()),
+            registered: parent.is_some(),
+            path: parent_dir.to_string_lossy().to_string(),
+            usage,
+            worktrees,
+        });
+    }
+
+    progress.report("sizing", Some(100), Some(&report.data_dir));
+    let mut state = CategoryUsage {
+        category: "state".to_string(),
+        paths: Vec::new(),
+        usage: DiskUsage::default(),
+    };
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(data_dir)
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    entries.sort();
+    for path in entries {
+        if path == worktrees_root || access_log.contains(&path) {
+            continue;
+        }
+        let usage = disk_usage_blocking(path.clone()).await;
+        let is_dir = path
+            .symlink_metadata()
+            .is_ok_and(|metadata| metadata.is_dir());
+        let name = path.file_name().and_then(|name| name.to_str());
+        match name {
+            Some(name) if is_dir => report.categories.push(CategoryUsage {
+                category: name.to_string(),
+                paths: vec![path.to_string_lossy().to_string()],
+                usage,
+            }),
+            _ => {
+                state.paths.push(path.to_string_lossy().to_string());
+                state.usage.add(usage);
+            }
+        }
+    }
+    report.categories.push(state);
+    let log_files: Vec<&PathBuf> = access_log.iter().filter(|path| path.exists()).collect();
+    if !access_log.is_empty() {
+        let mut usage = DiskUsage::default();
+        for path in &log_files {
+            usage.add(disk_usage_blocking(path.to_path_buf()).await);
+        }
+        report.categories.push(CategoryUsage {
+            category: "accessLog".to_string(),
+            paths: log_files
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect(),
+            usage,
+        });
+    }
+
+    report.total_bytes = report
+        .parents
+        .iter()
+        .map(|parent| parent.usage.size_bytes)
+        .chain(
+            report
+                .categories
+                .iter()
+                .map(|category| category.usage.size_bytes),
+        )
+        .sum();
+    report
+}
+
+/// A file directly in the data dir (`workspaces.json`, `settings.json`, ...).
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct StateFileUsage {
+    pub(crate) name: String,
+    pub(crate) path: String,
+    #[serde(flatten)]
+    pub(crate) usage: DiskUsage,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorktreesUsage {
+    pub(crate) path: String,
+    /// Totals over every parent folder.
+    #[serde(flatten)]
+    pub(crate) usage: DiskUsage,
+    pub(crate) parents: Vec<ParentUsage>,
+}
+
+/// `disk_usage`: the `data_dir_report` sizes, with the state files listed
+/// one by one and the worktrees totalled.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DiskUsageReport {
+    pub(crate) data_dir: String,
+    pub(crate) state_files: Vec<StateFileUsage>,
+    pub(crate) worktrees: WorktreesUsage,
+    /// Every other folder in the data dir by name, and the access log.
+    pub(crate) categories: Vec<CategoryUsage>,
+    pub(crate) total_bytes: u64,
+}
+
+impl From<DataDirReport> for DiskUsageReport {
+    fn from(report: DataDirReport) -> Self {
+        let mut worktrees = WorktreesUsage {
+            path: Path::new(&report.data_dir)
+                .join("worktrees")
+                .to_string_lossy()
+                .to_string(),
+            usage: DiskUsage::default(),
+            parents: report.parents,
+        };
+        for parent in &worktrees.parents {
+            worktrees.usage.add(parent.usage);
+        }
+        let mut state_files = Vec::new();
+        let mut categories = Vec::new();
+        for category in report.categories {
+            if category.category != "state" {
+                categories.push(category);
+                continue;
+            }
+            for path in category.paths {
+                let file = Path::new(&path);
+                state_files.push(StateFileUsage {
+                    name: file
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default(),
+                    usage: disk_usage(file),
+                    path,
+                });
+            }
+        }
+        Self {
+            data_dir: report.data_dir,
+            state_files,
+            worktrees,
+            categories,
+            total_bytes: report.total_bytes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        collect_data_usage, disk_usage, parse_cleanup_categories, CleanupCategory, DiskUsageReport,
+        OperationProgress,
+    };
+    use uuid::Uuid;
+
+    #[test]
+    fn disk_usage_counts_files_and_latest_change() {
+        let temp_dir = std::env::temp_dir().join(format!("codex-monitor-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(temp_dir.join("nested")).expect("create temp dir");
+        std::fs::write(temp_dir.join("a.txt"), b"hello").expect("write a");
+        std::fs::write(temp_dir.join("nested").join("b.txt"), b"world!").expect("write b");
+
+        let usage = disk_usage(&temp_dir);
+        assert_eq!(usage.size_bytes, 11);
+        assert_eq!(usage.file_count, 2);
+        assert!(usage.modified_at.is_some());
+
+        let file = disk_usage(&temp_dir.join("a.txt"));
+        assert_eq!((file.size_bytes, file.file_count), (5, 1));
+        assert_eq!(disk_usage(&temp_dir.join("missing")).file_count, 0);
+    }
+
+    #[tokio::test]
+    async fn disk_usage_report_lists_state_files_and_worktrees() {
+        let data_dir = std::env::temp_dir().join(format!("codex-monitor-test-{}", Uuid::new_v4()));
+        let worktree = data_dir.join("worktrees").join("parent").join("feature");
+        std::fs::create_dir_all(&worktree).expect("create worktree");
+        std::fs::create_dir_all(data_dir.join("trash")).expect("create trash");
+        std::fs::write(worktree.join("main.rs"), b"fn main() {}").expect("write worktree");
+        std::fs::write(data_dir.join("workspaces.json"), b"[]").expect("write workspaces");
+        std::fs::write(data_dir.join("settings.json"), b"{}").expect("write settings");
+
+        let report = collect_data_usage(&data_dir, &[], &[], &OperationProgress::detached()).await;
+        let usage = DiskUsageReport::from(report);
+        let files: Vec<(&str, u64)> = usage
+            .state_files
+            .iter()
+            .map(|file| (file.name.as_str(), file.usage.size_bytes))
+            .collect();
+        assert_eq!(files, [("settings.json", 2), ("workspaces.json", 2)]);
+        assert_eq!(usage.worktrees.usage.size_bytes, 12);
+        assert_eq!(usage.worktrees.parents[0].parent_id, "parent");
+        assert!(!usage.worktrees.parents[0].registered);
+        assert_eq!(usage.categories[0].category, "trash");
+        assert_eq!(usage.total_bytes, 16);
+
+        let _ = std::fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn parses_cleanup_categories_in_run_order() {
+        let categories = parse_cleanup_categories(&[
+            "trash".to_string(),
+            "emptyWorktreeDirs".to_string(),
+            "orphanedWorktrees".to_string(),
+        ])
+        .expect("categories");
+        assert_eq!(
+            categories.into_iter().collect::<Vec<_>>(),
+            [
+                CleanupCategory::OrphanedWorktrees,
+                CleanupCategory::EmptyWorktreeDirs,
+                CleanupCategory::Trash,
+            ]
+        );
+        assert!(parse_cleanup_categories(&["logs".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use super::conflicts::validate_relative_path;
+use super::git_state::ensure_no_git_operation;
+use super::run_git_command;
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DiscardResult {
+    /// Tracked files whose staged and unstaged changes are (or would be) reverted.
+    pub(crate) tracked: Vec<String>,
+    /// Untracked files that are (or would be) deleted; only with `includeUntracked`.
+    pub(crate) untracked: Vec<String>,
+    /// False for a dry run.
+    pub(crate) applied: bool,
+}
+
+fn split_nul(output: &str) -> Vec<String> {
+    output
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Pathspecs for `paths`, or the whole checkout when none are given.
+fn scope(paths: &[String]) -> Result<Vec<String>, String> {
+    if paths.is_empty() {
+        return Ok(vec![".".to_string()]);
+    }
+    paths
+        .iter()
+        .map(|path| {
+            validate_relative_path(path)?;
+            Ok(path.trim().to_string())
+        })
+        .collect()
+}
+
+async fn git_with_pathspecs(
+    repo_path: &PathBuf,
+    args: &[&str],
+    pathspecs: &[String],
+) -> Result<String, String> {
+    // Literal pathspecs so `*` or `:(...)` in a file name only match that file.
+    let mut full = vec!["--literal-pathspecs"];
+    full.extend_from_slice(args);
+    full.push("--");
+    full.extend(pathspecs.iter().map(String::as_str));
+    run_git_command(repo_path, &full).await
+}
+
+/// Reverts changes to `paths` (everything when empty) back to `HEAD`, and
+/// deletes untracked files in scope when `include_untracked` is set. With
+/// `dry_run` only the affected files are reported.
+pub(crate) async fn discard_changes(
+    repo_path: &PathBuf,
+    paths: &[String],
+    include_untracked: bool,
+    dry_run: bool,
+) -> Result<DiscardResult, String> {
+    let pathspecs = scope(paths)?;
+    ensure_no_git_operation(repo_path, "discard changes")?;
+
+    let tracked = split_nul(
+        &git_with_pathspecs(
+            repo_path,
+            // Relative to `repo_path` like the other commands, even when the
+            // workspace is a folder below the repo root.
+            &[
+                "diff",
+                "HEAD",
+                "--name-only",
+                "--relative",
+                "--no-renames",
+                "-z",
+            ],
+            &pathspecs,
+        )
+        .await?,
+    );
+    let untracked = if include_untracked {
+        split_nul(
+            &git_with_pathspecs(
+                repo_path,
+                &["ls-files", "--others", "--exclude-standard", "-z"],
+                &pathspecs,
+            )
+            .await?,
+        )
+    } else {
+        Vec::new()
+    };
+
+    if !dry_run {
+        if !tracked.is_empty() {
+            git_with_pathspecs(
+                repo_path,
+                &["restore", "--source=HEAD", "--staged", "--worktree"],
+                &tracked,
+            )
+            .await?;
+        }
+        if !untracked.is_empty() {
+            git_with_pathspecs(repo_path, &["clean", "-fd"], &pathspecs).await?;
+        }
+    }
+    Ok(DiscardResult {
+        tracked,
+        untracked,
+        applied: !dry_run,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{discard_changes, scope, split_nul};
+    use git2::Repository;
+    use std::path::Path;
+    use uuid::Uuid;
+
+    #[test]
+    fn splits_nul_separated_paths() {
+        assert_eq!(
+            split_nul("a.txt\0dir/b c.txt\0"),
+            vec!["a.txt".to_string(), "dir/b c.txt".to_string()]
+        );
+        assert!(split_nul("").is_empty());
+    }
+
+    #[test]
+    fn scopes_to_checkout_or_validated_paths() {
+        assert_eq!(scope(&[]).unwrap(), vec![".".to_string()]);
+        assert_eq!(
+            scope(&["src/lib.rs".to_string()]).unwrap(),
+            vec!["src/lib.rs".to_string()]
+        );
+        assert!(scope(&["../outside".to_string()]).is_err());
+        assert!(scope(&["/etc/passwd".to_string()]).is_err());
+    }
+
+    #[tokio::test]
+    async fn discards_in_a_folder_below_the_repo_root() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-discard-{}", Uuid::new_v4()));
+        let workspace = root.join("app");
+        std::fs::create_dir_all(&workspace).expect("create workspace");
+        std::fs::write(workspace.join("a.txt"), "one").expect("write file");
+        let repo = Repository::init(&root).expect("init repo");
+        let mut index = repo.index().expect("index");
+        index.add_path(Path::new("app/a.txt")).expect("add path");
+        let tree = repo
+            .find_tree(index.write_tree().expect("write tree"))
+            .expect("find tree");
+        let sig = git2::Signature::now("Test", "test@example.com").expect("signature");
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .expect("commit");
+        std::fs::write(workspace.join("a.txt"), "two").expect("change file");
+
+        let result = discard_changes(&workspace, &[], false, false).await;
+        let content = std::fs::read_to_string(workspace.join("a.txt")).expect("read file");
+        let _ = std::fs::remove_dir_all(&root);
+
+        assert_eq!(result.expect("discard").tracked, vec!["a.txt".to_string()]);
+        assert_eq!(content, "one");
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use super::git_state::{is_bare_repo, resolve_git_dir};
+
+pub(crate) const DEFAULT_DISCOVERY_DEPTH: u32 = 3;
+pub(crate) const MAX_DISCOVERY_DEPTH: u32 = 6;
+const MAX_CANDIDATES: usize = 500;
+/// Folders read per call, so a huge tree can't stall the daemon.
+const MAX_SCANNED_DIRS: usize = 20_000;
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DiscoveredWorkspace {
+    pub(crate) path: String,
+    pub(crate) name: String,
+    pub(crate) is_git_repo: bool,
+    /// Already added as a workspace.
+    pub(crate) registered: bool,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct DiscoveryResult {
+    pub(crate) roots: Vec<String>,
+    pub(crate) candidates: Vec<DiscoveredWorkspace>,
+    /// The candidate or folder budget ran out before the scan finished.
+    pub(crate) truncated: bool,
+}
+
+fn skip_dir(name: &str) -> bool {
+    name.starts_with('.')
+        || matches!(
+            name,
+            "node_modules" | "target" | "dist" | "build" | "vendor" | "Library"
+        )
+}
+
+/// Subfolders worth scanning, sorted so results are stable.
+fn child_dirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut children: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().map(|kind| kind.is_dir()).unwrap_or(false))
+        .filter(|entry| !skip_dir(&entry.file_name().to_string_lossy()))
+        .map(|entry| entry.path())
+        .collect();
+    children.sort();
+    children
+}
+
+pub(crate) fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+struct Scan<'a> {
+    registered: &'a HashSet<PathBuf>,
+    seen: HashSet<PathBuf>,
+    candidates: Vec<DiscoveredWorkspace>,
+    scanned_dirs: usize,
+    truncated: bool,
+}
+
+impl Scan<'_> {
+    fn push(&mut self, path: &Path, is_git_repo: bool) {
+        let canonical = canonical(path);
+        if !self.seen.insert(canonical.clone()) {
+            return;
+        }
+        if self.candidates.len() >= MAX_CANDIDATES {
+            self.truncated = true;
+            return;
+        }
+        self.candidates.push(DiscoveredWorkspace {
+            path: path.to_string_lossy().to_string(),
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            is_git_repo,
+            registered: self.registered.contains(&canonical),
+        });
+    }
+
+    /// Records repos under `dir`; returns whether any were found.
+    fn walk(&mut self, dir: &Path, depth: u32) -> bool {
+        if resolve_git_dir(dir).is_some() {
+            self.push(dir, true);
+            return true;
+        }
+        if depth == 0 || is_bare_repo(dir) {
+            return false;
+        }
+        if self.scanned_dirs >= MAX_SCANNED_DIRS {
+            self.truncated = true;
+            return false;
+        }
+        self.scanned_dirs += 1;
+        let mut found = false;
+        for child in child_dirs(dir) {
+            found |= self.walk(&child, depth - 1);
+        }
+        found
+    }
+}
+
+/// Finds git repos up to `max_depth` folders below each root. Top-level
+/// folders of a root without any repo inside are offered as plain folders.
+pub(crate) fn discover_workspaces(
+    roots: &[PathBuf],
+    max_depth: u32,
+    registered: &HashSet<PathBuf>,
+) -> DiscoveryResult {
+    let mut scan = Scan {
+        registered,
+        seen: HashSet::new(),
+        candidates: Vec::new(),
+        scanned_dirs: 0,
+        truncated: false,
+    };
+    for root in roots.iter().filter(|root| root.is_dir()) {
+        if resolve_git_dir(root).is_some() {
+            scan.push(root, true);
+            continue;
+        }
+        for child in child_dirs(root) {
+            if !scan.walk(&child, max_depth.saturating_sub(1)) && !is_bare_repo(&child) {
+                scan.push(&child, false);
+            }
+        }
+    }
+    DiscoveryResult {
+        roots: roots
+            .iter()
+            .map(|root| root.to_string_lossy().to_string())
+            .collect(),
+        candidates: scan.candidates,
+        truncated: scan.truncated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{canonical, discover_workspaces};
+    use std::collections::HashSet;
+    use std::fs;
+    use uuid::Uuid;
+
+    #[test]
+    fn finds_repos_and_plain_folders() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-discover-{}", Uuid::new_v4()));
+        fs::create_dir_all(root.join("app/.git")).expect("app repo");
+        fs::create_dir_all(root.join("group/lib/.git")).expect("nested repo");
+        fs::create_dir_all(root.join("group/lib/sub/.git")).expect("repo inside repo");
+        fs::create_dir_all(root.join("notes")).expect("plain folder");
+        fs::create_dir_all(root.join("node_modules/pkg/.git")).expect("skipped repo");
+        fs::create_dir_all(root.join("deep/a/b/c/.git")).expect("too deep");
+
+        let registered: HashSet<_> = [canonical(&root.join("app"))].into_iter().collect();
+        let result = discover_workspaces(std::slice::from_ref(&root), 3, &registered);
+        let summary: Vec<(String, bool, bool)> = result
+            .candidates
+            .iter()
+            .map(|candidate| {
+                let relative = candidate
+                    .path
+                    .strip_prefix(root.to_string_lossy().as_ref())
+                    .unwrap_or(&candidate.path)
+                    .trim_start_matches(['/', '\\'])
+                    .replace('\\', "/");
+                (relative, candidate.is_git_repo, candidate.registered)
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("app".to_string(), true, true),
+                ("deep".to_string(), false, false),
+                ("group/lib".to_string(), true, false),
+                ("notes".to_string(), false, false),
+            ]
+        );
+        assert!(!result.truncated);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+pub(crate) const DEFAULT_EVENT_BUFFER: usize = 2048;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct LagCounters {
+    /// How many times the receiver fell behind the broadcast buffer.
+    pub(crate) occurrences: u64,
+    /// Events overwritten before the receiver could read them.
+    pub(crate) dropped_events: u64,
+}
+
+impl LagCounters {
+    fn record(&mut self, dropped: u64) {
+        self.occurrences += 1;
+        self.dropped_events += dropped;
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ConnectionLag {
+    pub(crate) peer: SocketAddr,
+    #[serde(flatten)]
+    pub(crate) counters: LagCounters,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct EventLagSnapshot {
+    #[serde(flatten)]
+    pub(crate) total: LagCounters,
+    pub(crate) connections: Vec<ConnectionLag>,
+}
+
+/// Accounts for events lost because a connection read the broadcast channel
+/// too slowly, globally and per open connection.
+#[derive(Default)]
+pub(crate) struct EventLagTracker {
+    total: Mutex<LagCounters>,
+    connections: Mutex<HashMap<SocketAddr, LagCounters>>,
+}
+
+impl EventLagTracker {
+    /// Records a lag for `peer`; returns true the first time that connection lags.
+    pub(crate) fn record(&self, peer: SocketAddr, dropped: u64) -> bool {
+        if let Ok(mut total) = self.total.lock() {
+            total.record(dropped);
+        }
+        let Ok(mut connections) = self.connections.lock() else {
+            return false;
+        };
+        let counters = connections.entry(peer).or_default();
+        counters.record(dropped);
+        counters.occurrences == 1
+    }
+
+    pub(crate) fn forget(&self, peer: SocketAddr) {
+        if let Ok(mut connections) = self.connections.lock() {
+            connections.remove(&peer);
+        }
+    }
+
+    pub(crate) fn snapshot(&self) -> EventLagSnapshot {
+        let total = self.total.lock().map(|total| *total).unwrap_or_default();
+        let mut connections: Vec<ConnectionLag> = self
+            .connections
+            .lock()
+            .map(|connections| {
+                connections
+                    .iter()
+                    .map(|(peer, counters)| ConnectionLag {
+                        peer: *peer,
+                        counters: *counters,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        connections.sort_by_key(|connection| connection.peer);
+        EventLagSnapshot { total, connections }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EventLagTracker, LagCounters};
+    use std::net::SocketAddr;
+
+    fn peer(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
+    }
+
+    #[test]
+    fn first_lag_per_connection_is_reported_once() {
+        let tracker = EventLagTracker::default();
+        assert!(tracker.record(peer(1), 5));
+        assert!(!tracker.record(peer(1), 3));
+        assert!(tracker.record(peer(2), 1));
+    }
+
+    #[test]
+    fn snapshot_keeps_global_totals_after_disconnect() {
+        let tracker = EventLagTracker::default();
+        tracker.record(peer(1), 5);
+        tracker.record(peer(2), 2);
+        tracker.forget(peer(1));
+
+        let snapshot = tracker.snapshot();
+        assert_eq!(
+            snapshot.total,
+            LagCounters {
+                occurrences: 2,
+                dropped_events: 7,
+            }
+        );
+        assert_eq!(snapshot.connections.len(), 1);
+        assert_eq!(snapshot.connections[0].peer, peer(2));
+        assert_eq!(snapshot.connections[0].counters.dropped_events, 2);
+    }
+}
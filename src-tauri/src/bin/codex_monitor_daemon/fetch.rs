@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+
+use super::backend::git_command::git_command;
+use super::types::WorkspaceFetchStatus;
+
+/// Shortest allowed `backgroundFetchIntervalSecs`.
+pub(crate) const MIN_FETCH_INTERVAL_SECS: u64 = 60;
+/// How often the background loop checks which repos are due.
+pub(crate) const FETCH_TICK: Duration = Duration::from_secs(30);
+/// Repos fetched at the same time by the background loop.
+pub(crate) const MAX_CONCURRENT_FETCHES: usize = 2;
+const FETCH_TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_FETCH_BACKOFF_SECS: u64 = 6 * 60 * 60;
+
+/// One async lock per main repo (keyed by its workspace id), held by daemon
+/// operations that change or fetch into that repo so they never overlap.
+#[derive(Default)]
+pub(crate) struct RepoLocks {
+    locks: StdMutex<HashMap<String, Arc<Mutex<()>>>>,
+}
+
+impl RepoLocks {
+    pub(crate) fn lock_for(&self, repo_id: &str) -> Arc<Mutex<()>> {
+        let mut locks = self.locks.lock().unwrap_or_else(|err| err.into_inner());
+        Arc::clone(locks.entry(repo_id.to_string()).or_default())
+    }
+}
+
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+        .unwrap_or_default()
+}
+
+/// Per-repo fetch results, keyed by main workspace id.
+#[derive(Default)]
+pub(crate) struct FetchTracker {
+    statuses: StdMutex<HashMap<String, (WorkspaceFetchStatus, u64)>>,
+}
+
+impl FetchTracker {
+    pub(crate) fn status(&self, repo_id: &str) -> Option<WorkspaceFetchStatus> {
+        let statuses = self.statuses.lock().unwrap_or_else(|err| err.into_inner());
+        statuses.get(repo_id).map(|(status, _)| status.clone())
+    }
+
+    /// Whether a background fetch is due: never fetched, or `interval` has
+    /// passed since the last attempt, doubled for every consecutive failure.
+    pub(crate) fn is_due(&self, repo_id: &str, now: u64, interval_secs: u64) -> bool {
+        let statuses = self.statuses.lock().unwrap_or_else(|err| err.into_inner());
+        let Some((status, last_attempt)) = statuses.get(repo_id) else {
+            return true;
+        };
+        let wait_secs = backoff_secs(interval_secs, status.consecutive_failures);
+        now.saturating_sub(*last_attempt) >= wait_secs.saturating_mul(1000)
+    }
+
+    pub(crate) fn record(
+        &self,
+        repo_id: &str,
+        now: u64,
+        result: &Result<(), String>,
+    ) -> WorkspaceFetchStatus {
+        let mut statuses = self.statuses.lock().unwrap_or_else(|err| err.into_inner());
+        let (status, last_attempt) = statuses.entry(repo_id.to_string()).or_default();
+        *last_attempt = now;
+        match result {
+            Ok(()) => {
+                status.last_fetch_at = Some(now);
+                status.last_error = None;
+                status.consecutive_failures = 0;
+            }
+            Err(error) => {
+                status.last_error = Some(error.clone());
+                status.consecutive_failures = status.consecutive_failures.saturating_add(1);
+            }
+        }
+        status.clone()
+    }
+
+    pub(crate) fn forget(&self, repo_id: &str) {
+        let mut statuses = self.statuses.lock().unwrap_or_else(|err| err.into_inner());
+        statuses.remove(repo_id);
+    }
+}
+
+pub(crate) fn backoff_secs(interval_secs: u64, failures: u32) -> u64 {
+    let factor = 1u64.checked_shl(failures.min(16)).unwrap_or(u64::MAX);
+    interval_secs
+        .saturating_mul(factor)
+        .min(MAX_FETCH_BACKOFF_SECS.max(interval_secs))
+}
+
+/// `git fetch --prune` for all remotes; never prompts for credentials.
+pub(crate) async fn run_fetch(repo_path: &Path) -> Result<(), String> {
+    let mut command = git_command();
+    command
+        .args(["fetch", "--all", "--prune", "--quiet"])
+        .current_dir(repo_path)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    let output = match timeout(FETCH_TIMEOUT, command.output()).await {
+        Ok(result) => result.map_err(|e| format!("Failed to run git: {e}"))?,
+        Err(_) => {
+            return Err(format!(
+                "git fetch timed out after {}s.",
+                FETCH_TIMEOUT.as_secs()
+            ))
+        }
+    };
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let detail = stderr.trim();
+    if detail.is_empty() {
+        Err("git fetch failed.".to_string())
+    } else {
+        Err(detail.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{backoff_secs, FetchTracker, MAX_FETCH_BACKOFF_SECS};
+
+    #[test]
+    fn backs_off_after_failures() {
+        assert_eq!(backoff_secs(300, 0), 300);
+        assert_eq!(backoff_secs(300, 1), 600);
+        assert_eq!(backoff_secs(300, 3), 2400);
+        assert_eq!(backoff_secs(300, 40), MAX_FETCH_BACKOFF_SECS);
+    }
+
+    #[test]
+    fn tracks_due_repos_and_results() {
+        let tracker = FetchTracker::default();
+        assert!(tracker.is_due("repo", 1_000, 60));
+
+        let status = tracker.record("repo", 1_000, &Ok(()));
+        assert_eq!(status.last_fetch_at, Some(1_000));
+        assert!(!tracker.is_due("repo", 30_000, 60));
+        assert!(tracker.is_due("repo", 61_000, 60));
+
+        let status = tracker.record("repo", 61_000, &Err("offline".to_string()));
+        assert_eq!(status.last_fetch_at, Some(1_000));
+        assert_eq!(status.last_error.as_deref(), Some("offline"));
+        assert_eq!(status.consecutive_failures, 1);
+        assert!(!tracker.is_due("repo", 121_000, 60));
+        assert!(tracker.is_due("repo", 181_000, 60));
+
+        tracker.forget("repo");
+        assert!(tracker.status("repo").is_none());
+    }
+}
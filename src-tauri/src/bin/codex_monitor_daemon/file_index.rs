@@ -0,0 +1,462 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::utils::normalize_git_path;
+use super::{list_workspace_files_inner, should_skip_dir, workspace_walker, MAX_WORKSPACE_FILES};
+
+/// Paths kept in a workspace's change log; older changes are dropped and
+/// tokens from before them get a full snapshot instead.
+const MAX_LOGGED_PATHS: usize = 50_000;
+/// Changed paths collected between two calls before the next call rescans
+/// the whole workspace instead of walking each of them.
+const MAX_PENDING_PATHS: usize = 1_000;
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FileDelta {
+    /// True when `added` is a full snapshot the client should replace its
+    /// list with (unknown or expired `sinceToken`).
+    pub(crate) reset: bool,
+    pub(crate) added: Vec<String>,
+    pub(crate) removed: Vec<String>,
+    pub(crate) token: String,
+}
+
+struct Change {
+    /// Index version this change produced.
+    version: u64,
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+struct WorkspaceIndex {
+    /// Changes whenever the index is rebuilt, so tokens from an earlier index
+    /// (or an earlier daemon run) are never mistaken for current ones.
+    epoch: String,
+    version: u64,
+    files: BTreeSet<String>,
+    log: VecDeque<Change>,
+    logged_paths: usize,
+}
+
+impl WorkspaceIndex {
+    fn new(files: BTreeSet<String>) -> Self {
+        Self {
+            epoch: Uuid::new_v4().simple().to_string(),
+            version: 0,
+            files,
+            log: VecDeque::new(),
+            logged_paths: 0,
+        }
+    }
+
+    fn token(&self) -> String {
+        format!("{}.{}", self.epoch, self.version)
+    }
+
+    fn apply(&mut self, files: BTreeSet<String>) {
+        let added: Vec<String> = files.difference(&self.files).cloned().collect();
+        let removed: Vec<String> = self.files.difference(&files).cloned().collect();
+        self.files = files;
+        if added.is_empty() && removed.is_empty() {
+            return;
+        }
+        self.version += 1;
+        self.logged_paths += added.len() + removed.len();
+        self.log.push_back(Change {
+            version: self.version,
+            added,
+            removed,
+        });
+        while self.logged_paths > MAX_LOGGED_PATHS {
+            let Some(change) = self.log.pop_front() else {
+                break;
+            };
+            self.logged_paths -= change.added.len() + change.removed.len();
+        }
+    }
+
+    /// What changed since `since_token`, or the full listing when the token
+    /// is from another index or older than the log.
+    fn delta(&self, since_token: Option<&str>) -> FileDelta {
+        let changes = since_token
+            .and_then(parse_token)
+            .filter(|(epoch, _)| *epoch == self.epoch)
+            .and_then(|(_, version)| self.changes_since(version));
+        match changes {
+            Some((added, removed)) => FileDelta {
+                reset: false,
+                added,
+                removed,
+                token: self.token(),
+            },
+            None => FileDelta {
+                reset: true,
+                added: self.files.iter().cloned().collect(),
+                removed: Vec::new(),
+                token: self.token(),
+            },
+        }
+    }
+
+    /// Net changes after `since`, or `None` when the log no longer reaches
+    /// back that far.
+    fn changes_since(&self, since: u64) -> Option<(Vec<String>, Vec<String>)> {
+        if since > self.version {
+            return None;
+        }
+        let oldest = self
+            .log
+            .front()
+            .map_or(self.version, |change| change.version - 1);
+        if since < oldest {
+            return None;
+        }
+        let mut added = BTreeSet::new();
+        let mut removed = BTreeSet::new();
+        for change in self.log.iter().filter(|change| change.version > since) {
+            for path in &change.added {
+                if !removed.remove(path) {
+                    added.insert(path.clone());
+                }
+            }
+            for path in &change.removed {
+                if !added.remove(path) {
+                    removed.insert(path.clone());
+                }
+            }
+        }
+        Some((added.into_iter().collect(), removed.into_iter().collect()))
+    }
+}
+
+fn parse_token(token: &str) -> Option<(&str, u64)> {
+    let (epoch, version) = token.trim().split_once('.')?;
+    Some((epoch, version.parse().ok()?))
+}
+
+/// What the watcher reported since the index was last brought up to date.
+#[derive(Default)]
+struct Pending {
+    /// Changed paths, relative to the workspace root.
+    paths: BTreeSet<String>,
+    /// Set when events were lost or can't be placed in the index.
+    rescan: bool,
+}
+
+impl Pending {
+    fn record(&mut self, roots: &[PathBuf], event: notify::Result<Event>) {
+        let event = match event {
+            Ok(event) if event.need_rescan() => {
+                self.rescan = true;
+                return;
+            }
+            Ok(event) => event,
+            Err(_) => {
+                self.rescan = true;
+                return;
+            }
+        };
+        // Reads and content or metadata changes leave the listing alone.
+        if matches!(
+            event.kind,
+            EventKind::Access(_) | EventKind::Modify(ModifyKind::Data(_) | ModifyKind::Metadata(_))
+        ) {
+            return;
+        }
+        for path in &event.paths {
+            let relative = roots.iter().find_map(|root| relative_path(root, path));
+            match relative {
+                Some(relative) if relative.is_empty() => self.rescan = true,
+                Some(relative) if in_skipped_dir(&relative) => {}
+                Some(relative) => {
+                    self.paths.insert(relative);
+                }
+                None => self.rescan = true,
+            }
+        }
+        if self.paths.len() > MAX_PENDING_PATHS {
+            self.paths.clear();
+            self.rescan = true;
+        }
+    }
+}
+
+/// `path` relative to `root`, with `/` separators.
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(root).ok()?;
+    Some(normalize_git_path(&relative.to_string_lossy()))
+}
+
+/// Whether a folder the listing never enters holds `path`, like `.git`.
+fn in_skipped_dir(path: &str) -> bool {
+    let mut parents = path.split('/').rev().skip(1);
+    parents.any(should_skip_dir)
+}
+
+/// Whether `path` is one of `changed` or lies inside one of them.
+fn is_changed(path: &str, changed: &BTreeSet<String>) -> bool {
+    path.match_indices('/')
+        .map(|(end, _)| &path[..end])
+        .chain(std::iter::once(path))
+        .any(|prefix| changed.contains(prefix))
+}
+
+/// Whether one of `changed` lies inside the folder `dir`.
+fn holds_changes(dir: &str, changed: &BTreeSet<String>) -> bool {
+    let prefix = format!("{dir}/");
+    changed
+        .range(prefix.clone()..)
+        .next()
+        .is_some_and(|path| path.starts_with(&prefix))
+}
+
+fn scan_all(root: &Path) -> BTreeSet<String> {
+    list_workspace_files_inner(&root.to_path_buf(), MAX_WORKSPACE_FILES, false)
+        .into_iter()
+        .map(|entry| entry.path)
+        .collect()
+}
+
+/// `files` with the `changed` paths walked again. Only the changed paths and
+/// the folders leading to them are visited, so ignore files apply as in a
+/// full walk.
+fn rescan_changed(
+    root: &Path,
+    files: &BTreeSet<String>,
+    changed: &BTreeSet<String>,
+) -> BTreeSet<String> {
+    let mut updated: BTreeSet<String> = files
+        .iter()
+        .filter(|path| !is_changed(path, changed))
+        .cloned()
+        .collect();
+    let filter_root = root.to_path_buf();
+    let filter_changed = changed.clone();
+    let walker = workspace_walker(root, false)
+        .filter_entry(move |entry| {
+            if entry.depth() == 0 {
+                return true;
+            }
+            let Some(path) = relative_path(&filter_root, entry.path()) else {
+                return false;
+            };
+            if entry.file_type().is_some_and(|ft| ft.is_dir()) {
+                let name = entry.file_name().to_string_lossy();
+                return !should_skip_dir(&name)
+                    && (is_changed(&path, &filter_changed)
+                        || holds_changes(&path, &filter_changed));
+            }
+            is_changed(&path, &filter_changed)
+        })
+        .build();
+    for entry in walker.flatten() {
+        if updated.len() >= MAX_WORKSPACE_FILES {
+            break;
+        }
+        if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+            continue;
+        }
+        if let Some(path) = relative_path(root, entry.path()).filter(|path| !path.is_empty()) {
+            updated.insert(path);
+        }
+    }
+    updated
+}
+
+/// A workspace's file index and the watcher keeping it current.
+struct WatchedIndex {
+    root: PathBuf,
+    pending: Arc<Mutex<Pending>>,
+    /// Feeds `pending` until dropped. `None` when the folder couldn't be
+    /// watched; every call then rescans it.
+    watcher: Option<RecommendedWatcher>,
+    index: Mutex<Option<WorkspaceIndex>>,
+}
+
+impl WatchedIndex {
+    fn watch(root: &Path) -> Self {
+        let pending = Arc::new(Mutex::new(Pending::default()));
+        // Events may name the root as given or fully resolved.
+        let mut roots = vec![root.to_path_buf()];
+        roots.extend(
+            root.canonicalize()
+                .ok()
+                .filter(|canonical| canonical != root),
+        );
+        let sink = Arc::clone(&pending);
+        let watcher = notify::recommended_watcher(move |event| {
+            let mut pending = sink.lock().unwrap_or_else(|err| err.into_inner());
+            pending.record(&roots, event);
+        })
+        .and_then(|mut watcher| {
+            watcher.watch(root, RecursiveMode::Recursive)?;
+            Ok(watcher)
+        })
+        .ok();
+        Self {
+            root: root.to_path_buf(),
+            pending,
+            watcher,
+            index: Mutex::new(None),
+        }
+    }
+
+    /// Brings the index up to date with what the watcher saw and reports
+    /// the changes since `since_token`.
+    fn delta(&self, since_token: Option<&str>) -> FileDelta {
+        let mut index = self.index.lock().unwrap_or_else(|err| err.into_inner());
+        // Taken first: changes made during the walk below are walked again
+        // next time.
+        let pending =
+            std::mem::take(&mut *self.pending.lock().unwrap_or_else(|err| err.into_inner()));
+        if let Some(index) = index.as_mut() {
+            if self.watcher.is_none() || pending.rescan {
+                index.apply(scan_all(&self.root));
+            } else if !pending.paths.is_empty() {
+                index.apply(rescan_changed(&self.root, &index.files, &pending.paths));
+            }
+        }
+        index
+            .get_or_insert_with(|| WorkspaceIndex::new(scan_all(&self.root)))
+            .delta(since_token)
+    }
+}
+
+/// Versioned file lists per workspace for `list_workspace_files_delta`. The
+/// first call for a workspace lists it and starts watching it; later calls
+/// walk only what the watcher saw change.
+#[derive(Default)]
+pub(crate) struct FileIndexes {
+    indexes: Mutex<HashMap<String, Arc<WatchedIndex>>>,
+}
+
+impl FileIndexes {
+    /// Returns what changed in the workspace at `root` since `since_token`.
+    /// A workspace that moved is listed again under a new token.
+    pub(crate) fn delta(
+        &self,
+        workspace_id: &str,
+        root: &Path,
+        since_token: Option<&str>,
+    ) -> FileDelta {
+        let watched = {
+            let mut indexes = self.indexes.lock().unwrap_or_else(|err| err.into_inner());
+            match indexes.get(workspace_id) {
+                Some(watched) if watched.root == root => Arc::clone(watched),
+                _ => {
+                    let watched = Arc::new(WatchedIndex::watch(root));
+                    indexes.insert(workspace_id.to_string(), Arc::clone(&watched));
+                    watched
+                }
+            }
+        };
+        watched.delta(since_token)
+    }
+
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        let mut indexes = self.indexes.lock().unwrap_or_else(|err| err.into_inner());
+        indexes.remove(workspace_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FileIndexes, WorkspaceIndex};
+    use std::collections::BTreeSet;
+    use std::time::{Duration, Instant};
+    use uuid::Uuid;
+
+    fn paths(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    fn files(items: &[&str]) -> BTreeSet<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn returns_snapshot_then_deltas() {
+        let mut index = WorkspaceIndex::new(files(&["a", "b"]));
+        let first = index.delta(None);
+        assert!(first.reset);
+        assert_eq!(first.added, paths(&["a", "b"]));
+
+        index.apply(files(&["a", "b"]));
+        let unchanged = index.delta(Some(&first.token));
+        assert!(!unchanged.reset);
+        assert!(unchanged.added.is_empty() && unchanged.removed.is_empty());
+        assert_eq!(unchanged.token, first.token);
+
+        index.apply(files(&["a", "c"]));
+        let second = index.delta(Some(&first.token));
+        assert!(!second.reset);
+        assert_eq!(second.added, paths(&["c"]));
+        assert_eq!(second.removed, paths(&["b"]));
+
+        // Changes that cancel out across versions are not reported.
+        index.apply(files(&["a", "c", "d"]));
+        index.apply(files(&["a", "c"]));
+        let third = index.delta(Some(&second.token));
+        assert!(third.added.is_empty() && third.removed.is_empty());
+    }
+
+    #[test]
+    fn unknown_tokens_get_a_snapshot() {
+        let mut index = WorkspaceIndex::new(files(&["a"]));
+        let first = index.delta(None);
+        index.apply(files(&["a", "b"]));
+        let stale = index.delta(Some("other.0"));
+        assert!(stale.reset);
+        assert_eq!(stale.added, paths(&["a", "b"]));
+
+        let rebuilt = WorkspaceIndex::new(files(&["a"])).delta(Some(&first.token));
+        assert!(rebuilt.reset);
+        assert_ne!(rebuilt.token, first.token);
+    }
+
+    #[test]
+    fn follows_watched_changes() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-index-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("sub")).expect("create");
+        std::fs::create_dir_all(root.join("node_modules")).expect("create");
+        std::fs::write(root.join(".gitignore"), "ignored.txt\n").expect("write");
+        std::fs::write(root.join("a.txt"), "a").expect("write");
+        std::fs::write(root.join("sub/b.txt"), "b").expect("write");
+        std::fs::write(root.join("node_modules/x.js"), "x").expect("write");
+
+        let indexes = FileIndexes::default();
+        let first = indexes.delta("ws", &root, None);
+        assert!(first.reset);
+        assert_eq!(first.added, paths(&[".gitignore", "a.txt", "sub/b.txt"]));
+
+        std::fs::write(root.join("c.txt"), "c").expect("write");
+        std::fs::write(root.join("ignored.txt"), "i").expect("write");
+        std::fs::write(root.join("node_modules/y.js"), "y").expect("write");
+        std::fs::remove_dir_all(root.join("sub")).expect("remove");
+        // The watcher reports changes a little later.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let delta = loop {
+            let delta = indexes.delta("ws", &root, Some(&first.token));
+            if delta.removed.len() == 1 || Instant::now() > deadline {
+                break delta;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        };
+        assert!(!delta.reset);
+        assert_eq!(delta.added, paths(&["c.txt"]));
+        assert_eq!(delta.removed, paths(&["sub/b.txt"]));
+
+        indexes.forget("ws");
+        let rebuilt = indexes.delta("ws", &root, Some(&delta.token));
+        assert!(rebuilt.reset);
+        assert_eq!(rebuilt.added, paths(&[".gitignore", "a.txt", "c.txt"]));
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
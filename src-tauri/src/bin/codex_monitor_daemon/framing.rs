@@ -0,0 +1,638 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+use crate::backend::compression::compress_message;
+
+pub(crate) const DEFAULT_MAX_REQUEST_BYTES: usize = 16 * 1024 * 1024;
+pub(crate) const DEFAULT_MAX_RESPONSE_BYTES: usize = 32 * 1024 * 1024;
+pub(crate) const DEFAULT_MAX_OUTBOUND_QUEUE_BYTES: usize = 64 * 1024 * 1024;
+/// Writes go out in pieces this big so one huge message can't hold a write
+/// past the timeout below.
+const WRITE_CHUNK_BYTES: usize = 64 * 1024;
+/// A client that accepts no data for this long is disconnected.
+const WRITE_CHUNK_TIMEOUT: Duration = Duration::from_secs(30);
+/// A client that starts a line and then sends nothing for this long is
+/// disconnected.
+pub(crate) const LINE_READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// A connection that sends no request for this long before it has
+/// authenticated is closed.
+pub(crate) const AUTH_TIMEOUT: Duration = Duration::from_secs(30);
+/// Room left for the `truncated` marker when shrinking a response.
+const TRUNCATION_SLACK_BYTES: usize = 1024;
+/// Arrays shrunk at most per response before giving up.
+const MAX_TRUNCATED_ARRAYS: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FramingLimits {
+    pub(crate) max_request_bytes: usize,
+    pub(crate) max_response_bytes: usize,
+    pub(crate) max_outbound_queue_bytes: usize,
+}
+
+impl Default for FramingLimits {
+    fn default() -> Self {
+        Self {
+            max_request_bytes: DEFAULT_MAX_REQUEST_BYTES,
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            max_outbound_queue_bytes: DEFAULT_MAX_OUTBOUND_QUEUE_BYTES,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct FramingSnapshot {
+    pub(crate) oversized_requests: u64,
+    pub(crate) truncated_responses: u64,
+    pub(crate) oversized_responses: u64,
+    /// Connections closed because their outbound queue filled up.
+    pub(crate) outbound_overflows: u64,
+}
+
+/// Counts line-protocol limit violations across all connections.
+#[derive(Default)]
+pub(crate) struct FramingCounters {
+    oversized_requests: AtomicU64,
+    truncated_responses: AtomicU64,
+    oversized_responses: AtomicU64,
+    outbound_overflows: AtomicU64,
+}
+
+impl FramingCounters {
+    pub(crate) fn record_oversized_request(&self) {
+        self.oversized_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_truncated_response(&self) {
+        self.truncated_responses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_oversized_response(&self) {
+        self.oversized_responses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn record_outbound_overflow(&self) {
+        self.outbound_overflows.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn snapshot(&self) -> FramingSnapshot {
+        FramingSnapshot {
+            oversized_requests: self.oversized_requests.load(Ordering::Relaxed),
+            truncated_responses: self.truncated_responses.load(Ordering::Relaxed),
+            oversized_responses: self.oversized_responses.load(Ordering::Relaxed),
+            outbound_overflows: self.outbound_overflows.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Error code for a request line that can't be read as a request.
+const PARSE_ERROR: &str = "PARSE_ERROR";
+
+/// Answers a line that isn't valid JSON, or a compressed envelope that can't
+/// be unpacked. The request's id can't be read, so the answer has id 0.
+pub(crate) fn build_parse_error_response(message: &str) -> String {
+    json!({
+        "id": 0,
+        "error": {
+            "code": PARSE_ERROR,
+            "message": message,
+        }
+    })
+    .to_string()
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ReadLine {
+    Line(String),
+    /// The line crossed the limit after this many bytes. The rest of it is
+    /// still unread; `discard_line` skips it.
+    TooLong(usize),
+    Eof,
+}
+
+/// `fill_buf`, failing with `TimedOut` when no data arrives within `limit`.
+async fn fill_buf_within<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    limit: Duration,
+) -> io::Result<&[u8]> {
+    timeout(limit, reader.fill_buf())
+        .await
+        .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))?
+}
+
+/// Reads one newline-terminated line, buffering at most `max_bytes` of it.
+/// A longer line is reported as soon as it crosses the limit, without
+/// waiting for its end. Waiting for a line is unbounded, but once it has
+/// started each read must arrive within `line_timeout`.
+pub(crate) async fn read_line_limited<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_bytes: usize,
+    line_timeout: Duration,
+) -> io::Result<ReadLine> {
+    let mut line = Vec::new();
+    loop {
+        let available = if line.is_empty() {
+            reader.fill_buf().await?
+        } else {
+            fill_buf_within(reader, line_timeout).await?
+        };
+        if available.is_empty() {
+            return Ok(if line.is_empty() {
+                ReadLine::Eof
+            } else {
+                ReadLine::Line(String::from_utf8_lossy(&line).into_owned())
+            });
+        }
+        let newline = available.iter().position(|byte| *byte == b'\n');
+        let chunk = &available[..newline.unwrap_or(available.len())];
+        if line.len() + chunk.len() > max_bytes {
+            // The newline, if any, stays for `discard_line`.
+            let length = line.len() + chunk.len();
+            let consumed = chunk.len();
+            reader.consume(consumed);
+            return Ok(ReadLine::TooLong(length));
+        }
+        line.extend_from_slice(chunk);
+        let consumed = chunk.len() + usize::from(newline.is_some());
+        reader.consume(consumed);
+        if newline.is_some() {
+            return Ok(ReadLine::Line(String::from_utf8_lossy(&line).into_owned()));
+        }
+    }
+}
+
+/// Skips the rest of a line `read_line_limited` reported as too long, up to
+/// and including its newline. Returns false when the input ended first.
+pub(crate) async fn discard_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    line_timeout: Duration,
+) -> io::Result<bool> {
+    loop {
+        let available = fill_buf_within(reader, line_timeout).await?;
+        if available.is_empty() {
+            return Ok(false);
+        }
+        match available.iter().position(|byte| *byte == b'\n') {
+            Some(newline) => {
+                reader.consume(newline + 1);
+                return Ok(true);
+            }
+            None => {
+                let consumed = available.len();
+                reader.consume(consumed);
+            }
+        }
+    }
+}
+
+/// Sending half of a connection's outbound queue. The queue holds at most
+/// `max_bytes` of unwritten messages; past that the connection is marked
+/// overflowed and further messages are refused.
+#[derive(Clone)]
+pub(crate) struct OutboundSender {
+    tx: mpsc::UnboundedSender<String>,
+    queued: Arc<AtomicUsize>,
+    overflowed: Arc<AtomicBool>,
+    compress: Arc<AtomicBool>,
+    max_bytes: usize,
+}
+
+pub(crate) struct OutboundReceiver {
+    rx: mpsc::UnboundedReceiver<String>,
+    queued: Arc<AtomicUsize>,
+    overflowed: Arc<AtomicBool>,
+    /// Set once the client negotiated compression; large messages are then
+    /// compressed as they are written.
+    compress: Arc<AtomicBool>,
+}
+
+pub(crate) fn outbound_queue(max_bytes: usize) -> (OutboundSender, OutboundReceiver) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let queued = Arc::new(AtomicUsize::new(0));
+    let overflowed = Arc::new(AtomicBool::new(false));
+    let compress = Arc::new(AtomicBool::new(false));
+    (
+        OutboundSender {
+            tx,
+            queued: Arc::clone(&queued),
+            overflowed: Arc::clone(&overflowed),
+            compress: Arc::clone(&compress),
+            max_bytes,
+        },
+        OutboundReceiver {
+            rx,
+            queued,
+            overflowed,
+            compress,
+        },
+    )
+}
+
+impl OutboundSender {
+    /// Queues `message`; false when the connection is closed or overflowed.
+    /// A single message is always accepted into an empty queue.
+    pub(crate) fn send(&self, message: String) -> bool {
+        if self.overflowed.load(Ordering::Relaxed) {
+            return false;
+        }
+        let size = message.len() + 1;
+        let queued = self.queued.fetch_add(size, Ordering::Relaxed) + size;
+        if queued > self.max_bytes && queued != size {
+            self.queued.fetch_sub(size, Ordering::Relaxed);
+            self.overflowed.store(true, Ordering::Relaxed);
+            return false;
+        }
+        if self.tx.send(message).is_err() {
+            self.queued.fetch_sub(size, Ordering::Relaxed);
+            return false;
+        }
+        true
+    }
+
+    pub(crate) fn is_overflowed(&self) -> bool {
+        self.overflowed.load(Ordering::Relaxed)
+    }
+
+    /// Compresses large messages written from now on. The queue limit keeps
+    /// counting their uncompressed size.
+    pub(crate) fn enable_compression(&self) {
+        self.compress.store(true, Ordering::Relaxed);
+    }
+}
+
+async fn write_chunked<W: AsyncWrite + Unpin>(writer: &mut W, bytes: &[u8]) -> bool {
+    for chunk in bytes.chunks(WRITE_CHUNK_BYTES) {
+        match timeout(WRITE_CHUNK_TIMEOUT, writer.write_all(chunk)).await {
+            Ok(Ok(())) => {}
+            _ => return false,
+        }
+    }
+    true
+}
+
+/// Writes queued messages as lines until the queue closes, a write fails or
+/// stalls, or the queue overflows; then shuts the writer down.
+pub(crate) async fn write_outbound<W: AsyncWrite + Unpin>(
+    mut writer: W,
+    mut outbound: OutboundReceiver,
+) {
+    while let Some(message) = outbound.rx.recv().await {
+        if outbound.overflowed.load(Ordering::Relaxed) {
+            break;
+        }
+        let size = message.len() + 1;
+        let message = if outbound.compress.load(Ordering::Relaxed) {
+            compress_message(message)
+        } else {
+            message
+        };
+        let written = write_chunked(&mut writer, message.as_bytes()).await
+            && write_chunked(&mut writer, b"\n").await;
+        outbound.queued.fetch_sub(size, Ordering::Relaxed);
+        if !written {
+            break;
+        }
+    }
+    let _ = writer.shutdown().await;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Truncation {
+    /// JSON pointer of the shortened array within `result`.
+    pub(crate) path: String,
+    pub(crate) dropped: usize,
+}
+
+fn json_len(value: &Value) -> usize {
+    serde_json::to_vec(value)
+        .map(|bytes| bytes.len())
+        .unwrap_or(0)
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// The non-empty array with the largest serialized size, as a JSON pointer.
+fn largest_array(value: &Value, path: &str, best: &mut Option<(usize, String)>) {
+    match value {
+        Value::Array(items) => {
+            if !items.is_empty() {
+                let size = json_len(value);
+                if !matches!(best, Some((largest, _)) if *largest >= size) {
+                    *best = Some((size, path.to_string()));
+                }
+            }
+            for (index, item) in items.iter().enumerate() {
+                largest_array(item, &format!("{path}/{index}"), best);
+            }
+        }
+        Value::Object(map) => {
+            for (key, item) in map {
+                largest_array(item, &format!("{path}/{}", escape_pointer(key)), best);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Drops trailing elements from the largest arrays in `value` until it
+/// serializes to at most `max_bytes`. Returns what was cut, or `Err` with
+/// the remaining size when shrinking arrays is not enough.
+pub(crate) fn truncate_to_fit(
+    value: &mut Value,
+    max_bytes: usize,
+) -> Result<Vec<Truncation>, usize> {
+    let mut truncations = Vec::new();
+    let budget = max_bytes.saturating_sub(TRUNCATION_SLACK_BYTES);
+    let mut size = json_len(value);
+    while size > budget && truncations.len() < MAX_TRUNCATED_ARRAYS {
+        let mut best = None;
+        largest_array(value, "", &mut best);
+        let Some((array_size, path)) = best else {
+            break;
+        };
+        let Some(Value::Array(items)) = value.pointer_mut(&path) else {
+            break;
+        };
+        let mut remaining = budget.saturating_sub(size - array_size + 2);
+        let mut keep = 0;
+        for item in items.iter() {
+            let item_size = json_len(item) + usize::from(keep > 0);
+            if item_size > remaining {
+                break;
+            }
+            remaining -= item_size;
+            keep += 1;
+        }
+        let dropped = items.len() - keep;
+        items.truncate(keep);
+        truncations.push(Truncation { path, dropped });
+        size = json_len(value);
+    }
+    if size > budget {
+        Err(size)
+    } else {
+        Ok(truncations)
+    }
+}
+
+/// What `cap_response_size` did to a response.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum CappedResponse {
+    Unchanged(String),
+    Truncated(String),
+    TooLarge(String),
+}
+
+/// Enforces the response size limit on a serialized result response. Fields
+/// next to `result`, such as `_meta` and `warnings`, count against the limit.
+/// The largest arrays in `result` are shortened and listed under `truncated`;
+/// a result that still doesn't fit is replaced by a `response_too_large`
+/// error, and the other fields are kept.
+pub(crate) fn cap_response_size(response: String, max_bytes: usize) -> CappedResponse {
+    if response.len() <= max_bytes {
+        return CappedResponse::Unchanged(response);
+    }
+    let Ok(mut envelope) = serde_json::from_str::<Value>(&response) else {
+        return CappedResponse::Unchanged(response);
+    };
+    let Some(result) = envelope.get_mut("result") else {
+        return CappedResponse::Unchanged(response);
+    };
+    let overhead = response.len().saturating_sub(json_len(result));
+    match truncate_to_fit(result, max_bytes.saturating_sub(overhead)) {
+        Ok(truncations) if truncations.is_empty() => CappedResponse::Unchanged(response),
+        Ok(truncations) => {
+            envelope["truncated"] = json!(truncations);
+            CappedResponse::Truncated(envelope.to_string())
+        }
+        Err(size) => {
+            if let Some(fields) = envelope.as_object_mut() {
+                fields.remove("result");
+                let message = format!(
+                    "response of {} bytes exceeds the {max_bytes}-byte limit",
+                    size + overhead
+                );
+                fields.insert(
+                    "error".to_string(),
+                    json!({ "code": "response_too_large", "message": message, "data": null }),
+                );
+            }
+            CappedResponse::TooLarge(envelope.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_parse_error_response, cap_response_size, discard_line, outbound_queue,
+        read_line_limited, truncate_to_fit, CappedResponse, ReadLine, LINE_READ_TIMEOUT,
+    };
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use tokio::io::{AsyncWriteExt, BufReader};
+
+    fn read_all(input: &[u8], max_bytes: usize) -> Vec<ReadLine> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            // A tiny buffer makes lines span several reads.
+            let mut reader = BufReader::with_capacity(4, input);
+            let mut lines = Vec::new();
+            loop {
+                let line = read_line_limited(&mut reader, max_bytes, LINE_READ_TIMEOUT)
+                    .await
+                    .expect("read");
+                let done = line == ReadLine::Eof;
+                if matches!(line, ReadLine::TooLong(_)) {
+                    discard_line(&mut reader, LINE_READ_TIMEOUT)
+                        .await
+                        .expect("discard");
+                }
+                lines.push(line);
+                if done {
+                    return lines;
+                }
+            }
+        })
+    }
+
+    #[test]
+    fn skips_lines_over_the_limit() {
+        let lines = read_all(b"{\"a\":1}\n0123456789abcdef\nok\npartial", 10);
+        assert_eq!(
+            lines,
+            vec![
+                ReadLine::Line("{\"a\":1}".to_string()),
+                ReadLine::TooLong(12),
+                ReadLine::Line("ok".to_string()),
+                ReadLine::Line("partial".to_string()),
+                ReadLine::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_frame_without_a_newline_is_not_buffered() {
+        let frame = vec![b'x'; 64 * 1024];
+        assert_eq!(
+            read_all(&frame, 1024),
+            vec![ReadLine::TooLong(1028), ReadLine::Eof]
+        );
+    }
+
+    #[test]
+    fn reports_an_oversized_line_before_it_ends() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            let (mut client, server) = tokio::io::duplex(64 * 1024);
+            let mut reader = BufReader::new(server);
+            client.write_all(&[b'x'; 2048]).await.expect("write");
+            let line = tokio::time::timeout(
+                Duration::from_secs(5),
+                read_line_limited(&mut reader, 1024, LINE_READ_TIMEOUT),
+            )
+            .await
+            .expect("answered before the newline")
+            .expect("read");
+            assert!(matches!(line, ReadLine::TooLong(length) if length > 1024));
+
+            client.write_all(b"yyy\nnext\n").await.expect("write");
+            assert!(discard_line(&mut reader, LINE_READ_TIMEOUT)
+                .await
+                .expect("discard"));
+            assert_eq!(
+                read_line_limited(&mut reader, 1024, LINE_READ_TIMEOUT)
+                    .await
+                    .expect("read"),
+                ReadLine::Line("next".to_string())
+            );
+        });
+    }
+
+    #[test]
+    fn stalled_lines_time_out_but_idle_connections_wait() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("runtime");
+        runtime.block_on(async {
+            let line_timeout = Duration::from_millis(50);
+            let (mut client, server) = tokio::io::duplex(1024);
+            let mut reader = BufReader::new(server);
+            let idle = tokio::time::timeout(
+                Duration::from_millis(200),
+                read_line_limited(&mut reader, 1024, line_timeout),
+            )
+            .await;
+            assert!(idle.is_err(), "an idle connection keeps waiting");
+
+            client.write_all(b"{\"id\":").await.expect("write");
+            let stalled = read_line_limited(&mut reader, 1024, line_timeout)
+                .await
+                .expect_err("stalled line");
+            assert_eq!(stalled.kind(), std::io::ErrorKind::TimedOut);
+
+            let (mut client, server) = tokio::io::duplex(1024);
+            let mut reader = BufReader::new(server);
+            client.write_all(b"xxxx").await.expect("write");
+            let stalled = discard_line(&mut reader, line_timeout)
+                .await
+                .expect_err("stalled discard");
+            assert_eq!(stalled.kind(), std::io::ErrorKind::TimedOut);
+        });
+    }
+
+    #[test]
+    fn parse_errors_carry_id_zero() {
+        let error = serde_json::from_str::<Value>("{\"id\": 1,").expect_err("invalid");
+        let response = build_parse_error_response(&format!("invalid JSON: {error}"));
+        let response: Value = serde_json::from_str(&response).expect("response is JSON");
+        assert_eq!(response["id"], json!(0));
+        assert_eq!(response["error"]["code"], json!("PARSE_ERROR"));
+        assert!(response["error"]["message"]
+            .as_str()
+            .is_some_and(|message| message.starts_with("invalid JSON: EOF while parsing")));
+    }
+
+    #[test]
+    fn outbound_queue_refuses_messages_past_the_cap() {
+        let (sender, _receiver) = outbound_queue(10);
+        assert!(sender.send("a very long first message".to_string()));
+        assert!(!sender.is_overflowed());
+        assert!(!sender.send("more".to_string()));
+        assert!(sender.is_overflowed());
+        assert!(!sender.send("x".to_string()));
+    }
+
+    #[test]
+    fn truncates_the_largest_array() {
+        let items: Vec<_> = (0..500).map(|index| json!({ "id": index })).collect();
+        let mut value = json!({ "data": items, "small": [1, 2, 3], "nextCursor": "c" });
+        let truncations = truncate_to_fit(&mut value, 4096).expect("fits");
+        assert_eq!(truncations.len(), 1);
+        assert_eq!(truncations[0].path, "/data");
+        let kept = value["data"].as_array().expect("array").len();
+        assert!(kept > 0 && kept < 500);
+        assert_eq!(truncations[0].dropped, 500 - kept);
+        assert_eq!(value["small"], json!([1, 2, 3]));
+        assert!(serde_json::to_vec(&value).unwrap().len() <= 4096);
+
+        let mut small = json!({ "ok": true });
+        assert_eq!(truncate_to_fit(&mut small, 4096), Ok(Vec::new()));
+        let mut text = json!({ "content": "x".repeat(10_000) });
+        assert!(truncate_to_fit(&mut text, 4096).is_err());
+    }
+    #[test]
+    fn counts_fields_next_to_the_result_against_the_limit() {
+        let items: Vec<_> = (0..200).map(|index| json!({ "id": index })).collect();
+        let meta = json!({ "note": "m".repeat(3_000) });
+        let response = json!({ "id": 1, "result": { "data": items }, "_meta": meta }).to_string();
+        assert!(json!({ "data": items }).to_string().len() < 4096);
+        let CappedResponse::Truncated(capped) = cap_response_size(response, 4096) else {
+            panic!("expected a truncated response");
+        };
+        assert!(capped.len() <= 4096, "{}", capped.len());
+        let capped: Value = serde_json::from_str(&capped).expect("json");
+        assert_eq!(capped["_meta"], meta);
+        assert_eq!(capped["truncated"][0]["path"], json!("/data"));
+
+        let response = json!({
+            "id": 2,
+            "result": { "content": "x".repeat(10_000) },
+            "_meta": { "durationMs": 1 },
+        })
+        .to_string();
+        let CappedResponse::TooLarge(error) = cap_response_size(response, 4096) else {
+            panic!("expected an error");
+        };
+        let error: Value = serde_json::from_str(&error).expect("json");
+        assert_eq!(error["id"], json!(2));
+        assert_eq!(error["error"]["code"], json!("response_too_large"));
+        assert_eq!(error["_meta"]["durationMs"], json!(1));
+        assert!(error.get("result").is_none());
+
+        let small = json!({ "id": 3, "result": [] }).to_string();
+        assert_eq!(
+            cap_response_size(small.clone(), 4096),
+            CappedResponse::Unchanged(small)
+        );
+    }
+}
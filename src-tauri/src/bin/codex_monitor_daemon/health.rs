@@ -0,0 +1,194 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::backend::app_server::{WorkspaceSession, REQUEST_CANCELED};
+
+/// Sessions probed at once by `health_check_all`.
+pub(crate) const MAX_CONCURRENT_PINGS: usize = 8;
+const DEFAULT_PING_TIMEOUT_MS: u64 = 2_000;
+const MIN_PING_TIMEOUT_MS: u64 = 100;
+const MAX_PING_TIMEOUT_MS: u64 = 30_000;
+/// Answers slower than this are reported as `slow` unless `slowMs` is given.
+const DEFAULT_SLOW_MS: u64 = 1_000;
+
+/// Outcome of one probe. `ok` is false when the session answered with an
+/// error; `dead` is also set when it didn't answer in time or its process
+/// is gone.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SessionHealth {
+    pub(crate) ok: bool,
+    pub(crate) latency_ms: u64,
+    pub(crate) error: Option<String>,
+    pub(crate) slow: bool,
+    pub(crate) dead: bool,
+}
+
+pub(crate) fn ping_timeout(timeout_ms: Option<u64>) -> Duration {
+    let ms = timeout_ms
+        .unwrap_or(DEFAULT_PING_TIMEOUT_MS)
+        .clamp(MIN_PING_TIMEOUT_MS, MAX_PING_TIMEOUT_MS);
+    Duration::from_millis(ms)
+}
+
+pub(crate) fn slow_after(slow_ms: Option<u64>) -> Duration {
+    Duration::from_millis(slow_ms.unwrap_or(DEFAULT_SLOW_MS))
+}
+
+/// Errors that mean the app-server process stopped, not that it refused
+/// the probe.
+fn is_gone(error: &str) -> bool {
+    error == REQUEST_CANCELED || error.to_ascii_lowercase().contains("broken pipe")
+}
+
+impl SessionHealth {
+    /// `answer` is `None` when the probe timed out.
+    fn from_probe(
+        answer: Option<Result<(), String>>,
+        latency: Duration,
+        slow_after: Duration,
+    ) -> Self {
+        let latency_ms = u64::try_from(latency.as_millis()).unwrap_or(u64::MAX);
+        match answer {
+            Some(Ok(())) => Self {
+                ok: true,
+                latency_ms,
+                error: None,
+                slow: latency >= slow_after,
+                dead: false,
+            },
+            Some(Err(error)) => Self {
+                ok: false,
+                latency_ms,
+                dead: is_gone(&error),
+                error: Some(error),
+                slow: false,
+            },
+            None => Self {
+                ok: false,
+                latency_ms,
+                error: Some(format!("no answer within {latency_ms}ms")),
+                slow: false,
+                dead: true,
+            },
+        }
+    }
+}
+
+/// Sends the session a `model/list` request and times the answer. Any
+/// answer counts, an error response included: it shows the app-server is
+/// reading its input.
+pub(crate) async fn ping_session(
+    session: &WorkspaceSession,
+    limit: Duration,
+    slow_after: Duration,
+) -> SessionHealth {
+    let started_at = Instant::now();
+    let answer = session
+        .send_request_within("model/list", json!({}), limit)
+        .await
+        .map(|result| result.map(|_| ()));
+    SessionHealth::from_probe(answer, started_at.elapsed(), slow_after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ping_session, ping_timeout, slow_after, SessionHealth};
+    use crate::backend::app_server::WorkspaceSession;
+    use crate::backend::codex_capabilities::SessionCapabilities;
+    use crate::types::{ExperimentalFlags, WorkspaceEntry, WorkspaceKind, WorkspaceSettings};
+    use std::collections::HashMap;
+    use std::process::Stdio;
+    use std::sync::atomic::AtomicU64;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+
+    #[test]
+    fn clamps_ping_timeouts() {
+        assert_eq!(ping_timeout(None), Duration::from_secs(2));
+        assert_eq!(ping_timeout(Some(0)), Duration::from_millis(100));
+        assert_eq!(ping_timeout(Some(600_000)), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn classifies_probe_outcomes() {
+        let limit = slow_after(Some(500));
+        let fast = SessionHealth::from_probe(Some(Ok(())), Duration::from_millis(20), limit);
+        assert!(fast.ok && !fast.slow && !fast.dead);
+        assert_eq!(fast.latency_ms, 20);
+
+        let slow = SessionHealth::from_probe(Some(Ok(())), Duration::from_millis(700), limit);
+        assert!(slow.ok && slow.slow);
+
+        let refused = SessionHealth::from_probe(
+            Some(Err(
+                "`model/list` is unsupported by this codex version".to_string()
+            )),
+            Duration::from_millis(1),
+            limit,
+        );
+        assert!(!refused.ok && !refused.dead);
+
+        for error in ["Broken pipe (os error 32)", "request canceled"] {
+            let gone =
+                SessionHealth::from_probe(Some(Err(error.to_string())), Duration::ZERO, limit);
+            assert!(!gone.ok && gone.dead);
+            assert_eq!(gone.error.as_deref(), Some(error));
+        }
+
+        let dead = SessionHealth::from_probe(None, Duration::from_millis(2_000), limit);
+        assert!(!dead.ok && dead.dead);
+        assert_eq!(dead.error.as_deref(), Some("no answer within 2000ms"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn timed_out_pings_are_forgotten_and_exited_sessions_are_dead() {
+        let mut child = tokio::process::Command::new("sh")
+            .args(["-c", "cat > /dev/null"])
+            .stdin(Stdio::piped())
+            .spawn()
+            .expect("spawn sh");
+        let stdin = child.stdin.take().expect("stdin");
+        let session = WorkspaceSession {
+            entry: WorkspaceEntry {
+                id: "ws".to_string(),
+                name: "ws".to_string(),
+                path: "/tmp".to_string(),
+                codex_bin: None,
+                kind: WorkspaceKind::Main,
+                parent_id: None,
+                worktree: None,
+                settings: WorkspaceSettings::default(),
+                status: None,
+            },
+            child: Mutex::new(child),
+            stdin: Mutex::new(stdin),
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            background_thread_callbacks: Mutex::new(HashMap::new()),
+            experimental_flags: ExperimentalFlags {
+                collab: false,
+                collaboration_modes: false,
+                steer: false,
+                unified_exec: false,
+            },
+            capabilities: std::sync::Mutex::new(SessionCapabilities::new(None)),
+        };
+
+        // `cat` reads the probe but never answers.
+        let limit = Duration::from_millis(100);
+        let silent = ping_session(&session, limit, slow_after(None)).await;
+        assert!(!silent.ok && silent.dead);
+        assert!(session.pending.lock().await.is_empty());
+
+        let mut child = session.child.lock().await;
+        child.kill().await.expect("kill");
+        drop(child);
+        let exited = ping_session(&session, limit, slow_after(None)).await;
+        assert!(!exited.ok && exited.dead, "{exited:?}");
+        assert!(session.pending.lock().await.is_empty());
+    }
+}
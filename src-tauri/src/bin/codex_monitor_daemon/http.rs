@@ -0,0 +1,499 @@
+use std::collections::HashMap;
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+use tokio::io::AsyncBufRead;
+
+use super::framing::{read_line_limited, ReadLine};
+
+/// Longest request head read before the connection is refused.
+pub(crate) const MAX_HEAD_BYTES: usize = 16 * 1024;
+/// Time a client gets to send its request head.
+pub(crate) const HEAD_TIMEOUT: Duration = Duration::from_secs(10);
+/// Comment sent on idle event streams so proxies keep them open.
+pub(crate) const SSE_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// The parts of a `GET` request the HTTP endpoint looks at.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct HttpRequest {
+    pub(crate) method: String,
+    pub(crate) path: String,
+    pub(crate) query: HashMap<String, String>,
+    /// From `Authorization: Bearer <token>`, or the `token` query parameter
+    /// for clients such as `EventSource` that can't set headers.
+    pub(crate) token: Option<String>,
+    /// By lowercased name; a repeated header keeps its last value.
+    pub(crate) headers: HashMap<String, String>,
+}
+
+/// What a request asks for.
+#[derive(Debug, PartialEq)]
+pub(crate) enum HttpRoute {
+    Events,
+    /// Prometheus metrics.
+    Metrics,
+    /// A read-only RPC method and its params.
+    Rpc(&'static str, Value),
+    /// A file inside a workspace, streamed as it is.
+    File {
+        workspace_id: String,
+        path: String,
+    },
+}
+
+/// The part of a file a `Range` header asks for.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ByteRange {
+    Full,
+    /// Inclusive first and last byte.
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// Reads a request head up to the blank line that ends it; `None` when the
+/// connection closes first or the head is longer than `MAX_HEAD_BYTES`.
+pub(crate) async fn read_request_head<R: AsyncBufRead + Unpin>(reader: &mut R) -> Option<String> {
+    let mut head = String::new();
+    loop {
+        let remaining = MAX_HEAD_BYTES.checked_sub(head.len())?;
+        let ReadLine::Line(line) = read_line_limited(reader, remaining, HEAD_TIMEOUT)
+            .await
+            .ok()?
+        else {
+            return None;
+        };
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            return (!head.is_empty()).then_some(head);
+        }
+        head.push_str(line);
+        head.push('\n');
+    }
+}
+
+/// Parses a request head: the request line and headers, without the blank
+/// line that ends them.
+pub(crate) fn parse_request_head(head: &str) -> Result<HttpRequest, String> {
+    let mut lines = head.lines();
+    let request_line = lines.next().unwrap_or_default();
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err("malformed request line".to_string());
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(format!("unsupported protocol: {version}"));
+    }
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let query: HashMap<String, String> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            Some((name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        })
+        .collect();
+    let bearer = headers.get("authorization").and_then(|value| {
+        let scheme = value.get(..7)?;
+        scheme
+            .eq_ignore_ascii_case("bearer ")
+            .then(|| value[7..].trim().to_string())
+    });
+    let token = bearer.or_else(|| query.get("token").cloned());
+    Ok(HttpRequest {
+        method: method.to_string(),
+        path: percent_decode(path),
+        query,
+        token,
+        headers,
+    })
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                index += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Maps a path to the event stream, the metrics, a workspace file or the
+/// read-only method behind it.
+pub(crate) fn route(request: &HttpRequest) -> Option<HttpRoute> {
+    let segments: Vec<&str> = request
+        .path
+        .trim_matches('/')
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    let route = match segments.as_slice() {
+        ["events"] => HttpRoute::Events,
+        ["metrics"] => HttpRoute::Metrics,
+        ["status"] => HttpRoute::Rpc("daemon_status", json!({})),
+        ["workspaces"] => HttpRoute::Rpc("list_workspaces", json!({})),
+        ["workspaces", id] => HttpRoute::Rpc("workspace_status", json!({ "id": id })),
+        ["workspaces", id, "threads"] => {
+            let mut params = json!({ "workspaceId": id });
+            if let Some(cursor) = request.query.get("cursor") {
+                params["cursor"] = json!(cursor);
+            }
+            if let Some(limit) = request
+                .query
+                .get("limit")
+                .and_then(|limit| limit.parse::<u32>().ok())
+            {
+                params["limit"] = json!(limit);
+            }
+            HttpRoute::Rpc("list_threads", params)
+        }
+        ["workspace", id, "file", path @ ..] if !path.is_empty() => HttpRoute::File {
+            workspace_id: id.to_string(),
+            path: path.join("/"),
+        },
+        _ => return None,
+    };
+    Some(route)
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        206 => "Partial Content",
+        304 => "Not Modified",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        416 => "Range Not Satisfiable",
+        _ => "Internal Server Error",
+    }
+}
+
+/// Status for a failed method: policy denials are 403, unknown workspaces
+/// 404, anything else 400.
+pub(crate) fn rpc_error_status(message: &str) -> u16 {
+    if message.starts_with("forbidden") {
+        403
+    } else if message == "workspace not found" {
+        404
+    } else {
+        400
+    }
+}
+
+/// A complete response with a JSON body; the connection is closed after it.
+pub(crate) fn json_response(status: u16, body: &Value) -> String {
+    let body = body.to_string();
+    let mut response = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n",
+        reason(status),
+        body.len()
+    );
+    if status == 401 {
+        response.push_str("WWW-Authenticate: Bearer\r\n");
+    }
+    response.push_str("\r\n");
+    response.push_str(&body);
+    response
+}
+
+/// Metrics in the Prometheus text exposition format.
+pub(crate) fn metrics_response(body: &str) -> String {
+    format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4; charset=utf-8\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+pub(crate) fn error_response(status: u16, message: &str) -> String {
+    json_response(status, &json!({ "error": { "message": message } }))
+}
+
+/// Reads a single `bytes=` range of a `len`-byte file. Anything else,
+/// including several ranges, gets the whole file.
+pub(crate) fn byte_range(header: Option<&str>, len: u64) -> ByteRange {
+    let Some(spec) = header.and_then(|header| header.trim().strip_prefix("bytes=")) else {
+        return ByteRange::Full;
+    };
+    let Some((first, last)) = spec.split_once('-').filter(|_| !spec.contains(',')) else {
+        return ByteRange::Full;
+    };
+    let (first, last) = (first.trim(), last.trim());
+    let (start, end) = if first.is_empty() {
+        // `bytes=-<n>`: the last n bytes.
+        match last.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(suffix) => (len.saturating_sub(suffix), len.saturating_sub(1)),
+            Err(_) => return ByteRange::Full,
+        }
+    } else {
+        let Ok(start) = first.parse::<u64>() else {
+            return ByteRange::Full;
+        };
+        let end = match last {
+            "" => u64::MAX,
+            last => match last.parse::<u64>() {
+                Ok(end) if end >= start => end,
+                _ => return ByteRange::Full,
+            },
+        };
+        (start, end.min(len.saturating_sub(1)))
+    };
+    if start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(start, end)
+}
+
+/// Content type guessed from the file extension. Types a browser would run
+/// scripts from (HTML, SVG, JavaScript) are served as plain text.
+pub(crate) fn content_type(path: &str) -> &'static str {
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "pdf" => "application/pdf",
+        "json" => "application/json",
+        "css" => "text/css; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "md" | "txt" | "log" | "rs" | "ts" | "tsx" | "py" | "toml" | "yaml" | "yml" | "sh"
+        | "html" | "htm" | "svg" | "js" | "mjs" => "text/plain; charset=utf-8",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "wasm" => "application/wasm",
+        "zip" => "application/zip",
+        _ => "application/octet-stream",
+    }
+}
+
+/// A validator that changes with the file's size or modification time.
+pub(crate) fn file_etag(metadata: &std::fs::Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |modified| modified.as_nanos());
+    format!("\"{:x}-{modified:x}\"", metadata.len())
+}
+
+/// Whether an `If-None-Match` header names `etag`.
+pub(crate) fn etag_matches(header: Option<&str>, etag: &str) -> bool {
+    header.is_some_and(|header| {
+        header
+            .split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == etag)
+    })
+}
+
+/// Headers of a file response whose body, `length` bytes, follows them.
+pub(crate) fn file_head(
+    status: u16,
+    content_type: &str,
+    etag: &str,
+    length: u64,
+    range: Option<(u64, u64, u64)>,
+) -> String {
+    let mut head = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: {content_type}\r\nContent-Length: {length}\r\nETag: {etag}\r\nAccept-Ranges: bytes\r\nCache-Control: private, no-cache\r\nX-Content-Type-Options: nosniff\r\nContent-Security-Policy: sandbox\r\nConnection: close\r\n",
+        reason(status)
+    );
+    if let Some((start, end, total)) = range {
+        head.push_str(&format!("Content-Range: bytes {start}-{end}/{total}\r\n"));
+    }
+    head.push_str("\r\n");
+    head
+}
+
+/// A bodiless answer to a conditional or unsatisfiable range request.
+pub(crate) fn empty_response(status: u16, etag: &str, total: u64) -> String {
+    let mut head = format!(
+        "HTTP/1.1 {status} {}\r\nETag: {etag}\r\nContent-Length: 0\r\nConnection: close\r\n",
+        reason(status)
+    );
+    if status == 416 {
+        head.push_str(&format!("Content-Range: bytes */{total}\r\n"));
+    }
+    head.push_str("\r\n");
+    head
+}
+
+/// Headers that open an event stream.
+pub(crate) fn sse_head() -> &'static str {
+    "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\nConnection: keep-alive\r\n\r\n"
+}
+
+/// One event; `notification` is a single line of JSON, so it fits in one
+/// `data:` line.
+pub(crate) fn sse_event(seq: Option<u64>, notification: &str) -> String {
+    match seq {
+        Some(seq) => format!("id: {seq}\ndata: {notification}\n\n"),
+        None => format!("data: {notification}\n\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        byte_range, content_type, empty_response, etag_matches, file_head, json_response,
+        metrics_response, parse_request_head, read_request_head, route, rpc_error_status,
+        sse_event, ByteRange, HttpRoute,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn parses_request_heads_and_tokens() {
+        let request = parse_request_head(
+            "GET /workspaces/ws%201/threads?limit=5&cursor=a%2Bb HTTP/1.1\r\nHost: x\r\nauthorization: bearer secret\r\n",
+        )
+        .expect("request");
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/workspaces/ws 1/threads");
+        assert_eq!(request.query.get("cursor").map(String::as_str), Some("a+b"));
+        assert_eq!(request.token.as_deref(), Some("secret"));
+        assert_eq!(request.headers.get("host").map(String::as_str), Some("x"));
+
+        let request = parse_request_head("GET /events?token=t%26k HTTP/1.1\r\n").expect("request");
+        assert_eq!(request.token.as_deref(), Some("t&k"));
+        assert!(parse_request_head("GET /events\r\n").is_err());
+        assert!(parse_request_head("GET /events SPDY/3\r\n").is_err());
+    }
+
+    #[test]
+    fn routes_paths_to_read_only_methods() {
+        let routed = |head: &str| route(&parse_request_head(head).expect("request"));
+        assert_eq!(routed("GET /events HTTP/1.1"), Some(HttpRoute::Events));
+        assert_eq!(routed("GET /metrics HTTP/1.1"), Some(HttpRoute::Metrics));
+        assert_eq!(
+            routed("GET /workspaces/ HTTP/1.1"),
+            Some(HttpRoute::Rpc("list_workspaces", json!({})))
+        );
+        assert_eq!(
+            routed("GET /workspaces/ws HTTP/1.1"),
+            Some(HttpRoute::Rpc("workspace_status", json!({ "id": "ws" })))
+        );
+        assert_eq!(
+            routed("GET /workspaces/ws/threads?limit=10&cursor=c HTTP/1.1"),
+            Some(HttpRoute::Rpc(
+                "list_threads",
+                json!({ "workspaceId": "ws", "limit": 10, "cursor": "c" })
+            ))
+        );
+        assert_eq!(routed("GET /workspaces/ws/remove HTTP/1.1"), None);
+        assert_eq!(
+            routed("GET /workspace/ws/file/docs/shot%201.png HTTP/1.1"),
+            Some(HttpRoute::File {
+                workspace_id: "ws".to_string(),
+                path: "docs/shot 1.png".to_string(),
+            })
+        );
+        assert_eq!(routed("GET /workspace/ws/file/ HTTP/1.1"), None);
+    }
+
+    #[test]
+    fn serves_ranges_types_and_validators() {
+        assert_eq!(byte_range(None, 100), ByteRange::Full);
+        assert_eq!(byte_range(Some("bytes=0-9"), 100), ByteRange::Partial(0, 9));
+        assert_eq!(
+            byte_range(Some("bytes=90-"), 100),
+            ByteRange::Partial(90, 99)
+        );
+        assert_eq!(
+            byte_range(Some("bytes=-10"), 100),
+            ByteRange::Partial(90, 99)
+        );
+        assert_eq!(
+            byte_range(Some("bytes=50-500"), 100),
+            ByteRange::Partial(50, 99)
+        );
+        assert_eq!(
+            byte_range(Some("bytes=100-"), 100),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(byte_range(Some("bytes=0-1,5-6"), 100), ByteRange::Full);
+        assert_eq!(byte_range(Some("bytes=9-1"), 100), ByteRange::Full);
+        assert_eq!(byte_range(Some("items=0-1"), 100), ByteRange::Full);
+
+        assert_eq!(content_type("shots/A.PNG"), "image/png");
+        assert_eq!(content_type("build.log"), "text/plain; charset=utf-8");
+        assert_eq!(content_type("Makefile"), "application/octet-stream");
+        assert_eq!(content_type("report.html"), "text/plain; charset=utf-8");
+        assert_eq!(content_type("logo.svg"), "text/plain; charset=utf-8");
+        assert_eq!(content_type("app.js"), "text/plain; charset=utf-8");
+
+        assert!(etag_matches(Some("\"a\", W/\"b\""), "\"b\""));
+        assert!(!etag_matches(Some("\"a\""), "\"b\""));
+        assert!(!etag_matches(None, "\"b\""));
+
+        let head = file_head(206, "image/png", "\"e\"", 10, Some((0, 9, 100)));
+        assert!(head.starts_with("HTTP/1.1 206 Partial Content\r\n"));
+        assert!(head.contains("Content-Length: 10\r\n"));
+        assert!(head.contains("Content-Security-Policy: sandbox\r\n"));
+        assert!(head.ends_with("Content-Range: bytes 0-9/100\r\n\r\n"));
+        let unsatisfiable = empty_response(416, "\"e\"", 100);
+        assert!(unsatisfiable.contains("Content-Range: bytes */100\r\n"));
+    }
+
+    #[test]
+    fn formats_responses_and_events() {
+        let response = json_response(401, &json!({ "ok": false }));
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        assert!(response.contains("Content-Length: 12\r\n"));
+        assert!(response.contains("WWW-Authenticate: Bearer\r\n"));
+        assert!(response.ends_with("\r\n\r\n{\"ok\":false}"));
+        assert_eq!(sse_event(Some(3), "{}"), "id: 3\ndata: {}\n\n");
+        assert_eq!(sse_event(None, "{}"), "data: {}\n\n");
+        let metrics = metrics_response("up 1\n");
+        assert!(metrics.contains("Content-Type: text/plain; version=0.0.4; charset=utf-8\r\n"));
+        assert!(metrics.ends_with(
+            "Content-Length: 5\r\nCache-Control: no-store\r\nConnection: close\r\n\r\nup 1\n"
+        ));
+        assert_eq!(rpc_error_status("forbidden: `x` is denied by policy"), 403);
+        assert_eq!(rpc_error_status("workspace not found"), 404);
+        assert_eq!(rpc_error_status("missing `id`"), 400);
+    }
+
+    #[tokio::test]
+    async fn reads_heads_up_to_the_blank_line() {
+        let mut reader: &[u8] = b"GET /status HTTP/1.1\r\nHost: x\r\n\r\nbody";
+        assert_eq!(
+            read_request_head(&mut reader).await.as_deref(),
+            Some("GET /status HTTP/1.1\nHost: x\n")
+        );
+        let mut truncated: &[u8] = b"GET /status HTTP/1.1\r\n";
+        assert_eq!(read_request_head(&mut truncated).await, None);
+    }
+}
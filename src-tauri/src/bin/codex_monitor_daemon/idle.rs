@@ -0,0 +1,221 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+/// How often the daemon looks for idle sessions.
+pub(crate) const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Default)]
+struct SessionIdle {
+    connected_at: Option<Instant>,
+    last_active: Option<Instant>,
+    /// Ids of app-server requests (approvals and the like) not yet answered.
+    open_requests: HashSet<String>,
+}
+
+#[derive(Default)]
+struct IdleState {
+    sessions: HashMap<String, SessionIdle>,
+    /// Workspaces whose session the daemon stopped for idleness.
+    reaped: HashSet<String>,
+}
+
+/// What `list_sessions` reports about a session's activity.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SessionActivity {
+    pub(crate) connected_at: Option<Instant>,
+    pub(crate) last_active: Option<Instant>,
+    pub(crate) open_requests: usize,
+}
+
+/// Last activity of each session, from requests that used it and events it
+/// sent, plus the server requests still waiting for a client's answer.
+#[derive(Default)]
+pub(crate) struct IdleTracker {
+    state: Mutex<IdleState>,
+    /// Held while a reaped session is brought back, so concurrent requests
+    /// don't spawn it twice, and by `purge_data` so none is brought back
+    /// while it tears everything down.
+    reconnects: tokio::sync::Mutex<()>,
+}
+
+/// The idle limit for a workspace: its own `idleDisconnectMinutes`, else the
+/// app-wide one. `0` or no value means never.
+pub(crate) fn idle_limit(app: Option<u32>, workspace: Option<u32>) -> Option<Duration> {
+    workspace
+        .or(app)
+        .filter(|minutes| *minutes > 0)
+        .map(|minutes| Duration::from_secs(u64::from(minutes) * 60))
+}
+
+fn request_key(id: &Value) -> String {
+    id.to_string()
+}
+
+impl IdleTracker {
+    fn lock(&self) -> std::sync::MutexGuard<'_, IdleState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    pub(crate) fn touch(&self, workspace_id: &str) {
+        let mut state = self.lock();
+        state
+            .sessions
+            .entry(workspace_id.to_string())
+            .or_default()
+            .last_active = Some(Instant::now());
+    }
+
+    /// Starts the clock for a newly connected session.
+    pub(crate) fn connected(&self, workspace_id: &str) {
+        let mut state = self.lock();
+        state.reaped.remove(workspace_id);
+        state.sessions.insert(
+            workspace_id.to_string(),
+            SessionIdle {
+                connected_at: Some(Instant::now()),
+                last_active: Some(Instant::now()),
+                open_requests: HashSet::new(),
+            },
+        );
+    }
+
+    /// Counts any app-server message as activity and remembers requests
+    /// (messages with both `id` and `method`) until they are answered.
+    pub(crate) fn record_event(&self, workspace_id: &str, message: &Value) {
+        let request_id = message
+            .get("id")
+            .filter(|_| message.get("method").is_some())
+            .map(request_key);
+        let mut state = self.lock();
+        let session = state.sessions.entry(workspace_id.to_string()).or_default();
+        session.last_active = Some(Instant::now());
+        if let Some(id) = request_id {
+            session.open_requests.insert(id);
+        }
+    }
+
+    pub(crate) fn record_response(&self, workspace_id: &str, request_id: &Value) {
+        let mut state = self.lock();
+        let session = state.sessions.entry(workspace_id.to_string()).or_default();
+        session.last_active = Some(Instant::now());
+        session.open_requests.remove(&request_key(request_id));
+    }
+
+    /// True once the session has been quiet for `limit` with no request
+    /// waiting for an answer. Sessions never seen count from now.
+    pub(crate) fn is_idle(&self, workspace_id: &str, limit: Duration) -> bool {
+        let mut state = self.lock();
+        let session = state.sessions.entry(workspace_id.to_string()).or_default();
+        let last_active = *session.last_active.get_or_insert_with(Instant::now);
+        session.open_requests.is_empty() && last_active.elapsed() >= limit
+    }
+
+    pub(crate) fn activity(&self, workspace_id: &str) -> SessionActivity {
+        let state = self.lock();
+        state
+            .sessions
+            .get(workspace_id)
+            .map(|session| SessionActivity {
+                connected_at: session.connected_at,
+                last_active: session.last_active,
+                open_requests: session.open_requests.len(),
+            })
+            .unwrap_or_default()
+    }
+
+    /// Marks the workspace's session as stopped for idleness, so a later
+    /// request may bring it back.
+    pub(crate) fn reaped(&self, workspace_id: &str) {
+        let mut state = self.lock();
+        state.sessions.remove(workspace_id);
+        state.reaped.insert(workspace_id.to_string());
+    }
+
+    pub(crate) fn was_reaped(&self, workspace_id: &str) -> bool {
+        self.lock().reaped.contains(workspace_id)
+    }
+
+    /// Waits for reconnects and purges in progress. Check `was_reaped` again
+    /// once it returns.
+    pub(crate) async fn lock_reconnects(&self) -> tokio::sync::MutexGuard<'_, ()> {
+        self.reconnects.lock().await
+    }
+
+    /// Drops what is known about a session that was stopped on purpose.
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        let mut state = self.lock();
+        state.sessions.remove(workspace_id);
+        state.reaped.remove(workspace_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{idle_limit, IdleTracker};
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn workspace_limit_overrides_the_app_limit() {
+        assert_eq!(idle_limit(None, None), None);
+        assert_eq!(idle_limit(Some(30), None), Some(Duration::from_secs(1800)));
+        assert_eq!(
+            idle_limit(Some(30), Some(5)),
+            Some(Duration::from_secs(300))
+        );
+        assert_eq!(idle_limit(Some(30), Some(0)), None);
+        assert_eq!(idle_limit(Some(0), None), None);
+    }
+
+    #[test]
+    fn open_requests_keep_a_session_alive() {
+        let idle = IdleTracker::default();
+        idle.connected("ws");
+        assert!(idle.is_idle("ws", Duration::ZERO));
+        assert!(!idle.is_idle("ws", Duration::from_secs(60)));
+
+        idle.record_event(
+            "ws",
+            &json!({ "id": 7, "method": "item/commandExecution/requestApproval", "params": {} }),
+        );
+        idle.record_event("ws", &json!({ "method": "turn/completed", "params": {} }));
+        assert!(!idle.is_idle("ws", Duration::ZERO));
+        assert_eq!(idle.activity("ws").open_requests, 1);
+        idle.record_response("ws", &json!(7));
+        assert!(idle.is_idle("ws", Duration::ZERO));
+        let activity = idle.activity("ws");
+        assert_eq!(activity.open_requests, 0);
+        assert!(activity.connected_at <= activity.last_active);
+
+        idle.reaped("ws");
+        assert!(idle.was_reaped("ws"));
+        idle.connected("ws");
+        assert!(!idle.was_reaped("ws"));
+        idle.reaped("ws");
+        idle.forget("ws");
+        assert!(!idle.was_reaped("ws"));
+    }
+
+    #[tokio::test]
+    async fn reconnects_wait_for_a_purge() {
+        let idle = Arc::new(IdleTracker::default());
+        idle.reaped("ws");
+        let purge = idle.lock_reconnects().await;
+        let reconnect = tokio::spawn({
+            let idle = Arc::clone(&idle);
+            async move {
+                let _guard = idle.lock_reconnects().await;
+                idle.was_reaped("ws")
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!reconnect.is_finished());
+        idle.forget("ws");
+        drop(purge);
+        assert!(!reconnect.await.expect("reconnect task"));
+    }
+}
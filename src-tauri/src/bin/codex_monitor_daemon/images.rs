@@ -0,0 +1,223 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// Largest local image `send_user_message` accepts unless `--max-image-bytes`
+/// says otherwise.
+pub(crate) const DEFAULT_MAX_IMAGE_BYTES: u64 = 20 * 1024 * 1024;
+const URL_SCHEMES: [&str; 3] = ["http", "https", "data"];
+
+/// An `images` entry `send_user_message` refused, by its index in the list.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RejectedImage {
+    pub(crate) index: usize,
+    pub(crate) image: String,
+    pub(crate) error: String,
+}
+
+/// The scheme of `value` if it looks like a URL. Single letters are
+/// Windows drive letters, not schemes.
+fn url_scheme(value: &str) -> Option<&str> {
+    let (scheme, _) = value.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = scheme.len() > 1
+        && chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then_some(scheme)
+}
+
+fn check_url(url: &str, scheme: &str) -> Result<(), String> {
+    let scheme = scheme.to_ascii_lowercase();
+    if !URL_SCHEMES.contains(&scheme.as_str()) {
+        return Err(format!("unsupported URL scheme: {scheme}"));
+    }
+    if url.chars().any(char::is_whitespace) {
+        return Err("URL contains whitespace".to_string());
+    }
+    let rest = &url[scheme.len() + 1..];
+    if scheme == "data" {
+        let (meta, payload) = rest.split_once(',').ok_or("data URL without a payload")?;
+        if !meta.to_ascii_lowercase().starts_with("image/") || payload.is_empty() {
+            return Err("data URL is not an image".to_string());
+        }
+        return Ok(());
+    }
+    let host = rest
+        .strip_prefix("//")
+        .and_then(|rest| rest.split(['/', '?', '#']).next())
+        .filter(|host| !host.is_empty())
+        .ok_or("URL without a host")?;
+    if host.starts_with(':') || host.ends_with('@') {
+        return Err("URL without a host".to_string());
+    }
+    Ok(())
+}
+
+/// Whether `header` starts like a PNG, JPEG, GIF, WebP, BMP or TIFF file.
+fn is_image_header(header: &[u8]) -> bool {
+    header.starts_with(b"\x89PNG\r\n\x1a\n")
+        || header.starts_with(b"\xff\xd8\xff")
+        || header.starts_with(b"GIF87a")
+        || header.starts_with(b"GIF89a")
+        || (header.starts_with(b"RIFF") && header.get(8..12) == Some(b"WEBP"))
+        || header.starts_with(b"BM")
+        || header.starts_with(b"II*\0")
+        || header.starts_with(b"MM\0*")
+}
+
+/// Resolves a local image against the workspace and checks it. Relative
+/// paths may not leave the workspace.
+fn check_local(path: &str, workspace: &Path, max_bytes: u64) -> Result<PathBuf, String> {
+    let relative = Path::new(path).is_relative();
+    let resolved = workspace.join(path);
+    let canonical = resolved
+        .canonicalize()
+        .map_err(|_| "file not found".to_string())?;
+    if relative {
+        let root = workspace
+            .canonicalize()
+            .map_err(|err| format!("workspace unavailable: {err}"))?;
+        if !canonical.starts_with(&root) {
+            return Err("path escapes the workspace".to_string());
+        }
+    }
+    let metadata = std::fs::metadata(&canonical).map_err(|err| err.to_string())?;
+    if !metadata.is_file() {
+        return Err("not a regular file".to_string());
+    }
+    if metadata.len() > max_bytes {
+        return Err(format!(
+            "{} bytes exceeds the {max_bytes}-byte image limit",
+            metadata.len()
+        ));
+    }
+    let mut header = [0u8; 12];
+    let read = File::open(&canonical)
+        .and_then(|mut file| file.read(&mut header))
+        .map_err(|err| err.to_string())?;
+    if !is_image_header(&header[..read]) {
+        return Err("not a PNG, JPEG, GIF, WebP, BMP or TIFF image".to_string());
+    }
+    Ok(canonical)
+}
+
+/// Checks `send_user_message` images: URLs must be well-formed `http(s)` or
+/// `data:image/` URLs, and local files must be images of at most `max_bytes`.
+/// Returns the accepted entries, local paths made absolute and URL schemes
+/// lowercased, and the rejected ones. Empty entries are dropped.
+pub(crate) fn validate_images(
+    images: &[String],
+    workspace: &Path,
+    max_bytes: u64,
+) -> (Vec<String>, Vec<RejectedImage>) {
+    let mut accepted = Vec::new();
+    let mut rejected = Vec::new();
+    for (index, image) in images.iter().enumerate() {
+        let trimmed = image.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let checked = match url_scheme(trimmed) {
+            Some(scheme) => check_url(trimmed, scheme).map(|()| {
+                let rest = &trimmed[scheme.len()..];
+                format!("{}{rest}", scheme.to_ascii_lowercase())
+            }),
+            None => check_local(trimmed, workspace, max_bytes)
+                .map(|path| path.to_string_lossy().into_owned()),
+        };
+        match checked {
+            Ok(image) => accepted.push(image),
+            Err(error) => rejected.push(RejectedImage {
+                index,
+                image: trimmed.to_string(),
+                error,
+            }),
+        }
+    }
+    (accepted, rejected)
+}
+
+/// Adds `rejectedImages` to a `send_user_message` response when any were.
+pub(crate) fn with_rejected_images(mut response: Value, rejected: &[RejectedImage]) -> Value {
+    if let (false, Some(object)) = (rejected.is_empty(), response.as_object_mut()) {
+        object.insert("rejectedImages".to_string(), json!(rejected));
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::validate_images;
+    use uuid::Uuid;
+
+    #[test]
+    fn accepts_images_and_explains_rejections() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-images-{}", Uuid::new_v4()));
+        let workspace = root.join("repo");
+        std::fs::create_dir_all(workspace.join("shots")).expect("create workspace");
+        std::fs::write(workspace.join("shots/a.png"), b"\x89PNG\r\n\x1a\n0000").expect("png");
+        std::fs::write(workspace.join("notes.png"), b"just text").expect("fake png");
+        std::fs::write(workspace.join("big.gif"), vec![b'G'; 64]).expect("big");
+        std::fs::write(root.join("outside.png"), b"\x89PNG\r\n\x1a\n").expect("outside");
+
+        let images: Vec<String> = [
+            "shots/a.png",
+            "  ",
+            "HTTPS://example.com/cat.png",
+            "data:image/png;base64,iVBORw0KGgo=",
+            "missing.png",
+            "shots",
+            "notes.png",
+            "big.gif",
+            "../outside.png",
+            "ftp://example.com/cat.png",
+            "https:///cat.png",
+            "data:text/plain,hi",
+        ]
+        .iter()
+        .map(|image| image.to_string())
+        .collect();
+        let (accepted, rejected) = validate_images(&images, &workspace, 32);
+
+        let png = workspace
+            .join("shots/a.png")
+            .canonicalize()
+            .expect("canonical");
+        assert_eq!(
+            accepted,
+            [
+                png.to_string_lossy().into_owned(),
+                "https://example.com/cat.png".to_string(),
+                "data:image/png;base64,iVBORw0KGgo=".to_string(),
+            ]
+        );
+        let errors: Vec<(usize, &str)> = rejected
+            .iter()
+            .map(|rejected| (rejected.index, rejected.error.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (4, "file not found"),
+                (5, "not a regular file"),
+                (6, "not a PNG, JPEG, GIF, WebP, BMP or TIFF image"),
+                (7, "64 bytes exceeds the 32-byte image limit"),
+                (8, "path escapes the workspace"),
+                (9, "unsupported URL scheme: ftp"),
+                (10, "URL without a host"),
+                (11, "data URL is not an image"),
+            ]
+        );
+
+        let outside = root.join("outside.png").to_string_lossy().into_owned();
+        let (accepted, rejected) = validate_images(&[outside], &workspace, 32);
+        assert_eq!(accepted.len(), 1);
+        assert!(rejected.is_empty());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
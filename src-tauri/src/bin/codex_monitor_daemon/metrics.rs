@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Upper bounds (seconds) of the duration histogram buckets.
+const DURATION_BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+/// Distinct method labels kept; later unknown names are counted as `other`
+/// so clients sending junk methods can't grow the output without bound.
+const MAX_METHODS: usize = 256;
+
+#[derive(Debug, Clone, Default)]
+struct Histogram {
+    buckets: [u64; DURATION_BUCKETS.len()],
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    fn observe(&mut self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter_mut().zip(DURATION_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let separator = if labels.is_empty() { "" } else { "," };
+        for (count, bound) in self.buckets.iter().zip(DURATION_BUCKETS) {
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{labels}{separator}le=\"{bound}\"}} {count}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{{labels}{separator}le=\"+Inf\"}} {}",
+            self.count
+        );
+        let braces = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{labels}}}")
+        };
+        let _ = writeln!(out, "{name}_sum{braces} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{braces} {}", self.count);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct MethodStats {
+    calls: u64,
+    errors: u64,
+    duration: Histogram,
+}
+
+#[derive(Default)]
+struct Registry {
+    methods: BTreeMap<String, MethodStats>,
+    git: Histogram,
+}
+
+/// Counters behind `GET /metrics`, rendered in the Prometheus text format.
+#[derive(Default)]
+pub(crate) struct Metrics {
+    registry: Mutex<Registry>,
+}
+
+impl Metrics {
+    /// Counts one request; `ok` is false for errors and for requests
+    /// refused by auth or rate limiting.
+    pub(crate) fn record_rpc(&self, method: &str, ok: bool, duration: Duration) {
+        let mut registry = self.registry.lock().unwrap_or_else(|err| err.into_inner());
+        let key = if registry.methods.contains_key(method) || registry.methods.len() < MAX_METHODS {
+            method
+        } else {
+            "other"
+        };
+        let stats = registry.methods.entry(key.to_string()).or_default();
+        stats.calls += 1;
+        if !ok {
+            stats.errors += 1;
+        }
+        stats.duration.observe(duration);
+    }
+
+    pub(crate) fn record_git(&self, duration: Duration) {
+        let mut registry = self.registry.lock().unwrap_or_else(|err| err.into_inner());
+        registry.git.observe(duration);
+    }
+
+    pub(crate) fn render(&self, connections: usize, sessions: usize) -> String {
+        let registry = self.registry.lock().unwrap_or_else(|err| err.into_inner());
+        let mut out = String::new();
+        out.push_str("# HELP codex_monitor_rpc_calls_total RPC requests by method.\n");
+        out.push_str("# TYPE codex_monitor_rpc_calls_total counter\n");
+        for (method, stats) in &registry.methods {
+            let _ = writeln!(
+                out,
+                "codex_monitor_rpc_calls_total{{method=\"{}\"}} {}",
+                escape_label(method),
+                stats.calls
+            );
+        }
+        out.push_str("# HELP codex_monitor_rpc_errors_total RPC requests that failed or were refused, by method.\n");
+        out.push_str("# TYPE codex_monitor_rpc_errors_total counter\n");
+        for (method, stats) in &registry.methods {
+            let _ = writeln!(
+                out,
+                "codex_monitor_rpc_errors_total{{method=\"{}\"}} {}",
+                escape_label(method),
+                stats.errors
+            );
+        }
+        out.push_str(
+            "# HELP codex_monitor_rpc_duration_seconds RPC request durations by method.\n",
+        );
+        out.push_str("# TYPE codex_monitor_rpc_duration_seconds histogram\n");
+        for (method, stats) in &registry.methods {
+            let labels = format!("method=\"{}\"", escape_label(method));
+            stats
+                .duration
+                .render(&mut out, "codex_monitor_rpc_duration_seconds", &labels);
+        }
+        out.push_str("# HELP codex_monitor_git_command_duration_seconds Durations of git commands run by requests and operations.\n");
+        out.push_str("# TYPE codex_monitor_git_command_duration_seconds histogram\n");
+        registry
+            .git
+            .render(&mut out, "codex_monitor_git_command_duration_seconds", "");
+        out.push_str("# HELP codex_monitor_connections Open RPC connections.\n");
+        out.push_str("# TYPE codex_monitor_connections gauge\n");
+        let _ = writeln!(out, "codex_monitor_connections {connections}");
+        out.push_str("# HELP codex_monitor_sessions Running workspace sessions.\n");
+        out.push_str("# TYPE codex_monitor_sessions gauge\n");
+        let _ = writeln!(out, "codex_monitor_sessions {sessions}");
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_label, Metrics, MAX_METHODS};
+    use std::time::Duration;
+
+    #[test]
+    fn renders_counters_histograms_and_gauges() {
+        let metrics = Metrics::default();
+        metrics.record_rpc("list_workspaces", true, Duration::from_millis(3));
+        metrics.record_rpc("list_workspaces", false, Duration::from_millis(30));
+        metrics.record_git(Duration::from_millis(200));
+
+        let text = metrics.render(2, 1);
+        assert!(text.contains("codex_monitor_rpc_calls_total{method=\"list_workspaces\"} 2\n"));
+        assert!(text.contains("codex_monitor_rpc_errors_total{method=\"list_workspaces\"} 1\n"));
+        assert!(text.contains(
+            "codex_monitor_rpc_duration_seconds_bucket{method=\"list_workspaces\",le=\"0.005\"} 1\n"
+        ));
+        assert!(text.contains(
+            "codex_monitor_rpc_duration_seconds_bucket{method=\"list_workspaces\",le=\"+Inf\"} 2\n"
+        ));
+        assert!(text.contains("codex_monitor_git_command_duration_seconds_bucket{le=\"0.1\"} 0\n"));
+        assert!(text.contains("codex_monitor_git_command_duration_seconds_bucket{le=\"0.25\"} 1\n"));
+        assert!(text.contains("codex_monitor_git_command_duration_seconds_count 1\n"));
+        assert!(text.contains("codex_monitor_connections 2\n"));
+        assert!(text.contains("codex_monitor_sessions 1\n"));
+    }
+
+    #[test]
+    fn caps_method_labels() {
+        let metrics = Metrics::default();
+        for index in 0..MAX_METHODS {
+            metrics.record_rpc(&format!("method_{index}"), true, Duration::ZERO);
+        }
+        metrics.record_rpc("one_too_many", false, Duration::ZERO);
+        metrics.record_rpc("method_0", true, Duration::ZERO);
+
+        let text = metrics.render(0, 0);
+        assert!(text.contains("codex_monitor_rpc_errors_total{method=\"other\"} 1\n"));
+        assert!(text.contains("codex_monitor_rpc_calls_total{method=\"method_0\"} 2\n"));
+        assert!(!text.contains("one_too_many"));
+        assert_eq!(escape_label("a\"b\\c"), "a\\\"b\\\\c");
+    }
+}
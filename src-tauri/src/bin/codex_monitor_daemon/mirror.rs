@@ -0,0 +1,489 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+
+use super::fetch::now_ms;
+
+/// Everything but these is rejected by a mirror.
+const MIRRORED_METHODS: [&str; 3] = ["list_workspaces", "workspace_status", "list_threads"];
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// How often threads of workspaces with new thread events are re-fetched.
+const DIRTY_REFRESH: Duration = Duration::from_secs(2);
+/// How often the whole snapshot is re-fetched while connected.
+const FULL_RESYNC: Duration = Duration::from_secs(30);
+
+pub(crate) const READ_ONLY_MESSAGE: &str = "mirror is read-only";
+
+#[derive(Debug, Clone)]
+pub(crate) struct MirrorConfig {
+    /// Address of the primary daemon, e.g. `10.0.0.2:4732`.
+    pub(crate) upstream: String,
+    pub(crate) token: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct MirrorStatus {
+    pub(crate) upstream: String,
+    pub(crate) connected: bool,
+    /// True while the snapshot can't be refreshed from the primary.
+    pub(crate) stale: bool,
+    pub(crate) last_sync_at: Option<u64>,
+    pub(crate) disconnected_at: Option<u64>,
+    pub(crate) reconnects: u64,
+    pub(crate) last_error: Option<String>,
+}
+
+#[derive(Default)]
+struct Snapshot {
+    workspaces: Vec<Value>,
+    /// First `list_threads` page per workspace id.
+    threads: HashMap<String, Value>,
+}
+
+/// A read-only copy of a primary daemon's workspaces and threads, kept in
+/// sync over the regular client protocol.
+pub(crate) struct Mirror {
+    config: MirrorConfig,
+    status: Mutex<MirrorStatus>,
+    snapshot: Mutex<Snapshot>,
+}
+
+fn string_field<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+impl Mirror {
+    pub(crate) fn new(config: MirrorConfig) -> Self {
+        let status = MirrorStatus {
+            upstream: config.upstream.clone(),
+            stale: true,
+            ..MirrorStatus::default()
+        };
+        Self {
+            config,
+            status: Mutex::new(status),
+            snapshot: Mutex::new(Snapshot::default()),
+        }
+    }
+
+    pub(crate) fn serves(method: &str) -> bool {
+        MIRRORED_METHODS.contains(&method)
+    }
+
+    pub(crate) fn status(&self) -> MirrorStatus {
+        self.status
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+
+    /// Answers a read RPC from the last snapshot, stale or not.
+    pub(crate) fn read(&self, method: &str, params: &Value) -> Result<Value, String> {
+        let snapshot = self.snapshot.lock().unwrap_or_else(|err| err.into_inner());
+        match method {
+            "list_workspaces" => Ok(Value::Array(snapshot.workspaces.clone())),
+            "workspace_status" => {
+                let id = string_field(params, "id").ok_or("missing `id`")?;
+                snapshot
+                    .workspaces
+                    .iter()
+                    .find(|workspace| string_field(workspace, "id") == Some(id))
+                    .cloned()
+                    .ok_or_else(|| "workspace not found".to_string())
+            }
+            "list_threads" => {
+                let id = string_field(params, "workspaceId").ok_or("missing `workspaceId`")?;
+                if params.get("cursor").is_some_and(|cursor| !cursor.is_null()) {
+                    return Err("A mirror only serves the first page of threads.".to_string());
+                }
+                let mut page = snapshot
+                    .threads
+                    .get(id)
+                    .cloned()
+                    .ok_or("workspace not found")?;
+                page["fromCache"] = Value::Bool(true);
+                Ok(page)
+            }
+            _ => Err(READ_ONLY_MESSAGE.to_string()),
+        }
+    }
+
+    fn update_status(&self, apply: impl FnOnce(&mut MirrorStatus)) {
+        let mut status = self.status.lock().unwrap_or_else(|err| err.into_inner());
+        apply(&mut status);
+    }
+
+    fn store_workspaces(&self, workspaces: Vec<Value>) {
+        let mut snapshot = self.snapshot.lock().unwrap_or_else(|err| err.into_inner());
+        snapshot.threads.retain(|id, _| {
+            workspaces
+                .iter()
+                .any(|workspace| string_field(workspace, "id") == Some(id))
+        });
+        snapshot.workspaces = workspaces;
+    }
+
+    fn store_threads(&self, workspace_id: &str, page: Value) {
+        let mut snapshot = self.snapshot.lock().unwrap_or_else(|err| err.into_inner());
+        snapshot.threads.insert(workspace_id.to_string(), page);
+    }
+
+    fn workspace_ids(&self) -> Vec<String> {
+        let snapshot = self.snapshot.lock().unwrap_or_else(|err| err.into_inner());
+        snapshot
+            .workspaces
+            .iter()
+            .filter_map(|workspace| string_field(workspace, "id"))
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Keeps the snapshot in sync until the daemon exits, reconnecting with
+    /// backoff. Upstream notifications are handed to `on_event` as
+    /// `(method, params)`.
+    pub(crate) async fn run(&self, on_event: impl Fn(&str, Value)) {
+        let mut backoff = MIN_BACKOFF;
+        loop {
+            let error = match Upstream::connect(&self.config).await {
+                Ok(mut upstream) => {
+                    let error = self.follow(&mut upstream, &on_event, &mut backoff).await;
+                    self.update_status(|status| {
+                        status.connected = false;
+                        status.stale = true;
+                        status.disconnected_at = Some(now_ms());
+                    });
+                    on_event("mirror-status", json!(self.status()));
+                    error
+                }
+                Err(error) => error,
+            };
+            self.update_status(|status| status.last_error = Some(error));
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+            self.update_status(|status| status.reconnects += 1);
+        }
+    }
+
+    /// Syncs, then forwards events and refreshes until the link drops.
+    async fn follow(
+        &self,
+        upstream: &mut Upstream,
+        on_event: &impl Fn(&str, Value),
+        backoff: &mut Duration,
+    ) -> String {
+        let mut dirty = BTreeSet::new();
+        if let Err(error) = self.sync(upstream, on_event, &mut dirty).await {
+            return error;
+        }
+        *backoff = MIN_BACKOFF;
+        self.update_status(|status| {
+            status.connected = true;
+            status.stale = false;
+            status.disconnected_at = None;
+            status.last_error = None;
+        });
+        on_event("mirror-status", json!(self.status()));
+
+        let mut refresh = tokio::time::interval(DIRTY_REFRESH);
+        let mut resync = tokio::time::interval(FULL_RESYNC);
+        resync.tick().await;
+        loop {
+            let result = tokio::select! {
+                message = upstream.next_message() => match message {
+                    Ok(message) => {
+                        if self.forward(&message, on_event, &mut dirty) {
+                            self.sync(upstream, on_event, &mut dirty).await
+                        } else {
+                            Ok(())
+                        }
+                    }
+                    Err(error) => Err(error),
+                },
+                _ = refresh.tick(), if !dirty.is_empty() => {
+                    let workspace_ids = std::mem::take(&mut dirty);
+                    self.sync_threads(upstream, workspace_ids, on_event, &mut dirty)
+                        .await
+                }
+                _ = resync.tick() => self.sync(upstream, on_event, &mut dirty).await,
+            };
+            if let Err(error) = result {
+                return error;
+            }
+        }
+    }
+
+    async fn sync(
+        &self,
+        upstream: &mut Upstream,
+        on_event: &impl Fn(&str, Value),
+        dirty: &mut BTreeSet<String>,
+    ) -> Result<(), String> {
+        let workspaces = upstream
+            .call("list_workspaces", json!({}), |message| {
+                self.forward(&message, on_event, dirty);
+            })
+            .await?;
+        let workspaces = match workspaces {
+            Value::Array(workspaces) => workspaces,
+            _ => return Err("list_workspaces did not return an array".to_string()),
+        };
+        self.store_workspaces(workspaces);
+        let workspace_ids = self.workspace_ids().into_iter().collect();
+        self.sync_threads(upstream, workspace_ids, on_event, dirty)
+            .await?;
+        self.update_status(|status| status.last_sync_at = Some(now_ms()));
+        Ok(())
+    }
+
+    /// Failing `list_threads` calls keep the previous page; only a broken
+    /// link is an error.
+    async fn sync_threads(
+        &self,
+        upstream: &mut Upstream,
+        workspace_ids: BTreeSet<String>,
+        on_event: &impl Fn(&str, Value),
+        dirty: &mut BTreeSet<String>,
+    ) -> Result<(), String> {
+        for workspace_id in workspace_ids {
+            let page = upstream
+                .call(
+                    "list_threads",
+                    json!({ "workspaceId": workspace_id }),
+                    |message| {
+                        self.forward(&message, on_event, dirty);
+                    },
+                )
+                .await;
+            match page {
+                Ok(page) => self.store_threads(&workspace_id, page),
+                Err(UpstreamError::Rpc(_)) => {}
+                Err(UpstreamError::Link(error)) => return Err(error),
+            }
+        }
+        Ok(())
+    }
+
+    /// Passes an upstream notification on and notes workspaces whose thread
+    /// list may have changed. Returns true when a full resync is needed.
+    fn forward(
+        &self,
+        message: &Value,
+        on_event: &impl Fn(&str, Value),
+        dirty: &mut BTreeSet<String>,
+    ) -> bool {
+        let Some(method) = string_field(message, "method") else {
+            return false;
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        match method {
+            // Only meaningful for the upstream link itself.
+            "events-resync-recommended" => return true,
+            "app-server-event" => {
+                let thread_event = params
+                    .get("message")
+                    .and_then(|message| string_field(message, "method"))
+                    .is_some_and(|method| method.starts_with("thread/"));
+                let workspace_id = string_field(&params, "workspaceId").filter(|_| thread_event);
+                if let Some(workspace_id) = workspace_id {
+                    dirty.insert(workspace_id.to_string());
+                }
+            }
+            "thread-title-updated" => {
+                if let Some(workspace_id) = string_field(&params, "workspaceId") {
+                    dirty.insert(workspace_id.to_string());
+                }
+            }
+            "workspace-restart-required" => return true,
+            "workspaces-changed" => {
+                on_event(method, params);
+                return true;
+            }
+            _ => {}
+        }
+        on_event(method, params);
+        false
+    }
+}
+
+enum UpstreamError {
+    /// The primary answered with an error.
+    Rpc(String),
+    /// The connection is gone.
+    Link(String),
+}
+
+impl From<UpstreamError> for String {
+    fn from(error: UpstreamError) -> Self {
+        match error {
+            UpstreamError::Rpc(message) | UpstreamError::Link(message) => message,
+        }
+    }
+}
+
+/// One client connection to the primary daemon.
+struct Upstream {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+    next_id: u64,
+}
+
+impl Upstream {
+    async fn connect(config: &MirrorConfig) -> Result<Self, String> {
+        let stream = TcpStream::connect(&config.upstream)
+            .await
+            .map_err(|err| format!("Failed to connect to {}: {err}", config.upstream))?;
+        let (reader, writer) = stream.into_split();
+        let mut upstream = Self {
+            lines: BufReader::new(reader).lines(),
+            writer,
+            next_id: 1,
+        };
+        if let Some(token) = &config.token {
+            upstream
+                .call("auth", json!({ "token": token }), |_| {})
+                .await?;
+        }
+        Ok(upstream)
+    }
+
+    async fn next_message(&mut self) -> Result<Value, String> {
+        loop {
+            let line = match self.lines.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => return Err("upstream closed the connection".to_string()),
+                Err(err) => return Err(err.to_string()),
+            };
+            if let Ok(message) = serde_json::from_str::<Value>(line.trim()) {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Sends a request and waits for its response, handing notifications
+    /// that arrive meanwhile to `on_notification`.
+    async fn call(
+        &mut self,
+        method: &str,
+        params: Value,
+        mut on_notification: impl FnMut(Value),
+    ) -> Result<Value, UpstreamError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = json!({ "id": id, "method": method, "params": params });
+        let mut line = request.to_string();
+        line.push('\n');
+        self.writer
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|err| UpstreamError::Link(err.to_string()))?;
+        loop {
+            let message = self.next_message().await.map_err(UpstreamError::Link)?;
+            if message.get("id").and_then(Value::as_u64) != Some(id) {
+                if message.get("id").is_none() {
+                    on_notification(message);
+                }
+                continue;
+            }
+            if let Some(error) = message.get("error") {
+                let error = string_field(error, "message").unwrap_or("upstream error");
+                return Err(UpstreamError::Rpc(format!("{method}: {error}")));
+            }
+            return Ok(message.get("result").cloned().unwrap_or(Value::Null));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Mirror, MirrorConfig};
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Answers like a primary daemon, then pushes one notification.
+    async fn fake_primary(listener: TcpListener) {
+        let (socket, _) = listener.accept().await.expect("accept");
+        let (reader, mut writer) = socket.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let request: Value = serde_json::from_str(&line).expect("request");
+            let result = match request["method"].as_str() {
+                Some("auth") => json!({ "ok": true }),
+                Some("list_workspaces") => json!([{ "id": "ws", "name": "repo" }]),
+                Some("list_threads") => json!({ "threads": [{ "id": "t1" }], "nextCursor": null }),
+                _ => json!(null),
+            };
+            let response = json!({ "id": request["id"], "result": result });
+            let mut output = format!("{response}\n");
+            if request["method"] == "list_threads" {
+                let event = json!({ "method": "terminal-output", "params": { "data": "hi" } });
+                output.push_str(&format!("{event}\n"));
+            }
+            writer.write_all(output.as_bytes()).await.expect("write");
+        }
+    }
+
+    #[tokio::test]
+    async fn mirrors_the_primary_and_rejects_other_methods() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let upstream = listener.local_addr().expect("addr").to_string();
+        tokio::spawn(fake_primary(listener));
+
+        let mirror = Arc::new(Mirror::new(MirrorConfig {
+            upstream,
+            token: Some("secret".to_string()),
+        }));
+        assert!(mirror.status().stale);
+        assert!(mirror.read("list_workspaces", &json!({})).is_ok());
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        {
+            let mirror = Arc::clone(&mirror);
+            let events = Arc::clone(&events);
+            tokio::spawn(async move {
+                mirror
+                    .run(|method, _| events.lock().unwrap().push(method.to_string()))
+                    .await;
+            });
+        }
+        for _ in 0..100 {
+            if events
+                .lock()
+                .unwrap()
+                .iter()
+                .any(|method| method == "terminal-output")
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let status = mirror.status();
+        assert!(status.connected && !status.stale);
+        let workspaces = mirror.read("list_workspaces", &json!({})).expect("list");
+        assert_eq!(workspaces, json!([{ "id": "ws", "name": "repo" }]));
+        let workspace = mirror.read("workspace_status", &json!({ "id": "ws" }));
+        assert_eq!(workspace.expect("status")["name"], "repo");
+        let page = mirror
+            .read("list_threads", &json!({ "workspaceId": "ws" }))
+            .expect("threads");
+        assert_eq!(page["threads"], json!([{ "id": "t1" }]));
+        assert_eq!(page["fromCache"], json!(true));
+        assert!(Mirror::serves("list_threads"));
+        assert!(!Mirror::serves("send_user_message"));
+        assert!(mirror.read("send_user_message", &json!({})).is_err());
+        let events = events.lock().unwrap();
+        assert!(events.iter().any(|method| method == "mirror-status"));
+        assert!(events.iter().any(|method| method == "terminal-output"));
+    }
+}
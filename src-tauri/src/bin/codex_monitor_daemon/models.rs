@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::Value;
+
+struct ModelEntry {
+    /// `id` and `model` as reported; either is accepted.
+    names: Vec<String>,
+    /// Empty when the app-server didn't list any.
+    efforts: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ModelCheckError {
+    UnknownModel {
+        model: String,
+        known: Vec<String>,
+    },
+    UnsupportedEffort {
+        model: String,
+        effort: String,
+        supported: Vec<String>,
+    },
+}
+
+impl ModelCheckError {
+    pub(crate) fn message(&self) -> String {
+        match self {
+            Self::UnknownModel { model, known } => {
+                format!("unknown model \"{model}\"; available: {}", known.join(", "))
+            }
+            Self::UnsupportedEffort {
+                model,
+                effort,
+                supported,
+            } => format!(
+                "model \"{model}\" does not support effort \"{effort}\"; supported: {}",
+                supported.join(", ")
+            ),
+        }
+    }
+}
+
+fn string_field(value: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .find_map(|key| value.get(key).and_then(Value::as_str))
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+fn reasoning_efforts(item: &Value) -> Vec<String> {
+    ["supportedReasoningEfforts", "supported_reasoning_efforts"]
+        .iter()
+        .find_map(|key| item.get(key).and_then(Value::as_array))
+        .map(|efforts| {
+            efforts
+                .iter()
+                .filter_map(|effort| match effort {
+                    Value::String(effort) => Some(effort.clone()),
+                    _ => string_field(effort, &["reasoningEffort", "reasoning_effort"]),
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn parse_entry(item: &Value) -> Option<ModelEntry> {
+    let mut names: Vec<String> = ["id", "model"]
+        .iter()
+        .filter_map(|key| string_field(item, &[key]))
+        .collect();
+    names.dedup();
+    if names.is_empty() {
+        return None;
+    }
+    let efforts = reasoning_efforts(item);
+    Some(ModelEntry { names, efforts })
+}
+
+/// A `model/list` entry in the shape `models_detailed` promises, whatever
+/// the app-server version. Capabilities it doesn't report are assumed
+/// absent.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ModelDetails {
+    pub(crate) id: String,
+    pub(crate) model: String,
+    pub(crate) display_name: String,
+    pub(crate) description: String,
+    pub(crate) is_default: bool,
+    /// Only when `inputModalities` lists `image`.
+    pub(crate) supports_images: bool,
+    pub(crate) supports_reasoning_effort: bool,
+    pub(crate) reasoning_efforts: Vec<String>,
+    pub(crate) default_reasoning_effort: Option<String>,
+    /// Context window in tokens; `None` when not reported.
+    pub(crate) max_context_tokens: Option<u64>,
+}
+
+fn parse_details(item: &Value) -> Option<ModelDetails> {
+    let id = string_field(item, &["id", "model"])?;
+    let model = string_field(item, &["model"]).unwrap_or_else(|| id.clone());
+    let reasoning_efforts = reasoning_efforts(item);
+    let supports_images = ["inputModalities", "input_modalities"]
+        .iter()
+        .find_map(|key| item.get(key).and_then(Value::as_array))
+        .is_some_and(|modalities| {
+            modalities
+                .iter()
+                .any(|modality| modality.as_str() == Some("image"))
+        });
+    let max_context_tokens = ["contextWindow", "context_window"]
+        .iter()
+        .find_map(|key| item.get(key).and_then(Value::as_u64))
+        .filter(|tokens| *tokens > 0);
+    Some(ModelDetails {
+        display_name: string_field(item, &["displayName", "display_name"])
+            .unwrap_or_else(|| model.clone()),
+        description: string_field(item, &["description"]).unwrap_or_default(),
+        is_default: ["isDefault", "is_default"]
+            .iter()
+            .find_map(|key| item.get(key).and_then(Value::as_bool))
+            .unwrap_or(false),
+        supports_images,
+        supports_reasoning_effort: !reasoning_efforts.is_empty(),
+        default_reasoning_effort: string_field(
+            item,
+            &["defaultReasoningEffort", "default_reasoning_effort"],
+        ),
+        reasoning_efforts,
+        max_context_tokens,
+        id,
+        model,
+    })
+}
+
+/// Maps a raw `model/list` response to `ModelDetails`, or its error message.
+pub(crate) fn model_details(response: &Value) -> Result<Vec<ModelDetails>, String> {
+    if let Some(error) = response.get("error") {
+        let message = string_field(error, &["message"]).unwrap_or_else(|| error.to_string());
+        return Err(message);
+    }
+    let result = response.get("result").unwrap_or(response);
+    let items = result
+        .get("data")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    Ok(items.iter().filter_map(parse_details).collect())
+}
+
+/// The last `model/list` result per workspace, used to reject unknown
+/// models and efforts before a turn starts.
+#[derive(Default)]
+pub(crate) struct ModelCatalog {
+    models: Mutex<HashMap<String, Vec<ModelEntry>>>,
+}
+
+impl ModelCatalog {
+    /// Remembers a raw `model/list` response; error responses are ignored.
+    pub(crate) fn record(&self, workspace_id: &str, response: &Value) {
+        let result = response.get("result").unwrap_or(response);
+        let Some(items) = result.get("data").and_then(Value::as_array) else {
+            return;
+        };
+        let entries: Vec<ModelEntry> = items.iter().filter_map(parse_entry).collect();
+        let mut models = self.models.lock().unwrap_or_else(|err| err.into_inner());
+        models.insert(workspace_id.to_string(), entries);
+    }
+
+    /// Checks `model` and `effort` against the cached list. Anything passes
+    /// while nothing is cached for the workspace.
+    pub(crate) fn check(
+        &self,
+        workspace_id: &str,
+        model: Option<&str>,
+        effort: Option<&str>,
+    ) -> Result<(), ModelCheckError> {
+        let Some(model) = model.map(str::trim).filter(|model| !model.is_empty()) else {
+            return Ok(());
+        };
+        let models = self.models.lock().unwrap_or_else(|err| err.into_inner());
+        let Some(entries) = models
+            .get(workspace_id)
+            .filter(|entries| !entries.is_empty())
+        else {
+            return Ok(());
+        };
+        let Some(entry) = entries
+            .iter()
+            .find(|entry| entry.names.iter().any(|name| name == model))
+        else {
+            return Err(ModelCheckError::UnknownModel {
+                model: model.to_string(),
+                known: entries.iter().map(|entry| entry.names[0].clone()).collect(),
+            });
+        };
+        match effort.map(str::trim).filter(|effort| !effort.is_empty()) {
+            Some(effort)
+                if !entry.efforts.is_empty()
+                    && !entry.efforts.iter().any(|supported| supported == effort) =>
+            {
+                Err(ModelCheckError::UnsupportedEffort {
+                    model: model.to_string(),
+                    effort: effort.to_string(),
+                    supported: entry.efforts.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        let mut models = self.models.lock().unwrap_or_else(|err| err.into_inner());
+        models.remove(workspace_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{model_details, ModelCatalog, ModelCheckError};
+    use serde_json::json;
+
+    #[test]
+    fn checks_models_and_efforts_against_the_cache() {
+        let catalog = ModelCatalog::default();
+        assert!(catalog.check("ws", Some("anything"), Some("max")).is_ok());
+
+        catalog.record(
+            "ws",
+            &json!({ "result": { "data": [
+                {
+                    "id": "gpt-5",
+                    "model": "gpt-5",
+                    "supportedReasoningEfforts": [
+                        { "reasoningEffort": "low" },
+                        { "reasoningEffort": "high" }
+                    ]
+                },
+                { "id": "mini", "model": "gpt-5-mini" }
+            ] } }),
+        );
+        assert!(catalog.check("ws", Some("gpt-5"), Some("high")).is_ok());
+        assert!(catalog.check("ws", Some("gpt-5-mini"), Some("any")).is_ok());
+        assert!(catalog.check("ws", None, Some("max")).is_ok());
+        assert!(matches!(
+            catalog.check("ws", Some("gpt5"), None),
+            Err(ModelCheckError::UnknownModel { known, .. }) if known == ["gpt-5", "mini"]
+        ));
+        assert!(matches!(
+            catalog.check("ws", Some("gpt-5"), Some("max")),
+            Err(ModelCheckError::UnsupportedEffort { .. })
+        ));
+
+        catalog.record("ws", &json!({ "error": { "message": "boom" } }));
+        assert!(catalog.check("ws", Some("gpt-5"), Some("max")).is_err());
+        catalog.forget("ws");
+        assert!(catalog.check("ws", Some("gpt5"), None).is_ok());
+    }
+
+    #[test]
+    fn maps_model_list_to_details_with_conservative_defaults() {
+        let details = model_details(&json!({ "result": { "data": [
+            {
+                "id": "gpt-5",
+                "model": "gpt-5",
+                "displayName": "GPT-5",
+                "description": "Frontier model",
+                "isDefault": true,
+                "inputModalities": ["text", "image"],
+                "contextWindow": 272000,
+                "supportedReasoningEfforts": [{ "reasoningEffort": "low" }, { "reasoningEffort": "high" }],
+                "defaultReasoningEffort": "high"
+            },
+            { "model": "legacy" },
+            { "displayName": "no id" }
+        ] } }))
+        .expect("details");
+
+        assert_eq!(details.len(), 2);
+        assert_eq!(details[0].display_name, "GPT-5");
+        assert!(details[0].is_default);
+        assert!(details[0].supports_images);
+        assert!(details[0].supports_reasoning_effort);
+        assert_eq!(details[0].reasoning_efforts, ["low", "high"]);
+        assert_eq!(details[0].default_reasoning_effort.as_deref(), Some("high"));
+        assert_eq!(details[0].max_context_tokens, Some(272_000));
+
+        assert_eq!(details[1].id, "legacy");
+        assert_eq!(details[1].display_name, "legacy");
+        assert!(!details[1].supports_images);
+        assert!(!details[1].supports_reasoning_effort);
+        assert_eq!(details[1].max_context_tokens, None);
+
+        assert_eq!(
+            model_details(&json!({ "error": { "message": "boom" } })),
+            Err("boom".to_string())
+        );
+    }
+}
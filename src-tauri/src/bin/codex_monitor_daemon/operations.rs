@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::watch;
+use uuid::Uuid;
+
+use super::fetch::now_ms;
+
+/// Finished operations kept for `get_operation`; the oldest are dropped first.
+const MAX_FINISHED_OPERATIONS: usize = 100;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum OperationState {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OperationStatus {
+    pub(crate) id: String,
+    /// The RPC method that started it, e.g. `clone_workspace`.
+    pub(crate) method: String,
+    pub(crate) state: OperationState,
+    pub(crate) phase: Option<String>,
+    pub(crate) percent: Option<u32>,
+    /// Latest line of output from the running step.
+    pub(crate) line: Option<String>,
+    pub(crate) cancellable: bool,
+    /// The method's normal response, once completed.
+    pub(crate) result: Option<Value>,
+    pub(crate) error: Option<String>,
+    pub(crate) started_at: u64,
+    pub(crate) finished_at: Option<u64>,
+}
+
+/// Resolves once the operation is cancelled; never for inline calls.
+#[derive(Clone)]
+pub(crate) struct CancelSignal(Option<watch::Receiver<bool>>);
+
+impl CancelSignal {
+    pub(crate) async fn cancelled(&mut self) {
+        if let Some(receiver) = &mut self.0 {
+            if receiver.wait_for(|cancelled| *cancelled).await.is_ok() {
+                return;
+            }
+        }
+        std::future::pending::<()>().await;
+    }
+}
+
+struct Entry {
+    status: OperationStatus,
+    cancel: watch::Sender<bool>,
+}
+
+type OnEvent = Box<dyn Fn(&'static str, &OperationStatus) + Send + Sync>;
+
+/// Long-running RPCs invoked with `async: true`, with their latest progress.
+pub(crate) struct Operations {
+    entries: Mutex<HashMap<String, Entry>>,
+    /// Receives `operation-progress`, `operation-completed` or
+    /// `operation-failed` with the updated status.
+    on_event: OnEvent,
+}
+
+/// Where a long-running step reports progress; a no-op for inline calls.
+#[derive(Clone)]
+pub(crate) struct OperationProgress {
+    target: Option<(Arc<Operations>, String)>,
+    cancel: CancelSignal,
+}
+
+impl OperationProgress {
+    pub(crate) fn detached() -> Self {
+        Self {
+            target: None,
+            cancel: CancelSignal(None),
+        }
+    }
+
+    pub(crate) fn report(&self, phase: &str, percent: Option<u32>, line: Option<&str>) {
+        if let Some((operations, id)) = &self.target {
+            operations.update(id, |status| {
+                status.phase = Some(phase.to_string());
+                status.percent = percent;
+                if let Some(line) = line {
+                    status.line = Some(line.to_string());
+                }
+            });
+        }
+    }
+
+    pub(crate) fn cancel_signal(&self) -> CancelSignal {
+        self.cancel.clone()
+    }
+}
+
+impl Operations {
+    pub(crate) fn new(
+        on_event: impl Fn(&'static str, &OperationStatus) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            on_event: Box::new(on_event),
+        }
+    }
+
+    pub(crate) fn start(
+        self: &Arc<Self>,
+        method: &str,
+        cancellable: bool,
+    ) -> (String, OperationProgress) {
+        let id = Uuid::new_v4().to_string();
+        let (cancel, receiver) = watch::channel(false);
+        let status = OperationStatus {
+            id: id.clone(),
+            method: method.to_string(),
+            state: OperationState::Running,
+            phase: None,
+            percent: None,
+            line: None,
+            cancellable,
+            result: None,
+            error: None,
+            started_at: now_ms(),
+            finished_at: None,
+        };
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        entries.insert(id.clone(), Entry { status, cancel });
+        drop(entries);
+        let progress = OperationProgress {
+            target: Some((Arc::clone(self), id.clone())),
+            cancel: CancelSignal(cancellable.then_some(receiver)),
+        };
+        (id, progress)
+    }
+
+    fn update(&self, id: &str, apply: impl FnOnce(&mut OperationStatus)) {
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        let Some(entry) = entries.get_mut(id) else {
+            return;
+        };
+        let before = entry.status.clone();
+        apply(&mut entry.status);
+        if entry.status != before {
+            (self.on_event)("operation-progress", &entry.status);
+        }
+    }
+
+    pub(crate) fn finish(&self, id: &str, result: Result<Value, String>) {
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        let Some(entry) = entries.get_mut(id) else {
+            return;
+        };
+        let cancelled = *entry.cancel.borrow();
+        let status = &mut entry.status;
+        status.finished_at = Some(now_ms());
+        let event = match result {
+            Ok(value) => {
+                status.state = OperationState::Completed;
+                status.result = Some(value);
+                "operation-completed"
+            }
+            Err(error) => {
+                status.state = if cancelled {
+                    OperationState::Cancelled
+                } else {
+                    OperationState::Failed
+                };
+                status.error = Some(error);
+                "operation-failed"
+            }
+        };
+        (self.on_event)(event, status);
+        Self::prune(&mut entries);
+    }
+
+    fn prune(entries: &mut HashMap<String, Entry>) {
+        let mut finished: Vec<(u64, String)> = entries
+            .values()
+            .filter_map(|entry| Some((entry.status.finished_at?, entry.status.id.clone())))
+            .collect();
+        if finished.len() <= MAX_FINISHED_OPERATIONS {
+            return;
+        }
+        finished.sort();
+        for (_, id) in &finished[..finished.len() - MAX_FINISHED_OPERATIONS] {
+            entries.remove(id);
+        }
+    }
+
+    pub(crate) fn get(&self, id: &str) -> Option<OperationStatus> {
+        let entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        entries.get(id).map(|entry| entry.status.clone())
+    }
+
+    /// Asks a running operation to stop; it finishes as `cancelled` once the
+    /// current step has been aborted.
+    pub(crate) fn cancel(&self, id: &str) -> Result<(), String> {
+        let entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        let entry = entries.get(id).ok_or("operation not found")?;
+        if entry.status.state != OperationState::Running {
+            return Err("Operation has already finished.".to_string());
+        }
+        if !entry.status.cancellable {
+            return Err(format!(
+                "{} operations can't be cancelled.",
+                entry.status.method
+            ));
+        }
+        entry.cancel.send_replace(true);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OperationState, Operations};
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn tracks_progress_and_completion() {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let operations = {
+            let events = Arc::clone(&events);
+            Arc::new(Operations::new(move |event, status| {
+                events.lock().unwrap().push((event, status.state));
+            }))
+        };
+        let (id, progress) = operations.start("clone_workspace", true);
+        let line = "Receiving objects:  40% (400/1000)";
+        progress.report("Receiving objects", Some(40), Some(line));
+        progress.report("Receiving objects", Some(40), Some(line));
+        operations.finish(&id, Ok(json!({ "id": "ws" })));
+
+        let status = operations.get(&id).expect("status");
+        assert_eq!(status.state, OperationState::Completed);
+        assert_eq!(status.percent, Some(40));
+        assert_eq!(status.line.as_deref(), Some(line));
+        assert_eq!(status.result, Some(json!({ "id": "ws" })));
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                ("operation-progress", OperationState::Running),
+                ("operation-completed", OperationState::Completed),
+            ]
+        );
+        assert!(operations.cancel(&id).is_err());
+    }
+
+    #[tokio::test]
+    async fn cancels_running_operations() {
+        let operations = Arc::new(Operations::new(|_, _| {}));
+        let (fixed, _) = operations.start("add_worktree", false);
+        assert!(operations.cancel(&fixed).is_err());
+
+        let (id, progress) = operations.start("clone_workspace", true);
+        let mut signal = progress.cancel_signal();
+        operations.cancel(&id).expect("cancel");
+        signal.cancelled().await;
+        operations.finish(&id, Err("Clone cancelled.".to_string()));
+        assert_eq!(
+            operations.get(&id).expect("status").state,
+            OperationState::Cancelled
+        );
+    }
+}
@@ -0,0 +1,101 @@
+use serde_json::Value;
+
+/// Parameter names from before the protocol settled on camelCase, as
+/// `(method, deprecated, current)`. They are still accepted but answered
+/// with a warning.
+const DEPRECATED_PARAMS: [(&str, &str, &str); 2] = [
+    ("add_workspace", "codex_bin", "codexBin"),
+    ("update_workspace_codex_bin", "codex_bin", "codexBin"),
+];
+
+/// Renames deprecated parameters of `method` to their camelCase names and
+/// returns a warning for each one used. A camelCase value sent alongside
+/// its deprecated form wins.
+pub(crate) fn normalize_params(method: &str, params: &mut Value) -> Vec<String> {
+    let Value::Object(map) = params else {
+        return Vec::new();
+    };
+    let mut warnings = Vec::new();
+    for (deprecated_method, deprecated, current) in DEPRECATED_PARAMS {
+        if deprecated_method != method {
+            continue;
+        }
+        let Some(value) = map.remove(deprecated) else {
+            continue;
+        };
+        map.entry(current).or_insert(value);
+        warnings.push(format!("`{deprecated}` is deprecated, use `{current}`"));
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{normalize_params, DEPRECATED_PARAMS};
+    use serde_json::{json, Map, Value};
+
+    const PROTOCOL_DOC: &str = include_str!("../../../../REMOTE_BACKEND_POC.md");
+
+    /// `(method, parameter names)` of every method documented with a
+    /// `{ ... }` parameter list.
+    fn documented_params() -> Vec<(&'static str, Vec<&'static str>)> {
+        PROTOCOL_DOC
+            .lines()
+            .filter_map(|line| {
+                let rest = line.strip_prefix("- `")?;
+                let (method, rest) = rest.split_once("` (`{")?;
+                let (names, _) = rest.split_once("}`)")?;
+                let names = names
+                    .split(',')
+                    .map(|name| name.split([':', '?']).next().unwrap_or_default().trim())
+                    .filter(|name| !name.is_empty())
+                    .collect();
+                Some((method, names))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn documented_params_are_camel_case_and_round_trip() {
+        let documented = documented_params();
+        assert!(documented.len() > 40);
+        for (method, names) in &documented {
+            let map: Map<String, Value> = names
+                .iter()
+                .map(|name| {
+                    assert!(!name.contains('_'), "{method} documents `{name}`");
+                    (name.to_string(), json!(format!("{name}-value")))
+                })
+                .collect();
+            let mut params = Value::Object(map.clone());
+            assert!(normalize_params(method, &mut params).is_empty());
+            assert_eq!(params, Value::Object(map));
+        }
+        for (method, _, current) in DEPRECATED_PARAMS {
+            let (_, names) = documented
+                .iter()
+                .find(|(documented, _)| *documented == method)
+                .expect("deprecated params belong to a documented method");
+            assert!(names.contains(&current), "{method} documents `{current}`");
+        }
+    }
+
+    #[test]
+    fn renames_deprecated_params_with_a_warning() {
+        let mut params = json!({ "path": "/repo", "codex_bin": "/bin/codex" });
+        let warnings = normalize_params("add_workspace", &mut params);
+        assert_eq!(params, json!({ "path": "/repo", "codexBin": "/bin/codex" }));
+        assert_eq!(warnings, ["`codex_bin` is deprecated, use `codexBin`"]);
+
+        let mut params = json!({ "id": "ws", "codex_bin": "old", "codexBin": "new" });
+        assert_eq!(
+            normalize_params("update_workspace_codex_bin", &mut params).len(),
+            1
+        );
+        assert_eq!(params, json!({ "id": "ws", "codexBin": "new" }));
+
+        let mut params = json!({ "codex_bin": "/bin/codex" });
+        assert!(normalize_params("list_workspaces", &mut params).is_empty());
+        assert_eq!(params, json!({ "codex_bin": "/bin/codex" }));
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// Workspaces whose new turns are held back by `pause_workspace`, kept in
+/// `paused_workspaces.json` so a pause survives a daemon restart.
+pub(crate) struct PausedWorkspaces {
+    path: PathBuf,
+    ids: Mutex<BTreeSet<String>>,
+}
+
+impl PausedWorkspaces {
+    pub(crate) fn load(path: PathBuf) -> Self {
+        let ids = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            ids: Mutex::new(ids),
+        }
+    }
+
+    pub(crate) fn is_paused(&self, workspace_id: &str) -> bool {
+        let ids = self.ids.lock().unwrap_or_else(|err| err.into_inner());
+        ids.contains(workspace_id)
+    }
+
+    /// Pauses or resumes the workspace; returns whether that changed anything.
+    pub(crate) fn set(&self, workspace_id: &str, paused: bool) -> Result<bool, String> {
+        let mut ids = self.ids.lock().unwrap_or_else(|err| err.into_inner());
+        let changed = if paused {
+            ids.insert(workspace_id.to_string())
+        } else {
+            ids.remove(workspace_id)
+        };
+        if changed {
+            self.persist(&ids)?;
+        }
+        Ok(changed)
+    }
+
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        let _ = self.set(workspace_id, false);
+    }
+
+    fn persist(&self, ids: &BTreeSet<String>) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(ids).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, data).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PausedWorkspaces;
+    use uuid::Uuid;
+
+    #[test]
+    fn pauses_persist_until_resumed() {
+        let path = std::env::temp_dir()
+            .join(format!("codex-monitor-paused-{}", Uuid::new_v4()))
+            .join("paused_workspaces.json");
+        let paused = PausedWorkspaces::load(path.clone());
+        assert!(!paused.is_paused("ws"));
+        assert_eq!(paused.set("ws", true), Ok(true));
+        assert_eq!(paused.set("ws", true), Ok(false));
+
+        let reloaded = PausedWorkspaces::load(path.clone());
+        assert!(reloaded.is_paused("ws"));
+        assert!(!reloaded.is_paused("other"));
+        reloaded.forget("ws");
+        assert!(!PausedWorkspaces::load(path.clone()).is_paused("ws"));
+
+        let _ = std::fs::remove_dir_all(path.parent().expect("parent"));
+    }
+}
@@ -0,0 +1,128 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::preflight::{CheckStatus, PreflightReport};
+
+/// How often the daemon reruns the environment checks for `daemon_status`.
+pub(crate) const PREFLIGHT_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+impl PreflightReport {
+    /// Counts of the environment checks, leaving out workspace ones so runs
+    /// with and without workspaces compare.
+    pub(crate) fn summary(&self) -> PreflightSummary {
+        let environment = self
+            .checks
+            .iter()
+            .filter(|check| check.workspace_id.is_none());
+        let mut summary = PreflightSummary {
+            ok: true,
+            checked_at: self.checked_at,
+            pass: 0,
+            warn: 0,
+            fail: 0,
+            problems: Vec::new(),
+        };
+        for check in environment {
+            match check.status {
+                CheckStatus::Pass => summary.pass += 1,
+                CheckStatus::Warn => summary.warn += 1,
+                CheckStatus::Fail => summary.fail += 1,
+            }
+            if check.status != CheckStatus::Pass {
+                summary.problems.push(check.id);
+            }
+        }
+        summary.ok = summary.fail == 0;
+        summary
+    }
+}
+
+/// What `daemon_status` reports of the last run.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PreflightSummary {
+    pub(crate) ok: bool,
+    pub(crate) checked_at: u64,
+    pub(crate) pass: usize,
+    pub(crate) warn: usize,
+    pub(crate) fail: usize,
+    /// Ids of the checks that warned or failed.
+    pub(crate) problems: Vec<&'static str>,
+}
+
+/// The summary of the last `preflight_check` or background run.
+#[derive(Default)]
+pub(crate) struct PreflightCache {
+    last: Mutex<Option<PreflightSummary>>,
+}
+
+impl PreflightCache {
+    pub(crate) fn store(&self, report: &PreflightReport) {
+        let mut last = self.last.lock().unwrap_or_else(|err| err.into_inner());
+        *last = Some(report.summary());
+    }
+
+    pub(crate) fn summary(&self) -> Option<PreflightSummary> {
+        self.last
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .clone()
+    }
+}
+
+/// Which workspaces `preflight_check` looks at, from `workspaceIds`: a list
+/// of ids or `"all"`. `None` checks the environment only.
+pub(crate) enum WorkspaceScope {
+    All,
+    Ids(Vec<String>),
+}
+
+pub(crate) fn parse_workspace_scope(params: &Value) -> Result<Option<WorkspaceScope>, String> {
+    match params.get("workspaceIds") {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(all)) if all == "all" => Ok(Some(WorkspaceScope::All)),
+        Some(Value::Array(ids)) => ids
+            .iter()
+            .map(|id| id.as_str().map(str::to_string))
+            .collect::<Option<Vec<_>>>()
+            .map(|ids| Some(WorkspaceScope::Ids(ids)))
+            .ok_or_else(|| "`workspaceIds` must be a list of strings or \"all\"".to_string()),
+        Some(_) => Err("`workspaceIds` must be a list of strings or \"all\"".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PreflightCache;
+    use crate::preflight::{
+        codex_check, data_dir_check, workspace_check, CheckStatus, PreflightReport,
+    };
+    use uuid::Uuid;
+
+    #[test]
+    fn summarizes_environment_checks() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-preflight-{}", Uuid::new_v4()));
+        let checks = vec![
+            data_dir_check(&root.join("data")),
+            codex_check(Ok(None)),
+            workspace_check("ws", &root.join("missing"), None),
+        ];
+        assert_eq!(checks[0].status, CheckStatus::Pass);
+        assert_eq!(checks[2].status, CheckStatus::Fail);
+        let report = PreflightReport::new(checks, 42);
+        assert!(!report.ok);
+
+        let cache = PreflightCache::default();
+        assert!(cache.summary().is_none());
+        cache.store(&report);
+        let summary = cache.summary().expect("stored");
+        assert!(summary.ok);
+        assert_eq!((summary.pass, summary.warn, summary.fail), (1, 1, 0));
+        assert_eq!(summary.problems, ["codex"]);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
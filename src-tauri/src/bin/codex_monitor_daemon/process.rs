@@ -0,0 +1,174 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+
+/// PID file owned by this process; removed again when dropped.
+pub(crate) struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    /// Writes the current PID to `path`. Fails when the file points at a
+    /// process that is still running; stale files are replaced.
+    pub(crate) fn acquire(path: &Path) -> Result<Self, String> {
+        if let Some(pid) = read_pid(path) {
+            if pid != std::process::id() && process_is_alive(pid) {
+                return Err(format!(
+                    "codex-monitor-daemon is already running (pid {pid}, pid file {})",
+                    path.display()
+                ));
+            }
+            std::fs::remove_file(path)
+                .map_err(|err| format!("Failed to remove stale pid file: {err}"))?;
+        }
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|err| format!("Failed to create pid file dir: {err}"))?;
+            }
+        }
+        // `create_new` so two daemons starting at once cannot both win.
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path)
+            .map_err(|err| format!("Failed to create pid file {}: {err}", path.display()))?;
+        writeln!(file, "{}", std::process::id())
+            .map_err(|err| format!("Failed to write pid file: {err}"))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Rewrites the file with the current PID, e.g. after forking.
+    #[cfg(unix)]
+    pub(crate) fn refresh(&self) -> Result<(), String> {
+        std::fs::write(&self.path, format!("{}\n", std::process::id()))
+            .map_err(|err| format!("Failed to write pid file: {err}"))
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        // Leave the file alone if another process has taken it over.
+        if read_pid(&self.path) == Some(std::process::id()) {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(unix)]
+fn process_is_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks that the process exists; EPERM means it exists but
+    // belongs to another user.
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Asks `tasklist` whether `pid` is running. When it can't answer, the
+/// process is assumed alive, so a live daemon is never displaced; the PID
+/// file then has to be removed by hand.
+#[cfg(windows)]
+fn process_is_alive(pid: u32) -> bool {
+    let output = std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {pid}"), "/FO", "CSV", "/NH"])
+        .stdin(std::process::Stdio::null())
+        .output();
+    // `"codex_monitor_daemon.exe","1234","Console",...`
+    let quoted = format!("\"{pid}\"");
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .any(|line| line.split(',').nth(1) == Some(quoted.as_str())),
+        _ => true,
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn process_is_alive(_pid: u32) -> bool {
+    // No liveness probe here; never displace a daemon that may be running.
+    true
+}
+
+/// Detaches from the controlling terminal (double fork + `setsid`) and points
+/// stdio at `/dev/null`. Must run before the tokio runtime starts threads.
+#[cfg(unix)]
+pub(crate) fn daemonize() -> Result<(), String> {
+    fn fork_and_exit_parent() -> Result<(), String> {
+        match unsafe { libc::fork() } {
+            -1 => Err(format!("fork failed: {}", std::io::Error::last_os_error())),
+            0 => Ok(()),
+            _ => std::process::exit(0),
+        }
+    }
+
+    fork_and_exit_parent()?;
+    if unsafe { libc::setsid() } == -1 {
+        return Err(format!(
+            "setsid failed: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+    fork_and_exit_parent()?;
+
+    std::env::set_current_dir("/").map_err(|err| format!("chdir failed: {err}"))?;
+    let dev_null = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/null")
+        .map_err(|err| format!("Failed to open /dev/null: {err}"))?;
+    for fd in [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+        if unsafe { libc::dup2(dev_null.as_raw_fd(), fd) } == -1 {
+            return Err(format!("dup2 failed: {}", std::io::Error::last_os_error()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PidFile;
+    use uuid::Uuid;
+
+    #[test]
+    fn pid_file_is_written_and_removed_on_drop() {
+        let path = std::env::temp_dir().join(format!("codex-monitor-test-{}.pid", Uuid::new_v4()));
+        let pid_file = PidFile::acquire(&path).expect("acquire pid file");
+        let contents = std::fs::read_to_string(&path).expect("read pid file");
+        assert_eq!(contents.trim(), std::process::id().to_string());
+        drop(pid_file);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn stale_pid_file_is_replaced() {
+        let path = std::env::temp_dir().join(format!("codex-monitor-test-{}.pid", Uuid::new_v4()));
+        // Larger than any default pid_max, so no process can own it.
+        std::fs::write(&path, "2147483647\n").expect("write stale pid file");
+        let _pid_file = PidFile::acquire(&path).expect("replace stale pid file");
+        let contents = std::fs::read_to_string(&path).expect("read pid file");
+        assert_eq!(contents.trim(), std::process::id().to_string());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn live_pid_file_is_rejected() {
+        if std::process::id() == 1 {
+            return;
+        }
+        let path = std::env::temp_dir().join(format!("codex-monitor-test-{}.pid", Uuid::new_v4()));
+        // PID 1 is always running.
+        std::fs::write(&path, "1\n").expect("write pid file");
+        assert!(PidFile::acquire(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
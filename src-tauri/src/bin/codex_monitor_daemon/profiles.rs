@@ -0,0 +1,172 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use super::fetch::now_ms;
+use crate::types::{WorkspaceEntry, WorkspaceSettings};
+
+/// A named copy of a workspace's configuration that can be applied to any
+/// workspace.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorkspaceProfile {
+    pub(crate) name: String,
+    pub(crate) settings: WorkspaceSettings,
+    #[serde(default)]
+    pub(crate) codex_bin: Option<String>,
+    /// Workspace the profile was captured from; informational only.
+    #[serde(default)]
+    pub(crate) source_workspace_id: Option<String>,
+    pub(crate) saved_at: u64,
+}
+
+impl WorkspaceProfile {
+    pub(crate) fn capture(name: &str, entry: &WorkspaceEntry) -> Result<Self, String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err("Profile name is required.".to_string());
+        }
+        Ok(Self {
+            name: name.to_string(),
+            settings: entry.settings.clone(),
+            codex_bin: entry.codex_bin.clone(),
+            source_workspace_id: Some(entry.id.clone()),
+            saved_at: now_ms(),
+        })
+    }
+
+    /// The profile's settings for a workspace currently using `current`.
+    /// Sidebar placement, color, icon and `gitRoot` describe where a workspace
+    /// lives and how it looks, not how it runs, so they are kept from `current`.
+    pub(crate) fn settings_for(&self, current: &WorkspaceSettings) -> WorkspaceSettings {
+        WorkspaceSettings {
+            sidebar_collapsed: current.sidebar_collapsed,
+            pinned: current.pinned,
+            sort_order: current.sort_order,
+            group_id: current.group_id.clone(),
+            git_root: current.git_root.clone(),
+            color: current.color.clone(),
+            icon: current.icon.clone(),
+            ..self.settings.clone()
+        }
+    }
+}
+
+/// Profiles saved in `<data-dir>/workspace_profiles.json`, sorted by name.
+pub(crate) struct ProfileStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl ProfileStore {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn read(&self) -> Result<Vec<WorkspaceProfile>, String> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let data = std::fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&data).map_err(|e| e.to_string())
+    }
+
+    pub(crate) fn list(&self) -> Result<Vec<WorkspaceProfile>, String> {
+        let _guard = self.lock.lock().unwrap_or_else(|err| err.into_inner());
+        self.read()
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Result<WorkspaceProfile, String> {
+        let name = name.trim();
+        self.list()?
+            .into_iter()
+            .find(|profile| profile.name == name)
+            .ok_or_else(|| format!("Profile not found: {name}"))
+    }
+
+    /// Stores `profile`, replacing one with the same name.
+    pub(crate) fn save(&self, profile: WorkspaceProfile) -> Result<(), String> {
+        let _guard = self.lock.lock().unwrap_or_else(|err| err.into_inner());
+        let mut profiles = self.read()?;
+        profiles.retain(|existing| existing.name != profile.name);
+        profiles.push(profile);
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(&profiles).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, data).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ProfileStore, WorkspaceProfile};
+    use crate::types::{WorkspaceEntry, WorkspaceKind, WorkspaceSettings};
+    use uuid::Uuid;
+
+    fn entry(id: &str, settings: WorkspaceSettings) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            path: format!("/tmp/{id}"),
+            codex_bin: Some("/opt/codex".to_string()),
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings,
+            status: None,
+        }
+    }
+
+    #[test]
+    fn saves_and_applies_profiles_across_workspaces() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-profiles-{}", Uuid::new_v4()));
+        let store = ProfileStore::new(dir.join("workspace_profiles.json"));
+        assert!(store.list().expect("list").is_empty());
+
+        let settings = WorkspaceSettings {
+            default_access_mode: Some("read-only".to_string()),
+            max_concurrent_turns: Some(1),
+            sort_order: Some(3),
+            ..WorkspaceSettings::default()
+        };
+        let source = entry("source", settings);
+        assert!(WorkspaceProfile::capture(" ", &source).is_err());
+        store
+            .save(WorkspaceProfile::capture(" safe ", &source).expect("capture"))
+            .expect("save");
+        let mut second = WorkspaceProfile::capture("open", &source).expect("capture");
+        second.settings.default_access_mode = Some("full-access".to_string());
+        store.save(second).expect("save");
+        store
+            .save(WorkspaceProfile::capture("safe", &source).expect("capture"))
+            .expect("overwrite");
+
+        let names: Vec<String> = store
+            .list()
+            .expect("list")
+            .into_iter()
+            .map(|profile| profile.name)
+            .collect();
+        assert_eq!(names, ["open", "safe"]);
+
+        let profile = store.get("safe").expect("profile");
+        assert_eq!(profile.codex_bin.as_deref(), Some("/opt/codex"));
+        let target = WorkspaceSettings {
+            sort_order: Some(9),
+            ..WorkspaceSettings::default()
+        };
+        let applied = profile.settings_for(&target);
+        assert_eq!(applied.default_access_mode.as_deref(), Some("read-only"));
+        assert_eq!(applied.max_concurrent_turns, Some(1));
+        assert_eq!(applied.sort_order, Some(9));
+        assert!(store.get("missing").is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,342 @@
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::process::Command;
+use tokio::time::timeout;
+
+use super::backend::app_server::build_codex_path_env;
+use super::backend::git_command::git_command;
+
+const GH_TIMEOUT: Duration = Duration::from_secs(60);
+const PUSH_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CreatedPullRequest {
+    pub(crate) url: String,
+    pub(crate) number: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CheckSummary {
+    pub(crate) passing: usize,
+    pub(crate) failing: usize,
+    pub(crate) pending: usize,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PullRequestStatus {
+    pub(crate) number: u64,
+    pub(crate) url: String,
+    pub(crate) title: String,
+    /// `OPEN`, `CLOSED` or `MERGED`, as reported by GitHub.
+    pub(crate) state: String,
+    pub(crate) is_draft: bool,
+    /// `APPROVED`, `CHANGES_REQUESTED`, `REVIEW_REQUIRED`, or `None`.
+    pub(crate) review_decision: Option<String>,
+    pub(crate) checks: CheckSummary,
+}
+
+/// Looks for `name` on the same PATH used to launch codex. On Windows that
+/// is the process PATH, since the codex PATH is joined with `:`, which
+/// drive letters already use.
+fn find_on_path(name: &str) -> Option<PathBuf> {
+    let path = if cfg!(windows) {
+        std::env::var("PATH").ok()?
+    } else {
+        build_codex_path_env(None)?
+    };
+    find_in_dirs(&path, name)
+}
+
+/// The executable `name` in the first of the `path`-style `dirs` holding
+/// it, with the platform's suffix, such as `gh.exe` on Windows.
+fn find_in_dirs(dirs: &str, name: &str) -> Option<PathBuf> {
+    let file_name = format!("{name}{}", std::env::consts::EXE_SUFFIX);
+    std::env::split_paths(dirs)
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| candidate.is_file())
+}
+
+fn is_gh_auth_error(detail: &str) -> bool {
+    let lower = detail.to_ascii_lowercase();
+    lower.contains("gh auth login")
+        || lower.contains("not logged in")
+        || lower.contains("authentication required")
+        || lower.contains("http 401")
+}
+
+fn is_no_pull_request_error(detail: &str) -> bool {
+    detail
+        .to_ascii_lowercase()
+        .contains("no pull requests found")
+}
+
+/// Captures the command's output and kills it once its `output` future is
+/// dropped, e.g. when `run_with_timeout` gives up on it.
+fn capture_output(command: &mut Command) {
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+}
+
+async fn run_with_timeout(
+    output: impl Future<Output = std::io::Result<std::process::Output>>,
+    limit: Duration,
+    what: &str,
+) -> Result<std::process::Output, String> {
+    match timeout(limit, output).await {
+        Ok(result) => result.map_err(|e| format!("Failed to run {what}: {e}")),
+        Err(_) => Err(format!("{what} timed out after {}s.", limit.as_secs())),
+    }
+}
+
+fn failure_detail(output: &std::process::Output) -> String {
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let detail = if stderr.trim().is_empty() {
+        stdout.trim()
+    } else {
+        stderr.trim()
+    };
+    detail.to_string()
+}
+
+async fn run_gh(cwd: &Path, args: &[&str]) -> Result<String, String> {
+    let gh = find_on_path("gh")
+        .ok_or("gh not found on PATH. Install the GitHub CLI to create pull requests.")?;
+    let mut command = Command::new(gh);
+    command
+        .args(args)
+        .current_dir(cwd)
+        .env("GH_PROMPT_DISABLED", "1");
+    if let Some(path_env) = build_codex_path_env(None) {
+        command.env("PATH", path_env);
+    }
+    capture_output(&mut command);
+    let output = run_with_timeout(command.output(), GH_TIMEOUT, "gh").await?;
+    if output.status.success() {
+        return Ok(String::from_utf8_lossy(&output.stdout).trim().to_string());
+    }
+    let detail = failure_detail(&output);
+    if is_gh_auth_error(&detail) {
+        return Err("gh not logged in. Run `gh auth login` on the daemon host.".to_string());
+    }
+    if detail.is_empty() {
+        Err("GitHub CLI command failed.".to_string())
+    } else {
+        Err(detail)
+    }
+}
+
+/// Pushes `branch` from the worktree and sets its upstream; never prompts.
+pub(crate) async fn push_with_upstream(
+    worktree_path: &Path,
+    remote: &str,
+    branch: &str,
+) -> Result<(), String> {
+    let mut command = git_command();
+    command
+        .args(["push", "--set-upstream", remote, branch])
+        .current_dir(worktree_path)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .env("GIT_SSH_COMMAND", "ssh -o BatchMode=yes");
+    capture_output(&mut command);
+    let output = run_with_timeout(command.output(), PUSH_TIMEOUT, "git push").await?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let detail = failure_detail(&output);
+    if detail.is_empty() {
+        Err("git push failed.".to_string())
+    } else {
+        Err(detail)
+    }
+}
+
+/// PR number from a URL like `https://github.com/org/repo/pull/42`.
+pub(crate) fn pull_request_number(url: &str) -> Option<u64> {
+    let (_, tail) = url.trim().rsplit_once("/pull/")?;
+    tail.split(['/', '#', '?']).next()?.parse().ok()
+}
+
+pub(crate) async fn create_pull_request(
+    repo_path: &Path,
+    branch: &str,
+    base: Option<&str>,
+    title: &str,
+    body: &str,
+    draft: bool,
+) -> Result<CreatedPullRequest, String> {
+    let mut args = vec![
+        "pr", "create", "--head", branch, "--title", title, "--body", body,
+    ];
+    if let Some(base) = base {
+        args.extend(["--base", base]);
+    }
+    if draft {
+        args.push("--draft");
+    }
+    let output = run_gh(repo_path, &args).await?;
+    // gh prints progress lines first and the PR URL last.
+    let url = output
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| line.starts_with("http"))
+        .ok_or_else(|| format!("Unexpected gh output: {output}"))?
+        .to_string();
+    Ok(CreatedPullRequest {
+        number: pull_request_number(&url),
+        url,
+    })
+}
+
+pub(crate) fn summarize_checks(rollup: &[Value]) -> CheckSummary {
+    let mut summary = CheckSummary::default();
+    for check in rollup {
+        // Check runs report `status`/`conclusion`; commit statuses report `state`.
+        let outcome = check
+            .get("conclusion")
+            .and_then(Value::as_str)
+            .filter(|value| !value.is_empty())
+            .or_else(|| check.get("state").and_then(Value::as_str))
+            .unwrap_or("");
+        match outcome.to_ascii_uppercase().as_str() {
+            "SUCCESS" | "NEUTRAL" | "SKIPPED" => summary.passing += 1,
+            "FAILURE" | "ERROR" | "CANCELLED" | "TIMED_OUT" | "ACTION_REQUIRED"
+            | "STARTUP_FAILURE" => summary.failing += 1,
+            _ => summary.pending += 1,
+        }
+    }
+    summary
+}
+
+pub(crate) fn parse_pull_request_view(output: &str) -> Result<PullRequestStatus, String> {
+    let value: Value = serde_json::from_str(output).map_err(|err| err.to_string())?;
+    let text = |key: &str| {
+        value
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let rollup = value
+        .get("statusCheckRollup")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    Ok(PullRequestStatus {
+        number: value.get("number").and_then(Value::as_u64).unwrap_or(0),
+        url: text("url"),
+        title: text("title"),
+        state: text("state"),
+        is_draft: value
+            .get("isDraft")
+            .and_then(Value::as_bool)
+            .unwrap_or(false),
+        review_decision: Some(text("reviewDecision")).filter(|value| !value.is_empty()),
+        checks: summarize_checks(&rollup),
+    })
+}
+
+/// Status of the PR for `branch`, or `None` when the branch has no PR.
+pub(crate) async fn pull_request_status(
+    repo_path: &Path,
+    branch: &str,
+) -> Result<Option<PullRequestStatus>, String> {
+    let result = run_gh(
+        repo_path,
+        &[
+            "pr",
+            "view",
+            branch,
+            "--json",
+            "number,url,title,state,isDraft,reviewDecision,statusCheckRollup",
+        ],
+    )
+    .await;
+    match result {
+        Ok(output) => parse_pull_request_view(&output).map(Some),
+        Err(error) if is_no_pull_request_error(&error) => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_in_dirs, is_gh_auth_error, parse_pull_request_view, pull_request_number};
+    use uuid::Uuid;
+
+    #[test]
+    fn finds_executables_with_the_platform_suffix() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-gh-{}", Uuid::new_v4()));
+        let (empty, bin) = (root.join("empty"), root.join("bin"));
+        std::fs::create_dir_all(&empty).expect("create empty dir");
+        std::fs::create_dir_all(&bin).expect("create bin dir");
+        let gh = bin.join(format!("gh{}", std::env::consts::EXE_SUFFIX));
+        std::fs::write(&gh, b"").expect("write gh");
+        let dirs = std::env::join_paths([&empty, &bin]).expect("join paths");
+        let dirs = dirs.to_str().expect("utf-8 path");
+
+        assert_eq!(find_in_dirs(dirs, "gh"), Some(gh));
+        assert_eq!(find_in_dirs(dirs, "glab"), None);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn parses_pull_request_numbers_from_urls() {
+        assert_eq!(
+            pull_request_number("https://github.com/org/repo/pull/42"),
+            Some(42)
+        );
+        assert_eq!(
+            pull_request_number("https://github.com/org/repo/pull/7/files"),
+            Some(7)
+        );
+        assert_eq!(pull_request_number("https://github.com/org/repo"), None);
+    }
+
+    #[test]
+    fn recognizes_auth_errors() {
+        assert!(is_gh_auth_error(
+            "To get started with GitHub CLI, please run:  gh auth login"
+        ));
+        assert!(is_gh_auth_error("HTTP 401: Bad credentials"));
+        assert!(!is_gh_auth_error("no pull requests found for branch \"x\""));
+    }
+
+    #[test]
+    fn summarizes_pull_request_view() {
+        let output = r#"{
+            "number": 12,
+            "url": "https://github.com/org/repo/pull/12",
+            "title": "Add thing",
+            "state": "OPEN",
+            "isDraft": true,
+            "reviewDecision": "",
+            "statusCheckRollup": [
+                {"__typename": "CheckRun", "status": "COMPLETED", "conclusion": "SUCCESS"},
+                {"__typename": "CheckRun", "status": "COMPLETED", "conclusion": "FAILURE"},
+                {"__typename": "CheckRun", "status": "IN_PROGRESS", "conclusion": ""},
+                {"__typename": "StatusContext", "state": "SUCCESS"}
+            ]
+        }"#;
+        let status = parse_pull_request_view(output).expect("status");
+        assert_eq!(status.number, 12);
+        assert!(status.is_draft);
+        assert_eq!(status.review_decision, None);
+        assert_eq!(status.checks.passing, 2);
+        assert_eq!(status.checks.failing, 1);
+        assert_eq!(status.checks.pending, 1);
+    }
+}
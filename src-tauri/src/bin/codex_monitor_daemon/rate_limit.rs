@@ -0,0 +1,213 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+pub(crate) const DEFAULT_REQUESTS_PER_SEC: f64 = 100.0;
+pub(crate) const DEFAULT_EXPENSIVE_REQUESTS_PER_SEC: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MethodClass {
+    Default,
+    /// Walks the workspace tree (file listings, searches).
+    FileWalk,
+    /// Talks to a git remote.
+    GitNetwork,
+}
+
+pub(crate) fn method_class(method: &str) -> MethodClass {
+    match method {
+        "list_workspace_files"
+        | "list_workspace_files_detailed"
+        | "list_workspace_files_delta"
+        | "discover_workspaces"
+        | "gc_worktrees"
+        | "data_dir_report"
+        | "disk_usage"
+        | "import_threads" => MethodClass::FileWalk,
+        "rename_worktree_upstream"
+        | "clone_workspace"
+        | "create_pull_request"
+        | "pull_request_status"
+        | "fetch_workspace" => MethodClass::GitNetwork,
+        _ => MethodClass::Default,
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RateLimitConfig {
+    pub(crate) enabled: bool,
+    /// Per-connection cap applied to every request.
+    pub(crate) requests_per_sec: f64,
+    /// Stricter per-connection cap for file walks and git network operations.
+    pub(crate) expensive_requests_per_sec: f64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            requests_per_sec: DEFAULT_REQUESTS_PER_SEC,
+            expensive_requests_per_sec: DEFAULT_EXPENSIVE_REQUESTS_PER_SEC,
+        }
+    }
+}
+
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    /// Buckets allow a burst of twice the sustained rate.
+    fn new(refill_per_sec: f64, now: Instant) -> Self {
+        let capacity = (refill_per_sec * 2.0).max(1.0);
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec,
+            updated_at: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.updated_at = now;
+    }
+
+    fn wait_time(&self) -> Option<Duration> {
+        if self.tokens >= 1.0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(
+            (1.0 - self.tokens) / self.refill_per_sec,
+        ))
+    }
+}
+
+pub(crate) struct ConnectionRateLimiter {
+    config: RateLimitConfig,
+    global: TokenBucket,
+    file_walk: TokenBucket,
+    git_network: TokenBucket,
+}
+
+impl ConnectionRateLimiter {
+    pub(crate) fn new(config: RateLimitConfig) -> Self {
+        Self::new_at(config, Instant::now())
+    }
+
+    fn new_at(config: RateLimitConfig, now: Instant) -> Self {
+        Self {
+            config,
+            global: TokenBucket::new(config.requests_per_sec, now),
+            file_walk: TokenBucket::new(config.expensive_requests_per_sec, now),
+            git_network: TokenBucket::new(config.expensive_requests_per_sec, now),
+        }
+    }
+
+    /// Takes a token for `method`, or returns how long the caller should wait.
+    /// Nothing is consumed when the request is rejected.
+    pub(crate) fn check(&mut self, method: &str) -> Result<(), Duration> {
+        self.check_at(method, Instant::now())
+    }
+
+    fn check_at(&mut self, method: &str, now: Instant) -> Result<(), Duration> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        self.global.refill(now);
+        let class = method_class(method);
+        let class_bucket = match class {
+            MethodClass::Default => None,
+            MethodClass::FileWalk => Some(&mut self.file_walk),
+            MethodClass::GitNetwork => Some(&mut self.git_network),
+        };
+        let class_wait = match class_bucket {
+            Some(bucket) => {
+                bucket.refill(now);
+                bucket.wait_time()
+            }
+            None => None,
+        };
+        let wait = match (self.global.wait_time(), class_wait) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        if let Some(wait) = wait {
+            return Err(wait);
+        }
+        self.global.tokens -= 1.0;
+        match class {
+            MethodClass::Default => {}
+            MethodClass::FileWalk => self.file_walk.tokens -= 1.0,
+            MethodClass::GitNetwork => self.git_network.tokens -= 1.0,
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ConnectionRateLimiter, RateLimitConfig};
+    use std::time::{Duration, Instant};
+
+    fn config(requests_per_sec: f64, expensive_requests_per_sec: f64) -> RateLimitConfig {
+        RateLimitConfig {
+            enabled: true,
+            requests_per_sec,
+            expensive_requests_per_sec,
+        }
+    }
+
+    #[test]
+    fn allows_burst_then_rejects_with_retry_hint() {
+        let start = Instant::now();
+        let mut limiter = ConnectionRateLimiter::new_at(config(1.0, 1.0), start);
+        assert!(limiter.check_at("ping", start).is_ok());
+        assert!(limiter.check_at("ping", start).is_ok());
+        let wait = limiter
+            .check_at("ping", start)
+            .expect_err("third call limited");
+        assert!(wait <= Duration::from_secs(1));
+        assert!(limiter
+            .check_at("ping", start + Duration::from_secs(1))
+            .is_ok());
+    }
+
+    #[test]
+    fn expensive_methods_use_their_own_bucket() {
+        let start = Instant::now();
+        let mut limiter = ConnectionRateLimiter::new_at(config(100.0, 0.5), start);
+        assert!(limiter.check_at("list_workspace_files", start).is_ok());
+        assert!(limiter.check_at("list_workspace_files", start).is_err());
+        assert!(limiter.check_at("list_workspaces", start).is_ok());
+    }
+
+    #[test]
+    fn rejected_requests_do_not_consume_tokens() {
+        let start = Instant::now();
+        let mut limiter = ConnectionRateLimiter::new_at(config(1.0, 0.5), start);
+        assert!(limiter.check_at("list_workspace_files", start).is_ok());
+        assert!(limiter.check_at("list_workspace_files", start).is_err());
+        assert!(limiter.check_at("ping", start).is_ok());
+    }
+
+    #[test]
+    fn disabled_limiter_allows_everything() {
+        let start = Instant::now();
+        let mut limiter = ConnectionRateLimiter::new_at(
+            RateLimitConfig {
+                enabled: false,
+                ..config(0.1, 0.1)
+            },
+            start,
+        );
+        for _ in 0..100 {
+            assert!(limiter.check_at("list_workspace_files", start).is_ok());
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use serde_json::Value;
+
+/// Error message prefix of requests a `--read-only` daemon refuses.
+pub(crate) const READ_ONLY_ERROR: &str = "READ_ONLY";
+
+/// Methods a `--read-only` daemon serves. Connecting a workspace only starts
+/// its codex session, which reading threads needs.
+const READ_METHODS: [&str; 48] = [
+    "account_rate_limits",
+    "branch_compare_url",
+    "collaboration_mode_list",
+    "conflict_status",
+    "connect_all_workspaces",
+    "connect_workspace",
+    "daemon_status",
+    "discover_workspaces",
+    "disk_usage",
+    "get_app_settings",
+    "get_codex_config_path",
+    "get_git_identity",
+    "get_operation",
+    "git_blame",
+    "is_workspace_path_dir",
+    "list_conflicts",
+    "list_sessions",
+    "list_tags",
+    "list_threads",
+    "list_trash",
+    "list_turn_artifacts",
+    "list_webhook_deliveries",
+    "list_webhooks",
+    "list_workspace_files",
+    "list_workspace_files_delta",
+    "list_workspace_files_detailed",
+    "list_workspace_profiles",
+    "list_workspaces",
+    "model_list",
+    "models_detailed",
+    "ping",
+    "ping_session",
+    "preflight_check",
+    "preview_message_policy",
+    "pull_request_status",
+    "read_turn_artifact",
+    "read_workspace_file",
+    "resume_thread",
+    "show_commit",
+    "skills_list",
+    "subscribe_thread",
+    "turn_inputs",
+    "unsubscribe_thread",
+    "wait_turn",
+    "workspace_activity",
+    "workspace_errors",
+    "workspace_remote_info",
+    "workspace_status",
+];
+
+fn flag(params: &Value, key: &str) -> bool {
+    params.get(key).and_then(Value::as_bool).unwrap_or(false)
+}
+
+/// Whether a request leaves the daemon's state, the workspaces and their
+/// repos as they are.
+pub(crate) fn is_read_request(method: &str, params: &Value) -> bool {
+    match method {
+        "data_dir_report" => params
+            .get("cleanup")
+            .and_then(Value::as_array)
+            .is_none_or(Vec::is_empty),
+        "gc_worktrees" => !flag(params, "apply"),
+        "health_check_all" => !flag(params, "reapDead"),
+        _ => READ_METHODS.contains(&method),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_read_request, READ_METHODS};
+    use serde_json::json;
+
+    #[test]
+    fn allows_reads_and_refuses_changes() {
+        let none = json!({});
+        assert!(is_read_request("list_threads", &none));
+        assert!(is_read_request("unsubscribe_thread", &none));
+        for method in [
+            "send_user_message",
+            "add_workspace",
+            "create_tag",
+            "purge_data",
+            "resume_last_thread",
+        ] {
+            assert!(!is_read_request(method, &none), "{method}");
+        }
+        assert!(is_read_request("gc_worktrees", &json!({ "apply": false })));
+        assert!(!is_read_request("gc_worktrees", &json!({ "apply": true })));
+        assert!(is_read_request(
+            "data_dir_report",
+            &json!({ "cleanup": [] })
+        ));
+        assert!(!is_read_request(
+            "data_dir_report",
+            &json!({ "cleanup": ["trash"] })
+        ));
+        assert!(!is_read_request(
+            "health_check_all",
+            &json!({ "reapDead": true })
+        ));
+
+        let mut sorted = READ_METHODS.to_vec();
+        sorted.sort_unstable();
+        assert_eq!(sorted, READ_METHODS);
+    }
+}
@@ -0,0 +1,138 @@
+use serde_json::{json, Value};
+
+use super::backend::codex_capabilities::UNSUPPORTED_METHOD_ERROR;
+use super::backend::git_command::{classify_git_error, GitErrorKind};
+
+/// Methods whose failures come from git, so their messages may be matched
+/// against git's output. Anything else (a codex reply, a file's contents)
+/// could merely quote a git error.
+const GIT_METHODS: &[&str] = &[
+    "abort_operation",
+    "add_worktree",
+    "branch_compare_url",
+    "cherry_pick",
+    "cherry_pick_abort",
+    "cherry_pick_commits",
+    "cherry_pick_continue",
+    "clone_workspace",
+    "conflict_status",
+    "continue_operation",
+    "create_pull_request",
+    "create_tag",
+    "discard_changes",
+    "fetch_workspace",
+    "gc_worktrees",
+    "get_git_identity",
+    "git_blame",
+    "list_conflicts",
+    "list_tags",
+    "mark_resolved",
+    "move_worktree",
+    "pull_request_status",
+    "remove_worktree",
+    "rename_worktree",
+    "rename_worktree_upstream",
+    "resolve_conflict",
+    "set_git_identity",
+    "show_commit",
+    "squash_worktree",
+    "workspace_remote_info",
+];
+
+/// An RPC failure; `code` and `data` let a method attach machine-readable
+/// details to the plain message.
+#[derive(Debug)]
+pub(crate) struct RpcError {
+    pub(crate) message: String,
+    pub(crate) code: Option<&'static str>,
+    pub(crate) data: Option<Value>,
+}
+
+impl From<String> for RpcError {
+    fn from(message: String) -> Self {
+        let code = message
+            .contains(UNSUPPORTED_METHOD_ERROR)
+            .then_some("codex_unsupported");
+        Self {
+            message,
+            code,
+            data: None,
+        }
+    }
+}
+
+impl From<&str> for RpcError {
+    fn from(message: &str) -> Self {
+        message.to_string().into()
+    }
+}
+
+impl RpcError {
+    /// Tags failures of git methods that `classify_git_error` recognizes
+    /// with a `git_*` code and git's output as `data.detail`; other messages
+    /// pass through.
+    pub(crate) fn classified(method: &str, message: String) -> Self {
+        if !GIT_METHODS.contains(&method) {
+            return message.into();
+        }
+        let Some(kind) = classify_git_error(&message) else {
+            return message.into();
+        };
+        Self {
+            code: Some(git_error_code(kind)),
+            data: Some(json!({ "detail": message })),
+            message,
+        }
+    }
+}
+
+fn git_error_code(kind: GitErrorKind) -> &'static str {
+    match kind {
+        GitErrorKind::NotAWorkingTree => "git_not_a_working_tree",
+        GitErrorKind::BranchExists => "git_branch_exists",
+        GitErrorKind::NonFastForward => "git_non_fast_forward",
+        GitErrorKind::DirtyWorkTree => "git_dirty_work_tree",
+        GitErrorKind::AuthFailed => "git_auth_failed",
+        GitErrorKind::LockHeld => "git_lock_held",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RpcError;
+    use crate::backend::codex_capabilities::SessionCapabilities;
+    use serde_json::json;
+
+    #[test]
+    fn codex_refusals_are_codex_unsupported() {
+        let mut capabilities = SessionCapabilities::new(Some("codex-cli 0.41.0".to_string()));
+        let unknown = json!({ "id": 3, "error": { "code": -32601, "message": "not found" } });
+        let message = capabilities
+            .record_response("review/start", &unknown)
+            .expect_err("unknown method");
+
+        let error = RpcError::from(message.clone());
+        assert_eq!(error.code, Some("codex_unsupported"));
+        let error = RpcError::classified("start_review", message.clone());
+        assert_eq!(error.code, Some("codex_unsupported"));
+        assert_eq!(error.message, message);
+
+        let checked = capabilities.check("review/start").expect_err("refused");
+        assert_eq!(RpcError::from(checked).code, Some("codex_unsupported"));
+        assert_eq!(RpcError::from("workspace not found").code, None);
+    }
+
+    #[test]
+    fn git_failures_get_git_codes() {
+        let message = "fatal: a branch named 'feature' already exists";
+        let error = RpcError::classified("add_worktree", message.to_string());
+        assert_eq!(error.code, Some("git_branch_exists"));
+        assert_eq!(error.data, Some(json!({ "detail": message })));
+
+        let error = RpcError::classified("read_workspace_file", message.to_string());
+        assert_eq!(error.code, None);
+        assert_eq!(error.data, None);
+        let error = RpcError::classified("send_user_message", message.to_string());
+        assert_eq!(error.code, None);
+    }
+}
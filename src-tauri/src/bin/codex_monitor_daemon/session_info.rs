@@ -0,0 +1,67 @@
+use std::time::Instant;
+
+use serde::Serialize;
+
+use super::fetch::now_ms;
+
+/// One running codex session, as `list_sessions` reports it.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SessionInfo {
+    pub(crate) workspace_id: String,
+    pub(crate) name: String,
+    /// `None` once the process has exited but the session isn't dropped yet.
+    pub(crate) pid: Option<u32>,
+    /// ms since the epoch.
+    pub(crate) started_at: Option<u64>,
+    pub(crate) uptime_secs: Option<u64>,
+    /// ms since the epoch of the last request or event.
+    pub(crate) last_activity_at: Option<u64>,
+    pub(crate) active_turns: u32,
+    pub(crate) queued_turns: u32,
+    /// Requests sent to the app-server that haven't been answered.
+    pub(crate) pending_requests: usize,
+    /// Approvals and other app-server requests waiting for a client.
+    pub(crate) open_server_requests: usize,
+    /// Resident memory of the codex process, where the platform reports it.
+    pub(crate) memory_bytes: Option<u64>,
+}
+
+/// When `at` was, in ms since the epoch.
+pub(crate) fn instant_ms(at: Instant) -> u64 {
+    let elapsed = u64::try_from(at.elapsed().as_millis()).unwrap_or(u64::MAX);
+    now_ms().saturating_sub(elapsed)
+}
+
+/// Resident set size of a process, read from `/proc` on Linux.
+pub(crate) fn resident_memory_bytes(pid: u32) -> Option<u64> {
+    if cfg!(not(target_os = "linux")) {
+        return None;
+    }
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    parse_vm_rss(&status)
+}
+
+fn parse_vm_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_vm_rss;
+
+    #[test]
+    fn reads_resident_memory_from_proc_status() {
+        let status = "Name:\tcodex\nVmPeak:\t  204800 kB\nVmRSS:\t   51200 kB\nThreads:\t8\n";
+        assert_eq!(parse_vm_rss(status), Some(51_200 * 1024));
+        assert_eq!(parse_vm_rss("Name:\tcodex\n"), None);
+    }
+}
@@ -0,0 +1,127 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use super::run_git_command;
+
+/// Cap for the patch text returned by `show_commit`.
+pub(crate) const MAX_COMMIT_DIFF_BYTES: usize = 400_000;
+
+/// `git show` pretty format; fields are NUL-separated so subjects and bodies
+/// may contain anything but NUL.
+const COMMIT_FORMAT: &str = "%H%x00%P%x00%an%x00%ae%x00%at%x00%cn%x00%ce%x00%ct%x00%s%x00%b";
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CommitDetails {
+    pub(crate) hash: String,
+    pub(crate) parents: Vec<String>,
+    pub(crate) author_name: String,
+    pub(crate) author_email: String,
+    /// Seconds since the epoch.
+    pub(crate) author_time: i64,
+    pub(crate) committer_name: String,
+    pub(crate) committer_email: String,
+    pub(crate) commit_time: i64,
+    pub(crate) subject: String,
+    pub(crate) body: String,
+    pub(crate) diff: String,
+    pub(crate) diff_truncated: bool,
+}
+
+fn parse_commit_metadata(output: &str) -> Option<CommitDetails> {
+    let fields: Vec<&str> = output.splitn(10, '\0').collect();
+    let [hash, parents, author_name, author_email, author_time, committer_name, committer_email, commit_time, subject, body] =
+        fields.as_slice()
+    else {
+        return None;
+    };
+    Some(CommitDetails {
+        hash: hash.trim().to_string(),
+        parents: parents.split_whitespace().map(str::to_string).collect(),
+        author_name: author_name.to_string(),
+        author_email: author_email.to_string(),
+        author_time: author_time.trim().parse().unwrap_or_default(),
+        committer_name: committer_name.to_string(),
+        committer_email: committer_email.to_string(),
+        commit_time: commit_time.trim().parse().unwrap_or_default(),
+        subject: subject.to_string(),
+        body: body.trim_end().to_string(),
+        diff: String::new(),
+        diff_truncated: false,
+    })
+}
+
+/// Cuts `text` to at most `limit` bytes on a character boundary.
+fn truncate_diff(mut text: String, limit: usize) -> (String, bool) {
+    if text.len() <= limit {
+        return (text, false);
+    }
+    let mut end = limit;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    text.truncate(end);
+    (text, true)
+}
+
+pub(crate) async fn show_commit(repo_path: &PathBuf, hash: &str) -> Result<CommitDetails, String> {
+    let hash = hash.trim();
+    if hash.is_empty() || hash.starts_with('-') {
+        return Err(format!("unknown commit: {hash}"));
+    }
+    let spec = format!("{hash}^{{commit}}");
+    let full = run_git_command(repo_path, &["rev-parse", "--verify", "--quiet", &spec])
+        .await
+        .map_err(|_| format!("unknown commit: {hash}"))?;
+
+    let format = format!("--format={COMMIT_FORMAT}");
+    let metadata = run_git_command(repo_path, &["show", "-s", &format, &full]).await?;
+    let mut details = parse_commit_metadata(&metadata).ok_or("Failed to parse commit metadata.")?;
+    // Merges diff against their first parent, i.e. what they brought in.
+    let diff = run_git_command(
+        repo_path,
+        &[
+            "show",
+            "--format=",
+            "--patch",
+            "-m",
+            "--first-parent",
+            &full,
+        ],
+    )
+    .await?;
+    let (diff, diff_truncated) = truncate_diff(diff, MAX_COMMIT_DIFF_BYTES);
+    details.diff = diff;
+    details.diff_truncated = diff_truncated;
+    Ok(details)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_commit_metadata, truncate_diff};
+
+    #[test]
+    fn parses_nul_separated_metadata() {
+        let output = "abc123\0p1 p2\0Ada\0ada@example.com\x001700000000\0Bot\0bot@example.com\x001700000100\0Fix things\0Longer body\n\nwith lines\n";
+        let details = parse_commit_metadata(output).expect("metadata");
+        assert_eq!(details.hash, "abc123");
+        assert_eq!(details.parents, vec!["p1".to_string(), "p2".to_string()]);
+        assert_eq!(details.author_name, "Ada");
+        assert_eq!(details.author_time, 1_700_000_000);
+        assert_eq!(details.committer_email, "bot@example.com");
+        assert_eq!(details.commit_time, 1_700_000_100);
+        assert_eq!(details.subject, "Fix things");
+        assert_eq!(details.body, "Longer body\n\nwith lines");
+        assert!(parse_commit_metadata("abc\0only").is_none());
+    }
+
+    #[test]
+    fn truncates_on_char_boundaries() {
+        assert_eq!(
+            truncate_diff("abc".to_string(), 10),
+            ("abc".to_string(), false)
+        );
+        assert_eq!(truncate_diff("aéb".to_string(), 2), ("a".to_string(), true));
+    }
+}
@@ -0,0 +1,88 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Saves JSON snapshots of a store to one file. Inside a tokio runtime the
+/// file is written on a blocking thread, and a slow write of an older
+/// snapshot never replaces a newer one.
+pub(crate) struct SnapshotFile {
+    path: PathBuf,
+    /// What the file holds, for the stderr message of a failed write.
+    what: &'static str,
+    /// Bumped with every snapshot; numbers the snapshots being written.
+    version: Mutex<u64>,
+    /// The version on disk.
+    written: Arc<Mutex<u64>>,
+}
+
+impl SnapshotFile {
+    pub(crate) fn new(path: PathBuf, what: &'static str) -> Self {
+        Self {
+            path,
+            what,
+            version: Mutex::new(0),
+            written: Arc::new(Mutex::new(0)),
+        }
+    }
+
+    /// Writes `data`. Callers hold the lock of the store it was taken from,
+    /// so snapshots are numbered in the order their changes happened.
+    pub(crate) fn save(&self, data: String) -> Result<(), String> {
+        let version = {
+            let mut version = self.version.lock().unwrap_or_else(|err| err.into_inner());
+            *version += 1;
+            *version
+        };
+        let path = self.path.clone();
+        let written = Arc::clone(&self.written);
+        let write = move || write_snapshot(&path, &data, version, &written);
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                let what = self.what;
+                runtime.spawn_blocking(move || {
+                    if let Err(err) = write() {
+                        eprintln!("codex-monitor-daemon: failed to save {what}: {err}");
+                    }
+                });
+                Ok(())
+            }
+            Err(_) => write(),
+        }
+    }
+}
+
+/// Writes snapshot `version` unless a newer one is already on disk.
+fn write_snapshot(
+    path: &Path,
+    data: &str,
+    version: u64,
+    written: &Mutex<u64>,
+) -> Result<(), String> {
+    let mut written = written.lock().unwrap_or_else(|err| err.into_inner());
+    if *written >= version {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(path, data).map_err(|e| e.to_string())?;
+    *written = version;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::write_snapshot;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    #[test]
+    fn older_snapshots_never_replace_newer_ones() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-snapshot-{}", Uuid::new_v4()));
+        let path = dir.join("store.json");
+        let written = Mutex::new(0);
+        assert_eq!(write_snapshot(&path, "second", 2, &written), Ok(()));
+        assert_eq!(write_snapshot(&path, "first", 1, &written), Ok(()));
+        assert_eq!(std::fs::read_to_string(&path).expect("read"), "second");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
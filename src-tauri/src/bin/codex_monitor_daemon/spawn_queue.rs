@@ -0,0 +1,160 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{Notify, Semaphore, SemaphorePermit};
+
+/// Codex processes started at once unless `--max-concurrent-spawns` says
+/// otherwise.
+pub(crate) const DEFAULT_MAX_CONCURRENT_SPAWNS: usize = 2;
+
+struct Waiting {
+    ticket: u64,
+    workspace_id: String,
+    cancel: Arc<Notify>,
+}
+
+/// Line of session spawns waiting for one of a fixed number of slots, so
+/// connecting many workspaces doesn't start every codex process at once.
+pub(crate) struct SpawnQueue {
+    permits: Semaphore,
+    next_ticket: AtomicU64,
+    waiting: Mutex<Vec<Waiting>>,
+}
+
+/// A place in the spawn queue; `wait` turns it into a slot. Dropping it,
+/// or the `wait` future, gives the place up.
+pub(crate) struct SpawnTicket<'a> {
+    queue: &'a SpawnQueue,
+    ticket: u64,
+    cancel: Arc<Notify>,
+    /// 1 for the first spawn waiting.
+    pub(crate) position: usize,
+}
+
+impl SpawnQueue {
+    pub(crate) fn new(max_concurrent: usize) -> Self {
+        Self {
+            permits: Semaphore::new(max_concurrent.max(1)),
+            next_ticket: AtomicU64::new(0),
+            waiting: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn enqueue(&self, workspace_id: &str) -> SpawnTicket<'_> {
+        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+        let cancel = Arc::new(Notify::new());
+        let mut waiting = self.waiting.lock().unwrap_or_else(|err| err.into_inner());
+        waiting.push(Waiting {
+            ticket,
+            workspace_id: workspace_id.to_string(),
+            cancel: Arc::clone(&cancel),
+        });
+        SpawnTicket {
+            queue: self,
+            ticket,
+            cancel,
+            position: waiting.len(),
+        }
+    }
+
+    /// Cancels the workspace's spawns that haven't started; returns whether
+    /// there were any.
+    pub(crate) fn cancel(&self, workspace_id: &str) -> bool {
+        let mut waiting = self.waiting.lock().unwrap_or_else(|err| err.into_inner());
+        let mut cancelled = false;
+        waiting.retain(|entry| {
+            if entry.workspace_id != workspace_id {
+                return true;
+            }
+            entry.cancel.notify_one();
+            cancelled = true;
+            false
+        });
+        cancelled
+    }
+
+    /// Workspaces with a spawn waiting, first in line first.
+    pub(crate) fn waiting_ids(&self) -> Vec<String> {
+        let waiting = self.waiting.lock().unwrap_or_else(|err| err.into_inner());
+        waiting
+            .iter()
+            .map(|entry| entry.workspace_id.clone())
+            .collect()
+    }
+
+    /// False when the ticket was already cancelled.
+    fn leave(&self, ticket: u64) -> bool {
+        let mut waiting = self.waiting.lock().unwrap_or_else(|err| err.into_inner());
+        let before = waiting.len();
+        waiting.retain(|entry| entry.ticket != ticket);
+        waiting.len() < before
+    }
+}
+
+impl<'a> SpawnTicket<'a> {
+    /// Waits for a free slot, held until the permit is dropped. `None` when
+    /// the spawn was cancelled first.
+    pub(crate) async fn wait(self) -> Option<SemaphorePermit<'a>> {
+        let permit = tokio::select! {
+            biased;
+            _ = self.cancel.notified() => None,
+            permit = self.queue.permits.acquire() => permit.ok(),
+        };
+        // A cancel that raced the permit still wins.
+        if !self.queue.leave(self.ticket) {
+            return None;
+        }
+        permit
+    }
+}
+
+impl Drop for SpawnTicket<'_> {
+    fn drop(&mut self) {
+        self.queue.leave(self.ticket);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SpawnQueue;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn spawns_wait_for_a_slot_and_can_be_cancelled() {
+        let queue = SpawnQueue::new(1);
+        let first = queue.enqueue("a").wait().await.expect("free slot");
+
+        let second = queue.enqueue("b");
+        let third = queue.enqueue("c");
+        assert_eq!((second.position, third.position), (1, 2));
+        assert_eq!(queue.waiting_ids(), ["b", "c"]);
+
+        assert!(queue.cancel("c"));
+        assert!(!queue.cancel("c"));
+        assert!(third.wait().await.is_none());
+        assert_eq!(queue.waiting_ids(), ["b"]);
+
+        let second = second.wait();
+        tokio::pin!(second);
+        assert!(tokio::time::timeout(Duration::from_millis(20), &mut second)
+            .await
+            .is_err());
+        drop(first);
+        assert!(second.await.is_some());
+        assert!(queue.waiting_ids().is_empty());
+    }
+
+    #[tokio::test]
+    async fn dropped_spawns_leave_the_line() {
+        let queue = SpawnQueue::new(1);
+        let _slot = queue.enqueue("a").wait().await.expect("free slot");
+
+        drop(queue.enqueue("b"));
+        let waiting = queue.enqueue("c").wait();
+        assert!(tokio::time::timeout(Duration::from_millis(20), waiting)
+            .await
+            .is_err());
+        assert!(queue.waiting_ids().is_empty());
+        assert_eq!(queue.enqueue("d").position, 1);
+    }
+}
@@ -0,0 +1,134 @@
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use super::git_state::ensure_no_git_operation;
+use super::run_git_command;
+
+/// Namespace for pre-squash heads; outside `refs/heads` so they stay out of
+/// branch listings but keep the old commits reachable.
+const BACKUP_REF_PREFIX: &str = "refs/codexmonitor/backup-";
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct SquashResult {
+    pub(crate) commit: String,
+    pub(crate) squashed: usize,
+    pub(crate) base: String,
+    /// Points at the pre-squash head; `git reset --hard <backupRef>` undoes it.
+    pub(crate) backup_ref: String,
+}
+
+pub(crate) fn default_squash_message(branch: &str, subjects: &[String]) -> String {
+    let noun = if subjects.len() == 1 {
+        "commit"
+    } else {
+        "commits"
+    };
+    let mut message = format!("Squash {} {noun} from {branch}\n", subjects.len());
+    if !subjects.is_empty() {
+        message.push('\n');
+        for subject in subjects {
+            message.push_str("- ");
+            message.push_str(subject);
+            message.push('\n');
+        }
+    }
+    message
+}
+
+fn backup_ref_name() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default();
+    format!("{BACKUP_REF_PREFIX}{millis}")
+}
+
+/// Squashes the worktree's commits since its merge-base with `parent_head`
+/// into one commit.
+pub(crate) async fn squash_worktree(
+    worktree_path: &PathBuf,
+    branch: &str,
+    parent_head: &str,
+    message: Option<String>,
+    force: bool,
+) -> Result<SquashResult, String> {
+    ensure_no_git_operation(worktree_path, "squash")?;
+    let dirty = run_git_command(
+        worktree_path,
+        &["status", "--porcelain", "--untracked-files=no"],
+    )
+    .await?;
+    if !dirty.is_empty() {
+        return Err("Commit or discard uncommitted changes before squashing.".to_string());
+    }
+
+    let head = run_git_command(worktree_path, &["rev-parse", "HEAD"]).await?;
+    let base = run_git_command(worktree_path, &["merge-base", "HEAD", parent_head]).await?;
+    let range = format!("{base}..HEAD");
+    let squashed: usize = run_git_command(worktree_path, &["rev-list", "--count", &range])
+        .await?
+        .parse()
+        .unwrap_or_default();
+    if squashed == 0 {
+        return Err("No commits to squash.".to_string());
+    }
+    if !force {
+        let unpushed: usize = run_git_command(
+            worktree_path,
+            &["rev-list", "--count", &range, "--not", "--remotes"],
+        )
+        .await?
+        .parse()
+        .unwrap_or_default();
+        if unpushed < squashed {
+            return Err(
+                "Some of these commits were already pushed; pass force to squash anyway."
+                    .to_string(),
+            );
+        }
+    }
+
+    let message = match message.filter(|message| !message.trim().is_empty()) {
+        Some(message) => message,
+        None => {
+            let subjects =
+                run_git_command(worktree_path, &["log", "--reverse", "--format=%s", &range])
+                    .await?;
+            let subjects: Vec<String> = subjects.lines().map(str::to_string).collect();
+            default_squash_message(branch, &subjects)
+        }
+    };
+
+    let backup_ref = backup_ref_name();
+    // The empty old value makes git refuse to overwrite an existing ref.
+    run_git_command(worktree_path, &["update-ref", &backup_ref, &head, ""]).await?;
+    run_git_command(worktree_path, &["reset", "--soft", &base]).await?;
+    if let Err(error) = run_git_command(worktree_path, &["commit", "-m", &message]).await {
+        let _ = run_git_command(worktree_path, &["reset", "--soft", &head]).await;
+        return Err(error);
+    }
+    let commit = run_git_command(worktree_path, &["rev-parse", "HEAD"]).await?;
+    Ok(SquashResult {
+        commit,
+        squashed,
+        base,
+        backup_ref,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::default_squash_message;
+
+    #[test]
+    fn default_message_lists_subjects() {
+        let subjects = vec!["wip".to_string(), "fix tests".to_string()];
+        assert_eq!(
+            default_squash_message("feature/x", &subjects),
+            "Squash 2 commits from feature/x\n\n- wip\n- fix tests\n"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use super::run_git_command;
+
+/// `git tag` format; `*objectname` is the peeled commit of annotated tags.
+const TAG_FORMAT: &str =
+    "--format=%(refname:strip=2)%00%(objecttype)%00%(objectname)%00%(*objectname)";
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TagInfo {
+    pub(crate) name: String,
+    /// The commit the tag points at (peeled for annotated tags).
+    pub(crate) commit: String,
+    pub(crate) annotated: bool,
+}
+
+fn parse_tag_line(line: &str) -> Option<TagInfo> {
+    let mut fields = line.split('\0');
+    let name = fields.next()?.trim();
+    let object_type = fields.next()?;
+    let object = fields.next()?;
+    let peeled = fields.next().unwrap_or_default();
+    if name.is_empty() {
+        return None;
+    }
+    let annotated = object_type == "tag";
+    let commit = if annotated && !peeled.is_empty() {
+        peeled
+    } else {
+        object
+    };
+    Some(TagInfo {
+        name: name.to_string(),
+        commit: commit.to_string(),
+        annotated,
+    })
+}
+
+pub(crate) async fn list_tags(
+    repo_path: &PathBuf,
+    pattern: Option<&str>,
+) -> Result<Vec<TagInfo>, String> {
+    let mut args = vec!["tag", "--list", TAG_FORMAT];
+    if let Some(pattern) = pattern.map(str::trim).filter(|pattern| !pattern.is_empty()) {
+        if pattern.starts_with('-') {
+            return Err(format!("Invalid tag pattern: {pattern}"));
+        }
+        args.push(pattern);
+    }
+    let output = run_git_command(repo_path, &args).await?;
+    Ok(output.lines().filter_map(parse_tag_line).collect())
+}
+
+async fn find_tag(repo_path: &PathBuf, name: &str) -> Result<Option<TagInfo>, String> {
+    let reference = format!("refs/tags/{name}");
+    let output = run_git_command(repo_path, &["for-each-ref", TAG_FORMAT, &reference]).await?;
+    Ok(output.lines().find_map(parse_tag_line))
+}
+
+pub(crate) async fn create_tag(
+    repo_path: &PathBuf,
+    name: &str,
+    message: Option<&str>,
+    target: Option<&str>,
+) -> Result<TagInfo, String> {
+    let name = name.trim();
+    let reference = format!("refs/tags/{name}");
+    if name.is_empty()
+        || name.starts_with('-')
+        || run_git_command(repo_path, &["check-ref-format", &reference])
+            .await
+            .is_err()
+    {
+        return Err(format!("Invalid tag name: {name}"));
+    }
+    if find_tag(repo_path, name).await?.is_some() {
+        return Err(format!("Tag {name} already exists."));
+    }
+    let target = target
+        .map(str::trim)
+        .filter(|target| !target.is_empty())
+        .unwrap_or("HEAD");
+    if target.starts_with('-') {
+        return Err(format!("unknown commit: {target}"));
+    }
+    let spec = format!("{target}^{{commit}}");
+    let commit = run_git_command(repo_path, &["rev-parse", "--verify", "--quiet", &spec])
+        .await
+        .map_err(|_| format!("unknown commit: {target}"))?;
+
+    let mut args = vec!["tag"];
+    if let Some(message) = message.map(str::trim).filter(|message| !message.is_empty()) {
+        args.extend(["-a", "-m", message]);
+    }
+    args.extend([name, commit.as_str()]);
+    run_git_command(repo_path, &args).await?;
+    find_tag(repo_path, name)
+        .await?
+        .ok_or_else(|| format!("Tag {name} was not created."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_tag_line, TagInfo};
+
+    #[test]
+    fn parses_lightweight_and_annotated_tags() {
+        assert_eq!(
+            parse_tag_line("v1.0\0commit\0abc\0"),
+            Some(TagInfo {
+                name: "v1.0".to_string(),
+                commit: "abc".to_string(),
+                annotated: false,
+            })
+        );
+        assert_eq!(
+            parse_tag_line("release/2\0tag\0t123\0def"),
+            Some(TagInfo {
+                name: "release/2".to_string(),
+                commit: "def".to_string(),
+                annotated: true,
+            })
+        );
+        assert_eq!(parse_tag_line(""), None);
+    }
+}
@@ -0,0 +1,604 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::comparable_path;
+use super::thread_list::ThreadPage;
+
+/// `thread/list` pages read to learn which threads the app-server knows.
+pub(crate) const MAX_LISTED_PAGES: usize = 50;
+/// Rollout files looked at per import, newest first.
+const MAX_SCANNED_ROLLOUTS: usize = 5000;
+/// Lines read from a rollout while looking for its first user message.
+const MAX_SCANNED_LINES: usize = 200;
+const MAX_PREVIEW_CHARS: usize = 200;
+
+/// A thread found in a rollout file of CODEX_HOME rather than through
+/// `thread/list`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ImportedThread {
+    pub(crate) id: String,
+    /// The rollout file, which `resume_thread` resumes from.
+    pub(crate) path: String,
+    pub(crate) cwd: String,
+    /// Seconds since the epoch, like the app-server's `createdAt`.
+    pub(crate) created_at: Option<i64>,
+    pub(crate) preview: String,
+    /// Which client wrote the rollout, e.g. `cli`.
+    pub(crate) source: Option<String>,
+}
+
+impl ImportedThread {
+    /// The thread object `list_threads` returns for it.
+    pub(crate) fn to_thread(&self) -> Value {
+        json!({
+            "id": self.id,
+            "preview": self.preview,
+            "cwd": self.cwd,
+            "path": self.path,
+            "createdAt": self.created_at,
+            "source": self.source,
+            "imported": true,
+        })
+    }
+}
+
+/// Marks a daemon `list_threads` cursor; any other cursor is the
+/// app-server's own.
+const CURSOR_PREFIX: &str = "imported:";
+
+/// Where `list_threads` stopped: the app-server page to read next and how
+/// much of it, and the imported threads, were already returned.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ListPosition {
+    /// The app-server cursor, `None` for its first page.
+    pub(crate) listed: Option<String>,
+    /// Threads of that page that were already returned.
+    pub(crate) skip: usize,
+    /// The app-server has no more threads; only imported ones are left.
+    pub(crate) listed_done: bool,
+    /// The last thread returned.
+    after: Option<Position>,
+}
+
+impl ListPosition {
+    fn into_cursor(self, has_imported: bool) -> String {
+        if self.skip == 0 && !self.listed_done && !has_imported {
+            if let Some(listed) = self.listed {
+                return listed;
+            }
+        }
+        let data = serde_json::to_vec(&self).unwrap_or_default();
+        format!("{CURSOR_PREFIX}{}", URL_SAFE_NO_PAD.encode(data))
+    }
+}
+
+/// A thread's place in the merged list. Imported threads go after listed
+/// threads created in the same second, ordered by id.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct Position {
+    created_at: i64,
+    /// Set when the thread is an imported one.
+    imported_id: Option<String>,
+}
+
+impl Position {
+    /// Whether `thread` comes after this position, so it wasn't returned yet.
+    fn precedes(&self, thread: &ImportedThread) -> bool {
+        let created = created_at(thread);
+        match &self.imported_id {
+            _ if created != self.created_at => created < self.created_at,
+            Some(id) => thread.id > *id,
+            None => true,
+        }
+    }
+}
+
+fn created_at(thread: &ImportedThread) -> i64 {
+    thread.created_at.unwrap_or(i64::MIN)
+}
+
+fn listed_created_at(thread: &Value) -> i64 {
+    thread
+        .get("createdAt")
+        .and_then(Value::as_i64)
+        .unwrap_or(i64::MIN)
+}
+
+/// Reads a `list_threads` cursor. App-server cursors pass through.
+pub(crate) fn list_position(cursor: Option<&str>) -> Result<ListPosition, String> {
+    let Some(cursor) = cursor else {
+        return Ok(ListPosition::default());
+    };
+    let Some(encoded) = cursor.strip_prefix(CURSOR_PREFIX) else {
+        return Ok(ListPosition {
+            listed: Some(cursor.to_string()),
+            ..ListPosition::default()
+        });
+    };
+    URL_SAFE_NO_PAD
+        .decode(encoded)
+        .ok()
+        .and_then(|data| serde_json::from_slice(&data).ok())
+        .ok_or_else(|| "invalid cursor".to_string())
+}
+
+/// Rollout files under `<codex-home>/sessions`, newest first; their names
+/// start with the time they were created.
+fn rollout_files(codex_home: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut pending = vec![codex_home.join("sessions")];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if file_type.is_file() && name.starts_with("rollout-") && name.ends_with(".jsonl") {
+                files.push(path);
+            }
+        }
+    }
+    files.sort_by(|a, b| b.file_name().cmp(&a.file_name()));
+    files.truncate(MAX_SCANNED_ROLLOUTS);
+    files
+}
+
+/// Reads the `session_meta` line a rollout starts with, and its first user
+/// message as the preview. Rollouts without an id and cwd, and those of
+/// sub-agents, are skipped.
+fn read_rollout(path: &Path) -> Option<ImportedThread> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut thread: Option<ImportedThread> = None;
+    for line in BufReader::new(file).lines().take(MAX_SCANNED_LINES) {
+        let Ok(line) = line else {
+            break;
+        };
+        let Ok(record) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        let payload = record.get("payload").unwrap_or(&Value::Null);
+        match record.get("type").and_then(Value::as_str) {
+            Some("session_meta") if thread.is_none() => {
+                let source = payload.get("source");
+                if source.is_some_and(|source| !source.is_string()) {
+                    return None;
+                }
+                thread = Some(ImportedThread {
+                    id: payload.get("id")?.as_str()?.to_string(),
+                    path: path.to_string_lossy().to_string(),
+                    cwd: payload.get("cwd")?.as_str()?.to_string(),
+                    created_at: payload
+                        .get("timestamp")
+                        .and_then(Value::as_str)
+                        .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+                        .map(|at| at.timestamp()),
+                    preview: String::new(),
+                    source: source.and_then(Value::as_str).map(str::to_string),
+                });
+            }
+            Some("event_msg")
+                if payload.get("type").and_then(Value::as_str) == Some("user_message") =>
+            {
+                let thread = thread.as_mut()?;
+                let message = payload.get("message").and_then(Value::as_str);
+                thread.preview = message
+                    .unwrap_or_default()
+                    .chars()
+                    .take(MAX_PREVIEW_CHARS)
+                    .collect();
+                break;
+            }
+            _ => {}
+        }
+    }
+    thread
+}
+
+/// Threads in the rollouts of `codex_home` that ran in `workspace_path` and
+/// aren't `known`, newest first, with the number of rollouts read.
+pub(crate) fn find_importable(
+    codex_home: &Path,
+    workspace_path: &Path,
+    known: &HashSet<String>,
+) -> (usize, Vec<ImportedThread>) {
+    let workspace_path = comparable_path(workspace_path);
+    let files = rollout_files(codex_home);
+    let mut seen = HashSet::new();
+    let threads = files
+        .iter()
+        .filter_map(|file| read_rollout(file))
+        .filter(|thread| !known.contains(&thread.id) && seen.insert(thread.id.clone()))
+        .filter(|thread| comparable_path(Path::new(&thread.cwd)) == workspace_path)
+        .collect();
+    (files.len(), threads)
+}
+
+/// Threads imported per workspace, kept in `imported_threads.json`.
+pub(crate) struct ImportedThreads {
+    path: PathBuf,
+    threads: Mutex<HashMap<String, Vec<ImportedThread>>>,
+}
+
+impl ImportedThreads {
+    pub(crate) fn load(path: PathBuf) -> Self {
+        let threads = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            threads: Mutex::new(threads),
+        }
+    }
+
+    pub(crate) fn ids(&self, workspace_id: &str) -> HashSet<String> {
+        let threads = self.threads.lock().unwrap_or_else(|err| err.into_inner());
+        threads
+            .get(workspace_id)
+            .map(|imported| imported.iter().map(|thread| thread.id.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn find(&self, workspace_id: &str, thread_id: &str) -> Option<ImportedThread> {
+        let threads = self.threads.lock().unwrap_or_else(|err| err.into_inner());
+        threads
+            .get(workspace_id)?
+            .iter()
+            .find(|thread| thread.id == thread_id)
+            .cloned()
+    }
+
+    pub(crate) fn add(&self, workspace_id: &str, added: &[ImportedThread]) -> Result<(), String> {
+        if added.is_empty() {
+            return Ok(());
+        }
+        let mut threads = self.threads.lock().unwrap_or_else(|err| err.into_inner());
+        let imported = threads.entry(workspace_id.to_string()).or_default();
+        imported.extend(added.iter().cloned());
+        imported.sort_by_key(|thread| std::cmp::Reverse(thread.created_at));
+        self.persist(&threads)
+    }
+
+    /// Merges the workspace's imported threads into an app-server page read
+    /// at `position`, newest first by `createdAt`, and keeps it to `limit`
+    /// threads. Listed threads that are imported get `imported: true`.
+    /// `page.next_cursor` becomes the cursor to pass back to
+    /// `list_position` for the next page.
+    pub(crate) fn merge(
+        &self,
+        workspace_id: &str,
+        page: &mut ThreadPage,
+        position: &ListPosition,
+        limit: usize,
+    ) {
+        let threads = self.threads.lock().unwrap_or_else(|err| err.into_inner());
+        let imported = threads.get(workspace_id).map(Vec::as_slice).unwrap_or(&[]);
+        let listed_ids: HashSet<&str> = page
+            .threads
+            .iter()
+            .filter_map(|thread| thread.get("id").and_then(Value::as_str))
+            .collect();
+        let mut listed: Vec<Value> = page.threads.iter().skip(position.skip).cloned().collect();
+        for thread in &mut listed {
+            let id = thread.get("id").and_then(Value::as_str).unwrap_or_default();
+            if imported.iter().any(|imported| imported.id == id) {
+                if let Some(object) = thread.as_object_mut() {
+                    object.insert("imported".to_string(), Value::Bool(true));
+                }
+            }
+        }
+
+        // Threads newer than everything returned so far, and not older than
+        // this page when the app-server has more to list.
+        let after = position.after.clone().or_else(|| {
+            position.listed.as_ref()?;
+            page.threads.first().map(|thread| Position {
+                created_at: listed_created_at(thread),
+                imported_id: None,
+            })
+        });
+        let oldest_listed = match (&page.next_cursor, listed.last()) {
+            (None, _) => None,
+            (Some(_), Some(thread)) => Some(listed_created_at(thread)),
+            (Some(_), None) => Some(i64::MAX),
+        };
+        let mut pending: Vec<&ImportedThread> = imported
+            .iter()
+            .filter(|thread| !listed_ids.contains(thread.id.as_str()))
+            .filter(|thread| after.as_ref().is_none_or(|after| after.precedes(thread)))
+            .filter(|thread| oldest_listed.is_none_or(|oldest| created_at(thread) > oldest))
+            .collect();
+        pending.sort_by(|a, b| {
+            created_at(b)
+                .cmp(&created_at(a))
+                .then_with(|| a.id.cmp(&b.id))
+        });
+
+        let mut merged = Vec::new();
+        let mut kept_listed = 0;
+        let mut kept_imported = 0;
+        let mut last = None;
+        while merged.len() < limit {
+            let next_listed = listed.get(kept_listed);
+            let next_imported = pending.get(kept_imported);
+            let take_imported = match (next_listed, next_imported) {
+                (_, None) => false,
+                (None, Some(_)) => true,
+                (Some(thread), Some(imported)) => created_at(imported) > listed_created_at(thread),
+            };
+            if take_imported {
+                let thread = pending[kept_imported];
+                last = Some(Position {
+                    created_at: created_at(thread),
+                    imported_id: Some(thread.id.clone()),
+                });
+                merged.push(thread.to_thread());
+                kept_imported += 1;
+            } else if let Some(thread) = next_listed {
+                last = Some(Position {
+                    created_at: listed_created_at(thread),
+                    imported_id: None,
+                });
+                merged.push(thread.clone());
+                kept_listed += 1;
+            } else {
+                break;
+            }
+        }
+
+        let next = if kept_listed < listed.len() {
+            Some(ListPosition {
+                listed: position.listed.clone(),
+                skip: position.skip + kept_listed,
+                listed_done: false,
+                after: last,
+            })
+        } else if kept_imported < pending.len() {
+            Some(ListPosition {
+                listed_done: page.next_cursor.is_none(),
+                listed: page.next_cursor.take(),
+                skip: 0,
+                after: last,
+            })
+        } else {
+            page.next_cursor.take().map(|cursor| ListPosition {
+                listed: Some(cursor),
+                skip: 0,
+                listed_done: false,
+                after: last,
+            })
+        };
+        page.threads = merged;
+        page.next_cursor = next.map(|next| next.into_cursor(!imported.is_empty()));
+    }
+
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        let mut threads = self.threads.lock().unwrap_or_else(|err| err.into_inner());
+        if threads.remove(workspace_id).is_some() {
+            let _ = self.persist(&threads);
+        }
+    }
+
+    fn persist(&self, threads: &HashMap<String, Vec<ImportedThread>>) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(threads).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, data).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_importable, list_position, ImportedThread, ImportedThreads, ListPosition};
+    use crate::thread_list::ThreadPage;
+    use serde_json::json;
+    use std::collections::HashSet;
+    use std::path::Path;
+    use uuid::Uuid;
+
+    fn listed_page(threads: &[(&str, i64)], next_cursor: Option<&str>) -> ThreadPage {
+        ThreadPage {
+            threads: threads
+                .iter()
+                .map(|(id, created_at)| json!({ "id": id, "createdAt": created_at }))
+                .collect(),
+            next_cursor: next_cursor.map(str::to_string),
+            from_cache: false,
+        }
+    }
+
+    fn ids(page: &ThreadPage) -> Vec<&str> {
+        page.threads
+            .iter()
+            .map(|thread| thread["id"].as_str().unwrap_or_default())
+            .collect()
+    }
+
+    fn imported(id: &str, created_at: i64) -> ImportedThread {
+        ImportedThread {
+            id: id.to_string(),
+            path: format!("/sessions/{id}.jsonl"),
+            cwd: "/repo".to_string(),
+            created_at: Some(created_at),
+            preview: String::new(),
+            source: Some("cli".to_string()),
+        }
+    }
+
+    #[test]
+    fn merges_imported_threads_into_pages_by_creation_time() {
+        let path =
+            std::env::temp_dir().join(format!("codex-monitor-imported-{}.json", Uuid::new_v4()));
+        let store = ImportedThreads::load(path.clone());
+        store
+            .add(
+                "ws",
+                &[imported("i1", 95), imported("i2", 75), imported("i3", 40)],
+            )
+            .expect("add");
+
+        // The app-server lists l1..l6 two at a time; i1 and i2 fall in the
+        // gaps between its pages and i3 is older than all of them.
+        let mut first = listed_page(&[("l1", 100), ("l2", 90)], Some("after-l2"));
+        let position = list_position(None).expect("first page");
+        store.merge("ws", &mut first, &position, 2);
+        assert_eq!(ids(&first), ["l1", "i1"]);
+
+        let position = list_position(first.next_cursor.as_deref()).expect("cursor");
+        assert_eq!(position.listed, None);
+        assert_eq!(position.skip, 1);
+        let mut second = listed_page(&[("l1", 100), ("l2", 90)], Some("after-l2"));
+        store.merge("ws", &mut second, &position, 2);
+        assert_eq!(ids(&second), ["l2"]);
+
+        let position = list_position(second.next_cursor.as_deref()).expect("cursor");
+        assert_eq!(position.listed.as_deref(), Some("after-l2"));
+        let mut third = listed_page(&[("l3", 80), ("l4", 70)], Some("after-l4"));
+        store.merge("ws", &mut third, &position, 2);
+        assert_eq!(ids(&third), ["l3", "i2"]);
+
+        let position = list_position(third.next_cursor.as_deref()).expect("cursor");
+        let mut fourth = listed_page(&[("l3", 80), ("l4", 70)], Some("after-l4"));
+        store.merge("ws", &mut fourth, &position, 2);
+        assert_eq!(ids(&fourth), ["l4"]);
+
+        let position = list_position(fourth.next_cursor.as_deref()).expect("cursor");
+        let mut fifth = listed_page(&[("l5", 60), ("l6", 50)], None);
+        store.merge("ws", &mut fifth, &position, 2);
+        assert_eq!(ids(&fifth), ["l5", "l6"]);
+
+        let position = list_position(fifth.next_cursor.as_deref()).expect("cursor");
+        assert!(position.listed_done);
+        let mut last = listed_page(&[], None);
+        store.merge("ws", &mut last, &position, 2);
+        assert_eq!(ids(&last), ["i3"]);
+        assert_eq!(last.next_cursor, None);
+
+        assert_eq!(
+            list_position(Some("app-server-cursor"))
+                .expect("raw")
+                .listed
+                .as_deref(),
+            Some("app-server-cursor")
+        );
+        assert!(list_position(Some("imported:not base64!")).is_err());
+
+        // Without imported threads the app-server's cursors pass through.
+        let mut plain = listed_page(&[("l1", 100)], Some("after-l1"));
+        store.merge("other", &mut plain, &ListPosition::default(), 1);
+        assert_eq!(plain.next_cursor.as_deref(), Some("after-l1"));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    fn write_rollout(dir: &Path, name: &str, lines: &[serde_json::Value]) {
+        std::fs::create_dir_all(dir).expect("create sessions dir");
+        let data: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        std::fs::write(dir.join(name), data.join("\n")).expect("write rollout");
+    }
+
+    fn session_meta(id: &str, cwd: &Path, source: serde_json::Value) -> serde_json::Value {
+        json!({
+            "timestamp": "2025-05-01T10:00:00.000Z",
+            "type": "session_meta",
+            "payload": {
+                "id": id,
+                "timestamp": "2025-05-01T10:00:00.000Z",
+                "cwd": cwd,
+                "originator": "codex_cli_rs",
+                "source": source,
+            },
+        })
+    }
+
+    #[test]
+    fn finds_unknown_rollouts_of_the_workspace() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-import-{}", Uuid::new_v4()));
+        let workspace = root.join("repo");
+        let elsewhere = root.join("other");
+        std::fs::create_dir_all(&workspace).expect("create workspace");
+        std::fs::create_dir_all(&elsewhere).expect("create other");
+        let day = root
+            .join("home")
+            .join("sessions")
+            .join("2025")
+            .join("05")
+            .join("01");
+        write_rollout(
+            &day,
+            "rollout-2025-05-01T10-00-00-a.jsonl",
+            &[
+                session_meta("a", &workspace, json!("cli")),
+                json!({ "type": "response_item", "payload": { "type": "message" } }),
+                json!({ "type": "event_msg", "payload": { "type": "user_message", "message": "fix the build" } }),
+            ],
+        );
+        write_rollout(
+            &day,
+            "rollout-2025-05-01T11-00-00-b.jsonl",
+            &[session_meta("b", &workspace, json!("vscode"))],
+        );
+        write_rollout(
+            &day,
+            "rollout-2025-05-01T12-00-00-c.jsonl",
+            &[session_meta("c", &elsewhere, json!("cli"))],
+        );
+        write_rollout(
+            &day,
+            "rollout-2025-05-01T13-00-00-d.jsonl",
+            &[session_meta(
+                "d",
+                &workspace,
+                json!({ "subagent": "review" }),
+            )],
+        );
+
+        let known: HashSet<String> = ["b".to_string()].into_iter().collect();
+        let (scanned, threads) = find_importable(&root.join("home"), &workspace, &known);
+        assert_eq!(scanned, 4);
+        assert_eq!(threads.len(), 1);
+        assert_eq!(threads[0].id, "a");
+        assert_eq!(threads[0].preview, "fix the build");
+        assert_eq!(threads[0].source.as_deref(), Some("cli"));
+        assert_eq!(threads[0].created_at, Some(1_746_093_600));
+
+        let store = ImportedThreads::load(root.join("imported_threads.json"));
+        store.add("ws", &threads).expect("add");
+        let reloaded = ImportedThreads::load(root.join("imported_threads.json"));
+        assert_eq!(reloaded.ids("ws"), ["a".to_string()].into_iter().collect());
+        assert!(reloaded.find("ws", "a").is_some());
+
+        let mut page = listed_page(&[("b", 1_746_100_000)], None);
+        reloaded.merge("ws", &mut page, &ListPosition::default(), 20);
+        assert_eq!(ids(&page), ["b", "a"]);
+        assert_eq!(page.threads[1]["imported"], json!(true));
+        assert_eq!(page.next_cursor, None);
+        let mut listed_again = listed_page(&[("a", 1_746_093_600)], None);
+        reloaded.merge("ws", &mut listed_again, &ListPosition::default(), 20);
+        assert_eq!(listed_again.threads.len(), 1);
+        assert_eq!(listed_again.threads[0]["imported"], json!(true));
+
+        reloaded.forget("ws");
+        assert!(reloaded.ids("ws").is_empty());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::comparable_path;
+
+pub(crate) const DEFAULT_THREAD_PAGE_SIZE: u32 = 20;
+pub(crate) const MAX_THREAD_PAGE_SIZE: u32 = 100;
+/// Threads remembered per workspace for the disconnected fallback.
+const MAX_CACHED_THREADS: usize = 500;
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ThreadPage {
+    pub(crate) threads: Vec<Value>,
+    pub(crate) next_cursor: Option<String>,
+    /// Served from the daemon's cache because the workspace is disconnected.
+    pub(crate) from_cache: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum ThreadListError {
+    /// The app-server rejected the cursor; pagination has to restart.
+    InvalidCursor(String),
+    Failed(String),
+}
+
+pub(crate) fn clamp_page_size(limit: Option<u32>) -> u32 {
+    limit
+        .unwrap_or(DEFAULT_THREAD_PAGE_SIZE)
+        .clamp(1, MAX_THREAD_PAGE_SIZE)
+}
+
+fn first_array(value: &Value, keys: &[&str]) -> Vec<Value> {
+    keys.iter()
+        .find_map(|key| value.get(key).and_then(Value::as_array))
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn first_string(value: &Value, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .find_map(|key| value.get(key).and_then(Value::as_str))
+        .filter(|cursor| !cursor.is_empty())
+        .map(str::to_string)
+}
+
+/// Turns a raw `thread/list` response (`{ result: { data, nextCursor } }` or
+/// an `error`) into a `ThreadPage`, accepting the field spellings the
+/// app-server has used.
+pub(crate) fn normalize_thread_list(
+    response: &Value,
+    had_cursor: bool,
+) -> Result<ThreadPage, ThreadListError> {
+    if let Some(error) = response.get("error") {
+        let message = error
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap_or("thread/list failed")
+            .to_string();
+        if had_cursor && message.to_ascii_lowercase().contains("cursor") {
+            return Err(ThreadListError::InvalidCursor(message));
+        }
+        return Err(ThreadListError::Failed(message));
+    }
+    let result = response.get("result").unwrap_or(response);
+    Ok(ThreadPage {
+        threads: first_array(result, &["data", "threads", "items"]),
+        next_cursor: first_string(result, &["nextCursor", "next_cursor", "cursor"]),
+        from_cache: false,
+    })
+}
+
+fn thread_id(thread: &Value) -> Option<&str> {
+    thread.get("id").and_then(Value::as_str)
+}
+
+/// The id of the first listed thread whose `cwd` is the workspace folder.
+/// `thread/list` returns every thread of the CODEX_HOME, newest first, so
+/// other folders' threads are skipped.
+pub(crate) fn first_thread_in(threads: &[Value], workspace_path: &Path) -> Option<String> {
+    let workspace_path = comparable_path(workspace_path);
+    threads
+        .iter()
+        .filter(|thread| {
+            thread
+                .get("cwd")
+                .and_then(Value::as_str)
+                .is_some_and(|cwd| comparable_path(Path::new(cwd)) == workspace_path)
+        })
+        .find_map(thread_id)
+        .map(str::to_string)
+}
+
+/// The threads most recently listed per workspace, served when the
+/// workspace's codex session is not running.
+#[derive(Default)]
+pub(crate) struct ThreadListCache {
+    threads: Mutex<HashMap<String, Vec<Value>>>,
+}
+
+impl ThreadListCache {
+    /// A first page (no cursor) replaces the cached list; later pages extend it.
+    pub(crate) fn record(&self, workspace_id: &str, first_page: bool, page: &[Value]) {
+        let mut threads = self.threads.lock().unwrap_or_else(|err| err.into_inner());
+        let cached = threads.entry(workspace_id.to_string()).or_default();
+        if first_page {
+            cached.clear();
+        }
+        let mut seen: HashSet<String> = cached
+            .iter()
+            .filter_map(thread_id)
+            .map(str::to_string)
+            .collect();
+        for thread in page {
+            if cached.len() >= MAX_CACHED_THREADS {
+                break;
+            }
+            if let Some(id) = thread_id(thread) {
+                if !seen.insert(id.to_string()) {
+                    continue;
+                }
+            }
+            cached.push(thread.clone());
+        }
+    }
+
+    /// Cached threads as a single page; continuation requests get an empty page.
+    pub(crate) fn page(&self, workspace_id: &str, had_cursor: bool) -> Option<ThreadPage> {
+        let threads = self.threads.lock().unwrap_or_else(|err| err.into_inner());
+        let cached = threads.get(workspace_id)?;
+        Some(ThreadPage {
+            threads: if had_cursor {
+                Vec::new()
+            } else {
+                cached.clone()
+            },
+            next_cursor: None,
+            from_cache: true,
+        })
+    }
+
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        let mut threads = self.threads.lock().unwrap_or_else(|err| err.into_inner());
+        threads.remove(workspace_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        clamp_page_size, first_thread_in, normalize_thread_list, ThreadListCache, ThreadListError,
+    };
+    use serde_json::json;
+    use std::path::Path;
+
+    #[test]
+    fn picks_the_newest_thread_of_the_workspace_folder() {
+        let threads = [
+            json!({ "id": "other", "cwd": "/tmp/elsewhere" }),
+            json!({ "id": "no-cwd" }),
+            json!({ "id": "mine", "cwd": "/tmp/repo" }),
+            json!({ "id": "older", "cwd": "/tmp/repo" }),
+        ];
+        assert_eq!(
+            first_thread_in(&threads, Path::new("/tmp/repo")),
+            Some("mine".to_string())
+        );
+        assert_eq!(first_thread_in(&threads[..2], Path::new("/tmp/repo")), None);
+    }
+
+    #[test]
+    fn clamps_page_sizes() {
+        assert_eq!(clamp_page_size(None), 20);
+        assert_eq!(clamp_page_size(Some(0)), 1);
+        assert_eq!(clamp_page_size(Some(10_000)), 100);
+    }
+
+    #[test]
+    fn normalizes_response_variants() {
+        let page = normalize_thread_list(
+            &json!({ "id": 1, "result": { "data": [{ "id": "a" }], "nextCursor": "c1" } }),
+            false,
+        )
+        .expect("page");
+        assert_eq!(page.threads, vec![json!({ "id": "a" })]);
+        assert_eq!(page.next_cursor.as_deref(), Some("c1"));
+
+        let page = normalize_thread_list(
+            &json!({ "result": { "threads": [], "next_cursor": "" } }),
+            false,
+        )
+        .expect("page");
+        assert!(page.threads.is_empty());
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn reports_invalid_cursors() {
+        let response = json!({ "error": { "message": "invalid cursor: abc" } });
+        assert_eq!(
+            normalize_thread_list(&response, true),
+            Err(ThreadListError::InvalidCursor(
+                "invalid cursor: abc".to_string()
+            ))
+        );
+        assert!(matches!(
+            normalize_thread_list(&json!({ "error": { "message": "boom" } }), true),
+            Err(ThreadListError::Failed(_))
+        ));
+    }
+
+    #[test]
+    fn caches_pages_per_workspace() {
+        let cache = ThreadListCache::default();
+        assert!(cache.page("ws", false).is_none());
+        cache.record("ws", true, &[json!({ "id": "a" }), json!({ "id": "b" })]);
+        cache.record("ws", false, &[json!({ "id": "b" }), json!({ "id": "c" })]);
+        let page = cache.page("ws", false).expect("cached");
+        assert!(page.from_cache);
+        assert_eq!(page.threads.len(), 3);
+        assert!(cache.page("ws", true).expect("cached").threads.is_empty());
+
+        cache.record("ws", true, &[json!({ "id": "z" })]);
+        assert_eq!(cache.page("ws", false).expect("cached").threads.len(), 1);
+        cache.forget("ws");
+        assert!(cache.page("ws", false).is_none());
+    }
+}
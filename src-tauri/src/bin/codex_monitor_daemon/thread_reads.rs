@@ -0,0 +1,256 @@
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::fetch::now_ms;
+
+/// Completed items remembered per thread for unread counts.
+const MAX_TRACKED_ITEMS: usize = 200;
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct WorkspaceReads {
+    /// Everything up to this time counts as read in every thread.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    all_read_at: Option<u64>,
+    /// Per-thread read positions, in ms since the epoch.
+    #[serde(default)]
+    threads: HashMap<String, u64>,
+}
+
+impl WorkspaceReads {
+    fn read_at(&self, thread_id: &str) -> u64 {
+        let thread = self.threads.get(thread_id).copied().unwrap_or_default();
+        thread.max(self.all_read_at.unwrap_or_default())
+    }
+}
+
+struct Item {
+    id: String,
+    at: u64,
+}
+
+#[derive(Default)]
+struct ReadsState {
+    positions: HashMap<String, WorkspaceReads>,
+    /// Keyed by (workspace id, thread id); only covers this daemon run.
+    activity: HashMap<(String, String), VecDeque<Item>>,
+}
+
+/// Where each thread was last read, shared by every client of the daemon,
+/// and the `item/completed` events that arrived since.
+pub(crate) struct ThreadReads {
+    path: PathBuf,
+    state: Mutex<ReadsState>,
+}
+
+fn string_field<'a>(value: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|key| value.get(key).and_then(Value::as_str))
+        .filter(|value| !value.is_empty())
+}
+
+impl ThreadReads {
+    pub(crate) fn load(path: PathBuf) -> Self {
+        let positions = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            state: Mutex::new(ReadsState {
+                positions,
+                activity: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Counts `item/completed` notifications from the app-server as new output.
+    pub(crate) fn record_event(&self, workspace_id: &str, message: &Value) {
+        if message.get("method").and_then(Value::as_str) != Some("item/completed") {
+            return;
+        }
+        let Some(params) = message.get("params") else {
+            return;
+        };
+        let Some(thread_id) = string_field(params, &["threadId", "thread_id"]) else {
+            return;
+        };
+        let item_id = params
+            .get("item")
+            .and_then(|item| string_field(item, &["id"]))
+            .unwrap_or_default();
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        let items = state
+            .activity
+            .entry((workspace_id.to_string(), thread_id.to_string()))
+            .or_default();
+        if items.len() >= MAX_TRACKED_ITEMS {
+            items.pop_front();
+        }
+        items.push_back(Item {
+            id: item_id.to_string(),
+            at: now_ms(),
+        });
+    }
+
+    /// Moves the read position of `thread_id` (or of the whole workspace when
+    /// `None`) to `item_id`, `at_ms`, or now. Positions never move backwards,
+    /// so a client that is behind can't resurrect unread output. An `item_id`
+    /// the thread's tracked output doesn't hold is an error.
+    pub(crate) fn mark_read(
+        &self,
+        workspace_id: &str,
+        thread_id: Option<&str>,
+        item_id: Option<&str>,
+        at_ms: Option<u64>,
+    ) -> Result<(), String> {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        let item_at = match (thread_id, item_id) {
+            (Some(thread_id), Some(item_id)) => Some(
+                state
+                    .activity
+                    .get(&(workspace_id.to_string(), thread_id.to_string()))
+                    .and_then(|items| items.iter().find(|item| item.id == item_id))
+                    .map(|item| item.at)
+                    .ok_or("item not found")?,
+            ),
+            _ => None,
+        };
+        let read_at = item_at.or(at_ms).unwrap_or_else(now_ms);
+        let reads = state.positions.entry(workspace_id.to_string()).or_default();
+        let slot = match thread_id {
+            Some(thread_id) => reads.threads.entry(thread_id.to_string()).or_default(),
+            None => reads.all_read_at.get_or_insert(0),
+        };
+        *slot = (*slot).max(read_at);
+        self.persist(&state.positions)
+    }
+
+    /// Completed items in the thread after its read position.
+    fn count(state: &ReadsState, workspace_id: &str, thread_id: &str) -> usize {
+        let Some(items) = state
+            .activity
+            .get(&(workspace_id.to_string(), thread_id.to_string()))
+        else {
+            return 0;
+        };
+        let read_at = state
+            .positions
+            .get(workspace_id)
+            .map(|reads| reads.read_at(thread_id))
+            .unwrap_or_default();
+        items.iter().filter(|item| item.at > read_at).count()
+    }
+
+    /// Adds `unread` and `unreadCount` to each thread object in a listing.
+    pub(crate) fn annotate(&self, workspace_id: &str, threads: &mut [Value]) {
+        let state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        for thread in threads {
+            let Some(id) = thread.get("id").and_then(Value::as_str) else {
+                continue;
+            };
+            let count = Self::count(&state, workspace_id, id);
+            if let Some(object) = thread.as_object_mut() {
+                object.insert("unread".to_string(), Value::Bool(count > 0));
+                object.insert("unreadCount".to_string(), Value::from(count));
+            }
+        }
+    }
+
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        state
+            .activity
+            .retain(|(workspace, _), _| workspace != workspace_id);
+        if state.positions.remove(workspace_id).is_some() {
+            let _ = self.persist(&state.positions);
+        }
+    }
+
+    fn persist(&self, positions: &HashMap<String, WorkspaceReads>) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(positions).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, data).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ThreadReads;
+    use serde_json::json;
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    fn unread_count(reads: &ThreadReads, thread_id: &str) -> u64 {
+        let mut threads = vec![json!({ "id": thread_id })];
+        reads.annotate("ws", &mut threads);
+        threads[0]["unreadCount"].as_u64().expect("count")
+    }
+
+    fn completed(thread_id: &str, item_id: &str) -> serde_json::Value {
+        json!({
+            "method": "item/completed",
+            "params": { "threadId": thread_id, "item": { "id": item_id, "type": "agentMessage" } }
+        })
+    }
+
+    #[test]
+    fn counts_items_after_the_read_position() {
+        let path = std::env::temp_dir()
+            .join(format!("codex-monitor-reads-{}", Uuid::new_v4()))
+            .join("thread_reads.json");
+        let reads = ThreadReads::load(path.clone());
+        reads.record_event("ws", &completed("t1", "a"));
+        std::thread::sleep(Duration::from_millis(5));
+        reads.record_event("ws", &completed("t1", "b"));
+        reads.record_event("ws", &json!({ "method": "turn/started", "params": {} }));
+        assert_eq!(unread_count(&reads, "t1"), 2);
+
+        let mut threads = vec![json!({ "id": "t1" }), json!({ "id": "t2" })];
+        reads.annotate("ws", &mut threads);
+        assert_eq!(threads[0]["unread"], json!(true));
+        assert_eq!(threads[0]["unreadCount"], json!(2));
+        assert_eq!(threads[1]["unreadCount"], json!(0));
+
+        assert_eq!(
+            reads.mark_read("ws", Some("t1"), Some("missing"), Some(u64::MAX)),
+            Err("item not found".to_string())
+        );
+        assert_eq!(unread_count(&reads, "t1"), 2);
+        reads
+            .mark_read("ws", Some("t1"), Some("a"), None)
+            .expect("mark read");
+        assert_eq!(unread_count(&reads, "t1"), 1);
+        reads
+            .mark_read("ws", Some("t1"), None, None)
+            .expect("mark read");
+        assert_eq!(unread_count(&reads, "t1"), 0);
+        // An older position from a lagging client does not unread anything.
+        reads
+            .mark_read("ws", Some("t1"), None, Some(0))
+            .expect("mark read");
+        assert_eq!(unread_count(&reads, "t1"), 0);
+
+        // Positions survive a restart.
+        let reloaded = ThreadReads::load(path.clone());
+        std::thread::sleep(Duration::from_millis(5));
+        reloaded.record_event("ws", &completed("t1", "c"));
+        reloaded.record_event("ws", &completed("t2", "d"));
+        assert_eq!(unread_count(&reloaded, "t1"), 1);
+        reloaded
+            .mark_read("ws", None, None, None)
+            .expect("mark all read");
+        assert_eq!(unread_count(&reloaded, "t1"), 0);
+        assert_eq!(unread_count(&reloaded, "t2"), 0);
+
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::remove_dir_all(parent);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use serde_json::Value;
+
+/// Where `seed_thread` takes the first message of the new thread from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SeedSource {
+    /// Text given by the client, copied from `thread_id` when known.
+    Text {
+        thread_id: Option<String>,
+        text: String,
+    },
+    /// A user or agent message of `thread_id`, read from the app-server.
+    Item { thread_id: String, item_id: String },
+}
+
+impl SeedSource {
+    pub(crate) fn thread_id(&self) -> Option<&str> {
+        match self {
+            Self::Text { thread_id, .. } => thread_id.as_deref(),
+            Self::Item { thread_id, .. } => Some(thread_id),
+        }
+    }
+}
+
+fn non_empty<'a>(source: &'a Value, key: &str) -> Option<&'a str> {
+    source
+        .get(key)
+        .and_then(Value::as_str)
+        .filter(|value| !value.trim().is_empty())
+}
+
+/// Reads `source`: `messageText`, or `itemId` together with `threadId`.
+pub(crate) fn parse_seed_source(params: &Value) -> Result<SeedSource, String> {
+    let source = params
+        .get("source")
+        .filter(|source| source.is_object())
+        .ok_or("missing `source`")?;
+    let thread_id = non_empty(source, "threadId").map(str::to_string);
+    match (
+        non_empty(source, "messageText"),
+        non_empty(source, "itemId"),
+    ) {
+        (Some(_), Some(_)) => Err("pass either `messageText` or `itemId`, not both".to_string()),
+        (Some(text), None) => Ok(SeedSource::Text {
+            thread_id,
+            text: text.to_string(),
+        }),
+        (None, Some(item_id)) => Ok(SeedSource::Item {
+            thread_id: thread_id.ok_or("`itemId` needs the source `threadId`")?,
+            item_id: item_id.to_string(),
+        }),
+        (None, None) => Err("missing `messageText` or `itemId` in `source`".to_string()),
+    }
+}
+
+/// The new thread's id in a `thread/start` response, with or without the
+/// `result` wrapper.
+pub(crate) fn started_thread_id(response: &Value) -> Option<&str> {
+    let result = response.get("result").unwrap_or(response);
+    result
+        .get("threadId")
+        .or_else(|| result.get("thread").and_then(|thread| thread.get("id")))
+        .and_then(Value::as_str)
+        .filter(|id| !id.is_empty())
+}
+
+/// The text of a user message's `text` inputs, or of an agent message.
+fn message_text(item: &Value) -> Option<String> {
+    let text = match item.get("type").and_then(Value::as_str)? {
+        "userMessage" => item
+            .get("content")
+            .and_then(Value::as_array)?
+            .iter()
+            .filter(|input| input.get("type").and_then(Value::as_str) == Some("text"))
+            .filter_map(|input| input.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "agentMessage" => item.get("text").and_then(Value::as_str)?.to_string(),
+        _ => return None,
+    };
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// The text of item `item_id` in a `thread/resume` response. Fails when the
+/// thread has no such item or it isn't a message with text.
+pub(crate) fn item_text(resumed: &Value, item_id: &str) -> Result<String, String> {
+    let result = resumed.get("result").unwrap_or(resumed);
+    let item = result
+        .get("thread")
+        .and_then(|thread| thread.get("turns"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|turn| turn.get("items").and_then(Value::as_array))
+        .flatten()
+        .find(|item| item.get("id").and_then(Value::as_str) == Some(item_id))
+        .ok_or_else(|| format!("item not found: {item_id}"))?;
+    message_text(item).ok_or_else(|| format!("item {item_id} is not a message with text"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{item_text, parse_seed_source, started_thread_id, SeedSource};
+    use serde_json::json;
+
+    #[test]
+    fn parses_sources() {
+        assert_eq!(
+            parse_seed_source(&json!({ "source": { "threadId": "t1", "messageText": "Hi" } })),
+            Ok(SeedSource::Text {
+                thread_id: Some("t1".to_string()),
+                text: "Hi".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_seed_source(&json!({ "source": { "threadId": "t1", "itemId": "i2" } })),
+            Ok(SeedSource::Item {
+                thread_id: "t1".to_string(),
+                item_id: "i2".to_string(),
+            })
+        );
+        assert!(parse_seed_source(&json!({ "source": { "itemId": "i2" } })).is_err());
+        assert!(parse_seed_source(&json!({ "source": { "messageText": " " } })).is_err());
+        assert!(parse_seed_source(
+            &json!({ "source": { "threadId": "t1", "messageText": "Hi", "itemId": "i2" } })
+        )
+        .is_err());
+        assert!(parse_seed_source(&json!({ "source": "t1" })).is_err());
+    }
+
+    #[test]
+    fn reads_message_items_and_thread_ids() {
+        let resumed = json!({ "id": 4, "result": { "thread": { "id": "t1", "turns": [
+            { "id": "turn-1", "items": [
+                { "type": "userMessage", "id": "i1", "content": [
+                    { "type": "text", "text": "Fix the build" },
+                    { "type": "image", "url": "https://example.com/a.png" },
+                    { "type": "text", "text": "on Linux" },
+                ] },
+                { "type": "commandExecution", "id": "i2", "command": "make" },
+            ] },
+            { "id": "turn-2", "items": [{ "type": "agentMessage", "id": "i3", "text": " Done. " }] },
+        ] } } });
+        assert_eq!(
+            item_text(&resumed, "i1"),
+            Ok("Fix the build\non Linux".to_string())
+        );
+        assert_eq!(item_text(&resumed, "i3"), Ok("Done.".to_string()));
+        assert!(item_text(&resumed, "i2").is_err());
+        assert!(item_text(&resumed, "i9").is_err());
+
+        assert_eq!(
+            started_thread_id(&json!({ "result": { "thread": { "id": "t2" } } })),
+            Some("t2")
+        );
+        assert_eq!(started_thread_id(&json!({ "threadId": "t3" })), Some("t3"));
+        assert_eq!(started_thread_id(&json!({ "error": {} })), None);
+    }
+}
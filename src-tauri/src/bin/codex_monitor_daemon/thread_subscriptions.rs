@@ -0,0 +1,224 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+use serde_json::Value;
+
+use super::turns::thread_id;
+
+/// What an event notification is about.
+#[derive(Debug, PartialEq, Eq)]
+enum EventScope<'a> {
+    /// Not tied to a workspace, e.g. `workspaces-changed` or operations.
+    Daemon,
+    /// Workspace-level, e.g. session lifecycle or errors without a thread.
+    Workspaces(Vec<&'a str>),
+    Thread(&'a str, &'a str),
+}
+
+fn event_scope(notification: &Value) -> EventScope<'_> {
+    let Some(params) = notification.get("params") else {
+        return EventScope::Daemon;
+    };
+    let (workspace_id, thread_params) = match notification.get("method").and_then(Value::as_str) {
+        Some("app-server-event") => (
+            params.get("workspace_id"),
+            params
+                .get("message")
+                .and_then(|message| message.get("params")),
+        ),
+        _ => (params.get("workspaceId"), Some(params)),
+    };
+    if let Some(workspace_id) = workspace_id.and_then(Value::as_str) {
+        let thread = thread_params.and_then(|params| {
+            thread_id(params).or_else(|| {
+                params
+                    .get("thread")
+                    .and_then(|thread| thread.get("id"))
+                    .and_then(Value::as_str)
+            })
+        });
+        return match thread {
+            Some(thread_id) => EventScope::Thread(workspace_id, thread_id),
+            None => EventScope::Workspaces(vec![workspace_id]),
+        };
+    }
+    match params.get("workspaceIds").and_then(Value::as_array) {
+        Some(ids) => EventScope::Workspaces(ids.iter().filter_map(Value::as_str).collect()),
+        None => EventScope::Daemon,
+    }
+}
+
+/// Workspaces an event notification is about; empty for daemon-wide events.
+pub(crate) fn event_workspace_ids(notification: &Value) -> Vec<&str> {
+    match event_scope(notification) {
+        EventScope::Daemon => Vec::new(),
+        EventScope::Workspaces(ids) => ids,
+        EventScope::Thread(workspace_id, _) => vec![workspace_id],
+    }
+}
+
+/// Threads one connection's views are focused on. While any exist, events
+/// of other threads and of workspaces without a subscription are not
+/// forwarded to it. Subscriptions are counted so each view can unsubscribe
+/// without ending another view's stream.
+#[derive(Default)]
+pub(crate) struct ThreadSubscriptions {
+    threads: Mutex<BTreeMap<(String, String), usize>>,
+}
+
+impl ThreadSubscriptions {
+    /// Adds a view of the thread and returns how many are subscribed.
+    pub(crate) fn subscribe(&self, workspace_id: String, thread_id: String) -> usize {
+        let mut threads = self.threads.lock().unwrap_or_else(|err| err.into_inner());
+        let count = threads.entry((workspace_id, thread_id)).or_default();
+        *count += 1;
+        *count
+    }
+
+    /// Drops one view of the thread and returns how many are left.
+    pub(crate) fn unsubscribe(
+        &self,
+        workspace_id: String,
+        thread_id: String,
+    ) -> Result<usize, String> {
+        let mut threads = self.threads.lock().unwrap_or_else(|err| err.into_inner());
+        let key = (workspace_id, thread_id);
+        let count = threads
+            .get_mut(&key)
+            .ok_or("not subscribed to that thread")?;
+        *count -= 1;
+        let count = *count;
+        if count == 0 {
+            threads.remove(&key);
+        }
+        Ok(count)
+    }
+
+    /// Whether a notification should be forwarded to the connection.
+    pub(crate) fn allows(&self, notification: &Value) -> bool {
+        let threads = self.threads.lock().unwrap_or_else(|err| err.into_inner());
+        if threads.is_empty() {
+            return true;
+        }
+        let watches = |workspace_id: &str| threads.keys().any(|(id, _)| id == workspace_id);
+        match event_scope(notification) {
+            EventScope::Daemon => true,
+            EventScope::Workspaces(ids) => ids.into_iter().any(watches),
+            EventScope::Thread(workspace_id, thread_id) => {
+                threads.contains_key(&(workspace_id.to_string(), thread_id.to_string()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{event_scope, event_workspace_ids, EventScope, ThreadSubscriptions};
+    use serde_json::json;
+
+    fn app_server_event(workspace_id: &str, params: serde_json::Value) -> serde_json::Value {
+        json!({
+            "method": "app-server-event",
+            "params": {
+                "workspace_id": workspace_id,
+                "message": { "method": "item/started", "params": params },
+            },
+            "seq": 1,
+        })
+    }
+
+    #[test]
+    fn scopes_events_by_workspace_and_thread() {
+        assert_eq!(
+            event_scope(&app_server_event("ws", json!({ "threadId": "t1" }))),
+            EventScope::Thread("ws", "t1")
+        );
+        assert_eq!(
+            event_scope(&app_server_event(
+                "ws",
+                json!({ "turn": { "thread_id": "t2" } })
+            )),
+            EventScope::Thread("ws", "t2")
+        );
+        assert_eq!(
+            event_scope(&app_server_event("ws", json!({ "thread": { "id": "t3" } }))),
+            EventScope::Thread("ws", "t3")
+        );
+        assert_eq!(
+            event_scope(&app_server_event("ws", json!({ "message": "boom" }))),
+            EventScope::Workspaces(vec!["ws"])
+        );
+        assert_eq!(
+            event_scope(&json!({
+                "method": "thread-title-updated",
+                "params": { "workspaceId": "ws", "threadId": "t1", "title": "x" },
+            })),
+            EventScope::Thread("ws", "t1")
+        );
+        assert_eq!(
+            event_scope(&json!({
+                "method": "workspace-restart-required",
+                "params": { "workspaceIds": ["a", "b"], "reason": "codexBin" },
+            })),
+            EventScope::Workspaces(vec!["a", "b"])
+        );
+        assert_eq!(
+            event_scope(&json!({ "method": "workspaces-changed", "params": { "order": [] } })),
+            EventScope::Daemon
+        );
+    }
+
+    #[test]
+    fn lists_the_workspaces_an_event_is_about() {
+        assert_eq!(
+            event_workspace_ids(&app_server_event("ws", json!({ "threadId": "t1" }))),
+            ["ws"]
+        );
+        assert_eq!(
+            event_workspace_ids(&json!({
+                "method": "workspace-restart-required",
+                "params": { "workspaceIds": ["a", "b"], "reason": "codexBin" },
+            })),
+            ["a", "b"]
+        );
+        assert!(
+            event_workspace_ids(&json!({ "method": "workspaces-changed", "params": {} }))
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn filters_events_while_threads_are_subscribed() {
+        let subscriptions = ThreadSubscriptions::default();
+        let other_thread = app_server_event("ws", json!({ "threadId": "t2" }));
+        assert!(subscriptions.allows(&other_thread));
+
+        assert_eq!(
+            subscriptions.subscribe("ws".to_string(), "t1".to_string()),
+            1
+        );
+        assert_eq!(
+            subscriptions.subscribe("ws".to_string(), "t1".to_string()),
+            2
+        );
+        assert!(subscriptions.allows(&app_server_event("ws", json!({ "threadId": "t1" }))));
+        assert!(!subscriptions.allows(&other_thread));
+        assert!(subscriptions.allows(&app_server_event("ws", json!({}))));
+        assert!(!subscriptions.allows(&app_server_event("other", json!({}))));
+        assert!(subscriptions.allows(&json!({ "method": "workspaces-changed", "params": {} })));
+
+        assert_eq!(
+            subscriptions.unsubscribe("ws".to_string(), "t1".to_string()),
+            Ok(1)
+        );
+        assert!(!subscriptions.allows(&other_thread));
+        assert_eq!(
+            subscriptions.unsubscribe("ws".to_string(), "t1".to_string()),
+            Ok(0)
+        );
+        assert!(subscriptions.allows(&other_thread));
+        assert!(subscriptions
+            .unsubscribe("ws".to_string(), "t1".to_string())
+            .is_err());
+    }
+}
@@ -0,0 +1,463 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::{mpsc, Notify};
+use tokio::time::timeout;
+
+use super::fetch::now_ms;
+use crate::backend::app_server::WorkspaceSession;
+
+/// Longest title kept, in characters.
+const MAX_TITLE_CHARS: usize = 80;
+/// Part of the first message shown to the titling turn, in characters.
+const MAX_PROMPT_CHARS: usize = 2000;
+/// Threads waiting for an auto-title; more are not titled.
+const MAX_PENDING_TITLES: usize = 32;
+/// Pause after each auto-title turn, so at most one runs per interval.
+pub(crate) const AUTO_TITLE_INTERVAL: Duration = Duration::from_secs(10);
+const AUTO_TITLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum TitleSource {
+    Manual,
+    Auto,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ThreadTitle {
+    pub(crate) title: String,
+    pub(crate) source: TitleSource,
+    pub(crate) updated_at: u64,
+}
+
+/// A thread whose first turn completed without a title.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TitleRequest {
+    pub(crate) workspace_id: String,
+    pub(crate) thread_id: String,
+    pub(crate) first_message: String,
+}
+
+#[derive(Default)]
+struct TitlesState {
+    /// Workspace id -> thread id -> title.
+    titles: HashMap<String, HashMap<String, ThreadTitle>>,
+    /// First user message of untitled threads, keyed by (workspace id,
+    /// thread id); only covers this daemon run.
+    first_messages: HashMap<(String, String), String>,
+    /// Threads already queued for an auto-title, so each is tried once.
+    attempted: HashSet<(String, String)>,
+    pending: VecDeque<TitleRequest>,
+}
+
+impl TitlesState {
+    fn has_title(&self, workspace_id: &str, thread_id: &str) -> bool {
+        self.titles
+            .get(workspace_id)
+            .is_some_and(|titles| titles.contains_key(thread_id))
+    }
+}
+
+/// Thread titles shared by every client of the daemon, saved in
+/// `<data-dir>/thread_titles.json`, plus the queue of threads to auto-title.
+pub(crate) struct ThreadTitles {
+    path: PathBuf,
+    state: Mutex<TitlesState>,
+    queued: Notify,
+}
+
+fn string_field<'a>(value: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .find_map(|key| value.get(key).and_then(Value::as_str))
+        .filter(|value| !value.is_empty())
+}
+
+fn user_message_text(item: &Value) -> Option<String> {
+    let content = item.get("content").and_then(Value::as_array)?;
+    let text = content
+        .iter()
+        .filter(|input| input.get("type").and_then(Value::as_str) == Some("text"))
+        .filter_map(|input| input.get("text").and_then(Value::as_str))
+        .collect::<Vec<_>>()
+        .join(" ");
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.chars().take(MAX_PROMPT_CHARS).collect())
+}
+
+/// First non-empty line of `raw` without surrounding quotes or a trailing
+/// period, whitespace collapsed and cut to `MAX_TITLE_CHARS`.
+pub(crate) fn clean_title(raw: &str) -> Option<String> {
+    let line = raw.lines().map(str::trim).find(|line| !line.is_empty())?;
+    let line = line
+        .trim_matches(|c: char| c == '"' || c == '\'' || c == '`' || c == '*')
+        .trim_end_matches('.');
+    let title: String = line
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(MAX_TITLE_CHARS)
+        .collect();
+    let title = title.trim_end().to_string();
+    (!title.is_empty()).then_some(title)
+}
+
+impl ThreadTitles {
+    pub(crate) fn load(path: PathBuf) -> Self {
+        let titles = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            path,
+            state: Mutex::new(TitlesState {
+                titles,
+                ..TitlesState::default()
+            }),
+            queued: Notify::new(),
+        }
+    }
+
+    /// Remembers the first user message of untitled threads and queues them
+    /// for an auto-title once a turn in them completes.
+    pub(crate) fn record_event(&self, workspace_id: &str, message: &Value) {
+        let method = message.get("method").and_then(Value::as_str);
+        if !matches!(method, Some("item/completed" | "turn/completed")) {
+            return;
+        }
+        let Some(params) = message.get("params") else {
+            return;
+        };
+        let turn = params.get("turn");
+        let Some(thread_id) = string_field(params, &["threadId", "thread_id"])
+            .or_else(|| turn.and_then(|turn| string_field(turn, &["threadId", "thread_id"])))
+        else {
+            return;
+        };
+        let key = (workspace_id.to_string(), thread_id.to_string());
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        if state.has_title(workspace_id, thread_id) || state.attempted.contains(&key) {
+            return;
+        }
+        if method == Some("item/completed") {
+            let Some(item) = params.get("item") else {
+                return;
+            };
+            if item.get("type").and_then(Value::as_str) != Some("userMessage")
+                || state.first_messages.contains_key(&key)
+            {
+                return;
+            }
+            if let Some(text) = user_message_text(item) {
+                state.first_messages.insert(key, text);
+            }
+            return;
+        }
+        let Some(first_message) = state.first_messages.remove(&key) else {
+            return;
+        };
+        if state.pending.len() >= MAX_PENDING_TITLES {
+            return;
+        }
+        state.attempted.insert(key);
+        state.pending.push_back(TitleRequest {
+            workspace_id: workspace_id.to_string(),
+            thread_id: thread_id.to_string(),
+            first_message,
+        });
+        drop(state);
+        self.queued.notify_one();
+    }
+
+    /// Waits for the next thread to auto-title.
+    pub(crate) async fn next_request(&self) -> TitleRequest {
+        loop {
+            let request = {
+                let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+                state.pending.pop_front()
+            };
+            match request {
+                Some(request) => return request,
+                None => self.queued.notified().await,
+            }
+        }
+    }
+
+    /// Stores a title, or clears it when `title` is empty, and returns what is
+    /// now stored. An auto-title is only stored while the thread has none, so
+    /// it never replaces one a user set.
+    pub(crate) fn set(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        title: Option<&str>,
+        source: TitleSource,
+    ) -> Result<Option<ThreadTitle>, String> {
+        let title = title.and_then(clean_title);
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        if source == TitleSource::Auto
+            && (title.is_none() || state.has_title(workspace_id, thread_id))
+        {
+            return Ok(None);
+        }
+        let key = (workspace_id.to_string(), thread_id.to_string());
+        state.first_messages.remove(&key);
+        let titles = state.titles.entry(workspace_id.to_string()).or_default();
+        let stored = match title {
+            Some(title) => {
+                let stored = ThreadTitle {
+                    title,
+                    source,
+                    updated_at: now_ms(),
+                };
+                titles.insert(thread_id.to_string(), stored.clone());
+                Some(stored)
+            }
+            None => {
+                titles.remove(thread_id);
+                None
+            }
+        };
+        self.persist(&state.titles)?;
+        Ok(stored)
+    }
+
+    /// Adds `title` and `titleSource` to each titled thread in a listing.
+    pub(crate) fn annotate(&self, workspace_id: &str, threads: &mut [Value]) {
+        let state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        let Some(titles) = state.titles.get(workspace_id) else {
+            return;
+        };
+        for thread in threads {
+            let Some(title) = thread
+                .get("id")
+                .and_then(Value::as_str)
+                .and_then(|id| titles.get(id))
+            else {
+                continue;
+            };
+            if let Some(object) = thread.as_object_mut() {
+                object.insert("title".to_string(), Value::from(title.title.clone()));
+                object.insert("titleSource".to_string(), json!(title.source));
+            }
+        }
+    }
+
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        state
+            .first_messages
+            .retain(|(workspace, _), _| workspace != workspace_id);
+        state
+            .pending
+            .retain(|request| request.workspace_id != workspace_id);
+        if state.titles.remove(workspace_id).is_some() {
+            let _ = self.persist(&state.titles);
+        }
+    }
+
+    fn persist(
+        &self,
+        titles: &HashMap<String, HashMap<String, ThreadTitle>>,
+    ) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(titles).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, data).map_err(|e| e.to_string())
+    }
+}
+
+fn title_prompt(first_message: &str) -> String {
+    format!(
+        "Write a title of at most five words for a coding conversation that starts with the \
+message below. Reply with the title only, without quotes.\n\nMessage:\n{first_message}"
+    )
+}
+
+/// Asks the session for a title in a hidden, read-only thread that is
+/// archived afterwards. Its events go to the thread's callback, so clients
+/// never see it and it takes no turn slot.
+pub(crate) async fn generate_title(
+    session: &WorkspaceSession,
+    first_message: &str,
+) -> Result<String, String> {
+    let thread_params = json!({
+        "cwd": session.entry.path,
+        "approvalPolicy": "never"
+    });
+    let response = session.send_request("thread/start", thread_params).await?;
+    if let Some(error) = response.get("error") {
+        return Err(string_field(error, &["message"])
+            .unwrap_or("thread/start failed")
+            .to_string());
+    }
+    let result = response.get("result").unwrap_or(&response);
+    let thread_id = string_field(result, &["threadId"])
+        .or_else(|| {
+            result
+                .get("thread")
+                .and_then(|thread| string_field(thread, &["id"]))
+        })
+        .ok_or("thread/start returned no thread id")?
+        .to_string();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Value>();
+    session
+        .background_thread_callbacks
+        .lock()
+        .await
+        .insert(thread_id.clone(), tx);
+    let turn_params = json!({
+        "threadId": thread_id,
+        "input": [{ "type": "text", "text": title_prompt(first_message) }],
+        "cwd": session.entry.path,
+        "approvalPolicy": "never",
+        "sandboxPolicy": { "type": "readOnly" },
+    });
+    let result = match session.send_request("turn/start", turn_params).await {
+        Ok(response) => match response.get("error") {
+            Some(error) => Err(string_field(error, &["message"])
+                .unwrap_or("turn/start failed")
+                .to_string()),
+            None => {
+                let mut text = String::new();
+                let collected = timeout(AUTO_TITLE_TIMEOUT, async {
+                    while let Some(event) = rx.recv().await {
+                        match event.get("method").and_then(Value::as_str) {
+                            Some("item/agentMessage/delta") => {
+                                if let Some(delta) = event
+                                    .get("params")
+                                    .and_then(|params| string_field(params, &["delta"]))
+                                {
+                                    text.push_str(delta);
+                                }
+                            }
+                            Some("turn/completed" | "turn/error") => break,
+                            _ => {}
+                        }
+                    }
+                })
+                .await;
+                match collected {
+                    Ok(()) => {
+                        clean_title(&text).ok_or_else(|| "no title was generated".to_string())
+                    }
+                    Err(_) => Err("timed out waiting for a title".to_string()),
+                }
+            }
+        },
+        Err(error) => Err(error),
+    };
+
+    session
+        .background_thread_callbacks
+        .lock()
+        .await
+        .remove(&thread_id);
+    let _ = session
+        .send_request("thread/archive", json!({ "threadId": thread_id }))
+        .await;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{clean_title, ThreadTitles, TitleRequest, TitleSource};
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    fn user_message(thread_id: &str, text: &str) -> Value {
+        json!({
+            "method": "item/completed",
+            "params": { "threadId": thread_id, "item": {
+                "id": "m1",
+                "type": "userMessage",
+                "content": [{ "type": "text", "text": text }, { "type": "image", "url": "x" }]
+            } }
+        })
+    }
+
+    fn turn_completed(thread_id: &str) -> Value {
+        json!({ "method": "turn/completed", "params": { "turn": { "threadId": thread_id, "id": "u1" } } })
+    }
+
+    #[test]
+    fn cleans_generated_titles() {
+        assert_eq!(
+            clean_title("\n  \"Fix flaky   login test.\"\nextra").as_deref(),
+            Some("Fix flaky login test")
+        );
+        assert_eq!(clean_title(" \n "), None);
+        assert_eq!(
+            clean_title(&"a".repeat(200)).map(|title| title.len()),
+            Some(80)
+        );
+    }
+
+    #[tokio::test]
+    async fn queues_untitled_threads_once_and_keeps_manual_titles() {
+        let path = std::env::temp_dir()
+            .join(format!("codex-monitor-titles-{}", Uuid::new_v4()))
+            .join("thread_titles.json");
+        let titles = ThreadTitles::load(path.clone());
+        titles.record_event("ws", &user_message("t1", "make the build faster"));
+        titles.record_event("ws", &user_message("t1", "second message"));
+        titles.record_event("ws", &user_message("t2", "rename the crate"));
+        titles
+            .set("ws", "t2", Some("Crate rename"), TitleSource::Manual)
+            .expect("set");
+        titles.record_event("ws", &turn_completed("t1"));
+        titles.record_event("ws", &turn_completed("t2"));
+        titles.record_event("ws", &turn_completed("t1"));
+
+        assert_eq!(
+            titles.next_request().await,
+            TitleRequest {
+                workspace_id: "ws".to_string(),
+                thread_id: "t1".to_string(),
+                first_message: "make the build faster".to_string(),
+            }
+        );
+        assert!(titles.state.lock().expect("state").pending.is_empty());
+
+        let auto = titles
+            .set("ws", "t1", Some("Faster builds"), TitleSource::Auto)
+            .expect("set");
+        assert_eq!(auto.map(|title| title.source), Some(TitleSource::Auto));
+        let skipped = titles
+            .set("ws", "t2", Some("Other"), TitleSource::Auto)
+            .expect("set");
+        assert!(skipped.is_none());
+
+        let reloaded = ThreadTitles::load(path.clone());
+        let mut threads = vec![
+            json!({ "id": "t1" }),
+            json!({ "id": "t2" }),
+            json!({ "id": "t3" }),
+        ];
+        reloaded.annotate("ws", &mut threads);
+        assert_eq!(threads[0]["title"], json!("Faster builds"));
+        assert_eq!(threads[0]["titleSource"], json!("auto"));
+        assert_eq!(threads[1]["title"], json!("Crate rename"));
+        assert_eq!(threads[1]["titleSource"], json!("manual"));
+        assert!(threads[2].get("title").is_none());
+
+        reloaded
+            .set("ws", "t2", Some("  "), TitleSource::Manual)
+            .expect("clear");
+        let mut threads = vec![json!({ "id": "t2" })];
+        reloaded.annotate("ws", &mut threads);
+        assert!(threads[0].get("title").is_none());
+
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::remove_dir_all(parent);
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::WorkspaceEntry;
+
+use super::worktree_move::move_dir;
+
+/// Days a trashed workspace is kept when `trashRetentionDays` isn't set.
+pub(crate) const DEFAULT_TRASH_RETENTION_DAYS: u32 = 7;
+/// How often expired trash is purged.
+pub(crate) const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const TOMBSTONE_FILE: &str = "tombstone.json";
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// A removed workspace and whatever of it was moved into the trash.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TrashedWorkspace {
+    pub(crate) entry: WorkspaceEntry,
+    pub(crate) original_path: String,
+    pub(crate) branch: Option<String>,
+    /// Folder in the trash item holding the worktree; `None` when nothing was
+    /// moved, as for main workspaces, whose repo is never deleted.
+    pub(crate) folder: Option<String>,
+    /// Git's admin dir for the worktree (`<repo>/.git/worktrees/<name>`),
+    /// moved into the trash item as `git_dir_folder` so a restore can put the
+    /// worktree back with `git worktree repair`.
+    pub(crate) git_dir: Option<String>,
+    pub(crate) git_dir_folder: Option<String>,
+}
+
+/// What one removal put in the trash, saved as
+/// `<data-dir>/trash/<id>/tombstone.json`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Tombstone {
+    pub(crate) id: String,
+    pub(crate) deleted_at: u64,
+    /// Worktrees first, then their main workspace when it was removed too.
+    pub(crate) workspaces: Vec<TrashedWorkspace>,
+}
+
+/// A tombstone as `list_trash` reports it.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TrashItem {
+    #[serde(flatten)]
+    pub(crate) tombstone: Tombstone,
+    /// `None` when the trash is kept until emptied.
+    pub(crate) expires_at: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct RestoreSummary {
+    pub(crate) restored: Vec<String>,
+    /// Workspace id -> why it stayed in the trash.
+    pub(crate) failed: BTreeMap<String, String>,
+    pub(crate) warnings: Vec<String>,
+}
+
+pub(crate) fn trash_root(data_dir: &Path) -> PathBuf {
+    data_dir.join("trash")
+}
+
+/// Runs trash file work, which may move or delete whole worktrees, on a
+/// blocking thread.
+pub(crate) async fn blocking<T: Send + 'static>(
+    work: impl FnOnce() -> T + Send + 'static,
+) -> Result<T, String> {
+    tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|err| err.to_string())
+}
+
+/// Creates `<root>/<deleted_at>-<workspace_id>` and returns its id and path.
+pub(crate) fn create_item(
+    root: &Path,
+    deleted_at: u64,
+    workspace_id: &str,
+) -> Result<(String, PathBuf), String> {
+    let id = format!("{deleted_at}-{workspace_id}");
+    let dir = root.join(&id);
+    std::fs::create_dir_all(&dir).map_err(|err| format!("Failed to create trash folder: {err}"))?;
+    Ok((id, dir))
+}
+
+/// The folder of a trash item, refusing ids that would leave the trash.
+pub(crate) fn item_dir(root: &Path, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || id.contains(['/', '\\']) || id.starts_with('.') {
+        return Err(format!("invalid trash id: {id}"));
+    }
+    Ok(root.join(id))
+}
+
+pub(crate) fn write_tombstone(root: &Path, tombstone: &Tombstone) -> Result<(), String> {
+    let path = item_dir(root, &tombstone.id)?.join(TOMBSTONE_FILE);
+    let data = serde_json::to_string_pretty(tombstone).map_err(|e| e.to_string())?;
+    std::fs::write(path, data).map_err(|e| e.to_string())
+}
+
+/// Saves what a removal trashed, or drops its item when nothing was.
+pub(crate) fn finish_item(root: &Path, tombstone: &Tombstone) -> Result<(), String> {
+    if tombstone.workspaces.is_empty() {
+        let dir = item_dir(root, &tombstone.id)?;
+        return std::fs::remove_dir_all(dir).map_err(|e| e.to_string());
+    }
+    write_tombstone(root, tombstone)
+}
+
+pub(crate) fn read_tombstone(root: &Path, id: &str) -> Result<Tombstone, String> {
+    let path = item_dir(root, id)?.join(TOMBSTONE_FILE);
+    let data = std::fs::read_to_string(path).map_err(|_| format!("trash item not found: {id}"))?;
+    serde_json::from_str(&data).map_err(|err| format!("unreadable trash item {id}: {err}"))
+}
+
+/// Every readable tombstone, newest first.
+pub(crate) fn list_tombstones(root: &Path) -> Vec<Tombstone> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut tombstones: Vec<Tombstone> = entries
+        .flatten()
+        .filter_map(|entry| read_tombstone(root, &entry.file_name().to_string_lossy()).ok())
+        .collect();
+    tombstones.sort_by(|a, b| {
+        b.deleted_at
+            .cmp(&a.deleted_at)
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    tombstones
+}
+
+pub(crate) fn remove_item(root: &Path, id: &str) -> Result<(), String> {
+    let dir = item_dir(root, id)?;
+    if !dir.exists() {
+        return Err(format!("trash item not found: {id}"));
+    }
+    std::fs::remove_dir_all(dir).map_err(|err| format!("Failed to empty trash item {id}: {err}"))
+}
+
+/// When a tombstone is purged; `None` with a retention of 0 days.
+pub(crate) fn expires_at(tombstone: &Tombstone, retention_days: u32) -> Option<u64> {
+    (retention_days > 0).then(|| tombstone.deleted_at + u64::from(retention_days) * DAY_MS)
+}
+
+/// The worktree's admin dir, from the `gitdir:` line of its `.git` file.
+pub(crate) fn worktree_git_dir(worktree: &Path) -> Option<PathBuf> {
+    let contents = std::fs::read_to_string(worktree.join(".git")).ok()?;
+    let git_dir = contents
+        .lines()
+        .find_map(|line| line.strip_prefix("gitdir:"))
+        .map(str::trim)
+        .filter(|git_dir| !git_dir.is_empty())?;
+    Some(worktree.join(git_dir))
+}
+
+/// Moves a worktree into `item`, and its admin dir next to it so the
+/// `git worktree prune` that follows leaves nothing of it behind in the repo.
+pub(crate) fn trash_worktree(
+    item: &Path,
+    entry: &WorkspaceEntry,
+) -> Result<TrashedWorkspace, String> {
+    let path = Path::new(&entry.path);
+    let git_dir = worktree_git_dir(path).filter(|git_dir| git_dir.is_dir());
+    let folder = entry.id.clone();
+    move_dir(path, &item.join(&folder))?;
+    let mut trashed = TrashedWorkspace {
+        entry: entry.clone(),
+        original_path: entry.path.clone(),
+        branch: entry
+            .worktree
+            .as_ref()
+            .map(|worktree| worktree.branch.clone()),
+        folder: Some(folder),
+        git_dir: None,
+        git_dir_folder: None,
+    };
+    if let Some(git_dir) = git_dir {
+        let git_dir_folder = format!("{}.git", entry.id);
+        if move_dir(&git_dir, &item.join(&git_dir_folder)).is_ok() {
+            trashed.git_dir = Some(git_dir.to_string_lossy().to_string());
+            trashed.git_dir_folder = Some(git_dir_folder);
+        }
+    }
+    Ok(trashed)
+}
+
+/// Moves a trashed worktree back to its original path. The admin dir is put
+/// back on a best-effort basis; a failure there comes back as a warning since
+/// `git worktree repair` can't relink the worktree without it.
+pub(crate) fn restore_worktree(
+    item: &Path,
+    trashed: &TrashedWorkspace,
+) -> Result<Option<String>, String> {
+    let Some(folder) = &trashed.folder else {
+        return Ok(None);
+    };
+    move_dir(&item.join(folder), Path::new(&trashed.original_path))?;
+    let (Some(git_dir), Some(git_dir_folder)) = (&trashed.git_dir, &trashed.git_dir_folder) else {
+        return Ok(None);
+    };
+    Ok(move_dir(&item.join(git_dir_folder), Path::new(git_dir))
+        .err()
+        .map(|err| format!("{}: git metadata not restored: {err}", trashed.entry.id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        create_item, expires_at, item_dir, list_tombstones, move_dir, read_tombstone, remove_item,
+        restore_worktree, trash_worktree, worktree_git_dir, write_tombstone, Tombstone,
+        TrashedWorkspace, DAY_MS,
+    };
+    use crate::types::{WorkspaceEntry, WorkspaceKind, WorkspaceSettings, WorktreeInfo};
+    use uuid::Uuid;
+
+    fn tombstone(id: &str, deleted_at: u64) -> Tombstone {
+        let entry = WorkspaceEntry {
+            id: "wt".to_string(),
+            name: "feature".to_string(),
+            path: "/repo/worktrees/feature".to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Worktree,
+            parent_id: Some("main".to_string()),
+            worktree: None,
+            settings: WorkspaceSettings::default(),
+            status: None,
+        };
+        Tombstone {
+            id: id.to_string(),
+            deleted_at,
+            workspaces: vec![TrashedWorkspace {
+                original_path: entry.path.clone(),
+                entry,
+                branch: Some("feature".to_string()),
+                folder: Some("wt".to_string()),
+                git_dir: None,
+                git_dir_folder: None,
+            }],
+        }
+    }
+
+    #[test]
+    fn stores_lists_and_removes_tombstones() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-trash-{}", Uuid::new_v4()));
+        let (older, _) = create_item(&root, 1_000, "wt").expect("create");
+        let (newer, _) = create_item(&root, 2_000, "wt").expect("create");
+        write_tombstone(&root, &tombstone(&older, 1_000)).expect("write");
+        write_tombstone(&root, &tombstone(&newer, 2_000)).expect("write");
+        std::fs::create_dir_all(root.join("no-tombstone")).expect("create");
+
+        let ids: Vec<String> = list_tombstones(&root).into_iter().map(|t| t.id).collect();
+        assert_eq!(ids, [newer.clone(), older.clone()]);
+        let read = read_tombstone(&root, &older).expect("read");
+        assert_eq!(read.workspaces[0].branch.as_deref(), Some("feature"));
+        assert!(item_dir(&root, "../escape").is_err());
+        assert!(read_tombstone(&root, "missing").is_err());
+
+        remove_item(&root, &older).expect("remove");
+        assert_eq!(list_tombstones(&root).len(), 1);
+        assert!(remove_item(&root, &older).is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn expiry_follows_the_retention() {
+        let stone = tombstone("1-wt", 1_000);
+        assert_eq!(expires_at(&stone, 7), Some(1_000 + 7 * DAY_MS));
+        assert_eq!(expires_at(&stone, 0), None);
+    }
+
+    #[test]
+    fn moves_folders_and_finds_worktree_git_dirs() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-trash-{}", Uuid::new_v4()));
+        let worktree = root.join("worktree");
+        std::fs::create_dir_all(worktree.join("src")).expect("create");
+        std::fs::write(worktree.join("src/main.rs"), "fn main() {}").expect("write");
+        std::fs::write(
+            worktree.join(".git"),
+            "gitdir: ../repo/.git/worktrees/feature\n",
+        )
+        .expect("write");
+        assert_eq!(
+            worktree_git_dir(&worktree),
+            Some(worktree.join("../repo/.git/worktrees/feature"))
+        );
+        assert_eq!(worktree_git_dir(&root), None);
+
+        let moved = root.join("trash/item/worktree");
+        move_dir(&worktree, &moved).expect("move");
+        assert!(!worktree.exists());
+        assert!(moved.join("src/main.rs").is_file());
+        std::fs::create_dir_all(&worktree).expect("create");
+        assert!(move_dir(&moved, &worktree).is_err());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn trashes_and_restores_a_worktree_with_its_git_dir() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-trash-{}", Uuid::new_v4()));
+        let git_dir = root.join("repo/.git/worktrees/feature");
+        let worktree = root.join("worktrees/feature");
+        std::fs::create_dir_all(&git_dir).expect("create");
+        std::fs::create_dir_all(&worktree).expect("create");
+        std::fs::write(git_dir.join("HEAD"), "ref: refs/heads/feature\n").expect("write");
+        std::fs::write(
+            worktree.join(".git"),
+            format!("gitdir: {}\n", git_dir.display()),
+        )
+        .expect("write");
+        let entry = WorkspaceEntry {
+            id: "wt".to_string(),
+            name: "feature".to_string(),
+            path: worktree.to_string_lossy().to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Worktree,
+            parent_id: Some("main".to_string()),
+            worktree: Some(WorktreeInfo {
+                branch: "feature".to_string(),
+                dir_name: None,
+            }),
+            settings: WorkspaceSettings::default(),
+            status: None,
+        };
+
+        let (_, item) = create_item(&root.join("trash"), 1_000, "wt").expect("create");
+        let trashed = trash_worktree(&item, &entry).expect("trash");
+        assert!(!worktree.exists() && !git_dir.exists());
+        assert_eq!(trashed.branch.as_deref(), Some("feature"));
+        assert_eq!(trashed.git_dir_folder.as_deref(), Some("wt.git"));
+
+        assert_eq!(restore_worktree(&item, &trashed).expect("restore"), None);
+        assert!(worktree.join(".git").is_file());
+        assert!(git_dir.join("HEAD").is_file());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
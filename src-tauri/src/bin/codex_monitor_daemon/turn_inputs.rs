@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::snapshot::SnapshotFile;
+use super::thread_list::clamp_page_size;
+
+/// Inputs kept per thread; older ones are dropped first.
+const MAX_INPUTS_PER_THREAD: usize = 200;
+
+/// What a client sent to start one turn, with the access mode that applied.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TurnInput {
+    pub(crate) turn_id: String,
+    pub(crate) at: u64,
+    pub(crate) text: String,
+    #[serde(default)]
+    pub(crate) images: Vec<String>,
+    #[serde(default)]
+    pub(crate) model: Option<String>,
+    #[serde(default)]
+    pub(crate) effort: Option<String>,
+    /// The mode the turn ran with, after the workspace's
+    /// `defaultAccessMode` filled in a missing or unknown one.
+    #[serde(default)]
+    pub(crate) access_mode: Option<String>,
+    #[serde(default)]
+    pub(crate) collaboration_mode: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TurnInputPage {
+    /// Newest first.
+    pub(crate) inputs: Vec<TurnInput>,
+    pub(crate) next_cursor: Option<String>,
+    /// The workspace doesn't keep turn inputs.
+    pub(crate) disabled: bool,
+}
+
+/// Workspace id -> thread id -> inputs, oldest first.
+type InputsByWorkspace = HashMap<String, HashMap<String, Vec<TurnInput>>>;
+
+/// The input of each turn the daemon started, saved in
+/// `<data-dir>/turn_inputs.json` so clients can resend or edit it later.
+pub(crate) struct TurnInputs {
+    file: SnapshotFile,
+    inputs: Mutex<InputsByWorkspace>,
+}
+
+/// The turn id in a `turn/start` response, with or without the `result`
+/// wrapper.
+pub(crate) fn started_turn_id(response: &Value) -> Option<&str> {
+    let turn = response
+        .get("result")
+        .and_then(|result| result.get("turn"))
+        .or_else(|| response.get("turn"))?;
+    turn.get("id")
+        .and_then(Value::as_str)
+        .filter(|id| !id.is_empty())
+}
+
+impl TurnInputs {
+    pub(crate) fn load(path: PathBuf) -> Self {
+        let inputs = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            file: SnapshotFile::new(path, "turn inputs"),
+            inputs: Mutex::new(inputs),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, InputsByWorkspace> {
+        self.inputs.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    /// Stores `input` under its thread; a turn id seen before is replaced.
+    pub(crate) fn record(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        input: TurnInput,
+    ) -> Result<(), String> {
+        let mut inputs = self.lock();
+        let thread = inputs
+            .entry(workspace_id.to_string())
+            .or_default()
+            .entry(thread_id.to_string())
+            .or_default();
+        thread.retain(|stored| stored.turn_id != input.turn_id);
+        thread.push(input);
+        if thread.len() > MAX_INPUTS_PER_THREAD {
+            let excess = thread.len() - MAX_INPUTS_PER_THREAD;
+            thread.drain(..excess);
+        }
+        self.persist(&inputs)
+    }
+
+    /// A page of a thread's inputs, newest first. `cursor` is the turn id of
+    /// the last input of the previous page.
+    pub(crate) fn page(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        cursor: Option<&str>,
+        limit: Option<u32>,
+    ) -> Result<TurnInputPage, String> {
+        let inputs = self.lock();
+        let stored = inputs
+            .get(workspace_id)
+            .and_then(|threads| threads.get(thread_id))
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let newest_first: Vec<&TurnInput> = stored.iter().rev().collect();
+        let start = match cursor {
+            Some(cursor) => {
+                newest_first
+                    .iter()
+                    .position(|input| input.turn_id == cursor)
+                    .ok_or_else(|| format!("unknown cursor: {cursor}"))?
+                    + 1
+            }
+            None => 0,
+        };
+        let limit = clamp_page_size(limit) as usize;
+        let page: Vec<TurnInput> = newest_first
+            .iter()
+            .skip(start)
+            .take(limit)
+            .map(|input| (*input).clone())
+            .collect();
+        let next_cursor = if start + page.len() < newest_first.len() {
+            page.last().map(|input| input.turn_id.clone())
+        } else {
+            None
+        };
+        Ok(TurnInputPage {
+            inputs: page,
+            next_cursor,
+            disabled: false,
+        })
+    }
+
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        let mut inputs = self.lock();
+        if inputs.remove(workspace_id).is_some() {
+            let _ = self.persist(&inputs);
+        }
+    }
+
+    /// Saves `inputs`, which the caller holds locked; inside a tokio runtime
+    /// on a blocking thread.
+    fn persist(&self, inputs: &InputsByWorkspace) -> Result<(), String> {
+        let data = serde_json::to_string_pretty(inputs).map_err(|e| e.to_string())?;
+        self.file.save(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{started_turn_id, TurnInput, TurnInputs};
+    use serde_json::json;
+    use uuid::Uuid;
+
+    fn input(turn_id: &str, text: &str) -> TurnInput {
+        TurnInput {
+            turn_id: turn_id.to_string(),
+            at: 0,
+            text: text.to_string(),
+            images: Vec::new(),
+            model: Some("gpt-5".to_string()),
+            effort: None,
+            access_mode: Some("read-only".to_string()),
+            collaboration_mode: None,
+        }
+    }
+
+    #[test]
+    fn reads_the_turn_id_of_start_responses() {
+        assert_eq!(
+            started_turn_id(&json!({ "result": { "turn": { "id": "t1" } } })),
+            Some("t1")
+        );
+        assert_eq!(
+            started_turn_id(&json!({ "turn": { "id": "t2" } })),
+            Some("t2")
+        );
+        assert_eq!(
+            started_turn_id(&json!({ "error": { "message": "no" } })),
+            None
+        );
+    }
+
+    #[test]
+    fn pages_inputs_newest_first_and_survives_reload() {
+        let path = std::env::temp_dir()
+            .join(format!("codex-monitor-turn-inputs-{}", Uuid::new_v4()))
+            .join("turn_inputs.json");
+        let inputs = TurnInputs::load(path.clone());
+        for turn in ["t1", "t2", "t3"] {
+            inputs
+                .record("ws", "thread", input(turn, &format!("prompt {turn}")))
+                .expect("record");
+        }
+        inputs
+            .record("ws", "thread", input("t2", "edited"))
+            .expect("record");
+
+        let first = inputs.page("ws", "thread", None, Some(2)).expect("page");
+        let ids: Vec<_> = first
+            .inputs
+            .iter()
+            .map(|input| input.turn_id.as_str())
+            .collect();
+        assert_eq!(ids, ["t2", "t3"]);
+        assert_eq!(first.inputs[0].text, "edited");
+        assert_eq!(first.next_cursor.as_deref(), Some("t3"));
+
+        let reloaded = TurnInputs::load(path.clone());
+        let second = reloaded
+            .page("ws", "thread", first.next_cursor.as_deref(), Some(2))
+            .expect("page");
+        assert_eq!(second.inputs.len(), 1);
+        assert_eq!(second.inputs[0].turn_id, "t1");
+        assert_eq!(second.next_cursor, None);
+        assert_eq!(second.inputs[0].text, "prompt t1");
+        assert!(reloaded.page("ws", "thread", Some("gone"), None).is_err());
+
+        reloaded.forget("ws");
+        assert!(TurnInputs::load(path.clone())
+            .page("ws", "thread", None, None)
+            .expect("page")
+            .inputs
+            .is_empty());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,588 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+use super::fetch::now_ms;
+
+/// Turns one app-server session runs at once unless `--max-turns-per-session`
+/// says otherwise.
+pub(crate) const DEFAULT_MAX_TURNS_PER_SESSION: u32 = 8;
+/// Ended turns kept per workspace for `wait_turn`; older ones are dropped.
+const MAX_ENDED_TURNS: usize = 200;
+const DEFAULT_WAIT_TURN_TIMEOUT_MS: u64 = 10 * 60 * 1000;
+const MAX_WAIT_TURN_TIMEOUT_MS: u64 = 60 * 60 * 1000;
+
+/// How a turn ended, as `wait_turn` reports it.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct TurnOutcome {
+    pub(crate) thread_id: String,
+    pub(crate) turn_id: String,
+    /// The turn's `status` from `turn/completed`, such as `completed`,
+    /// `interrupted` or `failed`; `completed` when it sends none.
+    pub(crate) status: String,
+    pub(crate) error: Option<Value>,
+    pub(crate) ended_at: u64,
+}
+
+/// Threads with a running turn per workspace, from the app-server's
+/// `turn/started` and `turn/completed` events plus turns this daemon has
+/// just started and not yet heard back about.
+#[derive(Default)]
+pub(crate) struct TurnTracker {
+    /// Workspace id -> thread id -> turn id, `None` until `turn/started`.
+    active: Mutex<HashMap<String, HashMap<String, Option<String>>>>,
+    queued: Mutex<HashMap<String, usize>>,
+    /// Workspace id -> ended turns, oldest first.
+    ended: Mutex<HashMap<String, VecDeque<TurnOutcome>>>,
+    /// Notified whenever a turn ends, a workspace's turns are dropped or a
+    /// queued message leaves the line.
+    freed: Notify,
+    /// Workspace id -> tickets of the messages waiting for a slot, in the
+    /// order they arrived.
+    waiting: Mutex<HashMap<String, VecDeque<u64>>>,
+    next_ticket: AtomicU64,
+}
+
+/// A message's place in its workspace's line; leaving it on drop lets the
+/// next one try for a slot.
+struct Ticket<'a> {
+    tracker: &'a TurnTracker,
+    workspace_id: &'a str,
+    id: u64,
+}
+
+impl Drop for Ticket<'_> {
+    fn drop(&mut self) {
+        let mut waiting = self
+            .tracker
+            .waiting
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        if let Some(tickets) = waiting.get_mut(self.workspace_id) {
+            tickets.retain(|ticket| *ticket != self.id);
+            if tickets.is_empty() {
+                waiting.remove(self.workspace_id);
+            }
+        }
+        drop(waiting);
+        self.tracker.freed.notify_waiters();
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Slot {
+    /// A new turn was counted and must be released if starting it fails.
+    Claimed,
+    /// The thread already has a running turn, which the message joins.
+    AlreadyRunning,
+    Busy,
+}
+
+pub(crate) fn thread_id(params: &Value) -> Option<&str> {
+    let turn = params.get("turn");
+    ["threadId", "thread_id"]
+        .iter()
+        .find_map(|key| {
+            params
+                .get(key)
+                .or_else(|| turn.and_then(|turn| turn.get(key)))
+                .and_then(Value::as_str)
+        })
+        .filter(|id| !id.is_empty())
+}
+
+fn turn_id(params: &Value) -> Option<&str> {
+    params
+        .get("turn")
+        .and_then(|turn| turn.get("id"))
+        .or_else(|| params.get("turnId"))
+        .and_then(Value::as_str)
+        .filter(|id| !id.is_empty())
+}
+
+pub(crate) fn wait_turn_timeout(timeout_ms: Option<u64>) -> Duration {
+    let ms = timeout_ms
+        .unwrap_or(DEFAULT_WAIT_TURN_TIMEOUT_MS)
+        .min(MAX_WAIT_TURN_TIMEOUT_MS);
+    Duration::from_millis(ms)
+}
+
+fn completed_outcome(thread_id: &str, turn_id: &str, params: &Value) -> TurnOutcome {
+    let turn = params.get("turn");
+    TurnOutcome {
+        thread_id: thread_id.to_string(),
+        turn_id: turn_id.to_string(),
+        status: turn
+            .and_then(|turn| turn.get("status"))
+            .and_then(Value::as_str)
+            .unwrap_or("completed")
+            .to_string(),
+        error: turn
+            .and_then(|turn| turn.get("error"))
+            .filter(|error| !error.is_null())
+            .cloned(),
+        ended_at: now_ms(),
+    }
+}
+
+/// The tighter of a workspace's `maxConcurrentTurns` and the daemon's
+/// per-session limit.
+pub(crate) fn turn_cap(workspace: Option<u32>, session: Option<u32>) -> Option<u32> {
+    match (workspace, session) {
+        (Some(workspace), Some(session)) => Some(workspace.min(session)),
+        (cap, None) | (None, cap) => cap,
+    }
+}
+
+impl TurnTracker {
+    pub(crate) fn record_event(&self, workspace_id: &str, message: &Value) {
+        let method = message.get("method").and_then(Value::as_str);
+        let started = match method {
+            Some("turn/started") => true,
+            Some("turn/completed") => false,
+            _ => return,
+        };
+        let Some(params) = message.get("params") else {
+            return;
+        };
+        let Some(thread_id) = thread_id(params) else {
+            return;
+        };
+        if started {
+            let mut active = self.active.lock().unwrap_or_else(|err| err.into_inner());
+            active
+                .entry(workspace_id.to_string())
+                .or_default()
+                .insert(thread_id.to_string(), turn_id(params).map(str::to_string));
+        } else {
+            if let Some(turn_id) = turn_id(params) {
+                self.record_end(workspace_id, completed_outcome(thread_id, turn_id, params));
+            }
+            self.finish(workspace_id, thread_id);
+        }
+    }
+
+    fn record_end(&self, workspace_id: &str, outcome: TurnOutcome) {
+        let mut ended = self.ended.lock().unwrap_or_else(|err| err.into_inner());
+        let turns = ended.entry(workspace_id.to_string()).or_default();
+        if turns.len() >= MAX_ENDED_TURNS {
+            turns.pop_front();
+        }
+        turns.push_back(outcome);
+    }
+
+    fn ended_turn(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        turn_id: &str,
+    ) -> Option<TurnOutcome> {
+        let ended = self.ended.lock().unwrap_or_else(|err| err.into_inner());
+        ended
+            .get(workspace_id)?
+            .iter()
+            .rev()
+            .find(|outcome| outcome.thread_id == thread_id && outcome.turn_id == turn_id)
+            .cloned()
+    }
+
+    /// Whether the thread runs `turn_id`. A claimed slot only counts once
+    /// its turn id is known, from `turn/started` or `record_turn_id`.
+    fn is_running(&self, workspace_id: &str, thread_id: &str, turn_id: &str) -> bool {
+        let active = self.active.lock().unwrap_or_else(|err| err.into_inner());
+        active
+            .get(workspace_id)
+            .and_then(|threads| threads.get(thread_id))
+            .is_some_and(|running| running.as_deref() == Some(turn_id))
+    }
+
+    /// Names the turn of a claimed slot once `turn/start` answers, which may
+    /// be before the app-server sends `turn/started`. Turns that already
+    /// ended are left alone.
+    pub(crate) fn record_turn_id(&self, workspace_id: &str, thread_id: &str, turn_id: &str) {
+        let mut active = self.active.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(running) = active
+            .get_mut(workspace_id)
+            .and_then(|threads| threads.get_mut(thread_id))
+        {
+            running.get_or_insert_with(|| turn_id.to_string());
+        }
+    }
+
+    /// Waits up to `limit` for a turn to end. `Ok(None)` if it still runs
+    /// by then; an error if the turn neither runs nor is among the recently
+    /// ended ones.
+    pub(crate) async fn wait_for_end(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        turn_id: &str,
+        limit: Duration,
+    ) -> Result<Option<TurnOutcome>, String> {
+        let deadline = Instant::now() + limit;
+        loop {
+            let changed = self.freed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+            if let Some(outcome) = self.ended_turn(workspace_id, thread_id, turn_id) {
+                return Ok(Some(outcome));
+            }
+            if !self.is_running(workspace_id, thread_id, turn_id) {
+                return Err(format!("turn not found: {turn_id}"));
+            }
+            if tokio::time::timeout_at(deadline, changed).await.is_err() {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// Claims a slot for a turn on `thread_id` unless `cap` turns already run
+    /// or queued messages wait for one.
+    pub(crate) fn try_start(&self, workspace_id: &str, thread_id: &str, cap: Option<u32>) -> Slot {
+        let waiting = self.waiting.lock().unwrap_or_else(|err| err.into_inner());
+        let queued_ahead = waiting.contains_key(workspace_id);
+        self.claim(workspace_id, thread_id, cap, queued_ahead)
+    }
+
+    fn claim(&self, workspace_id: &str, thread_id: &str, cap: Option<u32>, busy: bool) -> Slot {
+        let mut active = self.active.lock().unwrap_or_else(|err| err.into_inner());
+        let threads = active.entry(workspace_id.to_string()).or_default();
+        if threads.contains_key(thread_id) {
+            return Slot::AlreadyRunning;
+        }
+        if busy || cap.is_some_and(|cap| threads.len() >= cap.max(1) as usize) {
+            return Slot::Busy;
+        }
+        threads.insert(thread_id.to_string(), None);
+        Slot::Claimed
+    }
+
+    /// Claims a slot for the message holding `ticket` once it is first in
+    /// line; `None` while it has to wait.
+    fn claim_in_turn(
+        &self,
+        ticket: &Ticket<'_>,
+        thread_id: &str,
+        cap: Option<u32>,
+    ) -> Option<Slot> {
+        let waiting = self.waiting.lock().unwrap_or_else(|err| err.into_inner());
+        let first = waiting
+            .get(ticket.workspace_id)
+            .and_then(|tickets| tickets.front());
+        if first != Some(&ticket.id) {
+            return None;
+        }
+        match self.claim(ticket.workspace_id, thread_id, cap, false) {
+            Slot::Busy => None,
+            slot => Some(slot),
+        }
+    }
+
+    /// Waits for a slot behind the messages queued before; the caller counts
+    /// as queued meanwhile. Messages of a workspace start in the order they
+    /// were queued.
+    pub(crate) async fn start_when_free(
+        &self,
+        workspace_id: &str,
+        thread_id: &str,
+        cap: Option<u32>,
+    ) -> Slot {
+        let ticket = Ticket {
+            tracker: self,
+            workspace_id,
+            id: self.next_ticket.fetch_add(1, Ordering::Relaxed),
+        };
+        self.waiting
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .entry(workspace_id.to_string())
+            .or_default()
+            .push_back(ticket.id);
+        let slot = loop {
+            let freed = self.freed.notified();
+            tokio::pin!(freed);
+            freed.as_mut().enable();
+            match self.claim_in_turn(&ticket, thread_id, cap) {
+                Some(slot) => break slot,
+                None => freed.await,
+            }
+        };
+        drop(ticket);
+        let mut queued = self.queued.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(count) = queued.get_mut(workspace_id) {
+            *count = count.saturating_sub(1);
+        }
+        slot
+    }
+
+    /// Counts a message waiting for a slot; returns its place in line.
+    pub(crate) fn enqueue(&self, workspace_id: &str) -> usize {
+        let mut queued = self.queued.lock().unwrap_or_else(|err| err.into_inner());
+        let count = queued.entry(workspace_id.to_string()).or_default();
+        *count += 1;
+        *count
+    }
+
+    pub(crate) fn finish(&self, workspace_id: &str, thread_id: &str) {
+        let mut active = self.active.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(threads) = active.get_mut(workspace_id) {
+            threads.remove(thread_id);
+        }
+        drop(active);
+        self.freed.notify_waiters();
+    }
+
+    pub(crate) fn active_count(&self, workspace_id: &str) -> u32 {
+        let active = self.active.lock().unwrap_or_else(|err| err.into_inner());
+        active
+            .get(workspace_id)
+            .map_or(0, |threads| threads.len() as u32)
+    }
+
+    /// `(thread id, turn id)` of the workspace's turns the app-server has
+    /// reported started, sorted.
+    pub(crate) fn running_turns(&self, workspace_id: &str) -> Vec<(String, String)> {
+        let active = self.active.lock().unwrap_or_else(|err| err.into_inner());
+        let mut turns: Vec<(String, String)> = active
+            .get(workspace_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|(thread_id, turn_id)| Some((thread_id.clone(), turn_id.clone()?)))
+            .collect();
+        turns.sort();
+        turns
+    }
+
+    pub(crate) fn queued_count(&self, workspace_id: &str) -> u32 {
+        let queued = self.queued.lock().unwrap_or_else(|err| err.into_inner());
+        queued.get(workspace_id).copied().unwrap_or_default() as u32
+    }
+
+    /// Drops the workspace's turns when its session goes away. Turns the
+    /// app-server had started are recorded as `failed`.
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        let mut active = self.active.lock().unwrap_or_else(|err| err.into_inner());
+        let threads = active.remove(workspace_id).unwrap_or_default();
+        drop(active);
+        for (thread_id, turn_id) in threads {
+            let Some(turn_id) = turn_id else {
+                continue;
+            };
+            self.record_end(
+                workspace_id,
+                TurnOutcome {
+                    thread_id,
+                    turn_id,
+                    status: "failed".to_string(),
+                    error: Some(Value::String("the workspace session closed".to_string())),
+                    ended_at: now_ms(),
+                },
+            );
+        }
+        self.freed.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{turn_cap, wait_turn_timeout, Slot, TurnTracker};
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn caps_turns_per_workspace() {
+        let turns = TurnTracker::default();
+        assert_eq!(turns.try_start("ws", "t1", Some(1)), Slot::Claimed);
+        assert_eq!(turns.try_start("ws", "t1", Some(1)), Slot::AlreadyRunning);
+        assert_eq!(turns.try_start("ws", "t2", Some(1)), Slot::Busy);
+        assert_eq!(turns.try_start("other", "t2", Some(1)), Slot::Claimed);
+        assert_eq!(turns.try_start("ws", "t2", None), Slot::Claimed);
+        assert_eq!(turns.active_count("ws"), 2);
+
+        turns.record_event(
+            "ws",
+            &json!({ "method": "turn/completed", "params": { "turn": { "threadId": "t1" } } }),
+        );
+        turns.record_event(
+            "ws",
+            &json!({ "method": "turn/completed", "params": { "threadId": "t2" } }),
+        );
+        assert_eq!(turns.active_count("ws"), 0);
+        turns.record_event(
+            "ws",
+            &json!({ "method": "turn/started", "params": { "threadId": "t3" } }),
+        );
+        assert_eq!(turns.active_count("ws"), 1);
+        assert!(turns.running_turns("ws").is_empty());
+        turns.record_event(
+            "ws",
+            &json!({
+                "method": "turn/started",
+                "params": { "threadId": "t4", "turn": { "id": "turn-4" } },
+            }),
+        );
+        assert_eq!(
+            turns.running_turns("ws"),
+            [("t4".to_string(), "turn-4".to_string())]
+        );
+    }
+
+    #[test]
+    fn session_limit_tightens_the_workspace_cap() {
+        assert_eq!(turn_cap(None, None), None);
+        assert_eq!(turn_cap(Some(3), None), Some(3));
+        assert_eq!(turn_cap(None, Some(8)), Some(8));
+        assert_eq!(turn_cap(Some(3), Some(8)), Some(3));
+        assert_eq!(turn_cap(Some(12), Some(8)), Some(8));
+
+        let turns = TurnTracker::default();
+        let cap = turn_cap(None, Some(2));
+        assert_eq!(turns.try_start("ws", "t1", cap), Slot::Claimed);
+        assert_eq!(turns.try_start("ws", "t2", cap), Slot::Claimed);
+        assert_eq!(turns.try_start("ws", "t3", cap), Slot::Busy);
+    }
+
+    #[tokio::test]
+    async fn queued_turns_start_when_a_slot_frees() {
+        let turns = Arc::new(TurnTracker::default());
+        assert_eq!(turns.try_start("ws", "t1", Some(1)), Slot::Claimed);
+        assert_eq!(turns.enqueue("ws"), 1);
+
+        let waiter = {
+            let turns = Arc::clone(&turns);
+            tokio::spawn(async move { turns.start_when_free("ws", "t2", Some(1)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+        assert_eq!(turns.queued_count("ws"), 1);
+
+        turns.finish("ws", "t1");
+        let slot = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("slot freed")
+            .expect("waiter");
+        assert_eq!(slot, Slot::Claimed);
+        assert_eq!(turns.queued_count("ws"), 0);
+        assert_eq!(turns.active_count("ws"), 1);
+    }
+
+    #[tokio::test]
+    async fn queued_turns_start_in_order() {
+        let turns = Arc::new(TurnTracker::default());
+        assert_eq!(turns.try_start("ws", "t1", Some(1)), Slot::Claimed);
+        let queue = |thread_id: &'static str| {
+            let turns = Arc::clone(&turns);
+            tokio::spawn(async move { turns.start_when_free("ws", thread_id, Some(1)).await })
+        };
+        let mut waiters = Vec::new();
+        for thread_id in ["t2", "t3", "t4"] {
+            waiters.push(queue(thread_id));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let mut running = "t1";
+        for (waiter, thread_id) in waiters.into_iter().zip(["t2", "t3", "t4"]) {
+            turns.finish("ws", running);
+            let slot = tokio::time::timeout(Duration::from_secs(1), waiter)
+                .await
+                .expect("slot freed")
+                .expect("waiter");
+            assert_eq!(slot, Slot::Claimed);
+            assert_eq!(turns.active_count("ws"), 1);
+            assert_eq!(turns.try_start("ws", thread_id, None), Slot::AlreadyRunning);
+            if thread_id != "t4" {
+                // Messages that didn't queue wait behind the ones that did.
+                assert_eq!(turns.try_start("ws", "t9", None), Slot::Busy);
+            }
+            running = thread_id;
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        turns.finish("ws", running);
+        assert_eq!(turns.try_start("ws", "t9", Some(1)), Slot::Claimed);
+    }
+
+    #[tokio::test]
+    async fn waits_for_a_turn_to_end() {
+        let turns = Arc::new(TurnTracker::default());
+        assert!(turns
+            .wait_for_end("ws", "t1", "u1", Duration::ZERO)
+            .await
+            .is_err());
+        assert_eq!(turns.try_start("ws", "t1", None), Slot::Claimed);
+        assert_eq!(
+            turns.wait_for_end("ws", "t1", "u1", Duration::ZERO).await,
+            Err("turn not found: u1".to_string())
+        );
+        turns.record_turn_id("ws", "t1", "u1");
+        assert_eq!(
+            turns
+                .wait_for_end("ws", "t1", "u1", Duration::from_millis(10))
+                .await,
+            Ok(None)
+        );
+        assert!(turns
+            .wait_for_end("ws", "t1", "u9", Duration::ZERO)
+            .await
+            .is_err());
+
+        let waiter = {
+            let turns = Arc::clone(&turns);
+            tokio::spawn(async move {
+                turns
+                    .wait_for_end("ws", "t1", "u1", Duration::from_secs(5))
+                    .await
+            })
+        };
+        turns.record_event(
+            "ws",
+            &json!({ "method": "turn/started", "params": { "threadId": "t1", "turn": { "id": "u1" } } }),
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiter.is_finished());
+        turns.record_event(
+            "ws",
+            &json!({
+                "method": "turn/completed",
+                "params": {
+                    "threadId": "t1",
+                    "turn": { "id": "u1", "status": "failed", "error": { "message": "boom" } },
+                },
+            }),
+        );
+        let outcome = tokio::time::timeout(Duration::from_secs(1), waiter)
+            .await
+            .expect("turn ended")
+            .expect("waiter")
+            .expect("known turn")
+            .expect("outcome");
+        assert_eq!(outcome.status, "failed");
+        assert_eq!(outcome.error, Some(json!({ "message": "boom" })));
+        let again = turns
+            .wait_for_end("ws", "t1", "u1", Duration::ZERO)
+            .await
+            .expect("known turn");
+        assert_eq!(again, Some(outcome));
+
+        turns.record_event(
+            "ws",
+            &json!({ "method": "turn/started", "params": { "threadId": "t2", "turn": { "id": "u2" } } }),
+        );
+        turns.forget("ws");
+        let closed = turns
+            .wait_for_end("ws", "t2", "u2", Duration::ZERO)
+            .await
+            .expect("known turn")
+            .expect("outcome");
+        assert_eq!(closed.status, "failed");
+
+        assert_eq!(wait_turn_timeout(None), Duration::from_secs(600));
+        assert_eq!(wait_turn_timeout(Some(u64::MAX)), Duration::from_secs(3600));
+    }
+}
@@ -0,0 +1,805 @@
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use super::fetch::now_ms;
+
+/// Event methods posted when `--webhook-events` isn't given.
+pub(crate) const DEFAULT_WEBHOOK_EVENTS: [&str; 3] =
+    ["turn/completed", "error", "operation-failed"];
+/// Events waiting to be posted to one endpoint; more are dropped until it
+/// catches up.
+const QUEUE_SIZE: usize = 256;
+const MAX_ATTEMPTS: u32 = 4;
+const FIRST_RETRY: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Delivery attempts kept for `list_webhook_deliveries`.
+const DELIVERY_LOG_SIZE: usize = 200;
+/// Id of the endpoint given with `--webhook-url`.
+pub(crate) const CLI_ENDPOINT_ID: &str = "cli";
+const TEST_EVENT: &str = "webhook-test";
+
+#[derive(Debug, Clone)]
+pub(crate) struct WebhookConfig {
+    pub(crate) url: String,
+    /// Notification methods to post. App-server events are matched by the
+    /// method of the codex message they carry, e.g. `turn/completed`.
+    pub(crate) events: BTreeSet<String>,
+    pub(crate) secret: Option<String>,
+}
+
+/// A URL selected events are posted to, kept in `webhooks.json`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WebhookEndpoint {
+    pub(crate) id: String,
+    pub(crate) url: String,
+    /// Key of the `X-CodexMonitor-Signature` HMAC; posts are unsigned
+    /// without one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) secret: Option<String>,
+    /// Event methods to post; `DEFAULT_WEBHOOK_EVENTS` when empty.
+    #[serde(default)]
+    pub(crate) events: BTreeSet<String>,
+    /// Workspaces whose events are posted; every workspace when empty.
+    #[serde(default)]
+    pub(crate) workspace_ids: BTreeSet<String>,
+}
+
+impl WebhookEndpoint {
+    /// Whether an event goes to this endpoint. With a workspace filter,
+    /// events that aren't about a workspace are left out.
+    fn wants(&self, method: &str, workspace_id: Option<&str>) -> bool {
+        let event = if self.events.is_empty() {
+            DEFAULT_WEBHOOK_EVENTS.contains(&method)
+        } else {
+            self.events.contains(method)
+        };
+        let workspace = self.workspace_ids.is_empty()
+            || workspace_id.is_some_and(|id| self.workspace_ids.contains(id));
+        event && workspace
+    }
+}
+
+/// An endpoint as `list_webhooks` shows it, without the secret.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WebhookEndpointInfo {
+    pub(crate) id: String,
+    pub(crate) url: String,
+    pub(crate) events: BTreeSet<String>,
+    pub(crate) workspace_ids: BTreeSet<String>,
+    pub(crate) signed: bool,
+    /// Set with `--webhook-url`; `set_webhook` and `remove_webhook` can't
+    /// change it.
+    pub(crate) read_only: bool,
+}
+
+impl WebhookEndpointInfo {
+    fn new(endpoint: &WebhookEndpoint) -> Self {
+        Self {
+            id: endpoint.id.clone(),
+            url: endpoint.url.clone(),
+            events: endpoint.events.clone(),
+            workspace_ids: endpoint.workspace_ids.clone(),
+            signed: endpoint.secret.is_some(),
+            read_only: endpoint.id == CLI_ENDPOINT_ID,
+        }
+    }
+}
+
+/// What `set_webhook` changes; `None` keeps the current value.
+#[derive(Debug, Default)]
+pub(crate) struct WebhookUpdate {
+    /// A new endpoint is created when missing.
+    pub(crate) id: Option<String>,
+    pub(crate) url: Option<String>,
+    /// An empty secret removes it.
+    pub(crate) secret: Option<String>,
+    pub(crate) events: Option<Vec<String>>,
+    pub(crate) workspace_ids: Option<Vec<String>>,
+}
+
+/// One attempt at posting an event, as `list_webhook_deliveries` shows it.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WebhookDelivery {
+    /// Shared by the retries of one event.
+    pub(crate) delivery_id: String,
+    pub(crate) endpoint_id: String,
+    pub(crate) url: String,
+    pub(crate) event: String,
+    pub(crate) attempt: u32,
+    pub(crate) at: u64,
+    pub(crate) duration_ms: u64,
+    /// HTTP status of the answer; `None` when the request failed.
+    pub(crate) status: Option<u16>,
+    pub(crate) ok: bool,
+    pub(crate) error: Option<String>,
+}
+
+pub(crate) fn parse_webhook_url(value: &str) -> Result<String, String> {
+    let url = value.trim();
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err("--webhook-url requires an http:// or https:// URL".to_string());
+    }
+    Ok(url.to_string())
+}
+
+/// Parses `--webhook-hosts`, the hosts `set_webhook` endpoints may post to.
+pub(crate) fn parse_webhook_hosts(value: &str) -> Result<BTreeSet<String>, String> {
+    let hosts: BTreeSet<String> = value
+        .split(',')
+        .map(|host| host.trim().to_ascii_lowercase())
+        .filter(|host| !host.is_empty())
+        .collect();
+    if hosts.is_empty() {
+        return Err("--webhook-hosts requires at least one host".to_string());
+    }
+    Ok(hosts)
+}
+
+/// Lowercased host of an endpoint URL.
+fn url_host(url: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .ok()?
+        .host_str()
+        .map(|host| host.to_ascii_lowercase())
+}
+
+pub(crate) fn parse_webhook_events(value: &str) -> Result<BTreeSet<String>, String> {
+    let events: BTreeSet<String> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|event| !event.is_empty())
+        .map(str::to_string)
+        .collect();
+    if events.is_empty() {
+        return Err("--webhook-events requires at least one event method".to_string());
+    }
+    Ok(events)
+}
+
+pub(crate) fn default_webhook_events() -> BTreeSet<String> {
+    DEFAULT_WEBHOOK_EVENTS
+        .iter()
+        .map(|event| event.to_string())
+        .collect()
+}
+
+/// The method a notification is filtered by.
+fn event_method(notification: &Value) -> Option<&str> {
+    let method = notification.get("method").and_then(Value::as_str)?;
+    if method != "app-server-event" {
+        return Some(method);
+    }
+    notification
+        .get("params")
+        .and_then(|params| params.get("message"))
+        .and_then(|message| message.get("method"))
+        .and_then(Value::as_str)
+}
+
+/// The workspace a notification is about, if any.
+fn event_workspace_id(notification: &Value) -> Option<&str> {
+    let params = notification.get("params")?;
+    ["workspaceId", "workspace_id"]
+        .iter()
+        .find_map(|key| params.get(key).and_then(Value::as_str))
+}
+
+/// `X-CodexMonitor-Signature`: `sha256=` and the hex HMAC-SHA256 of
+/// `<timestamp>.<body>`, so a captured post can't be replayed with a new
+/// `X-CodexMonitor-Timestamp`.
+fn signature(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    let hex: String = mac
+        .finalize()
+        .into_bytes()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    format!("sha256={hex}")
+}
+
+/// Whether a failed post is worth repeating.
+fn retryable(status: u16) -> bool {
+    status == 429 || status >= 500
+}
+
+fn retry_delay(attempt: u32) -> Duration {
+    FIRST_RETRY * 2u32.pow(attempt.saturating_sub(1))
+}
+
+/// Recent delivery attempts, newest last.
+#[derive(Default)]
+struct DeliveryLog {
+    entries: Mutex<VecDeque<WebhookDelivery>>,
+}
+
+impl DeliveryLog {
+    fn push(&self, delivery: WebhookDelivery) {
+        let mut entries = self.entries.lock().unwrap_or_else(|err| err.into_inner());
+        if entries.len() == DELIVERY_LOG_SIZE {
+            entries.pop_front();
+        }
+        entries.push_back(delivery);
+    }
+}
+
+/// Posts selected daemon events to external URLs, so headless setups can
+/// notify someone with no client connected. Each endpoint has its own queue
+/// and task, which posts its events one at a time and in order; a slow
+/// endpoint doesn't hold up the others.
+/// Endpoints come from `--webhook-url` and from `set_webhook`, which keeps
+/// them in `webhooks.json`. `set_webhook` endpoints may only post to the
+/// hosts given with `--webhook-hosts`, so a client can't point the daemon
+/// at internal services.
+pub(crate) struct Webhooks {
+    path: PathBuf,
+    cli: Option<WebhookEndpoint>,
+    allowed_hosts: BTreeSet<String>,
+    endpoints: Mutex<Vec<WebhookEndpoint>>,
+    deliveries: Arc<DeliveryLog>,
+    client: reqwest::Client,
+    /// Delivery queues by endpoint id, started with the endpoint's first
+    /// event.
+    queues: Mutex<HashMap<String, DeliveryQueue>>,
+}
+
+type DeliveryQueue = mpsc::Sender<(WebhookEndpoint, Value)>;
+
+impl Webhooks {
+    /// Loads the stored endpoints.
+    pub(crate) fn load(
+        path: PathBuf,
+        cli: Option<WebhookConfig>,
+        allowed_hosts: BTreeSet<String>,
+    ) -> Self {
+        let endpoints = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        let client = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap_or_default();
+        Self {
+            path,
+            cli: cli.map(|config| WebhookEndpoint {
+                id: CLI_ENDPOINT_ID.to_string(),
+                url: config.url,
+                secret: config.secret,
+                events: config.events,
+                workspace_ids: BTreeSet::new(),
+            }),
+            allowed_hosts,
+            endpoints: Mutex::new(endpoints),
+            deliveries: Arc::default(),
+            client,
+            queues: Mutex::default(),
+        }
+    }
+
+    /// Starts the task that posts one endpoint's queued events.
+    fn start_queue(&self) -> DeliveryQueue {
+        let (tx, mut rx) = mpsc::channel::<(WebhookEndpoint, Value)>(QUEUE_SIZE);
+        let client = self.client.clone();
+        let deliveries = Arc::clone(&self.deliveries);
+        tokio::spawn(async move {
+            while let Some((endpoint, notification)) = rx.recv().await {
+                let delivery =
+                    post(&client, &endpoint, &notification, MAX_ATTEMPTS, &deliveries).await;
+                if !delivery.ok {
+                    eprintln!(
+                        "codex-monitor-daemon: webhook post to {} failed: {} after {} attempts",
+                        endpoint.url,
+                        delivery.error.unwrap_or_default(),
+                        delivery.attempt
+                    );
+                }
+            }
+        });
+        tx
+    }
+
+    /// The `--webhook-url` endpoint is trusted; stored ones need an allowed
+    /// host, which also covers endpoints kept from before `--webhook-hosts`
+    /// changed.
+    fn host_allowed(&self, endpoint: &WebhookEndpoint) -> Result<(), String> {
+        if endpoint.id == CLI_ENDPOINT_ID {
+            return Ok(());
+        }
+        let host = url_host(&endpoint.url).ok_or("url has no host")?;
+        if self.allowed_hosts.contains(&host) {
+            return Ok(());
+        }
+        Err(format!(
+            "webhook host {host} is not allowed; start the daemon with --webhook-hosts {host}"
+        ))
+    }
+
+    /// Queues `notification` for every endpoint that selected it.
+    pub(crate) fn offer(&self, notification: &Value) {
+        let Some(method) = event_method(notification) else {
+            return;
+        };
+        let workspace_id = event_workspace_id(notification);
+        let endpoints = self.endpoints.lock().unwrap_or_else(|err| err.into_inner());
+        let mut queues = self.queues.lock().unwrap_or_else(|err| err.into_inner());
+        for endpoint in self.cli.iter().chain(endpoints.iter()) {
+            if !endpoint.wants(method, workspace_id) || self.host_allowed(endpoint).is_err() {
+                continue;
+            }
+            let queue = queues
+                .entry(endpoint.id.clone())
+                .or_insert_with(|| self.start_queue());
+            if queue.is_closed() {
+                *queue = self.start_queue();
+            }
+            if queue
+                .try_send((endpoint.clone(), notification.clone()))
+                .is_err()
+            {
+                eprintln!(
+                    "codex-monitor-daemon: webhook queue for {} full, event dropped",
+                    endpoint.url
+                );
+            }
+        }
+    }
+
+    pub(crate) fn endpoints(&self) -> Vec<WebhookEndpointInfo> {
+        let endpoints = self.endpoints.lock().unwrap_or_else(|err| err.into_inner());
+        self.cli
+            .iter()
+            .chain(endpoints.iter())
+            .map(WebhookEndpointInfo::new)
+            .collect()
+    }
+
+    /// Creates or changes a stored endpoint.
+    pub(crate) fn set_endpoint(
+        &self,
+        update: WebhookUpdate,
+    ) -> Result<WebhookEndpointInfo, String> {
+        if update.id.as_deref() == Some(CLI_ENDPOINT_ID) {
+            return Err("the --webhook-url endpoint can't be changed".to_string());
+        }
+        let mut endpoints = self.endpoints.lock().unwrap_or_else(|err| err.into_inner());
+        let existing = update
+            .id
+            .as_deref()
+            .and_then(|id| endpoints.iter().position(|endpoint| endpoint.id == id));
+        let mut endpoint = match existing {
+            Some(index) => endpoints[index].clone(),
+            None => WebhookEndpoint {
+                id: update.id.unwrap_or_else(|| Uuid::new_v4().to_string()),
+                url: update.url.clone().ok_or("url is required")?,
+                secret: None,
+                events: BTreeSet::new(),
+                workspace_ids: BTreeSet::new(),
+            },
+        };
+        if let Some(url) = update.url {
+            endpoint.url =
+                parse_webhook_url(&url).map_err(|_| "url must be an http:// or https:// URL")?;
+            self.host_allowed(&endpoint)?;
+        }
+        if let Some(secret) = update.secret {
+            endpoint.secret = Some(secret).filter(|secret| !secret.is_empty());
+        }
+        if let Some(events) = update.events {
+            endpoint.events = events
+                .iter()
+                .map(|event| event.trim())
+                .filter(|event| !event.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        if let Some(workspace_ids) = update.workspace_ids {
+            endpoint.workspace_ids = workspace_ids.into_iter().collect();
+        }
+        let info = WebhookEndpointInfo::new(&endpoint);
+        match existing {
+            Some(index) => endpoints[index] = endpoint,
+            None => endpoints.push(endpoint),
+        }
+        self.persist(&endpoints)?;
+        Ok(info)
+    }
+
+    /// Returns whether there was such a stored endpoint.
+    pub(crate) fn remove_endpoint(&self, id: &str) -> Result<bool, String> {
+        if id == CLI_ENDPOINT_ID {
+            return Err("the --webhook-url endpoint can't be removed".to_string());
+        }
+        let mut endpoints = self.endpoints.lock().unwrap_or_else(|err| err.into_inner());
+        let before = endpoints.len();
+        endpoints.retain(|endpoint| endpoint.id != id);
+        if endpoints.len() == before {
+            return Ok(false);
+        }
+        // Its task ends once the events already queued are posted.
+        self.queues
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .remove(id);
+        self.persist(&endpoints)?;
+        Ok(true)
+    }
+
+    /// Forgets every stored endpoint, for `purge_data`.
+    pub(crate) fn clear(&self) {
+        let mut endpoints = self.endpoints.lock().unwrap_or_else(|err| err.into_inner());
+        endpoints.clear();
+        self.queues
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .retain(|id, _| id == CLI_ENDPOINT_ID);
+    }
+
+    /// Recent attempts, newest first, optionally for one endpoint.
+    pub(crate) fn deliveries(
+        &self,
+        endpoint_id: Option<&str>,
+        limit: usize,
+    ) -> Vec<WebhookDelivery> {
+        let entries = self
+            .deliveries
+            .entries
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        entries
+            .iter()
+            .rev()
+            .filter(|delivery| endpoint_id.is_none_or(|id| delivery.endpoint_id == id))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Posts a synthetic `webhook-test` event to the endpoint once, whatever
+    /// its filters, and waits for the answer.
+    pub(crate) async fn test(&self, id: &str) -> Result<WebhookDelivery, String> {
+        let endpoint = {
+            let endpoints = self.endpoints.lock().unwrap_or_else(|err| err.into_inner());
+            self.cli
+                .iter()
+                .chain(endpoints.iter())
+                .find(|endpoint| endpoint.id == id)
+                .cloned()
+                .ok_or("webhook not found")?
+        };
+        self.host_allowed(&endpoint)?;
+        let notification = json!({
+            "method": TEST_EVENT,
+            "params": { "endpointId": endpoint.id, "at": now_ms() },
+        });
+        Ok(post(&self.client, &endpoint, &notification, 1, &self.deliveries).await)
+    }
+
+    fn persist(&self, endpoints: &[WebhookEndpoint]) -> Result<(), String> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let data = serde_json::to_string_pretty(endpoints).map_err(|e| e.to_string())?;
+        write_private(&self.path, data.as_bytes()).map_err(|e| e.to_string())
+    }
+}
+
+/// Writes `webhooks.json` readable by the owner only, since it holds the
+/// endpoint secrets.
+fn write_private(path: &Path, data: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        if path.exists() {
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)?.write_all(data)
+}
+
+/// Posts one notification, retrying network errors, 429s and 5xx answers
+/// with a doubling delay. Every attempt is logged; the last one is returned.
+async fn post(
+    client: &reqwest::Client,
+    endpoint: &WebhookEndpoint,
+    notification: &Value,
+    max_attempts: u32,
+    log: &DeliveryLog,
+) -> WebhookDelivery {
+    let method = event_method(notification).unwrap_or_default().to_string();
+    let mut delivery = WebhookDelivery {
+        delivery_id: Uuid::new_v4().to_string(),
+        endpoint_id: endpoint.id.clone(),
+        url: endpoint.url.clone(),
+        event: method.clone(),
+        attempt: 0,
+        at: 0,
+        duration_ms: 0,
+        status: None,
+        ok: false,
+        error: None,
+    };
+    let body = match serde_json::to_vec(notification) {
+        Ok(body) => body,
+        Err(err) => {
+            delivery.error = Some(err.to_string());
+            return delivery;
+        }
+    };
+    loop {
+        delivery.attempt += 1;
+        delivery.at = now_ms();
+        let timestamp = (delivery.at / 1000).to_string();
+        let started_at = Instant::now();
+        let mut request = client
+            .post(&endpoint.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header("X-CodexMonitor-Event", &method)
+            .header("X-CodexMonitor-Delivery", &delivery.delivery_id)
+            .header("X-CodexMonitor-Timestamp", &timestamp);
+        if let Some(secret) = endpoint.secret.as_deref() {
+            request = request.header(
+                "X-CodexMonitor-Signature",
+                signature(secret, &timestamp, &body),
+            );
+        }
+        let result = request.body(body.clone()).send().await;
+        delivery.duration_ms = started_at.elapsed().as_millis() as u64;
+        let retry = match result {
+            Ok(response) => {
+                let status = response.status();
+                delivery.status = Some(status.as_u16());
+                delivery.ok = status.is_success();
+                delivery.error = (!delivery.ok).then(|| format!("HTTP {status}"));
+                !delivery.ok && retryable(status.as_u16())
+            }
+            Err(err) => {
+                delivery.status = None;
+                delivery.error = Some(err.to_string());
+                true
+            }
+        };
+        log.push(delivery.clone());
+        if !retry || delivery.attempt >= max_attempts {
+            return delivery;
+        }
+        tokio::time::sleep(retry_delay(delivery.attempt)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        default_webhook_events, event_method, event_workspace_id, parse_webhook_events,
+        parse_webhook_hosts, parse_webhook_url, retry_delay, retryable, signature, url_host,
+        WebhookConfig, WebhookUpdate, Webhooks,
+    };
+    use serde_json::json;
+    use std::collections::BTreeSet;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use uuid::Uuid;
+
+    #[test]
+    fn parses_urls_and_event_lists() {
+        assert_eq!(
+            parse_webhook_url(" https://hooks.example.com/x ").unwrap(),
+            "https://hooks.example.com/x"
+        );
+        assert!(parse_webhook_url("hooks.example.com").is_err());
+
+        let events = parse_webhook_events("turn/completed, workspace-auto-disconnected,,").unwrap();
+        assert_eq!(
+            events.into_iter().collect::<Vec<_>>(),
+            ["turn/completed", "workspace-auto-disconnected"]
+        );
+        assert!(parse_webhook_events(" , ").is_err());
+        assert!(default_webhook_events().contains("turn/completed"));
+    }
+
+    #[test]
+    fn matches_app_server_events_by_their_codex_method() {
+        let turn = json!({
+            "method": "app-server-event",
+            "params": {
+                "workspace_id": "ws",
+                "message": { "method": "turn/completed", "params": {} },
+            },
+            "seq": 4,
+        });
+        assert_eq!(event_method(&turn), Some("turn/completed"));
+        let failed = json!({ "method": "operation-failed", "params": {}, "seq": 5 });
+        assert_eq!(event_method(&failed), Some("operation-failed"));
+        assert_eq!(event_method(&json!({ "seq": 6 })), None);
+    }
+
+    #[test]
+    fn retries_server_errors_with_doubling_delays() {
+        assert!(retryable(503) && retryable(429));
+        assert!(!retryable(404) && !retryable(400));
+        assert_eq!(retry_delay(1), Duration::from_secs(1));
+        assert_eq!(retry_delay(3), Duration::from_secs(4));
+    }
+
+    #[test]
+    fn signs_the_timestamp_with_the_body() {
+        // HMAC-SHA256("s3cret", "1700000000.{"seq":1}").
+        assert_eq!(
+            signature("s3cret", "1700000000", br#"{"seq":1}"#),
+            "sha256=cbdcfdffb94ac16f20fc7dff420aed60e8abb875b1394e892e008fdba0124439"
+        );
+        assert_ne!(
+            signature("s3cret", "1700000000", b"{}"),
+            signature("s3cret", "1700000001", b"{}")
+        );
+    }
+
+    #[test]
+    fn parses_allowed_hosts() {
+        let hosts = parse_webhook_hosts("Hooks.Example.com, ci.example.com,,").unwrap();
+        assert_eq!(
+            hosts.into_iter().collect::<Vec<_>>(),
+            ["ci.example.com", "hooks.example.com"]
+        );
+        assert!(parse_webhook_hosts(" , ").is_err());
+        assert_eq!(
+            url_host("https://Hooks.Example.com:8443/x").as_deref(),
+            Some("hooks.example.com")
+        );
+        assert_eq!(url_host("http://127.0.0.1/x").as_deref(), Some("127.0.0.1"));
+    }
+
+    #[tokio::test]
+    async fn stores_endpoints_and_filters_events() {
+        let path = std::env::temp_dir()
+            .join(format!("codex-monitor-webhooks-{}", Uuid::new_v4()))
+            .join("webhooks.json");
+        let cli = WebhookConfig {
+            url: "https://ci.example.com/hook".to_string(),
+            events: BTreeSet::from(["turn/completed".to_string()]),
+            secret: None,
+        };
+        let allowed = BTreeSet::from(["bot.example.com".to_string()]);
+        let webhooks = Webhooks::load(path.clone(), Some(cli), allowed.clone());
+        let info = webhooks
+            .set_endpoint(WebhookUpdate {
+                id: Some("bot".to_string()),
+                url: Some("https://bot.example.com".to_string()),
+                secret: Some("s3cret".to_string()),
+                workspace_ids: Some(vec!["ws".to_string()]),
+                ..WebhookUpdate::default()
+            })
+            .expect("create");
+        assert!(info.signed && !info.read_only);
+        assert!(webhooks
+            .set_endpoint(WebhookUpdate {
+                id: Some("cli".to_string()),
+                ..WebhookUpdate::default()
+            })
+            .is_err());
+        assert!(webhooks
+            .set_endpoint(WebhookUpdate {
+                url: Some("ftp://bot".to_string()),
+                ..WebhookUpdate::default()
+            })
+            .is_err());
+        assert!(webhooks
+            .set_endpoint(WebhookUpdate {
+                url: Some("http://169.254.169.254/latest".to_string()),
+                ..WebhookUpdate::default()
+            })
+            .is_err());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let reloaded = Webhooks::load(path.clone(), None, allowed);
+        let stored = reloaded.endpoints();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].id, "bot");
+
+        let bot = webhooks.endpoints.lock().unwrap()[0].clone();
+        assert!(bot.wants("turn/completed", Some("ws")));
+        assert!(!bot.wants("turn/completed", Some("other")));
+        assert!(!bot.wants("turn/completed", None));
+        assert!(!bot.wants("thread/started", Some("ws")));
+
+        assert_eq!(webhooks.remove_endpoint("bot"), Ok(true));
+        assert_eq!(webhooks.remove_endpoint("bot"), Ok(false));
+        assert_eq!(webhooks.endpoints().len(), 1);
+
+        let _ = std::fs::remove_dir_all(path.parent().expect("parent"));
+    }
+
+    #[test]
+    fn finds_the_workspace_an_event_is_about() {
+        let event = json!({ "method": "app-server-event", "params": { "workspace_id": "ws" } });
+        assert_eq!(event_workspace_id(&event), Some("ws"));
+        let event = json!({ "method": "workspace-paused", "params": { "workspaceId": "w2" } });
+        assert_eq!(event_workspace_id(&event), Some("w2"));
+        assert_eq!(
+            event_workspace_id(&json!({ "method": "x", "params": {} })),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn a_stalled_endpoint_does_not_hold_up_the_others() {
+        // Connections to `stalled` are never answered.
+        let stalled = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let quick = TcpListener::bind("127.0.0.1:0").await.expect("bind");
+        let urls = [&stalled, &quick]
+            .map(|listener| format!("http://{}/hook", listener.local_addr().expect("addr")));
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = quick.accept().await {
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buf = [0u8; 4096];
+                    while let Ok(read @ 1..) = socket.read(&mut buf).await {
+                        request.extend_from_slice(&buf[..read]);
+                        if request.ends_with(b"}") {
+                            break;
+                        }
+                    }
+                    let _ = socket
+                        .write_all(b"HTTP/1.1 204 No Content\r\ncontent-length: 0\r\n\r\n")
+                        .await;
+                });
+            }
+        });
+
+        let dir = std::env::temp_dir().join(format!("codex-monitor-webhooks-{}", Uuid::new_v4()));
+        let allowed = BTreeSet::from(["127.0.0.1".to_string()]);
+        let webhooks = Webhooks::load(dir.join("webhooks.json"), None, allowed);
+        for (id, url) in ["stalled", "quick"].into_iter().zip(urls) {
+            webhooks
+                .set_endpoint(WebhookUpdate {
+                    id: Some(id.to_string()),
+                    url: Some(url),
+                    ..WebhookUpdate::default()
+                })
+                .expect("create");
+        }
+        webhooks.offer(&json!({ "method": "operation-failed", "params": {}, "seq": 1 }));
+
+        let delivered = tokio::time::timeout(Duration::from_secs(5), async {
+            while webhooks.deliveries(Some("quick"), 1).is_empty() {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await;
+        assert!(
+            delivered.is_ok(),
+            "the quick endpoint waited on the stalled one"
+        );
+        assert!(webhooks.deliveries(Some("quick"), 1)[0].ok);
+        assert!(webhooks.deliveries(Some("stalled"), 1).is_empty());
+        drop(stalled);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
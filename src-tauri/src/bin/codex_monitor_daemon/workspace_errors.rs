@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use super::snapshot::SnapshotFile;
+
+/// Errors kept per workspace; the oldest are dropped first.
+const MAX_WORKSPACE_ERRORS: usize = 100;
+
+/// The background work an error came from.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum ErrorSource {
+    /// Starting a codex session outside a request, such as after
+    /// `rename_worktree` or for `connect_all_workspaces`.
+    Respawn,
+    /// Background `git fetch`.
+    Fetch,
+    /// The startup check of workspace folders.
+    Check,
+    /// Recurring daemon work: auto-titles and rate limit polls.
+    Scheduler,
+    /// Interrupting turns for `pause_workspace`.
+    Interrupt,
+    /// Saving daemon state about the workspace.
+    Storage,
+}
+
+impl ErrorSource {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Respawn => "respawn",
+            Self::Fetch => "fetch",
+            Self::Check => "check",
+            Self::Scheduler => "scheduler",
+            Self::Interrupt => "interrupt",
+            Self::Storage => "storage",
+        }
+    }
+}
+
+/// One error in a workspace's journal. Repeats of the newest error are
+/// folded into it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorkspaceError {
+    /// Grows with every error or repeat, across workspaces;
+    /// `clear_workspace_errors` takes it as `upTo`.
+    pub(crate) id: u64,
+    /// Milliseconds since the epoch of the first occurrence.
+    pub(crate) at: u64,
+    /// Milliseconds since the epoch of the latest occurrence.
+    pub(crate) last_at: u64,
+    pub(crate) count: u32,
+    pub(crate) source: ErrorSource,
+    pub(crate) message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) thread_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) turn_id: Option<String>,
+}
+
+impl WorkspaceError {
+    fn repeats(&self, source: ErrorSource, message: &str, thread_id: Option<&str>) -> bool {
+        self.source == source && self.message == message && self.thread_id.as_deref() == thread_id
+    }
+}
+
+/// An error about to be recorded.
+#[derive(Debug, Clone)]
+pub(crate) struct NewError<'a> {
+    pub(crate) source: ErrorSource,
+    pub(crate) message: String,
+    pub(crate) thread_id: Option<&'a str>,
+    pub(crate) turn_id: Option<&'a str>,
+}
+
+impl NewError<'_> {
+    pub(crate) fn new(source: ErrorSource, message: String) -> Self {
+        Self {
+            source,
+            message,
+            thread_id: None,
+            turn_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Journal {
+    next_id: u64,
+    /// Oldest first.
+    errors: HashMap<String, Vec<WorkspaceError>>,
+}
+
+/// Errors of background work that no request is waiting for, per workspace,
+/// kept in `workspace_errors.json` until a client clears them.
+pub(crate) struct WorkspaceErrors {
+    file: SnapshotFile,
+    journal: Mutex<Journal>,
+}
+
+impl WorkspaceErrors {
+    pub(crate) fn load(path: PathBuf) -> Self {
+        let journal = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default();
+        Self {
+            file: SnapshotFile::new(path, "workspace errors"),
+            journal: Mutex::new(journal),
+        }
+    }
+
+    /// Adds `error` and returns the journal entry with the workspace's error
+    /// count. The error is also written to stderr. Inside a tokio runtime the
+    /// journal is saved on a blocking thread.
+    pub(crate) fn record(
+        &self,
+        workspace_id: &str,
+        error: NewError<'_>,
+        now: u64,
+    ) -> (WorkspaceError, usize) {
+        eprintln!(
+            "codex-monitor-daemon: {} error in workspace {workspace_id}: {}",
+            error.source.label(),
+            error.message
+        );
+        let mut journal = self.journal.lock().unwrap_or_else(|err| err.into_inner());
+        journal.next_id += 1;
+        let id = journal.next_id;
+        let errors = journal.errors.entry(workspace_id.to_string()).or_default();
+        let entry = match errors.last_mut() {
+            Some(last) if last.repeats(error.source, &error.message, error.thread_id) => {
+                last.id = id;
+                last.last_at = now;
+                last.count = last.count.saturating_add(1);
+                last.turn_id = error.turn_id.map(str::to_string).or(last.turn_id.take());
+                last.clone()
+            }
+            _ => {
+                let entry = WorkspaceError {
+                    id,
+                    at: now,
+                    last_at: now,
+                    count: 1,
+                    source: error.source,
+                    message: error.message,
+                    thread_id: error.thread_id.map(str::to_string),
+                    turn_id: error.turn_id.map(str::to_string),
+                };
+                errors.push(entry.clone());
+                if errors.len() > MAX_WORKSPACE_ERRORS {
+                    errors.remove(0);
+                }
+                entry
+            }
+        };
+        let count = errors.len();
+        if let Err(err) = self.save(&journal) {
+            eprintln!("codex-monitor-daemon: failed to save workspace errors: {err}");
+        }
+        (entry, count)
+    }
+
+    /// The workspace's errors, newest first, at most `limit`.
+    pub(crate) fn list(&self, workspace_id: &str, limit: usize) -> Vec<WorkspaceError> {
+        let journal = self.journal.lock().unwrap_or_else(|err| err.into_inner());
+        journal
+            .errors
+            .get(workspace_id)
+            .map(|errors| errors.iter().rev().take(limit).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn count(&self, workspace_id: &str) -> usize {
+        let journal = self.journal.lock().unwrap_or_else(|err| err.into_inner());
+        journal.errors.get(workspace_id).map_or(0, Vec::len)
+    }
+
+    /// Drops the workspace's errors, or those whose `id` is at most `up_to`;
+    /// returns how many were dropped.
+    pub(crate) fn clear(&self, workspace_id: &str, up_to: Option<u64>) -> Result<usize, String> {
+        let mut journal = self.journal.lock().unwrap_or_else(|err| err.into_inner());
+        let Some(errors) = journal.errors.get_mut(workspace_id) else {
+            return Ok(0);
+        };
+        let before = errors.len();
+        errors.retain(|error| up_to.is_some_and(|up_to| error.id > up_to));
+        let cleared = before - errors.len();
+        if errors.is_empty() {
+            journal.errors.remove(workspace_id);
+        }
+        if cleared > 0 {
+            self.save(&journal)?;
+        }
+        Ok(cleared)
+    }
+
+    pub(crate) fn forget(&self, workspace_id: &str) {
+        let _ = self.clear(workspace_id, None);
+    }
+
+    /// Snapshots `journal`, which the caller holds locked, and writes it.
+    fn save(&self, journal: &Journal) -> Result<(), String> {
+        let data = serde_json::to_string_pretty(journal).map_err(|e| e.to_string())?;
+        self.file.save(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorSource, NewError, WorkspaceErrors, MAX_WORKSPACE_ERRORS};
+    use uuid::Uuid;
+
+    #[test]
+    fn records_folds_repeats_and_clears() {
+        let path = std::env::temp_dir()
+            .join(format!("codex-monitor-errors-{}", Uuid::new_v4()))
+            .join("workspace_errors.json");
+        let errors = WorkspaceErrors::load(path.clone());
+        let fetch = || NewError::new(ErrorSource::Fetch, "network down".to_string());
+        let (first, count) = errors.record("ws", fetch(), 10);
+        assert_eq!((first.id, first.count, count), (1, 1, 1));
+        let (repeat, count) = errors.record("ws", fetch(), 20);
+        assert_eq!(
+            (repeat.id, repeat.count, repeat.at, repeat.last_at),
+            (2, 2, 10, 20)
+        );
+        assert_eq!(count, 1);
+        let title = NewError {
+            thread_id: Some("t1"),
+            ..NewError::new(ErrorSource::Scheduler, "title failed".to_string())
+        };
+        let (titled, count) = errors.record("ws", title, 30);
+        assert_eq!((titled.id, count), (3, 2));
+        errors.record("other", fetch(), 40);
+
+        let reloaded = WorkspaceErrors::load(path.clone());
+        let listed = reloaded.list("ws", 10);
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].thread_id.as_deref(), Some("t1"));
+        assert_eq!(reloaded.list("ws", 1).len(), 1);
+        assert_eq!(reloaded.clear("ws", Some(2)), Ok(1));
+        assert_eq!(reloaded.count("ws"), 1);
+        assert_eq!(reloaded.clear("ws", None), Ok(1));
+        assert_eq!(reloaded.clear("ws", None), Ok(0));
+        assert_eq!(WorkspaceErrors::load(path.clone()).count("other"), 1);
+        assert_eq!(reloaded.record("ws", fetch(), 50).0.id, 5);
+
+        for index in 0..=MAX_WORKSPACE_ERRORS {
+            let error = NewError::new(ErrorSource::Check, format!("error {index}"));
+            reloaded.record("busy", error, 60);
+        }
+        assert_eq!(reloaded.count("busy"), MAX_WORKSPACE_ERRORS);
+        assert_eq!(
+            reloaded.list("busy", 200).last().unwrap().message,
+            "error 1"
+        );
+
+        let _ = std::fs::remove_dir_all(path.parent().expect("parent"));
+    }
+    #[tokio::test]
+    async fn saves_off_thread_without_older_snapshots_winning() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-errors-{}", Uuid::new_v4()));
+        let errors = WorkspaceErrors::load(dir.join("journal.json"));
+        for index in 0..20 {
+            let error = NewError::new(ErrorSource::Check, format!("error {index}"));
+            errors.record("ws", error, index);
+        }
+        let mut saved = 0;
+        for _ in 0..100 {
+            saved = WorkspaceErrors::load(dir.join("journal.json")).count("ws");
+            if saved == 20 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(saved, 20);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,107 @@
+use std::cmp::Reverse;
+use std::time::Instant;
+
+use crate::types::WorkspaceInfo;
+
+/// Orderings `list_workspaces` offers through `sortBy`. Each is applied on
+/// top of the default order, which breaks ties.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum WorkspaceSort {
+    /// `sortOrder`, then name.
+    #[default]
+    Default,
+    /// Most recently active session first; workspaces without one last.
+    LastActivity,
+    /// Connected workspaces first.
+    Connected,
+    Path,
+}
+
+impl WorkspaceSort {
+    pub(crate) fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "default" => Ok(Self::Default),
+            "lastActivity" => Ok(Self::LastActivity),
+            "connected" => Ok(Self::Connected),
+            "path" => Ok(Self::Path),
+            other => Err(format!("unknown sortBy: {other}")),
+        }
+    }
+}
+
+/// Reorders workspaces already in the default order. `last_active` gives the
+/// last activity of a workspace's session.
+pub(crate) fn sort_workspaces_by(
+    workspaces: &mut [WorkspaceInfo],
+    sort: WorkspaceSort,
+    last_active: impl Fn(&str) -> Option<Instant>,
+) {
+    match sort {
+        WorkspaceSort::Default => {}
+        WorkspaceSort::LastActivity => {
+            workspaces.sort_by_cached_key(|workspace| Reverse(last_active(&workspace.id)))
+        }
+        WorkspaceSort::Connected => workspaces.sort_by_key(|workspace| !workspace.connected),
+        WorkspaceSort::Path => workspaces.sort_by(|a, b| a.path.cmp(&b.path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sort_workspaces_by, WorkspaceSort};
+    use crate::types::{WorkspaceEntry, WorkspaceInfo, WorkspaceKind, WorkspaceSettings};
+    use std::time::{Duration, Instant};
+
+    fn workspace(id: &str, path: &str, connected: bool) -> WorkspaceInfo {
+        let entry = WorkspaceEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            path: path.to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings::default(),
+            status: None,
+        };
+        WorkspaceInfo::from_entry(entry, connected)
+    }
+
+    fn ids(workspaces: &[WorkspaceInfo]) -> Vec<&str> {
+        workspaces
+            .iter()
+            .map(|workspace| workspace.id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn sorts_by_the_requested_mode_keeping_default_ties() {
+        let now = Instant::now();
+        let mut workspaces = vec![
+            workspace("a", "/z", false),
+            workspace("b", "/y", true),
+            workspace("c", "/x", false),
+            workspace("d", "/w", true),
+        ];
+        let last_active = |id: &str| match id {
+            "b" => Some(now - Duration::from_secs(60)),
+            "d" => Some(now),
+            _ => None,
+        };
+
+        sort_workspaces_by(&mut workspaces, WorkspaceSort::Default, last_active);
+        assert_eq!(ids(&workspaces), ["a", "b", "c", "d"]);
+        sort_workspaces_by(&mut workspaces, WorkspaceSort::Connected, last_active);
+        assert_eq!(ids(&workspaces), ["b", "d", "a", "c"]);
+        sort_workspaces_by(&mut workspaces, WorkspaceSort::LastActivity, last_active);
+        assert_eq!(ids(&workspaces), ["d", "b", "a", "c"]);
+        sort_workspaces_by(&mut workspaces, WorkspaceSort::Path, last_active);
+        assert_eq!(ids(&workspaces), ["d", "c", "b", "a"]);
+
+        assert_eq!(
+            WorkspaceSort::parse("lastActivity"),
+            Ok(WorkspaceSort::LastActivity)
+        );
+        assert!(WorkspaceSort::parse("size").is_err());
+    }
+}
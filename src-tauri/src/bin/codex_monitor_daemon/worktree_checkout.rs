@@ -0,0 +1,357 @@
+use std::path::{Path, PathBuf};
+
+use crate::types::{WorkspaceEntry, WorkspaceEntryStatus};
+
+use super::{
+    comparable_path, git_branch_exists, git_find_remote_tracking_branch, is_missing_worktree_error,
+    run_git_command,
+};
+
+/// Checks `branch` out in a new worktree at `worktree_path`: the local branch
+/// when it exists, else a new branch off the remote branch of that name, else
+/// a new branch off the parent's `HEAD`, which may be detached.
+pub(crate) async fn add_checkout(
+    repo_path: &PathBuf,
+    worktree_path: &Path,
+    branch: &str,
+) -> Result<(), String> {
+    let worktree_path = worktree_path.to_string_lossy().to_string();
+    if git_branch_exists(repo_path, branch).await? {
+        run_git_command(repo_path, &["worktree", "add", &worktree_path, branch]).await?;
+    } else if let Some(remote_ref) = git_find_remote_tracking_branch(repo_path, branch).await? {
+        run_git_command(
+            repo_path,
+            &["worktree", "add", "-b", branch, &worktree_path, &remote_ref],
+        )
+        .await?;
+    } else {
+        run_git_command(
+            repo_path,
+            &["worktree", "add", "-b", branch, &worktree_path],
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Removes the worktree at `worktree_path`, deleting the folder itself when
+/// git no longer knows it as a worktree.
+pub(crate) async fn remove_checkout(
+    parent_path: &PathBuf,
+    worktree_path: &Path,
+) -> Result<(), String> {
+    if !worktree_path.exists() {
+        return Ok(());
+    }
+    let worktree = worktree_path.to_string_lossy().to_string();
+    match run_git_command(parent_path, &["worktree", "remove", "--force", &worktree]).await {
+        Ok(_) => Ok(()),
+        Err(err) if is_missing_worktree_error(&err) => {
+            if worktree_path.exists() {
+                std::fs::remove_dir_all(worktree_path)
+                    .map_err(|fs_err| format!("Failed to remove worktree folder: {fs_err}"))?;
+            }
+            Ok(())
+        }
+        Err(err) => Err(err),
+    }
+}
+
+/// Drops git's records of worktrees whose folders are gone.
+pub(crate) async fn prune_worktrees(repo_path: &PathBuf) {
+    let _ = run_git_command(repo_path, &["worktree", "prune", "--expire", "now"]).await;
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct GitWorktreeRecord {
+    pub(crate) path: String,
+    /// `None` for detached or bare worktrees.
+    pub(crate) branch: Option<String>,
+}
+
+pub(crate) async fn git_list_worktrees(
+    repo_path: &PathBuf,
+) -> Result<Vec<GitWorktreeRecord>, String> {
+    let output = run_git_command(repo_path, &["worktree", "list", "--porcelain"]).await?;
+    Ok(parse_worktree_list(&output))
+}
+
+/// How a stored entry compares with its folder and, for worktrees, with what
+/// `git worktree list` of the parent reports.
+pub(crate) fn workspace_entry_status(
+    entry: &WorkspaceEntry,
+    parent_worktrees: Option<&Vec<GitWorktreeRecord>>,
+) -> WorkspaceEntryStatus {
+    let path = Path::new(&entry.path);
+    if !path.is_dir() {
+        return WorkspaceEntryStatus::PathMissing;
+    }
+    if !entry.kind.is_worktree() {
+        return WorkspaceEntryStatus::Ok;
+    }
+    let comparable = comparable_path(path);
+    let record = parent_worktrees.and_then(|records| {
+        records
+            .iter()
+            .find(|record| comparable_path(Path::new(&record.path)) == comparable)
+    });
+    let Some(record) = record else {
+        return WorkspaceEntryStatus::NotRegisteredWithGit;
+    };
+    let expected_branch = entry
+        .worktree
+        .as_ref()
+        .map(|worktree| worktree.branch.as_str());
+    if expected_branch.is_some() && record.branch.as_deref() != expected_branch {
+        return WorkspaceEntryStatus::BranchMismatch;
+    }
+    WorkspaceEntryStatus::Ok
+}
+
+fn parse_worktree_list(output: &str) -> Vec<GitWorktreeRecord> {
+    let mut records = Vec::new();
+    let mut current: Option<GitWorktreeRecord> = None;
+    for line in output.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            if let Some(record) = current.take() {
+                records.push(record);
+            }
+            current = Some(GitWorktreeRecord {
+                path: path.to_string(),
+                branch: None,
+            });
+        } else if let Some(branch) = line.strip_prefix("branch ") {
+            if let Some(record) = current.as_mut() {
+                record.branch = Some(branch.trim_start_matches("refs/heads/").to_string());
+            }
+        }
+    }
+    if let Some(record) = current {
+        records.push(record);
+    }
+    records
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        add_checkout, git_list_worktrees, parse_worktree_list, prune_worktrees, remove_checkout,
+        workspace_entry_status, GitWorktreeRecord,
+    };
+    use crate::git_state::{ensure_not_bare_repo, is_git_repo, read_head, HeadState, BARE_REPO};
+    use crate::types::{
+        WorkspaceEntry, WorkspaceEntryStatus, WorkspaceKind, WorkspaceSettings, WorktreeInfo,
+    };
+    use git2::Repository;
+    use std::path::{Path, PathBuf};
+    use uuid::Uuid;
+
+    fn commit(repo: &Repository) -> git2::Oid {
+        let tree_id = repo
+            .treebuilder(None)
+            .expect("treebuilder")
+            .write()
+            .expect("write tree");
+        let tree = repo.find_tree(tree_id).expect("find tree");
+        let sig = git2::Signature::now("Test", "test@example.com").expect("signature");
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .expect("commit")
+    }
+
+    async fn worktree_branches(repo_path: &PathBuf) -> Vec<(PathBuf, Option<String>)> {
+        git_list_worktrees(repo_path)
+            .await
+            .expect("list worktrees")
+            .into_iter()
+            .map(|record| (PathBuf::from(record.path), record.branch))
+            .collect()
+    }
+
+    fn canonical(path: &Path) -> PathBuf {
+        path.canonicalize().expect("canonicalize")
+    }
+
+    fn record(path: &Path, branch: Option<&str>) -> GitWorktreeRecord {
+        GitWorktreeRecord {
+            path: path.to_string_lossy().to_string(),
+            branch: branch.map(str::to_string),
+        }
+    }
+
+    fn worktree_entry(path: &Path, branch: &str) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id: "wt".to_string(),
+            name: "wt".to_string(),
+            path: path.to_string_lossy().to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Worktree,
+            parent_id: Some("main".to_string()),
+            worktree: Some(WorktreeInfo {
+                branch: branch.to_string(),
+                dir_name: None,
+            }),
+            settings: WorkspaceSettings::default(),
+            status: None,
+        }
+    }
+
+    #[test]
+    fn parses_porcelain_worktree_lists() {
+        let output = "worktree /repo\nHEAD 0123\nbranch refs/heads/main\n\n\
+                      worktree /repo.git\nbare\n\n\
+                      worktree /wt/detached\nHEAD 4567\ndetached\n\n\
+                      worktree /wt/gone\nHEAD 89ab\nbranch refs/heads/feature/x\n\
+                      prunable gitdir file points to non-existent location\n";
+        assert_eq!(
+            parse_worktree_list(output),
+            [
+                record(Path::new("/repo"), Some("main")),
+                record(Path::new("/repo.git"), None),
+                record(Path::new("/wt/detached"), None),
+                record(Path::new("/wt/gone"), Some("feature/x")),
+            ]
+        );
+        assert!(parse_worktree_list("").is_empty());
+    }
+
+    #[test]
+    fn compares_entries_with_git_worktrees() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-checkout-{}", Uuid::new_v4()));
+        let worktree = dir.join("feature");
+        std::fs::create_dir_all(&worktree).expect("create worktree folder");
+        let entry = worktree_entry(&worktree, "feature");
+        let records = vec![record(&worktree, Some("feature"))];
+        assert_eq!(
+            workspace_entry_status(&entry, Some(&records)),
+            WorkspaceEntryStatus::Ok
+        );
+        let renamed = vec![record(&worktree, Some("other"))];
+        assert_eq!(
+            workspace_entry_status(&entry, Some(&renamed)),
+            WorkspaceEntryStatus::BranchMismatch
+        );
+        assert_eq!(
+            workspace_entry_status(&entry, Some(&Vec::new())),
+            WorkspaceEntryStatus::NotRegisteredWithGit
+        );
+        assert_eq!(
+            workspace_entry_status(&entry, None),
+            WorkspaceEntryStatus::NotRegisteredWithGit
+        );
+
+        // Main workspaces only need their folder.
+        let main = WorkspaceEntry {
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            ..worktree_entry(&dir, "feature")
+        };
+        assert_eq!(
+            workspace_entry_status(&main, None),
+            WorkspaceEntryStatus::Ok
+        );
+
+        // A prunable worktree is still listed, but its folder is gone.
+        let gone = worktree_entry(&dir.join("gone"), "feature");
+        let prunable = vec![record(&dir.join("gone"), Some("feature"))];
+        assert_eq!(
+            workspace_entry_status(&gone, Some(&prunable)),
+            WorkspaceEntryStatus::PathMissing
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn worktrees_come_and_go_under_a_detached_parent() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-checkout-{}", Uuid::new_v4()));
+        let root = dir.join("repo");
+        let repo = Repository::init(&root).expect("init repo");
+        let head = commit(&repo);
+        repo.set_head_detached(head).expect("detach head");
+        let root = canonical(&root);
+
+        let feature = dir.join("feature");
+        add_checkout(&root, &feature, "feature")
+            .await
+            .expect("add worktree off a detached HEAD");
+        let feature = canonical(&feature);
+        assert_eq!(
+            read_head(&feature),
+            Some(HeadState::Branch("feature".to_string()))
+        );
+        assert_eq!(
+            read_head(&root),
+            Some(HeadState::Detached(head.to_string()))
+        );
+
+        // An existing branch is checked out as is.
+        repo.branch("stable", &repo.find_commit(head).expect("commit"), false)
+            .expect("branch");
+        let stable = dir.join("stable");
+        add_checkout(&root, &stable, "stable")
+            .await
+            .expect("add worktree of an existing branch");
+        let stable = canonical(&stable);
+        assert_eq!(
+            worktree_branches(&root).await,
+            [
+                (root.clone(), None),
+                (feature.clone(), Some("feature".to_string())),
+                (stable.clone(), Some("stable".to_string())),
+            ]
+        );
+
+        remove_checkout(&root, &feature)
+            .await
+            .expect("remove worktree");
+        assert!(!feature.exists());
+        remove_checkout(&root, &stable)
+            .await
+            .expect("remove worktree");
+        // A folder git doesn't know as a worktree is still removed.
+        let stray = dir.join("stray");
+        std::fs::create_dir_all(&stray).expect("create folder");
+        remove_checkout(&root, &stray).await.expect("remove folder");
+        assert!(!stray.exists());
+        prune_worktrees(&root).await;
+        assert_eq!(worktree_branches(&root).await, [(root.clone(), None)]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn bare_repos_only_serve_as_worktree_parents() {
+        let dir = std::env::temp_dir().join(format!("codex-monitor-checkout-{}", Uuid::new_v4()));
+        let bare = dir.join("repo.git");
+        let repo = Repository::init_bare(&bare).expect("init bare repo");
+        commit(&repo);
+        let bare = canonical(&bare);
+        assert!(ensure_not_bare_repo(&bare)
+            .unwrap_err()
+            .starts_with(BARE_REPO));
+
+        let feature = dir.join("feature");
+        add_checkout(&bare, &feature, "feature")
+            .await
+            .expect("add worktree of a bare repo");
+        let feature = canonical(&feature);
+        assert!(is_git_repo(&feature));
+        assert!(ensure_not_bare_repo(&feature).is_ok());
+        assert_eq!(
+            worktree_branches(&bare).await,
+            [
+                (bare.clone(), None),
+                (feature.clone(), Some("feature".to_string())),
+            ]
+        );
+
+        remove_checkout(&bare, &feature)
+            .await
+            .expect("remove worktree");
+        prune_worktrees(&bare).await;
+        assert_eq!(worktree_branches(&bare).await, [(bare.clone(), None)]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
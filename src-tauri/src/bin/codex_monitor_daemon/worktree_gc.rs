@@ -0,0 +1,424 @@
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::Serialize;
+
+use crate::types::WorkspaceEntry;
+
+use super::trash::blocking;
+use super::{comparable_path, git_list_worktrees, run_git_command};
+
+/// Folders modified more recently than this are skipped so a worktree that
+/// `add_worktree` is still setting up is never reported (or deleted) as orphaned.
+const MIN_ORPHAN_AGE: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct OrphanedWorktree {
+    pub(crate) path: String,
+    pub(crate) parent_id: String,
+    pub(crate) parent_registered: bool,
+    pub(crate) git_registered: bool,
+    pub(crate) size_bytes: u64,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub(crate) struct WorktreeGcFailure {
+    pub(crate) path: String,
+    pub(crate) error: String,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorktreeGcReport {
+    pub(crate) applied: bool,
+    pub(crate) orphans: Vec<OrphanedWorktree>,
+    pub(crate) reclaimable_bytes: u64,
+    pub(crate) removed: Vec<String>,
+    pub(crate) failures: Vec<WorktreeGcFailure>,
+}
+
+/// Compares folders under `<data-dir>/worktrees/<parent-id>` against the stored
+/// worktree entries and `git worktree list` of each parent. With `apply`, orphans
+/// whose parent id `authorize` accepts are deleted and git worktree metadata is
+/// pruned in the affected repos.
+pub(crate) async fn collect_worktree_gc(
+    worktrees_root: &Path,
+    workspaces: &[WorkspaceEntry],
+    apply: bool,
+    authorize: impl Fn(&str) -> Result<(), String>,
+) -> WorktreeGcReport {
+    let mut report = WorktreeGcReport {
+        applied: apply,
+        ..WorktreeGcReport::default()
+    };
+
+    let parents: HashMap<&str, &WorkspaceEntry> = workspaces
+        .iter()
+        .filter(|entry| !entry.kind.is_worktree())
+        .map(|entry| (entry.id.as_str(), entry))
+        .collect();
+
+    let scan = {
+        let (worktrees_root, workspaces) = (worktrees_root.to_path_buf(), workspaces.to_vec());
+        blocking(move || scan_worktree_dirs(&worktrees_root, &workspaces))
+            .await
+            .unwrap_or_default()
+    };
+
+    let mut prune_targets: Vec<PruneTarget> = Vec::new();
+    let mut unregistered_parent_dirs: Vec<PathBuf> = Vec::new();
+
+    for scanned in scan {
+        let parent_id = scanned.parent_id;
+        let parent = parents.get(parent_id.as_str()).copied();
+        let git_paths: HashSet<PathBuf> = match parent {
+            Some(parent) => git_list_worktrees(&PathBuf::from(&parent.path))
+                .await
+                .map(|worktrees| {
+                    worktrees
+                        .iter()
+                        .map(|worktree| comparable_path(Path::new(&worktree.path)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            None => {
+                unregistered_parent_dirs.push(scanned.dir);
+                HashSet::new()
+            }
+        };
+
+        for candidate in scanned.candidates {
+            let child = candidate.path;
+            let git_registered = git_paths.contains(&candidate.comparable);
+            report.reclaimable_bytes += candidate.size_bytes;
+            report.orphans.push(OrphanedWorktree {
+                path: child.to_string_lossy().to_string(),
+                parent_id: parent_id.clone(),
+                parent_registered: parent.is_some(),
+                git_registered,
+                size_bytes: candidate.size_bytes,
+            });
+
+            if !apply {
+                continue;
+            }
+            if let Err(error) = authorize(&parent_id) {
+                report.failures.push(WorktreeGcFailure {
+                    path: child.to_string_lossy().to_string(),
+                    error,
+                });
+                continue;
+            }
+            let removal = match parent {
+                Some(parent) => {
+                    let repo_path = PathBuf::from(&parent.path);
+                    let child_string = child.to_string_lossy().to_string();
+                    let removed_by_git = git_registered
+                        && run_git_command(
+                            &repo_path,
+                            &["worktree", "remove", "--force", &child_string],
+                        )
+                        .await
+                        .is_ok();
+                    let removal = if removed_by_git {
+                        Ok(())
+                    } else {
+                        let child = child.clone();
+                        blocking(move || remove_orphan_path(&child))
+                            .await
+                            .and_then(|r| r)
+                    };
+                    removal.map(|()| Some(PruneTarget::Repo(repo_path)))
+                }
+                // The main repo's git dir has to be read before the folder goes.
+                None => {
+                    let child = child.clone();
+                    blocking(move || {
+                        let common_dir = read_worktree_common_dir(&child);
+                        remove_orphan_path(&child).map(|()| common_dir.map(PruneTarget::GitDir))
+                    })
+                    .await
+                    .and_then(|r| r)
+                }
+            };
+            match removal {
+                Ok(prune_target) => {
+                    report.removed.push(child.to_string_lossy().to_string());
+                    if let Some(target) = prune_target {
+                        if !prune_targets.contains(&target) {
+                            prune_targets.push(target);
+                        }
+                    }
+                }
+                Err(error) => report.failures.push(WorktreeGcFailure {
+                    path: child.to_string_lossy().to_string(),
+                    error,
+                }),
+            }
+        }
+    }
+
+    if apply {
+        for target in prune_targets {
+            let _ = match target {
+                PruneTarget::Repo(repo_path) => {
+                    run_git_command(&repo_path, &["worktree", "prune", "--expire", "now"]).await
+                }
+                PruneTarget::GitDir(git_dir) => {
+                    let git_dir_string = git_dir.to_string_lossy().to_string();
+                    run_git_command(
+                        &git_dir,
+                        &[
+                            "--git-dir",
+                            &git_dir_string,
+                            "worktree",
+                            "prune",
+                            "--expire",
+                            "now",
+                        ],
+                    )
+                    .await
+                }
+            };
+        }
+        // Parent folders of workspaces that no longer exist are only removed once empty.
+        let _ = blocking(move || {
+            for parent_dir in unregistered_parent_dirs {
+                let _ = std::fs::remove_dir(&parent_dir);
+            }
+        })
+        .await;
+    }
+
+    report
+}
+
+/// One `<parent-id>` folder and the worktree folders in it that no workspace
+/// is registered at.
+struct ScannedParentDir {
+    dir: PathBuf,
+    parent_id: String,
+    candidates: Vec<OrphanCandidate>,
+}
+
+struct OrphanCandidate {
+    path: PathBuf,
+    comparable: PathBuf,
+    size_bytes: u64,
+}
+
+/// Reads the worktrees folder and sizes each unregistered worktree folder.
+/// Runs on a blocking thread.
+fn scan_worktree_dirs(
+    worktrees_root: &Path,
+    workspaces: &[WorkspaceEntry],
+) -> Vec<ScannedParentDir> {
+    let registered: HashSet<PathBuf> = workspaces
+        .iter()
+        .filter(|entry| entry.kind.is_worktree())
+        .map(|entry| comparable_path(Path::new(&entry.path)))
+        .collect();
+    let mut scan = Vec::new();
+    for dir in list_child_dirs(worktrees_root) {
+        let Some(parent_id) = dir.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let parent_id = parent_id.to_string();
+        let candidates = list_child_dirs(&dir)
+            .into_iter()
+            .filter_map(|path| {
+                let comparable = comparable_path(&path);
+                if registered.contains(&comparable) || modified_recently(&path) {
+                    return None;
+                }
+                let size_bytes = directory_size(&path);
+                Some(OrphanCandidate {
+                    path,
+                    comparable,
+                    size_bytes,
+                })
+            })
+            .collect();
+        scan.push(ScannedParentDir {
+            dir,
+            parent_id,
+            candidates,
+        });
+    }
+    scan
+}
+
+#[derive(PartialEq)]
+enum PruneTarget {
+    Repo(PathBuf),
+    GitDir(PathBuf),
+}
+
+pub(crate) fn list_child_dirs(path: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.symlink_metadata()
+                .map(|metadata| metadata.is_dir())
+                .unwrap_or(false)
+        })
+        .collect();
+    dirs.sort();
+    dirs
+}
+
+fn modified_recently(path: &Path) -> bool {
+    path.symlink_metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age < MIN_ORPHAN_AGE)
+}
+
+/// Sums file sizes below `path` without following symlinks.
+pub(crate) fn directory_size(path: &Path) -> u64 {
+    let mut total = 0u64;
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.path().symlink_metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else {
+                total += metadata.len();
+            }
+        }
+    }
+    total
+}
+
+/// Resolves the main repository's git dir from a worktree's `.git` file
+/// (`gitdir: <repo>/.git/worktrees/<name>`).
+fn read_worktree_common_dir(worktree_path: &Path) -> Option<PathBuf> {
+    let contents = std::fs::read_to_string(worktree_path.join(".git")).ok()?;
+    let gitdir = contents
+        .lines()
+        .find_map(|line| line.strip_prefix("gitdir:"))?;
+    let gitdir = PathBuf::from(gitdir.trim());
+    let common_dir = gitdir.parent()?.parent()?.to_path_buf();
+    if common_dir.is_dir() {
+        Some(common_dir)
+    } else {
+        None
+    }
+}
+
+fn remove_orphan_path(path: &Path) -> Result<(), String> {
+    std::fs::remove_dir_all(path).map_err(|err| format!("Failed to remove worktree folder: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{directory_size, read_worktree_common_dir, scan_worktree_dirs, MIN_ORPHAN_AGE};
+    use crate::types::{WorkspaceEntry, WorkspaceKind, WorkspaceSettings, WorktreeInfo};
+    use std::path::{Path, PathBuf};
+    use std::time::SystemTime;
+    use uuid::Uuid;
+
+    /// A scratch folder that is deleted when the test ends, even by a panic.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("codex-monitor-test-{}", Uuid::new_v4())))
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn directory_size_sums_nested_files() {
+        let temp = TempDir::new();
+        let temp_dir = &temp.0;
+        std::fs::create_dir_all(temp_dir.join("nested")).expect("create temp dir");
+        std::fs::write(temp_dir.join("a.txt"), b"hello").expect("write a");
+        std::fs::write(temp_dir.join("nested").join("b.txt"), b"world!").expect("write b");
+
+        assert_eq!(directory_size(temp_dir), 11);
+    }
+
+    #[test]
+    fn read_worktree_common_dir_resolves_repo_git_dir() {
+        let temp = TempDir::new();
+        let temp_dir = &temp.0;
+        let common_dir = temp_dir.join("repo").join(".git");
+        let gitdir = common_dir.join("worktrees").join("feature");
+        std::fs::create_dir_all(&gitdir).expect("create gitdir");
+        let worktree = temp_dir.join("worktree");
+        std::fs::create_dir_all(&worktree).expect("create worktree");
+        std::fs::write(
+            worktree.join(".git"),
+            format!("gitdir: {}\n", gitdir.display()),
+        )
+        .expect("write .git file");
+
+        assert_eq!(read_worktree_common_dir(&worktree), Some(common_dir));
+    }
+
+    fn worktree_entry(path: &Path) -> WorkspaceEntry {
+        WorkspaceEntry {
+            id: "wt".to_string(),
+            name: "feature".to_string(),
+            path: path.to_string_lossy().to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Worktree,
+            parent_id: Some("main".to_string()),
+            worktree: Some(WorktreeInfo {
+                branch: "feature".to_string(),
+                dir_name: None,
+            }),
+            settings: WorkspaceSettings::default(),
+            status: None,
+        }
+    }
+
+    fn age(path: &Path) {
+        let old = SystemTime::now() - MIN_ORPHAN_AGE * 2;
+        std::fs::File::open(path)
+            .and_then(|dir| dir.set_modified(old))
+            .expect("age folder");
+    }
+
+    #[test]
+    fn scan_lists_old_unregistered_worktree_folders() {
+        let temp = TempDir::new();
+        let parent = temp.0.join("main");
+        for name in ["registered", "orphan", "fresh"] {
+            std::fs::create_dir_all(parent.join(name)).expect("create worktree");
+        }
+        std::fs::write(parent.join("orphan").join("a.txt"), b"hello").expect("write");
+        age(&parent.join("registered"));
+        age(&parent.join("orphan"));
+
+        let entries = [worktree_entry(&parent.join("registered"))];
+        let scan = scan_worktree_dirs(&temp.0, &entries);
+        assert_eq!(scan.len(), 1);
+        assert_eq!(scan[0].parent_id, "main");
+        let candidates: Vec<_> = scan[0]
+            .candidates
+            .iter()
+            .map(|candidate| (candidate.path.clone(), candidate.size_bytes))
+            .collect();
+        assert_eq!(candidates, [(parent.join("orphan"), 5)]);
+    }
+}
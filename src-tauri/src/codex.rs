@@ -2,9 +2,9 @@ use serde_json::{json, Map, Value};
 use std::io::ErrorKind;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use tauri::{AppHandle, State};
+use tauri::{AppHandle, Manager, State};
 use tokio::process::Command;
 use tokio::sync::mpsc;
 use tokio::time::timeout;
@@ -14,8 +14,13 @@ use crate::backend::app_server::{
     build_codex_command_with_bin, build_codex_path_env, check_codex_installation,
     spawn_workspace_session as spawn_workspace_session_inner,
 };
+use crate::backend::message_policy::{
+    build_message_policy, effective_access_mode, workspace_approval_policy,
+    workspace_writable_roots,
+};
 use crate::codex_home::resolve_workspace_codex_home;
 use crate::event_sink::TauriEventSink;
+use crate::preflight::{run_checks, PreflightReport};
 use crate::remote_backend;
 use crate::rules;
 use crate::state::AppState;
@@ -28,10 +33,16 @@ pub(crate) async fn spawn_workspace_session(
     codex_home: Option<PathBuf>,
 ) -> Result<Arc<WorkspaceSession>, String> {
     let client_version = app_handle.package_info().version.to_string();
+    let experimental_flags = {
+        let state = app_handle.state::<AppState>();
+        let settings = state.app_settings.lock().await;
+        settings.experimental_flags()
+    };
     let event_sink = TauriEventSink::new(app_handle);
     spawn_workspace_session_inner(
         entry,
         default_codex_bin,
+        experimental_flags,
         client_version,
         event_sink,
         codex_home,
@@ -131,6 +142,46 @@ pub(crate) async fn codex_doctor(
     }))
 }
 
+/// The setup checklist of `preflight_check`. `workspace_ids` adds a check of
+/// each workspace's folder.
+#[tauri::command]
+pub(crate) async fn preflight_check(
+    workspace_ids: Option<Vec<String>>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "preflight_check",
+            json!({ "workspaceIds": workspace_ids }),
+        )
+        .await;
+    }
+
+    let entries = {
+        let workspaces = state.workspaces.lock().await;
+        workspace_ids
+            .unwrap_or_default()
+            .iter()
+            .map(|id| {
+                workspaces
+                    .get(id)
+                    .cloned()
+                    .ok_or_else(|| format!("workspace not found: {id}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    let codex_bin = state.app_settings.lock().await.codex_bin.clone();
+    let checks = run_checks(codex_bin, state.storage_path.parent(), &entries).await;
+    let checked_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or_default();
+    serde_json::to_value(PreflightReport::new(checks, checked_at)).map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub(crate) async fn start_thread(
     workspace_id: String,
@@ -147,13 +198,19 @@ pub(crate) async fn start_thread(
         .await;
     }
 
+    let approval_policy = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .and_then(workspace_approval_policy)
+    };
     let sessions = state.sessions.lock().await;
     let session = sessions
         .get(&workspace_id)
         .ok_or("workspace not connected")?;
     let params = json!({
         "cwd": session.entry.path,
-        "approvalPolicy": "on-request"
+        "approvalPolicy": approval_policy.unwrap_or("on-request")
     });
     session.send_request("thread/start", params).await
 }
@@ -273,30 +330,21 @@ pub(crate) async fn send_user_message(
         .await;
     }
 
+    let (access_mode, writable_roots, approval_policy) = {
+        let workspaces = state.workspaces.lock().await;
+        let entry = workspaces.get(&workspace_id).ok_or("workspace not found")?;
+        (
+            effective_access_mode(access_mode.as_deref(), entry),
+            workspace_writable_roots(entry, &workspaces),
+            workspace_approval_policy(entry),
+        )
+    };
     let sessions = state.sessions.lock().await;
     let session = sessions
         .get(&workspace_id)
         .ok_or("workspace not connected")?;
-    let access_mode = access_mode.unwrap_or_else(|| "current".to_string());
-    let sandbox_policy = match access_mode.as_str() {
-        "full-access" => json!({
-            "type": "dangerFullAccess"
-        }),
-        "read-only" => json!({
-            "type": "readOnly"
-        }),
-        _ => json!({
-            "type": "workspaceWrite",
-            "writableRoots": [session.entry.path],
-            "networkAccess": true
-        }),
-    };
-
-    let approval_policy = if access_mode == "full-access" {
-        "never"
-    } else {
-        "on-request"
-    };
+    let policy = build_message_policy(Some(&access_mode), &session.entry.path, &writable_roots)
+        .with_approval_policy(approval_policy);
 
     let trimmed_text = text.trim();
     let mut input: Vec<Value> = Vec::new();
@@ -327,13 +375,46 @@ pub(crate) async fn send_user_message(
         "threadId": thread_id,
         "input": input,
         "cwd": session.entry.path,
-        "approvalPolicy": approval_policy,
-        "sandboxPolicy": sandbox_policy,
+        "approvalPolicy": policy.approval_policy,
+        "sandboxPolicy": policy.sandbox_policy,
         "model": model,
         "effort": effort,
         "collaborationMode": collaboration_mode,
     });
-    session.send_request("turn/start", params).await
+    let mut response = session.send_request("turn/start", params).await?;
+    if let Some(object) = response.as_object_mut() {
+        object.insert("accessMode".to_string(), Value::String(access_mode));
+        object.insert("approvalPolicy".to_string(), json!(policy.approval_policy));
+    }
+    Ok(response)
+}
+
+#[tauri::command]
+pub(crate) async fn preview_message_policy(
+    workspace_id: String,
+    access_mode: Option<String>,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<Value, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        return remote_backend::call_remote(
+            &*state,
+            app,
+            "preview_message_policy",
+            json!({ "workspaceId": workspace_id, "accessMode": access_mode }),
+        )
+        .await;
+    }
+
+    let workspaces = state.workspaces.lock().await;
+    let entry = workspaces.get(&workspace_id).ok_or("workspace not found")?;
+    let writable_roots = workspace_writable_roots(entry, &workspaces);
+    let access_mode = effective_access_mode(access_mode.as_deref(), entry);
+    let mut policy = build_message_policy(Some(&access_mode), &entry.path, &writable_roots)
+        .with_approval_policy(workspace_approval_policy(entry))
+        .to_value();
+    policy["accessMode"] = Value::String(access_mode);
+    Ok(policy)
 }
 
 #[tauri::command]
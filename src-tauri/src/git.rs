@@ -1,3 +1,4 @@
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
 use git2::{BranchType, DiffOptions, Repository, Sort, Status, StatusOptions};
@@ -5,11 +6,17 @@ use serde_json::json;
 use tauri::State;
 use tokio::process::Command;
 
+use crate::backend::git_command::git_command;
+use crate::git_identity::{read_git_identity, write_git_identity, GitIdentity, GitIdentityScope};
+use crate::git_state::{
+    detect_git_operation, ensure_git_repo, ensure_no_git_operation, read_head, HeadState,
+};
 use crate::git_utils::{
     checkout_branch, commit_to_entry, diff_patch_to_string, diff_stats_for_path,
     list_git_roots as scan_git_roots, parse_github_repo, resolve_git_root,
 };
 use crate::state::AppState;
+use crate::submodules::submodule_paths;
 use crate::types::{
     BranchInfo, GitCommitDiff, GitFileDiff, GitFileStatus, GitHubIssue, GitHubIssuesResponse,
     GitHubPullRequest, GitHubPullRequestComment, GitHubPullRequestDiff,
@@ -20,7 +27,7 @@ use crate::utils::normalize_git_path;
 const INDEX_SKIP_WORKTREE_FLAG: u16 = 0x4000;
 
 async fn run_git_command(repo_root: &Path, args: &[&str]) -> Result<(), String> {
-    let output = Command::new("git")
+    let output = git_command()
         .args(args)
         .current_dir(repo_root)
         .output()
@@ -394,13 +401,12 @@ pub(crate) async fn get_git_status(
         .clone();
 
     let repo_root = resolve_git_root(&entry)?;
+    ensure_git_repo(&repo_root)?;
     let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
 
-    let branch_name = repo
-        .head()
-        .ok()
-        .and_then(|head| head.shorthand().map(|s| s.to_string()))
-        .unwrap_or_else(|| "unknown".to_string());
+    let (branch_name, detached_at) = read_head(&repo_root)
+        .map(HeadState::into_parts)
+        .unwrap_or_default();
 
     let mut status_options = StatusOptions::new();
     status_options
@@ -416,6 +422,7 @@ pub(crate) async fn get_git_status(
 
     let head_tree = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
     let index = repo.index().ok();
+    let submodules: HashSet<String> = submodule_paths(&repo_root).into_iter().collect();
 
     let mut files = Vec::new();
     let mut staged_files = Vec::new();
@@ -436,6 +443,7 @@ pub(crate) async fn get_git_status(
         }
         let status = entry.status();
         let normalized_path = normalize_git_path(path);
+        let submodule = submodules.contains(&normalized_path);
         let include_index = status.intersects(
             Status::INDEX_NEW
                 | Status::INDEX_MODIFIED
@@ -463,6 +471,7 @@ pub(crate) async fn get_git_status(
                     status: status_str.to_string(),
                     additions,
                     deletions,
+                    submodule,
                 });
             }
             combined_additions += additions;
@@ -481,6 +490,7 @@ pub(crate) async fn get_git_status(
                     status: status_str.to_string(),
                     additions,
                     deletions,
+                    submodule,
                 });
             }
             combined_additions += additions;
@@ -498,12 +508,15 @@ pub(crate) async fn get_git_status(
                 status: status_str.to_string(),
                 additions: combined_additions,
                 deletions: combined_deletions,
+                submodule,
             });
         }
     }
 
     Ok(json!({
         "branchName": branch_name,
+        "detachedAt": detached_at,
+        "operation": detect_git_operation(&repo_root),
         "files": files,
         "stagedFiles": staged_files,
         "unstagedFiles": unstaged_files,
@@ -946,6 +959,42 @@ pub(crate) async fn get_git_commit_diff(
     Ok(results)
 }
 
+#[tauri::command]
+pub(crate) async fn get_git_identity(
+    workspace_id: String,
+    state: State<'_, AppState>,
+) -> Result<GitIdentity, String> {
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .ok_or("workspace not found")?
+            .clone()
+    };
+    let repo_root = resolve_git_root(&entry)?;
+    read_git_identity(&repo_root).await
+}
+
+#[tauri::command]
+pub(crate) async fn set_git_identity(
+    workspace_id: String,
+    name: Option<String>,
+    email: Option<String>,
+    scope: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<GitIdentity, String> {
+    let entry = {
+        let workspaces = state.workspaces.lock().await;
+        workspaces
+            .get(&workspace_id)
+            .ok_or("workspace not found")?
+            .clone()
+    };
+    let scope = GitIdentityScope::parse(scope.as_deref(), entry.kind.is_worktree())?;
+    let repo_root = resolve_git_root(&entry)?;
+    write_git_identity(&repo_root, scope, name.as_deref(), email.as_deref()).await
+}
+
 #[tauri::command]
 pub(crate) async fn get_git_remote(
     workspace_id: String,
@@ -1262,6 +1311,7 @@ pub(crate) async fn checkout_git_branch(
         .ok_or("workspace not found")?
         .clone();
     let repo_root = resolve_git_root(&entry)?;
+    ensure_no_git_operation(&repo_root, "check out a branch")?;
     let repo = Repository::open(&repo_root).map_err(|e| e.to_string())?;
     checkout_branch(&repo, &name).map_err(|e| e.to_string())
 }
@@ -0,0 +1,129 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::backend::git_command::git_command;
+
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+pub(crate) struct GitIdentity {
+    pub(crate) name: Option<String>,
+    pub(crate) email: Option<String>,
+}
+
+/// Which config file `set_git_identity` writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum GitIdentityScope {
+    /// `.git/config` of the repository, shared by all of its worktrees.
+    Local,
+    /// `config.worktree` of this checkout only; enables
+    /// `extensions.worktreeConfig` in the repository when needed.
+    Worktree,
+}
+
+impl GitIdentityScope {
+    /// Worktrees share the repo config, so they must say which one they mean.
+    pub(crate) fn parse(value: Option<&str>, is_worktree: bool) -> Result<Self, String> {
+        match value.map(str::trim) {
+            Some("local") => Ok(Self::Local),
+            Some("worktree") => Ok(Self::Worktree),
+            None | Some("") if !is_worktree => Ok(Self::Local),
+            None | Some("") => Err(
+                "scope is required for worktrees: \"local\" (shared with the parent repo) or \"worktree\""
+                    .to_string(),
+            ),
+            Some(other) => Err(format!(
+                "invalid scope \"{other}\": expected \"local\" or \"worktree\""
+            )),
+        }
+    }
+
+    fn flag(self) -> &'static str {
+        match self {
+            Self::Local => "--local",
+            Self::Worktree => "--worktree",
+        }
+    }
+}
+
+async fn git_config(path: &Path, args: &[&str]) -> Result<Option<String>, String> {
+    let output = git_command()
+        .arg("config")
+        .args(args)
+        .current_dir(path)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    if output.status.success() {
+        let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        return Ok((!value.is_empty()).then_some(value));
+    }
+    // Exit code 1 means the key is not set (or there was nothing to unset).
+    if output.status.code() == Some(1) {
+        return Ok(None);
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if stderr.is_empty() {
+        Err("Git command failed.".to_string())
+    } else {
+        Err(stderr)
+    }
+}
+
+/// Effective `user.name`/`user.email` in `path`, from any config level.
+pub(crate) async fn read_git_identity(path: &Path) -> Result<GitIdentity, String> {
+    Ok(GitIdentity {
+        name: git_config(path, &["--get", "user.name"]).await?,
+        email: git_config(path, &["--get", "user.email"]).await?,
+    })
+}
+
+/// Writes the given fields at `scope` (an empty string unsets it there) and
+/// returns the resulting effective identity.
+pub(crate) async fn write_git_identity(
+    path: &Path,
+    scope: GitIdentityScope,
+    name: Option<&str>,
+    email: Option<&str>,
+) -> Result<GitIdentity, String> {
+    if name.is_none() && email.is_none() {
+        return Err("Provide name and/or email.".to_string());
+    }
+    if scope == GitIdentityScope::Worktree {
+        git_config(path, &["--local", "extensions.worktreeConfig", "true"]).await?;
+    }
+    for (key, value) in [("user.name", name), ("user.email", email)] {
+        let Some(value) = value.map(str::trim) else {
+            continue;
+        };
+        if value.is_empty() {
+            git_config(path, &[scope.flag(), "--unset", key]).await?;
+        } else {
+            git_config(path, &[scope.flag(), key, value]).await?;
+        }
+    }
+    read_git_identity(path).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GitIdentityScope;
+
+    #[test]
+    fn scope_defaults_to_local_only_for_main_workspaces() {
+        assert_eq!(
+            GitIdentityScope::parse(None, false),
+            Ok(GitIdentityScope::Local)
+        );
+        assert!(GitIdentityScope::parse(None, true).is_err());
+        assert!(GitIdentityScope::parse(Some(" "), true).is_err());
+        assert_eq!(
+            GitIdentityScope::parse(Some("worktree"), true),
+            Ok(GitIdentityScope::Worktree)
+        );
+        assert_eq!(
+            GitIdentityScope::parse(Some("local"), true),
+            Ok(GitIdentityScope::Local)
+        );
+        assert!(GitIdentityScope::parse(Some("global"), false).is_err());
+    }
+}
@@ -0,0 +1,336 @@
+use std::path::{Path, PathBuf};
+
+use crate::types::{GitOperation, WorkspaceEntry};
+
+/// The git dir of a checkout: `.git` itself, or the per-worktree dir a `.git`
+/// file points to (`gitdir: ...`, relative to the checkout when not absolute).
+pub(crate) fn resolve_git_dir(checkout: &Path) -> Option<PathBuf> {
+    let dot_git = checkout.join(".git");
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let content = std::fs::read_to_string(&dot_git).ok()?;
+    let target = content.trim().strip_prefix("gitdir:")?.trim();
+    if target.is_empty() {
+        return None;
+    }
+    let target = PathBuf::from(target);
+    Some(if target.is_absolute() {
+        target
+    } else {
+        checkout.join(target)
+    })
+}
+
+/// Error prefix for operations that need a git repository.
+pub(crate) const NOT_A_GIT_REPO: &str = "NOT_A_GIT_REPO";
+
+/// Whether `path` is the root of a git checkout. A folder inside another
+/// repo doesn't count; workspaces in a subfolder of a repo name the repo
+/// with their `gitRoot` setting.
+pub(crate) fn is_git_repo(path: &Path) -> bool {
+    resolve_git_dir(path).is_some()
+}
+
+/// The workspace folder, or its configured `gitRoot` inside it.
+pub(crate) fn workspace_git_path(entry: &WorkspaceEntry) -> PathBuf {
+    let base = PathBuf::from(&entry.path);
+    match entry
+        .settings
+        .git_root
+        .as_deref()
+        .map(str::trim)
+        .filter(|root| !root.is_empty())
+    {
+        Some(root) => base.join(root),
+        None => base,
+    }
+}
+
+/// Fails early with a `NOT_A_GIT_REPO` error for folders outside any repo, so
+/// git features don't fail deep inside git with confusing messages.
+pub(crate) fn ensure_git_repo(path: &Path) -> Result<(), String> {
+    if is_git_repo(path) {
+        Ok(())
+    } else {
+        Err(format!(
+            "{NOT_A_GIT_REPO}: {} is not a git repository.",
+            path.display()
+        ))
+    }
+}
+
+/// Error prefix for bare repositories, which have no checkout to work in.
+pub(crate) const BARE_REPO: &str = "BARE_REPO";
+
+/// Whether `path` is a bare repository (a git dir without a checkout).
+pub(crate) fn is_bare_repo(path: &Path) -> bool {
+    resolve_git_dir(path).is_none()
+        && path.join("HEAD").is_file()
+        && path.join("objects").is_dir()
+        && path.join("refs").is_dir()
+}
+
+/// Rejects bare repositories as workspaces with a `BARE_REPO` error.
+pub(crate) fn ensure_not_bare_repo(path: &Path) -> Result<(), String> {
+    if is_bare_repo(path) {
+        Err(format!(
+            "{BARE_REPO}: {} is a bare repository with no working tree. Add a clone of it instead.",
+            path.display()
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// What `HEAD` of a checkout points at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum HeadState {
+    /// A branch, possibly unborn (no commits yet).
+    Branch(String),
+    /// Detached at this commit.
+    Detached(String),
+}
+
+impl HeadState {
+    /// `(branch, detachedAt)`, as reported by status RPCs.
+    pub(crate) fn into_parts(self) -> (Option<String>, Option<String>) {
+        match self {
+            Self::Branch(branch) => (Some(branch), None),
+            Self::Detached(commit) => (None, Some(commit)),
+        }
+    }
+}
+
+fn parse_head(content: &str) -> Option<HeadState> {
+    let content = content.trim();
+    if let Some(target) = content.strip_prefix("ref:") {
+        let target = target.trim();
+        let branch = target.strip_prefix("refs/heads/").unwrap_or(target);
+        return Some(HeadState::Branch(branch.to_string()));
+    }
+    let is_hash = !content.is_empty() && content.chars().all(|ch| ch.is_ascii_hexdigit());
+    is_hash.then(|| HeadState::Detached(content.to_string()))
+}
+
+/// `HEAD` of the checkout containing `path`, read from its git dir.
+pub(crate) fn read_head(path: &Path) -> Option<HeadState> {
+    let git_dir = path.ancestors().find_map(resolve_git_dir)?;
+    let content = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    parse_head(&content)
+}
+
+/// Merge/rebase/cherry-pick/revert/bisect left in progress in `checkout`.
+pub(crate) fn detect_git_operation(checkout: &Path) -> Option<GitOperation> {
+    let git_dir = resolve_git_dir(checkout)?;
+    if git_dir.join("rebase-merge").is_dir() || git_dir.join("rebase-apply").is_dir() {
+        return Some(GitOperation::Rebase);
+    }
+    if git_dir.join("MERGE_HEAD").is_file() {
+        return Some(GitOperation::Merge);
+    }
+    if git_dir.join("CHERRY_PICK_HEAD").is_file() {
+        return Some(GitOperation::CherryPick);
+    }
+    if git_dir.join("REVERT_HEAD").is_file() {
+        return Some(GitOperation::Revert);
+    }
+    if git_dir.join("BISECT_LOG").is_file() {
+        return Some(GitOperation::Bisect);
+    }
+    None
+}
+
+/// Refuses to run `action` while a git operation is in progress in `checkout`.
+pub(crate) fn ensure_no_git_operation(checkout: &Path, action: &str) -> Result<(), String> {
+    match detect_git_operation(checkout) {
+        Some(operation) => Err(format!(
+            "Cannot {action}: a {} is in progress. Finish or abort it first.",
+            operation.label()
+        )),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        detect_git_operation, ensure_git_repo, ensure_no_git_operation, ensure_not_bare_repo,
+        is_bare_repo, is_git_repo, parse_head, read_head, resolve_git_dir, workspace_git_path,
+        HeadState, BARE_REPO, NOT_A_GIT_REPO,
+    };
+    use crate::types::{GitOperation, WorkspaceEntry, WorkspaceKind, WorkspaceSettings};
+    use git2::{Repository, WorktreeAddOptions, WorktreePruneOptions};
+    use std::path::{Path, PathBuf};
+    use uuid::Uuid;
+
+    fn temp_checkout() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("codex-monitor-git-state-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(root.join(".git")).expect("create .git");
+        root
+    }
+
+    #[test]
+    fn detects_operations_from_git_dir_markers() {
+        let checkout = temp_checkout();
+        assert_eq!(detect_git_operation(&checkout), None);
+        assert!(ensure_no_git_operation(&checkout, "check out a branch").is_ok());
+
+        std::fs::write(checkout.join(".git").join("MERGE_HEAD"), "abc\n").expect("write");
+        assert_eq!(detect_git_operation(&checkout), Some(GitOperation::Merge));
+        let error = ensure_no_git_operation(&checkout, "check out a branch").unwrap_err();
+        assert!(error.contains("merge is in progress"));
+
+        std::fs::create_dir_all(checkout.join(".git").join("rebase-merge")).expect("mkdir");
+        assert_eq!(detect_git_operation(&checkout), Some(GitOperation::Rebase));
+
+        let _ = std::fs::remove_dir_all(&checkout);
+    }
+
+    #[test]
+    fn follows_gitdir_files_of_linked_worktrees() {
+        let repo = temp_checkout();
+        let worktree_git_dir = repo.join(".git").join("worktrees").join("feature");
+        std::fs::create_dir_all(&worktree_git_dir).expect("create worktree git dir");
+        let worktree = repo.join("feature");
+        std::fs::create_dir_all(&worktree).expect("create worktree");
+        std::fs::write(
+            worktree.join(".git"),
+            format!("gitdir: {}\n", worktree_git_dir.display()),
+        )
+        .expect("write .git file");
+
+        assert_eq!(resolve_git_dir(&worktree), Some(worktree_git_dir.clone()));
+        std::fs::write(worktree_git_dir.join("CHERRY_PICK_HEAD"), "abc\n").expect("write");
+        assert_eq!(
+            detect_git_operation(&worktree),
+            Some(GitOperation::CherryPick)
+        );
+        assert_eq!(detect_git_operation(&repo), None);
+
+        let _ = std::fs::remove_dir_all(&repo);
+    }
+
+    #[test]
+    fn only_checkout_roots_are_repos() {
+        let repo = temp_checkout();
+        let nested = repo.join("src").join("lib");
+        std::fs::create_dir_all(&nested).expect("create nested");
+        assert!(is_git_repo(&repo));
+        assert!(ensure_git_repo(&repo).is_ok());
+        // A plain folder inside a repo, like one under a dotfiles repo in ~.
+        assert!(!is_git_repo(&nested));
+        assert!(ensure_git_repo(&nested)
+            .unwrap_err()
+            .starts_with(NOT_A_GIT_REPO));
+
+        let mut entry = WorkspaceEntry {
+            id: "ws".to_string(),
+            name: "lib".to_string(),
+            path: nested.to_string_lossy().to_string(),
+            codex_bin: None,
+            kind: WorkspaceKind::Main,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings::default(),
+            status: None,
+        };
+        assert_eq!(workspace_git_path(&entry), nested);
+        entry.settings.git_root = Some(" ../.. ".to_string());
+        assert!(is_git_repo(&workspace_git_path(&entry)));
+
+        let plain = std::env::temp_dir().join(format!("codex-monitor-plain-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&plain).expect("create plain dir");
+        let error = ensure_git_repo(&plain).unwrap_err();
+        assert!(error.starts_with(NOT_A_GIT_REPO));
+
+        let _ = std::fs::remove_dir_all(&repo);
+        let _ = std::fs::remove_dir_all(&plain);
+    }
+
+    #[test]
+    fn parses_branch_and_detached_heads() {
+        assert_eq!(
+            parse_head("ref: refs/heads/feature/x\n"),
+            Some(HeadState::Branch("feature/x".to_string()))
+        );
+        let hash = "0123456789abcdef0123456789abcdef01234567";
+        assert_eq!(
+            parse_head(&format!("{hash}\n")),
+            Some(HeadState::Detached(hash.to_string()))
+        );
+        assert_eq!(
+            HeadState::Detached(hash.to_string()).into_parts(),
+            (None, Some(hash.to_string()))
+        );
+        assert_eq!(parse_head(""), None);
+    }
+
+    fn commit_file(repo: &Repository, root: &Path) -> git2::Oid {
+        std::fs::write(root.join("a.txt"), "hello\n").expect("write file");
+        let mut index = repo.index().expect("index");
+        index.add_path(Path::new("a.txt")).expect("add path");
+        let tree_id = index.write_tree().expect("write tree");
+        let tree = repo.find_tree(tree_id).expect("find tree");
+        let sig = git2::Signature::now("Test", "test@example.com").expect("signature");
+        repo.commit(Some("HEAD"), &sig, &sig, "init", &tree, &[])
+            .expect("commit")
+    }
+
+    #[test]
+    fn worktrees_of_a_detached_parent_report_their_own_head() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-detached-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create repo root");
+        let repo = Repository::init(&root).expect("init repo");
+        let commit = commit_file(&repo, &root);
+        assert!(matches!(read_head(&root), Some(HeadState::Branch(_))));
+
+        repo.set_head_detached(commit).expect("detach head");
+        assert!(is_git_repo(&root));
+        assert_eq!(
+            read_head(&root.join("nested")),
+            Some(HeadState::Detached(commit.to_string()))
+        );
+
+        let target = repo.find_commit(commit).expect("find commit");
+        let branch = repo.branch("feature", &target, false).expect("branch");
+        let worktree_path = root.with_extension("feature");
+        let mut options = WorktreeAddOptions::new();
+        options.reference(Some(branch.get()));
+        let worktree = repo
+            .worktree("feature", &worktree_path, Some(&options))
+            .expect("add worktree");
+        assert_eq!(
+            read_head(&worktree_path),
+            Some(HeadState::Branch("feature".to_string()))
+        );
+        assert_eq!(
+            read_head(&root),
+            Some(HeadState::Detached(commit.to_string()))
+        );
+
+        std::fs::remove_dir_all(&worktree_path).expect("remove worktree");
+        let mut prune = WorktreePruneOptions::new();
+        worktree.prune(Some(&mut prune)).expect("prune worktree");
+        assert!(repo.worktrees().expect("worktrees").is_empty());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn rejects_bare_repositories() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-bare-{}", Uuid::new_v4()));
+        Repository::init_bare(&root).expect("init bare repo");
+        assert!(is_bare_repo(&root));
+        assert!(!is_git_repo(&root));
+        let error = ensure_not_bare_repo(&root).unwrap_err();
+        assert!(error.starts_with(BARE_REPO));
+
+        let checkout = temp_checkout();
+        assert!(!is_bare_repo(&checkout));
+        assert!(ensure_not_bare_repo(&checkout).is_ok());
+
+        let _ = std::fs::remove_dir_all(&root);
+        let _ = std::fs::remove_dir_all(&checkout);
+    }
+}
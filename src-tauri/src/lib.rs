@@ -12,9 +12,12 @@ mod dictation;
 mod dictation;
 mod event_sink;
 mod git;
+mod git_identity;
+mod git_state;
 mod git_utils;
 mod local_usage;
 mod menu;
+mod preflight;
 mod prompts;
 mod remote_backend;
 mod rules;
@@ -23,9 +26,12 @@ mod state;
 mod terminal;
 mod window;
 mod storage;
+mod submodules;
 mod types;
 mod utils;
 mod workspaces;
+mod worktree_move;
+mod worktree_paths;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -67,6 +73,7 @@ pub fn run() {
             settings::get_codex_config_path,
             menu::menu_set_accelerators,
             codex::codex_doctor,
+            codex::preflight_check,
             workspaces::list_workspaces,
             workspaces::is_workspace_path_dir,
             workspaces::add_workspace,
@@ -74,13 +81,16 @@ pub fn run() {
             workspaces::add_worktree,
             workspaces::remove_workspace,
             workspaces::remove_worktree,
+            workspaces::move_worktree,
             workspaces::rename_worktree,
             workspaces::rename_worktree_upstream,
             workspaces::apply_worktree_changes,
             workspaces::update_workspace_settings,
+            workspaces::patch_workspace_settings,
             workspaces::update_workspace_codex_bin,
             codex::start_thread,
             codex::send_user_message,
+            codex::preview_message_policy,
             codex::turn_interrupt,
             codex::start_review,
             codex::respond_to_server_request,
@@ -99,6 +109,8 @@ pub fn run() {
             git::get_git_log,
             git::get_git_commit_diff,
             git::get_git_remote,
+            git::get_git_identity,
+            git::set_git_identity,
             git::stage_git_file,
             git::stage_git_all,
             git::unstage_git_file,
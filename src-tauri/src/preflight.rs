@@ -0,0 +1,464 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::backend::app_server::check_codex_installation;
+use crate::backend::git_command::{git_command, run_git};
+use crate::types::WorkspaceEntry;
+use crate::{codex_config, codex_home, git_state};
+
+/// Oldest git whose `worktree` subcommands cover what CodexMonitor runs.
+const MIN_GIT_VERSION: (u32, u32) = (2, 17);
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+/// One line of the `preflight_check` checklist. `remediation` is a stable
+/// hint clients can map to a fix, such as `install-codex` or `run-login`.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PreflightCheck {
+    pub(crate) id: &'static str,
+    pub(crate) status: CheckStatus,
+    pub(crate) detail: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) remediation: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) workspace_id: Option<String>,
+}
+
+impl PreflightCheck {
+    fn pass(id: &'static str, detail: String) -> Self {
+        Self {
+            id,
+            status: CheckStatus::Pass,
+            detail,
+            remediation: None,
+            workspace_id: None,
+        }
+    }
+
+    fn warn(id: &'static str, detail: String, remediation: &'static str) -> Self {
+        Self {
+            status: CheckStatus::Warn,
+            remediation: Some(remediation),
+            ..Self::pass(id, detail)
+        }
+    }
+
+    fn fail(id: &'static str, detail: String, remediation: &'static str) -> Self {
+        Self {
+            status: CheckStatus::Fail,
+            remediation: Some(remediation),
+            ..Self::pass(id, detail)
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PreflightReport {
+    /// No check failed; warnings don't count.
+    pub(crate) ok: bool,
+    pub(crate) checked_at: u64,
+    pub(crate) checks: Vec<PreflightCheck>,
+}
+
+impl PreflightReport {
+    pub(crate) fn new(checks: Vec<PreflightCheck>, checked_at: u64) -> Self {
+        Self {
+            ok: checks.iter().all(|check| check.status != CheckStatus::Fail),
+            checked_at,
+            checks,
+        }
+    }
+}
+
+/// `(major, minor)` of `git --version` output such as `git version 2.43.0`
+/// or `git version 2.39.3 (Apple Git-146)`.
+fn git_version(output: &str) -> Option<(u32, u32)> {
+    let version = output.trim().strip_prefix("git version ")?;
+    let mut parts = version.split('.');
+    let major = parts.next()?.trim().parse().ok()?;
+    let minor = parts
+        .next()?
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>()
+        .parse()
+        .ok()?;
+    Some((major, minor))
+}
+
+/// From the output of `git --version`, or the error running it.
+pub(crate) fn git_check(result: Result<String, String>) -> PreflightCheck {
+    let output = match result {
+        Ok(output) => output,
+        Err(error) => return PreflightCheck::fail("git", error, "install-git"),
+    };
+    let output = output.trim().to_string();
+    match git_version(&output) {
+        Some(version) if version >= MIN_GIT_VERSION => PreflightCheck::pass("git", output),
+        Some(_) => PreflightCheck::warn(
+            "git",
+            format!(
+                "{output} is older than {}.{}; worktree features may fail.",
+                MIN_GIT_VERSION.0, MIN_GIT_VERSION.1
+            ),
+            "update-git",
+        ),
+        None => PreflightCheck::warn(
+            "git",
+            format!("Unrecognized `git --version` output: {output}"),
+            "update-git",
+        ),
+    }
+}
+
+/// From `check_codex_installation`.
+pub(crate) fn codex_check(result: Result<Option<String>, String>) -> PreflightCheck {
+    match result {
+        Ok(Some(version)) => PreflightCheck::pass("codex", version),
+        Ok(None) => PreflightCheck::warn(
+            "codex",
+            "`codex --version` printed no version.".to_string(),
+            "update-codex",
+        ),
+        Err(error) => PreflightCheck::fail("codex", error, "install-codex"),
+    }
+}
+
+pub(crate) fn codex_home_check(home: Option<&Path>) -> PreflightCheck {
+    let Some(home) = home else {
+        return PreflightCheck::fail(
+            "codexHome",
+            "Unable to resolve CODEX_HOME: neither CODEX_HOME nor HOME is set.".to_string(),
+            "set-codex-home",
+        );
+    };
+    if home.is_dir() {
+        PreflightCheck::pass("codexHome", home.display().to_string())
+    } else {
+        PreflightCheck::warn(
+            "codexHome",
+            format!(
+                "{} does not exist yet; codex creates it on first login.",
+                home.display()
+            ),
+            "run-login",
+        )
+    }
+}
+
+/// The first line that isn't a table header, comment or `key = value`,
+/// with its 1-based number. Multi-line strings and arrays are skipped over
+/// rather than parsed.
+fn config_syntax_error(contents: &str) -> Option<(usize, String)> {
+    let mut open_string: Option<&str> = None;
+    let mut depth: i32 = 0;
+    for (index, line) in contents.lines().enumerate() {
+        let trimmed = line.trim();
+        if let Some(quote) = open_string {
+            if trimmed.contains(quote) {
+                open_string = None;
+            }
+            continue;
+        }
+        if depth > 0 {
+            depth += bracket_balance(trimmed);
+            continue;
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if trimmed.starts_with('[') {
+            let header = trimmed.split('#').next().unwrap_or(trimmed).trim_end();
+            if !header.ends_with(']') || header.trim_matches(['[', ']']).trim().is_empty() {
+                return Some((index + 1, trimmed.to_string()));
+            }
+            continue;
+        }
+        let Some((key, value)) = trimmed.split_once('=') else {
+            return Some((index + 1, trimmed.to_string()));
+        };
+        let value = value.trim();
+        if key.trim().is_empty() || value.is_empty() {
+            return Some((index + 1, trimmed.to_string()));
+        }
+        for quote in ["\"\"\"", "'''"] {
+            if value.starts_with(quote) && value.matches(quote).count() == 1 {
+                open_string = Some(quote);
+            }
+        }
+        if value.starts_with('[') || value.starts_with('{') {
+            depth = bracket_balance(value);
+        }
+    }
+    match (open_string, depth > 0) {
+        (Some(quote), _) => Some((contents.lines().count(), format!("unclosed {quote}"))),
+        (None, true) => Some((contents.lines().count(), "unclosed array".to_string())),
+        (None, false) => None,
+    }
+}
+
+fn bracket_balance(text: &str) -> i32 {
+    text.chars().fold(0, |balance, c| match c {
+        '[' | '{' => balance + 1,
+        ']' | '}' => balance - 1,
+        _ => balance,
+    })
+}
+
+/// `contents` is `None` when `config.toml` doesn't exist.
+pub(crate) fn config_check(
+    path: &Path,
+    contents: Option<Result<String, String>>,
+) -> PreflightCheck {
+    match contents {
+        None => PreflightCheck::pass(
+            "codexConfig",
+            format!("{} not present; codex defaults apply.", path.display()),
+        ),
+        Some(Err(error)) => PreflightCheck::fail(
+            "codexConfig",
+            format!("Unable to read {}: {error}", path.display()),
+            "fix-codex-config",
+        ),
+        Some(Ok(contents)) => match config_syntax_error(&contents) {
+            None => PreflightCheck::pass("codexConfig", path.display().to_string()),
+            Some((line, text)) => PreflightCheck::warn(
+                "codexConfig",
+                format!(
+                    "{} may not parse: line {line} `{text}` doesn't look like TOML.",
+                    path.display()
+                ),
+                "fix-codex-config",
+            ),
+        },
+    }
+}
+
+/// `auth` is `CODEX_HOME/auth.json` if it could be read. An API key in the
+/// daemon's environment counts too.
+pub(crate) fn credentials_check(auth: Option<String>, env_api_key: bool) -> PreflightCheck {
+    let stored = auth
+        .and_then(|data| serde_json::from_str::<Value>(&data).ok())
+        .is_some_and(|auth| {
+            ["OPENAI_API_KEY", "tokens"]
+                .iter()
+                .any(|key| auth.get(key).is_some_and(|value| !value.is_null()))
+        });
+    if stored {
+        PreflightCheck::pass("credentials", "Logged in (auth.json).".to_string())
+    } else if env_api_key {
+        PreflightCheck::pass(
+            "credentials",
+            "OPENAI_API_KEY is set in the daemon's environment.".to_string(),
+        )
+    } else {
+        PreflightCheck::fail(
+            "credentials",
+            "No codex credentials found; run `codex login`.".to_string(),
+            "run-login",
+        )
+    }
+}
+
+/// Writes and removes a scratch file in the data dir.
+pub(crate) fn data_dir_check(data_dir: &Path) -> PreflightCheck {
+    let probe = data_dir.join(format!(".preflight-{}", Uuid::new_v4()));
+    let result = std::fs::create_dir_all(data_dir)
+        .and_then(|()| std::fs::write(&probe, b"ok"))
+        .and_then(|()| std::fs::remove_file(&probe));
+    match result {
+        Ok(()) => PreflightCheck::pass("dataDir", data_dir.display().to_string()),
+        Err(error) => PreflightCheck::fail(
+            "dataDir",
+            format!("{} is not writable: {error}", data_dir.display()),
+            "fix-data-dir-permissions",
+        ),
+    }
+}
+
+/// `git_dir` is the result of `git rev-parse --git-dir` for folders that
+/// look like repos, and `None` for plain folders.
+pub(crate) fn workspace_check(
+    workspace_id: &str,
+    path: &Path,
+    git_dir: Option<Result<(), String>>,
+) -> PreflightCheck {
+    let check = if !path.is_dir() {
+        PreflightCheck::fail(
+            "workspace",
+            format!("{} is missing or not a folder.", path.display()),
+            "remove-workspace",
+        )
+    } else {
+        match git_dir {
+            None => PreflightCheck::pass(
+                "workspace",
+                format!("{} (plain folder, git features off)", path.display()),
+            ),
+            Some(Ok(())) => PreflightCheck::pass("workspace", path.display().to_string()),
+            Some(Err(error)) => PreflightCheck::warn(
+                "workspace",
+                format!("{}: git can't read the repo: {error}", path.display()),
+                "repair-repo",
+            ),
+        }
+    };
+    PreflightCheck {
+        workspace_id: Some(workspace_id.to_string()),
+        ..check
+    }
+}
+
+/// Checks what CodexMonitor needs from this machine: git, codex, CODEX_HOME
+/// and its config, credentials and, when `data_dir` is given, that it is
+/// writable; then the folders of `entries`.
+pub(crate) async fn run_checks(
+    codex_bin: Option<String>,
+    data_dir: Option<&Path>,
+    entries: &[WorkspaceEntry],
+) -> Vec<PreflightCheck> {
+    let git_version = match tokio::time::timeout(
+        Duration::from_secs(5),
+        git_command().arg("--version").output(),
+    )
+    .await
+    {
+        Ok(Ok(output)) if output.status.success() => {
+            Ok(String::from_utf8_lossy(&output.stdout).to_string())
+        }
+        Ok(Ok(output)) => Err(String::from_utf8_lossy(&output.stderr).trim().to_string()),
+        Ok(Err(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+            Err("Git not found. Install git and ensure `git` is on your PATH.".to_string())
+        }
+        Ok(Err(err)) => Err(err.to_string()),
+        Err(_) => Err("Timed out while running `git --version`.".to_string()),
+    };
+    let codex_home = codex_home::resolve_default_codex_home();
+    let mut checks = vec![
+        git_check(git_version),
+        codex_check(check_codex_installation(codex_bin).await),
+        codex_home_check(codex_home.as_deref()),
+    ];
+    if let Some(path) = codex_config::config_toml_path() {
+        let contents = path
+            .exists()
+            .then(|| std::fs::read_to_string(&path).map_err(|err| err.to_string()));
+        checks.push(config_check(&path, contents));
+    }
+    let auth = codex_home
+        .as_ref()
+        .and_then(|home| std::fs::read_to_string(home.join("auth.json")).ok());
+    let env_api_key = std::env::var("OPENAI_API_KEY").is_ok_and(|key| !key.trim().is_empty());
+    checks.push(credentials_check(auth, env_api_key));
+    if let Some(data_dir) = data_dir {
+        checks.push(data_dir_check(data_dir));
+    }
+    for entry in entries {
+        let path = PathBuf::from(&entry.path);
+        let git_path = git_state::workspace_git_path(entry);
+        let git_dir = if path.is_dir() && git_state::is_git_repo(&git_path) {
+            let git_dir = run_git(&git_path, &["rev-parse", "--git-dir"]).await;
+            Some(git_dir.map(|_| ()))
+        } else {
+            None
+        };
+        checks.push(workspace_check(&entry.id, &path, git_dir));
+    }
+    checks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        codex_check, config_check, config_syntax_error, credentials_check, data_dir_check,
+        git_check, workspace_check, CheckStatus, PreflightReport,
+    };
+    use std::path::Path;
+    use uuid::Uuid;
+
+    #[test]
+    fn grades_tools_and_credentials() {
+        assert_eq!(
+            git_check(Ok("git version 2.39.3 (Apple Git-146)\n".to_string())).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(
+            git_check(Ok("git version 2.43.0.windows.1".to_string())).status,
+            CheckStatus::Pass
+        );
+        let old = git_check(Ok("git version 2.11.0".to_string()));
+        assert_eq!(
+            (old.status, old.remediation),
+            (CheckStatus::Warn, Some("update-git"))
+        );
+        let missing = codex_check(Err("Codex CLI not found.".to_string()));
+        assert_eq!(
+            (missing.status, missing.remediation),
+            (CheckStatus::Fail, Some("install-codex"))
+        );
+
+        let logged_in = r#"{ "OPENAI_API_KEY": null, "tokens": { "id_token": "x" } }"#;
+        assert_eq!(
+            credentials_check(Some(logged_in.to_string()), false).status,
+            CheckStatus::Pass
+        );
+        assert_eq!(credentials_check(None, true).status, CheckStatus::Pass);
+        let logged_out = credentials_check(Some(r#"{ "tokens": null }"#.to_string()), false);
+        assert_eq!(logged_out.remediation, Some("run-login"));
+    }
+
+    #[test]
+    fn flags_config_lines_that_are_not_toml() {
+        let valid = "model = \"gpt-5\"\n# comment\n[features]\nsteer = true\n\
+            notify = [\n  \"a\",\n  \"b\",\n]\ninstructions = \"\"\"\nline\n\"\"\"\n";
+        assert_eq!(config_syntax_error(valid), None);
+        assert_eq!(
+            config_syntax_error("model = \"gpt-5\"\nnot toml\n"),
+            Some((2, "not toml".to_string()))
+        );
+        assert_eq!(
+            config_syntax_error("[features\n").map(|(line, _)| line),
+            Some(1)
+        );
+        assert!(config_syntax_error("notify = [\n\"a\",\n").is_some());
+
+        let path = Path::new("/home/me/.codex/config.toml");
+        assert_eq!(config_check(path, None).status, CheckStatus::Pass);
+        assert_eq!(
+            config_check(path, Some(Ok("= 1".to_string()))).status,
+            CheckStatus::Warn
+        );
+    }
+
+    #[test]
+    fn checks_data_dir_and_workspace_folders() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-preflight-{}", Uuid::new_v4()));
+        let data_dir = data_dir_check(&root.join("data"));
+        assert_eq!(data_dir.status, CheckStatus::Pass);
+        assert_eq!(
+            std::fs::read_dir(root.join("data")).expect("read").count(),
+            0
+        );
+        let missing = workspace_check("ws", &root.join("missing"), None);
+        assert_eq!(
+            (missing.status, missing.workspace_id.as_deref()),
+            (CheckStatus::Fail, Some("ws"))
+        );
+        assert_eq!(workspace_check("ws", &root, None).status, CheckStatus::Pass);
+        assert!(!PreflightReport::new(vec![data_dir, missing], 42).ok);
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
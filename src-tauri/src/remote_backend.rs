@@ -8,6 +8,9 @@ use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
 use tokio::net::TcpStream;
 use tokio::sync::{mpsc, oneshot, Mutex};
 
+use crate::backend::compression::{
+    compress_message, decompress_message, MAX_DECOMPRESSED_BYTES, ZSTD_ENCODING,
+};
 use crate::state::AppState;
 use crate::types::BackendMode;
 
@@ -110,9 +113,17 @@ async fn ensure_remote_backend(state: &AppState, app: AppHandle) -> Result<Remot
     let connected = Arc::new(AtomicBool::new(true));
     let connected_for_writer = Arc::clone(&connected);
     let connected_for_reader = Arc::clone(&connected);
+    // Set once the daemon confirms compression at auth.
+    let compress = Arc::new(AtomicBool::new(false));
+    let compress_for_writer = Arc::clone(&compress);
 
     let write_task = tokio::spawn(async move {
         while let Some(message) = out_rx.recv().await {
+            let message = if compress_for_writer.load(Ordering::SeqCst) {
+                compress_message(message)
+            } else {
+                message
+            };
             if writer.write_all(message.as_bytes()).await.is_err()
                 || writer.write_all(b"\n").await.is_err()
             {
@@ -147,10 +158,15 @@ async fn ensure_remote_backend(state: &AppState, app: AppHandle) -> Result<Remot
     };
 
     if let Some(token) = token {
-        client
-            .call("auth", json!({ "token": token }))
-            .await
-            .map(|_| ())?;
+        let auth = client
+            .call(
+                "auth",
+                json!({ "token": token, "compression": ZSTD_ENCODING }),
+            )
+            .await?;
+        if auth.get("compression").and_then(Value::as_str) == Some(ZSTD_ENCODING) {
+            compress.store(true, Ordering::SeqCst);
+        }
     }
 
     {
@@ -181,6 +197,10 @@ async fn read_loop(
             Ok(value) => value,
             Err(_) => continue,
         };
+        let message = match decompress_message(message, MAX_DECOMPRESSED_BYTES) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
 
         if let Some(id) = message.get("id").and_then(|value| value.as_u64()) {
             let sender = pending.lock().await.remove(&id);
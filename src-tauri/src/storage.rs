@@ -67,6 +67,7 @@ mod tests {
             parent_id: None,
             worktree: None,
             settings: settings.clone(),
+            status: None,
         };
 
         write_workspaces(&path, &[entry]).expect("write workspaces");
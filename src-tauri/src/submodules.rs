@@ -0,0 +1,128 @@
+use std::path::Path;
+use std::process::Stdio;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc;
+
+use crate::backend::events::{EventSink, TerminalOutput};
+use crate::backend::git_command::git_command;
+
+/// Terminal id used for the output of `git submodule update`.
+pub(crate) const SUBMODULE_TERMINAL_ID: &str = "submodules";
+
+/// Submodule paths declared in `<repo>/.gitmodules`; empty when there is none.
+pub(crate) fn submodule_paths(repo_path: &Path) -> Vec<String> {
+    std::fs::read_to_string(repo_path.join(".gitmodules"))
+        .map(|content| parse_gitmodules_paths(&content))
+        .unwrap_or_default()
+}
+
+fn parse_gitmodules_paths(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.trim().split_once('=')?;
+            if key.trim() != "path" {
+                return None;
+            }
+            let value = value.trim().trim_matches('"').trim_end_matches('/');
+            (!value.is_empty()).then(|| value.to_string())
+        })
+        .collect()
+}
+
+/// Runs `git submodule update --init --recursive` in `worktree_path`, streaming
+/// git's output as terminal-output events for `workspace_id`.
+pub(crate) async fn init_submodules<E: EventSink>(
+    worktree_path: &Path,
+    workspace_id: &str,
+    event_sink: &E,
+) -> Result<(), String> {
+    let mut child = git_command()
+        .args(["submodule", "update", "--init", "--recursive", "--progress"])
+        .current_dir(worktree_path)
+        .env("GIT_TERMINAL_PROMPT", "0")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<(bool, Vec<u8>)>();
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(forward_pipe(stdout, false, tx.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(forward_pipe(stderr, true, tx.clone()));
+    }
+    drop(tx);
+
+    let mut last_error = String::new();
+    while let Some((is_stderr, chunk)) = rx.recv().await {
+        let data = String::from_utf8_lossy(&chunk).to_string();
+        if is_stderr {
+            last_error = data.clone();
+        }
+        event_sink.emit_terminal_output(TerminalOutput {
+            workspace_id: workspace_id.to_string(),
+            terminal_id: SUBMODULE_TERMINAL_ID.to_string(),
+            data,
+        });
+    }
+
+    let status = child
+        .wait()
+        .await
+        .map_err(|e| format!("Failed to run git: {e}"))?;
+    if status.success() {
+        return Ok(());
+    }
+    let detail = last_error
+        .lines()
+        .rev()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("git submodule update failed.");
+    Err(detail.to_string())
+}
+
+async fn forward_pipe<R: AsyncRead + Unpin + Send + 'static>(
+    mut reader: R,
+    is_stderr: bool,
+    tx: mpsc::UnboundedSender<(bool, Vec<u8>)>,
+) {
+    let mut buffer = [0u8; 4096];
+    loop {
+        match reader.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => {
+                if tx.send((is_stderr, buffer[..read].to_vec())).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_gitmodules_paths;
+
+    #[test]
+    fn parses_paths_from_gitmodules() {
+        let content = r#"
+[submodule "vendor/lib"]
+	path = vendor/lib
+	url = https://example.com/lib.git
+[submodule "docs"]
+	path = "docs/theme/"
+	url = ../theme.git
+	branch = main
+"#;
+        assert_eq!(
+            parse_gitmodules_paths(content),
+            vec!["vendor/lib".to_string(), "docs/theme".to_string()]
+        );
+        assert!(parse_gitmodules_paths("").is_empty());
+    }
+}
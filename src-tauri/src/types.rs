@@ -1,4 +1,7 @@
+use std::path::Path;
+
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct GitFileStatus {
@@ -6,6 +9,9 @@ pub(crate) struct GitFileStatus {
     pub(crate) status: String,
     pub(crate) additions: i64,
     pub(crate) deletions: i64,
+    /// The path is a submodule checkout rather than a regular file.
+    #[serde(default)]
+    pub(crate) submodule: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -175,6 +181,9 @@ pub(crate) struct WorkspaceEntry {
     pub(crate) worktree: Option<WorktreeInfo>,
     #[serde(default)]
     pub(crate) settings: WorkspaceSettings,
+    /// Result of the last consistency check against git; never persisted.
+    #[serde(skip)]
+    pub(crate) status: Option<WorkspaceEntryStatus>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -192,6 +201,157 @@ pub(crate) struct WorkspaceInfo {
     pub(crate) worktree: Option<WorktreeInfo>,
     #[serde(default)]
     pub(crate) settings: WorkspaceSettings,
+    /// `settings.color`, or for worktrees without one the parent's.
+    #[serde(default)]
+    pub(crate) color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) status: Option<WorkspaceEntryStatus>,
+    /// True when the running session was spawned with experimental flags that
+    /// no longer match the app settings and needs a restart to pick them up.
+    #[serde(default, rename = "flagsStale")]
+    pub(crate) flags_stale: bool,
+    /// Merge/rebase/etc. left in progress in the checkout, if any.
+    #[serde(default, rename = "gitOperation")]
+    pub(crate) git_operation: Option<GitOperation>,
+    /// False for plain folders; worktree and git features are unavailable.
+    #[serde(default = "default_is_git_repo", rename = "isGitRepo")]
+    pub(crate) is_git_repo: bool,
+    /// Checked-out branch; `None` when `HEAD` is detached or outside git.
+    #[serde(default)]
+    pub(crate) branch: Option<String>,
+    /// The commit `HEAD` is detached at, if it is.
+    #[serde(default, rename = "detachedAt")]
+    pub(crate) detached_at: Option<String>,
+    /// Background/manual fetch state; only reported by the daemon.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) fetch: Option<WorkspaceFetchStatus>,
+    /// Turns running in the workspace; only reported by the daemon.
+    #[serde(
+        default,
+        rename = "activeTurns",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) active_turns: Option<u32>,
+    /// Messages waiting for a turn slot; only reported by the daemon.
+    #[serde(
+        default,
+        rename = "queuedTurns",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) queued_turns: Option<u32>,
+    /// New turns are refused until `resume_workspace`; only reported by the
+    /// daemon.
+    #[serde(default)]
+    pub(crate) paused: bool,
+    /// What the running session's codex can serve.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) capabilities: Option<CodexCapabilities>,
+    /// Background errors not yet cleared with `clear_workspace_errors`; only
+    /// reported by the daemon.
+    #[serde(
+        default,
+        rename = "errorCount",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub(crate) error_count: Option<u32>,
+}
+
+/// The codex version of a session and the app-server methods it doesn't
+/// serve, so clients can hide what won't work.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CodexCapabilities {
+    /// As `codex --version` printed it, such as `codex-cli 0.46.0`.
+    pub(crate) version: Option<String>,
+    /// From the app-server's `initialize` response; only in
+    /// `workspace_status`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) user_agent: Option<String>,
+    #[serde(default)]
+    pub(crate) unsupported_methods: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct WorkspaceFetchStatus {
+    /// Milliseconds since the epoch of the last successful fetch.
+    pub(crate) last_fetch_at: Option<u64>,
+    pub(crate) last_error: Option<String>,
+    /// Failed attempts since the last success; drives the retry backoff.
+    pub(crate) consecutive_failures: u32,
+}
+
+impl WorkspaceInfo {
+    pub(crate) fn from_entry(entry: WorkspaceEntry, connected: bool) -> Self {
+        let git_operation = crate::git_state::detect_git_operation(Path::new(&entry.path));
+        let git_path = crate::git_state::workspace_git_path(&entry);
+        let is_git_repo = crate::git_state::is_git_repo(&git_path);
+        let (branch, detached_at) = crate::git_state::read_head(&git_path)
+            .map(crate::git_state::HeadState::into_parts)
+            .unwrap_or_default();
+        Self {
+            id: entry.id,
+            name: entry.name,
+            path: entry.path,
+            connected,
+            codex_bin: entry.codex_bin,
+            kind: entry.kind,
+            parent_id: entry.parent_id,
+            worktree: entry.worktree,
+            color: entry.settings.color.clone(),
+            settings: entry.settings,
+            status: entry.status,
+            flags_stale: false,
+            git_operation,
+            is_git_repo,
+            branch,
+            detached_at,
+            fetch: None,
+            active_turns: None,
+            queued_turns: None,
+            paused: false,
+            capabilities: None,
+            error_count: None,
+        }
+    }
+
+    /// Worktrees without a color of their own show their parent's.
+    pub(crate) fn inherit_color(&mut self, parent: Option<&WorkspaceEntry>) {
+        if self.color.is_none() && self.kind.is_worktree() {
+            self.color = parent.and_then(|parent| parent.settings.color.clone());
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum GitOperation {
+    Merge,
+    Rebase,
+    CherryPick,
+    Revert,
+    Bisect,
+}
+
+impl GitOperation {
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Self::Merge => "merge",
+            Self::Rebase => "rebase",
+            Self::CherryPick => "cherry-pick",
+            Self::Revert => "revert",
+            Self::Bisect => "bisect",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum WorkspaceEntryStatus {
+    Ok,
+    PathMissing,
+    NotRegisteredWithGit,
+    BranchMismatch,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -216,6 +376,9 @@ impl WorkspaceKind {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub(crate) struct WorktreeInfo {
     pub(crate) branch: String,
+    /// Folder name chosen under the worktrees dir; absent for older entries.
+    #[serde(default, rename = "dirName", skip_serializing_if = "Option::is_none")]
+    pub(crate) dir_name: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -232,12 +395,80 @@ pub(crate) struct WorkspaceGroup {
 pub(crate) struct WorkspaceSettings {
     #[serde(default, rename = "sidebarCollapsed")]
     pub(crate) sidebar_collapsed: bool,
+    /// Listed before unpinned workspaces, whatever their `sortOrder`.
+    #[serde(default)]
+    pub(crate) pinned: bool,
     #[serde(default, rename = "sortOrder")]
     pub(crate) sort_order: Option<u32>,
     #[serde(default, rename = "groupId")]
     pub(crate) group_id: Option<String>,
     #[serde(default, rename = "gitRoot")]
     pub(crate) git_root: Option<String>,
+    /// Worktrees only: also let the agent write to the parent repo.
+    #[serde(default, rename = "parentWritable")]
+    pub(crate) parent_writable: bool,
+    /// Run `git submodule update --init` in new worktrees of this repo.
+    #[serde(default, rename = "initSubmodules")]
+    pub(crate) init_submodules: bool,
+    /// Turns allowed to run at once across this workspace's threads; further
+    /// messages are rejected or queued. `None` means no limit.
+    #[serde(default, rename = "maxConcurrentTurns")]
+    pub(crate) max_concurrent_turns: Option<u32>,
+    /// Access mode for messages that don't send one; `None` means `current`.
+    #[serde(default, rename = "defaultAccessMode")]
+    pub(crate) default_access_mode: Option<String>,
+    /// Approval policy for this workspace's threads and turns, instead of
+    /// the one the access mode implies.
+    #[serde(default, rename = "approvalPolicy")]
+    pub(crate) approval_policy: Option<String>,
+    /// Overrides the app-wide `idleDisconnectMinutes`; `0` keeps this
+    /// workspace connected.
+    #[serde(default, rename = "idleDisconnectMinutes")]
+    pub(crate) idle_disconnect_minutes: Option<u32>,
+    /// Don't keep the input of this workspace's turns for `turn_inputs`.
+    #[serde(default, rename = "disableTurnInputs")]
+    pub(crate) disable_turn_inputs: bool,
+    /// Hex color clients show the workspace in, such as `#3b82f6`.
+    /// Worktrees without one use their parent's.
+    #[serde(default)]
+    pub(crate) color: Option<String>,
+    /// An emoji or a named icon token for clients to show.
+    #[serde(default)]
+    pub(crate) icon: Option<String>,
+    /// Save command outputs longer than `artifact_threshold_bytes` as files
+    /// instead of forwarding them inline.
+    #[serde(default, rename = "captureArtifacts")]
+    pub(crate) capture_artifacts: bool,
+    /// `None` means 64 KiB.
+    #[serde(default, rename = "artifactThresholdBytes")]
+    pub(crate) artifact_threshold_bytes: Option<u64>,
+}
+
+impl WorkspaceSettings {
+    /// Applies a partial update: keys in `patch` replace the current values,
+    /// an explicit `null` resets that field to its default, and absent keys
+    /// are left alone. Unknown keys are rejected so typos don't pass silently.
+    pub(crate) fn with_patch(&self, patch: &Value) -> Result<Self, String> {
+        let Value::Object(patch) = patch else {
+            return Err("Workspace settings patch must be a JSON object.".to_string());
+        };
+        let mut merged = match serde_json::to_value(self).map_err(|e| e.to_string())? {
+            Value::Object(map) => map,
+            _ => return Err("Workspace settings did not serialize to an object.".to_string()),
+        };
+        for (key, value) in patch {
+            if !merged.contains_key(key) {
+                return Err(format!("Unknown workspace setting: {key}"));
+            }
+            if value.is_null() {
+                merged.remove(key);
+            } else {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+        serde_json::from_value(Value::Object(merged))
+            .map_err(|err| format!("Invalid workspace settings patch: {err}"))
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -395,6 +626,49 @@ pub(crate) struct AppSettings {
     pub(crate) composer_code_block_copy_use_modifier: bool,
     #[serde(default = "default_workspace_groups", rename = "workspaceGroups")]
     pub(crate) workspace_groups: Vec<WorkspaceGroup>,
+    #[serde(
+        default = "default_worktree_gc_on_startup",
+        rename = "worktreeGcOnStartup"
+    )]
+    pub(crate) worktree_gc_on_startup: bool,
+    /// Where `clone_workspace` puts repos when no destination is given.
+    #[serde(default, rename = "projectsRoot")]
+    pub(crate) projects_root: Option<String>,
+    /// Seconds between background `git fetch --prune` runs in main workspaces;
+    /// `None` disables background fetching.
+    #[serde(default, rename = "backgroundFetchIntervalSecs")]
+    pub(crate) background_fetch_interval_secs: Option<u64>,
+    /// Parent folders `discover_workspaces` scans for repos; empty means the
+    /// projects root.
+    #[serde(default, rename = "discoveryRoots")]
+    pub(crate) discovery_roots: Vec<String>,
+    /// Let the daemon name untitled threads with a short background turn
+    /// after their first turn completes.
+    #[serde(default, rename = "autoThreadTitles")]
+    pub(crate) auto_thread_titles: bool,
+    /// Minutes without requests, turns or events after which the daemon
+    /// stops a workspace's codex session; `None` keeps sessions running.
+    #[serde(default, rename = "idleDisconnectMinutes")]
+    pub(crate) idle_disconnect_minutes: Option<u32>,
+    /// Reconnect a session the daemon stopped for idleness when a request
+    /// needs it, instead of failing with "workspace not connected".
+    #[serde(default = "default_auto_reconnect", rename = "autoReconnect")]
+    pub(crate) auto_reconnect: bool,
+    /// Move removed worktrees into the daemon's trash instead of deleting
+    /// them, as if every removal passed `softDelete: true`.
+    #[serde(default, rename = "softDelete")]
+    pub(crate) soft_delete: bool,
+    /// Days trashed worktrees are kept before they are purged; `None` keeps
+    /// them a week and 0 until the trash is emptied.
+    #[serde(default, rename = "trashRetentionDays")]
+    pub(crate) trash_retention_days: Option<u32>,
+    /// How `add_worktree` and `rename_worktree` name worktree folders.
+    #[serde(default, rename = "worktreeNaming")]
+    pub(crate) worktree_naming: WorktreeNaming,
+    /// Days saved command outputs are kept; `None` keeps them a week and 0
+    /// until their workspace is removed.
+    #[serde(default, rename = "artifactRetentionDays")]
+    pub(crate) artifact_retention_days: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -410,6 +684,22 @@ impl Default for BackendMode {
     }
 }
 
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum WorktreeNaming {
+    /// The sanitized branch name, e.g. `feature-x`.
+    #[default]
+    Branch,
+    /// Only the branch hash, e.g. `1f0b6e2c`, for short paths.
+    BranchHash,
+    /// The UTC creation time, then the branch, e.g. `20261016-142501-feature-x`.
+    Timestamp,
+}
+
+fn default_auto_reconnect() -> bool {
+    true
+}
+
 fn default_access_mode() -> String {
     "current".to_string()
 }
@@ -495,6 +785,10 @@ fn default_cycle_workspace_prev_shortcut() -> Option<String> {
     Some("cmd+shift+up".to_string())
 }
 
+fn default_is_git_repo() -> bool {
+    true
+}
+
 fn default_notification_sounds_enabled() -> bool {
     true
 }
@@ -567,6 +861,32 @@ fn default_workspace_groups() -> Vec<WorkspaceGroup> {
     Vec::new()
 }
 
+fn default_worktree_gc_on_startup() -> bool {
+    false
+}
+
+/// Experimental feature flags written to config.toml; codex only reads them
+/// when a session starts.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct ExperimentalFlags {
+    pub(crate) collab: bool,
+    pub(crate) collaboration_modes: bool,
+    pub(crate) steer: bool,
+    pub(crate) unified_exec: bool,
+}
+
+impl AppSettings {
+    pub(crate) fn experimental_flags(&self) -> ExperimentalFlags {
+        ExperimentalFlags {
+            collab: self.experimental_collab_enabled,
+            collaboration_modes: self.experimental_collaboration_modes_enabled,
+            steer: self.experimental_steer_enabled,
+            unified_exec: self.experimental_unified_exec_enabled,
+        }
+    }
+}
+
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
@@ -615,6 +935,17 @@ impl Default for AppSettings {
             composer_list_continuation: default_composer_list_continuation(),
             composer_code_block_copy_use_modifier: default_composer_code_block_copy_use_modifier(),
             workspace_groups: default_workspace_groups(),
+            worktree_gc_on_startup: default_worktree_gc_on_startup(),
+            projects_root: None,
+            background_fetch_interval_secs: None,
+            discovery_roots: Vec::new(),
+            auto_thread_titles: false,
+            idle_disconnect_minutes: None,
+            auto_reconnect: default_auto_reconnect(),
+            soft_delete: false,
+            trash_retention_days: None,
+            worktree_naming: WorktreeNaming::default(),
+            artifact_retention_days: None,
         }
     }
 }
@@ -622,8 +953,10 @@ impl Default for AppSettings {
 #[cfg(test)]
 mod tests {
     use super::{
-        AppSettings, BackendMode, WorkspaceEntry, WorkspaceGroup, WorkspaceKind, WorkspaceSettings,
+        AppSettings, BackendMode, WorkspaceEntry, WorkspaceGroup, WorkspaceInfo, WorkspaceKind,
+        WorkspaceSettings, WorktreeNaming,
     };
+    use serde_json::json;
 
     #[test]
     fn app_settings_defaults_from_empty_json() {
@@ -692,6 +1025,25 @@ mod tests {
         assert!(!settings.composer_list_continuation);
         assert!(!settings.composer_code_block_copy_use_modifier);
         assert!(settings.workspace_groups.is_empty());
+        assert!(!settings.worktree_gc_on_startup);
+        assert!(settings.projects_root.is_none());
+        assert!(settings.background_fetch_interval_secs.is_none());
+        assert!(settings.discovery_roots.is_empty());
+        assert!(!settings.auto_thread_titles);
+        assert!(settings.idle_disconnect_minutes.is_none());
+        assert!(settings.auto_reconnect);
+        assert!(!settings.soft_delete);
+        assert!(settings.trash_retention_days.is_none());
+        assert_eq!(settings.worktree_naming, WorktreeNaming::Branch);
+        assert!(settings.artifact_retention_days.is_none());
+    }
+
+    #[test]
+    fn worktree_naming_uses_kebab_case() {
+        let settings: AppSettings = serde_json::from_str(r#"{"worktreeNaming":"branch-hash"}"#)
+            .expect("settings deserialize");
+        assert_eq!(settings.worktree_naming, WorktreeNaming::BranchHash);
+        assert!(serde_json::from_str::<AppSettings>(r#"{"worktreeNaming":"random"}"#).is_err());
     }
 
     #[test]
@@ -732,6 +1084,8 @@ mod tests {
         assert!(entry.worktree.is_none());
         assert!(entry.settings.sort_order.is_none());
         assert!(entry.settings.group_id.is_none());
+        assert!(!entry.settings.pinned);
+        assert!(entry.status.is_none());
     }
 
     #[test]
@@ -741,5 +1095,73 @@ mod tests {
         assert!(settings.sort_order.is_none());
         assert!(settings.group_id.is_none());
         assert!(settings.git_root.is_none());
+        assert!(settings.max_concurrent_turns.is_none());
+        assert!(settings.default_access_mode.is_none());
+        assert!(settings.idle_disconnect_minutes.is_none());
+        assert!(settings.color.is_none());
+        assert!(settings.icon.is_none());
+        assert!(!settings.pinned);
+    }
+
+    #[test]
+    fn worktrees_inherit_the_parent_color() {
+        let entry = |id: &str, kind: WorkspaceKind, color: Option<&str>| WorkspaceEntry {
+            id: id.to_string(),
+            name: id.to_string(),
+            path: "/tmp".to_string(),
+            codex_bin: None,
+            kind,
+            parent_id: None,
+            worktree: None,
+            settings: WorkspaceSettings {
+                color: color.map(str::to_string),
+                ..WorkspaceSettings::default()
+            },
+            status: None,
+        };
+        let parent = entry("parent", WorkspaceKind::Main, Some("#ff0000"));
+
+        let mut worktree =
+            WorkspaceInfo::from_entry(entry("wt", WorkspaceKind::Worktree, None), false);
+        worktree.inherit_color(Some(&parent));
+        assert_eq!(worktree.color.as_deref(), Some("#ff0000"));
+
+        let mut own =
+            WorkspaceInfo::from_entry(entry("wt", WorkspaceKind::Worktree, Some("#00ff00")), false);
+        own.inherit_color(Some(&parent));
+        assert_eq!(own.color.as_deref(), Some("#00ff00"));
+
+        let mut main = WorkspaceInfo::from_entry(entry("main", WorkspaceKind::Main, None), false);
+        main.inherit_color(Some(&parent));
+        assert!(main.color.is_none());
+    }
+
+    #[test]
+    fn workspace_settings_patch_only_changes_given_keys() {
+        let settings = WorkspaceSettings {
+            sort_order: Some(2),
+            group_id: Some("group-1".to_string()),
+            ..WorkspaceSettings::default()
+        };
+
+        let patched = settings
+            .with_patch(&json!({ "sidebarCollapsed": true }))
+            .expect("patch");
+        assert!(patched.sidebar_collapsed);
+        assert_eq!(patched.sort_order, Some(2));
+        assert_eq!(patched.group_id.as_deref(), Some("group-1"));
+
+        let cleared = patched
+            .with_patch(&json!({ "groupId": null, "sidebarCollapsed": null }))
+            .expect("patch");
+        assert!(cleared.group_id.is_none());
+        assert!(!cleared.sidebar_collapsed);
+        assert_eq!(cleared.sort_order, Some(2));
+
+        let wrong_type = json!({ "sortOrder": "first" });
+        assert!(settings.with_patch(&wrong_type).is_err());
+        let unknown_key = json!({ "sidebar_collapsed": true });
+        assert!(settings.with_patch(&unknown_key).is_err());
+        assert!(settings.with_patch(&json!([])).is_err());
     }
 }
@@ -1,13 +1,112 @@
+use std::path::{Path, PathBuf};
+
 pub(crate) fn normalize_git_path(path: &str) -> String {
     path.replace('\\', "/")
 }
 
+/// Strips Windows verbatim prefixes (`\\?\`, `\\?\UNC\`) and lowercases the
+/// drive letter so canonicalized paths compare consistently.
+#[cfg(any(windows, test))]
+fn normalize_windows_path_str(path: &str) -> String {
+    let stripped = if let Some(rest) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{rest}")
+    } else if let Some(rest) = path.strip_prefix(r"\\?\") {
+        rest.to_string()
+    } else {
+        path.to_string()
+    };
+    let mut chars = stripped.chars();
+    match (chars.next(), chars.next()) {
+        (Some(drive), Some(':')) if drive.is_ascii_alphabetic() => {
+            format!("{}{}", drive.to_ascii_lowercase(), &stripped[1..])
+        }
+        _ => stripped,
+    }
+}
+
+pub(crate) fn normalize_canonical_path(path: &Path) -> PathBuf {
+    #[cfg(windows)]
+    {
+        PathBuf::from(normalize_windows_path_str(&path.to_string_lossy()))
+    }
+    #[cfg(not(windows))]
+    {
+        path.to_path_buf()
+    }
+}
+
+/// Containment check for canonicalized paths that tolerates platform-specific
+/// spellings of the same root.
+pub(crate) fn is_path_within(root: &Path, candidate: &Path) -> bool {
+    normalize_canonical_path(candidate).starts_with(normalize_canonical_path(root))
+}
+
 #[cfg(test)]
 mod tests {
-    use super::normalize_git_path;
+    use super::{is_path_within, normalize_git_path, normalize_windows_path_str};
+    use std::path::Path;
 
     #[test]
     fn normalize_git_path_replaces_backslashes() {
         assert_eq!(normalize_git_path("foo\\bar\\baz"), "foo/bar/baz");
     }
+
+    #[test]
+    fn normalize_windows_path_str_strips_verbatim_prefixes() {
+        assert_eq!(
+            normalize_windows_path_str(r"\\?\C:\Users\dev\repo"),
+            r"c:\Users\dev\repo"
+        );
+        assert_eq!(
+            normalize_windows_path_str(r"\\?\UNC\server\share\repo"),
+            r"\\server\share\repo"
+        );
+    }
+
+    #[test]
+    fn normalize_windows_path_str_folds_drive_letter_only() {
+        assert_eq!(
+            normalize_windows_path_str(r"D:\Repo\File.txt"),
+            r"d:\Repo\File.txt"
+        );
+        assert_eq!(normalize_windows_path_str("/tmp/Repo"), "/tmp/Repo");
+    }
+
+    #[test]
+    fn is_path_within_accepts_nested_paths() {
+        assert!(is_path_within(
+            Path::new("/tmp/repo"),
+            Path::new("/tmp/repo/src/main.rs")
+        ));
+        assert!(!is_path_within(
+            Path::new("/tmp/repo"),
+            Path::new("/tmp/repo-other/a.txt")
+        ));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn is_path_within_ignores_verbatim_prefix_and_drive_case() {
+        assert!(is_path_within(
+            Path::new(r"\\?\C:\Users\dev\repo"),
+            Path::new(r"c:\Users\dev\repo\src\main.rs"),
+        ));
+        assert!(is_path_within(
+            Path::new(r"C:\Users\dev\repo"),
+            Path::new(r"\\?\c:\Users\dev\repo\README.md"),
+        ));
+        assert!(!is_path_within(
+            Path::new(r"\\?\C:\Users\dev\repo"),
+            Path::new(r"\\?\C:\Users\dev\other\README.md"),
+        ));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn is_path_within_handles_unc_roots() {
+        assert!(is_path_within(
+            Path::new(r"\\?\UNC\server\share\repo"),
+            Path::new(r"\\server\share\repo\file.txt"),
+        ));
+    }
 }
@@ -1,27 +1,37 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 
 use ignore::WalkBuilder;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use serde_json::{json, Value};
 use tauri::{AppHandle, Manager, State};
 use tokio::io::AsyncWriteExt;
-use tokio::process::Command;
 use uuid::Uuid;
 
+use crate::backend::events::{EventSink, TerminalOutput};
+use crate::backend::git_command::{classify_git_error, git_command, run_git, GitErrorKind};
+use crate::backend::workspace_settings::{parse_workspace_settings, validate_settings_change};
 use crate::codex::spawn_workspace_session;
 use crate::codex_home::resolve_workspace_codex_home;
 use crate::remote_backend;
 use crate::state::AppState;
+use crate::git_state;
 use crate::git_utils::resolve_git_root;
+use crate::event_sink::TauriEventSink;
 use crate::storage::write_workspaces;
+use crate::submodules::{self, submodule_paths, SUBMODULE_TERMINAL_ID};
 use crate::types::{
     WorkspaceEntry, WorkspaceInfo, WorkspaceKind, WorkspaceSettings, WorktreeInfo,
 };
-use crate::utils::normalize_git_path;
+use crate::utils::{is_path_within, normalize_git_path};
+use crate::worktree_move::{move_destination, relocate_worktree};
+use crate::worktree_paths::{
+    create_dir_all_tracked, first_free_name, validate_worktree_path, worktree_dir_name,
+    worktree_path_for_branch, worktree_path_for_rename,
+};
 
 fn should_skip_dir(name: &str) -> bool {
     matches!(
@@ -30,23 +40,6 @@ fn should_skip_dir(name: &str) -> bool {
     )
 }
 
-fn sanitize_worktree_name(branch: &str) -> String {
-    let mut result = String::new();
-    for ch in branch.chars() {
-        if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.') {
-            result.push(ch);
-        } else {
-            result.push('-');
-        }
-    }
-    let trimmed = result.trim_matches('-').to_string();
-    if trimmed.is_empty() {
-        "worktree".to_string()
-    } else {
-        trimmed
-    }
-}
-
 fn sanitize_clone_dir_name(name: &str) -> String {
     let mut result = String::new();
     for ch in name.chars() {
@@ -127,7 +120,7 @@ fn read_workspace_file_inner(
     let canonical_path = candidate
         .canonicalize()
         .map_err(|err| format!("Failed to open file: {err}"))?;
-    if !canonical_path.starts_with(&canonical_root) {
+    if !is_path_within(&canonical_root, &canonical_path) {
         return Err("Invalid file path".to_string());
     }
     let metadata = std::fs::metadata(&canonical_path)
@@ -183,8 +176,10 @@ fn sort_workspaces(list: &mut Vec<WorkspaceInfo>) {
     list.sort_by(|a, b| {
         let a_order = a.settings.sort_order.unwrap_or(u32::MAX);
         let b_order = b.settings.sort_order.unwrap_or(u32::MAX);
-        a_order
-            .cmp(&b_order)
+        b.settings
+            .pinned
+            .cmp(&a.settings.pinned)
+            .then_with(|| a_order.cmp(&b_order))
             .then_with(|| a.name.cmp(&b.name))
             .then_with(|| a.id.cmp(&b.id))
     });
@@ -197,7 +192,8 @@ fn apply_workspace_settings_update(
 ) -> Result<WorkspaceEntry, String> {
     match workspaces.get_mut(id) {
         Some(entry) => {
-            entry.settings = settings.clone();
+            validate_settings_change(&entry.settings, &settings, &entry.path)?;
+            entry.settings = settings;
             Ok(entry.clone())
         }
         None => Err("workspace not found".to_string()),
@@ -205,61 +201,20 @@ fn apply_workspace_settings_update(
 }
 
 async fn run_git_command(repo_path: &PathBuf, args: &[&str]) -> Result<String, String> {
-    let output = Command::new("git")
-        .args(args)
-        .current_dir(repo_path)
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run git: {e}"))?;
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let detail = if stderr.trim().is_empty() {
-            stdout.trim()
-        } else {
-            stderr.trim()
-        };
-        if detail.is_empty() {
-            Err("Git command failed.".to_string())
-        } else {
-            Err(detail.to_string())
-        }
-    }
+    let stdout = run_git(repo_path, args).await?;
+    Ok(String::from_utf8_lossy(&stdout).trim().to_string())
 }
 
 fn is_missing_worktree_error(error: &str) -> bool {
-    error.contains("is not a working tree")
+    classify_git_error(error) == Some(GitErrorKind::NotAWorkingTree)
 }
 
 async fn run_git_command_bytes(repo_path: &PathBuf, args: &[&str]) -> Result<Vec<u8>, String> {
-    let output = Command::new("git")
-        .args(args)
-        .current_dir(repo_path)
-        .output()
-        .await
-        .map_err(|e| format!("Failed to run git: {e}"))?;
-    if output.status.success() {
-        Ok(output.stdout)
-    } else {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let detail = if stderr.trim().is_empty() {
-            stdout.trim()
-        } else {
-            stderr.trim()
-        };
-        if detail.is_empty() {
-            Err("Git command failed.".to_string())
-        } else {
-            Err(detail.to_string())
-        }
-    }
+    run_git(repo_path, args).await
 }
 
 async fn run_git_diff(repo_path: &PathBuf, args: &[&str]) -> Result<Vec<u8>, String> {
-    let output = Command::new("git")
+    let output = git_command()
         .args(args)
         .current_dir(repo_path)
         .output()
@@ -284,7 +239,7 @@ async fn run_git_diff(repo_path: &PathBuf, args: &[&str]) -> Result<Vec<u8>, Str
 }
 
 async fn git_branch_exists(repo_path: &PathBuf, branch: &str) -> Result<bool, String> {
-    let status = Command::new("git")
+    let status = git_command()
         .args(["show-ref", "--verify", &format!("refs/heads/{branch}")])
         .current_dir(repo_path)
         .status()
@@ -294,7 +249,7 @@ async fn git_branch_exists(repo_path: &PathBuf, branch: &str) -> Result<bool, St
 }
 
 async fn git_remote_exists(repo_path: &PathBuf, remote: &str) -> Result<bool, String> {
-    let status = Command::new("git")
+    let status = git_command()
         .args(["remote", "get-url", remote])
         .current_dir(repo_path)
         .status()
@@ -308,7 +263,7 @@ async fn git_remote_branch_exists(
     remote: &str,
     branch: &str,
 ) -> Result<bool, String> {
-    let output = Command::new("git")
+    let output = git_command()
         .args([
             "ls-remote",
             "--heads",
@@ -374,30 +329,45 @@ async fn unique_branch_name(
     desired: &str,
     remote: Option<&str>,
 ) -> Result<(String, bool), String> {
-    let mut candidate = desired.to_string();
     if desired.is_empty() {
-        return Ok((candidate, false));
+        return Ok((desired.to_string(), false));
     }
-    if !git_branch_exists(repo_path, &candidate).await?
-        && match remote {
-            Some(remote) => !git_remote_branch_exists(repo_path, remote, &candidate).await?,
-            None => true,
-        }
-    {
-        return Ok((candidate, false));
-    }
-    for index in 2..1000 {
-        candidate = format!("{desired}-{index}");
-        let local_exists = git_branch_exists(repo_path, &candidate).await?;
-        let remote_exists = match remote {
-            Some(remote) => git_remote_branch_exists(repo_path, remote, &candidate).await?,
-            None => false,
-        };
-        if !local_exists && !remote_exists {
-            return Ok((candidate, true));
-        }
+    let mut taken = git_list_branches(repo_path).await?;
+    if let Some(remote) = remote {
+        taken.extend(git_list_remote_heads(repo_path, remote).await?);
     }
-    Err("Unable to find an available branch name.".to_string())
+    let candidate = first_free_name(desired, |name| taken.contains(name));
+    let renamed = candidate != desired;
+    Ok((candidate, renamed))
+}
+
+/// Local branch names, listed once so picking a free name needs no git call
+/// per candidate.
+async fn git_list_branches(repo_path: &PathBuf) -> Result<HashSet<String>, String> {
+    let output = run_git_command(
+        repo_path,
+        &["for-each-ref", "--format=%(refname)", "refs/heads"],
+    )
+    .await?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("refs/heads/"))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Branch names on `remote` as `git ls-remote --heads` reports them.
+async fn git_list_remote_heads(
+    repo_path: &PathBuf,
+    remote: &str,
+) -> Result<HashSet<String>, String> {
+    let output = run_git_command(repo_path, &["ls-remote", "--heads", remote]).await?;
+    Ok(output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(_, reference)| reference.trim().strip_prefix("refs/heads/"))
+        .map(str::to_string)
+        .collect())
 }
 
 async fn git_get_origin_url(repo_path: &PathBuf) -> Option<String> {
@@ -407,48 +377,15 @@ async fn git_get_origin_url(repo_path: &PathBuf) -> Option<String> {
     }
 }
 
-fn unique_worktree_path(base_dir: &PathBuf, name: &str) -> PathBuf {
-    let mut candidate = base_dir.join(name);
-    if !candidate.exists() {
-        return candidate;
-    }
-    for index in 2..1000 {
-        let next = base_dir.join(format!("{name}-{index}"));
-        if !next.exists() {
-            candidate = next;
-            break;
-        }
-    }
-    candidate
-}
-
-fn unique_worktree_path_for_rename(
-    base_dir: &PathBuf,
-    name: &str,
-    current_path: &PathBuf,
-) -> Result<PathBuf, String> {
-    let candidate = base_dir.join(name);
-    if candidate == *current_path {
-        return Ok(candidate);
-    }
-    if !candidate.exists() {
-        return Ok(candidate);
-    }
-    for index in 2..1000 {
-        let next = base_dir.join(format!("{name}-{index}"));
-        if next == *current_path || !next.exists() {
-            return Ok(next);
-        }
-    }
-    Err(format!(
-        "Failed to find an available worktree path under {}.",
-        base_dir.display()
-    ))
+fn unique_child_path(base_dir: &PathBuf, name: &str) -> PathBuf {
+    base_dir.join(first_free_name(name, |candidate| {
+        base_dir.join(candidate).exists()
+    }))
 }
 
 fn build_clone_destination_path(copies_folder: &PathBuf, copy_name: &str) -> PathBuf {
     let safe_name = sanitize_clone_dir_name(copy_name);
-    unique_worktree_path(copies_folder, &safe_name)
+    unique_child_path(copies_folder, &safe_name)
 }
 
 fn null_device_path() -> &'static str {
@@ -469,21 +406,18 @@ pub(crate) async fn list_workspaces(
         return serde_json::from_value(response).map_err(|err| err.to_string());
     }
 
+    let current_flags = state.app_settings.lock().await.experimental_flags();
     let workspaces = state.workspaces.lock().await;
     let sessions = state.sessions.lock().await;
     let mut result = Vec::new();
     for entry in workspaces.values() {
-        result.push(WorkspaceInfo {
-            id: entry.id.clone(),
-            name: entry.name.clone(),
-            path: entry.path.clone(),
-            codex_bin: entry.codex_bin.clone(),
-            connected: sessions.contains_key(&entry.id),
-            kind: entry.kind.clone(),
-            parent_id: entry.parent_id.clone(),
-            worktree: entry.worktree.clone(),
-            settings: entry.settings.clone(),
-        });
+        let session = sessions.get(&entry.id);
+        let mut info = WorkspaceInfo::from_entry(entry.clone(), session.is_some());
+        info.flags_stale =
+            session.is_some_and(|session| session.experimental_flags != current_flags);
+        info.inherit_color(entry.parent_id.as_deref().and_then(|id| workspaces.get(id)));
+        info.capabilities = session.map(|session| session.capabilities().summary());
+        result.push(info);
     }
     sort_workspaces(&mut result);
     Ok(result)
@@ -520,7 +454,7 @@ pub(crate) async fn add_workspace(
             &*state,
             app,
             "add_workspace",
-            json!({ "path": path, "codex_bin": codex_bin }),
+            json!({ "path": path, "codexBin": codex_bin }),
         )
         .await?;
         return serde_json::from_value(response).map_err(|err| err.to_string());
@@ -529,6 +463,7 @@ pub(crate) async fn add_workspace(
     if !PathBuf::from(&path).is_dir() {
         return Err("Workspace path must be a folder.".to_string());
     }
+    git_state::ensure_not_bare_repo(Path::new(&path))?;
 
     let name = PathBuf::from(&path)
         .file_name()
@@ -544,6 +479,7 @@ pub(crate) async fn add_workspace(
         parent_id: None,
         worktree: None,
         settings: WorkspaceSettings::default(),
+        status: None,
     };
 
     let default_bin = {
@@ -574,17 +510,7 @@ pub(crate) async fn add_workspace(
         .await
         .insert(entry.id.clone(), session);
 
-    Ok(WorkspaceInfo {
-        id: entry.id,
-        name: entry.name,
-        path: entry.path,
-        codex_bin: entry.codex_bin,
-        connected: true,
-        kind: entry.kind,
-        parent_id: entry.parent_id,
-        worktree: entry.worktree,
-        settings: entry.settings,
-    })
+    Ok(WorkspaceInfo::from_entry(entry, true))
 }
 
 #[tauri::command]
@@ -662,6 +588,7 @@ pub(crate) async fn add_clone(
             group_id: inherited_group_id,
             ..WorkspaceSettings::default()
         },
+        status: None,
     };
 
     let default_bin = {
@@ -699,23 +626,14 @@ pub(crate) async fn add_clone(
         .await
         .insert(entry.id.clone(), session);
 
-    Ok(WorkspaceInfo {
-        id: entry.id,
-        name: entry.name,
-        path: entry.path,
-        codex_bin: entry.codex_bin,
-        connected: true,
-        kind: entry.kind,
-        parent_id: entry.parent_id,
-        worktree: entry.worktree,
-        settings: entry.settings,
-    })
+    Ok(WorkspaceInfo::from_entry(entry, true))
 }
 
 #[tauri::command]
 pub(crate) async fn add_worktree(
     parent_id: String,
     branch: String,
+    init_submodules: Option<bool>,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<WorkspaceInfo, String> {
@@ -735,6 +653,7 @@ pub(crate) async fn add_worktree(
     if parent_entry.kind.is_worktree() {
         return Err("Cannot create a worktree from another worktree.".to_string());
     }
+    git_state::ensure_git_repo(&git_state::workspace_git_path(&parent_entry))?;
 
     let worktree_root = app
         .path()
@@ -742,30 +661,49 @@ pub(crate) async fn add_worktree(
         .map_err(|e| format!("Failed to resolve app data dir: {e}"))?
         .join("worktrees")
         .join(&parent_entry.id);
-    std::fs::create_dir_all(&worktree_root)
-        .map_err(|e| format!("Failed to create worktree directory: {e}"))?;
-
-    let safe_name = sanitize_worktree_name(branch);
-    let worktree_path = unique_worktree_path(&worktree_root, &safe_name);
+    let naming = state.app_settings.lock().await.worktree_naming;
+    let worktree_path = worktree_path_for_branch(&worktree_root, branch, naming)?;
     let worktree_path_string = worktree_path.to_string_lossy().to_string();
+    let created_dirs = create_dir_all_tracked(&worktree_root)?;
+
+    let add_result = async {
+        let branch_exists = git_branch_exists(&PathBuf::from(&parent_entry.path), branch).await?;
+        if branch_exists {
+            run_git_command(
+                &PathBuf::from(&parent_entry.path),
+                &["worktree", "add", &worktree_path_string, branch],
+            )
+            .await
+        } else {
+            run_git_command(
+                &PathBuf::from(&parent_entry.path),
+                &["worktree", "add", "-b", branch, &worktree_path_string],
+            )
+            .await
+        }
+    }
+    .await;
+    if let Err(error) = add_result {
+        let _ = std::fs::remove_dir(&worktree_path);
+        created_dirs.remove_empty();
+        return Err(error);
+    }
 
-    let branch_exists = git_branch_exists(&PathBuf::from(&parent_entry.path), branch).await?;
-    if branch_exists {
-        run_git_command(
-            &PathBuf::from(&parent_entry.path),
-            &["worktree", "add", &worktree_path_string, branch],
-        )
-        .await?;
-    } else {
-        run_git_command(
-            &PathBuf::from(&parent_entry.path),
-            &["worktree", "add", "-b", branch, &worktree_path_string],
-        )
-        .await?;
+    let id = Uuid::new_v4().to_string();
+    let should_init = init_submodules.unwrap_or(parent_entry.settings.init_submodules);
+    if should_init && !submodule_paths(&worktree_path).is_empty() {
+        let event_sink = TauriEventSink::new(app.clone());
+        if let Err(error) = submodules::init_submodules(&worktree_path, &id, &event_sink).await {
+            event_sink.emit_terminal_output(TerminalOutput {
+                workspace_id: id.clone(),
+                terminal_id: SUBMODULE_TERMINAL_ID.to_string(),
+                data: format!("\r\nSubmodule init failed: {error}\r\n"),
+            });
+        }
     }
 
     let entry = WorkspaceEntry {
-        id: Uuid::new_v4().to_string(),
+        id,
         name: branch.to_string(),
         path: worktree_path_string,
         codex_bin: parent_entry.codex_bin.clone(),
@@ -773,8 +711,10 @@ pub(crate) async fn add_worktree(
         parent_id: Some(parent_entry.id.clone()),
         worktree: Some(WorktreeInfo {
             branch: branch.to_string(),
+            dir_name: worktree_dir_name(&worktree_path),
         }),
         settings: WorkspaceSettings::default(),
+        status: None,
     };
 
     let default_bin = {
@@ -795,17 +735,7 @@ pub(crate) async fn add_worktree(
         .await
         .insert(entry.id.clone(), session);
 
-    Ok(WorkspaceInfo {
-        id: entry.id,
-        name: entry.name,
-        path: entry.path,
-        codex_bin: entry.codex_bin,
-        connected: true,
-        kind: entry.kind,
-        parent_id: entry.parent_id,
-        worktree: entry.worktree,
-        settings: entry.settings,
-    })
+    Ok(WorkspaceInfo::from_entry(entry, true))
 }
 
 #[tauri::command]
@@ -879,6 +809,7 @@ pub(crate) async fn remove_workspace(
 #[tauri::command]
 pub(crate) async fn remove_worktree(
     id: String,
+    force: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<(), String> {
     let (entry, parent) = {
@@ -901,6 +832,10 @@ pub(crate) async fn remove_worktree(
         (entry, parent)
     };
 
+    if !force.unwrap_or(false) {
+        git_state::ensure_no_git_operation(Path::new(&entry.path), "remove the worktree")?;
+    }
+
     if let Some(session) = state.sessions.lock().await.remove(&entry.id) {
         let mut child = session.child.lock().await;
         let _ = child.kill().await;
@@ -1012,10 +947,9 @@ pub(crate) async fn rename_worktree(
     std::fs::create_dir_all(&worktree_root)
         .map_err(|e| format!("Failed to create worktree directory: {e}"))?;
 
-    let safe_name = sanitize_worktree_name(&final_branch);
+    let naming = state.app_settings.lock().await.worktree_naming;
     let current_path = PathBuf::from(&entry.path);
-    let next_path =
-        unique_worktree_path_for_rename(&worktree_root, &safe_name, &current_path)?;
+    let next_path = worktree_path_for_rename(&worktree_root, &final_branch, naming, &current_path)?;
     let next_path_string = next_path.to_string_lossy().to_string();
     if next_path_string != entry.path {
         if let Err(error) = run_git_command(
@@ -1044,10 +978,12 @@ pub(crate) async fn rename_worktree(
         match entry.worktree.as_mut() {
             Some(worktree) => {
                 worktree.branch = final_branch.clone();
+                worktree.dir_name = worktree_dir_name(&next_path);
             }
             None => {
                 entry.worktree = Some(WorktreeInfo {
                     branch: final_branch.clone(),
+                    dir_name: worktree_dir_name(&next_path),
                 });
             }
         }
@@ -1086,17 +1022,127 @@ pub(crate) async fn rename_worktree(
     }
 
     let connected = state.sessions.lock().await.contains_key(&entry_snapshot.id);
-    Ok(WorkspaceInfo {
-        id: entry_snapshot.id,
-        name: entry_snapshot.name,
-        path: entry_snapshot.path,
-        codex_bin: entry_snapshot.codex_bin,
-        connected,
-        kind: entry_snapshot.kind,
-        parent_id: entry_snapshot.parent_id,
-        worktree: entry_snapshot.worktree,
-        settings: entry_snapshot.settings,
-    })
+    Ok(WorkspaceInfo::from_entry(entry_snapshot, connected))
+}
+
+#[tauri::command]
+pub(crate) async fn move_worktree(
+    workspace_id: String,
+    base_dir: String,
+    state: State<'_, AppState>,
+    app: AppHandle,
+) -> Result<WorkspaceInfo, String> {
+    if remote_backend::is_remote_mode(&*state).await {
+        let response = remote_backend::call_remote(
+            &*state,
+            app,
+            "move_worktree",
+            json!({ "workspaceId": workspace_id, "baseDir": base_dir }),
+        )
+        .await?;
+        return serde_json::from_value(response).map_err(|err| err.to_string());
+    }
+
+    let (entry, parent) = {
+        let workspaces = state.workspaces.lock().await;
+        let entry = workspaces
+            .get(&workspace_id)
+            .cloned()
+            .ok_or("workspace not found")?;
+        if !entry.kind.is_worktree() {
+            return Err("Not a worktree workspace.".to_string());
+        }
+        let parent_id = entry.parent_id.clone().ok_or("worktree parent not found")?;
+        let parent = workspaces
+            .get(&parent_id)
+            .cloned()
+            .ok_or("worktree parent not found")?;
+        (entry, parent)
+    };
+
+    let current_path = PathBuf::from(&entry.path);
+    if !current_path.is_dir() {
+        return Err(format!("folder not found: {}", entry.path));
+    }
+    let next_path = move_destination(&base_dir, &current_path)?;
+    validate_worktree_path(&next_path)?;
+    let next_path_string = next_path.to_string_lossy().to_string();
+
+    let parent_root = resolve_git_root(&parent)?;
+    let created_dirs = create_dir_all_tracked(next_path.parent().ok_or("invalid destination")?)?;
+    if let Err(error) = relocate_worktree(&parent_root, &current_path, &next_path).await {
+        created_dirs.remove_empty();
+        return Err(error);
+    }
+
+    let updated = {
+        let mut workspaces = state.workspaces.lock().await;
+        match workspaces.get_mut(&workspace_id) {
+            Some(entry) => {
+                entry.path = next_path_string.clone();
+                if let Some(worktree) = entry.worktree.as_mut() {
+                    worktree.dir_name = worktree_dir_name(&next_path);
+                }
+                let snapshot = entry.clone();
+                let list: Vec<_> = workspaces.values().cloned().collect();
+                Some((snapshot, list))
+            }
+            None => None,
+        }
+    };
+    let Some((entry_snapshot, list)) = updated else {
+        if relocate_worktree(&parent_root, &next_path, &current_path)
+            .await
+            .is_ok()
+        {
+            created_dirs.remove_empty();
+        }
+        return Err("workspace not found".to_string());
+    };
+    if let Err(error) = write_workspaces(&state.storage_path, &list) {
+        if let Some(stored) = state.workspaces.lock().await.get_mut(&workspace_id) {
+            stored.path = entry.path.clone();
+            stored.worktree = entry.worktree.clone();
+        }
+        if relocate_worktree(&parent_root, &next_path, &current_path)
+            .await
+            .is_ok()
+        {
+            created_dirs.remove_empty();
+        }
+        return Err(error);
+    }
+
+    let was_connected = state.sessions.lock().await.contains_key(&entry_snapshot.id);
+    if was_connected {
+        if let Some(session) = state.sessions.lock().await.remove(&entry_snapshot.id) {
+            let mut child = session.child.lock().await;
+            let _ = child.kill().await;
+        }
+        let default_bin = {
+            let settings = state.app_settings.lock().await;
+            settings.codex_bin.clone()
+        };
+        let codex_home = resolve_workspace_codex_home(&entry_snapshot, Some(&parent.path));
+        match spawn_workspace_session(entry_snapshot.clone(), default_bin, app, codex_home).await {
+            Ok(session) => {
+                state
+                    .sessions
+                    .lock()
+                    .await
+                    .insert(entry_snapshot.id.clone(), session);
+            }
+            Err(error) => {
+                eprintln!(
+                    "move_worktree: respawn failed for {} after move: {error}",
+                    entry_snapshot.id
+                );
+            }
+        }
+    }
+
+    let connected = state.sessions.lock().await.contains_key(&entry_snapshot.id);
+    Ok(WorkspaceInfo::from_entry(entry_snapshot, connected))
 }
 
 #[tauri::command]
@@ -1276,7 +1322,7 @@ pub(crate) async fn apply_worktree_changes(
         return Err("No changes to apply.".to_string());
     }
 
-    let mut child = Command::new("git")
+    let mut child = git_command()
         .args(["apply", "--3way", "--whitespace=nowarn", "-"])
         .current_dir(&parent_root)
         .stdin(Stdio::piped())
@@ -1331,29 +1377,55 @@ pub(crate) async fn apply_worktree_changes(
 #[tauri::command]
 pub(crate) async fn update_workspace_settings(
     id: String,
-    settings: WorkspaceSettings,
+    settings: Value,
+    lenient: Option<bool>,
     state: State<'_, AppState>,
 ) -> Result<WorkspaceInfo, String> {
-    let (entry_snapshot, list) = {
+    let settings = parse_workspace_settings(&settings, lenient.unwrap_or(false))?;
+    let (entry_snapshot, parent, list) = {
         let mut workspaces = state.workspaces.lock().await;
         let entry_snapshot = apply_workspace_settings_update(&mut workspaces, &id, settings)?;
+        let parent = entry_snapshot
+            .parent_id
+            .as_deref()
+            .and_then(|parent_id| workspaces.get(parent_id))
+            .cloned();
         let list: Vec<_> = workspaces.values().cloned().collect();
-        (entry_snapshot, list)
+        (entry_snapshot, parent, list)
     };
     write_workspaces(&state.storage_path, &list)?;
 
     let connected = state.sessions.lock().await.contains_key(&id);
-    Ok(WorkspaceInfo {
-        id: entry_snapshot.id,
-        name: entry_snapshot.name,
-        path: entry_snapshot.path,
-        codex_bin: entry_snapshot.codex_bin,
-        connected,
-        kind: entry_snapshot.kind,
-        parent_id: entry_snapshot.parent_id,
-        worktree: entry_snapshot.worktree,
-        settings: entry_snapshot.settings,
-    })
+    let mut info = WorkspaceInfo::from_entry(entry_snapshot, connected);
+    info.inherit_color(parent.as_ref());
+    Ok(info)
+}
+
+#[tauri::command]
+pub(crate) async fn patch_workspace_settings(
+    id: String,
+    patch: Value,
+    state: State<'_, AppState>,
+) -> Result<WorkspaceInfo, String> {
+    let (entry_snapshot, parent, list) = {
+        let mut workspaces = state.workspaces.lock().await;
+        let entry = workspaces.get(&id).ok_or("workspace not found")?;
+        let settings = entry.settings.with_patch(&patch)?;
+        let entry_snapshot = apply_workspace_settings_update(&mut workspaces, &id, settings)?;
+        let parent = entry_snapshot
+            .parent_id
+            .as_deref()
+            .and_then(|parent_id| workspaces.get(parent_id))
+            .cloned();
+        let list: Vec<_> = workspaces.values().cloned().collect();
+        (entry_snapshot, parent, list)
+    };
+    write_workspaces(&state.storage_path, &list)?;
+
+    let connected = state.sessions.lock().await.contains_key(&id);
+    let mut info = WorkspaceInfo::from_entry(entry_snapshot, connected);
+    info.inherit_color(parent.as_ref());
+    Ok(info)
 }
 
 #[tauri::command]
@@ -1377,17 +1449,7 @@ pub(crate) async fn update_workspace_codex_bin(
     write_workspaces(&state.storage_path, &list)?;
 
     let connected = state.sessions.lock().await.contains_key(&id);
-    Ok(WorkspaceInfo {
-        id: entry_snapshot.id,
-        name: entry_snapshot.name,
-        path: entry_snapshot.path,
-        codex_bin: entry_snapshot.codex_bin,
-        connected,
-        kind: entry_snapshot.kind,
-        parent_id: entry_snapshot.parent_id,
-        worktree: entry_snapshot.worktree,
-        settings: entry_snapshot.settings,
-    })
+    Ok(WorkspaceInfo::from_entry(entry_snapshot, connected))
 }
 
 #[tauri::command]
@@ -1478,9 +1540,10 @@ mod tests {
 
     use super::{
         apply_workspace_settings_update, build_clone_destination_path, sanitize_clone_dir_name,
-        sanitize_worktree_name, sort_workspaces,
+        sort_workspaces,
     };
     use crate::storage::{read_workspaces, write_workspaces};
+    use crate::worktree_paths::sanitize_worktree_name;
     use crate::types::{WorktreeInfo, WorkspaceEntry, WorkspaceInfo, WorkspaceKind, WorkspaceSettings};
     use uuid::Uuid;
 
@@ -1499,6 +1562,7 @@ mod tests {
                 Some("parent".to_string()),
                 Some(WorktreeInfo {
                     branch: name.to_string(),
+                    dir_name: None,
                 }),
             )
         } else {
@@ -1515,10 +1579,33 @@ mod tests {
             worktree,
             settings: WorkspaceSettings {
                 sidebar_collapsed: false,
+                pinned: false,
                 sort_order,
                 group_id: None,
                 git_root: None,
+                parent_writable: false,
+                init_submodules: false,
+                max_concurrent_turns: None,
+                default_access_mode: None,
+                approval_policy: None,
+                idle_disconnect_minutes: None,
+                disable_turn_inputs: false,
+                color: None,
+                icon: None,
             },
+            color: None,
+            status: None,
+            flags_stale: false,
+            git_operation: None,
+            is_git_repo: true,
+            branch: None,
+            detached_at: None,
+            fetch: None,
+            active_turns: None,
+            queued_turns: None,
+            paused: false,
+            capabilities: None,
+            error_count: None,
         }
     }
 
@@ -1619,6 +1706,25 @@ mod tests {
         assert_eq!(names, vec!["alpha", "beta", "gamma"]);
     }
 
+    #[test]
+    fn sort_workspaces_places_pinned_first() {
+        let mut pinned = workspace("delta", None);
+        pinned.settings.pinned = true;
+        let mut pinned_ordered = workspace("gamma", Some(5));
+        pinned_ordered.settings.pinned = true;
+        let mut items = vec![
+            workspace("alpha", Some(1)),
+            pinned,
+            workspace("beta", None),
+            pinned_ordered,
+        ];
+
+        sort_workspaces(&mut items);
+
+        let names: Vec<_> = items.into_iter().map(|item| item.name).collect();
+        assert_eq!(names, vec!["gamma", "delta", "alpha", "beta"]);
+    }
+
     #[test]
     fn sort_workspaces_breaks_ties_by_id() {
         let mut items = vec![
@@ -1660,6 +1766,7 @@ mod tests {
             parent_id: None,
             worktree: None,
             settings: WorkspaceSettings::default(),
+            status: None,
         };
         let mut workspaces = HashMap::from([(id.clone(), entry)]);
 
@@ -0,0 +1,169 @@
+use std::path::{Path, PathBuf};
+
+use crate::backend::git_command::run_git;
+
+/// Where `move_worktree` puts a worktree: its folder, under the same name,
+/// in `base_dir`. The base must be an absolute path outside the worktree
+/// and, if it exists, a folder without an entry of that name.
+pub(crate) fn move_destination(base_dir: &str, current_path: &Path) -> Result<PathBuf, String> {
+    let base_dir = base_dir.trim();
+    if base_dir.is_empty() {
+        return Err("Destination folder is required.".to_string());
+    }
+    let base_dir = Path::new(base_dir);
+    if !base_dir.is_absolute() {
+        return Err("Destination folder must be an absolute path.".to_string());
+    }
+    if base_dir.exists() && !base_dir.is_dir() {
+        return Err(format!("{} is not a folder.", base_dir.display()));
+    }
+    let name = current_path
+        .file_name()
+        .ok_or("worktree path has no folder name")?;
+    let destination = base_dir.join(name);
+    if destination == current_path {
+        return Err("Worktree is already in that folder.".to_string());
+    }
+    let base = std::fs::canonicalize(base_dir).unwrap_or_else(|_| base_dir.to_path_buf());
+    let current =
+        std::fs::canonicalize(current_path).unwrap_or_else(|_| current_path.to_path_buf());
+    if base.starts_with(&current) {
+        return Err("Cannot move a worktree into itself.".to_string());
+    }
+    if base.join(name) == current {
+        return Err("Worktree is already in that folder.".to_string());
+    }
+    if destination.exists() {
+        return Err(format!("{} already exists.", destination.display()));
+    }
+    Ok(destination)
+}
+
+/// Whether a failed `git worktree move` (a plain rename) hit a file system
+/// boundary, so the folder has to be copied instead.
+pub(crate) fn is_cross_device_error(message: &str) -> bool {
+    let message = message.to_ascii_lowercase();
+    message.contains("cross-device link") || message.contains("different disk drive")
+}
+
+/// `git worktree move`, which only renames. Across file systems the folder
+/// is copied with [`move_dir`] on a blocking thread and the worktree's links
+/// repaired; a failed repair moves the folder back.
+pub(crate) async fn relocate_worktree(
+    parent_root: &Path,
+    from: &Path,
+    to: &Path,
+) -> Result<(), String> {
+    let from_string = from.to_string_lossy().to_string();
+    let to_string = to.to_string_lossy().to_string();
+    let error = match run_git(parent_root, &["worktree", "move", &from_string, &to_string]).await {
+        Ok(_) => return Ok(()),
+        Err(error) if is_cross_device_error(&error) => error,
+        Err(error) => return Err(error),
+    };
+    move_dir_blocking(from, to)
+        .await
+        .map_err(|err| format!("{error}; copy fallback failed: {err}"))?;
+    if let Err(err) = run_git(parent_root, &["worktree", "repair", &to_string]).await {
+        let _ = move_dir_blocking(to, from).await;
+        return Err(format!("git worktree repair failed after copying: {err}"));
+    }
+    Ok(())
+}
+
+async fn move_dir_blocking(from: &Path, to: &Path) -> Result<(), String> {
+    let (from, to) = (from.to_path_buf(), to.to_path_buf());
+    tokio::task::spawn_blocking(move || move_dir(&from, &to))
+        .await
+        .map_err(|err| err.to_string())?
+}
+
+/// Moves a folder, copying it when a rename can't cross file systems.
+pub(crate) fn move_dir(from: &Path, to: &Path) -> Result<(), String> {
+    if to.exists() {
+        return Err(format!("{} already exists", to.display()));
+    }
+    if let Some(parent) = to.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    if std::fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    copy_dir(from, to).map_err(|err| {
+        let _ = std::fs::remove_dir_all(to);
+        format!("Failed to move {}: {err}", from.display())
+    })?;
+    std::fs::remove_dir_all(from)
+        .map_err(|err| format!("Failed to remove {}: {err}", from.display()))
+}
+
+fn copy_dir(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(to)?;
+    for entry in std::fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            copy_symlink(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(std::fs::read_link(from)?, to)
+}
+
+#[cfg(not(unix))]
+fn copy_symlink(from: &Path, to: &Path) -> std::io::Result<()> {
+    std::fs::copy(from, to).map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_cross_device_error, move_destination};
+    use uuid::Uuid;
+
+    #[test]
+    fn validates_destinations() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-move-{}", Uuid::new_v4()));
+        let current = root.join("worktrees").join("feature-x");
+        let fast = root.join("fast");
+        std::fs::create_dir_all(&current).expect("create worktree");
+        std::fs::create_dir_all(&fast).expect("create destination");
+        let base = |path: &std::path::Path| path.to_string_lossy().to_string();
+
+        assert_eq!(
+            move_destination(&base(&fast), &current),
+            Ok(fast.join("feature-x"))
+        );
+        assert_eq!(
+            move_destination(&base(&root.join("new")), &current),
+            Ok(root.join("new").join("feature-x"))
+        );
+        assert!(move_destination("", &current).is_err());
+        assert!(move_destination("relative/dir", &current).is_err());
+        assert!(move_destination(&base(&root.join("worktrees")), &current).is_err());
+        assert!(move_destination(&base(&current.join("sub")), &current).is_err());
+        std::fs::write(root.join("file"), b"x").expect("write file");
+        assert!(move_destination(&base(&root.join("file")), &current).is_err());
+        std::fs::create_dir_all(fast.join("feature-x")).expect("create taken folder");
+        assert!(move_destination(&base(&fast), &current).is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn recognizes_cross_device_failures() {
+        assert!(is_cross_device_error(
+            "fatal: failed to move 'a' to 'b': Invalid cross-device link"
+        ));
+        assert!(is_cross_device_error("Cross-device link"));
+        assert!(!is_cross_device_error("fatal: 'a' is a main working tree"));
+    }
+}
@@ -0,0 +1,547 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+use crate::types::WorktreeNaming;
+
+/// Longest sanitized name kept verbatim; longer names are cut and suffixed
+/// with a hash so deep data dirs stay clear of path length limits.
+const MAX_WORKTREE_NAME_LEN: usize = 64;
+
+/// Longest absolute worktree path we hand to git. On Windows directories are
+/// limited to MAX_PATH minus room for an 8.3 file name unless long paths are
+/// enabled system-wide, which we cannot rely on.
+#[cfg(windows)]
+const MAX_WORKTREE_PATH_LEN: usize = 248;
+#[cfg(not(windows))]
+const MAX_WORKTREE_PATH_LEN: usize = 4095;
+
+/// Prefix of folders named with [`WorktreeNaming::Timestamp`], in UTC.
+const TIMESTAMP_FORMAT: &str = "%Y%m%d-%H%M%S";
+/// Length of a [`TIMESTAMP_FORMAT`] prefix such as `20261016-142501`.
+const TIMESTAMP_LEN: usize = 15;
+
+const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Maps a branch name to a single safe path component. Leading and trailing
+/// `-`/`.` are dropped (Windows silently strips trailing dots, and `.`/`..`
+/// must never be joined onto the worktree root).
+pub(crate) fn sanitize_worktree_name(branch: &str) -> String {
+    let mut result = String::new();
+    for ch in branch.chars() {
+        if ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.') {
+            result.push(ch);
+        } else {
+            result.push('-');
+        }
+    }
+    let trimmed = result.trim_matches(|ch| ch == '-' || ch == '.');
+    if trimmed.is_empty() {
+        return "worktree".to_string();
+    }
+    let mut name = trimmed.to_string();
+    if is_windows_reserved_name(&name) {
+        // `nul.txt` is reserved too, so the hash goes right after the stem.
+        let stem_len = name.find('.').unwrap_or(name.len());
+        name.insert_str(stem_len, &format!("-{}", branch_hash(branch)));
+    }
+    if name.len() > MAX_WORKTREE_NAME_LEN {
+        // Only ASCII is left at this point, so byte slicing is safe.
+        let cut = name[..MAX_WORKTREE_NAME_LEN].trim_end_matches(['-', '.']);
+        name = format!("{cut}-{}", branch_hash(branch));
+    }
+    name
+}
+
+fn is_windows_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or(name).to_ascii_lowercase();
+    WINDOWS_RESERVED_NAMES.contains(&stem.as_str())
+}
+
+/// Short, stable (FNV-1a) hash of the original branch name.
+pub(crate) fn branch_hash(branch: &str) -> String {
+    let mut hash: u32 = 0x811c_9dc5;
+    for byte in branch.as_bytes() {
+        hash ^= u32::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0193);
+    }
+    format!("{hash:08x}")
+}
+
+/// `desired` unless `is_taken`, else `desired-N` with the smallest free `N`
+/// from 2. Callers list what exists once and check candidates against that,
+/// so there is no cap on `N`.
+pub(crate) fn first_free_name(desired: &str, is_taken: impl Fn(&str) -> bool) -> String {
+    if !is_taken(desired) {
+        return desired.to_string();
+    }
+    let mut index: u64 = 2;
+    loop {
+        let candidate = format!("{desired}-{index}");
+        if !is_taken(&candidate) {
+            return candidate;
+        }
+        index += 1;
+    }
+}
+
+/// Names in `dir`, lowercased so folders differing only in case count as
+/// taken on case-insensitive file systems. Empty when `dir` doesn't exist.
+fn child_names(dir: &Path) -> HashSet<String> {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| entry.file_name().to_string_lossy().to_lowercase())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Picks the folder for a new worktree of `branch` under `base_dir`, named
+/// after `naming`. When the name is already taken (e.g. `feature/x` vs
+/// `feature x`), the branch hash is appended so the folder still identifies
+/// its branch.
+pub(crate) fn worktree_path_for_branch(
+    base_dir: &Path,
+    branch: &str,
+    naming: WorktreeNaming,
+) -> Result<PathBuf, String> {
+    let name = worktree_name(branch, naming, &timestamp_prefix(Utc::now()));
+    let taken = child_names(base_dir);
+    let path = free_worktree_path(base_dir, branch, naming, &name, |name| {
+        taken.contains(&name.to_lowercase())
+    });
+    validate_worktree_path(&path)?;
+    Ok(path)
+}
+
+/// Like [`worktree_path_for_branch`], but the worktree's current folder counts
+/// as available so renaming to an equivalent name does not move it. With
+/// [`WorktreeNaming::Timestamp`] the current folder's timestamp is kept, so
+/// it still tells when the worktree was created.
+pub(crate) fn worktree_path_for_rename(
+    base_dir: &Path,
+    branch: &str,
+    naming: WorktreeNaming,
+    current_path: &Path,
+) -> Result<PathBuf, String> {
+    let stamp = folder_timestamp(current_path).unwrap_or_else(|| timestamp_prefix(Utc::now()));
+    let name = worktree_name(branch, naming, &stamp);
+    let taken = child_names(base_dir);
+    let path = free_worktree_path(base_dir, branch, naming, &name, |name| {
+        base_dir.join(name) != current_path && taken.contains(&name.to_lowercase())
+    });
+    validate_worktree_path(&path)?;
+    Ok(path)
+}
+
+/// The folder name `naming` gives `branch`; `stamp` is only used by
+/// [`WorktreeNaming::Timestamp`]. Every scheme yields a single safe path
+/// component.
+fn worktree_name(branch: &str, naming: WorktreeNaming, stamp: &str) -> String {
+    match naming {
+        WorktreeNaming::Branch => sanitize_worktree_name(branch),
+        WorktreeNaming::BranchHash => branch_hash(branch),
+        WorktreeNaming::Timestamp => format!("{stamp}-{}", sanitize_worktree_name(branch)),
+    }
+}
+
+fn timestamp_prefix(now: DateTime<Utc>) -> String {
+    now.format(TIMESTAMP_FORMAT).to_string()
+}
+
+/// The timestamp prefix of a folder named with [`WorktreeNaming::Timestamp`].
+fn folder_timestamp(path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_str()?;
+    let stamp = name.get(..TIMESTAMP_LEN)?;
+    let is_stamp = name[TIMESTAMP_LEN..].starts_with('-')
+        && NaiveDateTime::parse_from_str(stamp, TIMESTAMP_FORMAT).is_ok();
+    is_stamp.then(|| stamp.to_string())
+}
+
+/// Checked before any directory is created or git command runs, so a bad
+/// path fails with a clear message instead of a git error.
+pub(crate) fn validate_worktree_path(path: &Path) -> Result<(), String> {
+    let len = path.as_os_str().len();
+    if len > MAX_WORKTREE_PATH_LEN {
+        return Err(format!(
+            "Worktree path is too long ({len} characters, limit {MAX_WORKTREE_PATH_LEN}): {}. Use a shorter branch name or a data directory closer to the drive root.",
+            path.display()
+        ));
+    }
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if is_windows_reserved_name(&name) {
+        return Err(format!(
+            "Worktree folder name `{name}` is a reserved device name on Windows."
+        ));
+    }
+    Ok(())
+}
+
+/// Directories made by [`create_dir_all_tracked`], deepest first.
+pub(crate) struct CreatedDirs {
+    dirs: Vec<PathBuf>,
+}
+
+impl CreatedDirs {
+    /// Removes the directories again, stopping at the first one that is not
+    /// empty (or already gone).
+    pub(crate) fn remove_empty(&self) {
+        for dir in &self.dirs {
+            if std::fs::remove_dir(dir).is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// `create_dir_all` that remembers which directories did not exist before, so
+/// a failed worktree creation leaves nothing behind.
+pub(crate) fn create_dir_all_tracked(path: &Path) -> Result<CreatedDirs, String> {
+    let mut dirs = Vec::new();
+    let mut current = Some(path);
+    while let Some(dir) = current {
+        if dir.as_os_str().is_empty() || dir.exists() {
+            break;
+        }
+        dirs.push(dir.to_path_buf());
+        current = dir.parent();
+    }
+    std::fs::create_dir_all(path)
+        .map_err(|err| format!("Failed to create worktree directory: {err}"))?;
+    Ok(CreatedDirs { dirs })
+}
+
+/// `name`, then the hashed one, then the hashed one numbered. A name that
+/// already is the hash is only numbered.
+fn free_worktree_path(
+    base_dir: &Path,
+    branch: &str,
+    naming: WorktreeNaming,
+    name: &str,
+    is_taken: impl Fn(&str) -> bool,
+) -> PathBuf {
+    if !is_taken(name) {
+        return base_dir.join(name);
+    }
+    let hashed = match naming {
+        WorktreeNaming::BranchHash => name.to_string(),
+        WorktreeNaming::Branch | WorktreeNaming::Timestamp => {
+            format!("{name}-{}", branch_hash(branch))
+        }
+    };
+    base_dir.join(first_free_name(&hashed, is_taken))
+}
+
+/// Folder name recorded in `WorktreeInfo::dir_name`.
+pub(crate) fn worktree_dir_name(path: &Path) -> Option<String> {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        branch_hash, create_dir_all_tracked, first_free_name, folder_timestamp,
+        is_windows_reserved_name, sanitize_worktree_name, validate_worktree_path, worktree_name,
+        worktree_path_for_branch, worktree_path_for_rename, MAX_WORKTREE_NAME_LEN,
+        MAX_WORKTREE_PATH_LEN,
+    };
+    use crate::types::WorktreeNaming;
+    use std::collections::HashSet;
+    use std::path::{Component, Path};
+    use uuid::Uuid;
+
+    const TRICKY_BRANCHES: &[&str] = &[
+        "",
+        ".",
+        "..",
+        "...",
+        "/",
+        "feature/x",
+        "feature x",
+        "feature\\x",
+        "feature/../../etc",
+        "release.",
+        "release...",
+        ".hidden",
+        "-leading",
+        "trailing-",
+        "con",
+        "CON",
+        "nul.txt",
+        "Lpt9",
+        "com1.",
+        "功能/测试",
+        "ветка",
+        "emoji-🚀-branch",
+        "café",
+        "tab\tseparated",
+        "new\nline",
+        "a:b*c?d\"e<f>g|h",
+    ];
+
+    fn assert_safe_component(branch: &str, name: &str) {
+        assert!(!name.is_empty(), "{branch:?} produced an empty name");
+        assert!(
+            name.chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '-' | '_' | '.')),
+            "{branch:?} produced unsafe chars: {name:?}"
+        );
+        assert!(
+            !name.starts_with(['-', '.']) && !name.ends_with(['-', '.']),
+            "{branch:?} produced untrimmed name {name:?}"
+        );
+        assert!(
+            name.len() <= MAX_WORKTREE_NAME_LEN + 9,
+            "{branch:?} produced an overlong name"
+        );
+        assert!(
+            !is_windows_reserved_name(name),
+            "{branch:?} produced reserved name {name:?}"
+        );
+        let components: Vec<_> = Path::new(name).components().collect();
+        assert!(
+            matches!(components.as_slice(), [Component::Normal(_)]),
+            "{branch:?} produced non-normal path {name:?}"
+        );
+    }
+
+    #[test]
+    fn sanitize_worktree_name_always_yields_a_safe_component() {
+        let long_ascii = "a".repeat(300);
+        let long_mixed = "feature/".repeat(40);
+        let long_unicode = "ü".repeat(200);
+        let mut branches: Vec<&str> = TRICKY_BRANCHES.to_vec();
+        branches.extend([
+            long_ascii.as_str(),
+            long_mixed.as_str(),
+            long_unicode.as_str(),
+        ]);
+        for branch in branches {
+            assert_safe_component(branch, &sanitize_worktree_name(branch));
+        }
+    }
+
+    #[test]
+    fn sanitize_worktree_name_keeps_existing_simple_names() {
+        assert_eq!(
+            sanitize_worktree_name("feature/new-thing"),
+            "feature-new-thing"
+        );
+        assert_eq!(sanitize_worktree_name("release_1.2.3"), "release_1.2.3");
+        assert_eq!(sanitize_worktree_name("feature--x"), "feature--x");
+        assert_eq!(sanitize_worktree_name("release."), "release");
+        assert_eq!(sanitize_worktree_name(".."), "worktree");
+    }
+
+    #[test]
+    fn long_names_are_truncated_with_distinct_hashes() {
+        let first = format!("{}-one", "x".repeat(100));
+        let second = format!("{}-two", "x".repeat(100));
+        let first_name = sanitize_worktree_name(&first);
+        let second_name = sanitize_worktree_name(&second);
+        assert_ne!(first_name, second_name);
+        assert!(first_name.ends_with(&branch_hash(&first)));
+    }
+
+    #[test]
+    fn reserved_windows_names_get_a_hash_suffix() {
+        assert_eq!(
+            sanitize_worktree_name("aux"),
+            format!("aux-{}", branch_hash("aux"))
+        );
+        assert_eq!(
+            sanitize_worktree_name("nul.txt"),
+            format!("nul-{}.txt", branch_hash("nul.txt"))
+        );
+        assert_eq!(sanitize_worktree_name("auxiliary"), "auxiliary");
+    }
+
+    #[test]
+    fn branch_hash_is_stable() {
+        assert_eq!(branch_hash(""), "811c9dc5");
+        assert_eq!(branch_hash("feature/x"), branch_hash("feature/x"));
+        assert_ne!(branch_hash("feature/x"), branch_hash("feature x"));
+    }
+
+    #[test]
+    fn colliding_branches_get_hashed_folders() {
+        let base = std::env::temp_dir().join(format!("codex-monitor-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(base.join("feature-x")).expect("create existing worktree");
+
+        let path =
+            worktree_path_for_branch(&base, "feature x", WorktreeNaming::Branch).expect("path");
+        assert_eq!(
+            path,
+            base.join(format!("feature-x-{}", branch_hash("feature x")))
+        );
+
+        std::fs::create_dir_all(&path).expect("create hashed worktree");
+        let next =
+            worktree_path_for_branch(&base, "feature x", WorktreeNaming::Branch).expect("path");
+        assert_eq!(
+            next,
+            base.join(format!("feature-x-{}-2", branch_hash("feature x")))
+        );
+    }
+
+    #[test]
+    fn free_names_have_no_suffix_cap() {
+        let mut taken: HashSet<String> = (2..=1500).map(|index| format!("fix-{index}")).collect();
+        assert_eq!(first_free_name("fix", |name| taken.contains(name)), "fix");
+        taken.insert("fix".to_string());
+        assert_eq!(
+            first_free_name("fix", |name| taken.contains(name)),
+            "fix-1501"
+        );
+        taken.remove("fix-7");
+        assert_eq!(first_free_name("fix", |name| taken.contains(name)), "fix-7");
+    }
+
+    #[test]
+    fn colliding_folders_match_case_insensitively() {
+        let base = std::env::temp_dir().join(format!("codex-monitor-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(base.join("Feature-X")).expect("create existing worktree");
+
+        let path =
+            worktree_path_for_branch(&base, "feature/x", WorktreeNaming::Branch).expect("path");
+        assert_eq!(
+            path,
+            base.join(format!("feature-x-{}", branch_hash("feature/x")))
+        );
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn free_names_stay_unhashed() {
+        let base = std::env::temp_dir().join(format!("codex-monitor-test-{}", Uuid::new_v4()));
+        let path =
+            worktree_path_for_branch(&base, "feature/x", WorktreeNaming::Branch).expect("path");
+        assert_eq!(path, base.join("feature-x"));
+    }
+
+    #[test]
+    fn rename_keeps_current_folder_when_it_matches() {
+        let base = std::env::temp_dir().join(format!("codex-monitor-test-{}", Uuid::new_v4()));
+        let hashed = base.join(format!("feature-x-{}", branch_hash("feature x")));
+        std::fs::create_dir_all(base.join("feature-x")).expect("create other worktree");
+        std::fs::create_dir_all(&hashed).expect("create current worktree");
+
+        let path = worktree_path_for_rename(&base, "feature x", WorktreeNaming::Branch, &hashed)
+            .expect("path");
+        assert_eq!(path, hashed);
+    }
+
+    #[test]
+    fn naming_schemes_yield_safe_components() {
+        let stamp = "20261016-142501";
+        for branch in TRICKY_BRANCHES {
+            for naming in [
+                WorktreeNaming::Branch,
+                WorktreeNaming::BranchHash,
+                WorktreeNaming::Timestamp,
+            ] {
+                let name = worktree_name(branch, naming, stamp);
+                assert_safe_component(branch, &name);
+                let rest = name.strip_prefix(&format!("{stamp}-")).unwrap_or(&name);
+                assert_safe_component(branch, rest);
+            }
+        }
+        assert_eq!(
+            worktree_name("feature/x", WorktreeNaming::BranchHash, stamp),
+            branch_hash("feature/x")
+        );
+        assert_eq!(
+            worktree_name("feature/x", WorktreeNaming::Timestamp, stamp),
+            "20261016-142501-feature-x"
+        );
+    }
+
+    #[test]
+    fn hashed_folders_are_numbered_when_taken() {
+        let base = std::env::temp_dir().join(format!("codex-monitor-test-{}", Uuid::new_v4()));
+        let hash = branch_hash("feature/x");
+        std::fs::create_dir_all(base.join(&hash)).expect("create existing worktree");
+
+        let path =
+            worktree_path_for_branch(&base, "feature/x", WorktreeNaming::BranchHash).expect("path");
+        assert_eq!(path, base.join(format!("{hash}-2")));
+        let _ = std::fs::remove_dir_all(&base);
+    }
+
+    #[test]
+    fn timestamped_folders_keep_their_timestamp_on_rename() {
+        let base = std::env::temp_dir().join(format!("codex-monitor-test-{}", Uuid::new_v4()));
+        let current = base.join("20250101-090000-feature-x");
+        assert_eq!(
+            folder_timestamp(&current).as_deref(),
+            Some("20250101-090000")
+        );
+        assert_eq!(folder_timestamp(&base.join("feature-x")), None);
+        assert_eq!(folder_timestamp(&base.join("20251399-999999-x")), None);
+
+        let path = worktree_path_for_rename(&base, "fix/y", WorktreeNaming::Timestamp, &current)
+            .expect("path");
+        assert_eq!(path, base.join("20250101-090000-fix-y"));
+
+        let created =
+            worktree_path_for_branch(&base, "fix/y", WorktreeNaming::Timestamp).expect("path");
+        let stamp = folder_timestamp(&created).expect("timestamp");
+        assert_eq!(
+            created.file_name().and_then(|name| name.to_str()),
+            Some(format!("{stamp}-fix-y").as_str())
+        );
+    }
+
+    #[test]
+    fn overlong_paths_are_rejected_before_creation() {
+        let base = std::env::temp_dir()
+            .join(format!("codex-monitor-test-{}", Uuid::new_v4()))
+            .join("d".repeat(MAX_WORKTREE_PATH_LEN));
+        let err = worktree_path_for_branch(&base, "feature/x", WorktreeNaming::Branch)
+            .expect_err("too long");
+        assert!(err.contains("too long"));
+        assert!(!base.exists());
+    }
+
+    #[test]
+    fn reserved_final_component_is_rejected() {
+        let base = std::env::temp_dir();
+        assert!(validate_worktree_path(&base.join("aux")).is_err());
+        assert!(validate_worktree_path(&base.join("aux-1234abcd")).is_ok());
+    }
+
+    #[test]
+    fn tracked_dirs_are_removed_when_empty() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-test-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).expect("create root");
+        let nested = root.join("worktrees").join("parent-id");
+        let created = create_dir_all_tracked(&nested).expect("create nested");
+        assert!(nested.is_dir());
+
+        created.remove_empty();
+        assert!(!root.join("worktrees").exists());
+        assert!(root.exists());
+    }
+
+    #[test]
+    fn tracked_dirs_keep_non_empty_parents() {
+        let root = std::env::temp_dir().join(format!("codex-monitor-test-{}", Uuid::new_v4()));
+        let nested = root.join("worktrees").join("parent-id");
+        let created = create_dir_all_tracked(&nested).expect("create nested");
+        std::fs::write(root.join("worktrees").join("keep.txt"), b"x").expect("write file");
+
+        created.remove_empty();
+        assert!(!nested.exists());
+        assert!(root.join("worktrees").exists());
+    }
+}
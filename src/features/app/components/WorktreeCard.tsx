@@ -25,6 +25,7 @@ export function WorktreeCard({
 }: WorktreeCardProps) {
   const worktreeCollapsed = worktree.settings.sidebarCollapsed;
   const worktreeBranch = worktree.worktree?.branch ?? "";
+  const worktreeDirName = worktree.worktree?.dirName ?? null;
 
   return (
     <div className={`worktree-card${isDeleting ? " deleting" : ""}`}>
@@ -53,7 +54,12 @@ export function WorktreeCard({
           }
         }}
       >
-        <div className="worktree-label">{worktreeBranch || worktree.name}</div>
+        <div
+          className="worktree-label"
+          title={worktreeDirName ? `Folder: ${worktreeDirName}` : undefined}
+        >
+          {worktreeBranch || worktree.name}
+        </div>
         <div className="worktree-actions">
           {isDeleting ? (
             <div className="worktree-deleting" role="status" aria-live="polite">
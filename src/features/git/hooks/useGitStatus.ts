@@ -60,7 +60,11 @@ export function useGitStatus(activeWorkspace: WorkspaceInfo | null) {
           return;
         }
         const cached = cachedStatusRef.current.get(workspaceId);
-        const resolvedBranchName = resolveBranchName(data.branchName, cached);
+        // Detached HEAD has no branch; show the short commit instead of
+        // falling back to a stale cached branch name.
+        const incomingBranch =
+          data.branchName ?? data.detachedAt?.slice(0, 7) ?? undefined;
+        const resolvedBranchName = resolveBranchName(incomingBranch, cached);
         const nextStatus = {
           ...data,
           branchName: resolvedBranchName,
@@ -296,8 +296,10 @@ export function useThreadActions({
             payload: response,
           });
           const result = (response.result ?? response) as Record<string, unknown>;
-          const data = Array.isArray(result?.data)
-            ? (result.data as Record<string, unknown>[])
+          // The daemon normalizes pages to `threads`; local codex returns `data`.
+          const threads = result?.threads ?? result?.data;
+          const data = Array.isArray(threads)
+            ? (threads as Record<string, unknown>[])
             : [];
           const nextCursor =
             (result?.nextCursor ?? result?.next_cursor ?? null) as string | null;
@@ -458,8 +460,9 @@ export function useThreadActions({
             payload: response,
           });
           const result = (response.result ?? response) as Record<string, unknown>;
-          const data = Array.isArray(result?.data)
-            ? (result.data as Record<string, unknown>[])
+          const threads = result?.threads ?? result?.data;
+          const data = Array.isArray(threads)
+            ? (threads as Record<string, unknown>[])
             : [];
           const next =
             (result?.nextCursor ?? result?.next_cursor ?? null) as string | null;
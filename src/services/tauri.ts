@@ -6,13 +6,16 @@ import type {
   DictationModelStatus,
   DictationSessionState,
   LocalUsageSnapshot,
+  PreflightReport,
   WorkspaceInfo,
   WorkspaceSettings,
 } from "../types";
 import type {
   GitFileDiff,
   GitFileStatus,
+  GitIdentity,
   GitCommitDiff,
+  GitOperation,
   GitHubIssuesResponse,
   GitHubPullRequestComment,
   GitHubPullRequestDiff,
@@ -55,9 +58,9 @@ export async function getCodexConfigPath(): Promise<string> {
 
 export async function addWorkspace(
   path: string,
-  codex_bin: string | null,
+  codexBin: string | null,
 ): Promise<WorkspaceInfo> {
-  return invoke<WorkspaceInfo>("add_workspace", { path, codex_bin });
+  return invoke<WorkspaceInfo>("add_workspace", { path, codexBin });
 }
 
 export async function isWorkspacePathDir(path: string): Promise<boolean> {
@@ -79,8 +82,13 @@ export async function addClone(
 export async function addWorktree(
   parentId: string,
   branch: string,
+  initSubmodules?: boolean | null,
 ): Promise<WorkspaceInfo> {
-  return invoke<WorkspaceInfo>("add_worktree", { parentId, branch });
+  return invoke<WorkspaceInfo>("add_worktree", {
+    parentId,
+    branch,
+    initSubmodules: initSubmodules ?? null,
+  });
 }
 
 export async function updateWorkspaceSettings(
@@ -90,19 +98,29 @@ export async function updateWorkspaceSettings(
   return invoke<WorkspaceInfo>("update_workspace_settings", { id, settings });
 }
 
+export async function patchWorkspaceSettings(
+  id: string,
+  patch: { [K in keyof WorkspaceSettings]?: WorkspaceSettings[K] | null },
+): Promise<WorkspaceInfo> {
+  return invoke<WorkspaceInfo>("patch_workspace_settings", { id, patch });
+}
+
 export async function updateWorkspaceCodexBin(
   id: string,
-  codex_bin: string | null,
+  codexBin: string | null,
 ): Promise<WorkspaceInfo> {
-  return invoke<WorkspaceInfo>("update_workspace_codex_bin", { id, codex_bin });
+  return invoke<WorkspaceInfo>("update_workspace_codex_bin", { id, codexBin });
 }
 
 export async function removeWorkspace(id: string): Promise<void> {
   return invoke("remove_workspace", { id });
 }
 
-export async function removeWorktree(id: string): Promise<void> {
-  return invoke("remove_worktree", { id });
+export async function removeWorktree(
+  id: string,
+  force?: boolean,
+): Promise<void> {
+  return invoke("remove_worktree", { id, force });
 }
 
 export async function renameWorktree(
@@ -112,6 +130,13 @@ export async function renameWorktree(
   return invoke<WorkspaceInfo>("rename_worktree", { id, branch });
 }
 
+export async function moveWorktree(
+  workspaceId: string,
+  baseDir: string,
+): Promise<WorkspaceInfo> {
+  return invoke<WorkspaceInfo>("move_worktree", { workspaceId, baseDir });
+}
+
 export async function renameWorktreeUpstream(
   id: string,
   oldBranch: string,
@@ -143,7 +168,7 @@ export async function sendUserMessage(
   options?: {
     model?: string | null;
     effort?: string | null;
-    accessMode?: "read-only" | "current" | "full-access";
+    accessMode?: "read-only" | "current" | "workspace-write-offline" | "full-access";
     images?: string[];
     collaborationMode?: Record<string, unknown> | null;
   },
@@ -160,6 +185,19 @@ export async function sendUserMessage(
   });
 }
 
+export async function previewMessagePolicy(
+  workspaceId: string,
+  accessMode?: "read-only" | "current" | "workspace-write-offline" | "full-access" | null,
+) {
+  return invoke<{
+    sandboxPolicy: Record<string, unknown>;
+    approvalPolicy: string;
+  }>("preview_message_policy", {
+    workspaceId,
+    accessMode: accessMode ?? null,
+  });
+}
+
 export async function interruptTurn(
   workspaceId: string,
   threadId: string,
@@ -213,7 +251,9 @@ export async function rememberApprovalRule(
 }
 
 export async function getGitStatus(workspace_id: string): Promise<{
-  branchName: string;
+  branchName: string | null;
+  detachedAt?: string | null;
+  operation: GitOperation | null;
   files: GitFileStatus[];
   stagedFiles: GitFileStatus[];
   unstagedFiles: GitFileStatus[];
@@ -254,6 +294,23 @@ export async function getGitRemote(workspace_id: string): Promise<string | null>
   return invoke("get_git_remote", { workspaceId: workspace_id });
 }
 
+export async function getGitIdentity(workspaceId: string) {
+  return invoke<GitIdentity>("get_git_identity", { workspaceId });
+}
+
+export async function setGitIdentity(
+  workspaceId: string,
+  identity: { name?: string | null; email?: string | null },
+  scope?: "local" | "worktree" | null,
+) {
+  return invoke<GitIdentity>("set_git_identity", {
+    workspaceId,
+    name: identity.name ?? null,
+    email: identity.email ?? null,
+    scope: scope ?? null,
+  });
+}
+
 export async function stageGitFile(workspaceId: string, path: string) {
   return invoke("stage_git_file", { workspaceId, path });
 }
@@ -451,6 +508,12 @@ export async function runCodexDoctor(
   return invoke<CodexDoctorResult>("codex_doctor", { codexBin });
 }
 
+export async function runPreflightCheck(
+  workspaceIds: string[] | null = null,
+): Promise<PreflightReport> {
+  return invoke<PreflightReport>("preflight_check", { workspaceIds });
+}
+
 export async function getWorkspaceFiles(workspaceId: string) {
   return invoke<string[]>("list_workspace_files", { workspaceId });
 }
@@ -1,8 +1,20 @@
 export type WorkspaceSettings = {
   sidebarCollapsed: boolean;
+  pinned?: boolean;
   sortOrder?: number | null;
   groupId?: string | null;
   gitRoot?: string | null;
+  parentWritable?: boolean;
+  initSubmodules?: boolean;
+  maxConcurrentTurns?: number | null;
+  defaultAccessMode?: AccessMode | null;
+  approvalPolicy?: ApprovalPolicy | null;
+  idleDisconnectMinutes?: number | null;
+  disableTurnInputs?: boolean;
+  color?: string | null;
+  icon?: string | null;
+  captureArtifacts?: boolean;
+  artifactThresholdBytes?: number | null;
 };
 
 export type WorkspaceGroup = {
@@ -16,6 +28,7 @@ export type WorkspaceKind = "main" | "worktree";
 
 export type WorktreeInfo = {
   branch: string;
+  dirName?: string | null;
 };
 
 export type WorkspaceInfo = {
@@ -28,8 +41,39 @@ export type WorkspaceInfo = {
   parentId?: string | null;
   worktree?: WorktreeInfo | null;
   settings: WorkspaceSettings;
+  color?: string | null;
+  flagsStale?: boolean;
+  gitOperation?: GitOperation | null;
+  isGitRepo?: boolean;
+  branch?: string | null;
+  detachedAt?: string | null;
+  fetch?: WorkspaceFetchStatus;
+  activeTurns?: number;
+  queuedTurns?: number;
+  paused?: boolean;
+  capabilities?: CodexCapabilities;
+  errorCount?: number;
+};
+
+export type CodexCapabilities = {
+  version: string | null;
+  userAgent?: string | null;
+  unsupportedMethods: string[];
+};
+
+export type WorkspaceFetchStatus = {
+  lastFetchAt: number | null;
+  lastError: string | null;
+  consecutiveFailures: number;
 };
 
+export type GitOperation =
+  | "merge"
+  | "rebase"
+  | "cherry-pick"
+  | "revert"
+  | "bisect";
+
 export type AppServerEvent = {
   workspace_id: string;
   message: Record<string, unknown>;
@@ -71,6 +115,8 @@ export type ReviewTarget =
   | { type: "custom"; instructions: string };
 
 export type AccessMode = "read-only" | "current" | "full-access";
+
+export type ApprovalPolicy = "untrusted" | "on-failure" | "on-request" | "never";
 export type BackendMode = "local" | "remote";
 export type ThemePreference = "system" | "light" | "dark";
 
@@ -148,6 +194,20 @@ export type CodexDoctorResult = {
   nodeDetails: string | null;
 };
 
+export type PreflightCheck = {
+  id: string;
+  status: "pass" | "warn" | "fail";
+  detail: string;
+  remediation?: string;
+  workspaceId?: string;
+};
+
+export type PreflightReport = {
+  ok: boolean;
+  checkedAt: number;
+  checks: PreflightCheck[];
+};
+
 export type ApprovalRequest = {
   workspace_id: string;
   request_id: number | string;
@@ -193,6 +253,12 @@ export type GitFileStatus = {
   status: string;
   additions: number;
   deletions: number;
+  submodule?: boolean;
+};
+
+export type GitIdentity = {
+  name: string | null;
+  email: string | null;
 };
 
 export type GitFileDiff = {